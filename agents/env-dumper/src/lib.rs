@@ -0,0 +1,64 @@
+//! Stock agent writing the target's environment to a log
+//! (`hook_inject::StockAgent::EnvDumper`).
+//!
+//! The entrypoint data is the path of the log, appended to, or empty for
+//! the target's standard error. The agent unloads once it has written the
+//! process id, executable, arguments, working directory and environment
+//! variables.
+
+use std::ffi::{c_char, c_void};
+use std::fmt::Write as _;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicI32, Ordering};
+
+/// Entry status read back by the injector (`i32::MIN` until reported).
+#[allow(non_upper_case_globals)]
+#[unsafe(no_mangle)]
+pub static hook_inject_entry_status: AtomicI32 = AtomicI32::new(i32::MIN);
+
+/// # Safety
+/// `data` must be null or a valid NUL-terminated C string pointer, and
+/// `stay_resident` null or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hook_inject_entry(
+    data: *const c_char,
+    stay_resident: *mut i32,
+    _state: *mut c_void,
+) {
+    let data = unsafe { hook_inject_agent::entry_data(data, stay_resident, false) };
+    let status = if dump(data).is_ok() { 0 } else { 1 };
+    hook_inject_entry_status.store(status, Ordering::SeqCst);
+}
+
+fn dump(log: &[u8]) -> io::Result<()> {
+    let mut text = format!("# pid {}\n", std::process::id());
+    if let Ok(exe) = std::env::current_exe() {
+        let _ = writeln!(text, "# exe {}", exe.display());
+    }
+    let args: Vec<_> = std::env::args_os()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    let _ = writeln!(text, "# args {args:?}");
+    if let Ok(cwd) = std::env::current_dir() {
+        let _ = writeln!(text, "# cwd {}", cwd.display());
+    }
+    for (key, value) in std::env::vars_os() {
+        let _ = writeln!(
+            text,
+            "{}={}",
+            key.to_string_lossy(),
+            value.to_string_lossy()
+        );
+    }
+
+    if log.is_empty() {
+        return io::stderr().write_all(text.as_bytes());
+    }
+    let path = std::str::from_utf8(log).map_err(io::Error::other)?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(text.as_bytes())
+}
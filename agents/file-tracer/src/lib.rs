@@ -0,0 +1,186 @@
+//! Stock agent logging the files the target opens
+//! (`hook_inject::StockAgent::FileTracer`).
+//!
+//! Hooks `open`, `open64`, `openat` and `openat64` and logs each call and
+//! its result. The entrypoint data is the path of the log, appended to, or
+//! empty for the target's standard error. Calls made while another thread
+//! is inside the original function are not logged. Linux only.
+
+use std::ffi::{c_char, c_void};
+use std::sync::atomic::{AtomicI32, Ordering};
+
+/// Entry status read back by the injector (`i32::MIN` until reported).
+#[allow(non_upper_case_globals)]
+#[unsafe(no_mangle)]
+pub static hook_inject_entry_status: AtomicI32 = AtomicI32::new(i32::MIN);
+
+/// # Safety
+/// `data` must be null or a valid NUL-terminated C string pointer, and
+/// `stay_resident` null or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hook_inject_entry(
+    data: *const c_char,
+    stay_resident: *mut i32,
+    _state: *mut c_void,
+) {
+    let data = unsafe { hook_inject_agent::entry_data(data, stay_resident, true) };
+    let status = if trace::start(data).is_ok() { 0 } else { 1 };
+    hook_inject_entry_status.store(status, Ordering::SeqCst);
+}
+
+/// Removes the hooks before the agent is unloaded.
+#[unsafe(no_mangle)]
+pub extern "C" fn hook_inject_unload(
+    _data: *const c_char,
+    _stay_resident: *mut i32,
+    _state: *mut c_void,
+) {
+    trace::stop();
+}
+
+#[cfg(target_os = "linux")]
+mod trace {
+    use std::ffi::{CStr, c_char, c_int, c_uint, c_void};
+    use std::fs::OpenOptions;
+    use std::io::{self, Write};
+    use std::sync::{Mutex, OnceLock};
+
+    use hook_inject_agent::{Hook, find_function, hook};
+
+    type Open = unsafe extern "C" fn(*const c_char, c_int, c_uint) -> c_int;
+    type OpenAt = unsafe extern "C" fn(c_int, *const c_char, c_int, c_uint) -> c_int;
+
+    static LOG: OnceLock<Mutex<Box<dyn Write + Send>>> = OnceLock::new();
+    static OPEN: OnceLock<(Hook, Open)> = OnceLock::new();
+    static OPEN64: OnceLock<(Hook, Open)> = OnceLock::new();
+    static OPENAT: OnceLock<(Hook, OpenAt)> = OnceLock::new();
+    static OPENAT64: OnceLock<(Hook, OpenAt)> = OnceLock::new();
+
+    // glibc exports some of these as aliases of one function, which must
+    // only be hooked once.
+    macro_rules! install {
+        ($seen:ident, $name:literal, $slot:ident: $ty:ty = $replacement:ident) => {
+            if let Some(target) = find_function($name).filter(|target| !$seen.contains(target)) {
+                $seen.push(target);
+                // SAFETY: `$name` is the libc function `$ty` describes.
+                let original = unsafe { std::mem::transmute::<*const c_void, $ty>(target) };
+                let replacement: $ty = $replacement;
+                $slot
+                    .get_or_init(|| (hook!(original, replacement), original))
+                    .0
+                    .enable()?;
+            }
+        };
+    }
+
+    pub(super) fn start(log: &[u8]) -> io::Result<()> {
+        // Open the log before hooking, so writing it never reenters a hook.
+        let writer: Box<dyn Write + Send> = if log.is_empty() {
+            Box::new(io::stderr())
+        } else {
+            let path = std::str::from_utf8(log).map_err(io::Error::other)?;
+            Box::new(OpenOptions::new().create(true).append(true).open(path)?)
+        };
+        let _ = LOG.set(Mutex::new(writer));
+
+        let mut seen = Vec::new();
+        install!(seen, "open", OPEN: Open = traced_open);
+        install!(seen, "open64", OPEN64: Open = traced_open64);
+        install!(seen, "openat", OPENAT: OpenAt = traced_openat);
+        install!(seen, "openat64", OPENAT64: OpenAt = traced_openat64);
+        Ok(())
+    }
+
+    pub(super) fn stop() {
+        for slot in [&OPEN, &OPEN64] {
+            if let Some((hook, _)) = slot.get() {
+                let _ = hook.disable();
+            }
+        }
+        for slot in [&OPENAT, &OPENAT64] {
+            if let Some((hook, _)) = slot.get() {
+                let _ = hook.disable();
+            }
+        }
+    }
+
+    unsafe extern "C" fn traced_open(path: *const c_char, flags: c_int, mode: c_uint) -> c_int {
+        let (hook, original) = OPEN.get().expect("hook stored before it is enabled");
+        let fd = hook.call_original(|| unsafe { original(path, flags, mode) });
+        log("open", None, path, flags, fd);
+        fd
+    }
+
+    unsafe extern "C" fn traced_open64(path: *const c_char, flags: c_int, mode: c_uint) -> c_int {
+        let (hook, original) = OPEN64.get().expect("hook stored before it is enabled");
+        let fd = hook.call_original(|| unsafe { original(path, flags, mode) });
+        log("open64", None, path, flags, fd);
+        fd
+    }
+
+    unsafe extern "C" fn traced_openat(
+        dirfd: c_int,
+        path: *const c_char,
+        flags: c_int,
+        mode: c_uint,
+    ) -> c_int {
+        let (hook, original) = OPENAT.get().expect("hook stored before it is enabled");
+        let fd = hook.call_original(|| unsafe { original(dirfd, path, flags, mode) });
+        log("openat", Some(dirfd), path, flags, fd);
+        fd
+    }
+
+    unsafe extern "C" fn traced_openat64(
+        dirfd: c_int,
+        path: *const c_char,
+        flags: c_int,
+        mode: c_uint,
+    ) -> c_int {
+        let (hook, original) = OPENAT64.get().expect("hook stored before it is enabled");
+        let fd = hook.call_original(|| unsafe { original(dirfd, path, flags, mode) });
+        log("openat64", Some(dirfd), path, flags, fd);
+        fd
+    }
+
+    fn log(call: &str, dirfd: Option<c_int>, path: *const c_char, flags: c_int, fd: c_int) {
+        // Writing the log must not change the errno the caller sees.
+        // SAFETY: errno is thread-local and always valid.
+        let errno = unsafe { *libc::__errno_location() };
+        let path = if path.is_null() {
+            "NULL".into()
+        } else {
+            // SAFETY: the caller passed a NUL-terminated path to libc.
+            format!("{:?}", unsafe { CStr::from_ptr(path) })
+        };
+        let dirfd = dirfd.map(|fd| format!("{fd}, ")).unwrap_or_default();
+        let result = if fd < 0 {
+            format!("-1 ({})", io::Error::from_raw_os_error(errno))
+        } else {
+            fd.to_string()
+        };
+        if let Some(log) = LOG.get() {
+            let mut log = log.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let _ = writeln!(
+                log,
+                "[pid {}] {call}({dirfd}{path}, {flags:#o}) = {result}",
+                std::process::id()
+            );
+        }
+        // SAFETY: as above.
+        unsafe { *libc::__errno_location() = errno };
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod trace {
+    use std::io;
+
+    pub(super) fn start(_log: &[u8]) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the file tracer only supports Linux",
+        ))
+    }
+
+    pub(super) fn stop() {}
+}
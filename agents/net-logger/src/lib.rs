@@ -0,0 +1,171 @@
+//! Stock agent logging the connections the target makes
+//! (`hook_inject::StockAgent::NetLogger`).
+//!
+//! Hooks `connect` and logs each call's address (IPv4, IPv6 or Unix socket
+//! path) and result. The entrypoint data is the path of the log, appended
+//! to, or empty for the target's standard error. Calls made while another
+//! thread is inside the original function are not logged. Linux only.
+
+use std::ffi::{c_char, c_void};
+use std::sync::atomic::{AtomicI32, Ordering};
+
+/// Entry status read back by the injector (`i32::MIN` until reported).
+#[allow(non_upper_case_globals)]
+#[unsafe(no_mangle)]
+pub static hook_inject_entry_status: AtomicI32 = AtomicI32::new(i32::MIN);
+
+/// # Safety
+/// `data` must be null or a valid NUL-terminated C string pointer, and
+/// `stay_resident` null or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hook_inject_entry(
+    data: *const c_char,
+    stay_resident: *mut i32,
+    _state: *mut c_void,
+) {
+    let data = unsafe { hook_inject_agent::entry_data(data, stay_resident, true) };
+    let status = if net::start(data).is_ok() { 0 } else { 1 };
+    hook_inject_entry_status.store(status, Ordering::SeqCst);
+}
+
+/// Removes the hook before the agent is unloaded.
+#[unsafe(no_mangle)]
+pub extern "C" fn hook_inject_unload(
+    _data: *const c_char,
+    _stay_resident: *mut i32,
+    _state: *mut c_void,
+) {
+    net::stop();
+}
+
+#[cfg(target_os = "linux")]
+mod net {
+    use std::ffi::{CStr, c_int, c_void};
+    use std::fs::OpenOptions;
+    use std::io::{self, Write};
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use std::sync::{Mutex, OnceLock};
+
+    use hook_inject_agent::{Hook, find_function, hook};
+
+    type Connect = unsafe extern "C" fn(c_int, *const libc::sockaddr, libc::socklen_t) -> c_int;
+
+    static LOG: OnceLock<Mutex<Box<dyn Write + Send>>> = OnceLock::new();
+    static CONNECT: OnceLock<(Hook, Connect)> = OnceLock::new();
+
+    pub(super) fn start(log: &[u8]) -> io::Result<()> {
+        // Open the log before hooking, so writing it never reenters the hook.
+        let writer: Box<dyn Write + Send> = if log.is_empty() {
+            Box::new(io::stderr())
+        } else {
+            let path = std::str::from_utf8(log).map_err(io::Error::other)?;
+            Box::new(OpenOptions::new().create(true).append(true).open(path)?)
+        };
+        let _ = LOG.set(Mutex::new(writer));
+
+        let target = find_function("connect")
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "connect is not exported"))?;
+        // SAFETY: `connect` is the libc function `Connect` describes.
+        let original = unsafe { std::mem::transmute::<*const c_void, Connect>(target) };
+        let replacement: Connect = traced_connect;
+        CONNECT
+            .get_or_init(|| (hook!(original, replacement), original))
+            .0
+            .enable()
+    }
+
+    pub(super) fn stop() {
+        if let Some((hook, _)) = CONNECT.get() {
+            let _ = hook.disable();
+        }
+    }
+
+    unsafe extern "C" fn traced_connect(
+        fd: c_int,
+        addr: *const libc::sockaddr,
+        len: libc::socklen_t,
+    ) -> c_int {
+        let (hook, original) = CONNECT.get().expect("hook stored before it is enabled");
+        let result = hook.call_original(|| unsafe { original(fd, addr, len) });
+        // Writing the log must not change the errno the caller sees.
+        // SAFETY: errno is thread-local and always valid.
+        let errno = unsafe { *libc::__errno_location() };
+        // SAFETY: the caller passed `len` valid bytes at `addr` to libc.
+        let peer = unsafe { describe(addr, len) };
+        let outcome = if result < 0 && errno != libc::EINPROGRESS {
+            format!("-1 ({})", io::Error::from_raw_os_error(errno))
+        } else if result < 0 {
+            "-1 (in progress)".to_owned()
+        } else {
+            result.to_string()
+        };
+        if let Some(log) = LOG.get() {
+            let mut log = log.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let _ = writeln!(
+                log,
+                "[pid {}] connect({fd}, {peer}) = {outcome}",
+                std::process::id()
+            );
+        }
+        // SAFETY: as above.
+        unsafe { *libc::__errno_location() = errno };
+        result
+    }
+
+    /// # Safety
+    /// `addr` must be null or valid for `len` bytes.
+    unsafe fn describe(addr: *const libc::sockaddr, len: libc::socklen_t) -> String {
+        if addr.is_null() {
+            return "NULL".into();
+        }
+        let len = len as usize;
+        // SAFETY: every sockaddr starts with its family.
+        let family = c_int::from(unsafe { (*addr).sa_family });
+        match family {
+            libc::AF_INET if len >= size_of::<libc::sockaddr_in>() => {
+                // SAFETY: the family and length say this is a `sockaddr_in`.
+                let addr = unsafe { &*addr.cast::<libc::sockaddr_in>() };
+                let ip = Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr));
+                format!("{ip}:{}", u16::from_be(addr.sin_port))
+            }
+            libc::AF_INET6 if len >= size_of::<libc::sockaddr_in6>() => {
+                // SAFETY: the family and length say this is a `sockaddr_in6`.
+                let addr = unsafe { &*addr.cast::<libc::sockaddr_in6>() };
+                let ip = Ipv6Addr::from(addr.sin6_addr.s6_addr);
+                format!("[{ip}]:{}", u16::from_be(addr.sin6_port))
+            }
+            libc::AF_UNIX => {
+                // The path follows the family and fills the rest of `len`.
+                let offset = size_of::<libc::sa_family_t>();
+                let path_len = len.saturating_sub(offset);
+                // SAFETY: `addr` is valid for `len` bytes.
+                let path =
+                    unsafe { std::slice::from_raw_parts(addr.cast::<u8>().add(offset), path_len) };
+                match path.first() {
+                    None => "unix:(unnamed)".into(),
+                    // Abstract socket names start with a NUL byte.
+                    Some(0) => format!("unix:@{}", String::from_utf8_lossy(&path[1..])),
+                    Some(_) => match CStr::from_bytes_until_nul(path) {
+                        Ok(path) => format!("unix:{}", path.to_string_lossy()),
+                        Err(_) => format!("unix:{}", String::from_utf8_lossy(path)),
+                    },
+                }
+            }
+            family => format!("family {family}"),
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod net {
+    use std::io;
+
+    pub(super) fn start(_log: &[u8]) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the network logger only supports Linux",
+        ))
+    }
+
+    pub(super) fn stop() {}
+}
@@ -1,12 +1,20 @@
 use std::env;
 use std::path::{Path, PathBuf};
 
-use hook_inject_build::{download_devkit, resolve_devkit_platform, resolve_devkit_versions};
+use hook_inject_build::{
+    download_devkit, embed_agent, resolve_devkit_platform, resolve_devkit_versions,
+};
 
 // === Configuration ===
 const DEFAULT_DEVKIT_VERSION: &str = "17.7.3";
 // Explicitly list supported devkit versions to avoid drifting with local installations.
 const SUPPORTED_DEVKIT_VERSIONS: &[&str] = &[DEFAULT_DEVKIT_VERSION];
+// Stock agents (`Library::stock`), each embedded when its feature is enabled.
+const STOCK_AGENTS: &[(&str, &str)] = &[
+    ("CARGO_FEATURE_STOCK_ENV_DUMPER", "agents/env-dumper"),
+    ("CARGO_FEATURE_STOCK_FILE_TRACER", "agents/file-tracer"),
+    ("CARGO_FEATURE_STOCK_NET_LOGGER", "agents/net-logger"),
+];
 
 // === Build entrypoint ===
 fn main() {
@@ -14,21 +22,36 @@ fn main() {
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rerun-if-changed=native/frida_shim.c");
     println!("cargo:rerun-if-changed=native/frida_shim.h");
+    println!("cargo:rerun-if-changed=native/frida_dynamic.c");
+    println!("cargo:rerun-if-changed=native/frida_dynamic.h");
     println!("cargo:rerun-if-env-changed=FRIDA_CORE_DEVKIT_DIR");
     println!("cargo:rerun-if-env-changed=HOOK_INJECT_DEVKIT_VERSION");
     println!("cargo:rerun-if-env-changed=HOOK_INJECT_DEVKIT_PLATFORM");
     println!("cargo:rerun-if-env-changed=CARGO_TARGET_DIR");
 
     let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    embed_stock_agents(&manifest_dir);
+    embed_preload_loader(&manifest_dir);
+    embed_clr_bootstrapper(&manifest_dir);
+
+    // Native-only builds need neither the devkit nor the shim.
+    if env::var_os("CARGO_FEATURE_FRIDA").is_none() {
+        return;
+    }
 
     // Allow power users to point at a prebuilt devkit directly.
     if let Some(devkit_dir) = env::var_os("FRIDA_CORE_DEVKIT_DIR") {
+        // Best effort: the directory doesn't say which platform it is for.
+        if let Ok(platform) = resolve_devkit_platform() {
+            emit_devkit_platform(&platform);
+        }
         build_with_devkit(&manifest_dir, &PathBuf::from(devkit_dir));
         return;
     }
 
     // Download a devkit by default to keep setup simple.
-    if let Some(devkit_dir) = try_download_devkit(&manifest_dir) {
+    if let Some((devkit_dir, platform)) = try_download_devkit(&manifest_dir) {
+        emit_devkit_platform(&platform);
         build_with_devkit(&manifest_dir, &devkit_dir);
         return;
     }
@@ -38,10 +61,53 @@ fn main() {
     );
 }
 
+// === Stock agents ===
+
+fn embed_stock_agents(manifest_dir: &Path) {
+    for (feature, crate_dir) in STOCK_AGENTS {
+        if env::var_os(feature).is_none() {
+            continue;
+        }
+        if let Err(err) = embed_agent(manifest_dir.join(crate_dir)) {
+            panic!("failed to build stock agent {crate_dir}: {err}");
+        }
+    }
+}
+
+// === Preload loader ===
+
+fn embed_preload_loader(manifest_dir: &Path) {
+    // `Backend::Preload` launches programs with this library in LD_PRELOAD
+    // (DYLD_INSERT_LIBRARIES on macOS).
+    let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_else(|_| env::consts::OS.to_string());
+    if env::var_os("CARGO_FEATURE_PRELOAD_BACKEND").is_none()
+        || !matches!(target_os.as_str(), "linux" | "macos")
+    {
+        return;
+    }
+    if let Err(err) = embed_agent(manifest_dir.join("hook-inject-preload")) {
+        panic!("failed to build the preload loader: {err}");
+    }
+}
+
+// === CLR bootstrapper ===
+
+fn embed_clr_bootstrapper(manifest_dir: &Path) {
+    // Injected after libraries with a managed assembly to run it.
+    let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_else(|_| env::consts::OS.to_string());
+    if env::var_os("CARGO_FEATURE_CLR_BOOTSTRAP").is_none() || target_os != "windows" {
+        return;
+    }
+    if let Err(err) = embed_agent(manifest_dir.join("hook-inject-clr")) {
+        panic!("failed to build the CLR bootstrapper: {err}");
+    }
+}
+
 //=== Devkit download ===
 
-fn try_download_devkit(manifest_dir: &Path) -> Option<PathBuf> {
-    // Download a devkit into target/ and return the resolved directory.
+fn try_download_devkit(manifest_dir: &Path) -> Option<(PathBuf, String)> {
+    // Download a devkit into target/ and return the resolved directory and
+    // its platform.
     let (versions, allow_fallback) =
         resolve_devkit_versions(DEFAULT_DEVKIT_VERSION, SUPPORTED_DEVKIT_VERSIONS);
     let platform = match resolve_devkit_platform() {
@@ -92,7 +158,7 @@ fn try_download_devkit(manifest_dir: &Path) -> Option<PathBuf> {
             "cargo:warning=using frida-core devkit {version} ({platform}) from {}",
             devkit_dir.display()
         );
-        return Some(devkit_dir);
+        return Some((devkit_dir, platform));
     }
 
     None
@@ -106,22 +172,35 @@ fn build_with_devkit(manifest_dir: &Path, devkit_dir: &Path) {
 
     emit_devkit_watch(&lib_dir, &header_dir);
 
-    println!("cargo:rustc-link-search=native={}", lib_dir.display());
-    println!(
-        "cargo:rustc-link-lib={}={}",
-        if is_static { "static" } else { "dylib" },
-        lib_name
-    );
-    link_system_libs(is_static);
-
     // frida-core.h is an amalgam, so no extra include paths are required here.
-    cc::Build::new()
-        .file(manifest_dir.join("native/frida_shim.c"))
-        .include(header_dir)
-        .compile("hook_frida_shim");
+    let mut shim = cc::Build::new();
+    shim.file(manifest_dir.join("native/frida_shim.c"))
+        .include(header_dir);
+
+    if env::var_os("CARGO_FEATURE_DYNAMIC_FRIDA").is_some() {
+        // Only the header is used; frida-core is loaded at runtime.
+        shim.file(manifest_dir.join("native/frida_dynamic.c"))
+            .define("HOOK_FRIDA_DYNAMIC", None);
+        link_loader_libs();
+    } else {
+        println!("cargo:rustc-link-search=native={}", lib_dir.display());
+        println!(
+            "cargo:rustc-link-lib={}={}",
+            if is_static { "static" } else { "dylib" },
+            lib_name
+        );
+        link_system_libs(is_static);
+    }
+
+    shim.compile("hook_frida_shim");
 }
 
 // === Devkit helpers ===
+fn emit_devkit_platform(platform: &str) {
+    // Reported by `hook_inject::runtime_version()`.
+    println!("cargo:rustc-env=HOOK_INJECT_BUILT_DEVKIT_PLATFORM={platform}");
+}
+
 fn emit_devkit_watch(lib_dir: &Path, header_dir: &Path) {
     // Keep Cargo rebuilds focused on the devkit artifacts we link against.
     let header = header_dir.join("frida-core.h");
@@ -146,6 +225,14 @@ fn emit_devkit_watch(lib_dir: &Path, header_dir: &Path) {
 }
 
 // === Linking ===
+fn link_loader_libs() {
+    // dlopen lives in libdl before glibc 2.34.
+    let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_else(|_| env::consts::OS.to_string());
+    if matches!(target_os.as_str(), "linux" | "android") {
+        println!("cargo:rustc-link-lib=dl");
+    }
+}
+
 fn link_system_libs(is_static: bool) {
     // Match Frida's link requirements for each target platform.
     let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_else(|_| env::consts::OS.to_string());
@@ -156,6 +243,12 @@ fn link_system_libs(is_static: bool) {
         println!("cargo:rustc-link-lib=resolv");
     }
 
+    // Bionic has pthreads and the resolver built in; Frida logs through liblog.
+    if target_os == "android" {
+        println!("cargo:rustc-link-lib=log");
+        println!("cargo:rustc-link-lib=m");
+    }
+
     if target_vendor == "apple" {
         println!("cargo:rustc-link-lib=bsm");
         println!("cargo:rustc-link-lib=resolv");
@@ -1,5 +1,11 @@
 use std::ffi::{CStr, c_char};
 use std::fs;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+/// Entry status read back by the injector (`i32::MIN` until reported).
+#[allow(non_upper_case_globals)]
+#[unsafe(no_mangle)]
+pub static hook_inject_entry_status: AtomicI32 = AtomicI32::new(i32::MIN);
 
 /// # Safety
 /// `data` must be a valid NUL-terminated C string pointer.
@@ -10,14 +16,48 @@ pub unsafe extern "C" fn hook_inject_entry(
     _state: *mut core::ffi::c_void,
 ) {
     if data.is_null() {
+        hook_inject_entry_status.store(1, Ordering::SeqCst);
         return;
     }
 
     let data = unsafe { CStr::from_ptr(data) };
     let path = data.to_string_lossy();
     if path.is_empty() {
+        hook_inject_entry_status.store(1, Ordering::SeqCst);
         return;
     }
 
-    let _ = fs::write(path.as_ref(), b"ok");
+    let status = if fs::write(path.as_ref(), b"ok").is_ok() {
+        0
+    } else {
+        2
+    };
+    hook_inject_entry_status.store(status, Ordering::SeqCst);
+}
+
+/// Writes `configured` to the path in `data`.
+///
+/// # Safety
+/// `data` must be a valid NUL-terminated C string pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hook_inject_configure(
+    data: *const c_char,
+    _stay_resident: *mut i32,
+    _state: *mut core::ffi::c_void,
+) {
+    if data.is_null() {
+        return;
+    }
+    let data = unsafe { CStr::from_ptr(data) };
+    let _ = fs::write(data.to_string_lossy().as_ref(), b"configured");
+}
+
+/// Nothing to tear down: the agent holds no hooks or threads and does not
+/// stay resident.
+#[unsafe(no_mangle)]
+pub extern "C" fn hook_inject_unload(
+    _data: *const c_char,
+    _stay_resident: *mut i32,
+    _state: *mut core::ffi::c_void,
+) {
 }
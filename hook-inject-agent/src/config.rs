@@ -0,0 +1,63 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Acknowledgements of configuration pushed with
+/// `InjectedProcess::push_config`.
+///
+/// Export one as `hook_inject_config_ack` (`hook_inject::CONFIG_ACK_SYMBOL`)
+/// and, from the configure entrypoint, call `applied` once new data is in
+/// effect or `rejected` if it is invalid. Its layout is two 64-bit
+/// counters: configurations applied, then rejected.
+///
+/// # Examples
+/// ```no_run
+/// use std::ffi::{CStr, c_char};
+/// use hook_inject_agent::ConfigAck;
+///
+/// #[allow(non_upper_case_globals)]
+/// #[unsafe(no_mangle)]
+/// pub static hook_inject_config_ack: ConfigAck = ConfigAck::new();
+///
+/// #[unsafe(no_mangle)]
+/// pub unsafe extern "C" fn hook_inject_configure(path: *const c_char, _: *mut i32, _: *mut std::ffi::c_void) {
+///     // `update_data` passes the path of the staged bytes.
+///     let path = unsafe { CStr::from_ptr(path) }.to_string_lossy();
+///     match std::fs::read_to_string(path.as_ref()) {
+///         Ok(config) if config.starts_with("verbosity=") => {
+///             // ... apply the new verbosity.
+///             hook_inject_config_ack.applied();
+///         }
+///         _ => hook_inject_config_ack.rejected(),
+///     }
+/// }
+/// ```
+#[repr(C)]
+pub struct ConfigAck {
+    applied: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl ConfigAck {
+    /// No configuration acknowledged yet.
+    pub const fn new() -> Self {
+        Self {
+            applied: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    /// Report that the latest configuration is in effect.
+    pub fn applied(&self) {
+        self.applied.fetch_add(1, Ordering::Release);
+    }
+
+    /// Report that the latest configuration was refused.
+    pub fn rejected(&self) {
+        self.rejected.fetch_add(1, Ordering::Release);
+    }
+}
+
+impl Default for ConfigAck {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,131 @@
+use std::cell::UnsafeCell;
+use std::hint;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+/// Bytes of events an `EventStream` holds (`hook_inject::EVENT_BUFFER_CAPACITY`).
+pub const EVENT_BUFFER_CAPACITY: usize = 65536;
+
+// Length and timestamp before each payload.
+const RECORD_HEADER_LEN: usize = 12;
+
+/// Events the injector reads with `InjectedProcess::events`.
+///
+/// Export one as `hook_inject_events` (`hook_inject::EVENTS_SYMBOL`). It is
+/// a ring buffer: when it is full, the oldest events are overwritten, and
+/// the injector notices the gap. Its layout is the offset of the oldest
+/// event and the offset after the newest (64-bit counts of bytes ever
+/// written), then `EVENT_BUFFER_CAPACITY` bytes of records: a 32-bit payload
+/// length, a 64-bit timestamp in milliseconds since the Unix epoch, and the
+/// payload.
+///
+/// # Examples
+/// ```no_run
+/// use hook_inject_agent::EventStream;
+///
+/// #[allow(non_upper_case_globals)]
+/// #[unsafe(no_mangle)]
+/// pub static hook_inject_events: EventStream = EventStream::new();
+///
+/// fn on_open(path: &str) {
+///     hook_inject_events.emit_json(&serde_json::json!({ "open": path }));
+/// }
+/// ```
+#[repr(C)]
+pub struct EventStream {
+    tail: AtomicU64,
+    head: AtomicU64,
+    buffer: UnsafeCell<[u8; EVENT_BUFFER_CAPACITY]>,
+    // Serializes writers; readers go by `tail` and `head` alone.
+    locked: AtomicBool,
+}
+
+// `buffer` is only written while `locked` is held.
+unsafe impl Sync for EventStream {}
+
+impl EventStream {
+    /// An empty stream.
+    pub const fn new() -> Self {
+        Self {
+            tail: AtomicU64::new(0),
+            head: AtomicU64::new(0),
+            buffer: UnsafeCell::new([0; EVENT_BUFFER_CAPACITY]),
+            locked: AtomicBool::new(false),
+        }
+    }
+
+    /// Append an event with `payload`, stamped with the current time.
+    ///
+    /// Returns false, dropping the event, if the payload is larger than the
+    /// buffer can hold.
+    pub fn emit(&self, payload: &[u8]) -> bool {
+        let len = RECORD_HEADER_LEN + payload.len();
+        if len > EVENT_BUFFER_CAPACITY {
+            return false;
+        }
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64);
+        let mut header = [0u8; RECORD_HEADER_LEN];
+        header[..4].copy_from_slice(&(payload.len() as u32).to_ne_bytes());
+        header[4..].copy_from_slice(&millis.to_ne_bytes());
+
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            hint::spin_loop();
+        }
+        // SAFETY: holding `locked` gives this thread sole write access.
+        let buffer = unsafe { &mut *self.buffer.get() };
+        let head = self.head.load(Ordering::Relaxed);
+        let end = head + len as u64;
+
+        // Move the tail past the records about to be overwritten before
+        // writing, so a reader copying them can tell.
+        let mut tail = self.tail.load(Ordering::Relaxed);
+        while end - tail > EVENT_BUFFER_CAPACITY as u64 {
+            let mut old = [0u8; 4];
+            read_ring(buffer, tail, &mut old);
+            tail += (RECORD_HEADER_LEN + u32::from_ne_bytes(old) as usize) as u64;
+        }
+        self.tail.store(tail, Ordering::Release);
+
+        write_ring(buffer, head, &header);
+        write_ring(buffer, head + RECORD_HEADER_LEN as u64, payload);
+        self.head.store(end, Ordering::Release);
+        self.locked.store(false, Ordering::Release);
+        true
+    }
+
+    /// Append an event with `value` serialized as JSON as its payload.
+    ///
+    /// Returns false if `value` fails to serialize or is too large.
+    pub fn emit_json<T: Serialize + ?Sized>(&self, value: &T) -> bool {
+        serde_json::to_vec(value).is_ok_and(|payload| self.emit(&payload))
+    }
+}
+
+impl Default for EventStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn write_ring(buffer: &mut [u8; EVENT_BUFFER_CAPACITY], offset: u64, bytes: &[u8]) {
+    let start = (offset % EVENT_BUFFER_CAPACITY as u64) as usize;
+    let first = bytes.len().min(EVENT_BUFFER_CAPACITY - start);
+    buffer[start..start + first].copy_from_slice(&bytes[..first]);
+    buffer[..bytes.len() - first].copy_from_slice(&bytes[first..]);
+}
+
+fn read_ring(buffer: &[u8; EVENT_BUFFER_CAPACITY], offset: u64, bytes: &mut [u8]) {
+    let start = (offset % EVENT_BUFFER_CAPACITY as u64) as usize;
+    let first = bytes.len().min(EVENT_BUFFER_CAPACITY - start);
+    bytes[..first].copy_from_slice(&buffer[start..start + first]);
+    let rest = bytes.len() - first;
+    bytes[first..].copy_from_slice(&buffer[..rest]);
+}
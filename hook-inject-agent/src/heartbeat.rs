@@ -0,0 +1,65 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Heartbeats the injector reads with `InjectedProcess::health`.
+///
+/// Export one as `hook_inject_heartbeat` (`hook_inject::HEARTBEAT_SYMBOL`).
+/// It holds the time of the last `beat` in milliseconds since the Unix
+/// epoch, or `0` before the first one.
+///
+/// # Examples
+/// ```no_run
+/// use std::time::Duration;
+/// use hook_inject_agent::Heartbeat;
+///
+/// #[allow(non_upper_case_globals)]
+/// #[unsafe(no_mangle)]
+/// pub static hook_inject_heartbeat: Heartbeat = Heartbeat::new();
+///
+/// fn worker_loop() {
+///     loop {
+///         // ... process one batch of events.
+///         hook_inject_heartbeat.beat();
+///     }
+/// }
+/// ```
+#[repr(transparent)]
+pub struct Heartbeat(AtomicU64);
+
+impl Heartbeat {
+    /// A heartbeat that has not beaten yet.
+    pub const fn new() -> Self {
+        Self(AtomicU64::new(0))
+    }
+
+    /// Record that the agent is alive now.
+    pub fn beat(&self) {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(1, |elapsed| elapsed.as_millis().max(1) as u64);
+        self.0.store(millis, Ordering::Release);
+    }
+
+    /// Beat every `interval` from a background thread, for as long as the
+    /// agent is loaded.
+    ///
+    /// This shows the agent is loaded and scheduled, not that its own work
+    /// makes progress; call `beat` from that work to catch it wedging.
+    pub fn spawn(&'static self, interval: Duration) -> std::io::Result<()> {
+        std::thread::Builder::new()
+            .name("hook-inject-heartbeat".into())
+            .spawn(move || {
+                loop {
+                    self.beat();
+                    std::thread::sleep(interval);
+                }
+            })
+            .map(|_| ())
+    }
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,307 @@
+use std::ffi::c_void;
+use std::io;
+use std::sync::Mutex;
+
+/// An inline hook redirecting calls to one function to another.
+///
+/// Enabling the hook overwrites the first instructions of the target with a
+/// jump to the replacement; disabling it, or dropping the hook, puts them
+/// back. Build one with `hook!`, which checks that both functions have the
+/// same signature, and keep it in a `static` so the replacement can reach
+/// the original through `call_original`.
+///
+/// Supported on Unix, on x86_64 and aarch64.
+///
+/// # Examples
+/// ```no_run
+/// use std::ffi::{CStr, c_char};
+/// use std::sync::OnceLock;
+/// use hook_inject_agent::{Hook, hook};
+///
+/// unsafe extern "C" {
+///     fn getenv(name: *const c_char) -> *mut c_char;
+/// }
+///
+/// static GETENV: OnceLock<Hook> = OnceLock::new();
+///
+/// unsafe extern "C" fn logged_getenv(name: *const c_char) -> *mut c_char {
+///     eprintln!("getenv({:?})", unsafe { CStr::from_ptr(name) });
+///     let hook = GETENV.get().expect("set before the hook is enabled");
+///     hook.call_original(|| unsafe { getenv(name) })
+/// }
+///
+/// fn install() -> std::io::Result<()> {
+///     GETENV.get_or_init(|| hook!(getenv, logged_getenv)).enable()
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Hook {
+    target: *mut u8,
+    patch: [u8; arch::PATCH_LEN],
+    // The target's original bytes while the hook is enabled.
+    original: Mutex<Option<[u8; arch::PATCH_LEN]>>,
+}
+
+// The hook only touches the target's code, under `original`'s lock.
+unsafe impl Send for Hook {}
+unsafe impl Sync for Hook {}
+
+impl Hook {
+    /// Prepare a hook redirecting `target` to `replacement`, both function
+    /// addresses. It is not enabled yet.
+    ///
+    /// # Safety
+    /// `replacement` must have the same signature and calling convention as
+    /// `target`, and `target` must be a function at least as long as the
+    /// jump written over it (14 bytes on x86_64, 16 on aarch64) whose first
+    /// instructions are not jumped to from inside it. No thread may be
+    /// executing those instructions while the hook is enabled or disabled.
+    pub unsafe fn new(target: *const c_void, replacement: *const c_void) -> Self {
+        Self {
+            target: target.cast_mut().cast(),
+            patch: arch::jump(replacement as usize),
+            original: Mutex::new(None),
+        }
+    }
+
+    /// Redirect calls to the target. Enabling an enabled hook does nothing.
+    pub fn enable(&self) -> io::Result<()> {
+        let mut original = self.lock();
+        if original.is_some() {
+            return Ok(());
+        }
+        *original = Some(self.swap(&self.patch)?);
+        Ok(())
+    }
+
+    /// Let calls reach the target again. Disabling a disabled hook does
+    /// nothing.
+    pub fn disable(&self) -> io::Result<()> {
+        let mut original = self.lock();
+        if let Some(bytes) = *original {
+            self.swap(&bytes)?;
+            *original = None;
+        }
+        Ok(())
+    }
+
+    /// Returns true if calls to the target are redirected.
+    pub fn is_enabled(&self) -> bool {
+        self.lock().is_some()
+    }
+
+    /// Run `f` with the hook disabled, so calls it makes to the target run
+    /// the original function.
+    ///
+    /// Other threads calling the target meanwhile run the original too, or
+    /// wait if they call `call_original` themselves; `f` must not call
+    /// `enable`, `disable` or `call_original` on the same hook.
+    ///
+    /// # Panics
+    /// Panics if the target's original instructions cannot be restored.
+    pub fn call_original<R>(&self, f: impl FnOnce() -> R) -> R {
+        let original = self.lock();
+        let Some(bytes) = *original else {
+            return f();
+        };
+        if self.swap(&bytes).is_err() {
+            // The target could not be restored, so the hook cannot be
+            // bypassed; calling `f` would only recurse into the replacement.
+            panic!("failed to restore the hooked function");
+        }
+        // Put the hook back even if `f` unwinds.
+        struct Reenable<'a>(&'a Hook);
+        impl Drop for Reenable<'_> {
+            fn drop(&mut self) {
+                let _ = self.0.swap(&self.0.patch);
+            }
+        }
+        let _reenable = Reenable(self);
+        f()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<[u8; arch::PATCH_LEN]>> {
+        self.original
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Write `bytes` over the start of the target, returning what was there.
+    fn swap(&self, bytes: &[u8; arch::PATCH_LEN]) -> io::Result<[u8; arch::PATCH_LEN]> {
+        if arch::PATCH_LEN == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "hooking is only supported on x86_64 and aarch64",
+            ));
+        }
+        let mut previous = [0; arch::PATCH_LEN];
+        // SAFETY: `new`'s contract makes the first `PATCH_LEN` bytes of the
+        // target ours to read and rewrite.
+        unsafe {
+            std::ptr::copy_nonoverlapping(self.target, previous.as_mut_ptr(), arch::PATCH_LEN);
+            sys::write_code(self.target, bytes)?;
+        }
+        Ok(previous)
+    }
+}
+
+impl Drop for Hook {
+    fn drop(&mut self) {
+        let _ = self.disable();
+    }
+}
+
+/// Build a `Hook` redirecting calls to `target` to `replacement`.
+///
+/// Both must be functions (or function pointers) with the same signature,
+/// which is checked at compile time; enable the hook with `Hook::enable`.
+/// The remaining requirements of `Hook::new`, that the target is long
+/// enough to hold the jump and that no thread is executing its first
+/// instructions while it is patched, hold for ordinary functions.
+///
+/// # Examples
+/// ```no_run
+/// use std::ffi::c_int;
+/// use hook_inject_agent::hook;
+///
+/// unsafe extern "C" {
+///     fn rand() -> c_int;
+/// }
+///
+/// unsafe extern "C" fn fair_dice_roll() -> c_int {
+///     4
+/// }
+///
+/// fn install() -> std::io::Result<()> {
+///     let hook = hook!(rand, fair_dice_roll);
+///     hook.enable()?;
+///     // Keep the hook enabled for as long as the agent is loaded.
+///     std::mem::forget(hook);
+///     Ok(())
+/// }
+/// ```
+#[macro_export]
+macro_rules! hook {
+    ($target:expr, $replacement:expr $(,)?) => {{
+        // Both coerce to one function pointer type only if the signatures
+        // match.
+        let [target, replacement] = [$target, $replacement];
+        // SAFETY: the signatures match; see the macro's documentation.
+        unsafe {
+            $crate::Hook::new(
+                target as *const ::std::ffi::c_void,
+                replacement as *const ::std::ffi::c_void,
+            )
+        }
+    }};
+}
+
+/// Look up an exported function by name in the modules loaded in the
+/// target, for hooking functions the agent does not link against.
+///
+/// Supported on Unix.
+pub fn find_function(name: &str) -> Option<*const c_void> {
+    sys::find_function(name)
+}
+
+#[cfg(target_arch = "x86_64")]
+mod arch {
+    pub(super) const PATCH_LEN: usize = 14;
+
+    /// `jmp qword ptr [rip]` followed by the destination.
+    pub(super) fn jump(to: usize) -> [u8; PATCH_LEN] {
+        let mut code = [0xff, 0x25, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        code[6..].copy_from_slice(&(to as u64).to_le_bytes());
+        code
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod arch {
+    pub(super) const PATCH_LEN: usize = 16;
+
+    /// `ldr x16, #8; br x16` followed by the destination.
+    pub(super) fn jump(to: usize) -> [u8; PATCH_LEN] {
+        let mut code = [0; PATCH_LEN];
+        code[..4].copy_from_slice(&0x5800_0050u32.to_le_bytes());
+        code[4..8].copy_from_slice(&0xd61f_0200u32.to_le_bytes());
+        code[8..].copy_from_slice(&(to as u64).to_le_bytes());
+        code
+    }
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+mod arch {
+    pub(super) const PATCH_LEN: usize = 0;
+
+    pub(super) fn jump(_to: usize) -> [u8; PATCH_LEN] {
+        []
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::ffi::{CString, c_void};
+    use std::io;
+
+    /// Copy `bytes` over code at `at`, leaving its pages readable and
+    /// executable.
+    ///
+    /// # Safety
+    /// `at..at + bytes.len()` must be code that no thread is executing.
+    pub(super) unsafe fn write_code(at: *mut u8, bytes: &[u8]) -> io::Result<()> {
+        // SAFETY: querying the page size has no preconditions.
+        let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let start = at as usize & !(page - 1);
+        let len = at as usize + bytes.len() - start;
+        let protect = |prot| {
+            // SAFETY: `start..start + len` covers mapped code pages.
+            if unsafe { libc::mprotect(start as *mut libc::c_void, len, prot) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        };
+        protect(libc::PROT_READ | libc::PROT_WRITE | libc::PROT_EXEC)?;
+        // SAFETY: the pages are writable and the caller owns the bytes.
+        unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), at, bytes.len()) };
+        protect(libc::PROT_READ | libc::PROT_EXEC)?;
+        flush_icache(at, bytes.len());
+        Ok(())
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    fn flush_icache(at: *mut u8, len: usize) {
+        unsafe extern "C" {
+            fn __clear_cache(start: *mut libc::c_char, end: *mut libc::c_char);
+        }
+        // SAFETY: flushing cache lines of mapped memory has no other effect.
+        unsafe { __clear_cache(at.cast(), at.add(len).cast()) };
+    }
+
+    #[cfg(not(target_arch = "aarch64"))]
+    fn flush_icache(_at: *mut u8, _len: usize) {}
+
+    pub(super) fn find_function(name: &str) -> Option<*const c_void> {
+        let name = CString::new(name).ok()?;
+        // SAFETY: `name` is a valid C string.
+        let symbol = unsafe { libc::dlsym(libc::RTLD_DEFAULT, name.as_ptr()) };
+        (!symbol.is_null()).then_some(symbol.cast_const().cast())
+    }
+}
+
+#[cfg(not(unix))]
+mod sys {
+    use std::ffi::c_void;
+    use std::io;
+
+    pub(super) unsafe fn write_code(_at: *mut u8, _bytes: &[u8]) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "hooking is only supported on Unix",
+        ))
+    }
+
+    pub(super) fn find_function(_name: &str) -> Option<*const c_void> {
+        None
+    }
+}
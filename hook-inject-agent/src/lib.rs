@@ -0,0 +1,154 @@
+//! Agent-side counterpart of `hook-inject`.
+//!
+//! An injector passes a typed value with `Library::with_payload`; the agent
+//! decodes it from its entrypoint's data argument:
+//!
+//! ```no_run
+//! use std::ffi::c_char;
+//!
+//! #[derive(serde::Deserialize)]
+//! struct Config {
+//!     port: u16,
+//! }
+//!
+//! #[unsafe(no_mangle)]
+//! pub unsafe extern "C" fn agent_main(data: *const c_char, _: *mut i32, _: *mut std::ffi::c_void) {
+//!     let config: Config = unsafe { hook_inject_agent::payload_from_ptr(data) }.unwrap();
+//! }
+//! ```
+//!
+//! An injector can also choose whether the agent stays loaded after its
+//! entrypoint returns (`Library::with_unload_policy`); `entry_data` applies
+//! that choice to the entrypoint's `stay_resident` flag, and panics can be
+//! reported back to the injector with `install_panic_hook` and liveness with
+//! a `Heartbeat`. Structured events go to the injector through an
+//! `EventStream`, as can the target's own output (`capture_output`), pushed configuration is acknowledged with a `ConfigAck`,
+//! bulk data can go through a `SharedMemory` segment, and functions in the
+//! target can be redirected with `hook!`.
+
+use std::ffi::{CStr, c_char};
+
+use serde::de::DeserializeOwned;
+
+mod config;
+mod events;
+mod heartbeat;
+mod hook;
+mod output;
+mod panic;
+mod shm;
+
+pub use config::ConfigAck;
+pub use events::{EVENT_BUFFER_CAPACITY, EventStream};
+pub use heartbeat::Heartbeat;
+pub use hook::{Hook, find_function};
+pub use output::{OutputCapture, capture_output};
+pub use panic::{PANIC_REPORT_CAPACITY, PanicReport, install_panic_hook};
+pub use shm::SharedMemory;
+
+/// Decode a payload set with `Library::with_payload` from the data string.
+///
+/// An unload policy header (see `split_unload_policy`) is skipped.
+///
+/// # Examples
+/// ```
+/// let value: Vec<u32> = hook_inject_agent::payload_from_cstr(c"[1,2,3]").unwrap();
+/// assert_eq!(value, [1, 2, 3]);
+/// ```
+pub fn payload_from_cstr<T: DeserializeOwned>(data: &CStr) -> serde_json::Result<T> {
+    serde_json::from_slice(split_unload_policy(data).1)
+}
+
+/// Decode a payload from the entrypoint's raw data argument.
+///
+/// A null pointer decodes like an empty string, which fails.
+///
+/// # Safety
+/// `data` must be null or point to a valid NUL-terminated string.
+pub unsafe fn payload_from_ptr<T: DeserializeOwned>(data: *const c_char) -> serde_json::Result<T> {
+    if data.is_null() {
+        return serde_json::from_slice(b"");
+    }
+    payload_from_cstr(unsafe { CStr::from_ptr(data) })
+}
+
+/// Whether the injector asked the agent to stay loaded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UnloadPolicy {
+    /// Stay loaded until ejected.
+    Resident,
+    /// Be unloaded as soon as the entrypoint returns.
+    Unload,
+}
+
+/// Split the unload policy header `Library::with_unload_policy` adds from
+/// the data string.
+///
+/// # Examples
+/// ```
+/// use hook_inject_agent::{UnloadPolicy, split_unload_policy};
+///
+/// let (policy, data) = split_unload_policy(c"\x01resident\x01port=80");
+/// assert_eq!(policy, Some(UnloadPolicy::Resident));
+/// assert_eq!(data, b"port=80");
+/// assert_eq!(split_unload_policy(c"port=80"), (None, &b"port=80"[..]));
+/// ```
+pub fn split_unload_policy(data: &CStr) -> (Option<UnloadPolicy>, &[u8]) {
+    let bytes = data.to_bytes();
+    for (header, policy) in [
+        (&b"\x01resident\x01"[..], UnloadPolicy::Resident),
+        (&b"\x01unload\x01"[..], UnloadPolicy::Unload),
+    ] {
+        if let Some(rest) = bytes.strip_prefix(header) {
+            return (Some(policy), rest);
+        }
+    }
+    (None, bytes)
+}
+
+/// Apply the injector's unload policy and return the data string without
+/// its header.
+///
+/// `stay_resident` is set as the injector asked, or to `resident` if it left
+/// the choice to the agent. Call it from the main entrypoint with the
+/// arguments the runtime passed.
+///
+/// # Safety
+/// `data` must be null or point to a valid NUL-terminated string that
+/// outlives the returned slice, and `stay_resident` must be null or valid
+/// for writes.
+///
+/// # Examples
+/// ```no_run
+/// use std::ffi::c_char;
+///
+/// #[unsafe(no_mangle)]
+/// pub unsafe extern "C" fn agent_main(
+///     data: *const c_char,
+///     stay_resident: *mut i32,
+///     _: *mut std::ffi::c_void,
+/// ) {
+///     let data = unsafe { hook_inject_agent::entry_data(data, stay_resident, true) };
+///     // ... install hooks configured by `data`.
+/// }
+/// ```
+pub unsafe fn entry_data<'a>(
+    data: *const c_char,
+    stay_resident: *mut i32,
+    resident: bool,
+) -> &'a [u8] {
+    let (policy, data) = if data.is_null() {
+        (None, &b""[..])
+    } else {
+        split_unload_policy(unsafe { CStr::from_ptr(data) })
+    };
+    let resident = match policy {
+        Some(UnloadPolicy::Resident) => true,
+        Some(UnloadPolicy::Unload) => false,
+        None => resident,
+    };
+    if !stay_resident.is_null() {
+        unsafe { *stay_resident = i32::from(resident) };
+    }
+    data
+}
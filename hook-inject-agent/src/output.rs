@@ -0,0 +1,231 @@
+use std::io;
+use std::thread::JoinHandle;
+
+use crate::EventStream;
+
+/// Redirects the target's stdout and stderr into an `EventStream`.
+///
+/// Returned by `capture_output`; `stop` puts the original descriptors back.
+/// Dropping it leaves the capture running for as long as the agent is
+/// loaded.
+#[derive(Debug)]
+pub struct OutputCapture {
+    // Each captured descriptor and a duplicate of what it pointed to before.
+    saved: Vec<(i32, i32)>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+/// Capture what the target writes to stdout and stderr, for the injector to
+/// read with `InjectedProcess::output`.
+///
+/// Both descriptors are redirected into pipes read by background threads,
+/// which still copy everything to the original destinations, so the
+/// target's own logs keep working. Each chunk becomes an event in `stream`
+/// whose payload is the descriptor number (`1` or `2`) followed by the
+/// bytes. Export the stream as `hook_inject_output`
+/// (`hook_inject::OUTPUT_SYMBOL`).
+///
+/// Only output written after the call is captured; data still buffered by
+/// the target's stdio is captured when it is flushed. Supported on Unix.
+///
+/// # Examples
+/// ```no_run
+/// use std::ffi::c_char;
+/// use std::sync::Mutex;
+/// use hook_inject_agent::{EventStream, OutputCapture};
+///
+/// #[allow(non_upper_case_globals)]
+/// #[unsafe(no_mangle)]
+/// pub static hook_inject_output: EventStream = EventStream::new();
+///
+/// static CAPTURE: Mutex<Option<OutputCapture>> = Mutex::new(None);
+///
+/// #[unsafe(no_mangle)]
+/// pub extern "C" fn agent_main(_data: *const c_char, stay_resident: *mut i32) {
+///     if let Ok(capture) = hook_inject_agent::capture_output(&hook_inject_output) {
+///         *CAPTURE.lock().unwrap() = Some(capture);
+///     }
+///     unsafe { *stay_resident = 1 };
+/// }
+///
+/// #[unsafe(no_mangle)]
+/// pub extern "C" fn hook_inject_unload(_data: *const c_char, _stay_resident: *mut i32) {
+///     if let Some(capture) = CAPTURE.lock().unwrap().take() {
+///         let _ = capture.stop();
+///     }
+/// }
+/// ```
+pub fn capture_output(stream: &'static EventStream) -> io::Result<OutputCapture> {
+    let mut capture = OutputCapture {
+        saved: Vec::new(),
+        threads: Vec::new(),
+    };
+    for fd in [1, 2] {
+        if let Err(err) = capture.add(fd, stream) {
+            let _ = capture.stop();
+            return Err(err);
+        }
+    }
+    Ok(capture)
+}
+
+impl OutputCapture {
+    fn add(&mut self, fd: i32, stream: &'static EventStream) -> io::Result<()> {
+        let (saved, pipe) = sys::redirect(fd)?;
+        match forward(fd, pipe, saved, stream) {
+            Ok(thread) => {
+                self.saved.push((fd, saved));
+                self.threads.push(thread);
+                Ok(())
+            }
+            Err(err) => {
+                let _ = sys::restore(fd, saved);
+                sys::close(saved);
+                sys::close(pipe);
+                Err(err)
+            }
+        }
+    }
+
+    /// Point stdout and stderr back where they were, and wait for the
+    /// captured output to be forwarded.
+    ///
+    /// Processes the target started while capturing inherit the pipes, and
+    /// this waits until they close them too.
+    pub fn stop(mut self) -> io::Result<()> {
+        let mut result = Ok(());
+        for (fd, saved) in self.saved.drain(..) {
+            // Replacing the pipe's only write end ends its reader, which
+            // then closes `saved`.
+            if let Err(err) = sys::restore(fd, saved) {
+                result = Err(err);
+            }
+        }
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+        result
+    }
+}
+
+fn forward(
+    fd: i32,
+    pipe: i32,
+    original: i32,
+    stream: &'static EventStream,
+) -> io::Result<JoinHandle<()>> {
+    std::thread::Builder::new()
+        .name("hook-inject-output".into())
+        .spawn(move || {
+            let mut chunk = [0u8; 4096];
+            chunk[0] = fd as u8;
+            while let Ok(len @ 1..) = sys::read(pipe, &mut chunk[1..]) {
+                sys::write_all(original, &chunk[1..=len]);
+                stream.emit(&chunk[..=len]);
+            }
+            sys::close(pipe);
+            sys::close(original);
+        })
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::io;
+
+    fn check(ret: libc::c_int) -> io::Result<libc::c_int> {
+        if ret < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(ret)
+        }
+    }
+
+    /// Point `fd` at a new pipe, returning a duplicate of its previous
+    /// destination and the pipe's read end.
+    pub(super) fn redirect(fd: i32) -> io::Result<(i32, i32)> {
+        let mut ends = [0; 2];
+        // SAFETY: `ends` has room for both descriptors.
+        check(unsafe { libc::pipe(ends.as_mut_ptr()) })?;
+        let [read, write] = ends;
+        // SAFETY: plain descriptor calls on descriptors owned here.
+        let result = unsafe {
+            libc::fcntl(read, libc::F_SETFD, libc::FD_CLOEXEC);
+            check(libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 0)).and_then(|saved| {
+                match check(libc::dup2(write, fd)) {
+                    Ok(_) => Ok((saved, read)),
+                    Err(err) => {
+                        libc::close(saved);
+                        Err(err)
+                    }
+                }
+            })
+        };
+        // SAFETY: `write` is owned here; `fd` holds its own reference.
+        unsafe { libc::close(write) };
+        if result.is_err() {
+            close(read);
+        }
+        result
+    }
+
+    pub(super) fn restore(fd: i32, saved: i32) -> io::Result<()> {
+        // SAFETY: `saved` is a descriptor owned by the capture.
+        check(unsafe { libc::dup2(saved, fd) }).map(|_| ())
+    }
+
+    pub(super) fn read(fd: i32, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            // SAFETY: `buf` is valid for writes of its length.
+            let len = unsafe { libc::read(fd, buf.as_mut_ptr().cast(), buf.len()) };
+            if len >= 0 {
+                return Ok(len as usize);
+            }
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    }
+
+    /// Best effort: output the original destination refuses is dropped.
+    pub(super) fn write_all(fd: i32, mut buf: &[u8]) {
+        while !buf.is_empty() {
+            // SAFETY: `buf` is valid for reads of its length.
+            let len = unsafe { libc::write(fd, buf.as_ptr().cast(), buf.len()) };
+            if len > 0 {
+                buf = &buf[len as usize..];
+            } else if len == 0 || io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+                return;
+            }
+        }
+    }
+
+    pub(super) fn close(fd: i32) {
+        // SAFETY: `fd` is owned by the caller and not used afterwards.
+        unsafe { libc::close(fd) };
+    }
+}
+
+#[cfg(not(unix))]
+mod sys {
+    use std::io;
+
+    pub(super) fn redirect(_fd: i32) -> io::Result<(i32, i32)> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "output capture is only supported on Unix",
+        ))
+    }
+
+    pub(super) fn restore(_fd: i32, _saved: i32) -> io::Result<()> {
+        Ok(())
+    }
+
+    pub(super) fn read(_fd: i32, _buf: &mut [u8]) -> io::Result<usize> {
+        Ok(0)
+    }
+
+    pub(super) fn write_all(_fd: i32, _buf: &[u8]) {}
+
+    pub(super) fn close(_fd: i32) {}
+}
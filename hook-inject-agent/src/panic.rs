@@ -0,0 +1,100 @@
+use std::cell::UnsafeCell;
+use std::panic::PanicHookInfo;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+/// Most bytes of text a `PanicReport` holds (`hook_inject::PANIC_REPORT_CAPACITY`).
+pub const PANIC_REPORT_CAPACITY: usize = 4092;
+
+/// Where `install_panic_hook` records a panic for the injector to read.
+///
+/// Export one as `hook_inject_panic_report` (`hook_inject::PANIC_REPORT_SYMBOL`);
+/// the injector reads it with `InjectedProcess::panic_report`. Its layout is
+/// a 32-bit length, `0` until a panic is recorded, followed by the text.
+#[repr(C)]
+pub struct PanicReport {
+    len: AtomicU32,
+    text: UnsafeCell<[u8; PANIC_REPORT_CAPACITY]>,
+    // Only the first panic is recorded; later ones would race its readers.
+    claimed: AtomicBool,
+}
+
+// `text` is written once, by whoever claims the report, before `len`
+// publishes it.
+unsafe impl Sync for PanicReport {}
+
+impl PanicReport {
+    /// An empty report.
+    pub const fn new() -> Self {
+        Self {
+            len: AtomicU32::new(0),
+            text: UnsafeCell::new([0; PANIC_REPORT_CAPACITY]),
+            claimed: AtomicBool::new(false),
+        }
+    }
+
+    /// The recorded panic, if any.
+    pub fn get(&self) -> Option<&str> {
+        let len = self.len.load(Ordering::Acquire) as usize;
+        if len == 0 {
+            return None;
+        }
+        // SAFETY: `len` is only set after `text` is written, and never again.
+        let text = unsafe { &(&*self.text.get())[..len] };
+        std::str::from_utf8(text).ok()
+    }
+
+    /// Record `text`, truncated to `PANIC_REPORT_CAPACITY` bytes, unless a
+    /// panic was recorded already.
+    pub fn record(&self, text: &str) {
+        if self.claimed.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let mut len = text.len().min(PANIC_REPORT_CAPACITY);
+        while !text.is_char_boundary(len) {
+            len -= 1;
+        }
+        // SAFETY: claiming the report gives this thread sole write access.
+        let buffer = unsafe { &mut *self.text.get() };
+        buffer[..len].copy_from_slice(&text.as_bytes()[..len]);
+        self.len.store(len as u32, Ordering::Release);
+    }
+}
+
+impl Default for PanicReport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Record the first panic in the agent, with its location and backtrace,
+/// in `report`.
+///
+/// The previous panic hook still runs afterwards. Panics must not unwind out
+/// of an `extern "C"` entrypoint, which aborts the target, so entrypoints
+/// should run their body under `std::panic::catch_unwind`.
+///
+/// # Examples
+/// ```no_run
+/// use std::ffi::c_char;
+/// use hook_inject_agent::PanicReport;
+///
+/// #[allow(non_upper_case_globals)]
+/// #[unsafe(no_mangle)]
+/// pub static hook_inject_panic_report: PanicReport = PanicReport::new();
+///
+/// #[unsafe(no_mangle)]
+/// pub extern "C" fn agent_main(_data: *const c_char, _stay_resident: *mut i32) {
+///     hook_inject_agent::install_panic_hook(&hook_inject_panic_report);
+///     let _ = std::panic::catch_unwind(|| {
+///         // ... install hooks.
+///     });
+/// }
+/// ```
+pub fn install_panic_hook(report: &'static PanicReport) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info: &PanicHookInfo<'_>| {
+        let backtrace = std::backtrace::Backtrace::force_capture();
+        report.record(&format!("{info}\n{backtrace}"));
+        previous(info);
+    }));
+}
@@ -0,0 +1,144 @@
+use std::io;
+
+/// A shared memory segment created by the injector
+/// (`hook_inject::SharedMemory`), mapped into the agent.
+///
+/// # Examples
+/// ```no_run
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use hook_inject_agent::SharedMemory;
+///
+/// fn start(data: &[u8]) -> std::io::Result<()> {
+///     let descriptor = std::str::from_utf8(data).map_err(std::io::Error::other)?;
+///     let shm = SharedMemory::open(descriptor)?;
+///     // The first 8 bytes count the records written so far.
+///     let count = unsafe { &*(shm.as_ptr() as *const AtomicU64) };
+///     count.fetch_add(1, Ordering::Release);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SharedMemory {
+    ptr: *mut u8,
+    len: usize,
+}
+
+// The mapping is plain memory; callers synchronize access to its contents.
+unsafe impl Send for SharedMemory {}
+unsafe impl Sync for SharedMemory {}
+
+impl SharedMemory {
+    /// Map the segment described by `descriptor`, the
+    /// `hook_inject::SharedMemory::descriptor` string (`<name>:<len>`).
+    ///
+    /// Supported on Unix.
+    pub fn open(descriptor: &str) -> io::Result<Self> {
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid shared memory descriptor",
+            )
+        };
+        let (name, len) = descriptor.rsplit_once(':').ok_or_else(invalid)?;
+        let len: usize = len.parse().map_err(|_| invalid())?;
+        if name.is_empty() || len == 0 {
+            return Err(invalid());
+        }
+        let ptr = sys::open(name, len)?;
+        Ok(Self { ptr, len })
+    }
+
+    /// The size of the segment in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the segment is empty, which `open` never returns.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The start of the mapping in the target.
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.ptr
+    }
+
+    /// View the segment as a byte slice.
+    ///
+    /// # Safety
+    /// No one, including the injector, may write to the segment while the
+    /// slice is alive.
+    pub unsafe fn as_slice(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    /// View the segment as a mutable byte slice.
+    ///
+    /// # Safety
+    /// No one, including the injector, may access the segment while the
+    /// slice is alive.
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn as_mut_slice(&self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl Drop for SharedMemory {
+    fn drop(&mut self) {
+        sys::unmap(self.ptr, self.len);
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::ffi::CString;
+    use std::io;
+
+    pub(super) fn open(name: &str, len: usize) -> io::Result<*mut u8> {
+        let name =
+            CString::new(name).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        // SAFETY: `name` is a valid C string; the descriptor is closed below.
+        let fd = unsafe { libc::shm_open(name.as_ptr(), libc::O_RDWR, 0) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: a fresh shared mapping of `len` bytes of `fd`.
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd,
+                0,
+            )
+        };
+        let mapped = if ptr == libc::MAP_FAILED {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(ptr.cast())
+        };
+        // SAFETY: `fd` is open and owned here; the mapping outlives it.
+        unsafe { libc::close(fd) };
+        mapped
+    }
+
+    pub(super) fn unmap(ptr: *mut u8, len: usize) {
+        // SAFETY: `ptr` and `len` describe a mapping made by `open`.
+        unsafe { libc::munmap(ptr.cast(), len) };
+    }
+}
+
+#[cfg(not(unix))]
+mod sys {
+    use std::io;
+
+    pub(super) fn open(_name: &str, _len: usize) -> io::Result<*mut u8> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "shared memory is only supported on Unix",
+        ))
+    }
+
+    pub(super) fn unmap(_ptr: *mut u8, _len: usize) {}
+}
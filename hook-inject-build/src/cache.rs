@@ -0,0 +1,284 @@
+//! Reuse of cdylibs built from unchanged crates.
+//!
+//! A fingerprint of everything the build depends on, as far as it can be
+//! told from outside cargo, is recorded in a stamp file next to the
+//! artifact, and remembered for the rest of the process, so later requests
+//! for the same build skip `cargo build`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+
+use crate::{
+    BuildError, BuildOptions, CdylibInfo, CrateMetadata, Result, build_cdylib_with, find_artifact,
+    read_metadata,
+};
+
+const STAMP_SUFFIX: &str = "hook-inject-fingerprint";
+
+/// Like `build_cdylib_with`, skipping `cargo build` when the crate has not
+/// changed since the artifact was last built with the same options.
+///
+/// The fingerprint covers:
+/// - every file under the crate directory, except the target directory,
+///   hidden entries and the metadata `data-file`;
+/// - the same for the path dependencies its manifest declares, and theirs;
+/// - the nearest `Cargo.lock` at or above the crate directory;
+/// - the version of `rustc` (or `$RUSTC`) and the environment variables
+///   cargo builds with: `RUSTFLAGS`, `RUSTC*`, `CARGO_BUILD_*`,
+///   `CARGO_PROFILE_*`, `CARGO_TARGET_*` and the like;
+/// - the build options.
+///
+/// Path dependencies inherited from a workspace and cargo configuration
+/// files are not covered; use `BuildOptions::cache(false)` if those change.
+///
+/// # Examples
+/// ```no_run
+/// use hook_inject_build::{BuildOptions, build_cdylib_cached};
+///
+/// // Builds once; later calls return the same artifact without running cargo.
+/// let info = build_cdylib_cached("path/to/agent-crate", &BuildOptions::new()).unwrap();
+/// ```
+pub fn build_cdylib_cached<P: AsRef<Path>>(
+    crate_path: P,
+    options: &BuildOptions,
+) -> Result<CdylibInfo> {
+    let crate_path = crate_path.as_ref();
+    if options.skip_cache {
+        return build_cdylib_with(crate_path, options);
+    }
+    let meta = match read_metadata(crate_path, options.target.as_deref()) {
+        Some(Ok(meta)) => meta,
+        Some(Err(err)) => return Err(err),
+        None => return Err(BuildError::new("missing Cargo.toml")),
+    };
+    let fingerprint = fingerprint(&meta, options)?;
+
+    let built = BUILT.get_or_init(Default::default);
+    let remembered = built.lock().unwrap().get(&fingerprint).cloned();
+    let cached = remembered
+        .filter(|path| path.is_file())
+        .or_else(|| find_artifact(&meta, options).filter(|path| stamp(path) == Some(fingerprint)));
+    if let Some(path) = cached {
+        built.lock().unwrap().insert(fingerprint, path.clone());
+        return Ok(CdylibInfo {
+            path,
+            entrypoint: meta.entrypoint,
+            data: meta.data,
+            sha256: meta.sha256,
+        });
+    }
+
+    let info = build_cdylib_with(crate_path, options)?;
+    // Without a stamp the next run rebuilds, which is only slower.
+    let _ = std::fs::write(stamp_path(&info.path), format!("{fingerprint:016x}"));
+    built.lock().unwrap().insert(fingerprint, info.path.clone());
+    Ok(info)
+}
+
+// Artifacts built by this process, by fingerprint.
+static BUILT: OnceLock<Mutex<HashMap<u64, PathBuf>>> = OnceLock::new();
+
+fn stamp_path(artifact: &Path) -> PathBuf {
+    let mut name = artifact.as_os_str().to_owned();
+    name.push(".");
+    name.push(STAMP_SUFFIX);
+    PathBuf::from(name)
+}
+
+fn stamp(artifact: &Path) -> Option<u64> {
+    let text = std::fs::read_to_string(stamp_path(artifact)).ok()?;
+    u64::from_str_radix(text.trim(), 16).ok()
+}
+
+/// Fingerprint the build of the crate `meta` describes with `options`.
+fn fingerprint(meta: &CrateMetadata, options: &BuildOptions) -> Result<u64> {
+    let mut hasher = Fnv::new();
+    hash_options(options, &mut hasher);
+    hash_toolchain(&mut hasher);
+    if let Some(lock) = meta
+        .crate_dir
+        .ancestors()
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|lock| lock.is_file())
+    {
+        hasher.field(b"lock");
+        hasher.field(&read(&lock)?);
+    }
+
+    let mut skip = vec![meta.target_dir.clone()];
+    skip.extend(options.target_dir.clone());
+    skip.extend(meta.data_file.clone());
+    let mut crates = vec![meta.crate_dir.clone()];
+    let mut seen = Vec::new();
+    while let Some(dir) = crates.pop() {
+        let dir = dir.canonicalize().unwrap_or(dir);
+        if seen.contains(&dir) {
+            continue;
+        }
+        hasher.field(b"crate");
+        hasher.field(dir.as_os_str().as_encoded_bytes());
+        hash_dir(&dir, &dir, &skip, &mut hasher).map_err(|e| {
+            BuildError::new(format!("failed to read {} for caching: {e}", dir.display()))
+        })?;
+        crates.extend(path_dependencies(&dir));
+        seen.push(dir);
+    }
+    Ok(hasher.finish())
+}
+
+fn hash_options(options: &BuildOptions, hasher: &mut Fnv) {
+    let BuildOptions {
+        target,
+        target_dir,
+        release,
+        locked,
+        offline,
+        frozen,
+        skip_relocate,
+        skip_codesign,
+        skip_cache: _,
+    } = options;
+    hasher.field(target.as_deref().unwrap_or("").as_bytes());
+    hasher.field(
+        target_dir
+            .as_deref()
+            .map_or(&[][..], |dir| dir.as_os_str().as_encoded_bytes()),
+    );
+    hasher.field(&[
+        *release as u8,
+        *locked as u8,
+        *offline as u8,
+        *frozen as u8,
+        *skip_relocate as u8,
+        *skip_codesign as u8,
+    ]);
+}
+
+/// Hash the compiler's version and the environment cargo builds with.
+fn hash_toolchain(hasher: &mut Fnv) {
+    // Asked once per process; without a compiler cargo fails anyway.
+    static VERSION: OnceLock<Vec<u8>> = OnceLock::new();
+    let version = VERSION.get_or_init(|| {
+        let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+        Command::new(rustc)
+            .arg("-vV")
+            .output()
+            .map(|output| output.stdout)
+            .unwrap_or_default()
+    });
+    hasher.field(version);
+
+    let mut vars: Vec<_> = std::env::vars_os()
+        .filter(|(key, _)| key.to_str().is_some_and(affects_build))
+        .collect();
+    vars.sort();
+    for (key, value) in vars {
+        hasher.field(key.as_encoded_bytes());
+        hasher.field(value.as_encoded_bytes());
+    }
+}
+
+/// Whether cargo reads the environment variable `key` for a build. Ones it
+/// sets for the crate running this, which differ between test binaries, do
+/// not count.
+fn affects_build(key: &str) -> bool {
+    const VARS: &[&str] = &[
+        "RUSTFLAGS",
+        "CARGO_ENCODED_RUSTFLAGS",
+        "CARGO_INCREMENTAL",
+        "CARGO_HOME",
+    ];
+    const PREFIXES: &[&str] = &[
+        "RUSTC",
+        "CARGO_BUILD_",
+        "CARGO_PROFILE_",
+        "CARGO_TARGET_",
+        "CARGO_UNSTABLE_",
+    ];
+    key != "CARGO_TARGET_TMPDIR"
+        && (VARS.contains(&key) || PREFIXES.iter().any(|prefix| key.starts_with(prefix)))
+}
+
+/// The directories of the path dependencies the manifest in `dir` declares.
+fn path_dependencies(dir: &Path) -> Vec<PathBuf> {
+    let Some(manifest) = std::fs::read_to_string(dir.join("Cargo.toml"))
+        .ok()
+        .and_then(|text| text.parse::<toml::Table>().ok())
+    else {
+        return Vec::new();
+    };
+    let mut tables = Vec::new();
+    for key in ["dependencies", "build-dependencies"] {
+        tables.extend(manifest.get(key).and_then(toml::Value::as_table));
+    }
+    if let Some(targets) = manifest.get("target").and_then(toml::Value::as_table) {
+        for target in targets.values() {
+            for key in ["dependencies", "build-dependencies"] {
+                tables.extend(target.get(key).and_then(toml::Value::as_table));
+            }
+        }
+    }
+    tables
+        .into_iter()
+        .flat_map(|table| table.values())
+        .filter_map(|dep| dep.get("path")?.as_str())
+        .map(|path| dir.join(path))
+        .collect()
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path).map_err(|e| {
+        BuildError::new(format!(
+            "failed to read {} for caching: {e}",
+            path.display()
+        ))
+    })
+}
+
+fn hash_dir(root: &Path, dir: &Path, skip: &[PathBuf], hasher: &mut Fnv) -> std::io::Result<()> {
+    let mut entries = std::fs::read_dir(dir)?.collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') || skip.contains(&path) {
+            continue;
+        }
+        let kind = entry.file_type()?;
+        if kind.is_dir() {
+            if name == "target" {
+                continue;
+            }
+            hash_dir(root, &path, skip, hasher)?;
+        } else if kind.is_file() {
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            hasher.field(relative.as_os_str().as_encoded_bytes());
+            hasher.field(&std::fs::read(&path)?);
+        }
+    }
+    Ok(())
+}
+
+/// 64-bit FNV-1a, whose output stays the same across toolchains, unlike
+/// `DefaultHasher`'s, so stamps written by one build are read by the next.
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+
+    /// Hash `bytes` as one field, length first so fields cannot run together.
+    fn field(&mut self, bytes: &[u8]) {
+        for byte in (bytes.len() as u64).to_le_bytes().iter().chain(bytes) {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
@@ -19,6 +19,8 @@ pub fn detect_devkit_platform() -> Result<String> {
         ("linux", "x86_64") => "linux-x86_64",
         ("windows", "aarch64") | ("windows", "arm64") => "windows-arm64",
         ("windows", "x86_64") => "windows-x86_64",
+        ("android", "aarch64") | ("android", "arm64") => "android-arm64",
+        ("android", "x86_64") => "android-x86_64",
         _ => {
             return Err(BuildError::new(format!(
                 "unsupported platform for devkit download: {os}-{arch}"
@@ -1,9 +1,12 @@
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
+mod cache;
 #[cfg(feature = "download-devkit")]
 mod devkit;
+mod relocate;
 
+pub use cache::build_cdylib_cached;
 #[cfg(feature = "download-devkit")]
 pub use devkit::{
     detect_devkit_platform, download_devkit, resolve_devkit_platform, resolve_devkit_versions,
@@ -11,25 +14,82 @@ pub use devkit::{
 
 #[cfg(feature = "build-utils")]
 pub use pkg_config::Library as PkgConfigLibrary;
+pub use relocate::relocate_library;
 
 //=== Error types ===
 
 #[derive(Debug)]
 pub struct BuildError {
     message: String,
+    stdout: Option<String>,
+    stderr: Option<String>,
 }
 
 impl BuildError {
     fn new<M: AsRef<str>>(msg: M) -> Self {
         Self {
             message: msg.as_ref().to_string(),
+            stdout: None,
+            stderr: None,
         }
     }
+
+    fn with_output(mut self, output: &std::process::Output) -> Self {
+        self.stdout = Some(String::from_utf8_lossy(&output.stdout).into_owned());
+        self.stderr = Some(String::from_utf8_lossy(&output.stderr).into_owned());
+        self
+    }
+
+    /// Captured standard output of the failed cargo (or codesign)
+    /// invocation, if any.
+    pub fn stdout(&self) -> Option<&str> {
+        self.stdout.as_deref()
+    }
+
+    /// Captured standard error of the failed cargo (or codesign)
+    /// invocation, if any.
+    pub fn stderr(&self) -> Option<&str> {
+        self.stderr.as_deref()
+    }
+
+    /// Compiler errors from the captured output, one entry per diagnostic.
+    ///
+    /// Each entry spans from its `error` line to the end of its snippet.
+    /// Warnings and progress lines are left out.
+    pub fn diagnostics(&self) -> Vec<&str> {
+        let Some(stderr) = self.stderr.as_deref() else {
+            return Vec::new();
+        };
+
+        let mut diagnostics = Vec::new();
+        let mut start = None;
+        let mut offset = 0;
+        for line in stderr.split_inclusive('\n') {
+            let text = line.trim_end();
+            if start.is_none() && (text.starts_with("error:") || text.starts_with("error[")) {
+                start = Some(offset);
+            } else if let Some(from) = start
+                && text.is_empty()
+            {
+                diagnostics.push(stderr[from..offset].trim_end());
+                start = None;
+            }
+            offset += line.len();
+        }
+        if let Some(from) = start {
+            diagnostics.push(stderr[from..].trim_end());
+        }
+        diagnostics
+    }
 }
 
 impl std::fmt::Display for BuildError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&self.message)
+        f.write_str(&self.message)?;
+        for diagnostic in self.diagnostics() {
+            write!(f, "\n{diagnostic}")?;
+        }
+        Ok(())
     }
 }
 
@@ -51,6 +111,7 @@ pub struct CdylibInfo {
     pub path: PathBuf,
     pub entrypoint: Option<String>,
     pub data: Option<String>,
+    pub sha256: Option<String>,
 }
 
 #[derive(Debug)]
@@ -58,6 +119,10 @@ pub struct CrateMetadata {
     pub package_name: String,
     pub entrypoint: Option<String>,
     pub data: Option<String>,
+    /// `data-file` resolved against the crate directory; its contents are
+    /// in `data`.
+    pub data_file: Option<PathBuf>,
+    pub sha256: Option<String>,
     pub manifest_path: PathBuf,
     pub crate_dir: PathBuf,
     pub target_dir: PathBuf,
@@ -65,28 +130,153 @@ pub struct CrateMetadata {
     pub cdylib_path: Option<PathBuf>,
 }
 
+/// Options for building and locating a cdylib.
+#[derive(Debug, Clone, Default)]
+pub struct BuildOptions {
+    target: Option<String>,
+    target_dir: Option<PathBuf>,
+    release: bool,
+    locked: bool,
+    offline: bool,
+    frozen: bool,
+    skip_relocate: bool,
+    skip_codesign: bool,
+    skip_cache: bool,
+}
+
+impl BuildOptions {
+    /// Create options that build for the host.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build for a target triple, e.g. `"i686-unknown-linux-gnu"`.
+    ///
+    /// Passed to `cargo build --target`; the artifact is then looked up under
+    /// `target/<triple>/`.
+    pub fn target(mut self, triple: impl Into<String>) -> Self {
+        self.target = Some(triple.into());
+        self
+    }
+
+    /// Build into, and look for the artifact in, this directory
+    /// (`cargo build --target-dir`).
+    pub fn target_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.target_dir = Some(dir.into());
+        self
+    }
+
+    /// Build with the release profile (`cargo build --release`).
+    pub fn release(mut self, release: bool) -> Self {
+        self.release = release;
+        self
+    }
+
+    /// Require `Cargo.lock` to be up to date (`cargo build --locked`).
+    pub fn locked(mut self, locked: bool) -> Self {
+        self.locked = locked;
+        self
+    }
+
+    /// Never touch the network (`cargo build --offline`).
+    ///
+    /// The build fails instead of downloading anything missing from the
+    /// local registry cache.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Both `locked` and `offline` (`cargo build --frozen`).
+    pub fn frozen(mut self, frozen: bool) -> Self {
+        self.frozen = frozen;
+        self
+    }
+
+    /// Rewrite build-tree relative load paths after building (default
+    /// true); see `relocate_library`.
+    pub fn relocate(mut self, relocate: bool) -> Self {
+        self.skip_relocate = !relocate;
+        self
+    }
+
+    /// Ad-hoc sign macOS artifacts after building (default true).
+    ///
+    /// Only applies when building on macOS for an `apple-darwin` target; see
+    /// `codesign_adhoc`.
+    pub fn codesign(mut self, codesign: bool) -> Self {
+        self.skip_codesign = !codesign;
+        self
+    }
+
+    /// Reuse an artifact built from the same sources and options (default
+    /// true); see `build_cdylib_cached`.
+    pub fn cache(mut self, cache: bool) -> Self {
+        self.skip_cache = !cache;
+        self
+    }
+}
+
 // TODO: H
 pub fn read_cdylib_file(crate_path: &Path) -> Option<Result<CdylibInfo>> {
-    let meta = match read_crate_metadata(crate_path)? {
+    read_cdylib_file_with(crate_path, &BuildOptions::default())
+}
+
+/// Like `read_cdylib_file`, looking for the artifact `options` would build.
+pub fn read_cdylib_file_with(
+    crate_path: &Path,
+    options: &BuildOptions,
+) -> Option<Result<CdylibInfo>> {
+    let meta = match read_metadata(crate_path, options.target.as_deref())? {
         Ok(meta) => meta,
         Err(err) => return Some(Err(err)),
     };
 
     Some(Ok(CdylibInfo {
-        path: meta.cdylib_path?,
+        path: find_artifact(&meta, options)?,
         entrypoint: meta.entrypoint,
         data: meta.data,
+        sha256: meta.sha256,
     }))
 }
 
 //=== Metadata helpers ===
 
 /// Read cdylib metadata from a crate manifest without building it.
+///
+/// `[package.metadata.hook-inject]` may hold tables named after an OS or
+/// architecture (as in `std::env::consts`), and `<os>.<arch>` tables inside
+/// OS tables, whose `entrypoint`, `data`, `data-file` and `sha256` override
+/// the general ones for that platform, most specific last:
+///
+/// ```toml
+/// [package.metadata.hook-inject]
+/// entrypoint = "agent_main"
+///
+/// [package.metadata.hook-inject.windows.x86]
+/// entrypoint = "_agent_main@12"
+/// ```
+///
+/// This resolves them for the platform this crate is compiled for; see
+/// `read_crate_metadata_for_target` for another one.
 pub fn read_crate_metadata<P: AsRef<Path>>(crate_path: P) -> Option<Result<CrateMetadata>> {
-    let manifest_path = if crate_path.as_ref().is_dir() {
-        crate_path.as_ref().join("Cargo.toml")
+    read_metadata(crate_path.as_ref(), None)
+}
+
+/// Like `read_crate_metadata`, resolving per-platform tables for a target
+/// triple.
+pub fn read_crate_metadata_for_target<P: AsRef<Path>>(
+    crate_path: P,
+    triple: &str,
+) -> Option<Result<CrateMetadata>> {
+    read_metadata(crate_path.as_ref(), Some(triple))
+}
+
+fn read_metadata(crate_path: &Path, triple: Option<&str>) -> Option<Result<CrateMetadata>> {
+    let manifest_path = if crate_path.is_dir() {
+        crate_path.join("Cargo.toml")
     } else {
-        crate_path.as_ref().to_path_buf()
+        crate_path.to_path_buf()
     };
 
     if !manifest_path.is_file() {
@@ -140,27 +330,44 @@ pub fn read_crate_metadata<P: AsRef<Path>>(crate_path: P) -> Option<Result<Crate
     }
 
     let meta = package.get("metadata").and_then(|m| m.get("hook-inject"));
-    let entrypoint = meta
-        .and_then(|m| m.get("entrypoint"))
-        .and_then(|v| v.as_str())
-        .map(|v| v.to_string());
-    let data = meta
-        .and_then(|m| m.get("data"))
-        .and_then(|v| v.as_str())
-        .map(|v| v.to_string());
+    let settings = match meta.map(|meta| AgentSettings::resolve(meta, triple)) {
+        Some(Ok(settings)) => settings,
+        Some(Err(err)) => return Some(Err(err)),
+        None => AgentSettings::default(),
+    };
+    let AgentSettings {
+        entrypoint,
+        mut data,
+        data_file,
+        sha256,
+    } = settings;
 
     let crate_dir = match manifest_path.parent() {
         Some(dir) => dir.to_path_buf(),
         None => return Some(Err(BuildError::new("invalid manifest path"))),
     };
+    let data_file = data_file.map(|file| crate_dir.join(file));
+    if let Some(file) = &data_file {
+        match std::fs::read_to_string(file) {
+            Ok(contents) => data = Some(contents),
+            Err(e) => {
+                return Some(Err(BuildError::new(format!(
+                    "failed to read data-file {}: {e}",
+                    file.display()
+                ))));
+            }
+        }
+    }
     let target_dir = resolve_target_dir(&crate_dir);
     let cdylib_filename = library_filename(package_name);
-    let cdylib_path = find_cdylib_in_targets(&crate_dir, &target_dir, &cdylib_filename);
+    let cdylib_path = find_cdylib_in_targets(&crate_dir, &target_dir, None, &cdylib_filename);
 
     Some(Ok(CrateMetadata {
         package_name: package_name.to_string(),
         entrypoint,
         data,
+        data_file,
+        sha256,
         manifest_path,
         crate_dir,
         target_dir,
@@ -169,6 +376,85 @@ pub fn read_crate_metadata<P: AsRef<Path>>(crate_path: P) -> Option<Result<Crate
     }))
 }
 
+/// `[package.metadata.hook-inject]` keys after per-platform overrides.
+#[derive(Default)]
+struct AgentSettings {
+    entrypoint: Option<String>,
+    data: Option<String>,
+    data_file: Option<String>,
+    sha256: Option<String>,
+}
+
+impl AgentSettings {
+    fn resolve(meta: &toml::Value, triple: Option<&str>) -> Result<Self> {
+        let (os, arch) = match triple {
+            Some(triple) => triple_platform(triple),
+            None => (std::env::consts::OS, std::env::consts::ARCH),
+        };
+        let os_table = meta.get(os);
+        let layers = [
+            (String::new(), Some(meta)),
+            (format!(".{os}"), os_table),
+            (format!(".{arch}"), meta.get(arch)),
+            (format!(".{os}.{arch}"), os_table.and_then(|t| t.get(arch))),
+        ];
+
+        let mut settings = Self::default();
+        for (suffix, table) in layers {
+            let Some(table) = table else {
+                continue;
+            };
+            let value = |key| table.get(key).and_then(|v| v.as_str()).map(str::to_string);
+            if let Some(entrypoint) = value("entrypoint") {
+                settings.entrypoint = Some(entrypoint);
+            }
+            if let Some(sha256) = value("sha256") {
+                settings.sha256 = Some(sha256);
+            }
+            // A more specific table's data replaces the general one, whichever
+            // key either uses.
+            match (value("data"), value("data-file")) {
+                (Some(_), Some(_)) => {
+                    return Err(BuildError::new(format!(
+                        "[package.metadata.hook-inject{suffix}] sets both data and data-file"
+                    )));
+                }
+                (Some(data), None) => (settings.data, settings.data_file) = (Some(data), None),
+                (None, Some(file)) => (settings.data, settings.data_file) = (None, Some(file)),
+                (None, None) => {}
+            }
+        }
+        Ok(settings)
+    }
+}
+
+/// The `std::env::consts` OS and architecture names for a target triple.
+fn triple_platform(triple: &str) -> (&'static str, &str) {
+    let arch = triple.split('-').next().unwrap_or(triple);
+    let arch = match arch {
+        "i386" | "i586" | "i686" => "x86",
+        arch if arch.starts_with("armv") || arch.starts_with("thumbv") => "arm",
+        "arm64" | "arm64e" => "aarch64",
+        arch => arch,
+    };
+    let os = if triple.contains("-windows") {
+        "windows"
+    } else if triple.contains("-darwin") {
+        "macos"
+    } else if triple.contains("-ios") {
+        "ios"
+    } else if triple.contains("-android") {
+        "android"
+    } else if triple.contains("-linux") {
+        "linux"
+    } else if triple.contains("-freebsd") {
+        "freebsd"
+    } else {
+        "unknown"
+    };
+    (os, arch)
+}
+
 /// Build a cdylib and return its resolved path and metadata.
 ///
 /// # Examples
@@ -178,32 +464,189 @@ pub fn read_crate_metadata<P: AsRef<Path>>(crate_path: P) -> Option<Result<Crate
 /// let info = build_cdylib("path/to/agent-crate").unwrap();
 /// ```
 pub fn build_cdylib<P: AsRef<Path>>(crate_path: P) -> Result<CdylibInfo> {
-    let meta = match read_crate_metadata(crate_path) {
+    build_cdylib_with(crate_path, &BuildOptions::default())
+}
+
+/// Build a cdylib with the given options.
+///
+/// # Examples
+/// ```no_run
+/// use hook_inject_build::{BuildOptions, build_cdylib_with};
+///
+/// let options = BuildOptions::new()
+///     .target("i686-unknown-linux-gnu")
+///     .offline(true);
+/// let info = build_cdylib_with("path/to/agent-crate", &options).unwrap();
+/// ```
+pub fn build_cdylib_with<P: AsRef<Path>>(
+    crate_path: P,
+    options: &BuildOptions,
+) -> Result<CdylibInfo> {
+    let meta = match read_metadata(crate_path.as_ref(), options.target.as_deref()) {
         Some(Ok(meta)) => meta,
         Some(Err(err)) => return Err(err),
         None => return Err(BuildError::new("missing Cargo.toml")),
     };
-    let manifest_path = meta.manifest_path.clone();
-    let status = Command::new("cargo")
+    let mut command = Command::new("cargo");
+    command
         .arg("build")
         .arg("--manifest-path")
-        .arg(&manifest_path)
-        .status()
+        .arg(&meta.manifest_path);
+    if let Some(target) = &options.target {
+        command.arg("--target").arg(target);
+    }
+    if let Some(dir) = &options.target_dir {
+        command.arg("--target-dir").arg(dir);
+    }
+    if options.release {
+        command.arg("--release");
+    }
+    if options.locked {
+        command.arg("--locked");
+    }
+    if options.offline {
+        command.arg("--offline");
+    }
+    if options.frozen {
+        command.arg("--frozen");
+    }
+    // Captured so cargo's progress does not interleave with the caller's
+    // output; it comes back on the error if the build fails.
+    let output = command
+        .output()
         .map_err(|e| BuildError::new(format!("failed to invoke cargo: {e}")))?;
-    if !status.success() {
-        return Err(BuildError::new("cargo build failed"));
+    if !output.status.success() {
+        let err = match &options.target {
+            Some(target) => BuildError::new(format!(
+                "cargo build failed for target {target} (is it installed? try `rustup target add {target}`)"
+            )),
+            None => BuildError::new("cargo build failed"),
+        };
+        return Err(err.with_output(&output));
     }
 
-    let path = find_cdylib_in_targets(&meta.crate_dir, &meta.target_dir, &meta.cdylib_filename)
+    let path = find_artifact(&meta, options)
         .ok_or_else(|| BuildError::new("cdylib not found after build"))?;
+    if !options.skip_relocate {
+        relocate_library(&path)?;
+    }
+    // Signed last: relocating invalidates any existing signature.
+    let apple_target = match &options.target {
+        Some(target) => target.contains("apple-darwin"),
+        None => cfg!(target_os = "macos"),
+    };
+    if cfg!(target_os = "macos") && apple_target && !options.skip_codesign {
+        codesign_adhoc(&path)?;
+    }
 
     Ok(CdylibInfo {
         path,
         entrypoint: meta.entrypoint,
         data: meta.data,
+        sha256: meta.sha256,
     })
 }
 
+/// Ad-hoc sign a dylib with `codesign --force --sign -` (macOS only).
+///
+/// Targets running with the hardened runtime refuse to load unsigned
+/// dylibs. `build_cdylib` signs what it builds unless
+/// `BuildOptions::codesign(false)` is set; call this for libraries built
+/// some other way.
+///
+/// # Examples
+/// ```no_run
+/// hook_inject_build::codesign_adhoc("target/debug/libagent.dylib").unwrap();
+/// ```
+pub fn codesign_adhoc<P: AsRef<Path>>(path: P) -> Result<()> {
+    let path = path.as_ref();
+    if !cfg!(target_os = "macos") {
+        return Err(BuildError::new("ad-hoc codesigning requires macOS"));
+    }
+
+    let output = Command::new("codesign")
+        .arg("--force")
+        .arg("--sign")
+        .arg("-")
+        .arg(path)
+        .output()
+        .map_err(|e| BuildError::new(format!("failed to invoke codesign: {e}")))?;
+    if !output.status.success() {
+        return Err(BuildError::new(format!(
+            "codesign failed for {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+        .with_output(&output));
+    }
+    Ok(())
+}
+
+/// Build an agent crate from a build script and stage it for
+/// `hook_inject::embed_agent!`.
+///
+/// The cdylib is built for the crate being compiled (`TARGET`, and the
+/// release profile when `PROFILE` is `release`) in a target directory under
+/// `OUT_DIR`, so it does not contend with the outer build. The artifact and
+/// its metadata are copied to `OUT_DIR`, keyed by the agent's package name.
+///
+/// # Examples
+/// ```no_run
+/// // build.rs
+/// hook_inject_build::embed_agent("../agent").unwrap();
+/// ```
+pub fn embed_agent<P: AsRef<Path>>(crate_path: P) -> Result<()> {
+    embed_agent_with(crate_path, &BuildOptions::default())
+}
+
+/// Like `embed_agent`, with explicit build options.
+///
+/// A target triple, target directory or release profile set in `options`
+/// takes precedence over the one derived from the build script environment.
+pub fn embed_agent_with<P: AsRef<Path>>(crate_path: P, options: &BuildOptions) -> Result<()> {
+    let out_dir = std::env::var_os("OUT_DIR")
+        .map(PathBuf::from)
+        .ok_or_else(|| BuildError::new("embed_agent must be called from a build script"))?;
+    let mut options = options.clone();
+    if options.target.is_none() {
+        options.target = std::env::var("TARGET").ok();
+    }
+    if options.target_dir.is_none() {
+        options.target_dir = Some(out_dir.join("hook-inject-agents"));
+    }
+    if std::env::var("PROFILE").as_deref() == Ok("release") {
+        options.release = true;
+    }
+
+    let meta = match read_metadata(crate_path.as_ref(), options.target.as_deref()) {
+        Some(Ok(meta)) => meta,
+        Some(Err(err)) => return Err(err),
+        None => return Err(BuildError::new("missing Cargo.toml")),
+    };
+
+    let info = build_cdylib_with(&meta.manifest_path, &options)?;
+    let stem = out_dir.join(format!("hook-inject-agent-{}", meta.package_name));
+    let write_err = |e: std::io::Error| BuildError::new(format!("failed to stage agent: {e}"));
+    std::fs::copy(&info.path, stem.with_extension("bin")).map_err(write_err)?;
+    std::fs::write(
+        stem.with_extension("entrypoint"),
+        info.entrypoint.unwrap_or_default(),
+    )
+    .map_err(write_err)?;
+    std::fs::write(stem.with_extension("data"), info.data.unwrap_or_default())
+        .map_err(write_err)?;
+
+    println!("cargo:rerun-if-changed={}", meta.manifest_path.display());
+    println!(
+        "cargo:rerun-if-changed={}",
+        meta.crate_dir.join("src").display()
+    );
+    if let Some(file) = &meta.data_file {
+        println!("cargo:rerun-if-changed={}", file.display());
+    }
+    Ok(())
+}
+
 /// Build the platform-specific filename for a cdylib crate.
 pub fn library_filename(crate_name: &str) -> String {
     let name = crate_name.replace('-', "_");
@@ -217,19 +660,69 @@ pub fn library_filename(crate_name: &str) -> String {
     }
 }
 
+/// Build the filename of a cdylib crate compiled for a target triple.
+pub fn library_filename_for_target(crate_name: &str, triple: &str) -> String {
+    let name = crate_name.replace('-', "_");
+
+    if triple.contains("-windows") {
+        format!("{name}.dll")
+    } else if triple.contains("-apple-") {
+        format!("lib{name}.dylib")
+    } else {
+        format!("lib{name}.so")
+    }
+}
+
+fn find_artifact(meta: &CrateMetadata, options: &BuildOptions) -> Option<PathBuf> {
+    let filename = match &options.target {
+        Some(target) => library_filename_for_target(&meta.package_name, target),
+        None => meta.cdylib_filename.clone(),
+    };
+
+    if let Some(dir) = &options.target_dir {
+        let root = match &options.target {
+            Some(target) => dir.join(target),
+            None => dir.clone(),
+        };
+        return ["release", "debug"]
+            .iter()
+            .map(|profile| root.join(profile).join(&filename))
+            .find(|candidate| candidate.is_file());
+    }
+
+    match &options.target {
+        Some(target) => find_cdylib_in_targets(
+            &meta.crate_dir,
+            &meta.target_dir.join(target),
+            Some(target),
+            &filename,
+        ),
+        None => meta.cdylib_path.clone(),
+    }
+}
+
 fn resolve_target_dir(crate_dir: &Path) -> PathBuf {
     std::env::var_os("CARGO_TARGET_DIR")
         .map(PathBuf::from)
         .unwrap_or_else(|| crate_dir.join("target"))
 }
 
-fn find_cdylib_in_targets(crate_dir: &Path, target_dir: &Path, filename: &str) -> Option<PathBuf> {
+fn find_cdylib_in_targets(
+    crate_dir: &Path,
+    target_dir: &Path,
+    triple: Option<&str>,
+    filename: &str,
+) -> Option<PathBuf> {
     let mut candidates = Vec::new();
     candidates.push(target_dir.to_path_buf());
 
     let mut cur = crate_dir;
     for _ in 0..4 {
-        candidates.push(cur.join("target"));
+        // Cross builds land in `target/<triple>/`.
+        candidates.push(match triple {
+            Some(triple) => cur.join("target").join(triple),
+            None => cur.join("target"),
+        });
         if let Some(parent) = cur.parent() {
             cur = parent;
         } else {
@@ -0,0 +1,243 @@
+//! Post-build rewriting of the load paths baked into an agent.
+//!
+//! A library loaded into an arbitrary process cannot rely on that process's
+//! working directory, so paths relative to the build tree are replaced with
+//! ones relative to the agent itself (`$ORIGIN` / `@loader_path`).
+
+use std::path::Path;
+use std::process::Command;
+
+use object::read::elf::{Dyn, ElfFile, FileHeader};
+use object::read::macho::{
+    FatArch, LoadCommandVariant, MachHeader, MachOFatFile32, MachOFatFile64, MachOFile,
+};
+use object::{Endianness, FileKind, elf};
+
+use crate::{BuildError, Result};
+
+/// Rewrite load paths in a built agent so it loads from any location.
+///
+/// - ELF: relative `RPATH`/`RUNPATH` entries are dropped, `$ORIGIN` is added,
+///   and dependencies recorded by relative path are reduced to their file
+///   name. Requires `patchelf`.
+/// - Mach-O: relative install names of the library and its dependencies
+///   become `@rpath/<name>`, and `@loader_path` is added as an rpath.
+///   Requires `install_name_tool`.
+///
+/// Adding `$ORIGIN`/`@loader_path` alone is best-effort and skipped when the
+/// tool is not installed; fixing a relative path is not, and fails instead.
+/// Other formats are left untouched. Returns whether the file changed;
+/// signed Mach-O files must be re-signed afterwards (see `codesign_adhoc`).
+///
+/// # Examples
+/// ```no_run
+/// hook_inject_build::relocate_library("target/debug/libagent.so").unwrap();
+/// ```
+pub fn relocate_library<P: AsRef<Path>>(path: P) -> Result<bool> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path)
+        .map_err(|e| BuildError::new(format!("failed to read {}: {e}", path.display())))?;
+    let paths = LoadPaths::read(&bytes)
+        .map_err(|e| BuildError::new(format!("failed to parse {}: {e}", path.display())))?;
+
+    let (tool, args, required) = match paths.format {
+        Some(Format::Elf) => match elf_fixes(&paths) {
+            Some(fix) => ("patchelf", fix.0, fix.1),
+            None => return Ok(false),
+        },
+        Some(Format::MachO) => match macho_fixes(&paths) {
+            Some(fix) => ("install_name_tool", fix.0, fix.1),
+            None => return Ok(false),
+        },
+        None => return Ok(false),
+    };
+
+    let output = match Command::new(tool).args(&args).arg(path).output() {
+        Ok(output) => output,
+        Err(_) if !required => return Ok(false),
+        Err(e) => {
+            return Err(BuildError::new(format!(
+                "{} has build-tree relative load paths and {tool} could not be run to fix them: {e}",
+                path.display()
+            )));
+        }
+    };
+    if !output.status.success() {
+        return Err(BuildError::new(format!(
+            "{tool} failed for {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+        .with_output(&output));
+    }
+    Ok(true)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Elf,
+    MachO,
+}
+
+/// Load paths recorded in an image.
+#[derive(Default)]
+struct LoadPaths {
+    format: Option<Format>,
+    // Mach-O `LC_ID_DYLIB`.
+    install_name: Option<String>,
+    needed: Vec<String>,
+    search_paths: Vec<String>,
+}
+
+impl LoadPaths {
+    fn read(bytes: &[u8]) -> object::Result<Self> {
+        let mut paths = LoadPaths::default();
+        match FileKind::parse(bytes)? {
+            FileKind::Elf32 => {
+                paths.read_elf(&ElfFile::<elf::FileHeader32<Endianness>>::parse(bytes)?)?
+            }
+            FileKind::Elf64 => {
+                paths.read_elf(&ElfFile::<elf::FileHeader64<Endianness>>::parse(bytes)?)?
+            }
+            FileKind::MachO32 => paths
+                .read_macho(&MachOFile::<object::macho::MachHeader32<Endianness>>::parse(bytes)?)?,
+            FileKind::MachO64 => paths
+                .read_macho(&MachOFile::<object::macho::MachHeader64<Endianness>>::parse(bytes)?)?,
+            // `install_name_tool` rewrites every slice; the first one
+            // stands for the rest.
+            FileKind::MachOFat32 => {
+                let fat = MachOFatFile32::parse(bytes)?;
+                if let Some(arch) = fat.arches().first() {
+                    return Self::read(arch.data(bytes)?);
+                }
+            }
+            FileKind::MachOFat64 => {
+                let fat = MachOFatFile64::parse(bytes)?;
+                if let Some(arch) = fat.arches().first() {
+                    return Self::read(arch.data(bytes)?);
+                }
+            }
+            _ => {}
+        }
+        Ok(paths)
+    }
+
+    fn read_elf<Elf: FileHeader>(&mut self, elf: &ElfFile<'_, Elf>) -> object::Result<()> {
+        self.format = Some(Format::Elf);
+        let endian = elf.endian();
+        let sections = elf.elf_section_table();
+        let Some((dynamic, link)) = sections.dynamic(endian, elf.data())? else {
+            return Ok(());
+        };
+        let strings = sections.strings(endian, elf.data(), link)?;
+        for entry in dynamic {
+            let value = || entry.string(endian, strings).map(String::from_utf8_lossy);
+            match entry.tag32(endian) {
+                Some(elf::DT_NEEDED) => self.needed.push(value()?.into_owned()),
+                Some(elf::DT_RPATH | elf::DT_RUNPATH) => self.search_paths.extend(
+                    value()?
+                        .split(':')
+                        .filter(|path| !path.is_empty())
+                        .map(str::to_string),
+                ),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn read_macho<Mach: MachHeader>(&mut self, macho: &MachOFile<'_, Mach>) -> object::Result<()> {
+        self.format = Some(Format::MachO);
+        let endian = macho.endian();
+        let mut commands = macho.macho_load_commands()?;
+        while let Some(command) = commands.next()? {
+            let value = |name| {
+                command
+                    .string(endian, name)
+                    .map(|name| String::from_utf8_lossy(name).into_owned())
+            };
+            match command.variant()? {
+                LoadCommandVariant::IdDylib(dylib) => {
+                    self.install_name = Some(value(dylib.dylib.name)?)
+                }
+                LoadCommandVariant::Dylib(dylib) => self.needed.push(value(dylib.dylib.name)?),
+                LoadCommandVariant::Rpath(rpath) => self.search_paths.push(value(rpath.path)?),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `patchelf` arguments, and whether they fix something beyond adding
+/// `$ORIGIN`.
+fn elf_fixes(paths: &LoadPaths) -> Option<(Vec<String>, bool)> {
+    let is_relative = |path: &str| !path.starts_with('/') && !path.starts_with("$ORIGIN");
+    let is_origin = |path: &str| path.starts_with("$ORIGIN") || path.starts_with("${ORIGIN}");
+
+    let mut args = Vec::new();
+    let mut required = false;
+    for name in &paths.needed {
+        if name.contains('/') && is_relative(name) {
+            args.extend([
+                "--replace-needed".to_string(),
+                name.clone(),
+                file_name(name),
+            ]);
+            required = true;
+        }
+    }
+
+    let kept: Vec<&str> = paths
+        .search_paths
+        .iter()
+        .map(String::as_str)
+        .filter(|path| is_origin(path) || !is_relative(path))
+        .collect();
+    required |= kept.len() != paths.search_paths.len();
+    if required || !kept.contains(&"$ORIGIN") {
+        let mut rpath = vec!["$ORIGIN"];
+        rpath.extend(kept.into_iter().filter(|path| *path != "$ORIGIN"));
+        args.extend(["--set-rpath".to_string(), rpath.join(":")]);
+    }
+
+    (!args.is_empty()).then_some((args, required))
+}
+
+/// `install_name_tool` arguments, and whether they fix something beyond
+/// adding `@loader_path`.
+fn macho_fixes(paths: &LoadPaths) -> Option<(Vec<String>, bool)> {
+    let is_relative = |path: &str| !path.starts_with('/') && !path.starts_with('@');
+
+    let mut args = Vec::new();
+    if let Some(name) = paths
+        .install_name
+        .as_deref()
+        .filter(|name| is_relative(name))
+    {
+        args.extend(["-id".to_string(), format!("@rpath/{}", file_name(name))]);
+    }
+    for name in paths.needed.iter().filter(|name| is_relative(name)) {
+        args.extend([
+            "-change".to_string(),
+            name.clone(),
+            format!("@rpath/{}", file_name(name)),
+        ]);
+    }
+    let required = !args.is_empty();
+
+    let relative_rpaths = paths.search_paths.iter().filter(|path| is_relative(path));
+    for path in relative_rpaths.clone() {
+        args.extend(["-delete_rpath".to_string(), path.clone()]);
+    }
+    let required = required || relative_rpaths.count() > 0;
+    if !paths.search_paths.iter().any(|path| path == "@loader_path") {
+        args.extend(["-add_rpath".to_string(), "@loader_path".to_string()]);
+    }
+
+    (!args.is_empty()).then_some((args, required))
+}
+
+fn file_name(path: &str) -> String {
+    path.rsplit('/').next().unwrap_or(path).to_string()
+}
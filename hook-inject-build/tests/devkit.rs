@@ -0,0 +1,19 @@
+#![cfg(feature = "download-devkit")]
+
+use hook_inject_build::detect_devkit_platform;
+
+// The only test in this binary: it sets the target cfg variables a build
+// script would see.
+#[test]
+fn android_targets_map_to_android_devkits() {
+    for (arch, platform) in [("aarch64", "android-arm64"), ("x86_64", "android-x86_64")] {
+        unsafe {
+            std::env::set_var("CARGO_CFG_TARGET_OS", "android");
+            std::env::set_var("CARGO_CFG_TARGET_ARCH", arch);
+        }
+        assert_eq!(detect_devkit_platform().unwrap(), platform);
+    }
+
+    unsafe { std::env::set_var("CARGO_CFG_TARGET_ARCH", "arm") };
+    assert!(detect_devkit_platform().is_err());
+}
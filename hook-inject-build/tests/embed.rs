@@ -0,0 +1,31 @@
+use std::path::PathBuf;
+
+use hook_inject_build::{BuildOptions, embed_agent_with};
+
+#[test]
+fn embed_agent_stages_artifact_and_metadata() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("..");
+    let fixture = root.join("fixtures/agent");
+    let out_dir = std::env::temp_dir().join("hook-inject-build-embed");
+    let _ = std::fs::remove_dir_all(&out_dir);
+    std::fs::create_dir_all(&out_dir).expect("create out dir");
+
+    // Only test in this binary, so nothing else observes the environment.
+    unsafe { std::env::set_var("OUT_DIR", &out_dir) };
+    embed_agent_with(&fixture, &BuildOptions::new().offline(true)).expect("embed fixture agent");
+
+    let stem = out_dir.join("hook-inject-agent-hook-inject-fixture-agent");
+    assert!(
+        !std::fs::read(stem.with_extension("bin"))
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(
+        std::fs::read_to_string(stem.with_extension("entrypoint")).unwrap(),
+        "hook_inject_entry"
+    );
+    assert_eq!(
+        std::fs::read_to_string(stem.with_extension("data")).unwrap(),
+        "fixture"
+    );
+}
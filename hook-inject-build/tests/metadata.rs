@@ -26,3 +26,280 @@ fn library_filename_formats() {
         assert_eq!(name, "libfoo_bar.so");
     }
 }
+
+#[test]
+fn library_filename_for_target_formats() {
+    use hook_inject_build::library_filename_for_target;
+
+    assert_eq!(
+        library_filename_for_target("foo-bar", "i686-pc-windows-msvc"),
+        "foo_bar.dll"
+    );
+    assert_eq!(
+        library_filename_for_target("foo-bar", "aarch64-apple-darwin"),
+        "libfoo_bar.dylib"
+    );
+    assert_eq!(
+        library_filename_for_target("foo-bar", "armv7-linux-androideabi"),
+        "libfoo_bar.so"
+    );
+}
+
+#[test]
+fn build_failure_captures_diagnostics() {
+    use hook_inject_build::{BuildOptions, build_cdylib_with};
+
+    let dir = std::env::temp_dir().join("hook-inject-build-broken-agent");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src")).expect("create crate dir");
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        "[package]\nname = \"broken-agent\"\nversion = \"0.0.0\"\nedition = \"2021\"\n\n\
+         [lib]\ncrate-type = [\"cdylib\"]\n\n[workspace]\n",
+    )
+    .expect("write manifest");
+    std::fs::write(
+        dir.join("src/lib.rs"),
+        "pub fn broken() -> u32 { \"nope\" }\n",
+    )
+    .expect("write source");
+
+    let err = build_cdylib_with(&dir, &BuildOptions::new().offline(true)).unwrap_err();
+    let diagnostics = err.diagnostics();
+    assert!(!diagnostics.is_empty());
+    assert!(diagnostics[0].starts_with("error"));
+    assert!(err.stderr().unwrap().contains("mismatched types"));
+    assert!(err.to_string().contains("mismatched types"));
+}
+
+#[test]
+fn reads_sha256_metadata() {
+    let dir = std::env::temp_dir().join("hook-inject-build-pinned-agent");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("create crate dir");
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        "[package]\nname = \"pinned-agent\"\nversion = \"0.0.0\"\n\n\
+         [package.metadata.hook-inject]\nsha256 = \"00ff\"\n\n\
+         [lib]\ncrate-type = [\"cdylib\"]\n",
+    )
+    .expect("write manifest");
+
+    let meta = read_crate_metadata(&dir)
+        .expect("read metadata")
+        .expect("metadata ok");
+    assert_eq!(meta.sha256.as_deref(), Some("00ff"));
+}
+
+#[test]
+fn reads_data_file_relative_to_crate() {
+    let dir = std::env::temp_dir().join("hook-inject-build-data-file-agent");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("config")).expect("create crate dir");
+    let manifest = |extra: &str| {
+        format!(
+            "[package]\nname = \"data-file-agent\"\nversion = \"0.0.0\"\n\n\
+             [package.metadata.hook-inject]\ndata-file = \"config/agent.json\"\n{extra}\n\
+             [lib]\ncrate-type = [\"cdylib\"]\n"
+        )
+    };
+    std::fs::write(dir.join("Cargo.toml"), manifest("")).expect("write manifest");
+
+    let err = read_crate_metadata(&dir)
+        .expect("read metadata")
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("failed to read data-file"),
+        "{err}"
+    );
+
+    std::fs::write(dir.join("config/agent.json"), "{\"port\": 8080}\n").expect("write data");
+    let meta = read_crate_metadata(&dir)
+        .expect("read metadata")
+        .expect("metadata ok");
+    assert_eq!(meta.data.as_deref(), Some("{\"port\": 8080}\n"));
+    assert_eq!(meta.data_file, Some(dir.join("config/agent.json")));
+
+    std::fs::write(dir.join("Cargo.toml"), manifest("data = \"inline\"")).expect("write manifest");
+    let err = read_crate_metadata(&dir)
+        .expect("read metadata")
+        .unwrap_err();
+    assert!(err.to_string().contains("both data and data-file"), "{err}");
+}
+
+#[test]
+fn resolves_per_platform_metadata() {
+    use hook_inject_build::read_crate_metadata_for_target;
+
+    let dir = std::env::temp_dir().join("hook-inject-build-platform-agent");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("create crate dir");
+    std::fs::write(dir.join("windows.json"), "{}").expect("write data");
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        "[package]\nname = \"platform-agent\"\nversion = \"0.0.0\"\n\n\
+         [package.metadata.hook-inject]\nentrypoint = \"agent_main\"\ndata = \"base\"\n\n\
+         [package.metadata.hook-inject.windows]\nentrypoint = \"AgentMain\"\ndata-file = \"windows.json\"\n\n\
+         [package.metadata.hook-inject.windows.x86]\nentrypoint = \"_AgentMain@12\"\n\n\
+         [package.metadata.hook-inject.aarch64]\ndata = \"arm\"\n\n\
+         [lib]\ncrate-type = [\"cdylib\"]\n",
+    )
+    .expect("write manifest");
+
+    let resolve = |triple| {
+        read_crate_metadata_for_target(&dir, triple)
+            .expect("read metadata")
+            .expect("metadata ok")
+    };
+    let linux = resolve("x86_64-unknown-linux-gnu");
+    assert_eq!(linux.entrypoint.as_deref(), Some("agent_main"));
+    assert_eq!(linux.data.as_deref(), Some("base"));
+
+    let windows = resolve("x86_64-pc-windows-msvc");
+    assert_eq!(windows.entrypoint.as_deref(), Some("AgentMain"));
+    assert_eq!(windows.data.as_deref(), Some("{}"));
+
+    let windows_x86 = resolve("i686-pc-windows-msvc");
+    assert_eq!(windows_x86.entrypoint.as_deref(), Some("_AgentMain@12"));
+    assert_eq!(windows_x86.data.as_deref(), Some("{}"));
+
+    let windows_arm = resolve("aarch64-pc-windows-msvc");
+    assert_eq!(windows_arm.data.as_deref(), Some("arm"));
+    assert_eq!(windows_arm.data_file, None);
+}
+
+#[test]
+fn cached_build_skips_unchanged_crate() {
+    use hook_inject_build::{BuildOptions, build_cdylib_cached};
+
+    let dir = std::env::temp_dir().join(format!("hook-inject-build-cached-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src")).expect("create crate dir");
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        "[package]\nname = \"cached-agent\"\nversion = \"0.0.0\"\nedition = \"2021\"\n\n\
+         [lib]\ncrate-type = [\"cdylib\"]\n\n[workspace]\n",
+    )
+    .expect("write manifest");
+    std::fs::write(dir.join("src/lib.rs"), "pub fn answer() -> u32 { 42 }\n")
+        .expect("write source");
+    let options = BuildOptions::new()
+        .offline(true)
+        .target_dir(dir.join("target"));
+
+    let first = build_cdylib_cached(&dir, &options).expect("first build");
+
+    // Hidden files are not fingerprinted, and cargo would choke on this one,
+    // so only a cache hit succeeds.
+    std::fs::create_dir_all(dir.join(".cargo")).unwrap();
+    std::fs::write(dir.join(".cargo/config.toml"), "not [valid toml").unwrap();
+    let second = build_cdylib_cached(&dir, &options).expect("cached build");
+    assert_eq!(second.path, first.path);
+    std::fs::remove_dir_all(dir.join(".cargo")).unwrap();
+
+    // Changing a source invalidates the fingerprint.
+    std::fs::write(
+        dir.join("src/lib.rs"),
+        "pub fn answer() -> u32 { \"nope\" }\n",
+    )
+    .unwrap();
+    let err = build_cdylib_cached(&dir, &options).unwrap_err();
+    assert!(err.to_string().contains("mismatched types"), "{err}");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn cached_build_tracks_path_dependencies() {
+    use hook_inject_build::{BuildOptions, build_cdylib_cached};
+
+    let root = std::env::temp_dir().join(format!("hook-inject-build-deps-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let (agent, dep) = (root.join("agent"), root.join("dep"));
+    std::fs::create_dir_all(agent.join("src")).expect("create crate dir");
+    std::fs::create_dir_all(dep.join("src")).expect("create dependency dir");
+    std::fs::write(
+        agent.join("Cargo.toml"),
+        "[package]\nname = \"deps-agent\"\nversion = \"0.0.0\"\nedition = \"2021\"\n\n\
+         [lib]\ncrate-type = [\"cdylib\"]\n\n[dependencies]\ndep = { path = \"../dep\" }\n\n\
+         [workspace]\n",
+    )
+    .expect("write manifest");
+    std::fs::write(
+        agent.join("src/lib.rs"),
+        "pub fn answer() -> u32 { dep::answer() }\n",
+    )
+    .expect("write source");
+    std::fs::write(
+        dep.join("Cargo.toml"),
+        "[package]\nname = \"dep\"\nversion = \"0.0.0\"\nedition = \"2021\"\n",
+    )
+    .expect("write dependency manifest");
+    std::fs::write(dep.join("src/lib.rs"), "pub fn answer() -> u32 { 42 }\n")
+        .expect("write dependency source");
+    let options = BuildOptions::new()
+        .offline(true)
+        .target_dir(agent.join("target"));
+
+    build_cdylib_cached(&agent, &options).expect("first build");
+
+    // Changing the dependency invalidates the fingerprint.
+    std::fs::write(
+        dep.join("src/lib.rs"),
+        "pub fn answer() -> u32 { \"nope\" }\n",
+    )
+    .unwrap();
+    let err = build_cdylib_cached(&agent, &options).unwrap_err();
+    assert!(err.to_string().contains("mismatched types"), "{err}");
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[cfg(not(target_os = "macos"))]
+#[test]
+fn codesign_requires_macos() {
+    let err = hook_inject_build::codesign_adhoc("libagent.dylib").unwrap_err();
+    assert!(err.to_string().contains("requires macOS"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn relocate_fixes_relative_rpath() {
+    use hook_inject_build::{BuildOptions, build_cdylib_with, relocate_library};
+
+    let dir = std::env::temp_dir().join("hook-inject-build-relative-rpath");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src")).expect("create crate dir");
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        "[package]\nname = \"relative-rpath\"\nversion = \"0.0.0\"\nedition = \"2021\"\n\n\
+         [lib]\ncrate-type = [\"cdylib\"]\n\n[workspace]\n",
+    )
+    .expect("write manifest");
+    std::fs::write(
+        dir.join("build.rs"),
+        "fn main() { println!(\"cargo:rustc-cdylib-link-arg=-Wl,-rpath,target/lib\"); }\n",
+    )
+    .expect("write build script");
+    std::fs::write(dir.join("src/lib.rs"), "").expect("write lib");
+
+    let options = BuildOptions::new()
+        .target_dir(dir.join("target"))
+        .relocate(false);
+    let info = build_cdylib_with(&dir, &options).expect("build");
+
+    let has_patchelf = std::process::Command::new("patchelf")
+        .arg("--version")
+        .output()
+        .is_ok();
+    match relocate_library(&info.path) {
+        Ok(changed) => {
+            assert!(has_patchelf && changed);
+            assert!(!relocate_library(&info.path).expect("relocated again"));
+        }
+        Err(err) => {
+            assert!(!has_patchelf);
+            assert!(err.to_string().contains("relative load paths"), "{err}");
+        }
+    }
+}
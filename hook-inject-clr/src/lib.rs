@@ -0,0 +1,404 @@
+//! Bootstrapper for `hook_inject::ManagedAssembly`.
+//!
+//! The injector loads it into a Windows .NET target after the library
+//! itself, with the assembly to run in the entrypoint data: the status file
+//! to report to, then the assembly path, type name, method name and
+//! argument, one per line (the argument takes the rest).
+//!
+//! A .NET (Core) runtime already in the process is reached through
+//! `hostfxr`, which loads the assembly next to the application's and calls
+//! the method as a `ComponentEntryPoint`. Otherwise the .NET Framework CLR
+//! is used, the one loaded if any, or v4 started for the occasion, and the
+//! method is run in the default app domain with
+//! `ICLRRuntimeHost::ExecuteInDefaultAppDomain`.
+//!
+//! The outcome goes to the status file, `returned: <code>` with the method's
+//! return value or `failed: <reason>`, and the bootstrapper unloads.
+
+#[cfg(windows)]
+use std::ffi::{CStr, c_char, c_void};
+
+/// # Safety
+/// `data` must be null or a valid NUL-terminated C string pointer.
+#[cfg(windows)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hook_inject_entry(
+    data: *const c_char,
+    _stay_resident: *mut i32,
+    _state: *mut c_void,
+) {
+    if data.is_null() {
+        return;
+    }
+    bootstrap::run(unsafe { CStr::from_ptr(data) }.to_bytes());
+}
+
+#[cfg(windows)]
+mod bootstrap {
+    use std::ffi::{CStr, OsStr, c_char, c_void};
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+    use std::ptr;
+
+    type Hresult = i32;
+
+    #[repr(C)]
+    struct Guid(u32, u16, u16, [u8; 8]);
+
+    const CLSID_CLR_META_HOST: Guid = Guid(
+        0x9280188d,
+        0x0e8e,
+        0x4867,
+        [0xb3, 0x0c, 0x7f, 0xa8, 0x38, 0x84, 0xe8, 0xde],
+    );
+    const IID_ICLR_META_HOST: Guid = Guid(
+        0xd332db9e,
+        0xb9b3,
+        0x4125,
+        [0x82, 0x07, 0xa1, 0x48, 0x84, 0xf5, 0x32, 0x16],
+    );
+    const IID_ICLR_RUNTIME_INFO: Guid = Guid(
+        0xbd39d1d2,
+        0xba2f,
+        0x486a,
+        [0x89, 0xb0, 0xb4, 0xb0, 0xcb, 0x46, 0x68, 0x91],
+    );
+    const CLSID_CLR_RUNTIME_HOST: Guid = Guid(
+        0x90f1a06e,
+        0x7712,
+        0x4762,
+        [0x86, 0xb5, 0x7a, 0x5e, 0xba, 0x6b, 0xdb, 0x02],
+    );
+    const IID_ICLR_RUNTIME_HOST: Guid = Guid(
+        0x90f1a06c,
+        0x7712,
+        0x4762,
+        [0x86, 0xb5, 0x7a, 0x5e, 0xba, 0x6b, 0xdb, 0x02],
+    );
+
+    // The last .NET Framework runtime version; 4.x releases all report it.
+    const FRAMEWORK_VERSION: &str = "v4.0.30319";
+    // `hostfxr_delegate_type::hdt_load_assembly_and_get_function_pointer`.
+    const HDT_LOAD_ASSEMBLY_AND_GET_FUNCTION_POINTER: i32 = 5;
+
+    // Vtable slots, counting IUnknown's three.
+    const QUERY_INTERFACE: usize = 0;
+    const RELEASE: usize = 2;
+    const META_HOST_GET_RUNTIME: usize = 3;
+    const META_HOST_ENUMERATE_LOADED_RUNTIMES: usize = 6;
+    const ENUM_UNKNOWN_NEXT: usize = 3;
+    const RUNTIME_INFO_GET_INTERFACE: usize = 9;
+    const RUNTIME_HOST_START: usize = 3;
+    const RUNTIME_HOST_EXECUTE_IN_DEFAULT_APP_DOMAIN: usize = 11;
+
+    type QueryInterface =
+        unsafe extern "system" fn(*mut c_void, *const Guid, *mut *mut c_void) -> Hresult;
+    type Release = unsafe extern "system" fn(*mut c_void) -> u32;
+    type ClrCreateInstance =
+        unsafe extern "system" fn(*const Guid, *const Guid, *mut *mut c_void) -> Hresult;
+    type GetRuntime = unsafe extern "system" fn(
+        *mut c_void,
+        *const u16,
+        *const Guid,
+        *mut *mut c_void,
+    ) -> Hresult;
+    type EnumerateLoadedRuntimes =
+        unsafe extern "system" fn(*mut c_void, *mut c_void, *mut *mut c_void) -> Hresult;
+    type EnumNext =
+        unsafe extern "system" fn(*mut c_void, u32, *mut *mut c_void, *mut u32) -> Hresult;
+    type GetInterface = unsafe extern "system" fn(
+        *mut c_void,
+        *const Guid,
+        *const Guid,
+        *mut *mut c_void,
+    ) -> Hresult;
+    type Start = unsafe extern "system" fn(*mut c_void) -> Hresult;
+    type ExecuteInDefaultAppDomain = unsafe extern "system" fn(
+        *mut c_void,
+        *const u16,
+        *const u16,
+        *const u16,
+        *const u16,
+        *mut u32,
+    ) -> Hresult;
+
+    type InitializeForRuntimeConfig =
+        unsafe extern "C" fn(*const u16, *const c_void, *mut *mut c_void) -> i32;
+    type GetRuntimeDelegate = unsafe extern "C" fn(*mut c_void, i32, *mut *mut c_void) -> i32;
+    type Close = unsafe extern "C" fn(*mut c_void) -> i32;
+    type LoadAssemblyAndGetFunctionPointer = unsafe extern "system" fn(
+        *const u16,
+        *const u16,
+        *const u16,
+        *const u16,
+        *mut c_void,
+        *mut *mut c_void,
+    ) -> i32;
+    type ComponentEntryPoint = unsafe extern "system" fn(*mut c_void, i32) -> i32;
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetModuleHandleW(name: *const u16) -> *mut c_void;
+        fn LoadLibraryW(name: *const u16) -> *mut c_void;
+        fn GetProcAddress(module: *mut c_void, name: *const c_char) -> *mut c_void;
+        fn GetCurrentProcess() -> *mut c_void;
+    }
+
+    struct Request<'a> {
+        assembly: &'a str,
+        type_name: &'a str,
+        method: &'a str,
+        argument: &'a str,
+    }
+
+    pub(crate) fn run(data: &[u8]) {
+        let Ok(data) = std::str::from_utf8(data) else {
+            return;
+        };
+        let mut fields = data.splitn(5, '\n');
+        let Some(status) = fields.next().map(PathBuf::from) else {
+            return;
+        };
+        let outcome = match (fields.next(), fields.next(), fields.next(), fields.next()) {
+            (Some(assembly), Some(type_name), Some(method), Some(argument)) => {
+                let request = Request {
+                    assembly,
+                    type_name,
+                    method,
+                    argument,
+                };
+                if is_loaded("coreclr.dll") {
+                    run_core(&request)
+                } else {
+                    run_framework(&request)
+                }
+            }
+            _ => Err("malformed bootstrap request".to_string()),
+        };
+        let _ = std::fs::write(
+            &status,
+            match outcome {
+                Ok(code) => format!("returned: {code}"),
+                Err(reason) => format!("failed: {reason}"),
+            },
+        );
+    }
+
+    /// Run the method through the .NET (Core) runtime in the process.
+    fn run_core(request: &Request) -> Result<i32, String> {
+        let hostfxr = unsafe { GetModuleHandleW(wide("hostfxr.dll").as_ptr()) };
+        if hostfxr.is_null() {
+            return Err(
+                "the .NET runtime was not started through hostfxr, which is needed to load an \
+                 assembly into it"
+                    .to_string(),
+            );
+        }
+        let initialize: InitializeForRuntimeConfig =
+            unsafe { export(hostfxr, c"hostfxr_initialize_for_runtime_config")? };
+        let get_delegate: GetRuntimeDelegate =
+            unsafe { export(hostfxr, c"hostfxr_get_runtime_delegate")? };
+        let close: Close = unsafe { export(hostfxr, c"hostfxr_close")? };
+
+        // Checked by hostfxr against the runtime already running.
+        let config = Path::new(request.assembly).with_extension("runtimeconfig.json");
+        if !config.is_file() {
+            return Err(format!(
+                "{} is missing; build the assembly with EnableDynamicLoading set",
+                config.display()
+            ));
+        }
+        let mut context = ptr::null_mut();
+        let code =
+            unsafe { initialize(wide(config.as_os_str()).as_ptr(), ptr::null(), &mut context) };
+        if code < 0 || context.is_null() {
+            return Err(format!(
+                "hostfxr_initialize_for_runtime_config failed with {:#010x}",
+                code as u32
+            ));
+        }
+        let mut load = ptr::null_mut();
+        let code = unsafe {
+            get_delegate(
+                context,
+                HDT_LOAD_ASSEMBLY_AND_GET_FUNCTION_POINTER,
+                &mut load,
+            )
+        };
+        unsafe { close(context) };
+        if code < 0 || load.is_null() {
+            return Err(format!(
+                "hostfxr_get_runtime_delegate failed with {:#010x}",
+                code as u32
+            ));
+        }
+        let load: LoadAssemblyAndGetFunctionPointer = unsafe { std::mem::transmute(load) };
+
+        let mut entry = ptr::null_mut();
+        // A null delegate type asks for a `ComponentEntryPoint`.
+        let code = unsafe {
+            load(
+                wide(request.assembly).as_ptr(),
+                wide(request.type_name).as_ptr(),
+                wide(request.method).as_ptr(),
+                ptr::null(),
+                ptr::null_mut(),
+                &mut entry,
+            )
+        };
+        if code < 0 || entry.is_null() {
+            return Err(format!(
+                "loading {}.{} failed with {:#010x}",
+                request.type_name, request.method, code as u32
+            ));
+        }
+        let entry: ComponentEntryPoint = unsafe { std::mem::transmute(entry) };
+        let mut argument: Vec<u16> = request.argument.encode_utf16().collect();
+        let size = (argument.len() * 2) as i32;
+        Ok(unsafe { entry(argument.as_mut_ptr().cast(), size) })
+    }
+
+    /// Run the method through the .NET Framework CLR, starting it if needed.
+    fn run_framework(request: &Request) -> Result<i32, String> {
+        let mscoree = unsafe { LoadLibraryW(wide("mscoree.dll").as_ptr()) };
+        if mscoree.is_null() {
+            return Err("neither .NET nor the .NET Framework is available".to_string());
+        }
+        let create: ClrCreateInstance = unsafe { export(mscoree, c"CLRCreateInstance")? };
+        let mut meta_host = ptr::null_mut();
+        check(
+            unsafe { create(&CLSID_CLR_META_HOST, &IID_ICLR_META_HOST, &mut meta_host) },
+            "CLRCreateInstance",
+        )?;
+        let meta_host = Com(meta_host);
+        let runtime = match loaded_runtime(&meta_host)? {
+            Some(runtime) => runtime,
+            None => {
+                let get_runtime: GetRuntime = unsafe { meta_host.method(META_HOST_GET_RUNTIME) };
+                let mut runtime = ptr::null_mut();
+                check(
+                    unsafe {
+                        get_runtime(
+                            meta_host.0,
+                            wide(FRAMEWORK_VERSION).as_ptr(),
+                            &IID_ICLR_RUNTIME_INFO,
+                            &mut runtime,
+                        )
+                    },
+                    "ICLRMetaHost::GetRuntime",
+                )?;
+                Com(runtime)
+            }
+        };
+
+        let get_interface: GetInterface = unsafe { runtime.method(RUNTIME_INFO_GET_INTERFACE) };
+        let mut host = ptr::null_mut();
+        check(
+            unsafe {
+                get_interface(
+                    runtime.0,
+                    &CLSID_CLR_RUNTIME_HOST,
+                    &IID_ICLR_RUNTIME_HOST,
+                    &mut host,
+                )
+            },
+            "ICLRRuntimeInfo::GetInterface",
+        )?;
+        let host = Com(host);
+        // `S_FALSE` if the runtime was already started.
+        let start: Start = unsafe { host.method(RUNTIME_HOST_START) };
+        check(unsafe { start(host.0) }, "ICLRRuntimeHost::Start")?;
+
+        let execute: ExecuteInDefaultAppDomain =
+            unsafe { host.method(RUNTIME_HOST_EXECUTE_IN_DEFAULT_APP_DOMAIN) };
+        let mut returned = 0u32;
+        check(
+            unsafe {
+                execute(
+                    host.0,
+                    wide(request.assembly).as_ptr(),
+                    wide(request.type_name).as_ptr(),
+                    wide(request.method).as_ptr(),
+                    wide(request.argument).as_ptr(),
+                    &mut returned,
+                )
+            },
+            "ICLRRuntimeHost::ExecuteInDefaultAppDomain",
+        )?;
+        Ok(returned as i32)
+    }
+
+    /// The first .NET Framework runtime loaded in this process, if any.
+    fn loaded_runtime(meta_host: &Com) -> Result<Option<Com>, String> {
+        let enumerate: EnumerateLoadedRuntimes =
+            unsafe { meta_host.method(META_HOST_ENUMERATE_LOADED_RUNTIMES) };
+        let mut runtimes = ptr::null_mut();
+        check(
+            unsafe { enumerate(meta_host.0, GetCurrentProcess(), &mut runtimes) },
+            "ICLRMetaHost::EnumerateLoadedRuntimes",
+        )?;
+        let runtimes = Com(runtimes);
+        let next: EnumNext = unsafe { runtimes.method(ENUM_UNKNOWN_NEXT) };
+        let mut unknown = ptr::null_mut();
+        let mut fetched = 0;
+        if unsafe { next(runtimes.0, 1, &mut unknown, &mut fetched) } != 0 || fetched != 1 {
+            return Ok(None);
+        }
+        let unknown = Com(unknown);
+        let query: QueryInterface = unsafe { unknown.method(QUERY_INTERFACE) };
+        let mut runtime = ptr::null_mut();
+        check(
+            unsafe { query(unknown.0, &IID_ICLR_RUNTIME_INFO, &mut runtime) },
+            "IUnknown::QueryInterface",
+        )?;
+        Ok(Some(Com(runtime)))
+    }
+
+    /// An owned COM interface pointer.
+    struct Com(*mut c_void);
+
+    impl Com {
+        /// The function in vtable slot `index`.
+        ///
+        /// # Safety
+        /// `F` must be the slot's function pointer type.
+        unsafe fn method<F: Copy>(&self, index: usize) -> F {
+            unsafe {
+                let vtable = *(self.0 as *const *const *const c_void);
+                std::mem::transmute_copy(&*vtable.add(index))
+            }
+        }
+    }
+
+    impl Drop for Com {
+        fn drop(&mut self) {
+            let release: Release = unsafe { self.method(RELEASE) };
+            unsafe { release(self.0) };
+        }
+    }
+
+    /// # Safety
+    /// `F` must be the export's function pointer type.
+    unsafe fn export<F: Copy>(module: *mut c_void, name: &CStr) -> Result<F, String> {
+        let address = unsafe { GetProcAddress(module, name.as_ptr()) };
+        if address.is_null() {
+            return Err(format!("{} is not exported", name.to_string_lossy()));
+        }
+        Ok(unsafe { std::mem::transmute_copy(&address) })
+    }
+
+    fn is_loaded(module: &str) -> bool {
+        !unsafe { GetModuleHandleW(wide(module).as_ptr()) }.is_null()
+    }
+
+    fn check(code: Hresult, what: &str) -> Result<(), String> {
+        if code < 0 {
+            return Err(format!("{what} failed with {:#010x}", code as u32));
+        }
+        Ok(())
+    }
+
+    fn wide(text: impl AsRef<OsStr>) -> Vec<u16> {
+        text.as_ref().encode_wide().chain([0]).collect()
+    }
+}
@@ -0,0 +1,131 @@
+//! Loader for `hook_inject::Backend::Preload`.
+//!
+//! The injector launches the program with this library first in
+//! `LD_PRELOAD` (`DYLD_INSERT_LIBRARIES` on macOS) and the agent to load in
+//! `HOOK_INJECT_PRELOAD_*` variables.
+//! Its constructor runs before the program's `main`: it takes itself out of
+//! the environment, so the program's own children are left alone, loads the
+//! agent and calls its entrypoint the way the other backends do, then
+//! unloads it unless the agent asked to stay resident.
+//!
+//! Progress goes to the status file the injector watches: `loaded` once the
+//! entrypoint is found, `unloaded` or `resident` once it returns, or
+//! `failed: <reason>`.
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+mod loader {
+    use std::ffi::{CStr, CString, OsString, c_char, c_int, c_void};
+    use std::os::unix::ffi::OsStringExt;
+    use std::path::PathBuf;
+
+    const LIBRARY: &str = "HOOK_INJECT_PRELOAD_LIBRARY";
+    const ENTRYPOINT: &str = "HOOK_INJECT_PRELOAD_ENTRYPOINT";
+    const DATA: &str = "HOOK_INJECT_PRELOAD_DATA";
+    const STATUS: &str = "HOOK_INJECT_PRELOAD_STATUS";
+    #[cfg(target_os = "linux")]
+    const PRELOAD: &str = "LD_PRELOAD";
+    #[cfg(target_os = "macos")]
+    const PRELOAD: &str = "DYLD_INSERT_LIBRARIES";
+
+    const RTLD_NOW: c_int = 2;
+
+    type Entrypoint = unsafe extern "C" fn(*const c_char, *mut i32, *mut c_void);
+
+    // In libc itself since glibc 2.34; libdl still provides them.
+    #[cfg_attr(target_os = "linux", link(name = "dl"))]
+    unsafe extern "C" {
+        fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
+        fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+        fn dlclose(handle: *mut c_void) -> c_int;
+        fn dlerror() -> *mut c_char;
+    }
+
+    #[used]
+    #[cfg_attr(target_os = "linux", unsafe(link_section = ".init_array"))]
+    #[cfg_attr(target_os = "macos", unsafe(link_section = "__DATA,__mod_init_func"))]
+    static INIT: extern "C" fn() = init;
+
+    extern "C" fn init() {
+        // Preloaded by something other than the injector.
+        let Some(status) = std::env::var_os(STATUS).map(PathBuf::from) else {
+            return;
+        };
+        let report = |text: &str| {
+            let _ = std::fs::write(&status, text);
+        };
+        // SAFETY: constructors run before `main`, on the only thread.
+        let (library, entrypoint, data) = unsafe { take_environment() };
+        match load(library, entrypoint, data, || report("loaded")) {
+            Ok(Outcome::Unloaded) => report("unloaded"),
+            Ok(Outcome::Resident) => report("resident"),
+            Err(reason) => report(&format!("failed: {reason}")),
+        }
+    }
+
+    enum Outcome {
+        Unloaded,
+        Resident,
+    }
+
+    fn load(
+        library: CString,
+        entrypoint: CString,
+        data: CString,
+        loaded: impl FnOnce(),
+    ) -> Result<Outcome, String> {
+        let handle = unsafe { dlopen(library.as_ptr(), RTLD_NOW) };
+        if handle.is_null() {
+            return Err(last_error());
+        }
+        let entry = unsafe { dlsym(handle, entrypoint.as_ptr()) };
+        if entry.is_null() {
+            let reason = last_error();
+            unsafe { dlclose(handle) };
+            return Err(reason);
+        }
+        loaded();
+
+        let entry: Entrypoint = unsafe { std::mem::transmute(entry) };
+        let mut stay_resident = 0;
+        unsafe { entry(data.as_ptr(), &mut stay_resident, std::ptr::null_mut()) };
+        if stay_resident != 0 {
+            return Ok(Outcome::Resident);
+        }
+        unsafe { dlclose(handle) };
+        Ok(Outcome::Unloaded)
+    }
+
+    /// Take the loader's variables out of the environment, and this library
+    /// out of `PRELOAD`, where the injector put it first.
+    ///
+    /// # Safety
+    /// No other thread may be reading or writing the environment.
+    unsafe fn take_environment() -> (CString, CString, CString) {
+        let take = |name: &str| {
+            let value = std::env::var_os(name).unwrap_or_default();
+            unsafe { std::env::remove_var(name) };
+            CString::new(value.into_vec()).unwrap_or_default()
+        };
+        let strings = (take(LIBRARY), take(ENTRYPOINT), take(DATA));
+        unsafe { std::env::remove_var(STATUS) };
+
+        let preload = std::env::var_os(PRELOAD).unwrap_or_default();
+        match preload.into_vec().splitn(2, |&byte| byte == b':').nth(1) {
+            Some(rest) if !rest.is_empty() => unsafe {
+                std::env::set_var(PRELOAD, OsString::from_vec(rest.to_vec()))
+            },
+            _ => unsafe { std::env::remove_var(PRELOAD) },
+        }
+        strings
+    }
+
+    fn last_error() -> String {
+        let error = unsafe { dlerror() };
+        if error.is_null() {
+            return "unknown loader error".to_string();
+        }
+        unsafe { CStr::from_ptr(error) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
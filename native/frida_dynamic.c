@@ -0,0 +1,88 @@
+// Loader for HOOK_FRIDA_DYNAMIC builds; see frida_dynamic.h.
+#ifdef HOOK_FRIDA_DYNAMIC
+
+#include "frida_shim.h"
+#include <frida-core.h>
+#define HOOK_FRIDA_API_ONLY
+#include "frida_dynamic.h"
+#include <stdio.h>
+
+#ifdef _WIN32
+#include <windows.h>
+#else
+#include <dlfcn.h>
+#endif
+
+HookFridaApi hook_frida_api;
+static int hook_frida_loaded;
+
+static void *
+hook_open(const char * path) {
+#ifdef _WIN32
+  return (void *) LoadLibraryA(path);
+#else
+  return dlopen(path, RTLD_NOW | RTLD_LOCAL);
+#endif
+}
+
+static void *
+hook_lookup(void * library, const char * name) {
+#ifdef _WIN32
+  return (void *) GetProcAddress((HMODULE) library, name);
+#else
+  return dlsym(library, name);
+#endif
+}
+
+static void
+hook_close(void * library) {
+#ifdef _WIN32
+  FreeLibrary((HMODULE) library);
+#else
+  dlclose(library);
+#endif
+}
+
+static void
+hook_open_error(const char * path, char * error_out, size_t error_len) {
+#ifdef _WIN32
+  snprintf(error_out, error_len, "failed to load %s (error %lu)", path,
+      (unsigned long) GetLastError());
+#else
+  // dlerror's message already names the file.
+  const char * message = dlerror();
+  snprintf(error_out, error_len, "failed to load frida-core: %s",
+      message != NULL ? message : path);
+#endif
+}
+
+int
+hook_frida_load(const char * path, char * error_out, size_t error_len) {
+  // Serialized by the Rust side.
+  if (hook_frida_loaded)
+    return 1;
+
+  void * library = hook_open(path);
+  if (library == NULL) {
+    hook_open_error(path, error_out, error_len);
+    return 0;
+  }
+
+  HookFridaApi api;
+#define HOOK_FRIDA_RESOLVE(name) \
+  api.name = (__typeof__(api.name)) hook_lookup(library, #name); \
+  if (api.name == NULL) { \
+    snprintf(error_out, error_len, "%s does not export %s", path, #name); \
+    hook_close(library); \
+    return 0; \
+  }
+  HOOK_FRIDA_SYMBOLS(HOOK_FRIDA_RESOLVE)
+#undef HOOK_FRIDA_RESOLVE
+
+  // Kept loaded for the rest of the process, like a linked devkit.
+  hook_frida_api = api;
+  hook_frida_loaded = 1;
+  return 1;
+}
+
+#endif
@@ -0,0 +1,276 @@
+#ifndef HOOK_INJECT_FRIDA_DYNAMIC_H
+#define HOOK_INJECT_FRIDA_DYNAMIC_H
+
+// Built with HOOK_FRIDA_DYNAMIC: every frida-core and GLib function the shim
+// calls goes through a table resolved by hook_frida_load from a frida-core
+// shared library, so nothing links against the devkit. Include after
+// frida-core.h; the table's types come from its declarations.
+
+#define HOOK_FRIDA_SYMBOLS(X) \
+  X(frida_init) \
+  X(frida_shutdown) \
+  X(frida_deinit) \
+  X(frida_version_string) \
+  X(frida_error_quark) \
+  X(frida_device_manager_new) \
+  X(frida_device_manager_get_device_by_type_sync) \
+  X(frida_device_manager_add_remote_device_sync) \
+  X(frida_device_manager_get_device_by_id_sync) \
+  X(frida_device_manager_enumerate_devices_sync) \
+  X(frida_device_list_size) \
+  X(frida_device_list_get) \
+  X(frida_device_get_id) \
+  X(frida_device_get_name) \
+  X(frida_device_get_dtype) \
+  X(frida_injector_new) \
+  X(frida_injector_new_inprocess) \
+  X(frida_injector_inject_library_file_sync) \
+  X(frida_injector_inject_library_blob_sync) \
+  X(frida_injector_demonitor_sync) \
+  X(frida_device_spawn_sync) \
+  X(frida_device_resume_sync) \
+  X(frida_device_kill_sync) \
+  X(frida_device_input_sync) \
+  X(frida_device_attach_sync) \
+  X(frida_device_get_process_by_pid_sync) \
+  X(frida_device_query_system_parameters_sync) \
+  X(frida_device_inject_library_file_sync) \
+  X(frida_device_inject_library_blob_sync) \
+  X(frida_device_enable_spawn_gating_sync) \
+  X(frida_device_disable_spawn_gating_sync) \
+  X(frida_session_enable_child_gating_sync) \
+  X(frida_session_detach_sync) \
+  X(frida_session_is_detached) \
+  X(frida_spawn_options_new) \
+  X(frida_spawn_get_pid) \
+  X(frida_spawn_get_identifier) \
+  X(frida_child_get_pid) \
+  X(frida_child_get_parent_pid) \
+  X(frida_child_get_origin) \
+  X(frida_child_get_path) \
+  X(g_io_error_quark) \
+  X(g_error_free) \
+  X(g_error_matches) \
+  X(g_malloc0) \
+  X(g_malloc0_n) \
+  X(g_free) \
+  X(g_strdup) \
+  X(g_strcmp0) \
+  X(g_printerr) \
+  X(g_object_ref) \
+  X(g_object_unref) \
+  X(g_object_set) \
+  X(g_signal_connect_data) \
+  X(g_signal_handler_disconnect) \
+  X(g_mutex_init) \
+  X(g_mutex_clear) \
+  X(g_mutex_lock) \
+  X(g_mutex_unlock) \
+  X(g_cond_init) \
+  X(g_cond_clear) \
+  X(g_cond_signal) \
+  X(g_cond_wait_until) \
+  X(g_get_monotonic_time) \
+  X(g_thread_new) \
+  X(g_thread_join) \
+  X(g_async_queue_new) \
+  X(g_async_queue_new_full) \
+  X(g_async_queue_push) \
+  X(g_async_queue_timeout_pop) \
+  X(g_async_queue_unref) \
+  X(g_hash_table_new_full) \
+  X(g_hash_table_insert) \
+  X(g_hash_table_lookup) \
+  X(g_hash_table_remove) \
+  X(g_hash_table_unref) \
+  X(g_variant_is_of_type) \
+  X(g_variant_lookup) \
+  X(g_bytes_new) \
+  X(g_bytes_unref) \
+  X(g_cancellable_new) \
+  X(g_cancellable_cancel) \
+  X(g_cancellable_is_cancelled)
+
+#define HOOK_FRIDA_MEMBER(name) __typeof__(name) * name;
+typedef struct {
+  HOOK_FRIDA_SYMBOLS(HOOK_FRIDA_MEMBER)
+} HookFridaApi;
+#undef HOOK_FRIDA_MEMBER
+
+extern HookFridaApi hook_frida_api;
+
+// The loader fills the table by name, so it keeps the plain declarations.
+#ifndef HOOK_FRIDA_API_ONLY
+
+// Route every call below through the table. GLib defines some of these as
+// macros wrapping the function, so drop those first.
+#undef frida_init
+#define frida_init (hook_frida_api.frida_init)
+#undef frida_shutdown
+#define frida_shutdown (hook_frida_api.frida_shutdown)
+#undef frida_deinit
+#define frida_deinit (hook_frida_api.frida_deinit)
+#undef frida_version_string
+#define frida_version_string (hook_frida_api.frida_version_string)
+#undef frida_error_quark
+#define frida_error_quark (hook_frida_api.frida_error_quark)
+#undef frida_device_manager_new
+#define frida_device_manager_new (hook_frida_api.frida_device_manager_new)
+#undef frida_device_manager_get_device_by_type_sync
+#define frida_device_manager_get_device_by_type_sync (hook_frida_api.frida_device_manager_get_device_by_type_sync)
+#undef frida_device_manager_add_remote_device_sync
+#define frida_device_manager_add_remote_device_sync (hook_frida_api.frida_device_manager_add_remote_device_sync)
+#undef frida_device_manager_get_device_by_id_sync
+#define frida_device_manager_get_device_by_id_sync (hook_frida_api.frida_device_manager_get_device_by_id_sync)
+#undef frida_device_manager_enumerate_devices_sync
+#define frida_device_manager_enumerate_devices_sync (hook_frida_api.frida_device_manager_enumerate_devices_sync)
+#undef frida_device_list_size
+#define frida_device_list_size (hook_frida_api.frida_device_list_size)
+#undef frida_device_list_get
+#define frida_device_list_get (hook_frida_api.frida_device_list_get)
+#undef frida_device_get_id
+#define frida_device_get_id (hook_frida_api.frida_device_get_id)
+#undef frida_device_get_name
+#define frida_device_get_name (hook_frida_api.frida_device_get_name)
+#undef frida_device_get_dtype
+#define frida_device_get_dtype (hook_frida_api.frida_device_get_dtype)
+#undef frida_injector_new
+#define frida_injector_new (hook_frida_api.frida_injector_new)
+#undef frida_injector_new_inprocess
+#define frida_injector_new_inprocess (hook_frida_api.frida_injector_new_inprocess)
+#undef frida_injector_inject_library_file_sync
+#define frida_injector_inject_library_file_sync (hook_frida_api.frida_injector_inject_library_file_sync)
+#undef frida_injector_inject_library_blob_sync
+#define frida_injector_inject_library_blob_sync (hook_frida_api.frida_injector_inject_library_blob_sync)
+#undef frida_injector_demonitor_sync
+#define frida_injector_demonitor_sync (hook_frida_api.frida_injector_demonitor_sync)
+#undef frida_device_spawn_sync
+#define frida_device_spawn_sync (hook_frida_api.frida_device_spawn_sync)
+#undef frida_device_resume_sync
+#define frida_device_resume_sync (hook_frida_api.frida_device_resume_sync)
+#undef frida_device_kill_sync
+#define frida_device_kill_sync (hook_frida_api.frida_device_kill_sync)
+#undef frida_device_input_sync
+#define frida_device_input_sync (hook_frida_api.frida_device_input_sync)
+#undef frida_device_attach_sync
+#define frida_device_attach_sync (hook_frida_api.frida_device_attach_sync)
+#undef frida_device_get_process_by_pid_sync
+#define frida_device_get_process_by_pid_sync (hook_frida_api.frida_device_get_process_by_pid_sync)
+#undef frida_device_query_system_parameters_sync
+#define frida_device_query_system_parameters_sync (hook_frida_api.frida_device_query_system_parameters_sync)
+#undef frida_device_inject_library_file_sync
+#define frida_device_inject_library_file_sync (hook_frida_api.frida_device_inject_library_file_sync)
+#undef frida_device_inject_library_blob_sync
+#define frida_device_inject_library_blob_sync (hook_frida_api.frida_device_inject_library_blob_sync)
+#undef frida_device_enable_spawn_gating_sync
+#define frida_device_enable_spawn_gating_sync (hook_frida_api.frida_device_enable_spawn_gating_sync)
+#undef frida_device_disable_spawn_gating_sync
+#define frida_device_disable_spawn_gating_sync (hook_frida_api.frida_device_disable_spawn_gating_sync)
+#undef frida_session_enable_child_gating_sync
+#define frida_session_enable_child_gating_sync (hook_frida_api.frida_session_enable_child_gating_sync)
+#undef frida_session_detach_sync
+#define frida_session_detach_sync (hook_frida_api.frida_session_detach_sync)
+#undef frida_session_is_detached
+#define frida_session_is_detached (hook_frida_api.frida_session_is_detached)
+#undef frida_spawn_options_new
+#define frida_spawn_options_new (hook_frida_api.frida_spawn_options_new)
+#undef frida_spawn_get_pid
+#define frida_spawn_get_pid (hook_frida_api.frida_spawn_get_pid)
+#undef frida_spawn_get_identifier
+#define frida_spawn_get_identifier (hook_frida_api.frida_spawn_get_identifier)
+#undef frida_child_get_pid
+#define frida_child_get_pid (hook_frida_api.frida_child_get_pid)
+#undef frida_child_get_parent_pid
+#define frida_child_get_parent_pid (hook_frida_api.frida_child_get_parent_pid)
+#undef frida_child_get_origin
+#define frida_child_get_origin (hook_frida_api.frida_child_get_origin)
+#undef frida_child_get_path
+#define frida_child_get_path (hook_frida_api.frida_child_get_path)
+#undef g_io_error_quark
+#define g_io_error_quark (hook_frida_api.g_io_error_quark)
+#undef g_error_free
+#define g_error_free (hook_frida_api.g_error_free)
+#undef g_error_matches
+#define g_error_matches (hook_frida_api.g_error_matches)
+#undef g_malloc0
+#define g_malloc0 (hook_frida_api.g_malloc0)
+#undef g_malloc0_n
+#define g_malloc0_n (hook_frida_api.g_malloc0_n)
+#undef g_free
+#define g_free (hook_frida_api.g_free)
+#undef g_strdup
+#define g_strdup (hook_frida_api.g_strdup)
+#undef g_strcmp0
+#define g_strcmp0 (hook_frida_api.g_strcmp0)
+#undef g_printerr
+#define g_printerr (hook_frida_api.g_printerr)
+#undef g_object_ref
+#define g_object_ref (hook_frida_api.g_object_ref)
+#undef g_object_unref
+#define g_object_unref (hook_frida_api.g_object_unref)
+#undef g_object_set
+#define g_object_set (hook_frida_api.g_object_set)
+#undef g_signal_connect_data
+#define g_signal_connect_data (hook_frida_api.g_signal_connect_data)
+#undef g_signal_handler_disconnect
+#define g_signal_handler_disconnect (hook_frida_api.g_signal_handler_disconnect)
+#undef g_mutex_init
+#define g_mutex_init (hook_frida_api.g_mutex_init)
+#undef g_mutex_clear
+#define g_mutex_clear (hook_frida_api.g_mutex_clear)
+#undef g_mutex_lock
+#define g_mutex_lock (hook_frida_api.g_mutex_lock)
+#undef g_mutex_unlock
+#define g_mutex_unlock (hook_frida_api.g_mutex_unlock)
+#undef g_cond_init
+#define g_cond_init (hook_frida_api.g_cond_init)
+#undef g_cond_clear
+#define g_cond_clear (hook_frida_api.g_cond_clear)
+#undef g_cond_signal
+#define g_cond_signal (hook_frida_api.g_cond_signal)
+#undef g_cond_wait_until
+#define g_cond_wait_until (hook_frida_api.g_cond_wait_until)
+#undef g_get_monotonic_time
+#define g_get_monotonic_time (hook_frida_api.g_get_monotonic_time)
+#undef g_thread_new
+#define g_thread_new (hook_frida_api.g_thread_new)
+#undef g_thread_join
+#define g_thread_join (hook_frida_api.g_thread_join)
+#undef g_async_queue_new
+#define g_async_queue_new (hook_frida_api.g_async_queue_new)
+#undef g_async_queue_new_full
+#define g_async_queue_new_full (hook_frida_api.g_async_queue_new_full)
+#undef g_async_queue_push
+#define g_async_queue_push (hook_frida_api.g_async_queue_push)
+#undef g_async_queue_timeout_pop
+#define g_async_queue_timeout_pop (hook_frida_api.g_async_queue_timeout_pop)
+#undef g_async_queue_unref
+#define g_async_queue_unref (hook_frida_api.g_async_queue_unref)
+#undef g_hash_table_new_full
+#define g_hash_table_new_full (hook_frida_api.g_hash_table_new_full)
+#undef g_hash_table_insert
+#define g_hash_table_insert (hook_frida_api.g_hash_table_insert)
+#undef g_hash_table_lookup
+#define g_hash_table_lookup (hook_frida_api.g_hash_table_lookup)
+#undef g_hash_table_remove
+#define g_hash_table_remove (hook_frida_api.g_hash_table_remove)
+#undef g_hash_table_unref
+#define g_hash_table_unref (hook_frida_api.g_hash_table_unref)
+#undef g_variant_is_of_type
+#define g_variant_is_of_type (hook_frida_api.g_variant_is_of_type)
+#undef g_variant_lookup
+#define g_variant_lookup (hook_frida_api.g_variant_lookup)
+#undef g_bytes_new
+#define g_bytes_new (hook_frida_api.g_bytes_new)
+#undef g_bytes_unref
+#define g_bytes_unref (hook_frida_api.g_bytes_unref)
+#undef g_cancellable_new
+#define g_cancellable_new (hook_frida_api.g_cancellable_new)
+#undef g_cancellable_cancel
+#define g_cancellable_cancel (hook_frida_api.g_cancellable_cancel)
+#undef g_cancellable_is_cancelled
+#define g_cancellable_is_cancelled (hook_frida_api.g_cancellable_is_cancelled)
+
+#endif // HOOK_FRIDA_API_ONLY
+
+#endif
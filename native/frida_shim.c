@@ -1,6 +1,9 @@
 #include "frida_shim.h"
 // frida-core.h is an amalgam that includes GLib/GObject types.
 #include <frida-core.h>
+#ifdef HOOK_FRIDA_DYNAMIC
+#include "frida_dynamic.h"
+#endif
 #include <stdlib.h>
 #include <string.h>
 
@@ -9,8 +12,58 @@ struct HookFridaCtx {
   FridaDeviceManager * manager;
   FridaDevice * device;
   FridaInjector * injector;
+  // Child gating: sessions keyed by gated pid, and children waiting to be
+  // handled by the Rust side.
+  GMutex gating_lock;
+  GHashTable * gating_sessions;
+  GAsyncQueue * children;
+  gulong child_added_handler;
+  // Spawn gating: spawns waiting to be handled by the Rust side.
+  GAsyncQueue * spawns;
+  gulong spawn_added_handler;
+  // Ids from the `uninjected` signal of the injector, or of the device for
+  // remote devices, waiting for the Rust side.
+  GAsyncQueue * uninjected;
+  gpointer uninjected_source;
+  gulong uninjected_handler;
 };
 
+// A gated child announced by Frida's `child-added` signal.
+typedef struct {
+  guint pid;
+  guint parent_pid;
+  FridaChildOrigin origin;
+  gchar * path;
+} HookChildEvent;
+
+static void
+hook_child_event_free(gpointer data) {
+  HookChildEvent * event = data;
+  g_free(event->path);
+  g_free(event);
+}
+
+// A spawn announced by Frida's `spawn-added` signal.
+typedef struct {
+  guint pid;
+  gchar * identifier;
+} HookSpawnEvent;
+
+static void
+hook_spawn_event_free(gpointer data) {
+  HookSpawnEvent * event = data;
+  g_free(event->identifier);
+  g_free(event);
+}
+
+static void
+hook_on_uninjected(gpointer source, guint id, gpointer user_data) {
+  HookFridaCtx * ctx = user_data;
+  (void) source;
+
+  g_async_queue_push(ctx->uninjected, GUINT_TO_POINTER(id));
+}
+
 static gboolean
 hook_debug_enabled(void) {
   return getenv("HOOK_INJECT_DEBUG") != NULL;
@@ -27,6 +80,10 @@ static HookFridaErrorKind hook_error_kind_from_gerror(GError * err) {
   if (err == NULL)
     return HOOK_FRIDA_ERROR_RUNTIME;
 
+  // Only our own deadlines cancel operations, so cancellation means timeout.
+  if (g_error_matches(err, G_IO_ERROR, G_IO_ERROR_CANCELLED) ||
+      g_error_matches(err, FRIDA_ERROR, FRIDA_ERROR_TIMED_OUT))
+    return HOOK_FRIDA_ERROR_TIMED_OUT;
   if (g_error_matches(err, FRIDA_ERROR, FRIDA_ERROR_INVALID_ARGUMENT))
     return HOOK_FRIDA_ERROR_INVALID_ARGUMENT;
   if (g_error_matches(err, FRIDA_ERROR, FRIDA_ERROR_PERMISSION_DENIED))
@@ -61,6 +118,68 @@ static void hook_set_error(GError * err, int32_t * error_kind_out, char ** error
   }
 }
 
+// Cancels a GCancellable once a deadline passes. With a zero timeout no
+// cancellable is created and operations wait indefinitely.
+typedef struct {
+  GCancellable * cancellable;
+  GThread * thread;
+  GMutex mutex;
+  GCond cond;
+  gboolean finished;
+  gint64 deadline;
+} HookDeadline;
+
+static gpointer
+hook_deadline_worker(gpointer user_data) {
+  HookDeadline * deadline = user_data;
+
+  g_mutex_lock(&deadline->mutex);
+  while (!deadline->finished) {
+    if (!g_cond_wait_until(&deadline->cond, &deadline->mutex, deadline->deadline)) {
+      if (!deadline->finished)
+        g_cancellable_cancel(deadline->cancellable);
+      break;
+    }
+  }
+  g_mutex_unlock(&deadline->mutex);
+  return NULL;
+}
+
+static void
+hook_deadline_start(HookDeadline * deadline, uint32_t timeout_ms) {
+  memset(deadline, 0, sizeof(*deadline));
+  if (timeout_ms == 0)
+    return;
+
+  deadline->cancellable = g_cancellable_new();
+  g_mutex_init(&deadline->mutex);
+  g_cond_init(&deadline->cond);
+  deadline->deadline = g_get_monotonic_time() + (gint64) timeout_ms * G_TIME_SPAN_MILLISECOND;
+  deadline->thread = g_thread_new("hook-inject-deadline", hook_deadline_worker, deadline);
+}
+
+static gboolean
+hook_deadline_expired(HookDeadline * deadline) {
+  return deadline->cancellable != NULL && g_cancellable_is_cancelled(deadline->cancellable);
+}
+
+static void
+hook_deadline_finish(HookDeadline * deadline) {
+  if (deadline->cancellable == NULL)
+    return;
+
+  g_mutex_lock(&deadline->mutex);
+  deadline->finished = TRUE;
+  g_cond_signal(&deadline->cond);
+  g_mutex_unlock(&deadline->mutex);
+  g_thread_join(deadline->thread);
+
+  g_mutex_clear(&deadline->mutex);
+  g_cond_clear(&deadline->cond);
+  g_object_unref(deadline->cancellable);
+  deadline->cancellable = NULL;
+}
+
 static gboolean
 hook_should_try_device_fallback(GError * err) {
   if (err == NULL)
@@ -70,15 +189,83 @@ hook_should_try_device_fallback(GError * err) {
       g_error_matches(err, FRIDA_ERROR, FRIDA_ERROR_PERMISSION_DENIED);
 }
 
-HookFridaCtx *
-hook_frida_new(int32_t * error_kind_out, char ** error_out) {
-  // Initialize Frida and create a local-device injector.
+// Initialize Frida and allocate a context without a device or injector.
+static HookFridaCtx *
+hook_ctx_new(void) {
   frida_init();
   hook_debug("hook-frida: frida_init done");
 
   HookFridaCtx * ctx = g_new0(HookFridaCtx, 1);
+  g_mutex_init(&ctx->gating_lock);
+  ctx->gating_sessions = g_hash_table_new_full(NULL, NULL, NULL, g_object_unref);
+  ctx->children = g_async_queue_new_full(hook_child_event_free);
+  ctx->spawns = g_async_queue_new_full(hook_spawn_event_free);
+  ctx->uninjected = g_async_queue_new();
   ctx->manager = frida_device_manager_new();
   hook_debug("hook-frida: device manager created");
+  return ctx;
+}
+
+// Queue the ids `source` reports in its `uninjected` signal.
+static void
+hook_watch_uninjected(HookFridaCtx * ctx, gpointer source) {
+  ctx->uninjected_source = source;
+  ctx->uninjected_handler = g_signal_connect(
+      source, "uninjected", G_CALLBACK(hook_on_uninjected), ctx);
+}
+
+// Inject a library file through the injector, falling back to the device.
+// Remote devices have no local injector and go straight to the device.
+static guint
+hook_inject_file(HookFridaCtx * ctx,
+    guint pid,
+    const char * library_path,
+    const char * entrypoint,
+    const char * data,
+    GCancellable * cancellable,
+    GError ** error) {
+  guint id = 0;
+  if (ctx->injector != NULL) {
+    id = frida_injector_inject_library_file_sync(
+        ctx->injector, pid, library_path, entrypoint, data, cancellable, error);
+    if (*error == NULL || !hook_should_try_device_fallback(*error) || ctx->device == NULL)
+      return id;
+    hook_debug("hook-frida: injector failed, trying device fallback");
+    g_error_free(*error);
+    *error = NULL;
+  }
+
+  return frida_device_inject_library_file_sync(
+      ctx->device, pid, library_path, entrypoint, data, cancellable, error);
+}
+
+// Like hook_inject_file, for an in-memory library.
+static guint
+hook_inject_bytes(HookFridaCtx * ctx,
+    guint pid,
+    GBytes * bytes,
+    const char * entrypoint,
+    const char * data,
+    GCancellable * cancellable,
+    GError ** error) {
+  guint id = 0;
+  if (ctx->injector != NULL) {
+    id = frida_injector_inject_library_blob_sync(
+        ctx->injector, pid, bytes, entrypoint, data, cancellable, error);
+    if (*error == NULL || !hook_should_try_device_fallback(*error) || ctx->device == NULL)
+      return id;
+    g_error_free(*error);
+    *error = NULL;
+  }
+
+  return frida_device_inject_library_blob_sync(
+      ctx->device, pid, bytes, entrypoint, data, cancellable, error);
+}
+
+HookFridaCtx *
+hook_frida_new(int32_t * error_kind_out, char ** error_out) {
+  // Create a local-device injector.
+  HookFridaCtx * ctx = hook_ctx_new();
   // Prefer the helper injector for broader macOS compatibility.
   const char * mode = getenv("HOOK_INJECT_INJECTOR");
   if (mode != NULL && g_strcmp0(mode, "inprocess") == 0) {
@@ -87,6 +274,8 @@ hook_frida_new(int32_t * error_kind_out, char ** error_out) {
     ctx->injector = frida_injector_new();
   }
   hook_debug("hook-frida: injector created");
+  // Connected up front so no unload is missed before anyone asks.
+  hook_watch_uninjected(ctx, ctx->injector);
 
   GError * error = NULL;
   ctx->device = frida_device_manager_get_device_by_type_sync(
@@ -108,12 +297,91 @@ hook_frida_new(int32_t * error_kind_out, char ** error_out) {
   return ctx;
 }
 
+HookFridaCtx *
+hook_frida_new_remote(const char * address,
+    uint32_t timeout_ms,
+    int32_t * error_kind_out,
+    char ** error_out) {
+  HookFridaCtx * ctx = hook_ctx_new();
+
+  HookDeadline deadline;
+  hook_deadline_start(&deadline, timeout_ms);
+
+  GError * error = NULL;
+  ctx->device = frida_device_manager_add_remote_device_sync(
+      ctx->manager,
+      address,
+      NULL,
+      deadline.cancellable,
+      &error);
+  hook_deadline_finish(&deadline);
+  hook_debug("hook-frida: remote device lookup finished");
+  if (error != NULL) {
+    hook_set_error(error, error_kind_out, error_out);
+    g_error_free(error);
+    hook_frida_free(ctx);
+    return NULL;
+  }
+
+  // frida-server monitors what it injects and reports unloads on the device.
+  hook_watch_uninjected(ctx, ctx->device);
+
+  if (error_kind_out != NULL)
+    *error_kind_out = HOOK_FRIDA_ERROR_NONE;
+  return ctx;
+}
+
+HookFridaCtx *
+hook_frida_new_device(const char * id,
+    uint32_t timeout_ms,
+    int32_t * error_kind_out,
+    char ** error_out) {
+  HookFridaCtx * ctx = hook_ctx_new();
+  // Frida waits for the device itself; a negative timeout never gives up.
+  gint timeout = timeout_ms == 0 ? -1 : (gint) MIN(timeout_ms, G_MAXINT);
+
+  GError * error = NULL;
+  if (id != NULL) {
+    ctx->device = frida_device_manager_get_device_by_id_sync(
+        ctx->manager, id, timeout, NULL, &error);
+  } else {
+    ctx->device = frida_device_manager_get_device_by_type_sync(
+        ctx->manager, FRIDA_DEVICE_TYPE_USB, timeout, NULL, &error);
+  }
+  hook_debug("hook-frida: device lookup finished");
+  if (error != NULL) {
+    hook_set_error(error, error_kind_out, error_out);
+    g_error_free(error);
+    hook_frida_free(ctx);
+    return NULL;
+  }
+
+  hook_watch_uninjected(ctx, ctx->device);
+
+  if (error_kind_out != NULL)
+    *error_kind_out = HOOK_FRIDA_ERROR_NONE;
+  return ctx;
+}
+
 void
 hook_frida_free(HookFridaCtx * ctx) {
-  // Release Frida objects and shut down the library.
+  // Release Frida objects.
   if (ctx == NULL)
     return;
 
+  if (ctx->device != NULL && ctx->child_added_handler != 0)
+    g_signal_handler_disconnect(ctx->device, ctx->child_added_handler);
+  g_hash_table_unref(ctx->gating_sessions);
+  if (ctx->device != NULL && ctx->spawn_added_handler != 0)
+    g_signal_handler_disconnect(ctx->device, ctx->spawn_added_handler);
+  g_async_queue_unref(ctx->children);
+  g_async_queue_unref(ctx->spawns);
+  if (ctx->uninjected_handler != 0)
+    g_signal_handler_disconnect(ctx->uninjected_source, ctx->uninjected_handler);
+  if (ctx->uninjected != NULL)
+    g_async_queue_unref(ctx->uninjected);
+  g_mutex_clear(&ctx->gating_lock);
+
   if (ctx->device != NULL)
     g_object_unref(ctx->device);
   if (ctx->manager != NULL)
@@ -122,7 +390,19 @@ hook_frida_free(HookFridaCtx * ctx) {
     g_object_unref(ctx->injector);
 
   g_free(ctx);
+  // Frida itself stays initialized: frida_init only runs once per process,
+  // so shutting it down here would leave a retried hook_frida_new broken.
+}
+
+const char *
+hook_frida_version(void) {
+  return frida_version_string();
+}
+
+void
+hook_frida_shutdown(void) {
   frida_shutdown();
+  frida_deinit();
 }
 
 int
@@ -131,37 +411,22 @@ hook_frida_inject_process(HookFridaCtx * ctx,
     const char * library_path,
     const char * entrypoint,
     const char * data,
+    uint32_t timeout_ms,
     uint32_t * out_id,
     int32_t * error_kind_out,
     char ** error_out) {
-  if (ctx == NULL || ctx->injector == NULL)
+  if (ctx == NULL || (ctx->injector == NULL && ctx->device == NULL))
     return 0;
 
   hook_debug("hook-frida: inject_process starting");
   // Inject the library into an existing process.
-  GError * error = NULL;
-  guint id = frida_injector_inject_library_file_sync(
-      ctx->injector,
-      (guint) pid,
-      library_path,
-      entrypoint,
-      data,
-      NULL,
-      &error);
+  HookDeadline deadline;
+  hook_deadline_start(&deadline, timeout_ms);
 
-  if (error != NULL && hook_should_try_device_fallback(error) && ctx->device != NULL) {
-    hook_debug("hook-frida: inject_process helper failed, trying device fallback");
-    g_error_free(error);
-    error = NULL;
-    id = frida_device_inject_library_file_sync(
-        ctx->device,
-        (guint) pid,
-        library_path,
-        entrypoint,
-        data,
-        NULL,
-        &error);
-  }
+  GError * error = NULL;
+  guint id = hook_inject_file(
+      ctx, (guint) pid, library_path, entrypoint, data, deadline.cancellable, &error);
+  hook_deadline_finish(&deadline);
 
   if (error != NULL) {
     hook_set_error(error, error_kind_out, error_out);
@@ -184,39 +449,23 @@ hook_frida_inject_blob(HookFridaCtx * ctx,
     size_t blob_len,
     const char * entrypoint,
     const char * data,
+    uint32_t timeout_ms,
     uint32_t * out_id,
     int32_t * error_kind_out,
     char ** error_out) {
-  if (ctx == NULL || ctx->injector == NULL)
+  if (ctx == NULL || (ctx->injector == NULL && ctx->device == NULL))
     return 0;
 
   // Inject from an in-memory library blob.
+  HookDeadline deadline;
+  hook_deadline_start(&deadline, timeout_ms);
+
   GError * error = NULL;
   GBytes * bytes = g_bytes_new(blob, blob_len);
-
-  guint id = frida_injector_inject_library_blob_sync(
-      ctx->injector,
-      (guint) pid,
-      bytes,
-      entrypoint,
-      data,
-      NULL,
-      &error);
-
-  if (error != NULL && hook_should_try_device_fallback(error) && ctx->device != NULL) {
-    g_error_free(error);
-    error = NULL;
-    id = frida_device_inject_library_blob_sync(
-        ctx->device,
-        (guint) pid,
-        bytes,
-        entrypoint,
-        data,
-        NULL,
-        &error);
-  }
-
+  guint id = hook_inject_bytes(
+      ctx, (guint) pid, bytes, entrypoint, data, deadline.cancellable, &error);
   g_bytes_unref(bytes);
+  hook_deadline_finish(&deadline);
 
   if (error != NULL) {
     hook_set_error(error, error_kind_out, error_out);
@@ -242,11 +491,12 @@ hook_frida_inject_launch(HookFridaCtx * ctx,
     const char * library_path,
     const char * entrypoint,
     const char * data,
+    uint32_t timeout_ms,
     uint32_t * out_pid,
     uint32_t * out_id,
     int32_t * error_kind_out,
     char ** error_out) {
-  if (ctx == NULL || ctx->device == NULL || ctx->injector == NULL)
+  if (ctx == NULL || ctx->device == NULL)
     return 0;
 
   // Spawn the process suspended, inject, and resume.
@@ -259,46 +509,33 @@ hook_frida_inject_launch(HookFridaCtx * ctx,
     g_object_set(options, "cwd", cwd, NULL);
   g_object_set(options, "stdio", stdio, NULL);
 
+  HookDeadline deadline;
+  hook_deadline_start(&deadline, timeout_ms);
+
   GError * error = NULL;
-  guint pid = frida_device_spawn_sync(ctx->device, program, options, NULL, &error);
+  guint pid = frida_device_spawn_sync(ctx->device, program, options, deadline.cancellable, &error);
   g_object_unref(options);
 
   if (error != NULL) {
+    hook_deadline_finish(&deadline);
     hook_set_error(error, error_kind_out, error_out);
     g_error_free(error);
     return 0;
   }
 
-  guint id = frida_injector_inject_library_file_sync(
-      ctx->injector,
-      pid,
-      library_path,
-      entrypoint,
-      data,
-      NULL,
-      &error);
+  guint id = hook_inject_file(
+      ctx, pid, library_path, entrypoint, data, deadline.cancellable, &error);
 
-  if (error != NULL && hook_should_try_device_fallback(error) && ctx->device != NULL) {
-    g_error_free(error);
-    error = NULL;
-    id = frida_device_inject_library_file_sync(
-        ctx->device,
-        pid,
-        library_path,
-        entrypoint,
-        data,
-        NULL,
-        &error);
-  }
+  if (error == NULL)
+    frida_device_resume_sync(ctx->device, pid, deadline.cancellable, &error);
 
-  if (error != NULL) {
-    hook_set_error(error, error_kind_out, error_out);
-    g_error_free(error);
-    return 0;
-  }
+  gboolean expired = hook_deadline_expired(&deadline);
+  hook_deadline_finish(&deadline);
 
-  frida_device_resume_sync(ctx->device, pid, NULL, &error);
   if (error != NULL) {
+    // Don't leave a suspended target behind when the launch ran out of time.
+    if (expired)
+      frida_device_kill_sync(ctx->device, pid, NULL, NULL);
     hook_set_error(error, error_kind_out, error_out);
     g_error_free(error);
     return 0;
@@ -321,6 +558,7 @@ hook_frida_spawn(HookFridaCtx * ctx,
     const char * const * envp,
     const char * cwd,
     int32_t stdio,
+    uint32_t timeout_ms,
     uint32_t * out_pid,
     int32_t * error_kind_out,
     char ** error_out) {
@@ -337,9 +575,13 @@ hook_frida_spawn(HookFridaCtx * ctx,
     g_object_set(options, "cwd", cwd, NULL);
   g_object_set(options, "stdio", stdio, NULL);
 
+  HookDeadline deadline;
+  hook_deadline_start(&deadline, timeout_ms);
+
   GError * error = NULL;
-  guint pid = frida_device_spawn_sync(ctx->device, program, options, NULL, &error);
+  guint pid = frida_device_spawn_sync(ctx->device, program, options, deadline.cancellable, &error);
   g_object_unref(options);
+  hook_deadline_finish(&deadline);
 
   if (error != NULL) {
     hook_set_error(error, error_kind_out, error_out);
@@ -358,14 +600,68 @@ hook_frida_spawn(HookFridaCtx * ctx,
 int
 hook_frida_resume(HookFridaCtx * ctx,
     uint32_t pid,
+    uint32_t timeout_ms,
     int32_t * error_kind_out,
     char ** error_out) {
   if (ctx == NULL || ctx->device == NULL)
     return 0;
 
   // Resume a process spawned in suspended mode.
+  HookDeadline deadline;
+  hook_deadline_start(&deadline, timeout_ms);
+
+  GError * error = NULL;
+  frida_device_resume_sync(ctx->device, pid, deadline.cancellable, &error);
+  hook_deadline_finish(&deadline);
+
+  if (error != NULL) {
+    hook_set_error(error, error_kind_out, error_out);
+    g_error_free(error);
+    return 0;
+  }
+
+  if (error_kind_out != NULL)
+    *error_kind_out = HOOK_FRIDA_ERROR_NONE;
+  return 1;
+}
+
+int
+hook_frida_kill(HookFridaCtx * ctx,
+    uint32_t pid,
+    int32_t * error_kind_out,
+    char ** error_out) {
+  if (ctx == NULL || ctx->device == NULL)
+    return 0;
+
   GError * error = NULL;
-  frida_device_resume_sync(ctx->device, pid, NULL, &error);
+  frida_device_kill_sync(ctx->device, pid, NULL, &error);
+
+  if (error != NULL) {
+    hook_set_error(error, error_kind_out, error_out);
+    g_error_free(error);
+    return 0;
+  }
+
+  if (error_kind_out != NULL)
+    *error_kind_out = HOOK_FRIDA_ERROR_NONE;
+  return 1;
+}
+
+int
+hook_frida_input(HookFridaCtx * ctx,
+    uint32_t pid,
+    const uint8_t * data,
+    size_t data_len,
+    int32_t * error_kind_out,
+    char ** error_out) {
+  if (ctx == NULL || ctx->device == NULL)
+    return 0;
+
+  // Forward bytes to the stdin pipe Frida created for the spawned process.
+  GError * error = NULL;
+  GBytes * bytes = g_bytes_new(data, data_len);
+  frida_device_input_sync(ctx->device, pid, bytes, NULL, &error);
+  g_bytes_unref(bytes);
 
   if (error != NULL) {
     hook_set_error(error, error_kind_out, error_out);
@@ -383,9 +679,18 @@ hook_frida_demonitor(HookFridaCtx * ctx,
     uint32_t id,
     int32_t * error_kind_out,
     char ** error_out) {
-  if (ctx == NULL || ctx->injector == NULL)
+  if (ctx == NULL)
     return 0;
 
+  // frida-server keeps monitoring what it injected; the device API has no
+  // way to stop it, and nothing is reported here once the Rust side forgets
+  // the id.
+  if (ctx->injector == NULL) {
+    if (error_kind_out != NULL)
+      *error_kind_out = HOOK_FRIDA_ERROR_NONE;
+    return 1;
+  }
+
   // Stop monitoring the injection.
   GError * error = NULL;
   frida_injector_demonitor_sync(ctx->injector, id, NULL, &error);
@@ -401,6 +706,334 @@ hook_frida_demonitor(HookFridaCtx * ctx,
   return 1;
 }
 
+static void
+hook_on_child_added(FridaDevice * device, FridaChild * child, gpointer user_data) {
+  HookFridaCtx * ctx = user_data;
+  (void) device;
+
+  HookChildEvent * event = g_new0(HookChildEvent, 1);
+  event->pid = frida_child_get_pid(child);
+  event->parent_pid = frida_child_get_parent_pid(child);
+  event->origin = frida_child_get_origin(child);
+  event->path = g_strdup(frida_child_get_path(child));
+  g_async_queue_push(ctx->children, event);
+}
+
+int
+hook_frida_enable_child_gating(HookFridaCtx * ctx,
+    uint32_t pid,
+    int32_t * error_kind_out,
+    char ** error_out) {
+  if (ctx == NULL || ctx->device == NULL)
+    return 0;
+
+  // Children are only reported while a gating session is attached.
+  GError * error = NULL;
+  FridaSession * session = frida_device_attach_sync(ctx->device, pid, NULL, NULL, &error);
+  if (error == NULL)
+    frida_session_enable_child_gating_sync(session, NULL, &error);
+
+  if (error != NULL) {
+    if (session != NULL)
+      g_object_unref(session);
+    hook_set_error(error, error_kind_out, error_out);
+    g_error_free(error);
+    return 0;
+  }
+
+  g_mutex_lock(&ctx->gating_lock);
+  if (ctx->child_added_handler == 0) {
+    ctx->child_added_handler = g_signal_connect(
+        ctx->device, "child-added", G_CALLBACK(hook_on_child_added), ctx);
+  }
+  // Replaces (and releases) a session lost to exec.
+  g_hash_table_insert(ctx->gating_sessions, GUINT_TO_POINTER(pid), session);
+  g_mutex_unlock(&ctx->gating_lock);
+
+  if (error_kind_out != NULL)
+    *error_kind_out = HOOK_FRIDA_ERROR_NONE;
+  return 1;
+}
+
+int
+hook_frida_disable_child_gating(HookFridaCtx * ctx,
+    uint32_t pid,
+    int32_t * error_kind_out,
+    char ** error_out) {
+  if (ctx == NULL)
+    return 0;
+
+  g_mutex_lock(&ctx->gating_lock);
+  FridaSession * session = g_hash_table_lookup(ctx->gating_sessions, GUINT_TO_POINTER(pid));
+  if (session != NULL)
+    g_object_ref(session);
+  g_hash_table_remove(ctx->gating_sessions, GUINT_TO_POINTER(pid));
+  g_mutex_unlock(&ctx->gating_lock);
+
+  if (session == NULL) {
+    if (error_kind_out != NULL)
+      *error_kind_out = HOOK_FRIDA_ERROR_NONE;
+    return 1;
+  }
+
+  // Detaching also disables gating; a session already lost to exit or exec
+  // has nothing left to undo.
+  GError * error = NULL;
+  if (!frida_session_is_detached(session))
+    frida_session_detach_sync(session, NULL, &error);
+  g_object_unref(session);
+
+  if (error != NULL) {
+    hook_set_error(error, error_kind_out, error_out);
+    g_error_free(error);
+    return 0;
+  }
+
+  if (error_kind_out != NULL)
+    *error_kind_out = HOOK_FRIDA_ERROR_NONE;
+  return 1;
+}
+
+int
+hook_frida_next_child(HookFridaCtx * ctx,
+    uint32_t timeout_ms,
+    uint32_t * out_pid,
+    uint32_t * out_parent_pid,
+    int32_t * out_origin,
+    char ** out_path,
+    int32_t * error_kind_out,
+    char ** error_out) {
+  (void) error_out;
+  if (ctx == NULL)
+    return 0;
+
+  HookChildEvent * event =
+      g_async_queue_timeout_pop(ctx->children, (guint64) timeout_ms * G_TIME_SPAN_MILLISECOND);
+  if (event == NULL) {
+    *out_pid = 0;
+  } else {
+    *out_pid = event->pid;
+    *out_parent_pid = event->parent_pid;
+    *out_origin = (int32_t) event->origin;
+    *out_path = g_steal_pointer(&event->path);
+    hook_child_event_free(event);
+  }
+
+  if (error_kind_out != NULL)
+    *error_kind_out = HOOK_FRIDA_ERROR_NONE;
+  return 1;
+}
+
+static void
+hook_on_spawn_added(FridaDevice * device, FridaSpawn * spawn, gpointer user_data) {
+  HookFridaCtx * ctx = user_data;
+  (void) device;
+
+  HookSpawnEvent * event = g_new0(HookSpawnEvent, 1);
+  event->pid = frida_spawn_get_pid(spawn);
+  event->identifier = g_strdup(frida_spawn_get_identifier(spawn));
+  g_async_queue_push(ctx->spawns, event);
+}
+
+int
+hook_frida_enable_spawn_gating(HookFridaCtx * ctx,
+    int32_t * error_kind_out,
+    char ** error_out) {
+  if (ctx == NULL || ctx->device == NULL)
+    return 0;
+
+  g_mutex_lock(&ctx->gating_lock);
+  if (ctx->spawn_added_handler == 0) {
+    ctx->spawn_added_handler = g_signal_connect(
+        ctx->device, "spawn-added", G_CALLBACK(hook_on_spawn_added), ctx);
+  }
+  g_mutex_unlock(&ctx->gating_lock);
+
+  // From here on every new process on the device is held until resumed.
+  GError * error = NULL;
+  frida_device_enable_spawn_gating_sync(ctx->device, NULL, &error);
+
+  if (error != NULL) {
+    hook_set_error(error, error_kind_out, error_out);
+    g_error_free(error);
+    return 0;
+  }
+
+  if (error_kind_out != NULL)
+    *error_kind_out = HOOK_FRIDA_ERROR_NONE;
+  return 1;
+}
+
+int
+hook_frida_disable_spawn_gating(HookFridaCtx * ctx,
+    int32_t * error_kind_out,
+    char ** error_out) {
+  if (ctx == NULL || ctx->device == NULL)
+    return 0;
+
+  GError * error = NULL;
+  frida_device_disable_spawn_gating_sync(ctx->device, NULL, &error);
+
+  if (error != NULL) {
+    hook_set_error(error, error_kind_out, error_out);
+    g_error_free(error);
+    return 0;
+  }
+
+  if (error_kind_out != NULL)
+    *error_kind_out = HOOK_FRIDA_ERROR_NONE;
+  return 1;
+}
+
+int
+hook_frida_next_spawn(HookFridaCtx * ctx,
+    uint32_t timeout_ms,
+    uint32_t * out_pid,
+    char ** out_identifier,
+    int32_t * error_kind_out,
+    char ** error_out) {
+  (void) error_out;
+  if (ctx == NULL)
+    return 0;
+
+  HookSpawnEvent * event =
+      g_async_queue_timeout_pop(ctx->spawns, (guint64) timeout_ms * G_TIME_SPAN_MILLISECOND);
+  if (event == NULL) {
+    *out_pid = 0;
+  } else {
+    *out_pid = event->pid;
+    *out_identifier = g_steal_pointer(&event->identifier);
+    hook_spawn_event_free(event);
+  }
+
+  if (error_kind_out != NULL)
+    *error_kind_out = HOOK_FRIDA_ERROR_NONE;
+  return 1;
+}
+
+int
+hook_frida_next_uninjected(HookFridaCtx * ctx,
+    uint32_t timeout_ms,
+    uint32_t * out_id,
+    int32_t * error_kind_out,
+    char ** error_out) {
+  (void) error_out;
+  if (ctx == NULL || ctx->uninjected == NULL)
+    return 0;
+
+  gpointer id =
+      g_async_queue_timeout_pop(ctx->uninjected, (guint64) timeout_ms * G_TIME_SPAN_MILLISECOND);
+  *out_id = GPOINTER_TO_UINT(id);
+
+  if (error_kind_out != NULL)
+    *error_kind_out = HOOK_FRIDA_ERROR_NONE;
+  return 1;
+}
+
+int
+hook_frida_device_os(HookFridaCtx * ctx,
+    char ** out_os,
+    int32_t * error_kind_out,
+    char ** error_out) {
+  if (ctx == NULL || ctx->device == NULL || out_os == NULL)
+    return 0;
+
+  GError * error = NULL;
+  GHashTable * parameters =
+      frida_device_query_system_parameters_sync(ctx->device, NULL, &error);
+  if (error != NULL) {
+    hook_set_error(error, error_kind_out, error_out);
+    g_error_free(error);
+    return 0;
+  }
+
+  // "os" is a vardict such as {"id": "ios", "name": "iOS", "version": ...}.
+  gchar * os = NULL;
+  GVariant * info = g_hash_table_lookup(parameters, "os");
+  if (info != NULL && g_variant_is_of_type(info, G_VARIANT_TYPE_VARDICT))
+    g_variant_lookup(info, "id", "s", &os);
+  g_hash_table_unref(parameters);
+
+  *out_os = os != NULL ? os : g_strdup("");
+  if (error_kind_out != NULL)
+    *error_kind_out = HOOK_FRIDA_ERROR_NONE;
+  return 1;
+}
+
+int
+hook_frida_find_process(HookFridaCtx * ctx,
+    uint32_t pid,
+    int32_t * error_kind_out,
+    char ** error_out) {
+  if (ctx == NULL || ctx->device == NULL)
+    return 0;
+
+  GError * error = NULL;
+  FridaProcess * process =
+      frida_device_get_process_by_pid_sync(ctx->device, pid, NULL, NULL, &error);
+
+  if (error != NULL) {
+    hook_set_error(error, error_kind_out, error_out);
+    g_error_free(error);
+    return 0;
+  }
+  g_object_unref(process);
+
+  if (error_kind_out != NULL)
+    *error_kind_out = HOOK_FRIDA_ERROR_NONE;
+  return 1;
+}
+
+int
+hook_frida_enumerate_devices(HookFridaDevice ** out_devices,
+    size_t * out_len,
+    int32_t * error_kind_out,
+    char ** error_out) {
+  if (out_devices == NULL || out_len == NULL)
+    return 0;
+
+  frida_init();
+  FridaDeviceManager * manager = frida_device_manager_new();
+  GError * error = NULL;
+  FridaDeviceList * list = frida_device_manager_enumerate_devices_sync(manager, NULL, &error);
+  if (error != NULL) {
+    hook_set_error(error, error_kind_out, error_out);
+    g_error_free(error);
+    g_object_unref(manager);
+    return 0;
+  }
+
+  gint len = frida_device_list_size(list);
+  HookFridaDevice * devices = g_new0(HookFridaDevice, MAX(len, 1));
+  for (gint i = 0; i < len; i++) {
+    FridaDevice * device = frida_device_list_get(list, i);
+    devices[i].id = g_strdup(frida_device_get_id(device));
+    devices[i].name = g_strdup(frida_device_get_name(device));
+    devices[i].kind = (int32_t) frida_device_get_dtype(device);
+    g_object_unref(device);
+  }
+  g_object_unref(list);
+  g_object_unref(manager);
+
+  *out_devices = devices;
+  *out_len = (size_t) len;
+  if (error_kind_out != NULL)
+    *error_kind_out = HOOK_FRIDA_ERROR_NONE;
+  return 1;
+}
+
+void
+hook_frida_devices_free(HookFridaDevice * devices, size_t len) {
+  if (devices == NULL)
+    return;
+
+  for (size_t i = 0; i < len; i++) {
+    g_free(devices[i].id);
+    g_free(devices[i].name);
+  }
+  g_free(devices);
+}
+
 void
 hook_frida_string_free(char * s) {
   // Free strings returned to Rust.
@@ -17,36 +17,68 @@ typedef enum {
   HOOK_FRIDA_ERROR_NOT_SUPPORTED = 2,
   HOOK_FRIDA_ERROR_PERMISSION_DENIED = 3,
   HOOK_FRIDA_ERROR_PROCESS_NOT_FOUND = 4,
-  HOOK_FRIDA_ERROR_RUNTIME = 5
+  HOOK_FRIDA_ERROR_RUNTIME = 5,
+  HOOK_FRIDA_ERROR_TIMED_OUT = 6
 } HookFridaErrorKind;
 
 // Create a Frida injector context for the local device.
 HookFridaCtx * hook_frida_new(int32_t * error_kind_out, char ** error_out);
+// Create a Frida context for the frida-server listening at `address`
+// ("host" or "host:port"). There is no local injector: libraries go in
+// through the device, and its injections cannot be demonitored. timeout_ms
+// bounds connecting; 0 waits indefinitely.
+HookFridaCtx * hook_frida_new_remote(const char * address,
+    uint32_t timeout_ms,
+    int32_t * error_kind_out,
+    char ** error_out);
+// Create a Frida context for the device with `id`, or for the first USB
+// device if `id` is NULL, waiting up to timeout_ms for it to be attached
+// (0 waits indefinitely). Like remote contexts, it has no local injector.
+HookFridaCtx * hook_frida_new_device(const char * id,
+    uint32_t timeout_ms,
+    int32_t * error_kind_out,
+    char ** error_out);
 // Release all Frida resources held by the context.
 void hook_frida_free(HookFridaCtx * ctx);
+// Stop Frida's own threads and release it; call after freeing every
+// context. Frida cannot be initialized again afterwards.
+void hook_frida_shutdown(void);
+// Version of the linked frida-core; usable before hook_frida_new.
+const char * hook_frida_version(void);
 
-// Inject a library file into an existing process.
+// Built with HOOK_FRIDA_DYNAMIC only: load the frida-core shared library at
+// `path` and resolve every function the shim uses. Must succeed before any
+// other call; later calls return 1 without loading anything. On failure
+// returns 0 and writes a message into `error_out`.
+int hook_frida_load(const char * path, char * error_out, size_t error_len);
+
+// Inject a library file into an existing process (timeout_ms 0 = no
+// timeout).
 int hook_frida_inject_process(HookFridaCtx * ctx,
     int32_t pid,
     const char * library_path,
     const char * entrypoint,
     const char * data,
+    uint32_t timeout_ms,
     uint32_t * out_id,
     int32_t * error_kind_out,
     char ** error_out);
 
-// Inject an in-memory library blob into an existing process.
+// Inject an in-memory library blob into an existing process (timeout_ms 0
+// = no timeout).
 int hook_frida_inject_blob(HookFridaCtx * ctx,
     int32_t pid,
     const uint8_t * blob,
     size_t blob_len,
     const char * entrypoint,
     const char * data,
+    uint32_t timeout_ms,
     uint32_t * out_id,
     int32_t * error_kind_out,
     char ** error_out);
 
 // Spawn a process suspended, inject, then resume it.
+// timeout_ms bounds the whole launch; 0 waits indefinitely.
 int hook_frida_inject_launch(HookFridaCtx * ctx,
     const char * program,
     const char * const * argv,
@@ -56,25 +88,43 @@ int hook_frida_inject_launch(HookFridaCtx * ctx,
     const char * library_path,
     const char * entrypoint,
     const char * data,
+    uint32_t timeout_ms,
     uint32_t * out_pid,
     uint32_t * out_id,
     int32_t * error_kind_out,
     char ** error_out);
 
-// Spawn a process suspended without injecting.
+// Spawn a process suspended without injecting (timeout_ms 0 = no timeout).
 int hook_frida_spawn(HookFridaCtx * ctx,
     const char * program,
     const char * const * argv,
     const char * const * envp,
     const char * cwd,
     int32_t stdio,
+    uint32_t timeout_ms,
     uint32_t * out_pid,
     int32_t * error_kind_out,
     char ** error_out);
 
-// Resume a suspended process previously spawned by Frida.
+// Resume a suspended process previously spawned by Frida (timeout_ms 0 = no
+// timeout).
 int hook_frida_resume(HookFridaCtx * ctx,
     uint32_t pid,
+    uint32_t timeout_ms,
+    int32_t * error_kind_out,
+    char ** error_out);
+
+// Kill a process, e.g. a suspended spawn that should never run.
+int hook_frida_kill(HookFridaCtx * ctx,
+    uint32_t pid,
+    int32_t * error_kind_out,
+    char ** error_out);
+
+// Write bytes to the stdin pipe of a process spawned with piped stdio.
+int hook_frida_input(HookFridaCtx * ctx,
+    uint32_t pid,
+    const uint8_t * data,
+    size_t data_len,
     int32_t * error_kind_out,
     char ** error_out);
 
@@ -84,6 +134,91 @@ int hook_frida_demonitor(HookFridaCtx * ctx,
     int32_t * error_kind_out,
     char ** error_out);
 
+// Attach to a process and hold its children (fork, exec, spawn) suspended
+// until they are resumed.
+int hook_frida_enable_child_gating(HookFridaCtx * ctx,
+    uint32_t pid,
+    int32_t * error_kind_out,
+    char ** error_out);
+
+// Stop holding the children of a process.
+int hook_frida_disable_child_gating(HookFridaCtx * ctx,
+    uint32_t pid,
+    int32_t * error_kind_out,
+    char ** error_out);
+
+// Wait up to timeout_ms for a gated child. out_pid is 0 if none arrived;
+// out_path (possibly NULL) must be freed with hook_frida_string_free.
+// out_origin mirrors FridaChildOrigin: 0 fork, 1 exec, 2 spawn.
+int hook_frida_next_child(HookFridaCtx * ctx,
+    uint32_t timeout_ms,
+    uint32_t * out_pid,
+    uint32_t * out_parent_pid,
+    int32_t * out_origin,
+    char ** out_path,
+    int32_t * error_kind_out,
+    char ** error_out);
+
+// Hold every new process on the device suspended until it is resumed.
+int hook_frida_enable_spawn_gating(HookFridaCtx * ctx,
+    int32_t * error_kind_out,
+    char ** error_out);
+
+// Stop holding new processes.
+int hook_frida_disable_spawn_gating(HookFridaCtx * ctx,
+    int32_t * error_kind_out,
+    char ** error_out);
+
+// Wait up to timeout_ms for a gated spawn. out_pid is 0 if none arrived;
+// out_identifier (possibly NULL) must be freed with hook_frida_string_free.
+int hook_frida_next_spawn(HookFridaCtx * ctx,
+    uint32_t timeout_ms,
+    uint32_t * out_pid,
+    char ** out_identifier,
+    int32_t * error_kind_out,
+    char ** error_out);
+
+// Wait up to timeout_ms for the injector to report an injected library as
+// unloaded (the agent returned without staying resident, or the target
+// exited). out_id is 0 if none was.
+int hook_frida_next_uninjected(HookFridaCtx * ctx,
+    uint32_t timeout_ms,
+    uint32_t * out_id,
+    int32_t * error_kind_out,
+    char ** error_out);
+
+// Write the id of the OS the context's device runs ("ios", "android",
+// "macos", ...) to out_os, or an empty string if it does not report one.
+// out_os must be freed with hook_frida_string_free.
+int hook_frida_device_os(HookFridaCtx * ctx,
+    char ** out_os,
+    int32_t * error_kind_out,
+    char ** error_out);
+
+// Check that a process with `pid` runs on the context's device.
+int hook_frida_find_process(HookFridaCtx * ctx,
+    uint32_t pid,
+    int32_t * error_kind_out,
+    char ** error_out);
+
+// A device known to Frida's device manager. kind mirrors FridaDeviceType:
+// 0 local, 1 remote, 2 USB.
+typedef struct {
+  char * id;
+  char * name;
+  int32_t kind;
+} HookFridaDevice;
+
+// List the devices currently attached. out_devices must be freed with
+// hook_frida_devices_free.
+int hook_frida_enumerate_devices(HookFridaDevice ** out_devices,
+    size_t * out_len,
+    int32_t * error_kind_out,
+    char ** error_out);
+
+// Free a list returned by hook_frida_enumerate_devices.
+void hook_frida_devices_free(HookFridaDevice * devices, size_t len);
+
 // Free error strings returned by this shim.
 void hook_frida_string_free(char * s);
 
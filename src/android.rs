@@ -0,0 +1,291 @@
+use std::ffi::OsStr;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::library::{LibrarySource, sha256_hex};
+use crate::{Architecture, Error, InjectOptions, Injector, Library, Result, staging};
+
+const ADB_ENV: &str = "HOOK_INJECT_ADB";
+// Where `push_agent` puts agents; shell-writable on every Android release.
+const PUSH_DIR: &str = "/data/local/tmp/hook-inject";
+
+/// An Android device or emulator adb can reach, as listed by
+/// `adb_devices`.
+///
+/// Injecting goes through frida-server running on the device as root;
+/// Frida reaches it over adb, under the device's serial (see
+/// `AdbDevice::injector`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdbDevice {
+    serial: String,
+    state: String,
+    model: Option<String>,
+}
+
+impl AdbDevice {
+    /// The serial adb and Frida know the device by.
+    pub fn serial(&self) -> &str {
+        &self.serial
+    }
+
+    /// adb's state for the device: `device` once it is usable, otherwise
+    /// e.g. `unauthorized` or `offline`.
+    pub fn state(&self) -> &str {
+        &self.state
+    }
+
+    /// The device's model, if adb reports it.
+    pub fn model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+
+    /// Whether adb can run commands on the device.
+    pub fn is_online(&self) -> bool {
+        self.state == "device"
+    }
+
+    /// The device's primary ABI, e.g. `arm64-v8a`.
+    pub fn abi(&self) -> Result<String> {
+        let abi = self.shell("getprop ro.product.cpu.abi")?;
+        Ok(abi.trim().to_string())
+    }
+
+    /// The architecture of the device's primary ABI, which agents for its
+    /// apps are built for.
+    pub fn architecture(&self) -> Result<Architecture> {
+        let abi = self.abi()?;
+        match abi.as_str() {
+            "arm64-v8a" => Ok(Architecture::Aarch64),
+            "armeabi-v7a" | "armeabi" => Ok(Architecture::Arm),
+            "x86_64" => Ok(Architecture::X86_64),
+            "x86" => Ok(Architecture::X86),
+            "riscv64" => Ok(Architecture::Riscv64),
+            _ => Err(Error::not_supported(format_args!(
+                "{}: unknown Android ABI `{abi}`",
+                self.serial
+            ))),
+        }
+    }
+
+    /// An injector for the device's processes, through the frida-server
+    /// running on it; see `Injector::device`.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{Library, Program};
+    ///
+    /// let device = hook_inject::adb_devices()?.remove(0);
+    /// let injector = device.injector()?;
+    /// let library = Library::from_path("target/aarch64-linux-android/release/libagent.so")?;
+    /// let injected = injector.inject_program(Program::new("com.example.app"), library)?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn injector(&self) -> Result<Injector> {
+        self.injector_with(InjectOptions::default())
+    }
+
+    /// `injector` with default options for its calls.
+    pub fn injector_with(&self, options: InjectOptions) -> Result<Injector> {
+        self.check_online()?;
+        Injector::builder()
+            .device(self.serial.clone())
+            .options(options)
+            .build()
+    }
+
+    /// Push `library` to `/data/local/tmp/hook-inject` on the device and
+    /// return it as loaded from there (see `Library::from_device_path`).
+    ///
+    /// Injectors otherwise send the image through frida-server, which maps
+    /// it from memory; a pushed agent is loaded from its file, and shows up
+    /// as such in the target's memory maps. Apps may be barred by SELinux
+    /// from loading it there; `push_agent_as` puts it in an app's own
+    /// directory instead.
+    pub fn push_agent(&self, library: &Library) -> Result<Library> {
+        self.push_agent_with(library, &InjectOptions::default())
+    }
+
+    /// `push_agent`, staging an in-memory library where `options` say
+    /// before it is pushed.
+    pub fn push_agent_with(&self, library: &Library, options: &InjectOptions) -> Result<Library> {
+        self.check_online()?;
+        let name = agent_file_name(library)?;
+        let remote = format!("{PUSH_DIR}/{name}");
+        self.shell(&format!("mkdir -p {PUSH_DIR}"))?;
+        match library.source() {
+            LibrarySource::Path(path) => self.push(path, &remote)?,
+            LibrarySource::Device(_) => return Ok(library.clone()),
+            // In memory, compressed or not.
+            _ => {
+                let bytes = library.image_bytes()?;
+                staging::prepare(options)?;
+                let local = staging::stage_bytes(options, "so", &bytes)?;
+                let pushed = self.push(&local, &remote);
+                let _ = std::fs::remove_file(&local);
+                pushed?;
+            }
+        }
+        self.shell(&format!("chmod 755 {remote}"))?;
+        Ok(library.staged_on_device(remote))
+    }
+
+    /// Push `library` into the private directory of app `package` with
+    /// `run-as`, and return it as loaded from there.
+    ///
+    /// An app can always load libraries from its own directory, where
+    /// SELinux may keep it from loading them from `/data/local/tmp`.
+    /// `run-as` only works for debuggable apps; others fail with
+    /// `ErrorKind::PermissionDenied`.
+    pub fn push_agent_as(&self, library: &Library, package: &str) -> Result<Library> {
+        self.push_agent_as_with(library, package, &InjectOptions::default())
+    }
+
+    /// `push_agent_as`, staging an in-memory library where `options` say
+    /// before it is pushed.
+    pub fn push_agent_as_with(
+        &self,
+        library: &Library,
+        package: &str,
+        options: &InjectOptions,
+    ) -> Result<Library> {
+        if package.is_empty()
+            || !package
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_')
+        {
+            return Err(Error::invalid_input(format_args!(
+                "`{package}` is not an Android package name"
+            )));
+        }
+        let pushed = self.push_agent_with(library, options)?;
+        let LibrarySource::Device(source) = pushed.source() else {
+            return Ok(pushed);
+        };
+        let name = source.rsplit('/').next().unwrap_or(source);
+        let remote = format!("/data/data/{package}/files/hook-inject/{name}");
+        // run-as starts in the app's data directory.
+        let staged = format!("files/hook-inject/{name}");
+        let copy =
+            format!("mkdir -p files/hook-inject && cp {source} {staged} && chmod 700 {staged}");
+        self.shell(&format!("run-as {package} sh -c '{copy}'"))
+            .map_err(|err| {
+                if err.to_string().contains("not debuggable") {
+                    Error::permission_denied(format_args!("{package} is not debuggable: {err}"))
+                } else {
+                    err
+                }
+            })?;
+        Ok(pushed.staged_on_device(remote))
+    }
+
+    fn check_online(&self) -> Result<()> {
+        if self.is_online() {
+            return Ok(());
+        }
+        Err(Error::runtime_unavailable(format_args!(
+            "{} is {}, not ready for adb",
+            self.serial, self.state
+        )))
+    }
+
+    fn push(&self, local: &Path, remote: &str) -> Result<()> {
+        adb([
+            OsStr::new("-s"),
+            OsStr::new(&self.serial),
+            OsStr::new("push"),
+            local.as_os_str(),
+            OsStr::new(remote),
+        ])
+        .map(drop)
+    }
+
+    fn shell(&self, command: &str) -> Result<String> {
+        adb(["-s", self.serial.as_str(), "shell", command])
+    }
+}
+
+/// List the Android devices and emulators adb can see, including ones not
+/// ready for use (see `AdbDevice::is_online`).
+///
+/// Runs the `adb` from `HOOK_INJECT_ADB` or `PATH`; fails with
+/// `ErrorKind::RuntimeUnavailable` if there is none.
+///
+/// # Examples
+/// ```no_run
+/// for device in hook_inject::adb_devices()? {
+///     println!("{} {}", device.serial(), device.model().unwrap_or("?"));
+/// }
+/// # Ok::<(), hook_inject::Error>(())
+/// ```
+pub fn adb_devices() -> Result<Vec<AdbDevice>> {
+    let output = adb(["devices", "-l"])?;
+    Ok(output.lines().filter_map(parse_device).collect())
+}
+
+/// Parse a line of `adb devices -l`:
+/// `<serial> <state> [usb:1-1] [product:..] [model:Pixel_7] ...`.
+fn parse_device(line: &str) -> Option<AdbDevice> {
+    if line.starts_with("List of devices") || line.starts_with('*') {
+        return None;
+    }
+    let mut fields = line.split_whitespace();
+    let serial = fields.next()?.to_string();
+    let state = fields.next()?.to_string();
+    let model = fields
+        .find_map(|field| field.strip_prefix("model:"))
+        .map(|model| model.replace('_', " "));
+    Some(AdbDevice {
+        serial,
+        state,
+        model,
+    })
+}
+
+/// The name to push `library` under: its own file name, or one derived
+/// from its image for in-memory libraries.
+fn agent_file_name(library: &Library) -> Result<String> {
+    if let LibrarySource::Path(path) = library.source()
+        && let Some(name) = path.file_name().and_then(OsStr::to_str)
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+    {
+        return Ok(name.to_string());
+    }
+    let digest = sha256_hex(&library.image_bytes()?);
+    Ok(format!("agent-{}.so", &digest[..16]))
+}
+
+/// Run adb with `args`, returning its stdout.
+fn adb<I, S>(args: I) -> Result<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let program = std::env::var_os(ADB_ENV)
+        .filter(|path| !path.is_empty())
+        .unwrap_or_else(|| "adb".into());
+    let output = Command::new(&program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|err| {
+            Error::runtime_unavailable(format_args!(
+                "failed to run {}: {err} (install Android platform-tools or set {ADB_ENV})",
+                Path::new(&program).display()
+            ))
+        })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let message = if stderr.trim().is_empty() {
+            stdout.trim()
+        } else {
+            stderr.trim()
+        };
+        return Err(Error::runtime(format_args!("adb failed: {message}")));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
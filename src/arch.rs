@@ -0,0 +1,473 @@
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+
+use crate::{Error, Result};
+
+// Enough of a file to reach the PE header behind a DOS stub.
+const HEADER_LEN: usize = 4096;
+
+/// Executable image format of a library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum BinaryFormat {
+    /// ELF (Linux, Android).
+    Elf,
+    /// Mach-O (macOS, iOS).
+    MachO,
+    /// PE/COFF (Windows).
+    Pe,
+}
+
+impl BinaryFormat {
+    /// The format native to the platform this crate was built for, if any.
+    pub fn native() -> Option<BinaryFormat> {
+        if cfg!(any(target_os = "macos", target_os = "ios")) {
+            Some(BinaryFormat::MachO)
+        } else if cfg!(windows) {
+            Some(BinaryFormat::Pe)
+        } else if cfg!(any(target_os = "linux", target_os = "android")) {
+            Some(BinaryFormat::Elf)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for BinaryFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BinaryFormat::Elf => "ELF",
+            BinaryFormat::MachO => "Mach-O",
+            BinaryFormat::Pe => "PE",
+        })
+    }
+}
+
+/// CPU architecture of a library or process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Architecture {
+    /// 32-bit x86.
+    X86,
+    /// 64-bit x86.
+    X86_64,
+    /// 32-bit ARM.
+    Arm,
+    /// 64-bit ARM.
+    Aarch64,
+    /// 64-bit RISC-V.
+    Riscv64,
+}
+
+impl Architecture {
+    /// The architecture this crate was built for, if listed.
+    pub fn native() -> Option<Architecture> {
+        if cfg!(target_arch = "x86") {
+            Some(Architecture::X86)
+        } else if cfg!(target_arch = "x86_64") {
+            Some(Architecture::X86_64)
+        } else if cfg!(target_arch = "arm") {
+            Some(Architecture::Arm)
+        } else if cfg!(target_arch = "aarch64") {
+            Some(Architecture::Aarch64)
+        } else if cfg!(target_arch = "riscv64") {
+            Some(Architecture::Riscv64)
+        } else {
+            None
+        }
+    }
+
+    /// Rust target triple for this architecture on the platform this crate
+    /// was built for, e.g. `"i686-unknown-linux-gnu"` on x86_64 Linux.
+    ///
+    /// Returns `None` for combinations Rust has no standard target for.
+    pub fn target_triple(&self) -> Option<&'static str> {
+        let triples: [Option<&'static str>; 5] = if cfg!(target_os = "android") {
+            [
+                Some("i686-linux-android"),
+                Some("x86_64-linux-android"),
+                Some("armv7-linux-androideabi"),
+                Some("aarch64-linux-android"),
+                Some("riscv64-linux-android"),
+            ]
+        } else if cfg!(all(target_os = "linux", target_env = "musl")) {
+            [
+                Some("i686-unknown-linux-musl"),
+                Some("x86_64-unknown-linux-musl"),
+                Some("armv7-unknown-linux-musleabihf"),
+                Some("aarch64-unknown-linux-musl"),
+                Some("riscv64gc-unknown-linux-musl"),
+            ]
+        } else if cfg!(target_os = "linux") {
+            [
+                Some("i686-unknown-linux-gnu"),
+                Some("x86_64-unknown-linux-gnu"),
+                Some("armv7-unknown-linux-gnueabihf"),
+                Some("aarch64-unknown-linux-gnu"),
+                Some("riscv64gc-unknown-linux-gnu"),
+            ]
+        } else if cfg!(target_os = "macos") {
+            [
+                None,
+                Some("x86_64-apple-darwin"),
+                None,
+                Some("aarch64-apple-darwin"),
+                None,
+            ]
+        } else if cfg!(target_os = "ios") {
+            [
+                None,
+                Some("x86_64-apple-ios"),
+                None,
+                Some("aarch64-apple-ios"),
+                None,
+            ]
+        } else if cfg!(all(windows, target_env = "gnu")) {
+            [
+                Some("i686-pc-windows-gnu"),
+                Some("x86_64-pc-windows-gnu"),
+                None,
+                None,
+                None,
+            ]
+        } else if cfg!(windows) {
+            [
+                Some("i686-pc-windows-msvc"),
+                Some("x86_64-pc-windows-msvc"),
+                Some("thumbv7a-pc-windows-msvc"),
+                Some("aarch64-pc-windows-msvc"),
+                None,
+            ]
+        } else {
+            [None; 5]
+        };
+
+        triples[match self {
+            Architecture::X86 => 0,
+            Architecture::X86_64 => 1,
+            Architecture::Arm => 2,
+            Architecture::Aarch64 => 3,
+            Architecture::Riscv64 => 4,
+        }]
+    }
+
+    /// Name as used in target triples, e.g. `"x86_64"` or `"aarch64"`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Architecture::X86 => "x86",
+            Architecture::X86_64 => "x86_64",
+            Architecture::Arm => "arm",
+            Architecture::Aarch64 => "aarch64",
+            Architecture::Riscv64 => "riscv64",
+        }
+    }
+}
+
+impl fmt::Display for Architecture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Format and architecture read from an image header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ImageKind {
+    pub(crate) format: BinaryFormat,
+    pub(crate) architecture: Option<Architecture>,
+}
+
+/// Identify an image from its leading bytes.
+///
+/// Returns `None` for unrecognized data and for multi-architecture (fat)
+/// Mach-O files, whose architecture depends on the loading process.
+pub(crate) fn identify(header: &[u8]) -> Option<ImageKind> {
+    let u16_le = |at: usize| Some(u16::from_le_bytes(header.get(at..at + 2)?.try_into().ok()?));
+    let u32_le = |at: usize| Some(u32::from_le_bytes(header.get(at..at + 4)?.try_into().ok()?));
+
+    match header.get(..4)? {
+        [0x7f, b'E', b'L', b'F'] => {
+            let is_64 = *header.get(4)? == 2;
+            let architecture = match (u16_le(18)?, is_64) {
+                (3, false) => Some(Architecture::X86),
+                (62, true) => Some(Architecture::X86_64),
+                (40, false) => Some(Architecture::Arm),
+                (183, true) => Some(Architecture::Aarch64),
+                (243, true) => Some(Architecture::Riscv64),
+                _ => None,
+            };
+            Some(ImageKind {
+                format: BinaryFormat::Elf,
+                architecture,
+            })
+        }
+        [0xce | 0xcf, 0xfa, 0xed, 0xfe] => Some(ImageKind {
+            format: BinaryFormat::MachO,
+            architecture: macho_architecture(u32_le(4)?),
+        }),
+        [b'M', b'Z', ..] => {
+            let pe = u32_le(0x3c)? as usize;
+            if header.get(pe..pe + 4)? != b"PE\0\0" {
+                return None;
+            }
+            let architecture = match u16_le(pe + 4)? {
+                0x14c => Some(Architecture::X86),
+                0x8664 => Some(Architecture::X86_64),
+                0x1c0 | 0x1c4 => Some(Architecture::Arm),
+                0xaa64 => Some(Architecture::Aarch64),
+                _ => None,
+            };
+            Some(ImageKind {
+                format: BinaryFormat::Pe,
+                architecture,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Identify the image stored at `path`.
+pub(crate) fn identify_file(path: &Path) -> Result<Option<ImageKind>> {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    std::fs::File::open(path)?
+        .take(HEADER_LEN as u64)
+        .read_to_end(&mut header)?;
+    Ok(identify(&header))
+}
+
+fn macho_architecture(cpu_type: u32) -> Option<Architecture> {
+    match cpu_type {
+        7 => Some(Architecture::X86),
+        0x0100_0007 => Some(Architecture::X86_64),
+        12 => Some(Architecture::Arm),
+        0x0100_000c => Some(Architecture::Aarch64),
+        _ => None,
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn process_architecture(pid: i32) -> Result<Architecture> {
+    let path = format!("/proc/{pid}/exe");
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    std::fs::File::open(&path)
+        .and_then(|file| file.take(HEADER_LEN as u64).read_to_end(&mut header))
+        .map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => Error::process_not_found(pid),
+            std::io::ErrorKind::PermissionDenied => {
+                Error::permission_denied(format!("permission denied reading {path}"))
+            }
+            _ => Error::from(err),
+        })?;
+
+    identify(&header)
+        .and_then(|kind| kind.architecture)
+        .ok_or_else(|| {
+            Error::not_supported(format_args!("unrecognized executable format for pid {pid}"))
+        })
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn process_architecture(pid: i32) -> Result<Architecture> {
+    let (cpu_type, _) = cpu_info(pid)?;
+    macho_architecture(cpu_type as u32).ok_or_else(|| {
+        Error::not_supported(format_args!(
+            "unrecognized CPU type {cpu_type:#x} for pid {pid}"
+        ))
+    })
+}
+
+/// Whether `pid` runs the arm64e slice, whose code pointers are signed.
+#[cfg(target_os = "macos")]
+pub(crate) fn is_arm64e(pid: i32) -> Result<bool> {
+    // <mach/machine.h>: CPU_SUBTYPE_ARM64E, below the capability bits.
+    const CPU_SUBTYPE_ARM64E: i32 = 2;
+    const CPU_SUBTYPE_MASK: i32 = 0xff000000u32 as i32;
+
+    let (cpu_type, cpu_subtype) = cpu_info(pid)?;
+    Ok(
+        macho_architecture(cpu_type as u32) == Some(Architecture::Aarch64)
+            && cpu_subtype & !CPU_SUBTYPE_MASK == CPU_SUBTYPE_ARM64E,
+    )
+}
+
+/// The CPU type and subtype of the slice `pid` runs.
+#[cfg(target_os = "macos")]
+fn cpu_info(pid: i32) -> Result<(i32, i32)> {
+    use std::ffi::{c_int, c_void};
+
+    // <sys/proc_info.h>: PROC_PIDARCHINFO returns the running slice's CPU type.
+    const PROC_PIDARCHINFO: c_int = 19;
+
+    #[repr(C)]
+    #[derive(Default)]
+    #[allow(dead_code)]
+    struct ProcArchInfo {
+        cpu_type: i32,
+        cpu_subtype: i32,
+    }
+
+    let mut info = ProcArchInfo::default();
+    let size = std::mem::size_of::<ProcArchInfo>() as c_int;
+    let written = unsafe {
+        libc::proc_pidinfo(
+            pid,
+            PROC_PIDARCHINFO,
+            0,
+            &mut info as *mut ProcArchInfo as *mut c_void,
+            size,
+        )
+    };
+    if written != size {
+        crate::Process::from_pid(pid)?;
+        return Err(Error::permission_denied(format_args!(
+            "cannot query the architecture of pid {pid}"
+        )));
+    }
+    Ok((info.cpu_type, info.cpu_subtype))
+}
+
+#[cfg(windows)]
+pub(crate) fn process_architecture(pid: i32) -> Result<Architecture> {
+    use windows_sys::Win32::Foundation::{CloseHandle, ERROR_ACCESS_DENIED, GetLastError, HANDLE};
+    use windows_sys::Win32::System::SystemInformation::{
+        IMAGE_FILE_MACHINE, IMAGE_FILE_MACHINE_AMD64, IMAGE_FILE_MACHINE_ARM64,
+        IMAGE_FILE_MACHINE_ARMNT, IMAGE_FILE_MACHINE_I386, IMAGE_FILE_MACHINE_UNKNOWN,
+    };
+    use windows_sys::Win32::System::Threading::{
+        IsWow64Process2, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    if pid <= 0 {
+        return Err(Error::invalid_input("pid must be > 0"));
+    }
+
+    let handle: HANDLE = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid as u32) };
+    if handle.is_null() {
+        let err = unsafe { GetLastError() };
+        if err == ERROR_ACCESS_DENIED {
+            return Err(Error::permission_denied(
+                "permission denied while opening process (OpenProcess)",
+            ));
+        }
+        return Err(Error::process_not_found(pid));
+    }
+
+    let mut process_machine: IMAGE_FILE_MACHINE = 0;
+    let mut native_machine: IMAGE_FILE_MACHINE = 0;
+    let ok = unsafe { IsWow64Process2(handle, &mut process_machine, &mut native_machine) };
+    unsafe { CloseHandle(handle) };
+    if ok == 0 {
+        return Err(Error::runtime("IsWow64Process2 failed"));
+    }
+
+    // Processes not running under WOW64 report IMAGE_FILE_MACHINE_UNKNOWN.
+    let machine = if process_machine == IMAGE_FILE_MACHINE_UNKNOWN {
+        native_machine
+    } else {
+        process_machine
+    };
+    match machine {
+        IMAGE_FILE_MACHINE_I386 => Ok(Architecture::X86),
+        IMAGE_FILE_MACHINE_AMD64 => Ok(Architecture::X86_64),
+        IMAGE_FILE_MACHINE_ARMNT => Ok(Architecture::Arm),
+        IMAGE_FILE_MACHINE_ARM64 => Ok(Architecture::Aarch64),
+        other => Err(Error::not_supported(format_args!(
+            "unrecognized machine type {other:#x} for pid {pid}"
+        ))),
+    }
+}
+
+/// The architecture of the machine itself, which differs from
+/// `Architecture::native` for injectors built as 32-bit code or run under
+/// emulation (e.g. x86_64 under Rosetta).
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn host_architecture() -> Option<Architecture> {
+    let mut uts: libc::utsname = unsafe { std::mem::zeroed() };
+    if unsafe { libc::uname(&mut uts) } != 0 {
+        return Architecture::native();
+    }
+    let machine = unsafe { std::ffi::CStr::from_ptr(uts.machine.as_ptr()) }.to_bytes();
+    match machine {
+        b"x86_64" => Some(Architecture::X86_64),
+        b"i386" | b"i486" | b"i586" | b"i686" => Some(Architecture::X86),
+        // 32-bit processes on arm64 kernels are told `armv8l`.
+        b"aarch64" | b"arm64" | b"armv8l" => Some(Architecture::Aarch64),
+        _ if machine.starts_with(b"arm") => Some(Architecture::Arm),
+        b"riscv64" => Some(Architecture::Riscv64),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn host_architecture() -> Option<Architecture> {
+    use std::ffi::{c_int, c_void};
+
+    // Set on Apple silicon, for translated processes too.
+    let mut arm64: c_int = 0;
+    let mut size = std::mem::size_of::<c_int>();
+    let found = unsafe {
+        libc::sysctlbyname(
+            c"hw.optional.arm64".as_ptr(),
+            &mut arm64 as *mut c_int as *mut c_void,
+            &mut size,
+            std::ptr::null_mut(),
+            0,
+        )
+    } == 0;
+    Some(if found && arm64 == 1 {
+        Architecture::Aarch64
+    } else {
+        Architecture::X86_64
+    })
+}
+
+#[cfg(windows)]
+pub(crate) fn host_architecture() -> Option<Architecture> {
+    use windows_sys::Win32::System::SystemInformation::{
+        IMAGE_FILE_MACHINE, IMAGE_FILE_MACHINE_AMD64, IMAGE_FILE_MACHINE_ARM64,
+        IMAGE_FILE_MACHINE_ARMNT, IMAGE_FILE_MACHINE_I386,
+    };
+    use windows_sys::Win32::System::Threading::{GetCurrentProcess, IsWow64Process2};
+
+    let mut process_machine: IMAGE_FILE_MACHINE = 0;
+    let mut native_machine: IMAGE_FILE_MACHINE = 0;
+    let ok = unsafe {
+        IsWow64Process2(
+            GetCurrentProcess(),
+            &mut process_machine,
+            &mut native_machine,
+        )
+    };
+    if ok == 0 {
+        return Architecture::native();
+    }
+    match native_machine {
+        IMAGE_FILE_MACHINE_I386 => Some(Architecture::X86),
+        IMAGE_FILE_MACHINE_AMD64 => Some(Architecture::X86_64),
+        IMAGE_FILE_MACHINE_ARMNT => Some(Architecture::Arm),
+        IMAGE_FILE_MACHINE_ARM64 => Some(Architecture::Aarch64),
+        _ => None,
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    windows
+)))]
+pub(crate) fn host_architecture() -> Option<Architecture> {
+    Architecture::native()
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    windows
+)))]
+pub(crate) fn process_architecture(_pid: i32) -> Result<Architecture> {
+    Err(Error::not_supported(
+        "process architecture detection is not supported on this platform",
+    ))
+}
@@ -0,0 +1,136 @@
+// Running a `ManagedAssembly` in a Windows .NET target once its library is
+// injected.
+//
+// The `hook-inject-clr` bootstrapper, built and embedded by the build
+// script, is injected after the library like any other agent, with the
+// assembly in its data. It hands the assembly to the runtime in the target,
+// or starts the .NET Framework CLR, and writes the outcome to a status file
+// in the staging directory before unloading.
+
+use crate::{Error, InjectOptions, ManagedAssembly, Process, Result};
+
+use super::BackendHandle;
+
+/// Fail unless `assembly` can be bootstrapped here.
+pub(super) fn check(assembly: &ManagedAssembly) -> Result<()> {
+    if !cfg!(windows) {
+        return Err(Error::not_supported(
+            "managed assemblies are only bootstrapped into Windows targets",
+        ));
+    }
+    if !cfg!(feature = "clr-bootstrap") {
+        return Err(Error::not_supported(
+            "bootstrapping managed assemblies needs the `clr-bootstrap` feature",
+        ));
+    }
+    // Fields are passed one per line, the argument last.
+    let path = assembly
+        .path()
+        .to_str()
+        .ok_or_else(|| Error::invalid_input("managed assembly path is not valid UTF-8"))?;
+    for (field, value) in [
+        ("path", path),
+        ("type name", assembly.type_name()),
+        ("method", assembly.method()),
+    ] {
+        if value.is_empty() || value.contains(['\n', '\0']) {
+            return Err(Error::invalid_input(format_args!(
+                "managed assembly {field} must be a non-empty single line"
+            )));
+        }
+    }
+    if assembly.argument().contains('\0') {
+        return Err(Error::invalid_input(
+            "managed assembly argument contains an interior NUL byte",
+        ));
+    }
+    Ok(())
+}
+
+/// Have the bootstrapper run `assembly` in `process`.
+#[cfg(all(windows, feature = "clr-bootstrap"))]
+pub(super) fn bootstrap(
+    backend: &BackendHandle,
+    process: Process,
+    assembly: &ManagedAssembly,
+    options: &InjectOptions,
+) -> Result<()> {
+    use std::time::Duration;
+
+    use crate::clock::poll_until;
+
+    // Starting the CLR and running the method take a while.
+    const WAIT: Duration = Duration::from_secs(30);
+    const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+    let path = std::path::absolute(assembly.path())?;
+    if !path.is_file() {
+        return Err(Error::invalid_input(format_args!(
+            "managed assembly not found: {}",
+            path.display()
+        )));
+    }
+    let status = crate::staging::artifact_path(options, process.pid(), "status")?;
+    let request = [
+        status.to_string_lossy().as_ref(),
+        &path.to_string_lossy(),
+        assembly.type_name(),
+        assembly.method(),
+        assembly.argument(),
+    ]
+    .join("\n");
+    let bootstrapper = crate::embed_agent!("hook-inject-clr")?.with_data(request)?;
+    let id = backend.inject_library(process, &bootstrapper, options)?;
+
+    let timeout = options.timeout_value().unwrap_or(WAIT);
+    // Written in one go once the method has returned.
+    let outcome = poll_until(options.clock_value(), Some(timeout), POLL_INTERVAL, || {
+        Ok(std::fs::read_to_string(&status)
+            .ok()
+            .filter(|text| !text.is_empty()))
+    })
+    .and_then(|text| {
+        text.ok_or_else(|| {
+            Error::timed_out(format_args!(
+                "{}.{} did not return within {timeout:?}",
+                assembly.type_name(),
+                assembly.method()
+            ))
+        })
+    });
+    // The bootstrapper unloads itself once it has reported.
+    let _ = backend.inner.uninject(id);
+    let _ = std::fs::remove_file(&status);
+
+    let text = outcome?;
+    if let Some(reason) = text.strip_prefix("failed: ") {
+        return Err(Error::runtime(format_args!(
+            "failed to run {} in pid {}: {reason}",
+            path.display(),
+            process.pid()
+        )));
+    }
+    match text.strip_prefix("returned: ").map(str::trim) {
+        Some("0") => Ok(()),
+        Some(code) => Err(Error::runtime(format_args!(
+            "{}.{} returned {code} in pid {}",
+            assembly.type_name(),
+            assembly.method(),
+            process.pid()
+        ))),
+        None => Err(Error::runtime(format_args!(
+            "unexpected report from the bootstrapper in pid {}: {text}",
+            process.pid()
+        ))),
+    }
+}
+
+#[cfg(not(all(windows, feature = "clr-bootstrap")))]
+pub(super) fn bootstrap(
+    _backend: &BackendHandle,
+    _process: Process,
+    assembly: &ManagedAssembly,
+    _options: &InjectOptions,
+) -> Result<()> {
+    check(assembly)
+}
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use super::BackendHandle;
+use crate::{
+    ChildOrigin, Descendant, InjectOptions, InjectionEventKind, Library, Process, Result, notify,
+};
+
+// How long the watcher blocks waiting for a child before polling again.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// An injection whose target is gated so its exec and children are handled.
+#[derive(Clone)]
+struct Follower {
+    library: Library,
+    options: InjectOptions,
+    id: u64,
+    // The process the user injected; descendants share its entry lifetime.
+    root: Process,
+}
+
+// Keyed by process rather than pid: the same pid may run on several devices.
+static FOLLOWERS: LazyLock<Mutex<HashMap<Process, Follower>>> = LazyLock::new(Default::default);
+
+/// Start following `process` if the options ask for it.
+///
+/// Must be called while the target is still suspended (or before it can
+/// exec or fork), so nothing slips past between injection and gating.
+pub(super) fn start(
+    backend: &BackendHandle,
+    process: Process,
+    library: &Library,
+    id: u64,
+    options: &InjectOptions,
+) -> Result<()> {
+    if !options.follow_exec_value() && options.children_value().is_none() {
+        return Ok(());
+    }
+
+    let follower = Follower {
+        library: library.clone(),
+        options: options.clone(),
+        id,
+        root: process,
+    };
+    gate(backend, process, follower)?;
+
+    // Each runtime reports its own targets' children.
+    super::ensure_watcher("hook-inject-follow", backend, poll);
+    Ok(())
+}
+
+/// Stop following `process` and any descendants injected on its behalf.
+///
+/// Descendant injections are uninjected; the id of the latest injection into
+/// `process` itself is returned for the caller to uninject.
+pub(super) fn stop(backend: &BackendHandle, process: Process) -> Option<u64> {
+    let removed: Vec<(Process, Follower)> = {
+        let mut followers = lock();
+        let descendants: Vec<Process> = followers
+            .iter()
+            .filter(|(_, follower)| follower.root == process)
+            .map(|(descendant, _)| *descendant)
+            .collect();
+        descendants
+            .into_iter()
+            .filter_map(|descendant| {
+                followers
+                    .remove(&descendant)
+                    .map(|follower| (descendant, follower))
+            })
+            .collect()
+    };
+
+    let mut root_id = None;
+    for (descendant, follower) in removed {
+        let _ = backend.inner.disable_child_gating(descendant);
+        if descendant == process {
+            root_id = Some(follower.id);
+        } else {
+            let _ = backend.inner.uninject(follower.id);
+        }
+    }
+    root_id
+}
+
+fn gate(backend: &BackendHandle, process: Process, follower: Follower) -> Result<()> {
+    backend.inner.enable_child_gating(process)?;
+    lock().insert(process, follower);
+    Ok(())
+}
+
+fn lock() -> std::sync::MutexGuard<'static, HashMap<Process, Follower>> {
+    FOLLOWERS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn poll(backend: &BackendHandle) {
+    match backend.inner.next_child(POLL_INTERVAL) {
+        Ok(Some(child)) => handle(backend, child),
+        Ok(None) => {}
+        Err(_) => std::thread::sleep(POLL_INTERVAL),
+    }
+}
+
+fn handle(backend: &BackendHandle, child: Descendant) {
+    let parent = lock().get(&child.parent()).cloned();
+    if let Some(follower) = parent {
+        match child.origin() {
+            ChildOrigin::Exec => reinject(backend, child.process(), follower),
+            _ => adopt(backend, &child, follower),
+        }
+    }
+
+    // Gated children stay suspended until resumed, followed or not.
+    let _ = backend.inner.resume(child.process(), None);
+}
+
+fn reinject(backend: &BackendHandle, process: Process, follower: Follower) {
+    // The exec replaced the image, taking the agent and the gating session
+    // with it.
+    let notifier = follower.options.notifier_value().cloned();
+    let label = follower.options.label_value().map(str::to_string);
+    if !follower.options.follow_exec_value() {
+        lock().remove(&process);
+        notify::emit(
+            notifier.as_ref(),
+            InjectionEventKind::AgentLost,
+            process,
+            label.as_deref(),
+        );
+        return;
+    }
+
+    let result = backend
+        .inject_library(process, &follower.library, &follower.options)
+        .and_then(|id| gate(backend, process, Follower { id, ..follower }));
+    match result {
+        Ok(()) => notify::emit(
+            notifier.as_ref(),
+            InjectionEventKind::Reinjected,
+            process,
+            label.as_deref(),
+        ),
+        Err(_) => {
+            lock().remove(&process);
+            notify::emit(
+                notifier.as_ref(),
+                InjectionEventKind::AgentLost,
+                process,
+                label.as_deref(),
+            );
+        }
+    }
+}
+
+fn adopt(backend: &BackendHandle, child: &Descendant, follower: Follower) {
+    let Some(filter) = follower.options.children_value() else {
+        return;
+    };
+    if !filter.accepts(child) {
+        return;
+    }
+
+    let process = child.process();
+    let notifier = follower.options.notifier_value().cloned();
+    let Ok(id) = backend.inject_library(process, &follower.library, &follower.options) else {
+        return;
+    };
+    notify::emit(
+        notifier.as_ref(),
+        InjectionEventKind::Injected,
+        process,
+        follower.options.label_value(),
+    );
+    // If gating fails the child's own descendants go unnoticed, but it is
+    // still recorded so stopping the root uninjects it.
+    let _ = backend.inner.enable_child_gating(process);
+    lock().insert(process, Follower { id, ..follower });
+}
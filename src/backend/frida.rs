@@ -1,9 +1,18 @@
-use std::ffi::{CStr, CString, OsStr};
+use std::ffi::{CStr, CString, OsStr, OsString};
 use std::os::raw::{c_char, c_int};
+use std::path::PathBuf;
 use std::ptr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
 
+use super::Engine;
+use crate::device::DeviceSpec;
+use crate::ios;
 use crate::library::LibrarySource;
-use crate::{Error, Library, Process, Program, Result, Stdio};
+use crate::{
+    ChildOrigin, Descendant, Device, DeviceKind, Error, InjectOptions, Library, Process, Program,
+    Result, Stdio,
+};
 
 #[repr(C)]
 struct HookFridaCtx {
@@ -13,7 +22,23 @@ struct HookFridaCtx {
 unsafe extern "C" {
     fn hook_frida_new(error_kind_out: *mut c_int, error_out: *mut *mut c_char)
     -> *mut HookFridaCtx;
+    fn hook_frida_new_remote(
+        address: *const c_char,
+        timeout_ms: u32,
+        error_kind_out: *mut c_int,
+        error_out: *mut *mut c_char,
+    ) -> *mut HookFridaCtx;
+    fn hook_frida_new_device(
+        id: *const c_char,
+        timeout_ms: u32,
+        error_kind_out: *mut c_int,
+        error_out: *mut *mut c_char,
+    ) -> *mut HookFridaCtx;
     fn hook_frida_free(ctx: *mut HookFridaCtx);
+    fn hook_frida_shutdown();
+    fn hook_frida_version() -> *const c_char;
+    #[cfg(feature = "dynamic-frida")]
+    fn hook_frida_load(path: *const c_char, error_out: *mut c_char, error_len: usize) -> c_int;
 
     fn hook_frida_inject_process(
         ctx: *mut HookFridaCtx,
@@ -21,6 +46,7 @@ unsafe extern "C" {
         library_path: *const c_char,
         entrypoint: *const c_char,
         data: *const c_char,
+        timeout_ms: u32,
         out_id: *mut u32,
         error_kind_out: *mut c_int,
         error_out: *mut *mut c_char,
@@ -33,6 +59,7 @@ unsafe extern "C" {
         blob_len: usize,
         entrypoint: *const c_char,
         data: *const c_char,
+        timeout_ms: u32,
         out_id: *mut u32,
         error_kind_out: *mut c_int,
         error_out: *mut *mut c_char,
@@ -48,6 +75,7 @@ unsafe extern "C" {
         library_path: *const c_char,
         entrypoint: *const c_char,
         data: *const c_char,
+        timeout_ms: u32,
         out_pid: *mut u32,
         out_id: *mut u32,
         error_kind_out: *mut c_int,
@@ -61,6 +89,7 @@ unsafe extern "C" {
         envp: *const *const c_char,
         cwd: *const c_char,
         stdio: i32,
+        timeout_ms: u32,
         out_pid: *mut u32,
         error_kind_out: *mut c_int,
         error_out: *mut *mut c_char,
@@ -69,6 +98,23 @@ unsafe extern "C" {
     fn hook_frida_resume(
         ctx: *mut HookFridaCtx,
         pid: u32,
+        timeout_ms: u32,
+        error_kind_out: *mut c_int,
+        error_out: *mut *mut c_char,
+    ) -> c_int;
+
+    fn hook_frida_kill(
+        ctx: *mut HookFridaCtx,
+        pid: u32,
+        error_kind_out: *mut c_int,
+        error_out: *mut *mut c_char,
+    ) -> c_int;
+
+    fn hook_frida_input(
+        ctx: *mut HookFridaCtx,
+        pid: u32,
+        data: *const u8,
+        data_len: usize,
         error_kind_out: *mut c_int,
         error_out: *mut *mut c_char,
     ) -> c_int;
@@ -79,10 +125,94 @@ unsafe extern "C" {
         error_kind_out: *mut c_int,
         error_out: *mut *mut c_char,
     ) -> c_int;
+
+    fn hook_frida_enable_child_gating(
+        ctx: *mut HookFridaCtx,
+        pid: u32,
+        error_kind_out: *mut c_int,
+        error_out: *mut *mut c_char,
+    ) -> c_int;
+
+    fn hook_frida_disable_child_gating(
+        ctx: *mut HookFridaCtx,
+        pid: u32,
+        error_kind_out: *mut c_int,
+        error_out: *mut *mut c_char,
+    ) -> c_int;
+
+    fn hook_frida_next_child(
+        ctx: *mut HookFridaCtx,
+        timeout_ms: u32,
+        out_pid: *mut u32,
+        out_parent_pid: *mut u32,
+        out_origin: *mut i32,
+        out_path: *mut *mut c_char,
+        error_kind_out: *mut c_int,
+        error_out: *mut *mut c_char,
+    ) -> c_int;
+
+    fn hook_frida_enable_spawn_gating(
+        ctx: *mut HookFridaCtx,
+        error_kind_out: *mut c_int,
+        error_out: *mut *mut c_char,
+    ) -> c_int;
+
+    fn hook_frida_disable_spawn_gating(
+        ctx: *mut HookFridaCtx,
+        error_kind_out: *mut c_int,
+        error_out: *mut *mut c_char,
+    ) -> c_int;
+
+    fn hook_frida_next_spawn(
+        ctx: *mut HookFridaCtx,
+        timeout_ms: u32,
+        out_pid: *mut u32,
+        out_identifier: *mut *mut c_char,
+        error_kind_out: *mut c_int,
+        error_out: *mut *mut c_char,
+    ) -> c_int;
+
+    fn hook_frida_next_uninjected(
+        ctx: *mut HookFridaCtx,
+        timeout_ms: u32,
+        out_id: *mut u32,
+        error_kind_out: *mut c_int,
+        error_out: *mut *mut c_char,
+    ) -> c_int;
+
+    fn hook_frida_device_os(
+        ctx: *mut HookFridaCtx,
+        out_os: *mut *mut c_char,
+        error_kind_out: *mut c_int,
+        error_out: *mut *mut c_char,
+    ) -> c_int;
+
+    fn hook_frida_find_process(
+        ctx: *mut HookFridaCtx,
+        pid: u32,
+        error_kind_out: *mut c_int,
+        error_out: *mut *mut c_char,
+    ) -> c_int;
+
+    fn hook_frida_enumerate_devices(
+        out_devices: *mut *mut HookFridaDevice,
+        out_len: *mut usize,
+        error_kind_out: *mut c_int,
+        error_out: *mut *mut c_char,
+    ) -> c_int;
+    fn hook_frida_devices_free(devices: *mut HookFridaDevice, len: usize);
     fn hook_frida_string_free(s: *mut c_char);
 }
 
+#[repr(C)]
+struct HookFridaDevice {
+    id: *mut c_char,
+    name: *mut c_char,
+    kind: i32,
+}
+
 pub(crate) fn init() -> Result<FridaBackend> {
+    load(None)?;
     unsafe {
         let mut err_ptr: *mut c_char = ptr::null_mut();
         let mut err_kind: c_int = HOOK_FRIDA_ERROR_NONE;
@@ -95,12 +225,205 @@ pub(crate) fn init() -> Result<FridaBackend> {
             return Err(Error::runtime_unavailable(msg));
         }
 
-        Ok(FridaBackend { ctx })
+        Ok(FridaBackend {
+            ctx,
+            device: 0,
+            os: String::new(),
+        })
+    }
+}
+
+// Remote devices are numbered from 1 as they are connected; their injection
+// ids are the server's shifted by the number, so they don't collide with
+// the local runtime's or each other's.
+static NEXT_DEVICE: AtomicU32 = AtomicU32::new(1);
+const DEVICE_ID_SHIFT: u32 = 40;
+// Ids of devices from here on would reach the preload backend's.
+const MAX_DEVICES: u32 = 1 << 8;
+
+// How long to wait for a device to be attached when the caller gives no
+// timeout; USB devices show up a moment after Frida starts looking.
+const DEVICE_WAIT: Duration = Duration::from_secs(5);
+
+/// Connect to the device `spec` names.
+pub(crate) fn init_device(spec: &DeviceSpec, timeout: Option<Duration>) -> Result<FridaBackend> {
+    load(None)?;
+    let c_name = match spec {
+        DeviceSpec::Remote(name) | DeviceSpec::Id(name) => Some(
+            CString::new(name.as_str())
+                .map_err(|_| Error::invalid_input("device id or address contains a NUL byte"))?,
+        ),
+        DeviceSpec::Usb => None,
+    };
+    let device = NEXT_DEVICE.fetch_add(1, Ordering::Relaxed);
+    if device >= MAX_DEVICES {
+        return Err(Error::runtime(format_args!(
+            "cannot connect to more than {} remote devices",
+            MAX_DEVICES - 1
+        )));
+    }
+    unsafe {
+        let mut err_ptr: *mut c_char = ptr::null_mut();
+        let mut err_kind: c_int = HOOK_FRIDA_ERROR_NONE;
+        let ctx = match (spec, &c_name) {
+            (DeviceSpec::Remote(_), Some(address)) => hook_frida_new_remote(
+                address.as_ptr(),
+                timeout_ms(timeout),
+                &mut err_kind as *mut c_int,
+                &mut err_ptr as *mut *mut c_char,
+            ),
+            _ => hook_frida_new_device(
+                c_name.as_ref().map_or(ptr::null(), |id| id.as_ptr()),
+                timeout_ms(Some(timeout.unwrap_or(DEVICE_WAIT))),
+                &mut err_kind as *mut c_int,
+                &mut err_ptr as *mut *mut c_char,
+            ),
+        };
+        if ctx.is_null() {
+            let msg = read_error(err_ptr);
+            return Err(match err_kind {
+                HOOK_FRIDA_ERROR_TIMED_OUT => {
+                    Error::timed_out(format_args!("connecting to {spec}: {msg}"))
+                }
+                _ => Error::runtime_unavailable(format_args!("connecting to {spec}: {msg}")),
+            });
+        }
+
+        let mut backend = FridaBackend {
+            ctx,
+            device,
+            os: String::new(),
+        };
+        // Best effort: a device that does not say gets no OS-specific
+        // handling.
+        backend.os = backend.query_os().unwrap_or_default();
+        Ok(backend)
     }
 }
 
+/// List the devices Frida's device manager knows of.
+pub(crate) fn devices() -> Result<Vec<Device>> {
+    load(None)?;
+    unsafe {
+        let mut list: *mut HookFridaDevice = ptr::null_mut();
+        let mut len = 0usize;
+        let mut err_ptr: *mut c_char = ptr::null_mut();
+        let mut err_kind: c_int = HOOK_FRIDA_ERROR_NONE;
+        let ok = hook_frida_enumerate_devices(
+            &mut list as *mut *mut HookFridaDevice,
+            &mut len as *mut usize,
+            &mut err_kind as *mut c_int,
+            &mut err_ptr as *mut *mut c_char,
+        );
+        if ok == 0 {
+            return Err(new_frida_error(err_kind, err_ptr, None));
+        }
+        let devices = std::slice::from_raw_parts(list, len)
+            .iter()
+            .map(|device| {
+                let kind = match device.kind {
+                    0 => DeviceKind::Local,
+                    2 => DeviceKind::Usb,
+                    _ => DeviceKind::Remote,
+                };
+                Device::new(c_string(device.id), c_string(device.name), kind)
+            })
+            .collect();
+        hook_frida_devices_free(list, len);
+        Ok(devices)
+    }
+}
+
+/// A copy of a C string from the shim; empty if NULL.
+unsafe fn c_string(s: *const c_char) -> String {
+    if s.is_null() {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(s) }.to_string_lossy().into_owned()
+}
+
+// Where `load` looks for frida-core when not given a path.
+#[cfg(feature = "dynamic-frida")]
+const LIBRARY_ENV: &str = "HOOK_INJECT_FRIDA_CORE";
+#[cfg(all(feature = "dynamic-frida", target_os = "macos"))]
+const DEFAULT_LIBRARY: &str = "libfrida-core.dylib";
+#[cfg(all(feature = "dynamic-frida", windows))]
+const DEFAULT_LIBRARY: &str = "frida-core.dll";
+#[cfg(all(feature = "dynamic-frida", not(any(target_os = "macos", windows))))]
+const DEFAULT_LIBRARY: &str = "libfrida-core.so";
+
+/// Load the frida-core shared library the shim calls into.
+///
+/// Without `path`, uses `HOOK_INJECT_FRIDA_CORE` or the system's library
+/// search path, and succeeds if a library is already loaded. An explicit
+/// `path` fails if a different one already is.
+#[cfg(feature = "dynamic-frida")]
+pub(super) fn load(path: Option<&std::path::Path>) -> Result<()> {
+    static LOADED: std::sync::Mutex<Option<PathBuf>> = std::sync::Mutex::new(None);
+    let mut loaded = LOADED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(existing) = &*loaded {
+        return match path {
+            Some(path) if path != existing => Err(Error::invalid_input(format_args!(
+                "frida-core is already loaded from {}",
+                existing.display()
+            ))),
+            _ => Ok(()),
+        };
+    }
+
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => std::env::var_os(LIBRARY_ENV)
+            .filter(|path| !path.is_empty())
+            .map_or_else(|| PathBuf::from(DEFAULT_LIBRARY), PathBuf::from),
+    };
+    let c_path = os_str_to_cstring(path.as_os_str(), "frida-core path")?;
+    let mut message = [0 as c_char; 512];
+    let ok = unsafe { hook_frida_load(c_path.as_ptr(), message.as_mut_ptr(), message.len()) };
+    if ok == 0 {
+        let message = unsafe { CStr::from_ptr(message.as_ptr()) };
+        return Err(Error::runtime_unavailable(message.to_string_lossy()));
+    }
+    *loaded = Some(path);
+    Ok(())
+}
+
+/// frida-core is linked in, so there is nothing to load.
+#[cfg(not(feature = "dynamic-frida"))]
+pub(super) fn load(_path: Option<&std::path::Path>) -> Result<()> {
+    Ok(())
+}
+
+/// Version of the linked frida-core, if it reports one.
+///
+/// With `dynamic-frida`, loads frida-core first and reports `None` if it
+/// cannot be loaded.
+pub(super) fn version() -> Option<String> {
+    load(None).ok()?;
+    let version = unsafe { hook_frida_version() };
+    if version.is_null() {
+        return None;
+    }
+    Some(
+        unsafe { CStr::from_ptr(version) }
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
+
+/// Stop Frida's threads once every `FridaBackend` has been dropped.
+pub(super) fn shutdown() {
+    unsafe { hook_frida_shutdown() }
+}
+
 pub(super) struct FridaBackend {
     ctx: *mut HookFridaCtx,
+    // The remote device the context reaches; 0 for this machine.
+    device: u32,
+    // The OS id the remote device reports, e.g. "ios"; empty if unknown.
+    os: String,
 }
 
 // Frida's injector context is used only through its C API, which is designed
@@ -119,45 +442,41 @@ impl Drop for FridaBackend {
     }
 }
 
-impl FridaBackend {
-    pub(super) fn inject_launch(
-        &self,
-        spec: &mut Program,
-        library: &Library,
-    ) -> Result<(Process, u64)> {
-        match library.source() {
-            LibrarySource::Path(_) => self.inject_launch_path(spec, library),
-            LibrarySource::Blob(_) => {
-                let process = self.spawn(spec)?;
-                let id = self.inject_blob(process, library)?;
-                self.resume(process)?;
-                Ok((process, id))
-            }
-        }
+impl Engine for FridaBackend {
+    fn name(&self) -> &'static str {
+        "frida"
     }
 
-    pub(super) fn inject_process(&self, process: Process, library: &Library) -> Result<u64> {
-        match library.source() {
-            LibrarySource::Path(_) => self.inject_process_path(process, library),
-            LibrarySource::Blob(_) => self.inject_blob(process, library),
-        }
+    fn spawns(&self) -> bool {
+        true
     }
 
-    fn inject_launch_path(&self, spec: &mut Program, library: &Library) -> Result<(Process, u64)> {
-        let program_path = spec.command().get_program();
-        let program = os_str_to_cstring(program_path, "program")?;
+    /// Spawn, inject and resume in one runtime call; path libraries only.
+    ///
+    /// Blobs are launched through `spawn` and `inject_process` instead, so a
+    /// failure part-way leaves a suspended handle to roll back.
+    fn inject_launch(
+        &self,
+        spec: &mut Program,
+        library: &Library,
+        timeout: Option<Duration>,
+    ) -> Result<(Process, u64)> {
+        let program_path = self.program_path(spec)?;
+        let program = os_str_to_cstring(&program_path, "program")?;
         let library_path = match library.source() {
             LibrarySource::Path(path) => os_str_to_cstring(path, "library_path")?,
-            LibrarySource::Blob(_) => {
+            LibrarySource::Device(path) => os_str_to_cstring(path, "library_path")?,
+            // Blobs, compressed or not.
+            _ => {
                 return Err(Error::invalid_input(
                     "library must be a file path for launch",
                 ));
             }
         };
         let entrypoint = library.entrypoint();
-        let data = library.data();
+        let data = library.entry_data()?;
 
-        let argv_storage = build_argv(spec, &program)?;
+        let argv_storage = build_argv(spec)?;
         let envp_storage = build_envp(spec)?;
         let cwd = spec
             .command()
@@ -181,6 +500,7 @@ impl FridaBackend {
                 library_path.as_ptr(),
                 entrypoint.as_ptr(),
                 data.as_ptr(),
+                timeout_ms(timeout),
                 &mut pid_out as *mut u32,
                 &mut id_out as *mut u32,
                 &mut err_kind as *mut c_int,
@@ -192,84 +512,66 @@ impl FridaBackend {
             return Err(new_frida_error(err_kind, err_ptr, None));
         }
 
-        let process = unsafe { Process::from_pid_unchecked(pid_out as i32) };
-        Ok((process, id_out as u64))
+        let process = self.on_device(pid_out);
+        Ok((process, self.injection_id(id_out)))
     }
 
-    fn inject_process_path(&self, process: Process, library: &Library) -> Result<u64> {
-        let library_path = match library.source() {
-            LibrarySource::Path(path) => os_str_to_cstring(path, "library_path")?,
-            LibrarySource::Blob(_) => {
-                return Err(Error::invalid_input("library must be a file path"));
-            }
-        };
-        let entrypoint = library.entrypoint();
-        let data = library.data();
-
-        let mut err_ptr: *mut c_char = ptr::null_mut();
-        let mut err_kind: c_int = HOOK_FRIDA_ERROR_NONE;
-        let mut id_out: u32 = 0;
+    fn inject_process(&self, process: Process, library: &Library) -> Result<u64> {
+        self.inject(process, library, None)
+    }
 
-        let ok = unsafe {
-            hook_frida_inject_process(
-                self.ctx,
-                process.pid(),
-                library_path.as_ptr(),
-                entrypoint.as_ptr(),
-                data.as_ptr(),
-                &mut id_out as *mut u32,
-                &mut err_kind as *mut c_int,
-                &mut err_ptr as *mut *mut c_char,
-            )
-        };
+    fn inject_process_within(
+        &self,
+        process: Process,
+        library: &Library,
+        timeout: Duration,
+    ) -> Result<u64> {
+        self.inject(process, library, Some(timeout))
+    }
 
-        if ok <= 0 {
-            return Err(new_frida_error(err_kind, err_ptr, None));
+    fn prepare_library(
+        &self,
+        library: &Library,
+        options: &InjectOptions,
+    ) -> Result<Option<Library>> {
+        if self.os != ios::OS_ID {
+            return Ok(None);
         }
-
-        Ok(id_out as u64)
+        ios::stage_dylib(library, options).map(Some)
     }
 
-    fn inject_blob(&self, process: Process, library: &Library) -> Result<u64> {
-        let bytes = match library.source() {
-            LibrarySource::Blob(bytes) => bytes,
-            LibrarySource::Path(_) => {
-                return Err(Error::invalid_input("library is not a blob"));
-            }
-        };
-        let entrypoint = library.entrypoint();
-        let data = library.data();
+    fn remote(&self) -> bool {
+        self.device != 0
+    }
 
+    fn find_process(&self, pid: i32) -> Result<Process> {
+        if !self.remote() {
+            return Process::from_pid(pid);
+        }
+        if pid <= 0 {
+            return Err(Error::invalid_input("pid must be > 0"));
+        }
         let mut err_ptr: *mut c_char = ptr::null_mut();
         let mut err_kind: c_int = HOOK_FRIDA_ERROR_NONE;
-        let mut id_out: u32 = 0;
-
         let ok = unsafe {
-            hook_frida_inject_blob(
+            hook_frida_find_process(
                 self.ctx,
-                process.pid(),
-                bytes.as_ptr(),
-                bytes.len(),
-                entrypoint.as_ptr(),
-                data.as_ptr(),
-                &mut id_out as *mut u32,
+                pid as u32,
                 &mut err_kind as *mut c_int,
                 &mut err_ptr as *mut *mut c_char,
             )
         };
-
         if ok <= 0 {
-            return Err(new_frida_error(err_kind, err_ptr, None));
+            return Err(new_frida_error(err_kind, err_ptr, Some(pid)));
         }
-
-        Ok(id_out as u64)
+        Ok(self.on_device(pid as u32))
     }
 
-    pub(super) fn spawn(&self, spec: &mut Program) -> Result<Process> {
-        let program_path = spec.command().get_program();
-        let program = os_str_to_cstring(program_path, "program path")?;
+    fn spawn(&self, spec: &mut Program, timeout: Option<Duration>) -> Result<Process> {
+        let program_path = self.program_path(spec)?;
+        let program = os_str_to_cstring(&program_path, "program path")?;
 
-        let argv_storage = build_argv(spec, &program)?;
+        let argv_storage = build_argv(spec)?;
         let envp_storage = build_envp(spec)?;
         let cwd = spec
             .command()
@@ -292,6 +594,7 @@ impl FridaBackend {
                 envp_storage.ptrs.as_ptr(),
                 cwd.as_ref().map(|s| s.as_ptr()).unwrap_or(ptr::null()),
                 map_stdio(spec.stdio_value()),
+                timeout_ms(timeout),
                 &mut pid_out as *mut u32,
                 &mut err_kind as *mut c_int,
                 &mut err_ptr as *mut *mut c_char,
@@ -299,18 +602,36 @@ impl FridaBackend {
         };
 
         if ok <= 0 {
-            return Err(new_frida_error(err_kind, err_ptr, None));
+            return Err(self.explain(new_frida_error(err_kind, err_ptr, None)));
         }
 
-        let process = unsafe { Process::from_pid_unchecked(pid_out as i32) };
+        let process = self.on_device(pid_out);
         Ok(process)
     }
 
-    pub(super) fn resume(&self, process: Process) -> Result<()> {
+    fn resume(&self, process: Process, timeout: Option<Duration>) -> Result<()> {
         let mut err_ptr: *mut c_char = ptr::null_mut();
         let mut err_kind: c_int = HOOK_FRIDA_ERROR_NONE;
         let ok = unsafe {
             hook_frida_resume(
+                self.ctx,
+                process.pid() as u32,
+                timeout_ms(timeout),
+                &mut err_kind as *mut c_int,
+                &mut err_ptr as *mut *mut c_char,
+            )
+        };
+        if ok <= 0 {
+            return Err(self.explain(new_frida_error(err_kind, err_ptr, Some(process.pid()))));
+        }
+        Ok(())
+    }
+
+    fn kill(&self, process: Process) -> Result<()> {
+        let mut err_ptr: *mut c_char = ptr::null_mut();
+        let mut err_kind: c_int = HOOK_FRIDA_ERROR_NONE;
+        let ok = unsafe {
+            hook_frida_kill(
                 self.ctx,
                 process.pid() as u32,
                 &mut err_kind as *mut c_int,
@@ -323,7 +644,26 @@ impl FridaBackend {
         Ok(())
     }
 
-    pub(super) fn uninject(&self, id: u64) -> Result<()> {
+    fn input(&self, process: Process, data: &[u8]) -> Result<()> {
+        let mut err_ptr: *mut c_char = ptr::null_mut();
+        let mut err_kind: c_int = HOOK_FRIDA_ERROR_NONE;
+        let ok = unsafe {
+            hook_frida_input(
+                self.ctx,
+                process.pid() as u32,
+                data.as_ptr(),
+                data.len(),
+                &mut err_kind as *mut c_int,
+                &mut err_ptr as *mut *mut c_char,
+            )
+        };
+        if ok <= 0 {
+            return Err(new_frida_error(err_kind, err_ptr, Some(process.pid())));
+        }
+        Ok(())
+    }
+
+    fn uninject(&self, id: u64) -> Result<()> {
         if id == 0 {
             return Ok(());
         }
@@ -333,6 +673,7 @@ impl FridaBackend {
         let ok = unsafe {
             hook_frida_demonitor(
                 self.ctx,
+                // The runtime's own id, without the device.
                 id as u32,
                 &mut err_kind as *mut c_int,
                 &mut err_ptr as *mut *mut c_char,
@@ -343,6 +684,322 @@ impl FridaBackend {
         }
         Ok(())
     }
+
+    fn enable_child_gating(&self, process: Process) -> Result<()> {
+        let mut err_ptr: *mut c_char = ptr::null_mut();
+        let mut err_kind: c_int = HOOK_FRIDA_ERROR_NONE;
+        let ok = unsafe {
+            hook_frida_enable_child_gating(
+                self.ctx,
+                process.pid() as u32,
+                &mut err_kind as *mut c_int,
+                &mut err_ptr as *mut *mut c_char,
+            )
+        };
+        if ok <= 0 {
+            return Err(new_frida_error(err_kind, err_ptr, Some(process.pid())));
+        }
+        Ok(())
+    }
+
+    fn disable_child_gating(&self, process: Process) -> Result<()> {
+        let mut err_ptr: *mut c_char = ptr::null_mut();
+        let mut err_kind: c_int = HOOK_FRIDA_ERROR_NONE;
+        let ok = unsafe {
+            hook_frida_disable_child_gating(
+                self.ctx,
+                process.pid() as u32,
+                &mut err_kind as *mut c_int,
+                &mut err_ptr as *mut *mut c_char,
+            )
+        };
+        if ok <= 0 {
+            return Err(new_frida_error(err_kind, err_ptr, Some(process.pid())));
+        }
+        Ok(())
+    }
+
+    fn next_child(&self, timeout: Duration) -> Result<Option<Descendant>> {
+        let mut err_ptr: *mut c_char = ptr::null_mut();
+        let mut err_kind: c_int = HOOK_FRIDA_ERROR_NONE;
+        let mut pid_out: u32 = 0;
+        let mut parent_out: u32 = 0;
+        let mut origin_out: i32 = 0;
+        let mut path_out: *mut c_char = ptr::null_mut();
+        let ok = unsafe {
+            hook_frida_next_child(
+                self.ctx,
+                timeout_ms(Some(timeout)),
+                &mut pid_out as *mut u32,
+                &mut parent_out as *mut u32,
+                &mut origin_out as *mut i32,
+                &mut path_out as *mut *mut c_char,
+                &mut err_kind as *mut c_int,
+                &mut err_ptr as *mut *mut c_char,
+            )
+        };
+        if ok <= 0 {
+            return Err(new_frida_error(err_kind, err_ptr, None));
+        }
+        if pid_out == 0 {
+            return Ok(None);
+        }
+
+        let path = take_string(path_out).map(PathBuf::from);
+        let origin = match origin_out {
+            0 => ChildOrigin::Fork,
+            1 => ChildOrigin::Exec,
+            _ => ChildOrigin::Spawn,
+        };
+        Ok(Some(Descendant::new(
+            self.on_device(pid_out),
+            self.on_device(parent_out),
+            origin,
+            path,
+        )))
+    }
+
+    /// Wait up to `timeout` for an injection to be reported as unloaded.
+    fn next_uninjected(&self, timeout: Duration) -> Result<Option<u64>> {
+        let mut err_ptr: *mut c_char = ptr::null_mut();
+        let mut err_kind: c_int = HOOK_FRIDA_ERROR_NONE;
+        let mut id_out: u32 = 0;
+        let ok = unsafe {
+            hook_frida_next_uninjected(
+                self.ctx,
+                timeout_ms(Some(timeout)),
+                &mut id_out as *mut u32,
+                &mut err_kind as *mut c_int,
+                &mut err_ptr as *mut *mut c_char,
+            )
+        };
+        if ok <= 0 {
+            return Err(new_frida_error(err_kind, err_ptr, None));
+        }
+        Ok((id_out != 0).then_some(self.injection_id(id_out)))
+    }
+
+    fn enable_spawn_gating(&self) -> Result<()> {
+        let mut err_ptr: *mut c_char = ptr::null_mut();
+        let mut err_kind: c_int = HOOK_FRIDA_ERROR_NONE;
+        let ok = unsafe {
+            hook_frida_enable_spawn_gating(
+                self.ctx,
+                &mut err_kind as *mut c_int,
+                &mut err_ptr as *mut *mut c_char,
+            )
+        };
+        if ok <= 0 {
+            return Err(new_frida_error(err_kind, err_ptr, None));
+        }
+        Ok(())
+    }
+
+    fn disable_spawn_gating(&self) -> Result<()> {
+        let mut err_ptr: *mut c_char = ptr::null_mut();
+        let mut err_kind: c_int = HOOK_FRIDA_ERROR_NONE;
+        let ok = unsafe {
+            hook_frida_disable_spawn_gating(
+                self.ctx,
+                &mut err_kind as *mut c_int,
+                &mut err_ptr as *mut *mut c_char,
+            )
+        };
+        if ok <= 0 {
+            return Err(new_frida_error(err_kind, err_ptr, None));
+        }
+        Ok(())
+    }
+
+    /// Wait for a gated spawn, returning its process and program identifier.
+    fn next_spawn(&self, timeout: Duration) -> Result<Option<(Process, String)>> {
+        let mut err_ptr: *mut c_char = ptr::null_mut();
+        let mut err_kind: c_int = HOOK_FRIDA_ERROR_NONE;
+        let mut pid_out: u32 = 0;
+        let mut identifier_out: *mut c_char = ptr::null_mut();
+        let ok = unsafe {
+            hook_frida_next_spawn(
+                self.ctx,
+                timeout_ms(Some(timeout)),
+                &mut pid_out as *mut u32,
+                &mut identifier_out as *mut *mut c_char,
+                &mut err_kind as *mut c_int,
+                &mut err_ptr as *mut *mut c_char,
+            )
+        };
+        if ok <= 0 {
+            return Err(new_frida_error(err_kind, err_ptr, None));
+        }
+        if pid_out == 0 {
+            return Ok(None);
+        }
+
+        let process = self.on_device(pid_out);
+        Ok(Some((
+            process,
+            take_string(identifier_out).unwrap_or_default(),
+        )))
+    }
+}
+
+impl FridaBackend {
+    fn inject(
+        &self,
+        process: Process,
+        library: &Library,
+        timeout: Option<Duration>,
+    ) -> Result<u64> {
+        self.inject_library(process, library, timeout)
+            .map_err(|err| self.explain(err))
+    }
+
+    fn inject_library(
+        &self,
+        process: Process,
+        library: &Library,
+        timeout: Option<Duration>,
+    ) -> Result<u64> {
+        match library.source() {
+            // The path is on this machine; the server is sent the image.
+            LibrarySource::Path(path) if self.remote() => {
+                self.inject_blob(process, &std::fs::read(path)?, library, timeout)
+            }
+            LibrarySource::Path(_) | LibrarySource::Device(_) => {
+                self.inject_process_path(process, library, timeout)
+            }
+            LibrarySource::Blob(bytes) => self.inject_blob(process, bytes, library, timeout),
+            #[cfg(feature = "compression")]
+            LibrarySource::Compressed(_) => {
+                self.inject_library(process, &library.decompressed()?, timeout)
+            }
+        }
+    }
+
+    /// `err` with the reason behind it, for what iOS refuses.
+    fn explain(&self, err: Error) -> Error {
+        if self.os == ios::OS_ID {
+            ios::explain(err)
+        } else {
+            err
+        }
+    }
+
+    /// The id of the OS the device runs.
+    fn query_os(&self) -> Result<String> {
+        let mut os_ptr: *mut c_char = ptr::null_mut();
+        let mut err_ptr: *mut c_char = ptr::null_mut();
+        let mut err_kind: c_int = HOOK_FRIDA_ERROR_NONE;
+        let ok = unsafe {
+            hook_frida_device_os(
+                self.ctx,
+                &mut os_ptr as *mut *mut c_char,
+                &mut err_kind as *mut c_int,
+                &mut err_ptr as *mut *mut c_char,
+            )
+        };
+        if ok <= 0 {
+            return Err(new_frida_error(err_kind, err_ptr, None));
+        }
+        Ok(take_string(os_ptr).unwrap_or_default())
+    }
+
+    /// `pid` on the device the runtime reaches.
+    fn on_device(&self, pid: u32) -> Process {
+        unsafe { Process::from_pid_unchecked(pid as i32) }.on_device(self.device)
+    }
+
+    /// The crate's id for the runtime's injection `id`.
+    fn injection_id(&self, id: u32) -> u64 {
+        u64::from(self.device) << DEVICE_ID_SHIFT | u64::from(id)
+    }
+
+    /// The program to launch: resolved against `PATH` here, or as given for
+    /// a remote device, whose paths this machine knows nothing about.
+    fn program_path(&self, spec: &Program) -> Result<PathBuf> {
+        if self.remote() {
+            return Ok(PathBuf::from(spec.command().get_program()));
+        }
+        spec.resolved_program()
+    }
+
+    fn inject_process_path(
+        &self,
+        process: Process,
+        library: &Library,
+        timeout: Option<Duration>,
+    ) -> Result<u64> {
+        let library_path = match library.source() {
+            LibrarySource::Path(path) => os_str_to_cstring(path, "library_path")?,
+            LibrarySource::Device(path) => os_str_to_cstring(path, "library_path")?,
+            // Blobs, compressed or not.
+            _ => {
+                return Err(Error::invalid_input("library must be a file path"));
+            }
+        };
+        let entrypoint = library.entrypoint();
+        let data = library.entry_data()?;
+
+        let mut err_ptr: *mut c_char = ptr::null_mut();
+        let mut err_kind: c_int = HOOK_FRIDA_ERROR_NONE;
+        let mut id_out: u32 = 0;
+
+        let ok = unsafe {
+            hook_frida_inject_process(
+                self.ctx,
+                process.pid(),
+                library_path.as_ptr(),
+                entrypoint.as_ptr(),
+                data.as_ptr(),
+                timeout_ms(timeout),
+                &mut id_out as *mut u32,
+                &mut err_kind as *mut c_int,
+                &mut err_ptr as *mut *mut c_char,
+            )
+        };
+
+        if ok <= 0 {
+            return Err(new_frida_error(err_kind, err_ptr, None));
+        }
+
+        Ok(self.injection_id(id_out))
+    }
+
+    /// Inject `bytes`, the image of `library`.
+    fn inject_blob(
+        &self,
+        process: Process,
+        bytes: &[u8],
+        library: &Library,
+        timeout: Option<Duration>,
+    ) -> Result<u64> {
+        let entrypoint = library.entrypoint();
+        let data = library.entry_data()?;
+
+        let mut err_ptr: *mut c_char = ptr::null_mut();
+        let mut err_kind: c_int = HOOK_FRIDA_ERROR_NONE;
+        let mut id_out: u32 = 0;
+
+        let ok = unsafe {
+            hook_frida_inject_blob(
+                self.ctx,
+                process.pid(),
+                bytes.as_ptr(),
+                bytes.len(),
+                entrypoint.as_ptr(),
+                data.as_ptr(),
+                timeout_ms(timeout),
+                &mut id_out as *mut u32,
+                &mut err_kind as *mut c_int,
+                &mut err_ptr as *mut *mut c_char,
+            )
+        };
+
+        if ok <= 0 {
+            return Err(new_frida_error(err_kind, err_ptr, None));
+        }
+
+        Ok(self.injection_id(id_out))
+    }
 }
 
 struct CArgv {
@@ -355,10 +1012,11 @@ struct CEnvp {
     ptrs: Vec<*const c_char>,
 }
 
-fn build_argv(spec: &Program, program: &CString) -> Result<CArgv> {
+fn build_argv(spec: &Program) -> Result<CArgv> {
     // Frida expects a NULL-terminated argv array; keep owned CStrings alive.
+    // argv[0] keeps the name as given (like `Command`), not the PATH-resolved path.
     let mut cstrings = Vec::new();
-    cstrings.push(program.clone());
+    cstrings.push(os_str_to_cstring(spec.command().get_program(), "program")?);
     for arg in spec.command().get_args() {
         let s = CString::new(arg.to_string_lossy().as_bytes())
             .map_err(|_| Error::invalid_input("arg contains NUL"))?;
@@ -375,24 +1033,27 @@ fn build_argv(spec: &Program, program: &CString) -> Result<CArgv> {
 }
 
 fn build_envp(spec: &Program) -> Result<CEnvp> {
-    // Frida expects envp entries as KEY=VALUE strings, NULL-terminated.
-    let mut cstrings = Vec::new();
-    for (k, v) in spec.command().get_envs() {
-        if let Some(v) = v {
-            let mut kv = k.to_string_lossy().into_owned();
-            kv.push('=');
-            kv.push_str(&v.to_string_lossy());
-            let s = CString::new(kv.as_bytes())
-                .map_err(|_| Error::invalid_input("env contains NUL"))?;
-            cstrings.push(s);
+    // Frida replaces the child's environment with envp (KEY=VALUE strings,
+    // NULL-terminated), so start from ours unless inheritance is disabled.
+    let mut vars: Vec<(OsString, OsString)> = if spec.inherits_env() {
+        std::env::vars_os().collect()
+    } else {
+        Vec::new()
+    };
+
+    for (key, value) in spec.command().get_envs() {
+        vars.retain(|(existing, _)| !env_key_eq(existing, key));
+        if let Some(value) = value {
+            vars.push((key.to_os_string(), value.to_os_string()));
         }
     }
 
-    if cstrings.is_empty() {
-        return Ok(CEnvp {
-            _cstrings: Vec::new(),
-            ptrs: vec![ptr::null()],
-        });
+    let mut cstrings = Vec::with_capacity(vars.len());
+    for (key, value) in vars {
+        let mut kv = key;
+        kv.push("=");
+        kv.push(value);
+        cstrings.push(os_str_to_cstring(&kv, "env")?);
     }
 
     let mut ptrs: Vec<*const c_char> = cstrings.iter().map(|s| s.as_ptr()).collect();
@@ -404,6 +1065,15 @@ fn build_envp(spec: &Program) -> Result<CEnvp> {
     })
 }
 
+fn env_key_eq(a: &OsStr, b: &OsStr) -> bool {
+    // Environment variable names are case-insensitive on Windows.
+    if cfg!(windows) {
+        a.eq_ignore_ascii_case(b)
+    } else {
+        a == b
+    }
+}
+
 fn map_stdio(stdio: Stdio) -> i32 {
     match stdio {
         Stdio::Inherit => 0,
@@ -411,6 +1081,15 @@ fn map_stdio(stdio: Stdio) -> i32 {
         Stdio::Pipe => 2,
     }
 }
+
+// The shim treats 0 as "no timeout", so a zero duration becomes 1ms.
+fn timeout_ms(timeout: Option<Duration>) -> u32 {
+    match timeout {
+        Some(timeout) => timeout.as_millis().clamp(1, u32::MAX as u128) as u32,
+        None => 0,
+    }
+}
+
 fn new_frida_error(err_kind: c_int, err_ptr: *mut c_char, pid: Option<i32>) -> Error {
     let msg = read_error(err_ptr);
     map_frida_error(err_kind, msg, pid)
@@ -424,6 +1103,7 @@ const HOOK_FRIDA_ERROR_PERMISSION_DENIED: c_int = 3;
 const HOOK_FRIDA_ERROR_PROCESS_NOT_FOUND: c_int = 4;
 #[allow(dead_code)]
 const HOOK_FRIDA_ERROR_RUNTIME: c_int = 5;
+const HOOK_FRIDA_ERROR_TIMED_OUT: c_int = 6;
 
 fn map_frida_error(kind: c_int, msg: String, pid: Option<i32>) -> Error {
     // Map Frida error kinds into the public Rust error surface.
@@ -431,6 +1111,7 @@ fn map_frida_error(kind: c_int, msg: String, pid: Option<i32>) -> Error {
         HOOK_FRIDA_ERROR_INVALID_ARGUMENT => Error::invalid_input(msg),
         HOOK_FRIDA_ERROR_NOT_SUPPORTED => Error::not_supported(msg),
         HOOK_FRIDA_ERROR_PERMISSION_DENIED => Error::permission_denied(msg),
+        HOOK_FRIDA_ERROR_TIMED_OUT => Error::timed_out(msg),
         HOOK_FRIDA_ERROR_PROCESS_NOT_FOUND => {
             if let Some(pid) = pid {
                 Error::process_not_found(pid)
@@ -443,14 +1124,19 @@ fn map_frida_error(kind: c_int, msg: String, pid: Option<i32>) -> Error {
 }
 
 fn read_error(ptr: *mut c_char) -> String {
+    take_string(ptr).unwrap_or_else(|| "unknown error".to_string())
+}
+
+// Copy and free a string allocated by the shim.
+fn take_string(ptr: *mut c_char) -> Option<String> {
     if ptr.is_null() {
-        return "unknown error".to_string();
+        return None;
     }
 
     unsafe {
-        let msg = CStr::from_ptr(ptr).to_string_lossy().into_owned();
+        let value = CStr::from_ptr(ptr).to_string_lossy().into_owned();
         hook_frida_string_free(ptr);
-        msg
+        Some(value)
     }
 }
 
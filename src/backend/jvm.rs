@@ -0,0 +1,296 @@
+// Loading agents into running JVMs through HotSpot's attach mechanism, for
+// libraries with `JvmAttach` set.
+//
+// A JVM starts its attach listener when it finds an `.attach_pid<pid>` file
+// in its working or temp directory on `SIGQUIT`; the listener is a Unix
+// socket, `.java_pid<pid>` in the temp directory. A `load` command sent
+// there has the JVM load the library itself and call its `Agent_OnAttach`
+// with the library's data as options, so nothing runs under its feet. JVM
+// agents cannot be unloaded; the injection ends when the JVM exits.
+
+use crate::{Error, InjectOptions, JvmAttach, Library, Process, Result};
+
+// Above the other backends' ids, so injections of all of them can share
+// the registry and a JVM attach is told apart by its id alone.
+const FIRST_ID: u64 = 1 << 56;
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+static NEXT_ID: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(FIRST_ID);
+
+/// Whether `library` goes into `process` through the attach API.
+///
+/// Fails with `not_supported` for `JvmAttach::Always` if `process` is not
+/// running a JVM.
+pub(super) fn wants_attach(process: Process, library: &Library) -> Result<bool> {
+    match library.jvm_attach() {
+        JvmAttach::Never => Ok(false),
+        JvmAttach::Auto => Ok(is_jvm(process)),
+        JvmAttach::Always if is_jvm(process) => Ok(true),
+        JvmAttach::Always => Err(Error::not_supported(format_args!(
+            "pid {} is not running a JVM to attach to",
+            process.pid()
+        ))),
+    }
+}
+
+/// Whether injection `id` was made through the attach API.
+pub(super) fn is_attach(id: u64) -> bool {
+    id >= FIRST_ID
+}
+
+/// Whether `pid` has the JVM's library mapped; a process held before it
+/// has loaded one doesn't count.
+fn is_jvm(process: Process) -> bool {
+    const JVM_LIBRARIES: &[&str] = &["libjvm.so", "libjvm.dylib", "jvm.dll"];
+
+    process.memory_maps().is_ok_and(|regions| {
+        regions.iter().any(|region| {
+            region
+                .path()
+                .and_then(|path| path.file_name())
+                .is_some_and(|name| JVM_LIBRARIES.iter().any(|jvm| name == *jvm))
+        })
+    })
+}
+
+/// Have the JVM in `process` load `library` as an agent, returning the
+/// injection id.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub(super) fn attach(process: Process, library: &Library, options: &InjectOptions) -> Result<u64> {
+    use std::io::{Read, Write};
+    use std::sync::atomic::Ordering;
+
+    use crate::library::LibrarySource;
+
+    let LibrarySource::Path(path) = library.source() else {
+        return Err(Error::not_supported("the JVM loads agents from files"));
+    };
+    if library.data_bytes().is_some() {
+        return Err(Error::not_supported(
+            "JVM agents take their options as a string, not binary data",
+        ));
+    }
+    if options.follow_exec_value() {
+        return Err(Error::not_supported(
+            "following exec is not supported when attaching to a JVM",
+        ));
+    }
+    library.check_export(c"Agent_OnAttach")?;
+    let path = std::path::absolute(path)?;
+    let path = path.to_str().ok_or_else(|| {
+        Error::invalid_input(format_args!(
+            "the JVM cannot load {}: path is not valid UTF-8",
+            path.display()
+        ))
+    })?;
+    let data = library.data().to_str().map_err(|_| {
+        Error::invalid_input("JVM agent options (the library's data) must be valid UTF-8")
+    })?;
+
+    let pid = process.pid();
+    let timeout = options.timeout_value().unwrap_or(listener::WAIT);
+    let mut socket = listener::connect(pid, timeout, options.clock_value())?;
+    socket.set_read_timeout(Some(timeout))?;
+    // Protocol version 1: the command and three arguments, NUL-terminated.
+    let mut request = Vec::new();
+    for part in ["1", "load", path, "true", data] {
+        request.extend_from_slice(part.as_bytes());
+        request.push(0);
+    }
+    socket.write_all(&request)?;
+    let mut response = String::new();
+    socket.read_to_string(&mut response).map_err(|err| {
+        if matches!(
+            err.kind(),
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+        ) {
+            Error::timed_out(format_args!(
+                "the JVM in pid {pid} did not answer within {timeout:?}"
+            ))
+        } else {
+            Error::from(err)
+        }
+    })?;
+    check_response(pid, &response)?;
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let start_time = crate::process::start_time(pid);
+    crate::process::on_exit(pid, start_time, move || super::unload::unloaded(id))?;
+    Ok(id)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub(super) fn attach(
+    _process: Process,
+    _library: &Library,
+    _options: &InjectOptions,
+) -> Result<u64> {
+    Err(Error::not_supported(
+        "attaching to a JVM is only supported on Linux and macOS",
+    ))
+}
+
+/// Check the listener's reply to `load`: the command's status on the first
+/// line, then what `Agent_OnAttach` returned (`return code: <n>` on newer
+/// JVMs, the bare number on older ones).
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn check_response(pid: i32, response: &str) -> Result<()> {
+    let mut lines = response.lines().map(str::trim);
+    let status = lines.next().unwrap_or_default();
+    if status != "0" {
+        let detail: Vec<_> = lines.filter(|line| !line.is_empty()).collect();
+        return Err(Error::runtime(format_args!(
+            "the JVM in pid {pid} failed to load the agent (status {status}): {}",
+            detail.join("; ")
+        )));
+    }
+    let returned = lines
+        .next()
+        .map(|line| line.strip_prefix("return code:").unwrap_or(line).trim())
+        .unwrap_or("0");
+    if returned != "0" {
+        return Err(Error::runtime(format_args!(
+            "Agent_OnAttach failed in pid {pid} with {returned}"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+mod listener {
+    use std::os::unix::net::UnixStream;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use crate::clock::poll_until;
+    use crate::{Clock, Error, Result};
+
+    // How long the JVM gets to start its listener and answer, unless the
+    // call sets a timeout.
+    pub(super) const WAIT: Duration = Duration::from_secs(10);
+    const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+    /// Connect to the attach listener of the JVM in `pid`, starting it if
+    /// needed and waiting for it by `clock`.
+    pub(super) fn connect(pid: i32, timeout: Duration, clock: &dyn Clock) -> Result<UnixStream> {
+        let vm_pid = vm_pid(pid);
+        let socket = temp_dir(pid).join(format!(".java_pid{vm_pid}"));
+        if !socket.exists() {
+            start(pid, vm_pid, &socket, timeout, clock)?;
+        }
+        UnixStream::connect(&socket).map_err(Error::from)
+    }
+
+    /// Ask the JVM to start its listener, and wait for the socket.
+    fn start(
+        pid: i32,
+        vm_pid: i32,
+        socket: &Path,
+        timeout: Duration,
+        clock: &dyn Clock,
+    ) -> Result<()> {
+        let name = format!(".attach_pid{vm_pid}");
+        // The JVM looks in its working directory first.
+        let trigger = [cwd(pid).join(&name), temp_dir(pid).join(&name)]
+            .into_iter()
+            .find(|path| std::fs::File::create(path).is_ok())
+            .ok_or_else(|| {
+                Error::permission_denied(format_args!(
+                    "cannot create {name} where the JVM in pid {pid} looks for it"
+                ))
+            })?;
+
+        let started = signal_and_wait(pid, socket, timeout, clock);
+        let _ = std::fs::remove_file(&trigger);
+        started
+    }
+
+    fn signal_and_wait(
+        pid: i32,
+        socket: &Path,
+        timeout: Duration,
+        clock: &dyn Clock,
+    ) -> Result<()> {
+        if unsafe { libc::kill(pid, libc::SIGQUIT) } == -1 {
+            return Err(match std::io::Error::last_os_error() {
+                err if err.raw_os_error() == Some(libc::ESRCH) => Error::process_not_found(pid),
+                err => Error::from(err),
+            });
+        }
+        let started = poll_until(clock, Some(timeout), POLL_INTERVAL, || {
+            Ok(socket.exists().then_some(()))
+        })?;
+        started.ok_or_else(|| {
+            Error::timed_out(format_args!(
+                "the JVM in pid {pid} did not start its attach listener within {timeout:?}; it \
+                 may run with -XX:+DisableAttachMechanism"
+            ))
+        })
+    }
+
+    /// The pid the JVM knows itself by, in its own pid namespace.
+    #[cfg(target_os = "linux")]
+    fn vm_pid(pid: i32) -> i32 {
+        std::fs::read_to_string(format!("/proc/{pid}/status"))
+            .ok()
+            .and_then(|status| {
+                let line = status.lines().find(|line| line.starts_with("NSpid:"))?;
+                line.split_whitespace().last()?.parse().ok()
+            })
+            .unwrap_or(pid)
+    }
+
+    #[cfg(target_os = "macos")]
+    fn vm_pid(pid: i32) -> i32 {
+        pid
+    }
+
+    /// The JVM's temp directory, seen from here.
+    #[cfg(target_os = "linux")]
+    fn temp_dir(pid: i32) -> PathBuf {
+        // Through its root, so JVMs in containers are reached.
+        PathBuf::from(format!("/proc/{pid}/root/tmp"))
+    }
+
+    #[cfg(target_os = "macos")]
+    fn temp_dir(_pid: i32) -> PathBuf {
+        // The per-user temp directory, shared with JVMs of the same user.
+        let mut buf = [0u8; libc::PATH_MAX as usize];
+        let len = unsafe {
+            libc::confstr(
+                libc::_CS_DARWIN_USER_TEMP_DIR,
+                buf.as_mut_ptr().cast(),
+                buf.len(),
+            )
+        };
+        if len == 0 || len > buf.len() {
+            return PathBuf::from("/tmp");
+        }
+        PathBuf::from(String::from_utf8_lossy(&buf[..len - 1]).into_owned())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn cwd(pid: i32) -> PathBuf {
+        PathBuf::from(format!("/proc/{pid}/cwd"))
+    }
+
+    #[cfg(target_os = "macos")]
+    fn cwd(pid: i32) -> PathBuf {
+        let mut info: libc::proc_vnodepathinfo = unsafe { std::mem::zeroed() };
+        let size = std::mem::size_of::<libc::proc_vnodepathinfo>() as i32;
+        let read = unsafe {
+            libc::proc_pidinfo(
+                pid,
+                libc::PROC_PIDVNODEPATHINFO,
+                0,
+                (&mut info as *mut libc::proc_vnodepathinfo).cast(),
+                size,
+            )
+        };
+        if read != size {
+            return PathBuf::from("/");
+        }
+        let path = unsafe { std::ffi::CStr::from_ptr(info.pvi_cdir.vip_path.as_ptr().cast()) };
+        PathBuf::from(path.to_string_lossy().into_owned())
+    }
+}
@@ -0,0 +1,695 @@
+// Native Windows injection for `Backend::LoadLibrary`.
+//
+// The library's path is written into memory allocated in the target, and a
+// thread started there with `CreateRemoteThread` runs `LoadLibraryW` on it.
+// Once that returns, the library's base is found among the target's modules
+// and a second thread runs the trampoline (see `native`) on the entrypoint,
+// with `FreeLibrary` to unload. kernel32 is mapped at the same address in
+// every process of an architecture, so the addresses of its functions here
+// are theirs in the target too.
+//
+// A program held before its loader has run is initialized by the first of
+// these threads, which maps kernel32 on the way to `LoadLibraryW`.
+//
+// The other methods borrow one of the target's threads instead, by an APC
+// or by redirecting it, to run a bootstrap that starts a loader thread with
+// `CreateThread`: that thread runs `LoadLibraryW` and then the trampoline.
+// A flag in the context, claimed by the first thread to get there, keeps it
+// to one loader. Nothing waits for the target here, so a load failure only
+// shows as the outcome.
+
+use std::ffi::{CStr, c_void};
+use std::os::windows::ffi::OsStrExt;
+use std::path::Path;
+use std::time::Duration;
+
+use windows_sys::Win32::Foundation::{
+    CloseHandle, ERROR_ACCESS_DENIED, ERROR_INVALID_PARAMETER, GetLastError, HANDLE,
+    INVALID_HANDLE_VALUE, WAIT_OBJECT_0, WAIT_TIMEOUT,
+};
+use windows_sys::Win32::System::Diagnostics::Debug::{FlushInstructionCache, WriteProcessMemory};
+use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+    CreateToolhelp32Snapshot, TH32CS_SNAPTHREAD, THREADENTRY32, Thread32First, Thread32Next,
+};
+use windows_sys::Win32::System::LibraryLoader::{GetModuleHandleW, GetProcAddress};
+use windows_sys::Win32::System::Memory::{
+    MEM_COMMIT, MEM_RELEASE, MEM_RESERVE, PAGE_EXECUTE_READ, PAGE_READWRITE, VirtualAllocEx,
+    VirtualFreeEx, VirtualProtectEx,
+};
+use windows_sys::Win32::System::Threading::{
+    CreateRemoteThread, GetExitCodeThread, OpenProcess, OpenThread, PROCESS_ACCESS_RIGHTS,
+    PROCESS_CREATE_THREAD, PROCESS_QUERY_INFORMATION, PROCESS_TERMINATE, PROCESS_VM_OPERATION,
+    PROCESS_VM_READ, PROCESS_VM_WRITE, QueueUserAPC, ResumeThread, SuspendThread,
+    THREAD_GET_CONTEXT, THREAD_SET_CONTEXT, THREAD_SUSPEND_RESUME, TerminateProcess,
+    WaitForSingleObject,
+};
+
+use super::Engine;
+use super::native::{self, CTX_LEN, Injections};
+use crate::library::LibrarySource;
+use crate::{
+    Architecture, Error, InjectionMethod, Library, Process, Result, image, maps, spawn, status,
+};
+
+// Above Frida's 32-bit ids, so injections of both can share the registry.
+const FIRST_ID: u64 = 1 << 32;
+// The trampoline's page, then its context and the strings.
+const PAGE: u64 = 0x1000;
+// How long `LoadLibraryW`, and the library's `DllMain` with it, may take.
+const LOAD_WAIT: Duration = Duration::from_secs(30);
+// Where the bootstrap code goes in the trampoline's page.
+const QUEUED_AT: u64 = 0x100;
+// After the trampoline's context, for the loader and bootstrap:
+// `LoadLibraryW`, the path, `CreateThread`, `CloseHandle`, the trampoline,
+// the loader, and the flag the first thread to get there claims.
+const QUEUED_CTX_LEN: u64 = CTX_LEN + 56;
+
+pub(super) struct LoadLibraryBackend {
+    injections: Injections,
+}
+
+impl LoadLibraryBackend {
+    pub(super) fn new() -> Self {
+        Self {
+            injections: Injections::new(FIRST_ID),
+        }
+    }
+}
+
+impl Engine for LoadLibraryBackend {
+    fn name(&self) -> &'static str {
+        "loadlibrary"
+    }
+
+    fn methods(&self) -> &'static [InjectionMethod] {
+        code::METHODS
+    }
+
+    fn inject_process(&self, process: Process, library: &Library) -> Result<u64> {
+        self.inject_process_by(process, library, InjectionMethod::RemoteThread)
+    }
+
+    fn inject_process_by(
+        &self,
+        process: Process,
+        library: &Library,
+        method: InjectionMethod,
+    ) -> Result<u64> {
+        let LibrarySource::Path(path) = library.source() else {
+            return Err(Error::not_supported(
+                "the loadlibrary backend loads libraries from files",
+            ));
+        };
+        let target = process.architecture()?;
+        if Some(target) != Architecture::native() {
+            return Err(Error::not_supported(format_args!(
+                "the loadlibrary backend cannot inject into a {target} process from this build"
+            )));
+        }
+        let path = std::path::absolute(path)?;
+        let data = library.entry_data()?;
+        let image = library.image_bytes()?;
+        let entrypoint = library.entrypoint();
+        let Some(offset) = image::export_offset(&image, &entrypoint.to_string_lossy())? else {
+            return Err(Error::invalid_input(format_args!(
+                "library does not export `{}`",
+                entrypoint.to_string_lossy()
+            )));
+        };
+
+        let ctx = match method {
+            InjectionMethod::RemoteThread => inject(process, &path, &image, offset, &data)?,
+            method => inject_queued(process.pid(), &path, offset, &data, method)?,
+        };
+        Ok(self.injections.insert(process.pid(), ctx))
+    }
+
+    fn uninject(&self, id: u64) -> Result<()> {
+        self.injections.remove(id);
+        Ok(())
+    }
+
+    fn next_uninjected(&self, timeout: Duration) -> Result<Option<u64>> {
+        Ok(self.injections.next_unloaded(timeout))
+    }
+
+    // Programs are launched natively, held by CREATE_SUSPENDED.
+    fn resume(&self, process: Process, _timeout: Option<Duration>) -> Result<()> {
+        spawn::release(process)
+    }
+
+    fn kill(&self, process: Process) -> Result<()> {
+        let target = Target::open(process.pid(), PROCESS_TERMINATE)?;
+        if unsafe { TerminateProcess(target.handle, 1) } == 0 {
+            return Err(Error::from(std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+}
+
+/// Make `process` load `path` and start its entrypoint, at `offset` in
+/// `image`, returning the address of the trampoline's context.
+fn inject(process: Process, path: &Path, image: &[u8], offset: u64, data: &CStr) -> Result<u64> {
+    let pid = process.pid();
+    let kernel32 = Kernel32::resolve()?;
+    let target = Target::open(
+        pid,
+        PROCESS_CREATE_THREAD
+            | PROCESS_QUERY_INFORMATION
+            | PROCESS_VM_OPERATION
+            | PROCESS_VM_READ
+            | PROCESS_VM_WRITE,
+    )?;
+
+    let (strings, path_offset) = strings(data, path);
+    let len = PAGE + CTX_LEN + strings.len() as u64;
+    let base = target.allocate(len)?;
+    let ctx = base + PAGE;
+    let data_at = ctx + CTX_LEN;
+    let path_at = data_at + path_offset;
+    let staged = target
+        .write(base, native::TRAMPOLINE)
+        .and_then(|()| target.write(data_at, &strings))
+        .and_then(|()| target.make_executable(base, PAGE));
+    if let Err(err) = staged {
+        target.free(base);
+        return Err(err);
+    }
+
+    // The exit code is the module handle's low half: only zero is telling.
+    match target.run(kernel32.load_library, path_at, Some(LOAD_WAIT)) {
+        Ok(Some(0)) => {
+            target.free(base);
+            return Err(Error::runtime(format_args!(
+                "failed to load library into pid {pid}: {}",
+                path.display()
+            )));
+        }
+        Ok(_) => {}
+        Err(err) => {
+            // A thread that timed out may still read the path.
+            if err.kind() != crate::ErrorKind::TimedOut {
+                target.free(base);
+            }
+            return Err(err);
+        }
+    }
+    let module = status::module_base(process, image)?;
+
+    let context = native::context(module + offset, data_at, kernel32.free_library, module);
+    let started = target
+        .write(ctx, &context)
+        .and_then(|()| target.run(base, ctx, None));
+    if let Err(err) = started {
+        let _ = target.run(kernel32.free_library, module, Some(LOAD_WAIT));
+        return Err(err);
+    }
+    Ok(ctx)
+}
+
+/// Set `pid` up to load `path` and start its entrypoint, at `offset` in the
+/// image, on a thread borrowed by `method`; returns the address of the
+/// trampoline's context.
+fn inject_queued(
+    pid: i32,
+    path: &Path,
+    offset: u64,
+    data: &CStr,
+    method: InjectionMethod,
+) -> Result<u64> {
+    let kernel32 = Kernel32::resolve()?;
+    let target = Target::open(
+        pid,
+        PROCESS_QUERY_INFORMATION | PROCESS_VM_OPERATION | PROCESS_VM_READ | PROCESS_VM_WRITE,
+    )?;
+
+    let (strings, path_offset) = strings(data, path);
+    let base = target.allocate(PAGE + QUEUED_CTX_LEN + strings.len() as u64)?;
+    let ctx = base + PAGE;
+    let data_at = ctx + QUEUED_CTX_LEN;
+    let code_at = base + QUEUED_AT;
+
+    let mut page = native::TRAMPOLINE.to_vec();
+    page.resize(QUEUED_AT as usize, 0);
+    page.extend_from_slice(code::QUEUED);
+    // Where the hijack stub finds the context.
+    page.extend_from_slice(&ctx.to_ne_bytes());
+    // The loader adds the library's base to the entrypoint's offset.
+    let mut context = native::context(offset, data_at, kernel32.free_library, 0);
+    for word in [
+        kernel32.load_library,
+        data_at + path_offset,
+        kernel32.create_thread,
+        kernel32.close_handle,
+        base,
+        code_at + code::LOADER,
+        0,
+    ] {
+        context.extend_from_slice(&word.to_ne_bytes());
+    }
+    let staged = target
+        .write(base, &page)
+        .and_then(|()| target.write(ctx, &context))
+        .and_then(|()| target.write(data_at, &strings))
+        .and_then(|()| target.make_executable(base, PAGE))
+        .and_then(|()| match method {
+            InjectionMethod::Apc => queue_apcs(pid, code_at + code::BOOTSTRAP, ctx),
+            #[cfg(target_arch = "x86_64")]
+            InjectionMethod::ThreadHijack => hijack(&target, code_at + code::HIJACK),
+            method => Err(Error::not_supported(format_args!(
+                "the loadlibrary backend cannot inject by {method} here"
+            ))),
+        });
+    // Nothing in the target refers to the memory until one of them succeeds.
+    if let Err(err) = staged {
+        target.free(base);
+        return Err(err);
+    }
+    Ok(ctx)
+}
+
+/// Queue `bootstrap` with `ctx` to every thread of `pid` that accepts it.
+fn queue_apcs(pid: i32, bootstrap: u64, ctx: u64) -> Result<()> {
+    // SAFETY: only ever called in the target, where `bootstrap` is an APC
+    // routine.
+    let routine = unsafe {
+        std::mem::transmute::<usize, unsafe extern "system" fn(usize)>(bootstrap as usize)
+    };
+    let mut queued = 0;
+    for thread_id in threads(pid)? {
+        let thread = unsafe { OpenThread(THREAD_SET_CONTEXT, 0, thread_id) };
+        if thread.is_null() {
+            continue;
+        }
+        if unsafe { QueueUserAPC(Some(routine), thread, ctx as usize) } != 0 {
+            queued += 1;
+        }
+        unsafe { CloseHandle(thread) };
+    }
+    if queued == 0 {
+        return Err(Error::runtime(format_args!(
+            "no thread of pid {pid} accepted the APC"
+        )));
+    }
+    Ok(())
+}
+
+/// Redirect a thread of the target to `stub`, which returns to where the
+/// thread was.
+#[cfg(target_arch = "x86_64")]
+fn hijack(target: &Target, stub: u64) -> Result<()> {
+    use windows_sys::Win32::System::Diagnostics::Debug::{
+        CONTEXT, CONTEXT_CONTROL_AMD64, GetThreadContext, SetThreadContext,
+    };
+
+    // The stub calls into kernel32, which a program held before its loader
+    // has run does not have yet.
+    let pid = target.pid;
+    let has_kernel32 = maps::memory_maps(pid)?.iter().any(|region| {
+        region
+            .path()
+            .and_then(|path| path.file_name())
+            .is_some_and(|name| name.eq_ignore_ascii_case("kernel32.dll"))
+    });
+    if !has_kernel32 {
+        return Err(Error::not_supported(format_args!(
+            "pid {pid} has not loaded kernel32 yet, so none of its threads can be hijacked; \
+             use InjectionMethod::Apc for programs held before their loader has run"
+        )));
+    }
+    let Some(&thread_id) = threads(pid)?.first() else {
+        return Err(Error::process_not_found(pid));
+    };
+    let thread = unsafe {
+        OpenThread(
+            THREAD_SUSPEND_RESUME | THREAD_GET_CONTEXT | THREAD_SET_CONTEXT,
+            0,
+            thread_id,
+        )
+    };
+    if thread.is_null() {
+        return Err(Error::runtime(format_args!(
+            "failed to open thread {thread_id} of pid {pid} (error {})",
+            unsafe { GetLastError() }
+        )));
+    }
+    if unsafe { SuspendThread(thread) } == u32::MAX {
+        let err = std::io::Error::last_os_error();
+        unsafe { CloseHandle(thread) };
+        return Err(Error::runtime(format_args!(
+            "failed to suspend thread {thread_id} of pid {pid}: {err}"
+        )));
+    }
+
+    // GetThreadContext wants the record 16-byte aligned.
+    #[repr(C, align(16))]
+    struct Aligned(CONTEXT);
+    let mut context = Aligned(CONTEXT {
+        ContextFlags: CONTEXT_CONTROL_AMD64,
+        ..Default::default()
+    });
+    let redirected = if unsafe { GetThreadContext(thread, &mut context.0) } == 0 {
+        Err(Error::runtime(format_args!(
+            "failed to read the registers of thread {thread_id} of pid {pid} (error {})",
+            unsafe { GetLastError() }
+        )))
+    } else {
+        // The stub returns to the interrupted instruction.
+        let sp = context.0.Rsp - 8;
+        target
+            .write(sp, &context.0.Rip.to_ne_bytes())
+            .and_then(|()| {
+                context.0.Rsp = sp;
+                context.0.Rip = stub;
+                if unsafe { SetThreadContext(thread, &context.0) } == 0 {
+                    return Err(Error::runtime(format_args!(
+                        "failed to redirect thread {thread_id} of pid {pid} (error {})",
+                        unsafe { GetLastError() }
+                    )));
+                }
+                Ok(())
+            })
+    };
+    unsafe { ResumeThread(thread) };
+    unsafe { CloseHandle(thread) };
+    redirected
+}
+
+/// The ids of the threads of `pid`.
+fn threads(pid: i32) -> Result<Vec<u32>> {
+    let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0) };
+    if snapshot == INVALID_HANDLE_VALUE {
+        return Err(Error::from(std::io::Error::last_os_error()));
+    }
+    let mut threads = Vec::new();
+    let mut entry = THREADENTRY32 {
+        dwSize: std::mem::size_of::<THREADENTRY32>() as u32,
+        ..Default::default()
+    };
+    let mut more = unsafe { Thread32First(snapshot, &mut entry) } != 0;
+    while more {
+        if entry.th32OwnerProcessID == pid as u32 {
+            threads.push(entry.th32ThreadID);
+        }
+        more = unsafe { Thread32Next(snapshot, &mut entry) } != 0;
+    }
+    unsafe { CloseHandle(snapshot) };
+    Ok(threads)
+}
+
+/// The data and the path, as the entrypoint and `LoadLibraryW` take them,
+/// and the path's offset.
+fn strings(data: &CStr, path: &Path) -> (Vec<u8>, u64) {
+    let mut strings = data.to_bytes_with_nul().to_vec();
+    let path_offset = strings.len().next_multiple_of(2) as u64;
+    strings.resize(path_offset as usize, 0);
+    for unit in path.as_os_str().encode_wide().chain([0]) {
+        strings.extend_from_slice(&unit.to_ne_bytes());
+    }
+    (strings, path_offset)
+}
+
+/// Addresses of the kernel32 functions injection runs, in every process.
+struct Kernel32 {
+    load_library: u64,
+    free_library: u64,
+    create_thread: u64,
+    close_handle: u64,
+}
+
+impl Kernel32 {
+    fn resolve() -> Result<Self> {
+        let name: Vec<u16> = "kernel32.dll".encode_utf16().chain([0]).collect();
+        let module = unsafe { GetModuleHandleW(name.as_ptr()) };
+        if module.is_null() {
+            return Err(Error::runtime("kernel32.dll is not loaded"));
+        }
+        let address = |symbol: &CStr| {
+            unsafe { GetProcAddress(module, symbol.as_ptr().cast()) }
+                .map(|function| function as usize as u64)
+                .ok_or_else(|| {
+                    Error::runtime(format_args!(
+                        "kernel32.dll does not export `{}`",
+                        symbol.to_string_lossy()
+                    ))
+                })
+        };
+        Ok(Self {
+            load_library: address(c"LoadLibraryW")?,
+            free_library: address(c"FreeLibrary")?,
+            create_thread: address(c"CreateThread")?,
+            close_handle: address(c"CloseHandle")?,
+        })
+    }
+}
+
+/// An open handle to the target process.
+struct Target {
+    pid: i32,
+    handle: HANDLE,
+}
+
+impl Target {
+    fn open(pid: i32, access: PROCESS_ACCESS_RIGHTS) -> Result<Self> {
+        if pid <= 0 {
+            return Err(Error::invalid_input("pid must be > 0"));
+        }
+        let handle = unsafe { OpenProcess(access, 0, pid as u32) };
+        if handle.is_null() {
+            return Err(match unsafe { GetLastError() } {
+                ERROR_ACCESS_DENIED => Error::permission_denied(format_args!(
+                    "cannot open pid {pid} for injection (OpenProcess)"
+                )),
+                ERROR_INVALID_PARAMETER => Error::process_not_found(pid),
+                err => Error::runtime(format_args!("failed to open pid {pid} (error {err})")),
+            });
+        }
+        Ok(Self { pid, handle })
+    }
+
+    fn allocate(&self, len: u64) -> Result<u64> {
+        let address = unsafe {
+            VirtualAllocEx(
+                self.handle,
+                std::ptr::null(),
+                len as usize,
+                MEM_COMMIT | MEM_RESERVE,
+                PAGE_READWRITE,
+            )
+        };
+        if address.is_null() {
+            return Err(Error::runtime(format_args!(
+                "failed to allocate memory in pid {} (error {})",
+                self.pid,
+                unsafe { GetLastError() }
+            )));
+        }
+        Ok(address as u64)
+    }
+
+    fn free(&self, address: u64) {
+        unsafe { VirtualFreeEx(self.handle, address as *mut c_void, 0, MEM_RELEASE) };
+    }
+
+    fn write(&self, address: u64, bytes: &[u8]) -> Result<()> {
+        let mut written = 0;
+        let ok = unsafe {
+            WriteProcessMemory(
+                self.handle,
+                address as *const c_void,
+                bytes.as_ptr().cast(),
+                bytes.len(),
+                &mut written,
+            )
+        };
+        if ok == 0 || written != bytes.len() {
+            return Err(Error::runtime(format_args!(
+                "failed to write {} bytes at {address:#x} in pid {} (error {})",
+                bytes.len(),
+                self.pid,
+                unsafe { GetLastError() }
+            )));
+        }
+        Ok(())
+    }
+
+    fn make_executable(&self, address: u64, len: u64) -> Result<()> {
+        let mut old = 0;
+        let ok = unsafe {
+            VirtualProtectEx(
+                self.handle,
+                address as *const c_void,
+                len as usize,
+                PAGE_EXECUTE_READ,
+                &mut old,
+            )
+        };
+        if ok == 0 {
+            return Err(Error::runtime(format_args!(
+                "failed to make the trampoline executable in pid {} (error {})",
+                self.pid,
+                unsafe { GetLastError() }
+            )));
+        }
+        unsafe { FlushInstructionCache(self.handle, address as *const c_void, len as usize) };
+        Ok(())
+    }
+
+    /// Run `function` on a new thread in the target with `arg`, waiting up to
+    /// `wait` for its exit code; without one, the thread is left running.
+    fn run(&self, function: u64, arg: u64, wait: Option<Duration>) -> Result<Option<u32>> {
+        // SAFETY: only ever called in the target, where `function` is a
+        // thread start routine.
+        let start = unsafe {
+            std::mem::transmute::<usize, unsafe extern "system" fn(*mut c_void) -> u32>(
+                function as usize,
+            )
+        };
+        let thread = unsafe {
+            CreateRemoteThread(
+                self.handle,
+                std::ptr::null(),
+                0,
+                Some(start),
+                arg as *const c_void,
+                0,
+                std::ptr::null_mut(),
+            )
+        };
+        if thread.is_null() {
+            return Err(Error::runtime(format_args!(
+                "failed to start a thread in pid {} (error {})",
+                self.pid,
+                unsafe { GetLastError() }
+            )));
+        }
+        let Some(wait) = wait else {
+            unsafe { CloseHandle(thread) };
+            return Ok(None);
+        };
+
+        let millis = wait.as_millis().min(u32::MAX as u128 - 1) as u32;
+        let waited = unsafe { WaitForSingleObject(thread, millis) };
+        let mut code = 0u32;
+        let exited =
+            waited == WAIT_OBJECT_0 && unsafe { GetExitCodeThread(thread, &mut code) } != 0;
+        unsafe { CloseHandle(thread) };
+        match waited {
+            _ if exited => Ok(Some(code)),
+            WAIT_TIMEOUT => Err(Error::timed_out(format_args!(
+                "the thread started in pid {} did not finish within {wait:?}",
+                self.pid
+            ))),
+            _ => Err(Error::runtime(format_args!(
+                "failed to wait for the thread started in pid {}",
+                self.pid
+            ))),
+        }
+    }
+}
+
+impl Drop for Target {
+    fn drop(&mut self) {
+        unsafe { CloseHandle(self.handle) };
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod code {
+    use crate::InjectionMethod;
+
+    pub(super) const METHODS: &[InjectionMethod] = &[
+        InjectionMethod::RemoteThread,
+        InjectionMethod::Apc,
+        InjectionMethod::ThreadHijack,
+    ];
+
+    pub(super) const LOADER: u64 = 0;
+    pub(super) const BOOTSTRAP: u64 = 0x36;
+    pub(super) const HIJACK: u64 = 0x88;
+
+    // loader: push rbx; sub rsp, 32; mov rbx, rcx
+    //   mov rcx, [rbx+56]; call [rbx+48]; test rax, rax; jz 1f
+    //   mov [rbx+24], rax; add [rbx], rax
+    //   mov rcx, rbx; add rsp, 32; pop rbx; jmp [rcx+80]
+    //   1: mov qword [rbx+40], 2; xor eax, eax; add rsp, 32; pop rbx; ret
+    // bootstrap: push rbx; sub rsp, 48; mov rbx, rcx
+    //   xor eax, eax; mov ecx, 1; lock cmpxchg [rbx+96], rcx; jnz 2f
+    //   xor ecx, ecx; xor edx, edx; mov r8, [rbx+88]; mov r9, rbx
+    //   mov qword [rsp+32], 0; mov qword [rsp+40], 0; call [rbx+64]
+    //   test rax, rax; jz 3f; mov rcx, rax; call [rbx+72]; jmp 2f
+    //   3: mov qword [rbx+40], 2
+    //   2: add rsp, 48; pop rbx; ret
+    // hijack: pushfq; push rax, rcx, rdx, r8, r9, r10, r11, rbx
+    //   mov rbx, rsp; and rsp, -16; sub rsp, 96; movdqu [rsp+16*i], xmm0-5
+    //   sub rsp, 32; mov rcx, [rip+ctx]; call bootstrap; add rsp, 32
+    //   movdqu xmm0-5, [rsp+16*i]; mov rsp, rbx
+    //   pop rbx, r11, r10, r9, r8, rdx, rcx, rax; popfq; ret
+    // ctx: (the 8 bytes after the code)
+    pub(super) const QUEUED: &[u8] = &[
+        0x53, 0x48, 0x83, 0xec, 0x20, 0x48, 0x89, 0xcb, 0x48, 0x8b, 0x4b, 0x38, 0xff, 0x53, 0x30,
+        0x48, 0x85, 0xc0, 0x74, 0x12, 0x48, 0x89, 0x43, 0x18, 0x48, 0x01, 0x03, 0x48, 0x89, 0xd9,
+        0x48, 0x83, 0xc4, 0x20, 0x5b, 0xff, 0x61, 0x50, 0x48, 0xc7, 0x43, 0x28, 0x02, 0x00, 0x00,
+        0x00, 0x31, 0xc0, 0x48, 0x83, 0xc4, 0x20, 0x5b, 0xc3, 0x53, 0x48, 0x83, 0xec, 0x30, 0x48,
+        0x89, 0xcb, 0x31, 0xc0, 0xb9, 0x01, 0x00, 0x00, 0x00, 0xf0, 0x48, 0x0f, 0xb1, 0x4b, 0x60,
+        0x75, 0x35, 0x31, 0xc9, 0x31, 0xd2, 0x4c, 0x8b, 0x43, 0x58, 0x49, 0x89, 0xd9, 0x48, 0xc7,
+        0x44, 0x24, 0x20, 0x00, 0x00, 0x00, 0x00, 0x48, 0xc7, 0x44, 0x24, 0x28, 0x00, 0x00, 0x00,
+        0x00, 0xff, 0x53, 0x40, 0x48, 0x85, 0xc0, 0x74, 0x08, 0x48, 0x89, 0xc1, 0xff, 0x53, 0x48,
+        0xeb, 0x08, 0x48, 0xc7, 0x43, 0x28, 0x02, 0x00, 0x00, 0x00, 0x48, 0x83, 0xc4, 0x30, 0x5b,
+        0xc3, 0x9c, 0x50, 0x51, 0x52, 0x41, 0x50, 0x41, 0x51, 0x41, 0x52, 0x41, 0x53, 0x53, 0x48,
+        0x89, 0xe3, 0x48, 0x83, 0xe4, 0xf0, 0x48, 0x83, 0xec, 0x60, 0xf3, 0x0f, 0x7f, 0x04, 0x24,
+        0xf3, 0x0f, 0x7f, 0x4c, 0x24, 0x10, 0xf3, 0x0f, 0x7f, 0x54, 0x24, 0x20, 0xf3, 0x0f, 0x7f,
+        0x5c, 0x24, 0x30, 0xf3, 0x0f, 0x7f, 0x64, 0x24, 0x40, 0xf3, 0x0f, 0x7f, 0x6c, 0x24, 0x50,
+        0x48, 0x83, 0xec, 0x20, 0x48, 0x8b, 0x0d, 0x3d, 0x00, 0x00, 0x00, 0xe8, 0x63, 0xff, 0xff,
+        0xff, 0x48, 0x83, 0xc4, 0x20, 0xf3, 0x0f, 0x6f, 0x04, 0x24, 0xf3, 0x0f, 0x6f, 0x4c, 0x24,
+        0x10, 0xf3, 0x0f, 0x6f, 0x54, 0x24, 0x20, 0xf3, 0x0f, 0x6f, 0x5c, 0x24, 0x30, 0xf3, 0x0f,
+        0x6f, 0x64, 0x24, 0x40, 0xf3, 0x0f, 0x6f, 0x6c, 0x24, 0x50, 0x48, 0x89, 0xdc, 0x5b, 0x41,
+        0x5b, 0x41, 0x5a, 0x41, 0x59, 0x41, 0x58, 0x5a, 0x59, 0x58, 0x9d, 0xc3,
+    ];
+}
+
+// There is no hijack stub: returning to the interrupted instruction would
+// need a register it may still be using.
+#[cfg(target_arch = "aarch64")]
+mod code {
+    use crate::InjectionMethod;
+
+    pub(super) const METHODS: &[InjectionMethod] =
+        &[InjectionMethod::RemoteThread, InjectionMethod::Apc];
+
+    pub(super) const LOADER: u64 = 0;
+    pub(super) const BOOTSTRAP: u64 = 0x58;
+
+    // loader: stp x29, x30, [sp, #-32]!; str x19, [sp, #16]; mov x19, x0
+    //   ldr x0, [x19, #56]; ldr x8, [x19, #48]; blr x8; cbz x0, 1f
+    //   str x0, [x19, #24]; ldr x8, [x19]; add x8, x8, x0; str x8, [x19]
+    //   mov x0, x19; ldr x8, [x19, #80]; ldr x19, [sp, #16]
+    //   ldp x29, x30, [sp], #32; br x8
+    //   1: mov x8, #2; str x8, [x19, #40]; mov w0, #0; ldr x19, [sp, #16]
+    //   ldp x29, x30, [sp], #32; ret
+    // bootstrap: stp x29, x30, [sp, #-32]!; str x19, [sp, #16]; mov x19, x0
+    //   add x9, x19, #96; mov x10, #1
+    //   3: ldaxr x11, [x9]; cbnz x11, 2f; stlxr w12, x10, [x9]; cbnz w12, 3b
+    //   mov x0, xzr; mov x1, xzr; ldr x2, [x19, #88]; mov x3, x19
+    //   mov x4, xzr; mov x5, xzr; ldr x8, [x19, #64]; blr x8; cbz x0, 4f
+    //   ldr x8, [x19, #72]; blr x8; b 5f
+    //   4: mov x8, #2; str x8, [x19, #40]; b 5f
+    //   2: clrex
+    //   5: ldr x19, [sp, #16]; ldp x29, x30, [sp], #32; ret
+    pub(super) const QUEUED: &[u8] = &[
+        0xfd, 0x7b, 0xbe, 0xa9, 0xf3, 0x0b, 0x00, 0xf9, 0xf3, 0x03, 0x00, 0xaa, 0x60, 0x1e, 0x40,
+        0xf9, 0x68, 0x1a, 0x40, 0xf9, 0x00, 0x01, 0x3f, 0xd6, 0x40, 0x01, 0x00, 0xb4, 0x60, 0x0e,
+        0x00, 0xf9, 0x68, 0x02, 0x40, 0xf9, 0x08, 0x01, 0x00, 0x8b, 0x68, 0x02, 0x00, 0xf9, 0xe0,
+        0x03, 0x13, 0xaa, 0x68, 0x2a, 0x40, 0xf9, 0xf3, 0x0b, 0x40, 0xf9, 0xfd, 0x7b, 0xc2, 0xa8,
+        0x00, 0x01, 0x1f, 0xd6, 0x48, 0x00, 0x80, 0xd2, 0x68, 0x16, 0x00, 0xf9, 0x00, 0x00, 0x80,
+        0x52, 0xf3, 0x0b, 0x40, 0xf9, 0xfd, 0x7b, 0xc2, 0xa8, 0xc0, 0x03, 0x5f, 0xd6, 0xfd, 0x7b,
+        0xbe, 0xa9, 0xf3, 0x0b, 0x00, 0xf9, 0xf3, 0x03, 0x00, 0xaa, 0x69, 0x82, 0x01, 0x91, 0x2a,
+        0x00, 0x80, 0xd2, 0x2b, 0xfd, 0x5f, 0xc8, 0x4b, 0x02, 0x00, 0xb5, 0x2a, 0xfd, 0x0c, 0xc8,
+        0xac, 0xff, 0xff, 0x35, 0xe0, 0x03, 0x1f, 0xaa, 0xe1, 0x03, 0x1f, 0xaa, 0x62, 0x2e, 0x40,
+        0xf9, 0xe3, 0x03, 0x13, 0xaa, 0xe4, 0x03, 0x1f, 0xaa, 0xe5, 0x03, 0x1f, 0xaa, 0x68, 0x22,
+        0x40, 0xf9, 0x00, 0x01, 0x3f, 0xd6, 0x80, 0x00, 0x00, 0xb4, 0x68, 0x26, 0x40, 0xf9, 0x00,
+        0x01, 0x3f, 0xd6, 0x05, 0x00, 0x00, 0x14, 0x48, 0x00, 0x80, 0xd2, 0x68, 0x16, 0x00, 0xf9,
+        0x02, 0x00, 0x00, 0x14, 0x5f, 0x3f, 0x03, 0xd5, 0xf3, 0x0b, 0x40, 0xf9, 0xfd, 0x7b, 0xc2,
+        0xa8, 0xc0, 0x03, 0x5f, 0xd6,
+    ];
+}
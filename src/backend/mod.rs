@@ -1,14 +1,205 @@
-use std::sync::{Arc, OnceLock};
+use std::ffi::CStr;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::thread::JoinHandle;
+use std::time::Duration;
 
+use crate::clock::poll_until;
+use crate::device::DeviceSpec;
+use crate::library::LibrarySource;
 use crate::{
-    InjectedProcess, InjectedProgram, Library, Process, Program, Result, SuspendedProgram,
+    AlreadyLoaded, Backend, Clock, Descendant, Device, EntryStatus, Error, InjectOptions,
+    InjectedProcess, InjectedProgram, InjectionEventKind, InjectionId, InjectionMethod, JvmAttach,
+    Library, Process, Program, Result, SuspendPoint, SuspendedProgram, UnloadPolicy, notify,
+    registry, spawn, staging, status,
 };
 
+// How long `configure` waits for the main entrypoint to report a status.
+const CONFIGURE_WAIT: Duration = Duration::from_secs(10);
+// How long `eject` waits for the library to be unmapped.
+const EJECT_WAIT: Duration = Duration::from_secs(5);
+// How often `eject` checks whether it is.
+const EJECT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+// How long `uninject` waits for the shutdown entrypoint to return.
+const SHUTDOWN_WAIT: Duration = Duration::from_secs(2);
+
+mod clr;
+mod follow;
+#[cfg(feature = "frida")]
 mod frida;
+mod jvm;
+#[cfg(all(windows, any(target_arch = "x86_64", target_arch = "aarch64")))]
+mod loadlibrary;
+#[cfg(all(
+    any(target_os = "linux", target_os = "macos", windows),
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod native;
+#[cfg(all(
+    any(target_os = "linux", target_os = "macos"),
+    feature = "preload-backend"
+))]
+mod preload;
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod ptrace;
+#[cfg(all(
+    target_os = "macos",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod taskport;
+mod unload;
+
+/// The operations a backend's runtime provides; `BackendHandle` builds the
+/// rest of the crate's behavior on top of them.
+///
+/// Injection ids are the runtime's own and must not collide with another
+/// runtime's, since injections of every backend share the registry.
+/// Operations a runtime lacks fail with `ErrorKind::NotSupported`.
+trait Engine: Send + Sync {
+    /// The backend's name, for errors.
+    fn name(&self) -> &'static str;
+
+    /// Whether the runtime launches programs itself; they are otherwise
+    /// launched natively and held for injection.
+    fn spawns(&self) -> bool {
+        false
+    }
+
+    /// Whether the runtime's targets run on another machine. Libraries are
+    /// then sent to it, and nothing about the targets can be checked here.
+    fn remote(&self) -> bool {
+        false
+    }
+
+    /// The process with `pid` among the runtime's targets.
+    fn find_process(&self, pid: i32) -> Result<Process> {
+        Process::from_pid(pid)
+    }
+
+    /// Load `library` into `process` and run its entrypoint, returning the
+    /// injection id.
+    fn inject_process(&self, process: Process, library: &Library) -> Result<u64>;
+
+    /// `inject_process`, failing with `ErrorKind::TimedOut` after `timeout`
+    /// where the runtime can bound it.
+    fn inject_process_within(
+        &self,
+        process: Process,
+        library: &Library,
+        _timeout: Duration,
+    ) -> Result<u64> {
+        self.inject_process(process, library)
+    }
+
+    /// `library` as the runtime's targets need it, with any files that takes
+    /// staged where `options` say; `None` if it loads as is.
+    fn prepare_library(
+        &self,
+        _library: &Library,
+        _options: &InjectOptions,
+    ) -> Result<Option<Library>> {
+        Ok(None)
+    }
+
+    /// The methods the runtime can load libraries with, the one
+    /// `inject_process` uses first; empty if it offers no choice.
+    fn methods(&self) -> &'static [InjectionMethod] {
+        &[]
+    }
+
+    /// `inject_process` with one of `methods`.
+    fn inject_process_by(
+        &self,
+        _process: Process,
+        _library: &Library,
+        _method: InjectionMethod,
+    ) -> Result<u64> {
+        Err(unsupported(self.name(), "choosing the injection method"))
+    }
+
+    /// Stop monitoring injection `id`, leaving the library loaded.
+    fn uninject(&self, id: u64) -> Result<()>;
+
+    /// Wait up to `timeout` for an injection to be reported as unloaded.
+    fn next_uninjected(&self, timeout: Duration) -> Result<Option<u64>>;
+
+    fn resume(&self, process: Process, timeout: Option<Duration>) -> Result<()>;
+
+    fn kill(&self, process: Process) -> Result<()>;
+
+    /// Spawn, inject and resume in one runtime call; path libraries only.
+    fn inject_launch(
+        &self,
+        _spec: &mut Program,
+        _library: &Library,
+        _timeout: Option<Duration>,
+    ) -> Result<(Process, u64)> {
+        Err(unsupported(self.name(), "launching programs"))
+    }
+
+    /// Whether the runtime injects by having the dynamic loader load the
+    /// library into programs as they start (`inject_preloaded`), instead of
+    /// launching them first.
+    fn preloads(&self) -> bool {
+        false
+    }
+
+    /// Launch `spec` natively with `library` preloaded, returning the child
+    /// and the injection id once the library is loaded.
+    fn inject_preloaded(
+        &self,
+        _spec: Program,
+        _library: &Library,
+        _options: &InjectOptions,
+    ) -> Result<(std::process::Child, u64)> {
+        Err(unsupported(self.name(), "preloading libraries"))
+    }
+
+    fn spawn(&self, _spec: &mut Program, _timeout: Option<Duration>) -> Result<Process> {
+        Err(unsupported(self.name(), "launching programs"))
+    }
+
+    fn input(&self, _process: Process, _data: &[u8]) -> Result<()> {
+        Err(unsupported(self.name(), "writing to a program's stdin"))
+    }
+
+    fn enable_child_gating(&self, _process: Process) -> Result<()> {
+        Err(unsupported(self.name(), "following exec and children"))
+    }
+
+    fn disable_child_gating(&self, _process: Process) -> Result<()> {
+        Err(unsupported(self.name(), "following exec and children"))
+    }
+
+    fn next_child(&self, _timeout: Duration) -> Result<Option<Descendant>> {
+        Err(unsupported(self.name(), "following exec and children"))
+    }
+
+    fn enable_spawn_gating(&self) -> Result<()> {
+        Err(unsupported(self.name(), "spawn gating"))
+    }
+
+    fn disable_spawn_gating(&self) -> Result<()> {
+        Err(unsupported(self.name(), "spawn gating"))
+    }
+
+    /// Wait for a gated spawn, returning its process and program identifier.
+    fn next_spawn(&self, _timeout: Duration) -> Result<Option<(Process, String)>> {
+        Err(unsupported(self.name(), "spawn gating"))
+    }
+}
+
+fn unsupported(engine: &str, what: &str) -> Error {
+    Error::not_supported(format_args!("the {engine} backend does not support {what}"))
+}
 
 #[derive(Clone)]
 pub(crate) struct BackendHandle {
-    inner: Arc<frida::FridaBackend>,
+    inner: Arc<dyn Engine>,
 }
 
 impl std::fmt::Debug for BackendHandle {
@@ -18,57 +209,791 @@ impl std::fmt::Debug for BackendHandle {
 }
 
 impl BackendHandle {
-    fn new(inner: frida::FridaBackend) -> Self {
+    fn new(inner: impl Engine + 'static) -> Self {
         Self {
             inner: Arc::new(inner),
         }
     }
 
-    pub(crate) fn uninject(&self, id: u64) -> Result<()> {
+    pub(crate) fn is_remote(&self) -> bool {
+        self.inner.remote()
+    }
+
+    pub(crate) fn find_process(&self, pid: i32) -> Result<Process> {
+        self.inner.find_process(pid)
+    }
+
+    /// Fail for what only works on targets on this machine, if the
+    /// runtime's are not.
+    fn check_remote(&self, library: &Library, options: &InjectOptions) -> Result<()> {
+        if !self.inner.remote() {
+            if let LibrarySource::Device(path) = library.source() {
+                return Err(Error::invalid_input(format_args!(
+                    "{path} is on a remote device; inject it with that device's injector"
+                )));
+            }
+            return Ok(());
+        }
+        let local_only = if library.data_bytes().is_some() {
+            "binary entrypoint data"
+        } else if library.managed_assembly().is_some() {
+            "managed assemblies"
+        } else if library.jvm_attach() != JvmAttach::Never {
+            "attaching to JVMs"
+        } else if options.follow_exec_value() || options.children_value().is_some() {
+            "following exec and children"
+        } else {
+            return Ok(());
+        };
+        Err(Error::not_supported(format_args!(
+            "{local_only} is not supported on a remote device"
+        )))
+    }
+
+    pub(crate) fn uninject(&self, process: Process, id: InjectionId) -> Result<()> {
+        // The runtime that monitors a reattached injection died with the
+        // process that made it.
+        if !registry::remove(id) {
+            return Ok(());
+        }
+        let id = id.as_u64();
+        unload::forget(id);
+        // A followed injection may have been re-applied since it was made.
+        let id = follow::stop(self, process).unwrap_or(id);
+        unload::forget(id);
+        // A JVM keeps its agents until it exits; there is nothing to stop.
+        if jvm::is_attach(id) {
+            return Ok(());
+        }
         self.inner.uninject(id)
     }
 
+    /// Forget injection `id` without demonitoring it.
+    pub(crate) fn detach(&self, id: InjectionId) {
+        registry::remove(id);
+        unload::forget(id.as_u64());
+    }
+
+    /// Run `callback` once the runtime reports injection `id` unloaded.
+    pub(crate) fn on_unloaded(&self, id: InjectionId, callback: impl FnOnce() + Send + 'static) {
+        unload::on_unloaded(self, id.as_u64(), Box::new(callback));
+    }
+
+    /// Block until injection `id` is unloaded, failing with `timed_out`
+    /// after `timeout`.
+    pub(crate) fn wait_until_unloaded(&self, id: InjectionId, timeout: Duration) -> Result<()> {
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.on_unloaded(id, move || {
+            let _ = tx.send(());
+        });
+        rx.recv_timeout(timeout).map_err(|_| {
+            Error::timed_out(format_args!("library was not unloaded within {timeout:?}"))
+        })
+    }
+
     pub(crate) fn inject_program(
         &self,
         mut spec: Program,
         library: Library,
+        options: &InjectOptions,
     ) -> Result<InjectedProgram> {
+        // Check against the program file so a mismatch fails before spawning.
+        // A remote device's programs are not on this machine.
+        if !self.inner.remote() {
+            let target = spec
+                .resolved_program()
+                .ok()
+                .and_then(|path| crate::arch::identify_file(&path).ok().flatten())
+                .and_then(|kind| kind.architecture);
+            library.check_target(target)?;
+        }
+        let method = self.method(options)?;
+        self.check_remote(&library, options)?;
+        if library.jvm_attach() == JvmAttach::Always {
+            return Err(Error::not_supported(
+                "a launched program is not running a JVM to attach to yet",
+            ));
+        }
+        if library.managed_assembly().is_some() {
+            return Err(Error::not_supported(
+                "a launched program has no .NET runtime to run an assembly in yet",
+            ));
+        }
+
+        staging::prepare(options)?;
+        if self.inner.preloads() {
+            if options.follow_exec_value() {
+                return Err(unsupported(
+                    self.inner.name(),
+                    "following exec and children",
+                ));
+            }
+            let stdio = spec.stdio_value();
+            let (native, id) = self.inner.inject_preloaded(spec, &library, options)?;
+            let process = unsafe { Process::from_pid_unchecked(native.id() as i32) };
+            let child = crate::Child::new(self.clone(), process, stdio).with_native(Some(native));
+            return Ok(self.launched(id, process, library, child, options, method));
+        }
+        // Following exec needs gating in place before the program first runs.
+        // Blobs have no single-call launch, nor have libraries sent to a
+        // remote device, and binary data is staged per target; going through
+        // a suspended handle kills the program if injecting or resuming fails
+        // part-way.
+        if spec.needs_native_spawn()
+            || !self.inner.spawns()
+            || self.inner.remote()
+            || options.follow_exec_value()
+            || library.data_bytes().is_some()
+            || library.source().in_memory()
+        {
+            return self.spawn(spec, options)?.inject_with(library, options);
+        }
+
         let stdio = spec.stdio_value();
-        let (process, id) = self.inner.inject_launch(&mut spec, &library)?;
-        let child = crate::Child::new(process, stdio);
-        Ok(InjectedProgram::new(self.clone(), id, process, child))
+        let (process, id) =
+            self.inner
+                .inject_launch(&mut spec, &library, options.timeout_value())?;
+        let child = crate::Child::new(self.clone(), process, stdio);
+        Ok(self.launched(id, process, library, child, options, method))
+    }
+
+    /// Register injection `id` of a program the runtime launched, and hand
+    /// it back.
+    fn launched(
+        &self,
+        id: u64,
+        process: Process,
+        library: Library,
+        child: crate::Child,
+        options: &InjectOptions,
+        method: Option<InjectionMethod>,
+    ) -> InjectedProgram {
+        let id = InjectionId::new(id);
+        let label = options.label_value();
+        registry::insert(id, process, &library, label);
+        let notifier = options.notifier_value().cloned();
+        notify::emit(
+            notifier.as_ref(),
+            InjectionEventKind::Injected,
+            process,
+            label,
+        );
+        InjectedProgram::new(self.clone(), id, process, library, child)
+            .with_notifier(notifier)
+            .with_label(label.map(str::to_string))
+            .with_method(method)
+            .with_staging_dir(options.staging_dir_value().map(Path::to_path_buf))
+            .with_clock(options.shared_clock())
     }
 
     pub(crate) fn inject_process(
         &self,
         process: Process,
         library: Library,
+        options: &InjectOptions,
     ) -> Result<InjectedProcess> {
-        let id = self.inner.inject_process(process, &library)?;
-        Ok(InjectedProcess::new(self.clone(), id, process))
+        // A remote target's image format and libc are not known here.
+        if !process.is_remote() {
+            let target = process.architecture().ok();
+            if let Some(target) = target {
+                options.check_realm(process, target)?;
+            }
+            library.check_target(target)?;
+            crate::compat::check_libc(&library, process)?;
+        }
+        let method = self.method(options)?;
+        self.check_remote(&library, options)?;
+        if options.already_loaded_value() != AlreadyLoaded::Inject
+            && status::is_loaded(process, &library)?
+        {
+            let existing = registry::find(process, &library)
+                .filter(|_| options.already_loaded_value() == AlreadyLoaded::Reuse);
+            let Some(existing) = existing else {
+                return Err(Error::already_injected(format_args!(
+                    "library is already loaded in pid {}",
+                    process.pid()
+                )));
+            };
+            let notifier = options.notifier_value().cloned();
+            return Ok(InjectedProcess::new(
+                self.clone(),
+                existing.id(),
+                process,
+                existing.library().clone(),
+            )
+            .with_notifier(notifier)
+            .with_label(existing.label().map(str::to_string))
+            .with_staging_dir(options.staging_dir_value().map(Path::to_path_buf))
+            .with_clock(options.shared_clock()));
+        }
+        staging::prepare(options)?;
+        let id = self.inject_library(process, &library, options)?;
+        if let Err(err) = follow::start(self, process, &library, id, options) {
+            let _ = self.inner.uninject(id);
+            return Err(err);
+        }
+        let id = InjectionId::new(id);
+        let label = options.label_value();
+        registry::insert(id, process, &library, label);
+        let notifier = options.notifier_value().cloned();
+        notify::emit(
+            notifier.as_ref(),
+            InjectionEventKind::Injected,
+            process,
+            label,
+        );
+        Ok(InjectedProcess::new(self.clone(), id, process, library)
+            .with_notifier(notifier)
+            .with_label(label.map(str::to_string))
+            .with_method(method)
+            .with_staging_dir(options.staging_dir_value().map(Path::to_path_buf))
+            .with_clock(options.shared_clock()))
+    }
+
+    /// The injection method `options` asks for, or the runtime's default;
+    /// `None` if the runtime offers no choice.
+    fn method(&self, options: &InjectOptions) -> Result<Option<InjectionMethod>> {
+        let methods = self.inner.methods();
+        match options.injection_method_value() {
+            Some(method) if !methods.contains(&method) => Err(Error::not_supported(format_args!(
+                "the {} backend cannot inject by {method} here",
+                self.inner.name()
+            ))),
+            Some(method) => Ok(Some(method)),
+            None => Ok(methods.first().copied()),
+        }
+    }
+
+    /// Replace injection `id` of `old` in `process` with `new`, returning
+    /// the new injection id, which keeps `label`. `new` is loaded and staged
+    /// as `options` say.
+    ///
+    /// An old agent still mapped is ejected, so a rebuilt library at the same
+    /// path is loaded afresh instead of resolving to the old image.
+    pub(crate) fn reinject(
+        &self,
+        process: Process,
+        id: InjectionId,
+        old: &Library,
+        new: &Library,
+        label: Option<&str>,
+        options: &InjectOptions,
+    ) -> Result<InjectionId> {
+        if !process.is_remote() {
+            new.check_target(process.architecture().ok())?;
+            crate::compat::check_libc(new, process)?;
+        }
+        if jvm::is_attach(id.as_u64()) {
+            return Err(Error::not_supported(
+                "a JVM cannot unload an agent to make way for another",
+            ));
+        }
+        if status::is_loaded(process, old)? {
+            self.eject(process, id, old, options.clock_value())?;
+        } else {
+            self.uninject(process, id)?;
+        }
+
+        staging::prepare(options)?;
+        let id = InjectionId::new(self.inject_library(process, new, options)?);
+        registry::insert(id, process, new, label);
+        Ok(id)
+    }
+
+    /// Inject into `process`, staging blobs the runtime cannot load from
+    /// memory, and binary entrypoint data, in private temp files. JVMs are
+    /// attached to instead where the library asks for it, and a managed
+    /// assembly is bootstrapped once the library is in.
+    pub(super) fn inject_library(
+        &self,
+        process: Process,
+        library: &Library,
+        options: &InjectOptions,
+    ) -> Result<u64> {
+        if jvm::wants_attach(process, library)? {
+            return jvm::attach(process, library, options);
+        }
+        if let Some(assembly) = library.managed_assembly() {
+            clr::check(assembly)?;
+        }
+        let staged = staging::stage_data(library, process, options)?;
+        let result = self.inject_image(process, &staged, options);
+        if result.is_err() && library.data_bytes().is_some() {
+            let _ = std::fs::remove_file(staged.data().to_string_lossy().as_ref());
+        }
+        let id = result?;
+        if let Some(assembly) = library.managed_assembly()
+            && let Err(err) = clr::bootstrap(self, process, assembly, options)
+        {
+            let _ = self.inner.uninject(id);
+            return Err(err);
+        }
+        Ok(id)
     }
 
-    pub(crate) fn spawn(&self, mut spec: Program) -> Result<crate::SuspendedProgram> {
+    fn inject_image(
+        &self,
+        process: Process,
+        library: &Library,
+        options: &InjectOptions,
+    ) -> Result<u64> {
+        let method = self.method(options)?;
+        let timeout = options.timeout_value();
+        let prepared = self.inner.prepare_library(library, options)?;
+        let library = prepared.as_ref().unwrap_or(library);
+        match self.load(process, library, method, timeout) {
+            Err(err) if err.is_not_supported() && library.source().in_memory() => {
+                let staged = staging::stage_library(library, process, options)?;
+                let result = self.load(process, &staged, method, timeout);
+                // Loaded images no longer need the file; where it is still
+                // mapped (Windows) it is left for `clean_stale_artifacts`.
+                if let LibrarySource::Path(path) = staged.source() {
+                    let _ = std::fs::remove_file(path);
+                }
+                result
+            }
+            result => result,
+        }
+    }
+
+    fn load(
+        &self,
+        process: Process,
+        library: &Library,
+        method: Option<InjectionMethod>,
+        timeout: Option<Duration>,
+    ) -> Result<u64> {
+        match (method, timeout) {
+            (Some(method), _) => self.inner.inject_process_by(process, library, method),
+            (None, Some(timeout)) => self.inner.inject_process_within(process, library, timeout),
+            (None, None) => self.inner.inject_process(process, library),
+        }
+    }
+
+    /// Call the configure entrypoint of `library`, already injected into
+    /// `process`, with `data`, once its main entrypoint has reported by
+    /// `clock`.
+    pub(crate) fn configure(
+        &self,
+        process: Process,
+        library: &Library,
+        data: &CStr,
+        clock: &dyn Clock,
+    ) -> Result<()> {
+        if let EntryStatus::Failed(code) =
+            status::wait_for_entry(process, library, CONFIGURE_WAIT, clock)?
+        {
+            return Err(Error::runtime(format_args!(
+                "agent entrypoint failed with code {code}; not configuring it"
+            )));
+        }
+        self.call_entrypoint(process, library, library.configure_entrypoint(), data)
+            .map(|_| ())
+    }
+
+    /// Stage `bytes` like `Library::with_data_bytes`, where `options` say,
+    /// and pass the file's path to the configure entrypoint of `library`.
+    pub(crate) fn update_data(
+        &self,
+        process: Process,
+        library: &Library,
+        bytes: Vec<u8>,
+        options: &InjectOptions,
+    ) -> Result<()> {
+        staging::prepare(options)?;
+        let staged =
+            staging::stage_data(&library.clone().with_data_bytes(bytes), process, options)?;
+        let result = self.configure(process, library, staged.data(), options.clock_value());
+        if result.is_err() {
+            let _ = std::fs::remove_file(staged.data().to_string_lossy().as_ref());
+        }
+        result
+    }
+
+    /// Call the exported function `symbol` of `library`, already injected
+    /// into `process`, with `data`.
+    pub(crate) fn call(
+        &self,
+        process: Process,
+        library: &Library,
+        symbol: &CStr,
+        data: &CStr,
+    ) -> Result<()> {
+        self.call_entrypoint(process, library, symbol, data)
+            .map(|_| ())
+    }
+
+    /// Call the shutdown entrypoint of `library`, if it has one and is still
+    /// loaded in `process`, and give it `SHUTDOWN_WAIT` to return.
+    pub(crate) fn shutdown(&self, process: Process, library: &Library) -> Result<()> {
+        let Some(symbol) = library.shutdown_entrypoint() else {
+            return Ok(());
+        };
+        // Agents on a remote device cannot be called again.
+        if self.inner.remote() {
+            return Ok(());
+        }
+        if !status::is_loaded(process, library)? {
+            return Ok(());
+        }
+        let call = self.call_entrypoint(process, library, symbol, c"")?;
+        // The runtime reports the call unloaded once it returns; an agent
+        // that takes longer is demonitored anyway.
+        let _ = self.wait_until_unloaded(InjectionId::new(call), SHUTDOWN_WAIT);
+        Ok(())
+    }
+
+    /// Have the agent tear itself down, then wait by `clock` for its image
+    /// to be unmapped and stop monitoring it.
+    pub(crate) fn eject(
+        &self,
+        process: Process,
+        id: InjectionId,
+        library: &Library,
+        clock: &dyn Clock,
+    ) -> Result<()> {
+        self.call_entrypoint(process, library, library.unload_entrypoint(), c"")?;
+
+        let unloaded = poll_until(clock, Some(EJECT_WAIT), EJECT_POLL_INTERVAL, || {
+            Ok((!status::is_loaded(process, library)?).then_some(()))
+        })?;
+        if unloaded.is_none() {
+            return Err(Error::runtime(format_args!(
+                "library is still loaded in pid {} after `{}` returned; the agent must release \
+                 whatever keeps it resident",
+                process.pid(),
+                library.unload_entrypoint().to_string_lossy()
+            )));
+        }
+        // The runtime does not see an agent unload itself.
+        unload::unloaded(id.as_u64());
+        self.uninject(process, id)
+    }
+
+    /// Call `symbol` in `library`, already injected into `process`, and
+    /// return the runtime's id for the call.
+    ///
+    /// The library is loaded again from the same file, which the loader
+    /// resolves to the image already mapped; the runtime drops that extra
+    /// reference when the call returns.
+    fn call_entrypoint(
+        &self,
+        process: Process,
+        library: &Library,
+        symbol: &CStr,
+        data: &CStr,
+    ) -> Result<u64> {
+        if self.inner.remote() {
+            return Err(Error::not_supported(format_args!(
+                "calling `{}` is not supported on a remote device",
+                symbol.to_string_lossy()
+            )));
+        }
+        if !matches!(library.source(), LibrarySource::Path(_)) {
+            return Err(Error::not_supported(format_args!(
+                "calling `{}` requires a library injected from a file path",
+                symbol.to_string_lossy()
+            )));
+        }
+        library.check_export(symbol)?;
+
+        // Only the main entrypoint gets the unload policy header.
+        let call = library
+            .clone()
+            .with_entrypoint(symbol)?
+            .with_data(data)?
+            .with_unload_policy(UnloadPolicy::Agent);
+        self.inner.inject_process(process, &call)
+    }
+
+    pub(crate) fn spawn(
+        &self,
+        mut spec: Program,
+        options: &InjectOptions,
+    ) -> Result<crate::SuspendedProgram> {
         let stdio = spec.stdio_value();
-        self.inner
-            .spawn(&mut spec)
-            .map(|process| SuspendedProgram::new(self.clone(), process, stdio))
+        let timeout = options.timeout_value();
+        if spec.needs_native_spawn() && self.inner.remote() {
+            return Err(Error::not_supported(
+                "programs on a remote device are launched by frida-server, without pre_exec \
+                 hooks, native stdio, a `Command`'s own settings or a suspend point",
+            ));
+        }
+        if spec.needs_native_spawn() || !self.inner.spawns() {
+            // A runtime that cannot launch programs may also need the
+            // program's libraries mapped before it can inject.
+            let default = if self.inner.spawns() {
+                SuspendPoint::LoaderEntry
+            } else {
+                spawn::preferred_point()
+            };
+            let point = spec.suspend_point().unwrap_or(default);
+            let native =
+                spawn::spawn_held(spec.into_command(), point, timeout, options.clock_value())?;
+            let process = unsafe { Process::from_pid_unchecked(native.id() as i32) };
+            return Ok(SuspendedProgram::new(self.clone(), process, stdio)
+                .with_native(native)
+                .with_timeout(timeout));
+        }
+
+        self.inner.spawn(&mut spec, timeout).map(|process| {
+            SuspendedProgram::new(self.clone(), process, stdio).with_timeout(timeout)
+        })
+    }
+
+    pub(crate) fn resume(&self, process: Process, timeout: Option<Duration>) -> Result<()> {
+        self.inner.resume(process, timeout)
+    }
+
+    pub(crate) fn kill(&self, process: Process) -> Result<()> {
+        self.inner.kill(process)
+    }
+
+    pub(crate) fn input(&self, process: Process, data: &[u8]) -> Result<()> {
+        self.inner.input(process, data)
+    }
+
+    pub(crate) fn enable_spawn_gating(&self) -> Result<()> {
+        self.inner.enable_spawn_gating()
     }
 
-    pub(crate) fn resume(&self, process: Process) -> Result<()> {
-        self.inner.resume(process)
+    pub(crate) fn disable_spawn_gating(&self) -> Result<()> {
+        self.inner.disable_spawn_gating()
     }
+
+    pub(crate) fn next_spawn(&self, timeout: Duration) -> Result<Option<(Process, String)>> {
+        self.inner.next_spawn(timeout)
+    }
+}
+
+// Only runtimes that came up are kept, so a failed init is retried.
+static RUNTIMES: Mutex<Vec<(Backend, BackendHandle)>> = Mutex::new(Vec::new());
+// Frida runtimes for remote and USB devices.
+static REMOTE_RUNTIMES: Mutex<Vec<(DeviceSpec, BackendHandle)>> = Mutex::new(Vec::new());
+// Set by `shutdown`; Frida cannot be initialized twice.
+static SHUT_DOWN: AtomicBool = AtomicBool::new(false);
+static WATCHERS: Mutex<Vec<Watcher>> = Mutex::new(Vec::new());
+// Bumped to stop the watchers started before it.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Version of the linked frida-core, if it reports one.
+pub(crate) fn frida_version() -> Option<String> {
+    #[cfg(feature = "frida")]
+    return frida::version();
+    #[cfg(not(feature = "frida"))]
+    None
 }
 
-static BACKEND: OnceLock<Result<BackendHandle>> = OnceLock::new();
+#[cfg(feature = "dynamic-frida")]
+pub(crate) fn load_frida_core(path: &std::path::Path) -> Result<()> {
+    frida::load(Some(path))
+}
 
 pub(crate) fn default_backend() -> Result<BackendHandle> {
-    if let Some(existing) = BACKEND.get() {
-        return existing.clone();
+    runtime(Backend::Frida)
+}
+
+/// The runtime of `backend`, set up on first use and shared afterwards.
+pub(crate) fn runtime(backend: Backend) -> Result<BackendHandle> {
+    // Held across init so concurrent first calls set up one runtime.
+    let mut runtimes = lock(&RUNTIMES);
+    if let Some((_, existing)) = runtimes.iter().find(|(kind, _)| *kind == backend) {
+        return Ok(existing.clone());
+    }
+    if backend == Backend::Frida && SHUT_DOWN.load(Ordering::SeqCst) {
+        return Err(Error::runtime_unavailable(
+            "the runtime was shut down and cannot be set up again",
+        ));
+    }
+
+    // Startup janitor: best effort, a failed sweep must not block injection.
+    let _ = staging::sweep(&staging::staging_dir(&InjectOptions::default()));
+
+    let handle = match backend {
+        #[cfg(feature = "frida")]
+        Backend::Frida => BackendHandle::new(frida::init()?),
+        #[cfg(all(
+            target_os = "linux",
+            any(target_arch = "x86_64", target_arch = "aarch64")
+        ))]
+        Backend::Ptrace => BackendHandle::new(ptrace::PtraceBackend::new()),
+        #[cfg(all(
+            any(target_os = "linux", target_os = "macos"),
+            feature = "preload-backend"
+        ))]
+        Backend::Preload => BackendHandle::new(preload::PreloadBackend::new()),
+        #[cfg(all(windows, any(target_arch = "x86_64", target_arch = "aarch64")))]
+        Backend::LoadLibrary => BackendHandle::new(loadlibrary::LoadLibraryBackend::new()),
+        #[cfg(all(
+            target_os = "macos",
+            any(target_arch = "x86_64", target_arch = "aarch64")
+        ))]
+        Backend::TaskPort => BackendHandle::new(taskport::TaskPortBackend::new()),
+        #[allow(unreachable_patterns)]
+        _ => return Err(crate::injector::unsupported(backend.name())),
+    };
+    runtimes.push((backend, handle.clone()));
+    Ok(handle)
+}
+
+/// The Frida runtime for the device `spec` names, connected on first use
+/// and shared afterwards. `timeout` bounds connecting.
+#[cfg(feature = "frida")]
+pub(crate) fn remote_runtime(
+    spec: &DeviceSpec,
+    timeout: Option<Duration>,
+) -> Result<BackendHandle> {
+    let mut remotes = lock(&REMOTE_RUNTIMES);
+    if let Some((_, existing)) = remotes.iter().find(|(known, _)| known == spec) {
+        return Ok(existing.clone());
+    }
+    if SHUT_DOWN.load(Ordering::SeqCst) {
+        return Err(Error::runtime_unavailable(
+            "the runtime was shut down and cannot be set up again",
+        ));
+    }
+    let handle = BackendHandle::new(frida::init_device(spec, timeout)?);
+    remotes.push((spec.clone(), handle.clone()));
+    Ok(handle)
+}
+
+#[cfg(not(feature = "frida"))]
+pub(crate) fn remote_runtime(
+    _spec: &DeviceSpec,
+    _timeout: Option<Duration>,
+) -> Result<BackendHandle> {
+    Err(crate::injector::unsupported(Backend::Frida.name()))
+}
+
+/// The devices Frida can reach.
+pub(crate) fn devices() -> Result<Vec<Device>> {
+    if SHUT_DOWN.load(Ordering::SeqCst) {
+        return Err(Error::runtime_unavailable(
+            "the runtime was shut down and cannot be set up again",
+        ));
+    }
+    #[cfg(feature = "frida")]
+    return frida::devices();
+    #[cfg(not(feature = "frida"))]
+    Err(crate::injector::unsupported(Backend::Frida.name()))
+}
+
+/// Tear the runtimes down, joining their threads.
+///
+/// Does nothing if none was set up. Fails, leaving them running, while
+/// injectors or handles still use one.
+pub(crate) fn shutdown() -> Result<()> {
+    let mut runtimes = lock(&RUNTIMES);
+    let mut remotes = lock(&REMOTE_RUNTIMES);
+    if runtimes.is_empty() && remotes.is_empty() {
+        return Ok(());
+    }
+    // Watchers hold a runtime while polling, so stop them before counting.
+    let stopped = stop_watchers();
+    let users: usize = runtimes
+        .iter()
+        .map(|(_, handle)| handle)
+        .chain(remotes.iter().map(|(_, handle)| handle))
+        .map(|handle| Arc::strong_count(&handle.inner) - 1)
+        .sum();
+    if users > 0 {
+        for (name, runtime, poll) in stopped {
+            start_watcher(name, runtime, poll);
+        }
+        return Err(Error::invalid_input(format_args!(
+            "the runtime is still used by {users} injectors or handles; drop them first"
+        )));
     }
+    let frida = !remotes.is_empty()
+        || runtimes
+            .iter()
+            .any(|(backend, _)| *backend == Backend::Frida);
+    runtimes.clear();
+    remotes.clear();
+    if frida {
+        SHUT_DOWN.store(true, Ordering::SeqCst);
+        #[cfg(feature = "frida")]
+        frida::shutdown();
+    }
+    Ok(())
+}
+
+type Poll = fn(&BackendHandle);
+
+struct Watcher {
+    name: &'static str,
+    runtime: Weak<dyn Engine>,
+    poll: Poll,
+    thread: JoinHandle<()>,
+}
+
+/// Call `poll` with the runtime from a background thread named `name`,
+/// over and over, until the runtime is shut down or dropped.
+///
+/// `poll` should block for a while when there is nothing to do.
+pub(crate) fn spawn_watcher(name: &'static str, backend: &BackendHandle, poll: Poll) {
+    start_watcher(name, Arc::downgrade(&backend.inner), poll);
+}
+
+/// `spawn_watcher`, unless the runtime already has a watcher named `name`.
+pub(crate) fn ensure_watcher(name: &'static str, backend: &BackendHandle, poll: Poll) {
+    let runtime = Arc::downgrade(&backend.inner);
+    {
+        let mut watchers = lock(&WATCHERS);
+        // A dropped runtime's watcher has returned; let it go.
+        watchers.retain(|watcher| watcher.runtime.strong_count() > 0);
+        if watchers
+            .iter()
+            .any(|watcher| watcher.name == name && watcher.runtime.ptr_eq(&runtime))
+        {
+            return;
+        }
+    }
+    start_watcher(name, runtime, poll);
+}
 
-    let handle = frida::init().map(BackendHandle::new);
+fn start_watcher(name: &'static str, runtime: Weak<dyn Engine>, poll: Poll) {
+    let mut watchers = lock(&WATCHERS);
+    let generation = GENERATION.load(Ordering::SeqCst);
+    let watched = runtime.clone();
+    let thread = std::thread::Builder::new()
+        .name(name.into())
+        .spawn(move || {
+            while GENERATION.load(Ordering::SeqCst) == generation {
+                let Some(inner) = watched.upgrade() else {
+                    return;
+                };
+                poll(&BackendHandle { inner });
+            }
+        })
+        .unwrap_or_else(|err| panic!("failed to spawn {name} thread: {err}"));
+    watchers.push(Watcher {
+        name,
+        runtime,
+        poll,
+        thread,
+    });
+}
 
-    let _ = BACKEND.set(handle.clone());
-    handle
+/// Stop and join the running watchers, returning what they were.
+fn stop_watchers() -> Vec<(&'static str, Weak<dyn Engine>, Poll)> {
+    // Not held while joining: a watcher may be starting another.
+    let watchers = {
+        let mut watchers = lock(&WATCHERS);
+        GENERATION.fetch_add(1, Ordering::SeqCst);
+        std::mem::take(&mut *watchers)
+    };
+    watchers
+        .into_iter()
+        .map(|watcher| {
+            let _ = watcher.thread.join();
+            (watcher.name, watcher.runtime, watcher.poll)
+        })
+        .collect()
 }
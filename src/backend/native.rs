@@ -0,0 +1,182 @@
+// Pieces shared by the native backends, which load the library with the
+// target's own loader and run its entrypoint on a thread they start there.
+//
+// That thread runs a small trampoline over a context the backend writes into
+// the target. It calls the entrypoint the way Frida does (`data`, a pointer
+// to the unload policy, and a null state), unloads the library unless the
+// agent asked to stay resident, and records the outcome in the context,
+// which is how unloads are noticed. The memory holding the trampoline is
+// never freed: the thread is still running its last instructions after the
+// unload.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::clock::poll_until;
+use crate::{SystemClock, maps, process};
+
+// How often `next_uninjected` checks the live injections.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// The context: the entrypoint, its data, the unload function, the library
+// handle, the unload policy the entrypoint sets (4 bytes) and the outcome.
+pub(super) const CTX_LEN: u64 = 48;
+const CTX_OUTCOME: u64 = 40;
+// The outcome once the trampoline has unloaded the library.
+const UNLOADED: u64 = 2;
+
+// System V: push rbx; mov rbx, rdi
+// mov rdi, [rbx+8]; lea rsi, [rbx+32]; xor edx, edx; call [rbx]
+// mov eax, [rbx+32]; test eax, eax; jnz 1f
+// mov rdi, [rbx+24]; call [rbx+16]; mov eax, 2; jmp 2f
+// 1: mov eax, 1
+// 2: mov [rbx+40], rax; xor eax, eax; pop rbx; ret
+#[cfg(all(target_arch = "x86_64", not(windows)))]
+pub(super) const TRAMPOLINE: &[u8] = &[
+    0x53, 0x48, 0x89, 0xfb, 0x48, 0x8b, 0x7b, 0x08, 0x48, 0x8d, 0x73, 0x20, 0x31, 0xd2, 0xff, 0x13,
+    0x8b, 0x43, 0x20, 0x85, 0xc0, 0x75, 0x0e, 0x48, 0x8b, 0x7b, 0x18, 0xff, 0x53, 0x10, 0xb8, 0x02,
+    0x00, 0x00, 0x00, 0xeb, 0x05, 0xb8, 0x01, 0x00, 0x00, 0x00, 0x48, 0x89, 0x43, 0x28, 0x31, 0xc0,
+    0x5b, 0xc3,
+];
+
+// Win64, with shadow space for the callees: push rbx; sub rsp, 32
+// mov rbx, rcx; mov rcx, [rbx+8]; lea rdx, [rbx+32]; xor r8d, r8d
+// call [rbx]; mov eax, [rbx+32]; test eax, eax; jnz 1f
+// mov rcx, [rbx+24]; call [rbx+16]; mov eax, 2; jmp 2f
+// 1: mov eax, 1
+// 2: mov [rbx+40], rax; xor eax, eax; add rsp, 32; pop rbx; ret
+#[cfg(all(target_arch = "x86_64", windows))]
+pub(super) const TRAMPOLINE: &[u8] = &[
+    0x53, 0x48, 0x83, 0xec, 0x20, 0x48, 0x89, 0xcb, 0x48, 0x8b, 0x4b, 0x08, 0x48, 0x8d, 0x53, 0x20,
+    0x45, 0x31, 0xc0, 0xff, 0x13, 0x8b, 0x43, 0x20, 0x85, 0xc0, 0x75, 0x0e, 0x48, 0x8b, 0x4b, 0x18,
+    0xff, 0x53, 0x10, 0xb8, 0x02, 0x00, 0x00, 0x00, 0xeb, 0x05, 0xb8, 0x01, 0x00, 0x00, 0x00, 0x48,
+    0x89, 0x43, 0x28, 0x31, 0xc0, 0x48, 0x83, 0xc4, 0x20, 0x5b, 0xc3,
+];
+
+// AAPCS64, which Windows shares (x18 is left alone):
+// stp x29, x30, [sp, #-32]!; str x19, [sp, #16]; mov x19, x0
+// ldr x0, [x19, #8]; add x1, x19, #32; mov x2, xzr; ldr x8, [x19]; blr x8
+// ldr w8, [x19, #32]; cbnz w8, 1f
+// ldr x0, [x19, #24]; ldr x8, [x19, #16]; blr x8; mov x8, #2; b 2f
+// 1: mov x8, #1
+// 2: str x8, [x19, #40]; mov x0, xzr; ldr x19, [sp, #16]
+//    ldp x29, x30, [sp], #32; ret
+#[cfg(target_arch = "aarch64")]
+pub(super) const TRAMPOLINE: &[u8] = &[
+    0xfd, 0x7b, 0xbe, 0xa9, 0xf3, 0x0b, 0x00, 0xf9, 0xf3, 0x03, 0x00, 0xaa, 0x60, 0x06, 0x40, 0xf9,
+    0x61, 0x82, 0x00, 0x91, 0xe2, 0x03, 0x1f, 0xaa, 0x68, 0x02, 0x40, 0xf9, 0x00, 0x01, 0x3f, 0xd6,
+    0x68, 0x22, 0x40, 0xb9, 0xc8, 0x00, 0x00, 0x35, 0x60, 0x0e, 0x40, 0xf9, 0x68, 0x0a, 0x40, 0xf9,
+    0x00, 0x01, 0x3f, 0xd6, 0x48, 0x00, 0x80, 0xd2, 0x02, 0x00, 0x00, 0x14, 0x28, 0x00, 0x80, 0xd2,
+    0x68, 0x16, 0x00, 0xf9, 0xe0, 0x03, 0x1f, 0xaa, 0xf3, 0x0b, 0x40, 0xf9, 0xfd, 0x7b, 0xc2, 0xa8,
+    0xc0, 0x03, 0x5f, 0xd6,
+];
+
+/// The trampoline's context, for a library loaded as `handle` that `unload`
+/// takes, and whose entrypoint is at `entry` with its data at `data`.
+pub(super) fn context(entry: u64, data: u64, unload: u64, handle: u64) -> Vec<u8> {
+    let mut context = Vec::with_capacity(CTX_LEN as usize);
+    for word in [entry, data, unload, handle, 0, 0] {
+        context.extend_from_slice(&word.to_ne_bytes());
+    }
+    context
+}
+
+/// The injections a native backend started, watched for their unload.
+pub(super) struct Injections {
+    next_id: AtomicU64,
+    live: Mutex<HashMap<u64, Live>>,
+}
+
+/// A monitored injection.
+struct Live {
+    pid: i32,
+    start_time: Option<u64>,
+    // Address of the trampoline's outcome word in the target.
+    outcome: u64,
+}
+
+impl Live {
+    fn has_unloaded(&self) -> bool {
+        // The target exiting takes the library with it.
+        if process::has_exited(self.pid, self.start_time) {
+            return true;
+        }
+        let mut word = [0u8; 8];
+        maps::read_memory(self.pid, self.outcome, &mut word).is_ok()
+            && u64::from_ne_bytes(word) == UNLOADED
+    }
+}
+
+impl Injections {
+    /// Ids start at `first_id`, which keeps them apart from other backends'.
+    pub(super) fn new(first_id: u64) -> Self {
+        Self {
+            next_id: AtomicU64::new(first_id),
+            live: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Watch the trampoline whose context is at `ctx` in `pid`, returning
+    /// the injection id.
+    pub(super) fn insert(&self, pid: i32, ctx: u64) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        lock(&self.live).insert(
+            id,
+            Live {
+                pid,
+                start_time: process::start_time(pid),
+                outcome: ctx + CTX_OUTCOME,
+            },
+        );
+        id
+    }
+
+    pub(super) fn remove(&self, id: u64) {
+        lock(&self.live).remove(&id);
+    }
+
+    /// Wait up to `timeout` for an injection to unload, and stop watching it.
+    pub(super) fn next_unloaded(&self, timeout: Duration) -> Option<u64> {
+        poll_until(&SystemClock, Some(timeout), POLL_INTERVAL, || {
+            Ok(self.take_unloaded())
+        })
+        .unwrap_or(None)
+    }
+
+    fn take_unloaded(&self) -> Option<u64> {
+        let mut live = lock(&self.live);
+        let id = live
+            .iter()
+            .find(|(_, injection)| injection.has_unloaded())
+            .map(|(id, _)| *id)?;
+        live.remove(&id);
+        Some(id)
+    }
+}
+
+/// Read a NUL-terminated string from the target, stopping at 4 KiB.
+#[cfg(unix)]
+pub(super) fn read_string(pid: i32, address: u64) -> String {
+    let mut bytes = Vec::new();
+    let mut chunk = [0u8; 64];
+    while bytes.len() < 4096
+        && maps::read_memory(pid, address + bytes.len() as u64, &mut chunk).is_ok()
+    {
+        match chunk.iter().position(|&byte| byte == 0) {
+            Some(end) => {
+                bytes.extend_from_slice(&chunk[..end]);
+                break;
+            }
+            None => bytes.extend_from_slice(&chunk),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+pub(super) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,312 @@
+// Injection through the dynamic loader for `Backend::Preload`.
+//
+// Programs are launched with the `hook-inject-preload` loader, built and
+// embedded by the build script, first in `LD_PRELOAD` (on macOS,
+// `DYLD_INSERT_LIBRARIES`). The dynamic loader maps it before any of the
+// program's own code runs, and its constructor loads the library the
+// `HOOK_INJECT_PRELOAD_*` variables name, runs the entrypoint on the main
+// thread and unloads it unless the agent stays resident. Each step is
+// written to a status file in the staging directory, which is how loads and
+// unloads are noticed. Nothing is traced and no task port is needed, so
+// YAMA's ptrace restrictions and missing entitlements don't get in the way;
+// by the same token, running processes cannot be reached.
+
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use super::{Engine, lock};
+use crate::clock::poll_until;
+use crate::library::LibrarySource;
+use crate::{
+    Clock, Error, InjectOptions, Library, Process, Program, Result, SystemClock, image, process,
+    spawn, staging,
+};
+
+// Above Frida's 32-bit ids and clear of the ptrace backend's, which count
+// up from 1 << 32, so injections of all of them can share the registry.
+const FIRST_ID: u64 = 1 << 48;
+
+// The loader, built from `hook-inject-preload` by the build script.
+const LOADER: &[u8] = include_bytes!(concat!(
+    env!("OUT_DIR"),
+    "/hook-inject-agent-hook-inject-preload.bin"
+));
+
+// What the loader reads; it takes them out of the program's environment.
+const LIBRARY_ENV: &str = "HOOK_INJECT_PRELOAD_LIBRARY";
+const ENTRYPOINT_ENV: &str = "HOOK_INJECT_PRELOAD_ENTRYPOINT";
+const DATA_ENV: &str = "HOOK_INJECT_PRELOAD_DATA";
+const STATUS_ENV: &str = "HOOK_INJECT_PRELOAD_STATUS";
+
+// The variable that has the dynamic loader load the loader.
+#[cfg(target_os = "linux")]
+const PRELOAD_ENV: &str = "LD_PRELOAD";
+#[cfg(target_os = "macos")]
+const PRELOAD_ENV: &str = "DYLD_INSERT_LIBRARIES";
+#[cfg(target_os = "linux")]
+const LOADER_SUFFIX: &str = "so";
+#[cfg(target_os = "macos")]
+const LOADER_SUFFIX: &str = "dylib";
+
+// How long the loader gets to report, unless the call sets a timeout.
+const LOAD_WAIT: Duration = Duration::from_secs(10);
+// How often the status files are checked.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+pub(super) struct PreloadBackend {
+    next_id: AtomicU64,
+    live: Mutex<HashMap<u64, Live>>,
+}
+
+/// A monitored injection.
+struct Live {
+    pid: i32,
+    start_time: Option<u64>,
+    status: PathBuf,
+}
+
+impl Live {
+    fn has_unloaded(&self) -> bool {
+        // The target exiting takes the library with it.
+        process::has_exited(self.pid, self.start_time) || read_status(&self.status) == "unloaded"
+    }
+}
+
+impl Drop for Live {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.status);
+    }
+}
+
+impl PreloadBackend {
+    pub(super) fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(FIRST_ID),
+            live: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn take_unloaded(&self) -> Option<u64> {
+        let mut live = lock(&self.live);
+        let id = live
+            .iter()
+            .find(|(_, injection)| injection.has_unloaded())
+            .map(|(id, _)| *id)?;
+        live.remove(&id);
+        Some(id)
+    }
+}
+
+impl Engine for PreloadBackend {
+    fn name(&self) -> &'static str {
+        "preload"
+    }
+
+    fn preloads(&self) -> bool {
+        true
+    }
+
+    fn inject_process(&self, _process: Process, _library: &Library) -> Result<u64> {
+        Err(Error::not_supported(
+            "the preload backend only injects into programs it launches",
+        ))
+    }
+
+    fn inject_preloaded(
+        &self,
+        spec: Program,
+        library: &Library,
+        options: &InjectOptions,
+    ) -> Result<(std::process::Child, u64)> {
+        let LibrarySource::Path(path) = library.source() else {
+            return Err(Error::not_supported(
+                "the preload backend loads libraries from files",
+            ));
+        };
+        if library.data_bytes().is_some() {
+            return Err(Error::not_supported(
+                "the preload backend passes entrypoint data in the environment, which cannot \
+                 hold binary data",
+            ));
+        }
+        if let Ok(program) = spec.resolved_program()
+            && let Some(reason) = ignores_preload(&program)
+        {
+            return Err(Error::not_supported(format_args!(
+                "{} {reason}",
+                program.display()
+            )));
+        }
+        let path = std::path::absolute(path)?;
+        let data = library.entry_data()?;
+
+        let status = staging::stage_bytes(options, "status", b"")?;
+        let loader = match staging::stage_bytes(options, LOADER_SUFFIX, LOADER) {
+            Ok(loader) => loader,
+            Err(err) => {
+                let _ = std::fs::remove_file(&status);
+                return Err(err);
+            }
+        };
+        let mut cmd = spec.into_command();
+        let mut preload = OsString::from(&loader);
+        if let Some(existing) = inherited(&cmd, PRELOAD_ENV).filter(|value| !value.is_empty()) {
+            preload.push(":");
+            preload.push(existing);
+        }
+        cmd.env(PRELOAD_ENV, preload)
+            .env(LIBRARY_ENV, &path)
+            .env(
+                ENTRYPOINT_ENV,
+                OsStr::from_bytes(library.entrypoint().to_bytes()),
+            )
+            .env(DATA_ENV, OsStr::from_bytes(data.to_bytes()))
+            .env(STATUS_ENV, &status);
+
+        let timeout = options.timeout_value().unwrap_or(LOAD_WAIT);
+        let launched = cmd.spawn().map_err(Error::from).and_then(|mut child| {
+            match wait_loaded(&mut child, &status, timeout, options.clock_value()) {
+                Ok(()) => Ok(child),
+                Err(err) => {
+                    // Injection failed, so the program is not left running.
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    Err(err)
+                }
+            }
+        });
+        // The dynamic loader has mapped it, or never will.
+        let _ = std::fs::remove_file(&loader);
+        let child = match launched {
+            Ok(child) => child,
+            Err(err) => {
+                let _ = std::fs::remove_file(&status);
+                return Err(err);
+            }
+        };
+
+        let pid = child.id() as i32;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        lock(&self.live).insert(
+            id,
+            Live {
+                pid,
+                start_time: process::start_time(pid),
+                status,
+            },
+        );
+        Ok((child, id))
+    }
+
+    fn uninject(&self, id: u64) -> Result<()> {
+        lock(&self.live).remove(&id);
+        Ok(())
+    }
+
+    fn next_uninjected(&self, timeout: Duration) -> Result<Option<u64>> {
+        poll_until(&SystemClock, Some(timeout), POLL_INTERVAL, || {
+            Ok(self.take_unloaded())
+        })
+    }
+
+    // Programs from `spawn` are launched natively, held by SIGSTOP.
+    fn resume(&self, process: Process, _timeout: Option<Duration>) -> Result<()> {
+        spawn::release(process)
+    }
+
+    fn kill(&self, process: Process) -> Result<()> {
+        if unsafe { libc::kill(process.pid(), libc::SIGKILL) } == -1 {
+            return Err(match std::io::Error::last_os_error() {
+                err if err.raw_os_error() == Some(libc::ESRCH) => {
+                    Error::process_not_found(process.pid())
+                }
+                err => Error::from(err),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Wait up to `timeout` on `clock` for the loader in `child` to report
+/// that the library is loaded.
+fn wait_loaded(
+    child: &mut std::process::Child,
+    status: &Path,
+    timeout: Duration,
+    clock: &dyn Clock,
+) -> Result<()> {
+    let pid = child.id();
+    let loaded = poll_until(clock, Some(timeout), POLL_INTERVAL, || {
+        // Checked first: the status is final once the program has exited.
+        let exited = child.try_wait()?;
+        match read_status(status).as_str() {
+            "" => {}
+            text => {
+                return match text.strip_prefix("failed: ") {
+                    Some(reason) => Err(Error::runtime(format_args!(
+                        "failed to load the library before pid {pid} started: {reason}"
+                    ))),
+                    None => Ok(Some(())),
+                };
+            }
+        }
+        match exited {
+            Some(exit) => Err(Error::runtime(format_args!(
+                "pid {pid} exited ({exit}) without loading the library; the dynamic loader may \
+                 have ignored {PRELOAD_ENV}"
+            ))),
+            None => Ok(None),
+        }
+    })?;
+    loaded.ok_or_else(|| {
+        Error::timed_out(format_args!(
+            "pid {pid} did not load the library within {timeout:?}; the dynamic loader may have \
+             ignored {PRELOAD_ENV}"
+        ))
+    })
+}
+
+fn read_status(path: &Path) -> String {
+    std::fs::read_to_string(path).unwrap_or_default()
+}
+
+/// The value the program launched by `cmd` gets for `name`.
+fn inherited(cmd: &Command, name: &str) -> Option<OsString> {
+    match cmd.get_envs().find(|(key, _)| *key == name) {
+        Some((_, value)) => value.map(OsStr::to_os_string),
+        None => std::env::var_os(name),
+    }
+}
+
+/// Why the dynamic loader would not load the loader into `program`, as a
+/// clause to follow its name, or `None` if it can't tell.
+fn ignores_preload(program: &Path) -> Option<&'static str> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // S_ISUID | S_ISGID
+    if std::fs::metadata(program).ok()?.permissions().mode() & 0o6000 != 0 {
+        return Some(if cfg!(target_os = "macos") {
+            "is setuid or setgid, so dyld ignores DYLD_INSERT_LIBRARIES"
+        } else {
+            "is setuid or setgid, so the dynamic loader ignores LD_PRELOAD"
+        });
+    }
+    let bytes = std::fs::read(program).ok()?;
+    #[cfg(target_os = "macos")]
+    return image::dyld_restriction(&bytes);
+    #[cfg(target_os = "linux")]
+    {
+        // Without dependencies, the dynamic loader never runs.
+        crate::arch::identify(&bytes)?;
+        let deps = image::dependencies(&bytes).ok()?;
+        deps.needed
+            .is_empty()
+            .then_some("is statically linked, so nothing loads what LD_PRELOAD names")
+    }
+}
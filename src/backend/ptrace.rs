@@ -0,0 +1,624 @@
+// Native Linux injection for `Backend::Ptrace`.
+//
+// The target's main thread is seized with ptrace and made to call into its
+// own libc: `mmap` for a scratch mapping, `dlopen` and `dlsym` for the
+// library and its entrypoint, then `pthread_create` to run the trampoline
+// (see `native`) on a thread of its own, with `dlclose` to unload. Each
+// call returns to address 0, and the fault that follows hands control back;
+// the thread's registers are then restored and it is detached.
+
+use std::ffi::{CStr, CString, c_void};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::ptr::null_mut;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::Engine;
+use super::native::{self, CTX_LEN, Injections, lock};
+use crate::library::LibrarySource;
+use crate::{Architecture, Error, Library, Process, Result, image, maps, spawn};
+
+// Above Frida's 32-bit ids, so injections of both can share the registry.
+const FIRST_ID: u64 = 1 << 32;
+
+// After the trampoline's context, at the start of the data after the code:
+// where `pthread_create` stores the thread, then the strings.
+const THREAD_ID: u64 = CTX_LEN;
+const STRINGS: u64 = THREAD_ID + 8;
+
+// Libraries that may provide the functions injection calls.
+const LIBC_PREFIXES: &[&str] = &[
+    "libc.so",
+    "libc-",
+    "libdl.so",
+    "libdl-",
+    "libpthread.so",
+    "libpthread-",
+    "ld-musl-",
+];
+
+pub(super) struct PtraceBackend {
+    // One injection at a time: a thread has a single tracer.
+    attach: Mutex<()>,
+    injections: Injections,
+}
+
+impl PtraceBackend {
+    pub(super) fn new() -> Self {
+        Self {
+            attach: Mutex::new(()),
+            injections: Injections::new(FIRST_ID),
+        }
+    }
+}
+
+impl Engine for PtraceBackend {
+    fn name(&self) -> &'static str {
+        "ptrace"
+    }
+
+    fn inject_process(&self, process: Process, library: &Library) -> Result<u64> {
+        let LibrarySource::Path(path) = library.source() else {
+            return Err(Error::not_supported(
+                "the ptrace backend loads libraries from files",
+            ));
+        };
+        let target = process.architecture()?;
+        if Some(target) != Architecture::native() {
+            return Err(Error::not_supported(format_args!(
+                "the ptrace backend cannot inject into a {target} process from this build"
+            )));
+        }
+        let path = std::path::absolute(path)?;
+        let data = library.entry_data()?;
+
+        let pid = process.pid();
+        let symbols = Symbols::resolve(pid)?;
+        let ctx = {
+            let _attach = lock(&self.attach);
+            inject(pid, &symbols, &path, library.entrypoint(), &data)?
+        };
+        Ok(self.injections.insert(pid, ctx))
+    }
+
+    fn uninject(&self, id: u64) -> Result<()> {
+        self.injections.remove(id);
+        Ok(())
+    }
+
+    fn next_uninjected(&self, timeout: Duration) -> Result<Option<u64>> {
+        Ok(self.injections.next_unloaded(timeout))
+    }
+
+    // Programs are launched natively, held by SIGSTOP.
+    fn resume(&self, process: Process, _timeout: Option<Duration>) -> Result<()> {
+        spawn::release(process)
+    }
+
+    fn kill(&self, process: Process) -> Result<()> {
+        if unsafe { libc::kill(process.pid(), libc::SIGKILL) } == -1 {
+            return Err(match std::io::Error::last_os_error() {
+                err if err.raw_os_error() == Some(libc::ESRCH) => {
+                    Error::process_not_found(process.pid())
+                }
+                err => Error::from(err),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Make `pid` load `path` and start its entrypoint, returning the address of
+/// the trampoline's context.
+fn inject(pid: i32, symbols: &Symbols, path: &Path, entrypoint: &CStr, data: &CStr) -> Result<u64> {
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|err| Error::invalid_input(format_args!("library_path: {err}")))?;
+    let mut strings = Vec::new();
+    for string in [path.as_c_str(), entrypoint, data] {
+        strings.extend_from_slice(string.to_bytes_with_nul());
+    }
+    let page = page_size();
+    let data_len = (STRINGS + strings.len() as u64).next_multiple_of(page);
+
+    let mut tracee = Tracee::attach(pid)?;
+    let base = tracee.call(
+        symbols.mmap,
+        &[
+            0,
+            page + data_len,
+            (libc::PROT_READ | libc::PROT_WRITE) as u64,
+            (libc::MAP_PRIVATE | libc::MAP_ANONYMOUS) as u64,
+            u64::MAX,
+            0,
+        ],
+    )?;
+    if base == 0 || base == libc::MAP_FAILED as u64 {
+        return Err(Error::runtime(format_args!(
+            "failed to map memory in pid {pid}"
+        )));
+    }
+    write_memory(pid, base, native::TRAMPOLINE)?;
+    let protect = (libc::PROT_READ | libc::PROT_EXEC) as u64;
+    if tracee.call(symbols.mprotect, &[base, page, protect])? != 0 {
+        return Err(Error::runtime(format_args!(
+            "failed to make the trampoline executable in pid {pid}"
+        )));
+    }
+
+    let ctx = base + page;
+    let path_at = ctx + STRINGS;
+    let entrypoint_at = path_at + path.as_bytes_with_nul().len() as u64;
+    let data_at = entrypoint_at + entrypoint.to_bytes_with_nul().len() as u64;
+    write_memory(pid, path_at, &strings)?;
+
+    let handle = tracee.call(symbols.dlopen, &[path_at, libc::RTLD_NOW as u64])?;
+    if handle == 0 {
+        let reason = match symbols.dlerror {
+            Some(dlerror) => match tracee.call(dlerror, &[])? {
+                0 => "unknown error".to_string(),
+                message => native::read_string(pid, message),
+            },
+            None => "unknown error".to_string(),
+        };
+        return Err(Error::runtime(format_args!(
+            "failed to load library into pid {pid}: {reason}"
+        )));
+    }
+    let entry = tracee.call(symbols.dlsym, &[handle, entrypoint_at])?;
+    if entry == 0 {
+        let _ = tracee.call(symbols.dlclose, &[handle]);
+        return Err(Error::invalid_input(format_args!(
+            "library does not export `{}`",
+            entrypoint.to_string_lossy()
+        )));
+    }
+
+    let context = native::context(entry, data_at, symbols.dlclose, handle);
+    write_memory(pid, ctx, &context)?;
+    let created = tracee.call(symbols.pthread_create, &[ctx + THREAD_ID, 0, base, ctx])?;
+    if created as i32 != 0 {
+        let _ = tracee.call(symbols.dlclose, &[handle]);
+        return Err(Error::runtime(format_args!(
+            "failed to start the entrypoint thread in pid {pid} (error {})",
+            created as i32
+        )));
+    }
+    let mut thread = [0u8; 8];
+    maps::read_memory(pid, ctx + THREAD_ID, &mut thread)?;
+    tracee.call(symbols.pthread_detach, &[u64::from_ne_bytes(thread)])?;
+    Ok(ctx)
+}
+
+/// Addresses of the functions injection calls, in the target.
+struct Symbols {
+    mmap: u64,
+    mprotect: u64,
+    dlopen: u64,
+    dlsym: u64,
+    dlclose: u64,
+    dlerror: Option<u64>,
+    pthread_create: u64,
+    pthread_detach: u64,
+}
+
+impl Symbols {
+    fn resolve(pid: i32) -> Result<Self> {
+        let modules = libc_modules(pid)?;
+        let find = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| modules.iter().find_map(|module| module.address(name)))
+        };
+        let require = |names: &[&str]| {
+            find(names).ok_or_else(|| {
+                Error::not_supported(format_args!(
+                    "pid {pid} has no `{}` to call; libc may not be loaded yet (hold programs \
+                     at their entrypoint rather than the loader's)",
+                    names[0]
+                ))
+            })
+        };
+        Ok(Self {
+            mmap: require(&["mmap"])?,
+            mprotect: require(&["mprotect"])?,
+            // Older glibc only has the private variants unless libdl is loaded.
+            dlopen: require(&["dlopen", "__libc_dlopen_mode"])?,
+            dlsym: require(&["dlsym", "__libc_dlsym"])?,
+            dlclose: require(&["dlclose", "__libc_dlclose"])?,
+            dlerror: find(&["dlerror"]),
+            pthread_create: require(&["pthread_create"])?,
+            pthread_detach: require(&["pthread_detach"])?,
+        })
+    }
+}
+
+struct Module {
+    base: u64,
+    bytes: Vec<u8>,
+}
+
+impl Module {
+    fn address(&self, symbol: &str) -> Option<u64> {
+        let offset = image::export_offset(&self.bytes, symbol).ok()??;
+        Some(self.base.wrapping_add(offset))
+    }
+}
+
+/// The libc libraries mapped in `pid`, read from the target's view of the
+/// filesystem.
+fn libc_modules(pid: i32) -> Result<Vec<Module>> {
+    let mut found: Vec<(PathBuf, u64)> = Vec::new();
+    for region in maps::memory_maps(pid)? {
+        let Some(path) = region.path() else {
+            continue;
+        };
+        let is_libc = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| LIBC_PREFIXES.iter().any(|prefix| name.starts_with(prefix)));
+        if is_libc && region.offset() == 0 && !found.iter().any(|(known, _)| known == path) {
+            found.push((path.to_path_buf(), region.start()));
+        }
+    }
+
+    found
+        .into_iter()
+        .map(|(path, base)| {
+            // Through the target's root, which differs inside containers.
+            let rooted = Path::new(&format!("/proc/{pid}/root"))
+                .join(path.strip_prefix("/").unwrap_or(&path));
+            let bytes = std::fs::read(rooted).or_else(|_| std::fs::read(&path))?;
+            Ok(Module { base, bytes })
+        })
+        .collect()
+}
+
+/// The target's main thread, seized and stopped; dropping it restores the
+/// thread's registers and lets it go.
+struct Tracee {
+    pid: i32,
+    saved: arch::Saved,
+    // A signal that arrived as the thread stopped, delivered on detach.
+    signal: i32,
+}
+
+impl Tracee {
+    fn attach(pid: i32) -> Result<Self> {
+        ptrace(libc::PTRACE_SEIZE, pid, null_mut(), null_mut())
+            .map_err(|err| attach_error(pid, err))?;
+        let stopped = ptrace(libc::PTRACE_INTERRUPT, pid, null_mut(), null_mut())
+            .map_err(|err| attach_error(pid, err))
+            .and_then(|()| wait_stop(pid))
+            .and_then(|signal| {
+                Ok(Self {
+                    pid,
+                    saved: arch::save(pid)?,
+                    signal,
+                })
+            });
+        if stopped.is_err() {
+            let _ = ptrace(libc::PTRACE_DETACH, pid, null_mut(), null_mut());
+        }
+        stopped
+    }
+
+    /// Call `function` with `args` on the stopped thread and return its
+    /// result.
+    fn call(&mut self, function: u64, args: &[u64]) -> Result<u64> {
+        let pid = self.pid;
+        let mut regs = arch::regs(&self.saved);
+        // Below the red zone, 16-byte aligned.
+        let stack = (arch::sp(&regs) - 256) & !0xf;
+        arch::set_call(pid, &mut regs, function, args, stack)?;
+        arch::set_regs(pid, &regs)?;
+        // Not in a system call, so resuming does not restart one.
+        arch::clear_syscall(pid)?;
+
+        let mut deliver = 0;
+        loop {
+            ptrace(libc::PTRACE_CONT, pid, null_mut(), deliver as *mut c_void)
+                .map_err(|err| Error::runtime(format_args!("failed to resume pid {pid}: {err}")))?;
+            deliver = 0;
+            let status = wait(pid)?;
+            if !libc::WIFSTOPPED(status) {
+                return Err(Error::runtime(format_args!(
+                    "pid {pid} exited during injection (wait status {status:#x})"
+                )));
+            }
+            let signal = libc::WSTOPSIG(status);
+            if status >> 16 == libc::PTRACE_EVENT_STOP {
+                continue;
+            }
+            if signal == libc::SIGSEGV {
+                let regs = arch::get_regs(pid)?;
+                if arch::pc(&regs) == 0 {
+                    return Ok(arch::result(&regs));
+                }
+                return Err(Error::runtime(format_args!(
+                    "call into pid {pid} crashed at {:#x}",
+                    arch::pc(&regs)
+                )));
+            }
+            // Let the target handle anything else and carry on.
+            deliver = signal;
+        }
+    }
+}
+
+impl Drop for Tracee {
+    fn drop(&mut self) {
+        let _ = arch::restore(self.pid, &self.saved);
+        let _ = ptrace(
+            libc::PTRACE_DETACH,
+            self.pid,
+            null_mut(),
+            self.signal as usize as *mut c_void,
+        );
+    }
+}
+
+fn ptrace(
+    request: libc::c_uint,
+    pid: i32,
+    addr: *mut c_void,
+    data: *mut c_void,
+) -> std::io::Result<()> {
+    if unsafe { libc::ptrace(request, pid, addr, data) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn attach_error(pid: i32, err: std::io::Error) -> Error {
+    match err.raw_os_error() {
+        Some(libc::ESRCH) => Error::process_not_found(pid),
+        Some(libc::EPERM) => Error::permission_denied(format_args!(
+            "cannot trace pid {pid}: {err} (it may already be traced, or \
+             kernel.yama.ptrace_scope may forbid it)"
+        )),
+        _ => Error::runtime(format_args!("failed to trace pid {pid}: {err}")),
+    }
+}
+
+/// Wait for the thread to stop after `PTRACE_INTERRUPT`, returning a signal
+/// that stopped it first, if any.
+fn wait_stop(pid: i32) -> Result<i32> {
+    let status = wait(pid)?;
+    if !libc::WIFSTOPPED(status) {
+        return Err(Error::process_not_found(pid));
+    }
+    if status >> 16 == libc::PTRACE_EVENT_STOP {
+        return Ok(0);
+    }
+    Ok(libc::WSTOPSIG(status))
+}
+
+fn wait(pid: i32) -> Result<libc::c_int> {
+    let mut status: libc::c_int = 0;
+    loop {
+        if unsafe { libc::waitpid(pid, &mut status, libc::__WALL) } == pid {
+            return Ok(status);
+        }
+        let err = std::io::Error::last_os_error();
+        if err.kind() != std::io::ErrorKind::Interrupted {
+            return Err(Error::from(err));
+        }
+    }
+}
+
+fn write_memory(pid: i32, address: u64, bytes: &[u8]) -> Result<()> {
+    use std::os::unix::fs::FileExt;
+
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .open(format!("/proc/{pid}/mem"))?;
+    file.write_all_at(bytes, address).map_err(|err| {
+        Error::runtime(format_args!(
+            "failed to write {} bytes at {address:#x} in pid {pid}: {err}",
+            bytes.len()
+        ))
+    })
+}
+
+fn page_size() -> u64 {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as u64 }
+}
+
+fn get_regset<T>(pid: i32, set: libc::c_int, value: &mut T) -> Result<()> {
+    let mut iov = libc::iovec {
+        iov_base: (value as *mut T).cast(),
+        iov_len: size_of::<T>(),
+    };
+    let iov: *mut libc::iovec = &mut iov;
+    ptrace(
+        libc::PTRACE_GETREGSET,
+        pid,
+        set as usize as *mut c_void,
+        iov.cast(),
+    )
+    .map_err(|err| Error::runtime(format_args!("failed to read registers of pid {pid}: {err}")))
+}
+
+fn set_regset<T>(pid: i32, set: libc::c_int, value: &T) -> Result<()> {
+    let mut iov = libc::iovec {
+        iov_base: (value as *const T).cast_mut().cast(),
+        iov_len: size_of::<T>(),
+    };
+    let iov: *mut libc::iovec = &mut iov;
+    ptrace(
+        libc::PTRACE_SETREGSET,
+        pid,
+        set as usize as *mut c_void,
+        iov.cast(),
+    )
+    .map_err(|err| Error::runtime(format_args!("failed to set registers of pid {pid}: {err}")))
+}
+
+#[cfg(target_arch = "x86_64")]
+mod arch {
+    use super::{get_regset, set_regset, write_memory};
+    use crate::Result;
+
+    pub(super) type Regs = libc::user_regs_struct;
+    // `orig_rax`, the system call resuming would restart, is a register.
+    pub(super) type Saved = Regs;
+
+    pub(super) fn get_regs(pid: i32) -> Result<Regs> {
+        let mut regs: Regs = unsafe { std::mem::zeroed() };
+        get_regset(pid, libc::NT_PRSTATUS, &mut regs)?;
+        Ok(regs)
+    }
+
+    pub(super) fn set_regs(pid: i32, regs: &Regs) -> Result<()> {
+        set_regset(pid, libc::NT_PRSTATUS, regs)
+    }
+
+    pub(super) fn save(pid: i32) -> Result<Saved> {
+        get_regs(pid)
+    }
+
+    pub(super) fn restore(pid: i32, saved: &Saved) -> Result<()> {
+        set_regs(pid, saved)
+    }
+
+    pub(super) fn regs(saved: &Saved) -> Regs {
+        *saved
+    }
+
+    // `set_call` clears `orig_rax`.
+    pub(super) fn clear_syscall(_pid: i32) -> Result<()> {
+        Ok(())
+    }
+
+    pub(super) fn pc(regs: &Regs) -> u64 {
+        regs.rip
+    }
+
+    pub(super) fn sp(regs: &Regs) -> u64 {
+        regs.rsp
+    }
+
+    pub(super) fn result(regs: &Regs) -> u64 {
+        regs.rax
+    }
+
+    /// Set up a call of `function` that returns to address 0, with `stack`
+    /// as the 16-byte aligned stack top.
+    pub(super) fn set_call(
+        pid: i32,
+        regs: &mut Regs,
+        function: u64,
+        args: &[u64],
+        stack: u64,
+    ) -> Result<()> {
+        let sp = stack - 8;
+        write_memory(pid, sp, &0u64.to_ne_bytes())?;
+        let slots = [
+            &mut regs.rdi,
+            &mut regs.rsi,
+            &mut regs.rdx,
+            &mut regs.rcx,
+            &mut regs.r8,
+            &mut regs.r9,
+        ];
+        for (slot, &arg) in slots.into_iter().zip(args) {
+            *slot = arg;
+        }
+        regs.rip = function;
+        regs.rsp = sp;
+        // No vector registers are used by variadic callees.
+        regs.rax = 0;
+        regs.orig_rax = u64::MAX;
+        Ok(())
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod arch {
+    use super::{get_regset, set_regset};
+    use crate::Result;
+
+    // <asm/ptrace.h>: struct user_pt_regs.
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    pub(super) struct Regs {
+        regs: [u64; 31],
+        sp: u64,
+        pc: u64,
+        pstate: u64,
+    }
+
+    /// The registers, and the system call resuming would restart.
+    pub(super) struct Saved {
+        regs: Regs,
+        syscall: i32,
+    }
+
+    const NT_ARM_SYSTEM_CALL: libc::c_int = 0x404;
+
+    pub(super) fn get_regs(pid: i32) -> Result<Regs> {
+        let mut regs = Regs {
+            regs: [0; 31],
+            sp: 0,
+            pc: 0,
+            pstate: 0,
+        };
+        get_regset(pid, libc::NT_PRSTATUS, &mut regs)?;
+        Ok(regs)
+    }
+
+    pub(super) fn set_regs(pid: i32, regs: &Regs) -> Result<()> {
+        set_regset(pid, libc::NT_PRSTATUS, regs)
+    }
+
+    pub(super) fn save(pid: i32) -> Result<Saved> {
+        let mut syscall: i32 = -1;
+        get_regset(pid, NT_ARM_SYSTEM_CALL, &mut syscall)?;
+        Ok(Saved {
+            regs: get_regs(pid)?,
+            syscall,
+        })
+    }
+
+    pub(super) fn restore(pid: i32, saved: &Saved) -> Result<()> {
+        set_regs(pid, &saved.regs)?;
+        set_regset(pid, NT_ARM_SYSTEM_CALL, &saved.syscall)
+    }
+
+    pub(super) fn regs(saved: &Saved) -> Regs {
+        saved.regs
+    }
+
+    pub(super) fn clear_syscall(pid: i32) -> Result<()> {
+        set_regset(pid, NT_ARM_SYSTEM_CALL, &-1i32)
+    }
+
+    pub(super) fn pc(regs: &Regs) -> u64 {
+        regs.pc
+    }
+
+    pub(super) fn sp(regs: &Regs) -> u64 {
+        regs.sp
+    }
+
+    pub(super) fn result(regs: &Regs) -> u64 {
+        regs.regs[0]
+    }
+
+    /// Set up a call of `function` that returns to address 0, with `stack`
+    /// as the 16-byte aligned stack top.
+    pub(super) fn set_call(
+        _pid: i32,
+        regs: &mut Regs,
+        function: u64,
+        args: &[u64],
+        stack: u64,
+    ) -> Result<()> {
+        regs.regs[..args.len()].copy_from_slice(args);
+        regs.regs[30] = 0;
+        regs.sp = stack;
+        regs.pc = function;
+        Ok(())
+    }
+}
@@ -0,0 +1,583 @@
+// Native macOS injection for `Backend::TaskPort`.
+//
+// With the target's task port from `task_for_pid`, memory is allocated and
+// written there with the `mach_vm_*` calls, and a bare Mach thread is started
+// with `thread_create_running`. Such a thread has no pthread state, so all
+// it does is start a real one with `pthread_create_from_mach_thread` and
+// terminate itself. The pthread runs the loader: it detaches, calls `dlopen`
+// and `dlsym`, records the outcome in the context, and carries on into the
+// trampoline (see `native`) with `dlclose` to unload. Injection waits for
+// that record, so load failures are reported by the call.
+//
+// The dyld shared cache, with libSystem in it, is mapped at the same address
+// in every process of an architecture, so the addresses of its functions here
+// are theirs in the target too; that is checked before anything is written.
+
+use std::ffi::{CStr, CString, c_int};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::time::Duration;
+
+use super::Engine;
+use super::native::{self, CTX_LEN, Injections};
+use crate::clock::poll_until;
+use crate::library::LibrarySource;
+use crate::{
+    Architecture, Error, Library, Process, Result, SystemClock, arch, maps, process, spawn,
+};
+
+// Above Frida's 32-bit ids, so injections of both can share the registry.
+const FIRST_ID: u64 = 1 << 32;
+// Apple silicon's page size, a multiple of Intel's.
+const PAGE: u64 = 0x4000;
+// The bare thread's stack, after the context and strings.
+const STACK_LEN: u64 = 0x10000;
+// Where the loader and bootstrap go in the trampoline's page.
+const CODE_AT: u64 = 0x100;
+// How long `dlopen`, and the library's initializers with it, may take.
+const LOAD_WAIT: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// After the trampoline's context, for the loader and bootstrap: `dlopen`,
+// `dlsym`, `dlerror`, `pthread_create_from_mach_thread`, `pthread_self`,
+// `pthread_detach`, `mach_thread_self` and `thread_terminate`, then the path,
+// the entrypoint's name, the trampoline, the loader, the pthread, the load
+// status and the `dlerror` message.
+const TASK_CTX_LEN: u64 = CTX_LEN + 120;
+const CTX_STATUS: u64 = CTX_LEN + 104;
+const CTX_ERROR: u64 = CTX_LEN + 112;
+// Load statuses the loader records; any other is a failed load.
+const LOADED: u64 = 1;
+const NO_ENTRYPOINT: u64 = 3;
+
+const KERN_SUCCESS: c_int = 0;
+const VM_FLAGS_ANYWHERE: c_int = 1;
+const VM_PROT_READ: c_int = 0x1;
+const VM_PROT_EXECUTE: c_int = 0x4;
+const TASK_DYLD_INFO: c_int = 17;
+
+unsafe extern "C" {
+    fn mach_vm_allocate(
+        task: libc::mach_port_t,
+        address: *mut u64,
+        size: u64,
+        flags: c_int,
+    ) -> c_int;
+    fn mach_vm_deallocate(task: libc::mach_port_t, address: u64, size: u64) -> c_int;
+    fn mach_vm_write(task: libc::mach_port_t, address: u64, data: usize, count: u32) -> c_int;
+    fn mach_vm_protect(
+        task: libc::mach_port_t,
+        address: u64,
+        size: u64,
+        set_maximum: u32,
+        protection: c_int,
+    ) -> c_int;
+    fn task_info(
+        task: libc::mach_port_t,
+        flavor: c_int,
+        info: *mut c_int,
+        count: *mut u32,
+    ) -> c_int;
+    fn thread_create_running(
+        task: libc::mach_port_t,
+        flavor: c_int,
+        state: *const u32,
+        count: u32,
+        thread: *mut libc::mach_port_t,
+    ) -> c_int;
+    fn mach_port_deallocate(task: libc::mach_port_t, name: libc::mach_port_t) -> c_int;
+}
+
+pub(super) struct TaskPortBackend {
+    injections: Injections,
+}
+
+impl TaskPortBackend {
+    pub(super) fn new() -> Self {
+        Self {
+            injections: Injections::new(FIRST_ID),
+        }
+    }
+}
+
+impl Engine for TaskPortBackend {
+    fn name(&self) -> &'static str {
+        "taskport"
+    }
+
+    fn inject_process(&self, process: Process, library: &Library) -> Result<u64> {
+        let LibrarySource::Path(path) = library.source() else {
+            return Err(Error::not_supported(
+                "the taskport backend loads libraries from files",
+            ));
+        };
+        let target = process.architecture()?;
+        if Some(target) != Architecture::native() {
+            return Err(Error::not_supported(format_args!(
+                "the taskport backend cannot inject into a {target} process from this build"
+            )));
+        }
+        // The loader's calls would need signed pointers.
+        if arch::is_arm64e(process.pid())? {
+            return Err(Error::not_supported(
+                "the taskport backend cannot inject into arm64e processes",
+            ));
+        }
+        let path = std::path::absolute(path)?;
+        let data = library.entry_data()?;
+
+        let pid = process.pid();
+        let libsystem = LibSystem::resolve()?;
+        let ctx = inject(pid, &libsystem, &path, library.entrypoint(), &data)?;
+        Ok(self.injections.insert(pid, ctx))
+    }
+
+    fn uninject(&self, id: u64) -> Result<()> {
+        self.injections.remove(id);
+        Ok(())
+    }
+
+    fn next_uninjected(&self, timeout: Duration) -> Result<Option<u64>> {
+        Ok(self.injections.next_unloaded(timeout))
+    }
+
+    // Programs are launched natively, held by SIGSTOP.
+    fn resume(&self, process: Process, _timeout: Option<Duration>) -> Result<()> {
+        spawn::release(process)
+    }
+
+    fn kill(&self, process: Process) -> Result<()> {
+        if unsafe { libc::kill(process.pid(), libc::SIGKILL) } == -1 {
+            return Err(match std::io::Error::last_os_error() {
+                err if err.raw_os_error() == Some(libc::ESRCH) => {
+                    Error::process_not_found(process.pid())
+                }
+                err => Error::from(err),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Make `pid` load `path` and start its entrypoint, returning the address of
+/// the trampoline's context.
+fn inject(
+    pid: i32,
+    libsystem: &LibSystem,
+    path: &Path,
+    entrypoint: &CStr,
+    data: &CStr,
+) -> Result<u64> {
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|err| Error::invalid_input(format_args!("library_path: {err}")))?;
+    let mut strings = Vec::new();
+    for string in [path.as_c_str(), entrypoint, data] {
+        strings.extend_from_slice(string.to_bytes_with_nul());
+    }
+
+    let task = Task::open(pid)?;
+    task.check_initialized()?;
+    libsystem.check_shared(pid)?;
+
+    let data_len = (TASK_CTX_LEN + strings.len() as u64).next_multiple_of(PAGE);
+    let len = PAGE + data_len + STACK_LEN;
+    let base = task.allocate(len)?;
+    let ctx = base + PAGE;
+    let path_at = ctx + TASK_CTX_LEN;
+    let entrypoint_at = path_at + path.as_bytes_with_nul().len() as u64;
+    let data_at = entrypoint_at + entrypoint.to_bytes_with_nul().len() as u64;
+
+    let mut page = native::TRAMPOLINE.to_vec();
+    page.resize(CODE_AT as usize, 0);
+    page.extend_from_slice(code::CODE);
+    // The loader fills in the entrypoint and handle.
+    let mut context = native::context(0, data_at, libsystem.dlclose, 0);
+    for word in [
+        libsystem.dlopen,
+        libsystem.dlsym,
+        libsystem.dlerror,
+        libsystem.pthread_create_from_mach_thread,
+        libsystem.pthread_self,
+        libsystem.pthread_detach,
+        libsystem.mach_thread_self,
+        libsystem.thread_terminate,
+        path_at,
+        entrypoint_at,
+        base,
+        base + CODE_AT,
+        0,
+        0,
+        0,
+    ] {
+        context.extend_from_slice(&word.to_ne_bytes());
+    }
+    let started = task
+        .write(base, &page)
+        .and_then(|()| task.write(ctx, &context))
+        .and_then(|()| task.write(path_at, &strings))
+        .and_then(|()| task.protect(base, PAGE, VM_PROT_READ | VM_PROT_EXECUTE))
+        .and_then(|()| task.start(base + CODE_AT + code::BOOTSTRAP, ctx, base + len));
+    // Nothing in the target refers to the memory until the thread starts.
+    if let Err(err) = started {
+        task.free(base, len);
+        return Err(err);
+    }
+
+    // The memory stays: the threads are still in its code when they finish.
+    match wait_loaded(pid, ctx)? {
+        LOADED => Ok(ctx),
+        NO_ENTRYPOINT => Err(Error::invalid_input(format_args!(
+            "library does not export `{}`",
+            entrypoint.to_string_lossy()
+        ))),
+        _ => {
+            let mut message = [0u8; 8];
+            maps::read_memory(pid, ctx + CTX_ERROR, &mut message)?;
+            let reason = match u64::from_ne_bytes(message) {
+                0 => "failed to start a thread to load it".to_string(),
+                message => native::read_string(pid, message),
+            };
+            Err(Error::runtime(format_args!(
+                "failed to load library into pid {pid}: {reason}"
+            )))
+        }
+    }
+}
+
+/// Wait for the loader to record its status in the context at `ctx`.
+fn wait_loaded(pid: i32, ctx: u64) -> Result<u64> {
+    let status = poll_until(&SystemClock, Some(LOAD_WAIT), POLL_INTERVAL, || {
+        let mut status = [0u8; 8];
+        if maps::read_memory(pid, ctx + CTX_STATUS, &mut status).is_err()
+            && process::has_exited(pid, None)
+        {
+            return Err(Error::process_not_found(pid));
+        }
+        Ok(match u64::from_ne_bytes(status) {
+            0 => None,
+            status => Some(status),
+        })
+    })?;
+    status.ok_or_else(|| {
+        Error::timed_out(format_args!(
+            "library did not load into pid {pid} within {LOAD_WAIT:?}"
+        ))
+    })
+}
+
+/// Addresses of the libSystem functions injection runs, in every process.
+struct LibSystem {
+    dlopen: u64,
+    dlsym: u64,
+    dlclose: u64,
+    dlerror: u64,
+    pthread_create_from_mach_thread: u64,
+    pthread_self: u64,
+    pthread_detach: u64,
+    mach_thread_self: u64,
+    thread_terminate: u64,
+}
+
+impl LibSystem {
+    fn resolve() -> Result<Self> {
+        let address = |name: &CStr| {
+            let address = unsafe { libc::dlsym(libc::RTLD_DEFAULT, name.as_ptr()) };
+            if address.is_null() {
+                return Err(Error::runtime(format_args!(
+                    "libSystem does not export `{}`",
+                    name.to_string_lossy()
+                )));
+            }
+            Ok(address as u64)
+        };
+        Ok(Self {
+            dlopen: address(c"dlopen")?,
+            dlsym: address(c"dlsym")?,
+            dlclose: address(c"dlclose")?,
+            dlerror: address(c"dlerror")?,
+            pthread_create_from_mach_thread: address(c"pthread_create_from_mach_thread")?,
+            pthread_self: address(c"pthread_self")?,
+            pthread_detach: address(c"pthread_detach")?,
+            mach_thread_self: address(c"mach_thread_self")?,
+            thread_terminate: address(c"thread_terminate")?,
+        })
+    }
+
+    /// Fail unless `pid` has the shared cache where this process does, which
+    /// a process launched to have its own slide does not.
+    fn check_shared(&self, pid: i32) -> Result<()> {
+        let mut theirs = [0u8; 16];
+        let same = maps::read_memory(pid, self.dlopen, &mut theirs).is_ok() && {
+            // SAFETY: `dlopen` is mapped code here.
+            let ours = unsafe { std::slice::from_raw_parts(self.dlopen as *const u8, 16) };
+            theirs == ours
+        };
+        if !same {
+            return Err(Error::not_supported(format_args!(
+                "pid {pid} does not share this process's dyld shared cache"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The target's task port.
+struct Task {
+    pid: i32,
+    port: libc::mach_port_t,
+}
+
+impl Task {
+    fn open(pid: i32) -> Result<Self> {
+        Ok(Self {
+            pid,
+            port: maps::task_port(pid)?,
+        })
+    }
+
+    /// Fail unless dyld has initialized libSystem in the target, which a
+    /// program held before it has run has not.
+    fn check_initialized(&self) -> Result<()> {
+        // <mach/task_info.h>: task_dyld_info is declared under
+        // `#pragma pack(4)`.
+        #[repr(C, packed(4))]
+        #[derive(Default)]
+        #[allow(dead_code)]
+        struct TaskDyldInfo {
+            all_image_info_addr: u64,
+            all_image_info_size: u64,
+            all_image_info_format: c_int,
+        }
+        // <mach-o/dyld_images.h>: `libSystemInitialized` in
+        // dyld_all_image_infos.
+        const LIBSYSTEM_INITIALIZED: u64 = 25;
+
+        let mut info = TaskDyldInfo::default();
+        let mut count = (std::mem::size_of::<TaskDyldInfo>() / 4) as u32;
+        let kr = unsafe {
+            task_info(
+                self.port,
+                TASK_DYLD_INFO,
+                &mut info as *mut _ as *mut c_int,
+                &mut count,
+            )
+        };
+        let address = info.all_image_info_addr;
+        let mut initialized = [0u8];
+        if kr != KERN_SUCCESS
+            || address == 0
+            || maps::read_memory(self.pid, address + LIBSYSTEM_INITIALIZED, &mut initialized)
+                .is_err()
+            || initialized[0] == 0
+        {
+            return Err(Error::not_supported(format_args!(
+                "pid {} has not initialized libSystem yet; the taskport backend cannot inject \
+                 into programs held before dyld has run",
+                self.pid
+            )));
+        }
+        Ok(())
+    }
+
+    fn allocate(&self, len: u64) -> Result<u64> {
+        let mut address = 0;
+        let kr = unsafe { mach_vm_allocate(self.port, &mut address, len, VM_FLAGS_ANYWHERE) };
+        if kr != KERN_SUCCESS {
+            return Err(Error::runtime(format_args!(
+                "failed to allocate memory in pid {} (kern_return_t {kr})",
+                self.pid
+            )));
+        }
+        Ok(address)
+    }
+
+    fn free(&self, address: u64, len: u64) {
+        unsafe { mach_vm_deallocate(self.port, address, len) };
+    }
+
+    fn write(&self, address: u64, bytes: &[u8]) -> Result<()> {
+        let kr = unsafe {
+            mach_vm_write(
+                self.port,
+                address,
+                bytes.as_ptr() as usize,
+                bytes.len() as u32,
+            )
+        };
+        if kr != KERN_SUCCESS {
+            return Err(Error::runtime(format_args!(
+                "failed to write {} bytes at {address:#x} in pid {} (kern_return_t {kr})",
+                bytes.len(),
+                self.pid
+            )));
+        }
+        Ok(())
+    }
+
+    fn protect(&self, address: u64, len: u64, protection: c_int) -> Result<()> {
+        let kr = unsafe { mach_vm_protect(self.port, address, len, 0, protection) };
+        if kr != KERN_SUCCESS {
+            return Err(Error::runtime(format_args!(
+                "failed to make the trampoline executable in pid {} (kern_return_t {kr})",
+                self.pid
+            )));
+        }
+        Ok(())
+    }
+
+    /// Start a bare thread at `function`, with `arg` and the stack ending at
+    /// `stack`.
+    fn start(&self, function: u64, arg: u64, stack: u64) -> Result<()> {
+        // The state is counted in 32-bit words, low half first.
+        let state: Vec<u32> = code::registers(function, arg, stack)
+            .into_iter()
+            .flat_map(|register| [register as u32, (register >> 32) as u32])
+            .collect();
+        let mut thread = 0;
+        let kr = unsafe {
+            thread_create_running(
+                self.port,
+                code::THREAD_STATE_FLAVOR,
+                state.as_ptr(),
+                state.len() as u32,
+                &mut thread,
+            )
+        };
+        if kr != KERN_SUCCESS {
+            return Err(Error::runtime(format_args!(
+                "failed to start a thread in pid {} (kern_return_t {kr})",
+                self.pid
+            )));
+        }
+        unsafe { mach_port_deallocate(libc::mach_task_self(), thread) };
+        Ok(())
+    }
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        maps::release_task_port(self.port);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod code {
+    use std::ffi::c_int;
+
+    // The loader is at the start.
+    pub(super) const BOOTSTRAP: u64 = 0x86;
+
+    // <mach/i386/thread_status.h>: x86_THREAD_STATE64, whose 21 registers
+    // run rax, rbx, rcx, rdx, rdi, rsi, rbp, rsp, r8-r15, rip, rflags, cs,
+    // fs, gs.
+    pub(super) const THREAD_STATE_FLAVOR: c_int = 4;
+    const RDI: usize = 4;
+    const RSP: usize = 7;
+    const RIP: usize = 16;
+
+    pub(super) fn registers(pc: u64, arg: u64, stack: u64) -> [u64; 21] {
+        let mut registers = [0; 21];
+        registers[RDI] = arg;
+        registers[RSP] = stack - 16;
+        registers[RIP] = pc;
+        registers
+    }
+
+    // loader: push rbx; mov rbx, rdi
+    //   call [rbx+80]; mov rdi, rax; call [rbx+88]
+    //   mov rdi, [rbx+112]; mov esi, 2; call [rbx+48]; test rax, rax; jz 2f
+    //   mov [rbx+24], rax; mov rdi, rax; mov rsi, [rbx+120]; call [rbx+56]
+    //   test rax, rax; jz 1f
+    //   mov [rbx], rax; mov qword [rbx+152], 1
+    //   mov rdi, rbx; pop rbx; jmp [rdi+128]
+    //   1: mov rdi, [rbx+24]; call [rbx+16]
+    //   mov qword [rbx+40], 2; mov qword [rbx+152], 3; jmp 3f
+    //   2: call [rbx+64]; mov [rbx+160], rax
+    //   mov qword [rbx+40], 2; mov qword [rbx+152], 2
+    //   3: xor eax, eax; pop rbx; ret
+    // bootstrap: mov rbx, rdi; and rsp, -16
+    //   lea rdi, [rbx+144]; xor esi, esi; mov rdx, [rbx+136]; mov rcx, rbx
+    //   call [rbx+72]; test eax, eax; jz 4f
+    //   mov qword [rbx+40], 2; mov qword [rbx+152], 2
+    //   4: call [rbx+96]; mov edi, eax; call [rbx+104]
+    //   5: jmp 5b
+    pub(super) const CODE: &[u8] = &[
+        0x53, 0x48, 0x89, 0xfb, 0xff, 0x53, 0x50, 0x48, 0x89, 0xc7, 0xff, 0x53, 0x58, 0x48, 0x8b,
+        0x7b, 0x70, 0xbe, 0x02, 0x00, 0x00, 0x00, 0xff, 0x53, 0x30, 0x48, 0x85, 0xc0, 0x74, 0x47,
+        0x48, 0x89, 0x43, 0x18, 0x48, 0x89, 0xc7, 0x48, 0x8b, 0x73, 0x78, 0xff, 0x53, 0x38, 0x48,
+        0x85, 0xc0, 0x74, 0x18, 0x48, 0x89, 0x03, 0x48, 0xc7, 0x83, 0x98, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x48, 0x89, 0xdf, 0x5b, 0xff, 0xa7, 0x80, 0x00, 0x00, 0x00, 0x48, 0x8b,
+        0x7b, 0x18, 0xff, 0x53, 0x10, 0x48, 0xc7, 0x43, 0x28, 0x02, 0x00, 0x00, 0x00, 0x48, 0xc7,
+        0x83, 0x98, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0xeb, 0x1d, 0xff, 0x53, 0x40, 0x48,
+        0x89, 0x83, 0xa0, 0x00, 0x00, 0x00, 0x48, 0xc7, 0x43, 0x28, 0x02, 0x00, 0x00, 0x00, 0x48,
+        0xc7, 0x83, 0x98, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x31, 0xc0, 0x5b, 0xc3, 0x48,
+        0x89, 0xfb, 0x48, 0x83, 0xe4, 0xf0, 0x48, 0x8d, 0xbb, 0x90, 0x00, 0x00, 0x00, 0x31, 0xf6,
+        0x48, 0x8b, 0x93, 0x88, 0x00, 0x00, 0x00, 0x48, 0x89, 0xd9, 0xff, 0x53, 0x48, 0x85, 0xc0,
+        0x74, 0x13, 0x48, 0xc7, 0x43, 0x28, 0x02, 0x00, 0x00, 0x00, 0x48, 0xc7, 0x83, 0x98, 0x00,
+        0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0xff, 0x53, 0x60, 0x89, 0xc7, 0xff, 0x53, 0x68, 0xeb,
+        0xfe,
+    ];
+}
+
+// x18 is left alone, as Darwin reserves it.
+#[cfg(target_arch = "aarch64")]
+mod code {
+    use std::ffi::c_int;
+
+    // The loader is at the start.
+    pub(super) const BOOTSTRAP: u64 = 0xac;
+
+    // <mach/arm/thread_status.h>: ARM_THREAD_STATE64, which is x0-x28, fp,
+    // lr, sp, pc, then cpsr and padding in a last word.
+    pub(super) const THREAD_STATE_FLAVOR: c_int = 6;
+    const X0: usize = 0;
+    const SP: usize = 31;
+    const PC: usize = 32;
+
+    pub(super) fn registers(pc: u64, arg: u64, stack: u64) -> [u64; 34] {
+        let mut registers = [0; 34];
+        registers[X0] = arg;
+        registers[SP] = stack - 16;
+        registers[PC] = pc;
+        registers
+    }
+
+    // loader: stp x29, x30, [sp, #-32]!; str x19, [sp, #16]; mov x19, x0
+    //   ldr x8, [x19, #80]; blr x8; ldr x8, [x19, #88]; blr x8
+    //   ldr x0, [x19, #112]; mov w1, #2; ldr x8, [x19, #48]; blr x8
+    //   cbz x0, 2f
+    //   str x0, [x19, #24]; ldr x1, [x19, #120]; ldr x8, [x19, #56]; blr x8
+    //   cbz x0, 1f
+    //   str x0, [x19]; mov x8, #1; str x8, [x19, #152]
+    //   mov x0, x19; ldr x8, [x19, #128]; ldr x19, [sp, #16]
+    //   ldp x29, x30, [sp], #32; br x8
+    //   1: ldr x0, [x19, #24]; ldr x8, [x19, #16]; blr x8
+    //   mov x8, #2; str x8, [x19, #40]; mov x8, #3; str x8, [x19, #152]; b 3f
+    //   2: ldr x8, [x19, #64]; blr x8; str x0, [x19, #160]
+    //   mov x8, #2; str x8, [x19, #40]; str x8, [x19, #152]
+    //   3: mov x0, xzr; ldr x19, [sp, #16]; ldp x29, x30, [sp], #32; ret
+    // bootstrap: mov x19, x0
+    //   add x0, x19, #144; mov x1, xzr; ldr x2, [x19, #136]; mov x3, x19
+    //   ldr x8, [x19, #72]; blr x8; cbz w0, 4f
+    //   mov x8, #2; str x8, [x19, #40]; str x8, [x19, #152]
+    //   4: ldr x8, [x19, #96]; blr x8; ldr x8, [x19, #104]; blr x8
+    //   5: b 5b
+    pub(super) const CODE: &[u8] = &[
+        0xfd, 0x7b, 0xbe, 0xa9, 0xf3, 0x0b, 0x00, 0xf9, 0xf3, 0x03, 0x00, 0xaa, 0x68, 0x2a, 0x40,
+        0xf9, 0x00, 0x01, 0x3f, 0xd6, 0x68, 0x2e, 0x40, 0xf9, 0x00, 0x01, 0x3f, 0xd6, 0x60, 0x3a,
+        0x40, 0xf9, 0x41, 0x00, 0x80, 0x52, 0x68, 0x1a, 0x40, 0xf9, 0x00, 0x01, 0x3f, 0xd6, 0xc0,
+        0x02, 0x00, 0xb4, 0x60, 0x0e, 0x00, 0xf9, 0x61, 0x3e, 0x40, 0xf9, 0x68, 0x1e, 0x40, 0xf9,
+        0x00, 0x01, 0x3f, 0xd6, 0x20, 0x01, 0x00, 0xb4, 0x60, 0x02, 0x00, 0xf9, 0x28, 0x00, 0x80,
+        0xd2, 0x68, 0x4e, 0x00, 0xf9, 0xe0, 0x03, 0x13, 0xaa, 0x68, 0x42, 0x40, 0xf9, 0xf3, 0x0b,
+        0x40, 0xf9, 0xfd, 0x7b, 0xc2, 0xa8, 0x00, 0x01, 0x1f, 0xd6, 0x60, 0x0e, 0x40, 0xf9, 0x68,
+        0x0a, 0x40, 0xf9, 0x00, 0x01, 0x3f, 0xd6, 0x48, 0x00, 0x80, 0xd2, 0x68, 0x16, 0x00, 0xf9,
+        0x68, 0x00, 0x80, 0xd2, 0x68, 0x4e, 0x00, 0xf9, 0x07, 0x00, 0x00, 0x14, 0x68, 0x22, 0x40,
+        0xf9, 0x00, 0x01, 0x3f, 0xd6, 0x60, 0x52, 0x00, 0xf9, 0x48, 0x00, 0x80, 0xd2, 0x68, 0x16,
+        0x00, 0xf9, 0x68, 0x4e, 0x00, 0xf9, 0xe0, 0x03, 0x1f, 0xaa, 0xf3, 0x0b, 0x40, 0xf9, 0xfd,
+        0x7b, 0xc2, 0xa8, 0xc0, 0x03, 0x5f, 0xd6, 0xf3, 0x03, 0x00, 0xaa, 0x60, 0x42, 0x02, 0x91,
+        0xe1, 0x03, 0x1f, 0xaa, 0x62, 0x46, 0x40, 0xf9, 0xe3, 0x03, 0x13, 0xaa, 0x68, 0x26, 0x40,
+        0xf9, 0x00, 0x01, 0x3f, 0xd6, 0x80, 0x00, 0x00, 0x34, 0x48, 0x00, 0x80, 0xd2, 0x68, 0x16,
+        0x00, 0xf9, 0x68, 0x4e, 0x00, 0xf9, 0x68, 0x32, 0x40, 0xf9, 0x00, 0x01, 0x3f, 0xd6, 0x68,
+        0x36, 0x40, 0xf9, 0x00, 0x01, 0x3f, 0xd6, 0x00, 0x00, 0x00, 0x14,
+    ];
+}
@@ -0,0 +1,63 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use super::BackendHandle;
+
+// How long the watcher blocks waiting for an unload before polling again.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+type Callback = Box<dyn FnOnce() + Send>;
+
+#[derive(Default)]
+struct Listeners {
+    waiting: HashMap<u64, Vec<Callback>>,
+    // Injections reported unloaded, so callbacks registered late still run.
+    unloaded: HashSet<u64>,
+}
+
+static LISTENERS: LazyLock<Mutex<Listeners>> = LazyLock::new(Default::default);
+
+/// Run `callback` once injection `id` is unloaded, or now if it already is.
+pub(super) fn on_unloaded(backend: &BackendHandle, id: u64, callback: Callback) {
+    super::ensure_watcher("hook-inject-unload", backend, poll);
+
+    let mut listeners = lock();
+    if listeners.unloaded.contains(&id) {
+        drop(listeners);
+        callback();
+        return;
+    }
+    listeners.waiting.entry(id).or_default().push(callback);
+}
+
+/// Record injection `id` as unloaded and run its callbacks.
+pub(super) fn unloaded(id: u64) {
+    let callbacks = {
+        let mut listeners = lock();
+        listeners.unloaded.insert(id);
+        listeners.waiting.remove(&id).unwrap_or_default()
+    };
+    for callback in callbacks {
+        callback();
+    }
+}
+
+/// Drop the callbacks of injection `id`, which is no longer monitored.
+pub(super) fn forget(id: u64) {
+    lock().waiting.remove(&id);
+}
+
+fn lock() -> std::sync::MutexGuard<'static, Listeners> {
+    LISTENERS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn poll(backend: &BackendHandle) {
+    match backend.inner.next_uninjected(POLL_INTERVAL) {
+        Ok(Some(id)) => unloaded(id),
+        Ok(None) => {}
+        Err(_) => std::thread::sleep(POLL_INTERVAL),
+    }
+}
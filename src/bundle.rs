@@ -0,0 +1,213 @@
+//! Shared-library dependencies of agents built from a crate.
+
+use std::path::{Path, PathBuf};
+
+use crate::{BinaryFormat, Error, Result, arch, image};
+
+/// Make sure the loader can find every shared library `agent` links against.
+///
+/// Dependencies it would not find on its own, but which the crate's build
+/// produced (e.g. a library bundled by a `-sys` crate), are copied next to
+/// the agent. Whatever is still missing is reported in one error, instead
+/// of as a `dlopen` failure inside the target.
+///
+/// Images for another platform are skipped: the host cannot say what the
+/// target system provides.
+pub(crate) fn bundle_dependencies(agent: &Path) -> Result<()> {
+    let bytes = std::fs::read(agent)?;
+    let Some(format) = arch::identify(&bytes).map(|kind| kind.format) else {
+        return Ok(());
+    };
+    if BinaryFormat::native() != Some(format) {
+        return Ok(());
+    }
+    let Some(dir) = agent.parent() else {
+        return Ok(());
+    };
+
+    let deps = image::dependencies(&bytes)?;
+    let loader = Loader::new(format, dir, &deps.search_paths);
+    let mut missing = Vec::new();
+    for name in &deps.needed {
+        if loader.finds(name) {
+            continue;
+        }
+        let Some(built) = find_built(dir, file_name(name)) else {
+            missing.push(name.clone());
+            continue;
+        };
+        let staged = dir.join(file_name(name));
+        if !staged.exists() {
+            std::fs::copy(&built, &staged)?;
+        }
+        if !loader.finds(name) {
+            missing.push(format!(
+                "{name} (copied to {}, but the agent does not search its own directory)",
+                dir.display()
+            ));
+        }
+    }
+
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(Error::invalid_input(format_args!(
+        "{}: shared library dependencies not found: {}",
+        agent.display(),
+        missing.join(", ")
+    )))
+}
+
+/// Where the platform loader looks for a dependency of the agent.
+struct Loader {
+    format: BinaryFormat,
+    agent_dir: PathBuf,
+    dirs: Vec<PathBuf>,
+}
+
+impl Loader {
+    fn new(format: BinaryFormat, agent_dir: &Path, search_paths: &[String]) -> Self {
+        let agent = agent_dir.to_string_lossy();
+        let mut dirs: Vec<PathBuf> = search_paths
+            .iter()
+            .filter(|path| !path.contains("@executable_path"))
+            .map(|path| {
+                PathBuf::from(
+                    path.replace("${ORIGIN}", &agent)
+                        .replace("$ORIGIN", &agent)
+                        .replace("@loader_path", &agent),
+                )
+            })
+            .collect();
+
+        let env = match format {
+            BinaryFormat::Elf => "LD_LIBRARY_PATH",
+            BinaryFormat::MachO => "DYLD_LIBRARY_PATH",
+            BinaryFormat::Pe => "PATH",
+        };
+        if let Some(paths) = std::env::var_os(env) {
+            dirs.extend(std::env::split_paths(&paths));
+        }
+        match format {
+            BinaryFormat::Elf => dirs.extend(elf_system_dirs()),
+            BinaryFormat::MachO => {
+                dirs.extend(["/usr/local/lib", "/usr/lib"].map(PathBuf::from));
+            }
+            BinaryFormat::Pe => {
+                dirs.insert(0, agent_dir.to_path_buf());
+                if let Some(root) = std::env::var_os("SystemRoot") {
+                    let root = PathBuf::from(root);
+                    dirs.extend([root.join("System32"), root]);
+                }
+            }
+        }
+
+        Self {
+            format,
+            agent_dir: agent_dir.to_path_buf(),
+            dirs,
+        }
+    }
+
+    fn finds(&self, name: &str) -> bool {
+        match self.format {
+            BinaryFormat::Elf => {
+                if name.contains('/') {
+                    return Path::new(name).exists();
+                }
+                self.in_dirs(name) || in_ld_cache(name)
+            }
+            BinaryFormat::MachO => {
+                // System libraries live in the dyld shared cache, not on disk.
+                if name.starts_with("/usr/lib/") || name.starts_with("/System/") {
+                    return true;
+                }
+                if let Some(rest) = name.strip_prefix("@loader_path/") {
+                    return self.agent_dir.join(rest).exists();
+                }
+                if let Some(rest) = name.strip_prefix("@rpath/") {
+                    return self.in_dirs(rest);
+                }
+                if name.starts_with("@executable_path/") {
+                    // Depends on the target; leave it to the loader.
+                    return true;
+                }
+                Path::new(name).exists() || self.in_dirs(file_name(name))
+            }
+            BinaryFormat::Pe => {
+                let lower = name.to_ascii_lowercase();
+                lower.starts_with("api-ms-") || lower.starts_with("ext-ms-") || self.in_dirs(name)
+            }
+        }
+    }
+
+    fn in_dirs(&self, name: &str) -> bool {
+        self.dirs.iter().any(|dir| dir.join(name).exists())
+    }
+}
+
+fn elf_system_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    for base in ["/lib", "/usr/lib", "/lib64", "/usr/lib64", "/usr/local/lib"] {
+        dirs.push(PathBuf::from(base));
+        // Debian-style multiarch directories, e.g. /usr/lib/x86_64-linux-gnu.
+        let Ok(entries) = std::fs::read_dir(base) else {
+            continue;
+        };
+        dirs.extend(
+            entries
+                .flatten()
+                .filter(|entry| entry.file_name().to_string_lossy().contains("-linux-"))
+                .map(|entry| entry.path()),
+        );
+    }
+    dirs
+}
+
+/// Whether `/etc/ld.so.cache` lists `name`, covering `ld.so.conf` entries.
+fn in_ld_cache(name: &str) -> bool {
+    let Ok(cache) = std::fs::read("/etc/ld.so.cache") else {
+        return false;
+    };
+    let mut needle = name.as_bytes().to_vec();
+    needle.push(0);
+    cache
+        .windows(needle.len() + 1)
+        .any(|window| window[0] == 0 && window[1..] == needle[..])
+}
+
+/// Look for `name` among the build outputs next to the agent (`deps/` and
+/// build script `out/` directories).
+fn find_built(dir: &Path, name: &str) -> Option<PathBuf> {
+    let candidate = dir.join("deps").join(name);
+    if candidate.is_file() {
+        return Some(candidate);
+    }
+    let builds = std::fs::read_dir(dir.join("build")).ok()?;
+    builds
+        .flatten()
+        .find_map(|build| find_file(&build.path().join("out"), name, 4))
+}
+
+fn find_file(dir: &Path, name: &str, depth: usize) -> Option<PathBuf> {
+    let entries = std::fs::read_dir(dir).ok()?;
+    let mut subdirs = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            subdirs.push(path);
+        } else if entry.file_name() == name {
+            return Some(path);
+        }
+    }
+    if depth == 0 {
+        return None;
+    }
+    subdirs
+        .iter()
+        .find_map(|subdir| find_file(subdir, name, depth - 1))
+}
+
+fn file_name(name: &str) -> &str {
+    name.rsplit(['/', '\\']).next().unwrap_or(name)
+}
@@ -0,0 +1,131 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::Result;
+
+/// Source of time for long-running logic (timeouts, polling, retries).
+///
+/// The watchdog (`Watchdog::clock`), webhook retries
+/// (`WebhookNotifier::clock`) and heartbeat checks (`Health::is_alive_with`)
+/// take a `Clock`, so tests can substitute a
+/// `ManualClock` and advance time deterministically instead of sleeping.
+/// Waits on a target, e.g. for its agent to get ready or for it to exit, go
+/// by the clock in `InjectOptions::clock`; background checks the runtimes
+/// make on their own are on the system clock.
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// Return the current instant.
+    fn now(&self) -> Instant;
+
+    /// Return the current wall-clock time, to compare timestamps such as
+    /// heartbeats against.
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    /// Block the current thread for `duration`.
+    fn sleep(&self, duration: Duration);
+}
+
+/// Clock backed by the operating system.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// Clock that only moves when told to.
+///
+/// `sleep` advances the clock instead of blocking, so polling loops run to
+/// completion immediately. Clones share the same underlying time.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use hook_inject::{Clock, ManualClock};
+///
+/// let clock = ManualClock::new();
+/// let start = clock.now();
+/// clock.advance(Duration::from_secs(30));
+/// assert_eq!(clock.now() - start, Duration::from_secs(30));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ManualClock {
+    base: Instant,
+    system_base: SystemTime,
+    offset: Arc<Mutex<Duration>>,
+}
+
+impl ManualClock {
+    /// Create a clock frozen at the current instant.
+    pub fn new() -> Self {
+        Self {
+            base: Instant::now(),
+            system_base: SystemTime::now(),
+            offset: Arc::new(Mutex::new(Duration::ZERO)),
+        }
+    }
+
+    /// Move the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        let mut offset = self.offset.lock().unwrap_or_else(|e| e.into_inner());
+        *offset += duration;
+    }
+
+    /// Return how far the clock has been advanced since creation.
+    pub fn elapsed(&self) -> Duration {
+        *self.offset.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.base + self.elapsed()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.system_base + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+/// Call `poll` until it returns a value, sleeping on `clock` for `interval`
+/// in between, or until `timeout` has passed on `clock`, then return `None`.
+///
+/// `poll` runs at least once; without a timeout this waits for as long as
+/// it takes.
+pub(crate) fn poll_until<T>(
+    clock: &dyn Clock,
+    timeout: Option<Duration>,
+    interval: Duration,
+    mut poll: impl FnMut() -> Result<Option<T>>,
+) -> Result<Option<T>> {
+    let deadline = timeout.map(|timeout| clock.now() + timeout);
+    loop {
+        if let Some(value) = poll()? {
+            return Ok(Some(value));
+        }
+        let now = clock.now();
+        clock.sleep(match deadline {
+            Some(deadline) if now >= deadline => return Ok(None),
+            Some(deadline) => interval.min(deadline - now),
+            None => interval,
+        });
+    }
+}
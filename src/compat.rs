@@ -0,0 +1,129 @@
+//! C library compatibility between an agent and its target.
+//!
+//! An agent linked against a newer glibc than the target has mapped, or
+//! against musl when the target uses glibc (or the reverse), fails inside
+//! the target with a loader error that never reaches the injector. Both are
+//! visible from the outside, so they are reported up front instead.
+
+use crate::{Library, Process, Result};
+
+/// Fail with `invalid_input` if `library` needs a C library the target
+/// process does not provide.
+///
+/// Targets without a mapped C library (e.g. suspended before the loader ran,
+/// or statically linked) are not checked.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn check_libc(library: &Library, process: Process) -> Result<()> {
+    use crate::{BinaryFormat, Error, image};
+
+    if library.format() != Some(BinaryFormat::Elf) {
+        return Ok(());
+    }
+    let bytes = library.image_bytes()?;
+    let Some(wanted) = image::dependencies(&bytes)?
+        .needed
+        .iter()
+        .find_map(|name| Flavor::of(name))
+    else {
+        return Ok(());
+    };
+    // The check is advisory: if the maps cannot be read, injection reports
+    // the underlying problem itself.
+    let Ok(regions) = process.memory_maps() else {
+        return Ok(());
+    };
+    let Some((found, path)) = regions.iter().find_map(|region| {
+        let path = region.path()?;
+        let flavor = Flavor::of(&path.file_name()?.to_string_lossy())?;
+        Some((flavor, path.to_path_buf()))
+    }) else {
+        return Ok(());
+    };
+
+    if wanted != found {
+        return Err(Error::invalid_input(format_args!(
+            "library links against {}, but the target uses {} ({})",
+            wanted.name(),
+            found.name(),
+            path.display()
+        )));
+    }
+    if found == Flavor::Musl {
+        // musl does not version its symbols.
+        return Ok(());
+    }
+
+    let Some(required) = newest_glibc(image::required_versions(&bytes)?) else {
+        return Ok(());
+    };
+    // Read the target's copy, which differs from ours inside a container.
+    let rooted = format!("/proc/{}/root{}", process.pid(), path.display());
+    let Ok(target_libc) = std::fs::read(rooted).or_else(|_| std::fs::read(&path)) else {
+        return Ok(());
+    };
+    let Some(provided) = newest_glibc(image::defined_versions(&target_libc)?) else {
+        return Ok(());
+    };
+    if required.0 > provided.0 {
+        return Err(Error::invalid_input(format_args!(
+            "library requires {}, but the target's glibc ({}) provides up to {}",
+            required.1,
+            path.display(),
+            provided.1
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn check_libc(_library: &Library, _process: Process) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Flavor {
+    Glibc,
+    Musl,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl Flavor {
+    /// Classify a library by file name, as linked (`DT_NEEDED`) or mapped.
+    fn of(name: &str) -> Option<Self> {
+        let name = name.rsplit('/').next().unwrap_or(name);
+        if name == "libc.so.6" || (name.starts_with("libc-2.") && name.ends_with(".so")) {
+            Some(Flavor::Glibc)
+        } else if name == "libc.so"
+            || name.starts_with("ld-musl-")
+            || name.starts_with("libc.musl-")
+        {
+            Some(Flavor::Musl)
+        } else {
+            None
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Flavor::Glibc => "glibc",
+            Flavor::Musl => "musl",
+        }
+    }
+}
+
+/// The highest `GLIBC_x.y[.z]` version in `versions`, with its parsed form.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn newest_glibc(versions: Vec<String>) -> Option<(Vec<u32>, String)> {
+    versions
+        .into_iter()
+        .filter_map(|version| {
+            let parsed = version
+                .strip_prefix("GLIBC_")?
+                .split('.')
+                .map(|part| part.parse().ok())
+                .collect::<Option<Vec<u32>>>()?;
+            Some((parsed, version))
+        })
+        .max()
+}
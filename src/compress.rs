@@ -0,0 +1,112 @@
+use std::fmt;
+use std::io::Read;
+
+use ruzstd::decoding::StreamingDecoder;
+use ruzstd::decoding::errors::{FrameDecoderError, ReadFrameHeaderError};
+
+use crate::{Error, Result};
+
+/// Compression format of a library blob (requires the `compression`
+/// feature).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Compression {
+    /// gzip (RFC 1952), e.g. from `gzip -9`.
+    Gzip,
+    /// Zstandard (RFC 8878), e.g. from `zstd -19`; dictionaries are not
+    /// supported.
+    Zstd,
+}
+
+impl Compression {
+    /// Detect the compression format from the leading magic bytes.
+    pub fn detect(bytes: &[u8]) -> Option<Compression> {
+        match bytes {
+            [0x1f, 0x8b, ..] => Some(Compression::Gzip),
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        })
+    }
+}
+
+/// Most bytes `Library::from_compressed_bytes` decodes a blob to (256 MiB).
+///
+/// A few kilobytes of crafted input can otherwise expand until memory runs
+/// out.
+pub const MAX_DECOMPRESSED_SIZE: u64 = 256 << 20;
+
+/// Decompress a whole blob, failing once it decodes to more than `max_size`
+/// bytes.
+pub(crate) fn decompress(compression: Compression, bytes: &[u8], max_size: u64) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let decoded = match compression {
+        Compression::Gzip => gunzip(bytes, &mut out, max_size),
+        Compression::Zstd => unzstd(bytes, &mut out, max_size),
+    };
+    decoded.map_err(|reason| {
+        Error::invalid_input(format_args!(
+            "failed to decompress {compression} library: {reason}"
+        ))
+    })?;
+    Ok(out)
+}
+
+// Every member, as `gzip -d` decodes concatenated files.
+fn gunzip(bytes: &[u8], out: &mut Vec<u8>, max_size: u64) -> std::result::Result<(), String> {
+    read_limited(flate2::read::MultiGzDecoder::new(bytes), out, max_size)
+}
+
+/// Read all of `reader` into `out`, failing once `out` would grow past
+/// `max_size` bytes.
+fn read_limited(
+    reader: impl Read,
+    out: &mut Vec<u8>,
+    max_size: u64,
+) -> std::result::Result<(), String> {
+    let room = max_size.saturating_sub(out.len() as u64);
+    reader
+        .take(room.saturating_add(1))
+        .read_to_end(out)
+        .map_err(|err| err.to_string())?;
+    if out.len() as u64 > max_size {
+        return Err(format!("decompressed size exceeds {max_size} bytes"));
+    }
+    Ok(())
+}
+
+// Every frame, skipping skippable ones; ruzstd leaves checking the content
+// checksum to its caller.
+fn unzstd(mut bytes: &[u8], out: &mut Vec<u8>, max_size: u64) -> std::result::Result<(), String> {
+    while !bytes.is_empty() {
+        let mut decoder = match StreamingDecoder::new(&mut bytes) {
+            Ok(decoder) => decoder,
+            Err(FrameDecoderError::ReadFrameHeaderError(ReadFrameHeaderError::SkipFrame {
+                length,
+                ..
+            })) => {
+                bytes = bytes
+                    .get(length as usize..)
+                    .ok_or("truncated skippable frame")?;
+                continue;
+            }
+            Err(err) => return Err(err.to_string()),
+        };
+        read_limited(&mut decoder, out, max_size)?;
+        let frame = decoder.into_frame_decoder();
+        if let Some(expected) = frame.get_checksum_from_data()
+            && frame.get_calculated_checksum() != Some(expected)
+        {
+            return Err("content checksum mismatch".into());
+        }
+    }
+    Ok(())
+}
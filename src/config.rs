@@ -0,0 +1,211 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::{Error, ErrorKind, InjectedProcess, Result};
+
+type PushCallback = Box<dyn Fn(Result<()>) + Send>;
+
+/// Watches a local config file and pushes every change to an agent.
+///
+/// The file is polled; whenever its contents change they are pushed with
+/// `InjectedProcess::push_config`, which waits for the agent to acknowledge
+/// them. The contents when watching starts are taken to be what the agent
+/// already has. A file that is briefly missing or unreadable, as while an
+/// editor replaces it, is checked again on the next poll. Contents that fail
+/// to push are not retried until the file changes again. Watching stops
+/// when the target exits.
+///
+/// # Examples
+/// ```no_run
+/// use std::time::Duration;
+/// use hook_inject::{inject_process, ConfigWatcher, Library, Process};
+///
+/// let path = "/etc/my-agent.toml";
+/// let library = Library::from_path("/path/to/libagent.so")?.with_data_bytes(std::fs::read(path)?);
+/// let injected = inject_process(Process::from_pid(1234)?, library)?;
+/// let watched = ConfigWatcher::new(path)
+///     .poll_interval(Duration::from_millis(500))
+///     .on_push(|result| {
+///         if let Err(err) = result {
+///             eprintln!("config push failed: {err}");
+///         }
+///     })
+///     .watch(&injected)?;
+/// // ... later:
+/// watched.stop();
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct ConfigWatcher {
+    path: PathBuf,
+    poll_interval: Duration,
+    ack_timeout: Duration,
+    on_push: Option<PushCallback>,
+}
+
+impl fmt::Debug for ConfigWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConfigWatcher")
+            .field("path", &self.path)
+            .field("poll_interval", &self.poll_interval)
+            .field("ack_timeout", &self.ack_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ConfigWatcher {
+    /// Watch the file at `path`, checking it every second and waiting up to
+    /// 5 seconds for each acknowledgement.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            poll_interval: Duration::from_secs(1),
+            ack_timeout: Duration::from_secs(5),
+            on_push: None,
+        }
+    }
+
+    /// How often to check the file for changes (default 1s).
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// How long the agent has to acknowledge each push (default 5s).
+    pub fn ack_timeout(mut self, timeout: Duration) -> Self {
+        self.ack_timeout = timeout;
+        self
+    }
+
+    /// Call `callback` with the outcome of every push, from the watcher's
+    /// thread.
+    pub fn on_push(mut self, callback: impl Fn(Result<()>) + Send + 'static) -> Self {
+        self.on_push = Some(Box::new(callback));
+        self
+    }
+
+    /// Start watching the file for `injected` from a background thread.
+    ///
+    /// Fails if the file cannot be read now.
+    pub fn watch(self, injected: &InjectedProcess) -> Result<WatchedConfig> {
+        let contents = std::fs::read(&self.path).map_err(|err| {
+            Error::new(
+                ErrorKind::Io,
+                format_args!("failed to read config file {}: {err}", self.path.display()),
+            )
+            .with_source(err)
+        })?;
+        let (sender, receiver) = mpsc::channel();
+        let applied = Arc::new(AtomicU32::new(0));
+        let failed = Arc::new(AtomicU32::new(0));
+        let watcher = Watcher {
+            policy: self,
+            applied: applied.clone(),
+            failed: failed.clone(),
+        };
+        let injected = injected.share();
+        let thread = std::thread::Builder::new()
+            .name("hook-inject-config".into())
+            .spawn(move || watcher.run(injected, contents, receiver))
+            .expect("failed to spawn config watcher thread");
+        Ok(WatchedConfig {
+            sender,
+            thread: Some(thread),
+            applied,
+            failed,
+        })
+    }
+}
+
+/// A config file watched by a `ConfigWatcher`.
+///
+/// Dropping it stops watching.
+#[derive(Debug)]
+pub struct WatchedConfig {
+    sender: Sender<()>,
+    thread: Option<JoinHandle<()>>,
+    applied: Arc<AtomicU32>,
+    failed: Arc<AtomicU32>,
+}
+
+impl WatchedConfig {
+    /// How many changes the agent acknowledged as applied.
+    pub fn applied(&self) -> u32 {
+        self.applied.load(Ordering::SeqCst)
+    }
+
+    /// How many changes failed to push, were rejected, or were not
+    /// acknowledged in time.
+    pub fn failed(&self) -> u32 {
+        self.failed.load(Ordering::SeqCst)
+    }
+
+    /// Returns true until the target exits or watching is stopped.
+    pub fn is_watching(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_some_and(|thread| !thread.is_finished())
+    }
+
+    /// Stop watching.
+    pub fn stop(mut self) {
+        self.join();
+    }
+
+    fn join(&mut self) {
+        let _ = self.sender.send(());
+        if let Some(thread) = self.thread.take()
+            && let Err(panic) = thread.join()
+        {
+            std::panic::resume_unwind(panic);
+        }
+    }
+}
+
+impl Drop for WatchedConfig {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+struct Watcher {
+    policy: ConfigWatcher,
+    applied: Arc<AtomicU32>,
+    failed: Arc<AtomicU32>,
+}
+
+impl Watcher {
+    fn run(self, injected: InjectedProcess, mut pushed: Vec<u8>, stop: mpsc::Receiver<()>) {
+        loop {
+            match stop.recv_timeout(self.policy.poll_interval) {
+                Ok(()) | Err(RecvTimeoutError::Disconnected) => return,
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+            if injected.wait_target_exit(Duration::ZERO).is_ok() {
+                return;
+            }
+            let Ok(contents) = std::fs::read(&self.policy.path) else {
+                continue;
+            };
+            if contents == pushed {
+                continue;
+            }
+
+            let result = injected.push_config(contents.clone(), self.policy.ack_timeout);
+            let counter = if result.is_ok() {
+                &self.applied
+            } else {
+                &self.failed
+            };
+            counter.fetch_add(1, Ordering::SeqCst);
+            if let Some(callback) = &self.policy.on_push {
+                callback(result);
+            }
+            pushed = contents;
+        }
+    }
+}
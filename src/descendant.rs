@@ -0,0 +1,63 @@
+use std::path::{Path, PathBuf};
+
+use crate::Process;
+
+/// How a gated child process came to be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ChildOrigin {
+    /// The parent called `fork`.
+    Fork,
+    /// The process replaced its image with `exec`; the pid is unchanged.
+    Exec,
+    /// The parent spawned a new program (e.g. `posix_spawn`).
+    Spawn,
+}
+
+/// A descendant of an injected target, held suspended until it is handled.
+///
+/// Passed to the filter given to `InjectOptions::follow_children`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Descendant {
+    process: Process,
+    parent: Process,
+    origin: ChildOrigin,
+    path: Option<PathBuf>,
+}
+
+impl Descendant {
+    #[cfg(feature = "frida")]
+    pub(crate) fn new(
+        process: Process,
+        parent: Process,
+        origin: ChildOrigin,
+        path: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            process,
+            parent,
+            origin,
+            path,
+        }
+    }
+
+    /// The new process.
+    pub fn process(&self) -> Process {
+        self.process
+    }
+
+    /// The process it descends from.
+    pub fn parent(&self) -> Process {
+        self.parent
+    }
+
+    /// How the process was created.
+    pub fn origin(&self) -> ChildOrigin {
+        self.origin
+    }
+
+    /// Path of the program the process runs, when known.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+}
@@ -0,0 +1,103 @@
+use std::fmt;
+
+use crate::{Result, backend};
+
+/// How a device is reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DeviceKind {
+    /// This machine.
+    Local,
+    /// A frida-server reached over the network.
+    Remote,
+    /// A phone or tablet connected over USB.
+    Usb,
+}
+
+impl fmt::Display for DeviceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DeviceKind::Local => "local",
+            DeviceKind::Remote => "remote",
+            DeviceKind::Usb => "usb",
+        })
+    }
+}
+
+/// A device Frida can inject on, as listed by `devices`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Device {
+    id: String,
+    name: String,
+    kind: DeviceKind,
+}
+
+impl Device {
+    #[cfg(feature = "frida")]
+    pub(crate) fn new(id: String, name: String, kind: DeviceKind) -> Self {
+        Self { id, name, kind }
+    }
+
+    /// The identifier `Injector::device` takes; for USB devices, the
+    /// serial number.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// A human-readable name, e.g. the phone's model.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// How the device is reached.
+    pub fn kind(&self) -> DeviceKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for Device {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}, {})", self.name, self.id, self.kind)
+    }
+}
+
+/// List the devices Frida can currently reach: this machine, USB devices
+/// that are attached, and remote devices connected so far.
+///
+/// Injecting on a USB device needs frida-server running on it.
+///
+/// # Examples
+/// ```no_run
+/// use hook_inject::{DeviceKind, Injector};
+///
+/// for device in hook_inject::devices()? {
+///     if device.kind() == DeviceKind::Usb {
+///         let injector = Injector::device(device.id())?;
+///     }
+/// }
+/// # Ok::<(), hook_inject::Error>(())
+/// ```
+pub fn devices() -> Result<Vec<Device>> {
+    backend::devices()
+}
+
+/// Which device a remote runtime reaches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum DeviceSpec {
+    /// The frida-server listening at an address.
+    Remote(String),
+    /// The device with an id.
+    Id(String),
+    /// The first USB device.
+    Usb,
+}
+
+impl fmt::Display for DeviceSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceSpec::Remote(address) => write!(f, "frida-server at {address}"),
+            DeviceSpec::Id(id) => write!(f, "device `{id}`"),
+            DeviceSpec::Usb => f.write_str("the USB device"),
+        }
+    }
+}
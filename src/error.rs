@@ -4,21 +4,37 @@ use std::fmt::Display;
 /// Result alias for this crate.
 pub type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum ErrorKind {
+/// Broad category of an `Error`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The caller passed an invalid argument.
     InvalidInput,
+    /// The operation is not supported on this platform or backend.
     NotSupported,
+    /// The injection runtime could not be initialized.
     RuntimeUnavailable,
+    /// The target process does not exist.
     ProcessNotFound,
+    /// The operation was denied by the OS.
     PermissionDenied,
+    /// An I/O operation failed.
     Io,
+    /// The runtime reported an error without a more specific category.
     Runtime,
+    /// The operation did not complete within its timeout.
+    TimedOut,
+    /// The library is already loaded in the target (see
+    /// `InjectOptions::already_loaded`).
+    AlreadyInjected,
+    /// The agent recorded a panic (see `PANIC_REPORT_SYMBOL`).
+    AgentPanicked,
 }
 
 /// Error type for this crate.
 ///
-/// This is intentionally a struct to minimize breaking changes over time, and
-/// only exposes its message via `Display`.
+/// This is intentionally a struct to minimize breaking changes over time. Its
+/// category is available through `kind()` and its message via `Display`.
 #[derive(Debug)]
 pub struct Error {
     kind: ErrorKind,
@@ -54,6 +70,21 @@ impl Error {
         Self::new(ErrorKind::Runtime, msg)
     }
 
+    pub(crate) fn timed_out(msg: impl Display) -> Self {
+        Self::new(ErrorKind::TimedOut, msg)
+    }
+
+    pub(crate) fn already_injected(msg: impl Display) -> Self {
+        Self::new(ErrorKind::AlreadyInjected, msg)
+    }
+
+    pub(crate) fn agent_panicked(report: impl Display) -> Self {
+        Self::new(
+            ErrorKind::AgentPanicked,
+            format_args!("agent panicked: {report}"),
+        )
+    }
+
     pub(crate) fn from_io(err: std::io::Error) -> Self {
         Self {
             kind: ErrorKind::Io,
@@ -62,7 +93,19 @@ impl Error {
         }
     }
 
-    fn new(kind: ErrorKind, msg: impl Display) -> Self {
+    /// Create an error of the given kind.
+    ///
+    /// Intended for code that extends this crate, such as custom backends, so
+    /// its errors classify the same way as built-in ones.
+    ///
+    /// # Examples
+    /// ```
+    /// use hook_inject::{Error, ErrorKind};
+    ///
+    /// let err = Error::new(ErrorKind::PermissionDenied, "ptrace_scope forbids attach");
+    /// assert!(err.is_permission_denied());
+    /// ```
+    pub fn new(kind: ErrorKind, msg: impl Display) -> Self {
         Self {
             kind,
             message: msg.to_string(),
@@ -70,6 +113,20 @@ impl Error {
         }
     }
 
+    /// Attach an underlying cause, returned by `std::error::Error::source`.
+    pub fn with_source(
+        mut self,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Return the error category.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
     /// Returns true if the target process was not found.
     pub fn is_process_not_found(&self) -> bool {
         self.kind == ErrorKind::ProcessNotFound
@@ -94,6 +151,21 @@ impl Error {
     pub fn is_not_supported(&self) -> bool {
         self.kind == ErrorKind::NotSupported
     }
+
+    /// Returns true if the operation gave up after its timeout elapsed.
+    pub fn is_timed_out(&self) -> bool {
+        self.kind == ErrorKind::TimedOut
+    }
+
+    /// Returns true if the library was already loaded in the target.
+    pub fn is_already_injected(&self) -> bool {
+        self.kind == ErrorKind::AlreadyInjected
+    }
+
+    /// Returns true if the agent panicked.
+    pub fn is_agent_panicked(&self) -> bool {
+        self.kind == ErrorKind::AgentPanicked
+    }
 }
 
 impl Clone for Error {
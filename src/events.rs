@@ -0,0 +1,220 @@
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{
+    EVENT_BUFFER_CAPACITY, Error, InjectionId, Library, Process, Result, image, maps, process,
+    status,
+};
+
+// Length and timestamp before each payload.
+const RECORD_HEADER_LEN: u64 = 12;
+// The oldest and next offsets before the records.
+const STREAM_HEADER_LEN: u64 = 16;
+
+/// An event emitted by an agent (see `EVENTS_SYMBOL`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentEvent {
+    timestamp: SystemTime,
+    injection: InjectionId,
+    payload: Vec<u8>,
+}
+
+impl AgentEvent {
+    /// When the agent emitted the event, by the target's clock.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+
+    /// The injection of the agent that emitted the event.
+    pub fn injection(&self) -> InjectionId {
+        self.injection
+    }
+
+    /// The payload, in whatever format the agent chose.
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// Take the payload.
+    pub fn into_payload(self) -> Vec<u8> {
+        self.payload
+    }
+
+    /// Decode a JSON payload, as emitted by
+    /// `hook_inject_agent::EventStream::emit_json`.
+    #[cfg(feature = "serde")]
+    pub fn decode<T: serde::de::DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_slice(&self.payload).map_err(|err| {
+            Error::invalid_input(format_args!("failed to decode event payload: {err}"))
+                .with_source(err)
+        })
+    }
+}
+
+/// The events an agent emits, read from the target's memory.
+///
+/// Iterating blocks until the next event, polling the target, and ends
+/// once the agent is unloaded or the target exits. `try_read` returns what
+/// is available without waiting. Events are read from the oldest the
+/// agent still holds; events it overwrote before they were read are
+/// counted in `lost_bytes`.
+///
+/// # Examples
+/// ```no_run
+/// use hook_inject::{inject_process, Library, Process};
+///
+/// let process = Process::from_pid(1234)?;
+/// let injected = inject_process(process, Library::from_path("/path/to/libagent.so")?)?;
+/// for event in injected.events()? {
+///     let event = event?;
+///     println!("{:?}: {}", event.timestamp(), String::from_utf8_lossy(event.payload()));
+/// }
+/// # Ok::<(), hook_inject::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct AgentEvents {
+    injection: InjectionId,
+    process: Process,
+    library: Library,
+    start_time: Option<u64>,
+    address: u64,
+    cursor: u64,
+    lost_bytes: u64,
+    pending: VecDeque<AgentEvent>,
+    poll_interval: Duration,
+}
+
+impl AgentEvents {
+    /// Read the stream `library` exports as `symbol`; `purpose` completes
+    /// the error when it does not ("so it cannot {purpose}").
+    pub(crate) fn new(
+        injection: InjectionId,
+        process: Process,
+        library: Library,
+        start_time: Option<u64>,
+        symbol: &str,
+        purpose: &str,
+    ) -> Result<Self> {
+        let bytes = library.image_bytes()?;
+        let Some(offset) = image::export_offset(&bytes, symbol)? else {
+            return Err(Error::not_supported(format_args!(
+                "agent does not export {symbol}, so it cannot {purpose}"
+            )));
+        };
+        let address = status::module_base(process, &bytes)?.wrapping_add(offset);
+        Ok(Self {
+            injection,
+            process,
+            library,
+            start_time,
+            address,
+            cursor: 0,
+            lost_bytes: 0,
+            pending: VecDeque::new(),
+            poll_interval: Duration::from_millis(50),
+        })
+    }
+
+    /// How long iterating waits between reads of the target (default 50ms).
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Bytes of events the agent overwrote before they were read.
+    pub fn lost_bytes(&self) -> u64 {
+        self.lost_bytes
+    }
+
+    /// Read the events emitted since the last read, without waiting.
+    pub fn try_read(&mut self) -> Result<Vec<AgentEvent>> {
+        self.fill()?;
+        Ok(self.pending.drain(..).collect())
+    }
+
+    fn fill(&mut self) -> Result<()> {
+        let capacity = EVENT_BUFFER_CAPACITY as u64;
+        let [tail, head] = self.read_offsets()?;
+        let start = self.cursor.max(tail);
+        if head <= start {
+            return Ok(());
+        }
+        // A tail read together with a newer head may lag by more than the
+        // buffer; the tail read below sorts out what is still valid.
+        let copy_start = start.max(head.saturating_sub(capacity));
+        let mut span = vec![0u8; (head - copy_start) as usize];
+        let ring = (copy_start % capacity) as usize;
+        let first = span.len().min(EVENT_BUFFER_CAPACITY - ring);
+        let records = self.address + STREAM_HEADER_LEN;
+        maps::read_memory(
+            self.process.pid(),
+            records + ring as u64,
+            &mut span[..first],
+        )?;
+        maps::read_memory(self.process.pid(), records, &mut span[first..])?;
+
+        // The agent moves the tail past records before overwriting them, so
+        // everything from the tail read after the copy is intact.
+        let [tail, _] = self.read_offsets()?;
+        let mut offset = copy_start.max(tail);
+        self.lost_bytes += offset - self.cursor;
+        while offset + RECORD_HEADER_LEN <= head {
+            let at = (offset - copy_start) as usize;
+            let len = u32::from_ne_bytes(span[at..at + 4].try_into().expect("4 bytes")) as u64;
+            let millis = u64::from_ne_bytes(span[at + 4..at + 12].try_into().expect("8 bytes"));
+            let end = offset + RECORD_HEADER_LEN + len;
+            if end > head {
+                break;
+            }
+            let payload = at + RECORD_HEADER_LEN as usize;
+            self.pending.push_back(AgentEvent {
+                timestamp: UNIX_EPOCH + Duration::from_millis(millis),
+                injection: self.injection,
+                payload: span[payload..payload + len as usize].to_vec(),
+            });
+            offset = end;
+        }
+        self.cursor = offset;
+        Ok(())
+    }
+
+    fn read_offsets(&self) -> Result<[u64; 2]> {
+        let mut header = [0u8; STREAM_HEADER_LEN as usize];
+        maps::read_memory(self.process.pid(), self.address, &mut header)?;
+        let (tail, head) = header.split_at(8);
+        Ok([
+            u64::from_ne_bytes(tail.try_into().expect("8 bytes")),
+            u64::from_ne_bytes(head.try_into().expect("8 bytes")),
+        ])
+    }
+
+    fn has_ended(&self) -> bool {
+        process::has_exited(self.process.pid(), self.start_time)
+            || !status::is_loaded(self.process, &self.library).unwrap_or(false)
+    }
+}
+
+impl Iterator for AgentEvents {
+    type Item = Result<AgentEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            if let Err(err) = self.fill() {
+                return if self.has_ended() {
+                    None
+                } else {
+                    Some(Err(err))
+                };
+            }
+            if self.pending.is_empty() {
+                if self.has_ended() {
+                    return None;
+                }
+                std::thread::sleep(self.poll_interval);
+            }
+        }
+    }
+}
@@ -0,0 +1,444 @@
+use std::fmt;
+
+/// Why a capability is or isn't available in this build.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CapabilityStatus {
+    /// The capability can be used.
+    Available,
+    /// The capability requires a Cargo feature that was not enabled.
+    MissingFeature(&'static str),
+    /// The capability is not implemented for the current target platform.
+    UnsupportedPlatform,
+    /// The injection engine cannot provide this capability.
+    BackendLimitation(&'static str),
+}
+
+impl CapabilityStatus {
+    /// Returns true if the capability can be used.
+    pub fn is_available(&self) -> bool {
+        matches!(self, CapabilityStatus::Available)
+    }
+}
+
+impl fmt::Display for CapabilityStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CapabilityStatus::Available => f.write_str("available"),
+            CapabilityStatus::MissingFeature(feature) => {
+                write!(f, "requires the `{feature}` feature")
+            }
+            CapabilityStatus::UnsupportedPlatform => {
+                write!(f, "unsupported on {}", std::env::consts::OS)
+            }
+            CapabilityStatus::BackendLimitation(reason) => {
+                write!(f, "backend limitation: {reason}")
+            }
+        }
+    }
+}
+
+/// A single optional capability and its status.
+#[derive(Clone, Debug)]
+pub struct Capability {
+    name: &'static str,
+    description: &'static str,
+    status: CapabilityStatus,
+}
+
+impl Capability {
+    /// Stable identifier, e.g. `"blob-injection"`.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Human-readable summary of what the capability does.
+    pub fn description(&self) -> &'static str {
+        self.description
+    }
+
+    /// Whether the capability is active, and why not if it isn't.
+    pub fn status(&self) -> CapabilityStatus {
+        self.status
+    }
+}
+
+/// Structured summary of the capabilities compiled into this build.
+///
+/// # Examples
+/// ```
+/// let report = hook_inject::feature_report();
+/// for capability in report.capabilities() {
+///     println!("{}: {}", capability.name(), capability.status());
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct FeatureReport {
+    capabilities: Vec<Capability>,
+}
+
+impl FeatureReport {
+    /// Return every known capability.
+    pub fn capabilities(&self) -> &[Capability] {
+        &self.capabilities
+    }
+
+    /// Look up a capability by name.
+    pub fn get(&self, name: &str) -> Option<&Capability> {
+        self.capabilities.iter().find(|c| c.name == name)
+    }
+
+    /// Returns true if the named capability exists and is available.
+    pub fn is_available(&self, name: &str) -> bool {
+        self.get(name).is_some_and(|c| c.status.is_available())
+    }
+}
+
+impl fmt::Display for FeatureReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .capabilities
+            .iter()
+            .map(|c| c.name.len())
+            .max()
+            .unwrap_or(0);
+        for capability in &self.capabilities {
+            writeln!(f, "{:width$}  {}", capability.name, capability.status)?;
+        }
+        Ok(())
+    }
+}
+
+/// Report which optional capabilities are active in this build.
+///
+/// This is computed from compile-time configuration only; it does not
+/// initialize the injection backend.
+pub fn feature_report() -> FeatureReport {
+    // Keep this list in sync when adding feature-gated or platform-specific APIs.
+    let capabilities = vec![
+        capability(
+            "inject-process",
+            "inject a library into a running process",
+            engine_status(),
+        ),
+        capability(
+            "inject-program",
+            "launch a program suspended and inject before it runs",
+            engine_status(),
+        ),
+        capability(
+            "spawn-suspended",
+            "spawn a program suspended for manual injection",
+            engine_status(),
+        ),
+        capability(
+            "blob-injection",
+            "inject a library from in-memory bytes",
+            engine_status(),
+        ),
+        capability(
+            "launch-stdin",
+            "write to the stdin of programs launched with Stdio::Pipe",
+            engine_status(),
+        ),
+        capability(
+            "launch-stdio-pipes",
+            "read stdout/stderr of programs launched by the injector",
+            if cfg!(feature = "frida") {
+                CapabilityStatus::BackendLimitation(
+                    "Frida launches do not expose output pipes; use inject_command instead",
+                )
+            } else {
+                // Without Frida, programs are launched natively.
+                engine_status()
+            },
+        ),
+        capability(
+            "emulated-realm",
+            "inject into the emulated ABI realm on Android",
+            if cfg!(not(target_os = "android")) {
+                CapabilityStatus::UnsupportedPlatform
+            } else {
+                CapabilityStatus::BackendLimitation(
+                    "the Frida library injector only reaches the native realm",
+                )
+            },
+        ),
+        capability(
+            "inject-command",
+            "launch a std Command with full stdio, inject, then resume",
+            if cfg!(any(target_os = "linux", target_os = "macos", windows)) {
+                engine_status()
+            } else {
+                CapabilityStatus::UnsupportedPlatform
+            },
+        ),
+        capability(
+            "frida-backend",
+            "inject through frida-core (Backend::Frida)",
+            frida_status(),
+        ),
+        capability(
+            "ptrace-backend",
+            "inject without Frida through ptrace and the target's own dlopen",
+            if cfg!(all(
+                target_os = "linux",
+                any(target_arch = "x86_64", target_arch = "aarch64")
+            )) {
+                CapabilityStatus::Available
+            } else {
+                CapabilityStatus::UnsupportedPlatform
+            },
+        ),
+        capability(
+            "loadlibrary-backend",
+            "inject without Frida through CreateRemoteThread and LoadLibraryW",
+            if cfg!(all(
+                windows,
+                any(target_arch = "x86_64", target_arch = "aarch64")
+            )) {
+                CapabilityStatus::Available
+            } else {
+                CapabilityStatus::UnsupportedPlatform
+            },
+        ),
+        capability(
+            "taskport-backend",
+            "inject without Frida through task_for_pid and a remote dlopen",
+            if cfg!(all(
+                target_os = "macos",
+                any(target_arch = "x86_64", target_arch = "aarch64")
+            )) {
+                CapabilityStatus::Available
+            } else {
+                CapabilityStatus::UnsupportedPlatform
+            },
+        ),
+        capability(
+            "preload-backend",
+            "inject programs as they launch through LD_PRELOAD or DYLD_INSERT_LIBRARIES",
+            if cfg!(not(any(target_os = "linux", target_os = "macos"))) {
+                CapabilityStatus::UnsupportedPlatform
+            } else if cfg!(feature = "preload-backend") {
+                CapabilityStatus::Available
+            } else {
+                CapabilityStatus::MissingFeature("preload-backend")
+            },
+        ),
+        capability(
+            "jvm-attach",
+            "load agents into running JVMs through the attach API (Agent_OnAttach)",
+            if cfg!(any(target_os = "linux", target_os = "macos")) {
+                CapabilityStatus::Available
+            } else {
+                CapabilityStatus::UnsupportedPlatform
+            },
+        ),
+        capability(
+            "clr-bootstrap",
+            "run a managed assembly in a .NET target after injecting (hostfxr or the CLR host)",
+            if cfg!(not(windows)) {
+                CapabilityStatus::UnsupportedPlatform
+            } else if cfg!(feature = "clr-bootstrap") {
+                CapabilityStatus::Available
+            } else {
+                CapabilityStatus::MissingFeature("clr-bootstrap")
+            },
+        ),
+        capability(
+            "uprobes",
+            "observe calls in a process through uprobes, without injecting (UprobeTracer)",
+            if cfg!(all(
+                target_os = "linux",
+                any(target_arch = "x86_64", target_arch = "aarch64")
+            )) {
+                CapabilityStatus::Available
+            } else {
+                CapabilityStatus::UnsupportedPlatform
+            },
+        ),
+        capability(
+            "injection-method",
+            "choose how the library is loaded (remote thread, queued APC, thread hijack)",
+            if cfg!(all(
+                windows,
+                any(target_arch = "x86_64", target_arch = "aarch64")
+            )) {
+                CapabilityStatus::Available
+            } else {
+                CapabilityStatus::UnsupportedPlatform
+            },
+        ),
+        capability(
+            "follow-exec",
+            "re-inject the library when the target calls exec",
+            frida_status(),
+        ),
+        capability(
+            "follow-children",
+            "inject approved forked or spawned descendants of the target",
+            frida_status(),
+        ),
+        capability(
+            "spawn-gate",
+            "inject matching programs as they are spawned anywhere on the device",
+            if cfg!(not(feature = "frida")) {
+                CapabilityStatus::MissingFeature("frida")
+            } else if cfg!(any(
+                target_os = "macos",
+                target_os = "ios",
+                target_os = "android"
+            )) {
+                CapabilityStatus::Available
+            } else {
+                CapabilityStatus::BackendLimitation(
+                    "Frida only supports spawn gating on macOS, iOS and Android",
+                )
+            },
+        ),
+        capability(
+            "entry-status",
+            "read the status an agent entrypoint reports after injection",
+            if cfg!(any(target_os = "linux", target_os = "macos", windows)) {
+                engine_status()
+            } else {
+                CapabilityStatus::UnsupportedPlatform
+            },
+        ),
+        capability(
+            "pre-exec",
+            "run caller code in a launched program between fork and exec",
+            if cfg!(any(target_os = "linux", target_os = "macos")) {
+                CapabilityStatus::Available
+            } else {
+                CapabilityStatus::UnsupportedPlatform
+            },
+        ),
+        capability(
+            "suspend-at-loader-entry",
+            "hold a spawned program before the dynamic loader runs",
+            if cfg!(any(target_os = "linux", target_os = "macos", windows)) {
+                CapabilityStatus::Available
+            } else {
+                CapabilityStatus::UnsupportedPlatform
+            },
+        ),
+        capability(
+            "serde",
+            "deserialize Program launch specs from config files",
+            if cfg!(feature = "serde") {
+                CapabilityStatus::Available
+            } else {
+                CapabilityStatus::MissingFeature("serde")
+            },
+        ),
+        capability(
+            "download",
+            "fetch agent libraries over HTTPS into a hash-pinned cache",
+            if cfg!(feature = "download") {
+                CapabilityStatus::Available
+            } else {
+                CapabilityStatus::MissingFeature("download")
+            },
+        ),
+        capability(
+            "compression",
+            "load gzip- or zstd-compressed agent blobs",
+            if cfg!(feature = "compression") {
+                CapabilityStatus::Available
+            } else {
+                CapabilityStatus::MissingFeature("compression")
+            },
+        ),
+        capability(
+            "signatures",
+            "verify minisign signatures over agent libraries before injection",
+            if cfg!(feature = "signatures") {
+                CapabilityStatus::Available
+            } else {
+                CapabilityStatus::MissingFeature("signatures")
+            },
+        ),
+        capability(
+            "webhook",
+            "POST injection lifecycle events to an HTTP endpoint",
+            if cfg!(feature = "webhook") {
+                CapabilityStatus::Available
+            } else {
+                CapabilityStatus::MissingFeature("webhook")
+            },
+        ),
+        capability(
+            "dynamic-frida",
+            "load frida-core from a shared library at runtime instead of linking it",
+            if cfg!(feature = "dynamic-frida") {
+                CapabilityStatus::Available
+            } else {
+                CapabilityStatus::MissingFeature("dynamic-frida")
+            },
+        ),
+        capability(
+            "remote-device",
+            "inject into processes on a machine running frida-server (Injector::remote)",
+            frida_status(),
+        ),
+        capability(
+            "usb-device",
+            "inject into processes on a USB-attached Android or iOS device (Injector::usb)",
+            frida_status(),
+        ),
+        capability(
+            "android",
+            "list adb devices and push agents to them, directly or with run-as (AdbDevice)",
+            CapabilityStatus::Available,
+        ),
+        capability(
+            "ios",
+            "inject signed dylibs on jailbroken or developer-mode iOS devices (Library::signed_for_ios)",
+            frida_status(),
+        ),
+        capability(
+            "from-crate",
+            "build and locate agent cdylibs from a Cargo crate",
+            CapabilityStatus::Available,
+        ),
+    ];
+
+    FeatureReport { capabilities }
+}
+
+/// For what every engine does: available with Frida, or where a Frida-free
+/// engine (ptrace, LoadLibrary or task port) is built.
+fn engine_status() -> CapabilityStatus {
+    if cfg!(feature = "frida")
+        || cfg!(all(
+            any(target_os = "linux", target_os = "macos", windows),
+            any(target_arch = "x86_64", target_arch = "aarch64")
+        ))
+    {
+        CapabilityStatus::Available
+    } else {
+        CapabilityStatus::MissingFeature("frida")
+    }
+}
+
+/// For what only the Frida backend does.
+fn frida_status() -> CapabilityStatus {
+    if cfg!(feature = "frida") {
+        CapabilityStatus::Available
+    } else {
+        CapabilityStatus::MissingFeature("frida")
+    }
+}
+
+fn capability(
+    name: &'static str,
+    description: &'static str,
+    status: CapabilityStatus,
+) -> Capability {
+    Capability {
+        name,
+        description,
+        status,
+    }
+}
@@ -0,0 +1,161 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Mutex, MutexGuard, Once};
+use std::time::Duration;
+
+use crate::backend::{self, BackendHandle};
+use crate::{InjectOptions, InjectedProcess, Library, ProcessMatcher, Result};
+
+// How long the dispatcher blocks waiting for a spawn before polling again.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Injects a library into matching programs as they are spawned anywhere on
+/// the local device.
+///
+/// While any gate is running, the backend holds every new process on the
+/// device suspended. Processes matching a gate's `ProcessMatcher` are
+/// injected before their first instruction runs; all of them are then
+/// resumed. This reaches short-lived helpers that are launched by someone
+/// else and exit before they could be attached to.
+///
+/// Stopping or dropping the gate stops intercepting; libraries already
+/// injected stay loaded. Spawn gating is provided by Frida on macOS, iOS and
+/// Android; elsewhere `start` fails with `Error::not_supported`.
+///
+/// # Examples
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use hook_inject::{Library, ProcessMatcher, SpawnGate};
+///
+/// let library = Library::from_path("/path/to/libagent.dylib")?;
+/// let gate = SpawnGate::start(ProcessMatcher::pattern("*/crash-helper"), library)?;
+/// while let Some(result) = gate.next(Duration::from_secs(60)) {
+///     let injected = result?;
+///     println!("instrumented pid {}", injected.process().pid());
+/// }
+/// # Ok::<(), hook_inject::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct SpawnGate {
+    id: u64,
+    injections: Receiver<Result<InjectedProcess>>,
+    backend: BackendHandle,
+}
+
+struct Registration {
+    id: u64,
+    matcher: ProcessMatcher,
+    library: Library,
+    options: InjectOptions,
+    injections: Sender<Result<InjectedProcess>>,
+}
+
+static GATES: Mutex<Vec<Registration>> = Mutex::new(Vec::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static DISPATCHER: Once = Once::new();
+
+impl SpawnGate {
+    /// Start injecting `library` into spawns selected by `matcher`.
+    pub fn start(matcher: impl Into<ProcessMatcher>, library: impl Into<Library>) -> Result<Self> {
+        Self::start_with(matcher, library, InjectOptions::default())
+    }
+
+    /// Like `start`, applying per-call options to every injection.
+    pub fn start_with(
+        matcher: impl Into<ProcessMatcher>,
+        library: impl Into<Library>,
+        options: InjectOptions,
+    ) -> Result<Self> {
+        options.validate()?;
+        let library = library.into().with_options(&options)?;
+        let backend = backend::default_backend()?;
+
+        let (sender, receiver) = mpsc::channel();
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        {
+            // Hold the lock across the toggle so gating follows the registry.
+            let mut gates = gates();
+            if gates.is_empty() {
+                backend.enable_spawn_gating()?;
+            }
+            gates.push(Registration {
+                id,
+                matcher: matcher.into(),
+                library,
+                options,
+                injections: sender,
+            });
+        }
+
+        DISPATCHER.call_once(|| {
+            backend::spawn_watcher("hook-inject-spawn-gate", &backend, dispatch);
+        });
+
+        Ok(Self {
+            id,
+            injections: receiver,
+            backend,
+        })
+    }
+
+    /// Wait up to `timeout` for the next matching spawn to be handled.
+    ///
+    /// Returns the injected process, or the error that prevented injecting
+    /// it (the process is resumed either way). Returns `None` if nothing
+    /// matched within `timeout`.
+    pub fn next(&self, timeout: Duration) -> Option<Result<InjectedProcess>> {
+        self.injections.recv_timeout(timeout).ok()
+    }
+
+    /// Stop intercepting spawns.
+    pub fn stop(mut self) -> Result<()> {
+        self.unregister()
+    }
+
+    fn unregister(&mut self) -> Result<()> {
+        let mut gates = gates();
+        let before = gates.len();
+        gates.retain(|gate| gate.id != self.id);
+        if before != gates.len() && gates.is_empty() {
+            return self.backend.disable_spawn_gating();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SpawnGate {
+    fn drop(&mut self) {
+        let _ = self.unregister();
+    }
+}
+
+fn gates() -> MutexGuard<'static, Vec<Registration>> {
+    GATES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn dispatch(backend: &BackendHandle) {
+    match backend.next_spawn(POLL_INTERVAL) {
+        Ok(Some((process, identifier))) => {
+            let target = gates()
+                .iter()
+                .find(|gate| gate.matcher.matches_program(process.pid(), &identifier))
+                .map(|gate| {
+                    (
+                        gate.library.clone(),
+                        gate.options.clone(),
+                        gate.injections.clone(),
+                    )
+                });
+            if let Some((library, options, injections)) = target {
+                let _ = injections.send(backend.inject_process(process, library, &options));
+            }
+            // Every spawn is held while gating is on, matched or not.
+            let _ = backend.resume(process, None);
+        }
+        Ok(None) => {}
+        Err(_) => std::thread::sleep(POLL_INTERVAL),
+    }
+}
@@ -0,0 +1,417 @@
+use object::read::elf::{Dyn, ElfFile, FileHeader};
+use object::read::macho::{
+    FatArch, LoadCommandVariant, MachHeader, MachOFatFile32, MachOFatFile64, MachOFile,
+};
+use object::read::pe::{ImageNtHeaders, PeFile};
+use object::{LittleEndian, Object, elf};
+
+use crate::{Error, Result};
+
+/// Return the names of functions exported by a shared library image.
+///
+/// Mach-O symbol names are returned without their leading underscore so they
+/// compare equal to the C-level names used for entrypoints.
+pub(crate) fn exports(bytes: &[u8]) -> Result<Vec<String>> {
+    let file = object::File::parse(bytes)
+        .map_err(|err| Error::invalid_input(format_args!("failed to parse library: {err}")))?;
+
+    let is_macho = matches!(file.format(), object::BinaryFormat::MachO);
+    let exports = file
+        .exports()
+        .map_err(|err| Error::invalid_input(format_args!("failed to read exports: {err}")))?;
+
+    Ok(exports
+        .iter()
+        .map(|export| {
+            let name = String::from_utf8_lossy(export.name());
+            match name.strip_prefix('_') {
+                Some(stripped) if is_macho => stripped.to_string(),
+                _ => name.into_owned(),
+            }
+        })
+        .collect())
+}
+
+/// Fail with `invalid_input` unless `symbol` is exported by the image.
+///
+/// Multi-architecture Mach-O files must export it from every slice, since
+/// the loader picks the slice.
+pub(crate) fn require_export(bytes: &[u8], symbol: &str) -> Result<()> {
+    for image in slices(bytes)? {
+        if !exports(image)?.iter().any(|name| name == symbol) {
+            return Err(Error::invalid_input(format_args!(
+                "entrypoint `{symbol}` is not exported by the library"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Shared libraries an image links against, and the extra directories it
+/// asks the loader to search (ELF `DT_RPATH`/`DT_RUNPATH`, Mach-O
+/// `LC_RPATH`), in load-command order.
+#[derive(Debug, Default)]
+pub(crate) struct Dependencies {
+    pub(crate) needed: Vec<String>,
+    pub(crate) search_paths: Vec<String>,
+}
+
+/// Read the dependencies of an image; for fat Mach-O files, of every slice.
+pub(crate) fn dependencies(bytes: &[u8]) -> Result<Dependencies> {
+    let mut deps = Dependencies::default();
+    for image in slices(bytes)? {
+        let read = match object::File::parse(image) {
+            Ok(object::File::Elf32(elf)) => elf_dependencies(&elf, &mut deps),
+            Ok(object::File::Elf64(elf)) => elf_dependencies(&elf, &mut deps),
+            Ok(object::File::MachO32(macho)) => macho_dependencies(&macho, &mut deps),
+            Ok(object::File::MachO64(macho)) => macho_dependencies(&macho, &mut deps),
+            Ok(object::File::Pe32(pe)) => pe_dependencies(&pe, &mut deps),
+            Ok(object::File::Pe64(pe)) => pe_dependencies(&pe, &mut deps),
+            Ok(_) => Ok(()),
+            Err(err) => Err(err),
+        };
+        read.map_err(|err| {
+            Error::invalid_input(format_args!("failed to read library dependencies: {err}"))
+        })?;
+    }
+
+    for list in [&mut deps.needed, &mut deps.search_paths] {
+        let mut seen = std::collections::HashSet::new();
+        list.retain(|name| seen.insert(name.clone()));
+    }
+    Ok(deps)
+}
+
+fn elf_dependencies<Elf: FileHeader>(
+    elf: &ElfFile<'_, Elf>,
+    deps: &mut Dependencies,
+) -> object::Result<()> {
+    let endian = elf.endian();
+    let sections = elf.elf_section_table();
+    let Some((dynamic, link)) = sections.dynamic(endian, elf.data())? else {
+        return Ok(());
+    };
+    let strings = sections.strings(endian, elf.data(), link)?;
+    for entry in dynamic {
+        let value = || entry.string(endian, strings).map(String::from_utf8_lossy);
+        match entry.tag32(endian) {
+            Some(elf::DT_NEEDED) => deps.needed.push(value()?.into_owned()),
+            Some(elf::DT_RPATH | elf::DT_RUNPATH) => deps
+                .search_paths
+                .extend(value()?.split(':').map(str::to_string)),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn macho_dependencies<Mach: MachHeader>(
+    macho: &MachOFile<'_, Mach>,
+    deps: &mut Dependencies,
+) -> object::Result<()> {
+    let endian = macho.endian();
+    let mut commands = macho.macho_load_commands()?;
+    while let Some(command) = commands.next()? {
+        let (list, name) = match command.variant()? {
+            LoadCommandVariant::Dylib(dylib) => (&mut deps.needed, dylib.dylib.name),
+            LoadCommandVariant::Rpath(rpath) => (&mut deps.search_paths, rpath.path),
+            _ => continue,
+        };
+        list.push(String::from_utf8_lossy(command.string(endian, name)?).into_owned());
+    }
+    Ok(())
+}
+
+fn pe_dependencies<Pe: ImageNtHeaders>(
+    pe: &PeFile<'_, Pe>,
+    deps: &mut Dependencies,
+) -> object::Result<()> {
+    let Some(table) = pe.import_table()? else {
+        return Ok(());
+    };
+    let mut descriptors = table.descriptors()?;
+    while let Some(descriptor) = descriptors.next()? {
+        let name = table.name(descriptor.name.get(LittleEndian))?;
+        deps.needed.push(String::from_utf8_lossy(name).into_owned());
+    }
+    Ok(())
+}
+
+/// Symbol versions an ELF image requires from its dependencies
+/// (`.gnu.version_r`), e.g. `GLIBC_2.34`. Other formats have none.
+pub(crate) fn required_versions(bytes: &[u8]) -> Result<Vec<String>> {
+    symbol_versions(bytes, false)
+}
+
+/// Symbol versions an ELF image defines (`.gnu.version_d`).
+pub(crate) fn defined_versions(bytes: &[u8]) -> Result<Vec<String>> {
+    symbol_versions(bytes, true)
+}
+
+fn symbol_versions(bytes: &[u8], defined: bool) -> Result<Vec<String>> {
+    let read = match object::File::parse(bytes) {
+        Ok(object::File::Elf32(elf)) => elf_versions(&elf, defined),
+        Ok(object::File::Elf64(elf)) => elf_versions(&elf, defined),
+        Ok(_) => Ok(Vec::new()),
+        Err(err) => Err(err),
+    };
+    read.map_err(|err| Error::invalid_input(format_args!("failed to read symbol versions: {err}")))
+}
+
+fn elf_versions<Elf: FileHeader>(
+    elf: &ElfFile<'_, Elf>,
+    defined: bool,
+) -> object::Result<Vec<String>> {
+    let endian = elf.endian();
+    let sections = elf.elf_section_table();
+    let mut versions = Vec::new();
+    let mut push = |name: &[u8]| versions.push(String::from_utf8_lossy(name).into_owned());
+    if defined {
+        let Some((mut defs, link)) = sections.gnu_verdef(endian, elf.data())? else {
+            return Ok(versions);
+        };
+        let strings = sections.strings(endian, elf.data(), link)?;
+        while let Some((_, mut names)) = defs.next()? {
+            // The first name is the version itself; the rest are parents.
+            if let Some(name) = names.next()? {
+                push(name.name(endian, strings)?);
+            }
+        }
+    } else {
+        let Some((mut needs, link)) = sections.gnu_verneed(endian, elf.data())? else {
+            return Ok(versions);
+        };
+        let strings = sections.strings(endian, elf.data(), link)?;
+        while let Some((_, mut names)) = needs.next()? {
+            while let Some(name) = names.next()? {
+                push(name.name(endian, strings)?);
+            }
+        }
+    }
+    Ok(versions)
+}
+
+// Code signature blobs (big-endian) and the CodeDirectory flags that
+// restrict what dyld lets the environment do.
+#[cfg(target_os = "macos")]
+const CSMAGIC_EMBEDDED_SIGNATURE: u32 = 0xfade_0cc0;
+#[cfg(target_os = "macos")]
+const CSSLOT_CODEDIRECTORY: u32 = 0;
+#[cfg(target_os = "macos")]
+const CSSLOT_ENTITLEMENTS: u32 = 5;
+#[cfg(target_os = "macos")]
+const CS_RESTRICT: u32 = 0x800;
+#[cfg(target_os = "macos")]
+const CS_REQUIRE_LV: u32 = 0x2000;
+#[cfg(target_os = "macos")]
+const CS_RUNTIME: u32 = 0x10000;
+
+/// Why dyld would not load `DYLD_INSERT_LIBRARIES` into a Mach-O executable,
+/// as a clause to follow its name, or `None` if nothing in the image stops
+/// it; for fat files, in any slice.
+///
+/// dyld strips `DYLD_*` variables for images with a `__RESTRICT` segment,
+/// for restricted or hardened-runtime signatures without the entitlement
+/// that allows them, and for platform binaries while SIP is enabled (which
+/// is assumed). Library validation refuses an inserted library that isn't
+/// signed by the program's team. Unreadable images report `None`.
+#[cfg(target_os = "macos")]
+pub(crate) fn dyld_restriction(bytes: &[u8]) -> Option<&'static str> {
+    slices(bytes)
+        .ok()?
+        .into_iter()
+        .find_map(|image| match object::File::parse(image) {
+            Ok(object::File::MachO32(macho)) => macho_restriction(&macho),
+            Ok(object::File::MachO64(macho)) => macho_restriction(&macho),
+            _ => None,
+        })
+}
+
+#[cfg(target_os = "macos")]
+fn macho_restriction<Mach: MachHeader>(macho: &MachOFile<'_, Mach>) -> Option<&'static str> {
+    use object::{ObjectSegment, macho::LC_CODE_SIGNATURE};
+
+    if macho
+        .segments()
+        .any(|segment| segment.name_bytes().ok().flatten() == Some(b"__RESTRICT"))
+    {
+        return Some("has a __RESTRICT segment, so dyld ignores DYLD_INSERT_LIBRARIES");
+    }
+    let endian = macho.endian();
+    let mut commands = macho.macho_load_commands().ok()?;
+    while let Ok(Some(command)) = commands.next() {
+        if let Ok(LoadCommandVariant::LinkeditData(data)) = command.variant()
+            && data.cmd.get(endian) == LC_CODE_SIGNATURE
+        {
+            let start = data.dataoff.get(endian) as usize;
+            let end = start.checked_add(data.datasize.get(endian) as usize)?;
+            return signature_restriction(macho.data().get(start..end)?);
+        }
+    }
+    None
+}
+
+/// Check an embedded code signature's CodeDirectory and entitlements.
+#[cfg(target_os = "macos")]
+fn signature_restriction(signature: &[u8]) -> Option<&'static str> {
+    let word = |bytes: &[u8], at: usize| {
+        let word = bytes.get(at..at.checked_add(4)?)?;
+        Some(u32::from_be_bytes(word.try_into().ok()?))
+    };
+    if word(signature, 0)? != CSMAGIC_EMBEDDED_SIGNATURE {
+        return None;
+    }
+    let (mut flags, mut platform, mut entitlements) = (0, 0, &[][..]);
+    for index in 0..word(signature, 8)? as usize {
+        let slot = word(signature, 12 + index * 8)?;
+        let blob = signature.get(word(signature, 16 + index * 8)? as usize..)?;
+        match slot {
+            // flags at 12, platform at 38.
+            CSSLOT_CODEDIRECTORY => {
+                flags = word(blob, 12)?;
+                platform = *blob.get(38)?;
+            }
+            // An XML plist after the magic and length.
+            CSSLOT_ENTITLEMENTS => entitlements = blob.get(8..word(blob, 4)? as usize)?,
+            _ => {}
+        }
+    }
+
+    let entitled = |key: &str| {
+        let key = format!("<key>{key}</key>");
+        entitlements
+            .windows(key.len())
+            .position(|window| window == key.as_bytes())
+            .is_some_and(|at| {
+                entitlements[at + key.len()..]
+                    .trim_ascii_start()
+                    .starts_with(b"<true/>")
+            })
+    };
+    if platform != 0 {
+        Some(
+            "is a platform binary, so dyld ignores DYLD_INSERT_LIBRARIES while System \
+             Integrity Protection is enabled",
+        )
+    } else if flags & CS_RESTRICT != 0 {
+        Some("is signed as restricted, so dyld ignores DYLD_INSERT_LIBRARIES")
+    } else if flags & CS_RUNTIME != 0
+        && !entitled("com.apple.security.cs.allow-dyld-environment-variables")
+    {
+        Some(
+            "is signed with the hardened runtime and without the \
+             com.apple.security.cs.allow-dyld-environment-variables entitlement, so dyld \
+             ignores DYLD_INSERT_LIBRARIES",
+        )
+    } else if flags & (CS_RUNTIME | CS_REQUIRE_LV) != 0
+        && !entitled("com.apple.security.cs.disable-library-validation")
+    {
+        Some("enforces library validation, which refuses the injector's loader")
+    } else {
+        None
+    }
+}
+
+/// Whether `bytes` is a Mach-O image with an embedded code signature, in
+/// every slice for fat files.
+pub(crate) fn is_code_signed(bytes: &[u8]) -> Result<bool> {
+    for image in slices(bytes)? {
+        let signed = match object::File::parse(image) {
+            Ok(object::File::MachO32(macho)) => has_code_signature(&macho),
+            Ok(object::File::MachO64(macho)) => has_code_signature(&macho),
+            _ => false,
+        };
+        if !signed {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn has_code_signature<Mach: MachHeader>(macho: &MachOFile<'_, Mach>) -> bool {
+    use object::macho::LC_CODE_SIGNATURE;
+
+    let Ok(mut commands) = macho.macho_load_commands() else {
+        return false;
+    };
+    while let Ok(Some(command)) = commands.next() {
+        if command.cmd() == LC_CODE_SIGNATURE {
+            return true;
+        }
+    }
+    false
+}
+
+/// Split a fat Mach-O file into its per-architecture images; any other
+/// image is returned whole.
+fn slices(bytes: &[u8]) -> Result<Vec<&[u8]>> {
+    let slices = match object::FileKind::parse(bytes) {
+        Ok(object::FileKind::MachOFat32) => {
+            MachOFatFile32::parse(bytes).and_then(|fat| fat_slices(fat.arches(), bytes))
+        }
+        Ok(object::FileKind::MachOFat64) => {
+            MachOFatFile64::parse(bytes).and_then(|fat| fat_slices(fat.arches(), bytes))
+        }
+        _ => return Ok(vec![bytes]),
+    };
+    slices.map_err(|err| Error::invalid_input(format_args!("failed to parse library: {err}")))
+}
+
+fn fat_slices<'data>(
+    arches: &[impl FatArch],
+    bytes: &'data [u8],
+) -> object::Result<Vec<&'data [u8]>> {
+    arches.iter().map(|arch| arch.data(bytes)).collect()
+}
+
+/// Return the offset of an exported symbol from the image's load address, or
+/// `None` if the image does not export it.
+pub(crate) fn export_offset(bytes: &[u8], symbol: &str) -> Result<Option<u64>> {
+    let file = object::File::parse(bytes)
+        .map_err(|err| Error::invalid_input(format_args!("failed to parse library: {err}")))?;
+
+    let is_macho = matches!(file.format(), object::BinaryFormat::MachO);
+    let exports = file
+        .exports()
+        .map_err(|err| Error::invalid_input(format_args!("failed to read exports: {err}")))?;
+
+    let base = file.relative_address_base();
+    Ok(exports
+        .iter()
+        .find(|export| {
+            let name = export.name();
+            let name = match name.strip_prefix(b"_") {
+                Some(stripped) if is_macho => stripped,
+                _ => name,
+            };
+            name == symbol.as_bytes()
+        })
+        .map(|export| export.address().wrapping_sub(base)))
+}
+
+/// Return the file offset of the code of `symbol`, exported or in the
+/// symbol table, or `None` if the image does not define it. Uprobes are
+/// placed by file offset.
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+pub(crate) fn symbol_file_offset(bytes: &[u8], symbol: &str) -> Result<Option<u64>> {
+    use object::{ObjectSegment, ObjectSymbol};
+
+    let file = object::File::parse(bytes)
+        .map_err(|err| Error::invalid_input(format_args!("failed to parse library: {err}")))?;
+    let Some(address) = file
+        .symbols()
+        .chain(file.dynamic_symbols())
+        .find(|sym| sym.is_definition() && sym.name_bytes() == Ok(symbol.as_bytes()))
+        .map(|sym| sym.address())
+    else {
+        return Ok(None);
+    };
+    Ok(file.segments().find_map(|segment| {
+        let (offset, size) = segment.file_range();
+        let delta = address.checked_sub(segment.address())?;
+        (delta < size).then_some(offset + delta)
+    }))
+}
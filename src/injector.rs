@@ -0,0 +1,654 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::backend::{self, BackendHandle};
+use crate::device::DeviceSpec;
+use crate::{
+    Error, ErrorKind, InjectOptions, InjectedProcess, InjectedProgram, InjectedSet, InjectionBatch,
+    Library, Process, ProcessMatcher, Program, Result, SuspendedProgram, spawn,
+};
+
+const BACKEND_ENV: &str = "HOOK_INJECT_BACKEND";
+
+/// Runtime engine an `Injector` injects with.
+///
+/// Unless an injector is built with `InjectorBuilder::backend`, the
+/// `HOOK_INJECT_BACKEND` environment variable picks one by name (see
+/// `Backend::from_env`), so one binary can be pointed at a different engine
+/// per deployment. Choosing a backend that is not compiled into this build
+/// fails with `ErrorKind::NotSupported`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Backend {
+    /// frida-core, through the bundled C shim (`frida`). Needs the `frida`
+    /// feature, which is on by default.
+    Frida,
+    /// Native Linux injection without Frida (`ptrace`): the target is
+    /// briefly stopped with `ptrace` and made to `dlopen` the library, whose
+    /// entrypoint then runs on a thread of its own. x86_64 and aarch64 only.
+    ///
+    /// Libraries are loaded from files, and programs are launched natively
+    /// and held at their entrypoint. Following exec or children and spawn
+    /// gating are not supported.
+    Ptrace,
+    /// Native Windows injection without Frida (`loadlibrary`): a thread
+    /// created in the target with `CreateRemoteThread` runs `LoadLibraryW`
+    /// on the DLL, and the entrypoint then runs on a second one. x86_64 and
+    /// aarch64 only.
+    ///
+    /// Libraries are loaded from files, and programs are launched natively
+    /// and held suspended. Following exec or children and spawn gating are
+    /// not supported.
+    LoadLibrary,
+    /// Native macOS injection without Frida (`taskport`): with the target's
+    /// task port from `task_for_pid`, a thread started there makes it
+    /// `dlopen` the library, and the entrypoint then runs on a thread of its
+    /// own. x86_64 and aarch64 (not arm64e targets) only; the injector needs
+    /// the rights `task_for_pid` asks for, and the target must allow it.
+    ///
+    /// Libraries are loaded from files. Programs are launched natively, but
+    /// are held before dyld has run, where there is nothing to load with yet,
+    /// so injecting into them fails with `ErrorKind::NotSupported`, as do
+    /// following exec or children and spawn gating.
+    TaskPort,
+    /// Injection through the dynamic loader (`preload`), on Linux and
+    /// macOS: programs are launched with a small loader in `LD_PRELOAD`
+    /// (`DYLD_INSERT_LIBRARIES` on macOS), which loads the library and runs
+    /// its entrypoint on the main thread before `main`. Nothing is traced
+    /// and no task port is needed, so it works where YAMA restricts
+    /// `ptrace` and on machines without the debugging entitlements. Needs
+    /// the `preload-backend` feature.
+    ///
+    /// Only programs it launches can be injected, from library files and
+    /// with string entrypoint data. Statically linked and setuid programs
+    /// ignore `LD_PRELOAD`; on macOS, dyld also strips
+    /// `DYLD_INSERT_LIBRARIES` for platform binaries and hardened-runtime
+    /// programs without the entitlement that allows it. Such programs are
+    /// rejected with `ErrorKind::NotSupported` before they are launched, as
+    /// are injecting into running processes, following exec or children
+    /// and spawn gating.
+    Preload,
+}
+
+impl Backend {
+    /// The backends compiled into this build.
+    pub fn compiled() -> &'static [Backend] {
+        let backends: &'static [Backend] = if cfg!(all(
+            target_os = "linux",
+            any(target_arch = "x86_64", target_arch = "aarch64"),
+            feature = "preload-backend"
+        )) {
+            &[Backend::Frida, Backend::Ptrace, Backend::Preload]
+        } else if cfg!(all(
+            target_os = "linux",
+            any(target_arch = "x86_64", target_arch = "aarch64")
+        )) {
+            &[Backend::Frida, Backend::Ptrace]
+        } else if cfg!(all(
+            windows,
+            any(target_arch = "x86_64", target_arch = "aarch64")
+        )) {
+            &[Backend::Frida, Backend::LoadLibrary]
+        } else if cfg!(all(
+            target_os = "macos",
+            any(target_arch = "x86_64", target_arch = "aarch64"),
+            feature = "preload-backend"
+        )) {
+            &[Backend::Frida, Backend::TaskPort, Backend::Preload]
+        } else if cfg!(all(
+            target_os = "macos",
+            any(target_arch = "x86_64", target_arch = "aarch64")
+        )) {
+            &[Backend::Frida, Backend::TaskPort]
+        } else if cfg!(all(
+            any(target_os = "linux", target_os = "macos"),
+            feature = "preload-backend"
+        )) {
+            &[Backend::Frida, Backend::Preload]
+        } else {
+            &[Backend::Frida]
+        };
+        // Frida leads every list.
+        if cfg!(feature = "frida") {
+            backends
+        } else {
+            &backends[1..]
+        }
+    }
+
+    /// The name `HOOK_INJECT_BACKEND` and `str::parse` take.
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Frida => "frida",
+            Backend::Ptrace => "ptrace",
+            Backend::LoadLibrary => "loadlibrary",
+            Backend::TaskPort => "taskport",
+            Backend::Preload => "preload",
+        }
+    }
+
+    /// The backend named by `HOOK_INJECT_BACKEND`, or the default if it is
+    /// unset or empty.
+    ///
+    /// Fails with `ErrorKind::NotSupported` if it names a backend this
+    /// build does not have.
+    pub fn from_env() -> Result<Backend> {
+        match std::env::var(BACKEND_ENV) {
+            Ok(name) if !name.trim().is_empty() => name.parse(),
+            Ok(_) | Err(std::env::VarError::NotPresent) => Ok(Backend::default()),
+            Err(std::env::VarError::NotUnicode(name)) => Err(unsupported(&name.to_string_lossy())),
+        }
+    }
+}
+
+impl Default for Backend {
+    /// Frida, or without the `frida` feature the first native backend
+    /// compiled in.
+    fn default() -> Backend {
+        Backend::compiled()
+            .first()
+            .copied()
+            .unwrap_or(Backend::Frida)
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Backend {
+    type Err = Error;
+
+    /// Parse a backend name, ignoring case and surrounding whitespace.
+    fn from_str(name: &str) -> Result<Backend> {
+        let name = name.trim();
+        Backend::compiled()
+            .iter()
+            .copied()
+            .find(|backend| backend.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| unsupported(name))
+    }
+}
+
+pub(crate) fn unsupported(name: &str) -> Error {
+    let compiled: Vec<_> = Backend::compiled().iter().map(|b| b.name()).collect();
+    Error::not_supported(format_args!(
+        "backend `{name}` is not available in this build (available: {})",
+        compiled.join(", ")
+    ))
+}
+
+/// Injects libraries with its own backend and default options.
+///
+/// The free functions (`inject_process`, `inject_program`, ...) use a
+/// default `Injector`, with the backend `HOOK_INJECT_BACKEND` names; build
+/// one to pick the backend or to give every call
+/// the same options, and keep several when different parts of an
+/// application need different settings. Injectors using the same backend
+/// share its runtime, which is set up by the first one built and kept for
+/// the rest of the process. A runtime that fails to set up is not kept, so
+/// the next build tries again.
+///
+/// # Examples
+/// ```no_run
+/// use std::time::Duration;
+/// use hook_inject::{Backend, InjectOptions, Injector, Library, Process};
+///
+/// let injector = Injector::builder()
+///     .backend(Backend::Frida)
+///     .options(InjectOptions::new().timeout(Duration::from_secs(5)))
+///     .build()?;
+/// let library = Library::from_path("/path/to/libagent.so")?;
+/// let injected = injector.inject_process(Process::from_pid(1234)?, library)?;
+/// injected.uninject()?;
+/// # Ok::<(), hook_inject::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Injector {
+    // Unset for the default injector the free functions use, which picks
+    // and sets up its backend once a call's inputs check out.
+    runtime: Option<(Backend, BackendHandle)>,
+    options: InjectOptions,
+}
+
+impl Injector {
+    /// An injector with the backend `HOOK_INJECT_BACKEND` names and the
+    /// default options.
+    pub fn new() -> Result<Self> {
+        Self::builder().build()
+    }
+
+    /// Start configuring an injector.
+    pub fn builder() -> InjectorBuilder {
+        InjectorBuilder::default()
+    }
+
+    /// An injector for the processes of the machine running frida-server at
+    /// `address` (`host:port`; frida-server listens on 27042 by default),
+    /// with the default options.
+    ///
+    /// Programs are launched, injected and resumed on that machine, and
+    /// libraries are sent to it from here. Its processes are looked up with
+    /// `Injector::process`; being elsewhere, they cannot be inspected,
+    /// followed through exec, or given binary entrypoint data.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{Injector, Library, Program};
+    ///
+    /// let injector = Injector::remote("192.168.1.20:27042")?;
+    /// let library = Library::from_path("/path/to/libagent.so")?;
+    /// let injected = injector.inject_program(Program::from_shell("/usr/bin/sleep 60")?, library)?;
+    /// injected.uninject()?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn remote(address: impl Into<String>) -> Result<Self> {
+        Self::builder().remote(address).build()
+    }
+
+    /// An injector for the processes of the device with `id` (see
+    /// `devices`), with the default options. Its targets are handled as
+    /// those of `Injector::remote`; the id `local` is this machine.
+    ///
+    /// Waits up to the default options' timeout, or 5 seconds, for the
+    /// device to be attached.
+    pub fn device(id: impl Into<String>) -> Result<Self> {
+        Self::builder().device(id).build()
+    }
+
+    /// An injector for the processes of the first USB device, e.g. an
+    /// Android phone or iOS device running frida-server, with the default
+    /// options. Its targets are handled as those of `Injector::remote`;
+    /// programs may be launched by app identifier.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{Injector, Library, Program};
+    ///
+    /// let injector = Injector::usb()?;
+    /// let library = Library::from_path("/path/to/libagent.so")?;
+    /// let injected = injector.inject_program(Program::new("com.example.app"), library)?;
+    /// injected.uninject()?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn usb() -> Result<Self> {
+        Self::builder().usb().build()
+    }
+
+    /// The default injector, without setting up its runtime yet.
+    pub(crate) fn lazy() -> Self {
+        Self {
+            runtime: None,
+            options: InjectOptions::default(),
+        }
+    }
+
+    fn runtime(&self) -> Result<BackendHandle> {
+        match &self.runtime {
+            Some((_, backend)) => Ok(backend.clone()),
+            None => runtime(Backend::from_env()?),
+        }
+    }
+
+    /// The backend this injector uses.
+    pub fn backend(&self) -> Backend {
+        match &self.runtime {
+            Some((backend, _)) => *backend,
+            None => Backend::from_env().unwrap_or_default(),
+        }
+    }
+
+    /// The options used by calls that do not pass their own.
+    pub fn options(&self) -> &InjectOptions {
+        &self.options
+    }
+
+    /// Whether this injector's targets run on a remote device.
+    pub fn is_remote(&self) -> bool {
+        matches!(&self.runtime, Some((_, backend)) if backend.is_remote())
+    }
+
+    /// The process with `pid` among this injector's targets: on this
+    /// machine, as `Process::from_pid`, or on the remote device.
+    ///
+    /// Fails with `ErrorKind::ProcessNotFound` if there is none.
+    pub fn process(&self, pid: i32) -> Result<Process> {
+        match &self.runtime {
+            Some((_, backend)) => backend.find_process(pid),
+            None => Process::from_pid(pid),
+        }
+    }
+
+    /// Inject a library into a program launched under injector control.
+    ///
+    /// See `inject_program`.
+    pub fn inject_program(
+        &self,
+        spec: impl Into<Program>,
+        library: impl Into<Library>,
+    ) -> Result<InjectedProgram> {
+        self.inject_program_with(spec, library, self.options.clone())
+    }
+
+    /// Inject a library into a launched program with per-call options,
+    /// used instead of the injector's.
+    ///
+    /// See `inject_program_with`.
+    pub fn inject_program_with(
+        &self,
+        spec: impl Into<Program>,
+        library: impl Into<Library>,
+        options: InjectOptions,
+    ) -> Result<InjectedProgram> {
+        options.validate()?;
+        let library = library.into().with_options(&options)?;
+        let backend = self.runtime()?;
+        let injected = backend.inject_program(spec.into(), library, &options)?;
+        let Some(timeout) = options.ready_timeout_value() else {
+            return Ok(injected);
+        };
+        if let Err(err) = injected.wait_ready(timeout) {
+            // The program was launched for the agent; don't leave it running.
+            let process = injected.process();
+            let _ = injected.uninject();
+            let _ = backend.kill(process);
+            return Err(err);
+        }
+        Ok(injected)
+    }
+
+    /// Launch a `Command` natively, inject before it runs, then resume it.
+    ///
+    /// See `inject_command`.
+    pub fn inject_command(
+        &self,
+        cmd: std::process::Command,
+        library: impl Into<Library>,
+    ) -> Result<InjectedProgram> {
+        let program = Program::native(cmd).suspend_at(spawn::preferred_point());
+        self.inject_program(program, library)
+    }
+
+    /// Inject a library into an already-running process.
+    ///
+    /// See `inject_process`.
+    pub fn inject_process(
+        &self,
+        process: Process,
+        library: impl Into<Library>,
+    ) -> Result<InjectedProcess> {
+        self.inject_process_with(process, library, self.options.clone())
+    }
+
+    /// Inject a library into an already-running process with per-call
+    /// options, used instead of the injector's.
+    ///
+    /// See `inject_process_with`.
+    pub fn inject_process_with(
+        &self,
+        process: Process,
+        library: impl Into<Library>,
+        options: InjectOptions,
+    ) -> Result<InjectedProcess> {
+        options.validate()?;
+        let library = library.into().with_options(&options)?;
+        let injected = self.runtime()?.inject_process(process, library, &options)?;
+        await_ready(injected, &options)
+    }
+
+    /// Inject a library into each of `processes`, returning one result per
+    /// target, in order.
+    ///
+    /// See `inject_processes`.
+    pub fn inject_processes(
+        &self,
+        processes: &[Process],
+        library: &Library,
+    ) -> Vec<(Process, Result<InjectedProcess>)> {
+        self.inject_processes_with(processes, library, self.options.clone())
+    }
+
+    /// Inject a library into each of `processes` with per-call options,
+    /// used instead of the injector's.
+    ///
+    /// See `inject_processes_with`.
+    pub fn inject_processes_with(
+        &self,
+        processes: &[Process],
+        library: &Library,
+        options: InjectOptions,
+    ) -> Vec<(Process, Result<InjectedProcess>)> {
+        let prepared = options.validate().and_then(|()| {
+            let library = library.clone().with_options(&options)?;
+            Ok((self.runtime()?, library))
+        });
+        let (backend, library) = match prepared {
+            Ok(prepared) => prepared,
+            Err(err) => {
+                return processes
+                    .iter()
+                    .map(|&process| (process, Err(err.clone())))
+                    .collect();
+            }
+        };
+        let inject = |process| {
+            backend
+                .inject_process(process, library.clone(), &options)
+                .and_then(|injected| await_ready(injected, &options))
+        };
+
+        let workers = options.concurrency_value().min(processes.len());
+        if workers <= 1 {
+            return processes
+                .iter()
+                .map(|&process| (process, inject(process)))
+                .collect();
+        }
+        // Worker `w` takes targets `w`, `w + workers`, ...
+        let mut results: Vec<_> = std::thread::scope(|scope| {
+            let threads: Vec<_> = (0..workers)
+                .map(|worker| {
+                    let inject = &inject;
+                    scope.spawn(move || {
+                        processes
+                            .iter()
+                            .enumerate()
+                            .skip(worker)
+                            .step_by(workers)
+                            .map(|(index, &process)| (index, process, inject(process)))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            threads
+                .into_iter()
+                .flat_map(|thread| {
+                    thread
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                })
+                .collect()
+        });
+        results.sort_by_key(|(index, ..)| *index);
+        results
+            .into_iter()
+            .map(|(_, process, result)| (process, result))
+            .collect()
+    }
+
+    /// Inject a library into every running process matching `matcher`.
+    ///
+    /// See `inject_by_name`.
+    pub fn inject_by_name(
+        &self,
+        matcher: impl Into<ProcessMatcher>,
+        library: impl Into<Library>,
+    ) -> Result<InjectionBatch> {
+        let matcher = matcher.into();
+        if self.is_remote() {
+            return Err(Error::not_supported(
+                "finding processes by name is not supported on a remote device",
+            ));
+        }
+        let processes = Process::find_all(matcher.clone())?;
+        if processes.is_empty() {
+            return Err(Error::new(
+                ErrorKind::ProcessNotFound,
+                format_args!("no process matching {matcher}"),
+            ));
+        }
+        Ok(InjectionBatch::collect(
+            self.inject_processes(&processes, &library.into()),
+        ))
+    }
+
+    /// Inject several libraries into a running process, in order, rolling
+    /// back if one fails.
+    ///
+    /// See `inject_process_all`.
+    pub fn inject_process_all(
+        &self,
+        process: Process,
+        libraries: &[Library],
+    ) -> Result<InjectedSet> {
+        let mut set = InjectedSet::new();
+        for library in libraries {
+            match self.inject_process(process, library.clone()) {
+                Ok(injected) => set.push(injected),
+                Err(err) => {
+                    return Err(match set.force_uninject() {
+                        Ok(()) => err,
+                        Err(rollback) => Error::new(
+                            err.kind(),
+                            format_args!("{err}; rolling back earlier injections: {rollback}"),
+                        )
+                        .with_source(rollback),
+                    });
+                }
+            }
+        }
+        Ok(set)
+    }
+
+    /// Spawn a program in a suspended state.
+    ///
+    /// See `spawn`.
+    pub fn spawn(&self, spec: impl Into<Program>) -> Result<SuspendedProgram> {
+        self.spawn_with(spec, self.options.clone())
+    }
+
+    /// Spawn a program in a suspended state with per-call options, used
+    /// instead of the injector's.
+    ///
+    /// See `spawn_with`.
+    pub fn spawn_with(
+        &self,
+        spec: impl Into<Program>,
+        options: InjectOptions,
+    ) -> Result<SuspendedProgram> {
+        options.validate()?;
+        self.runtime()?.spawn(spec.into(), &options)
+    }
+}
+
+/// Configures an `Injector`; see `Injector::builder`.
+#[derive(Debug, Clone, Default)]
+pub struct InjectorBuilder {
+    backend: Option<Backend>,
+    device: Option<DeviceSpec>,
+    options: InjectOptions,
+}
+
+impl InjectorBuilder {
+    /// Use `backend` instead of the one `HOOK_INJECT_BACKEND` names.
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Target the machine running frida-server at `address` instead of this
+    /// one; see `Injector::remote`. Only the Frida backend reaches remote
+    /// devices.
+    pub fn remote(mut self, address: impl Into<String>) -> Self {
+        self.device = Some(DeviceSpec::Remote(address.into()));
+        self
+    }
+
+    /// Target the device with `id` instead of this machine; see
+    /// `Injector::device`.
+    pub fn device(mut self, id: impl Into<String>) -> Self {
+        self.device = Some(DeviceSpec::Id(id.into()));
+        self
+    }
+
+    /// Target the first USB device instead of this machine; see
+    /// `Injector::usb`.
+    pub fn usb(mut self) -> Self {
+        self.device = Some(DeviceSpec::Usb);
+        self
+    }
+
+    /// Apply `options` to every call that does not pass its own (default
+    /// `InjectOptions::default()`).
+    pub fn options(mut self, options: InjectOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Set up the backend's runtime, if no injector has yet, and build the
+    /// injector.
+    ///
+    /// Fails if the options are invalid, the backend is not available, or
+    /// its runtime cannot be set up. With `remote`, connecting is bounded by
+    /// the options' timeout and fails with `ErrorKind::TimedOut` past it.
+    pub fn build(mut self) -> Result<Injector> {
+        self.options.validate()?;
+        if self.device == Some(DeviceSpec::Id("local".to_string())) {
+            self.device = None;
+            self.backend.get_or_insert(Backend::Frida);
+        }
+        if let Some(device) = &self.device {
+            if let Some(backend) = self.backend.filter(|backend| *backend != Backend::Frida) {
+                return Err(Error::not_supported(format_args!(
+                    "the {backend} backend cannot reach remote devices"
+                )));
+            }
+            if matches!(device, DeviceSpec::Remote(name) | DeviceSpec::Id(name) if name.is_empty())
+            {
+                return Err(Error::invalid_input("device id or address is empty"));
+            }
+            let handle = backend::remote_runtime(device, self.options.timeout_value())?;
+            return Ok(Injector {
+                runtime: Some((Backend::Frida, handle)),
+                options: self.options,
+            });
+        }
+        let backend = match self.backend {
+            Some(backend) => backend,
+            None => Backend::from_env()?,
+        };
+        Ok(Injector {
+            runtime: Some((backend, runtime(backend)?)),
+            options: self.options,
+        })
+    }
+}
+
+fn runtime(backend: Backend) -> Result<BackendHandle> {
+    backend::runtime(backend)
+}
+
+/// Apply `InjectOptions::wait_ready`, uninjecting an agent that does not get
+/// ready.
+fn await_ready(injected: InjectedProcess, options: &InjectOptions) -> Result<InjectedProcess> {
+    let Some(timeout) = options.ready_timeout_value() else {
+        return Ok(injected);
+    };
+    if let Err(err) = injected.wait_ready(timeout) {
+        let _ = injected.uninject();
+        return Err(err);
+    }
+    Ok(injected)
+}
@@ -0,0 +1,100 @@
+use std::process::{Command, Stdio};
+
+use crate::library::LibrarySource;
+use crate::{Architecture, BinaryFormat, Error, InjectOptions, Library, Result};
+use crate::{image, staging};
+
+/// The id Frida reports for iOS devices (and iPadOS).
+#[cfg(feature = "frida")]
+pub(crate) const OS_ID: &str = "ios";
+
+/// `library` as iOS will load it: checked to be an arm64 Mach-O image, and
+/// ad-hoc signed if it carries no code signature. Signing works on a copy
+/// staged where `options` say.
+pub(crate) fn stage_dylib(library: &Library, options: &InjectOptions) -> Result<Library> {
+    if matches!(library.source(), LibrarySource::Device(_)) {
+        return Ok(library.clone());
+    }
+    let library = library.decompressed()?;
+    let format = library.format();
+    if format != Some(BinaryFormat::MachO) {
+        return Err(Error::invalid_input(match format {
+            Some(format) => format!("iOS loads Mach-O dylibs, but the library is a {format} image"),
+            None => "iOS loads Mach-O dylibs, but the library is not a recognized image".into(),
+        }));
+    }
+    // Fat images report no architecture; iOS picks their arm64 slice.
+    if let Some(architecture) = library.architecture()
+        && architecture != Architecture::Aarch64
+    {
+        return Err(Error::invalid_input(format_args!(
+            "the library is built for {architecture}, but iOS devices run arm64"
+        )));
+    }
+
+    let bytes = library.image_bytes()?;
+    if image::is_code_signed(&bytes)? {
+        return Ok(library);
+    }
+    let path = staging::stage_bytes(options, "dylib", &bytes)?;
+    let signed = sign(&path).and_then(|()| Ok(std::fs::read(&path)?));
+    let _ = std::fs::remove_file(&path);
+    Ok(library.with_image(signed?.into()))
+}
+
+/// Ad-hoc sign the Mach-O image at `path` in place.
+fn sign(path: &std::path::Path) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("codesign");
+        command.args(["--force", "--sign", "-"]);
+        command
+    } else {
+        let mut command = Command::new("ldid");
+        command.arg("-S");
+        command
+    };
+    let tool = command.get_program().to_string_lossy().into_owned();
+    let output = command
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|err| {
+            Error::invalid_input(format_args!(
+                "the library has no code signature, which iOS requires, and {tool} could not \
+                 sign it: {err}; sign it before injecting"
+            ))
+        })?;
+    if !output.status.success() {
+        return Err(Error::invalid_input(format_args!(
+            "the library has no code signature, which iOS requires, and {tool} failed to sign \
+             it: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+/// Frida's errors for what iOS refused over entitlements or code signing,
+/// with why and what to do about it.
+#[cfg(feature = "frida")]
+pub(crate) fn explain(err: Error) -> Error {
+    let message = err.to_string();
+    let has = |needle: &str| message.contains(needle);
+    let hint = if has("Need Gadget") || has("jailed") {
+        "the device is not jailbroken, so only apps signed with the get-task-allow \
+         entitlement (development builds) can be instrumented, through Frida Gadget"
+    } else if has("get-task-allow") || has("task_for_pid") || has("Unable to access process") {
+        "iOS withheld the target's task port: the app lacks the get-task-allow entitlement, \
+         or frida-server lacks task_for_pid-allow"
+    } else if has("code signature") || has("not valid for use in process") || has("cdhash") {
+        "iOS refused the dylib's code signature; the device must trust it (ad-hoc signatures \
+         load on jailbroken devices only)"
+    } else if has("denied by service delegate") || has("Locked") {
+        "SpringBoard refused to launch the app; unlock the device"
+    } else {
+        return err;
+    };
+    Error::permission_denied(format_args!("{message} ({hint})")).with_source(err)
+}
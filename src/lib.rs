@@ -1,7 +1,9 @@
 //! Cross-platform process injection API.
 //!
 //! This crate provides a minimal, ergonomic API and delegates platform-specific
-//! injection to a runtime engine (frida-core via FFI by default).
+//! injection to a runtime engine (frida-core via FFI by default). Without the
+//! default `frida` feature only the native backends are built, and the
+//! frida-core devkit is not downloaded.
 //!
 //! # Quickstart
 //! ```no_run
@@ -41,22 +43,97 @@
 //! ```
 //!
 
+use std::ffi::CStr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod android;
+mod arch;
 mod backend;
+mod bundle;
+mod clock;
+mod compat;
+#[cfg(feature = "compression")]
+mod compress;
+mod config;
+mod descendant;
+mod device;
+#[cfg(feature = "download")]
+mod download;
 mod error;
+mod events;
+mod features;
+mod gate;
+mod image;
+mod injector;
+mod ios;
 mod library;
+mod maps;
+mod notify;
+mod options;
+mod output;
+mod plan;
 mod process;
 mod program;
+mod registry;
+mod set;
+mod shm;
+#[cfg(feature = "signatures")]
+mod signature;
+mod spawn;
+mod staging;
+mod status;
+mod stock;
+mod uprobe;
+mod version;
+mod watchdog;
+#[cfg(feature = "webhook")]
+mod webhook;
 
-pub use error::{Error, Result};
-pub use library::Library;
-pub use process::Process;
-pub use program::{Child, Program, Stdio};
+pub use android::{AdbDevice, adb_devices};
+pub use arch::{Architecture, BinaryFormat};
+pub use clock::{Clock, ManualClock, SystemClock};
+#[cfg(feature = "compression")]
+pub use compress::{Compression, MAX_DECOMPRESSED_SIZE};
+pub use config::{ConfigWatcher, WatchedConfig};
+pub use descendant::{ChildOrigin, Descendant};
+pub use device::{Device, DeviceKind, devices};
+pub use error::{Error, ErrorKind, Result};
+pub use events::{AgentEvent, AgentEvents};
+pub use features::{Capability, CapabilityStatus, FeatureReport, feature_report};
+pub use gate::SpawnGate;
+pub use hook_inject_build::{BuildError, BuildOptions};
+pub use injector::{Backend, Injector, InjectorBuilder};
+pub use library::{IntoCString, JvmAttach, Library, ManagedAssembly, UnloadPolicy};
+pub use maps::{MemoryRegion, Protection};
+pub use notify::{InjectionEvent, InjectionEventKind, Notifier};
+pub use options::{AlreadyLoaded, InjectOptions, InjectionMethod, Realm};
+pub use output::{OutputChunk, OutputStream, TargetOutput};
+pub use plan::{InjectionPlan, PlanError, PlanReport, TargetReport};
+pub use process::{Process, ProcessMatcher};
+pub use program::{Child, ChildStdin, Program, Stdio, SuspendPoint};
+pub use registry::{Injection, InjectionId, InjectionRecord, injections};
+pub use set::{InjectedSet, InjectionBatch, UninjectError};
+pub use shm::SharedMemory;
+pub use status::{
+    CONFIG_ACK_SYMBOL, ENTRY_STATUS_PENDING, ENTRY_STATUS_SYMBOL, EVENT_BUFFER_CAPACITY,
+    EVENTS_SYMBOL, EntryStatus, HEARTBEAT_SYMBOL, Health, OUTPUT_SYMBOL, PANIC_REPORT_CAPACITY,
+    PANIC_REPORT_SYMBOL,
+};
+pub use stock::StockAgent;
+pub use uprobe::{ProbeHit, ProbeId, UprobeTracer};
+pub use version::{RuntimeVersion, runtime_version};
+pub use watchdog::{Watchdog, WatchedInjection};
+#[cfg(feature = "webhook")]
+pub use webhook::WebhookNotifier;
 
 /// Inject a library into a program launched under injector control.
 ///
 /// This spawns the process suspended, injects the library, and then resumes it.
-/// Stdout/stderr pipes are not exposed on this path; if you need to capture
-/// output, spawn with `Program::into_command()` and then inject by pid.
+/// With `Stdio::Pipe`, stdin is writable via `Child::take_stdin`. Stdout/stderr
+/// pipes are not exposed on this path; if you need to capture output, use
+/// `inject_command`.
 ///
 /// # Examples
 /// ```no_run
@@ -74,7 +151,56 @@ pub fn inject_program(
     spec: impl Into<Program>,
     library: impl Into<Library>,
 ) -> Result<InjectedProgram> {
-    backend::default_backend()?.inject_program(spec.into(), library.into())
+    inject_program_with(spec, library, InjectOptions::default())
+}
+
+/// Inject a library into a launched program with per-call options.
+///
+/// # Examples
+/// ```no_run
+/// use hook_inject::{inject_program_with, InjectOptions, Library, Program};
+///
+/// let library = Library::from_path("/path/to/libagent.so")?;
+/// let options = InjectOptions::new().staging_dir("/tmp/hook-inject-client-a");
+/// let injected = inject_program_with(Program::new("/usr/bin/true"), library, options)?;
+/// injected.uninject()?;
+/// # Ok::<(), hook_inject::Error>(())
+/// ```
+pub fn inject_program_with(
+    spec: impl Into<Program>,
+    library: impl Into<Library>,
+    options: InjectOptions,
+) -> Result<InjectedProgram> {
+    Injector::lazy().inject_program_with(spec, library, options)
+}
+
+/// Launch a `Command` natively, inject before it runs, then resume it.
+///
+/// Unlike `inject_program`, the program is spawned by `std::process::Command`
+/// itself, so every setting is honored, including per-stream stdio and files;
+/// piped output is available through `Child::take_stdout`/`take_stderr`. The
+/// program is held before `main` on Linux (x86_64/aarch64), right after
+/// `exec` on macOS, and with its main thread suspended on Windows, where any
+/// `creation_flags` set on the `Command` are replaced.
+///
+/// # Examples
+/// ```no_run
+/// use std::io::Read;
+/// use std::process::{Command, Stdio};
+/// use hook_inject::{inject_command, Library};
+///
+/// let mut cmd = Command::new("/usr/bin/env");
+/// cmd.stdout(Stdio::piped());
+/// let mut injected = inject_command(cmd, Library::from_path("/path/to/libagent.so")?)?;
+/// let mut output = String::new();
+/// injected.child_mut().take_stdout().unwrap().read_to_string(&mut output)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn inject_command(
+    cmd: std::process::Command,
+    library: impl Into<Library>,
+) -> Result<InjectedProgram> {
+    Injector::lazy().inject_command(cmd, library)
 }
 
 /// Inject a library into an already-running process.
@@ -90,7 +216,117 @@ pub fn inject_program(
 /// # Ok::<(), hook_inject::Error>(())
 /// ```
 pub fn inject_process(process: Process, library: impl Into<Library>) -> Result<InjectedProcess> {
-    backend::default_backend()?.inject_process(process, library.into())
+    inject_process_with(process, library, InjectOptions::default())
+}
+
+/// Inject a library into an already-running process with per-call options.
+///
+/// # Examples
+/// ```no_run
+/// use hook_inject::{inject_process_with, InjectOptions, Library, Process};
+///
+/// let process = Process::from_pid(1234)?;
+/// let library = Library::from_path("/path/to/libagent.so")?;
+/// let options = InjectOptions::new().staging_dir("/tmp/hook-inject-client-a");
+/// let injected = inject_process_with(process, library, options)?;
+/// injected.uninject()?;
+/// # Ok::<(), hook_inject::Error>(())
+/// ```
+pub fn inject_process_with(
+    process: Process,
+    library: impl Into<Library>,
+    options: InjectOptions,
+) -> Result<InjectedProcess> {
+    Injector::lazy().inject_process_with(process, library, options)
+}
+
+/// Inject a library into each of `processes`, returning one result per
+/// target, in order.
+///
+/// The runtime is set up once for the whole batch, and a failure for one
+/// target does not stop the others.
+///
+/// # Examples
+/// ```no_run
+/// use hook_inject::{inject_processes, Library, Process};
+///
+/// let workers = [Process::from_pid(1234)?, Process::from_pid(1235)?];
+/// let library = Library::from_path("/path/to/libagent.so")?;
+/// for (process, result) in inject_processes(&workers, &library) {
+///     match result {
+///         Ok(injected) => println!("pid {}: injection {}", process.pid(), injected.id()),
+///         Err(err) => eprintln!("pid {}: {err}", process.pid()),
+///     }
+/// }
+/// # Ok::<(), hook_inject::Error>(())
+/// ```
+pub fn inject_processes(
+    processes: &[Process],
+    library: &Library,
+) -> Vec<(Process, Result<InjectedProcess>)> {
+    inject_processes_with(processes, library, InjectOptions::default())
+}
+
+/// Inject a library into each of `processes` with per-call options.
+///
+/// Targets are injected `InjectOptions::concurrency` at a time; results
+/// keep the order of `processes` either way.
+pub fn inject_processes_with(
+    processes: &[Process],
+    library: &Library,
+    options: InjectOptions,
+) -> Vec<(Process, Result<InjectedProcess>)> {
+    Injector::lazy().inject_processes_with(processes, library, options)
+}
+
+/// Inject a library into every running process matching `matcher`.
+///
+/// Strings match the executable name (see `ProcessMatcher`). Matching
+/// processes are injected like `inject_processes`; those that fail are
+/// reported in `InjectionBatch::failures` instead of failing the call. Fails
+/// with `ErrorKind::ProcessNotFound` if nothing matches.
+///
+/// # Examples
+/// ```no_run
+/// use hook_inject::{inject_by_name, Library};
+///
+/// let batch = inject_by_name("nginx", Library::from_path("/path/to/libagent.so")?)?;
+/// for (process, err) in batch.failures() {
+///     eprintln!("pid {}: {err}", process.pid());
+/// }
+/// batch.into_injected().uninject()?;
+/// # Ok::<(), hook_inject::Error>(())
+/// ```
+pub fn inject_by_name(
+    matcher: impl Into<ProcessMatcher>,
+    library: impl Into<Library>,
+) -> Result<InjectionBatch> {
+    Injector::lazy().inject_by_name(matcher, library)
+}
+
+/// Inject several libraries into a running process, in order.
+///
+/// If one fails, the libraries injected before it are uninjected in reverse
+/// order and its error is returned, so the target is left as it was. The
+/// returned set uninjects them in reverse order too (see `InjectedSet`).
+///
+/// # Examples
+/// ```no_run
+/// use hook_inject::{inject_process_all, Library, Process};
+///
+/// let process = Process::from_pid(1234)?;
+/// let set = inject_process_all(
+///     process,
+///     &[
+///         Library::from_path("/path/to/libtracer.so")?,
+///         Library::from_path("/path/to/libprofiler.so")?,
+///     ],
+/// )?;
+/// set.uninject()?;
+/// # Ok::<(), hook_inject::Error>(())
+/// ```
+pub fn inject_process_all(process: Process, libraries: &[Library]) -> Result<InjectedSet> {
+    Injector::lazy().inject_process_all(process, libraries)
 }
 
 /// Spawn a program in a suspended state.
@@ -106,15 +342,134 @@ pub fn inject_process(process: Process, library: impl Into<Library>) -> Result<I
 /// # Ok::<(), hook_inject::Error>(())
 /// ```
 pub fn spawn(spec: impl Into<Program>) -> Result<SuspendedProgram> {
-    backend::default_backend()?.spawn(spec.into())
+    spawn_with(spec, InjectOptions::default())
+}
+
+/// Spawn a program in a suspended state with per-call options.
+///
+/// `InjectOptions::timeout` bounds the spawn itself and the later `resume` or
+/// `inject` on the returned handle.
+///
+/// # Examples
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use hook_inject::{spawn_with, InjectOptions, Program};
+///
+/// let options = InjectOptions::new().timeout(Duration::from_secs(5));
+/// let suspended = spawn_with(Program::new("/usr/bin/true"), options)?;
+/// let _child = suspended.resume()?;
+/// # Ok::<(), hook_inject::Error>(())
+/// ```
+pub fn spawn_with(spec: impl Into<Program>, options: InjectOptions) -> Result<SuspendedProgram> {
+    Injector::lazy().spawn_with(spec, options)
+}
+
+/// Set up the default runtime (see `Backend::from_env`) now instead of on
+/// the first injection.
+///
+/// Initializing Frida takes a noticeable moment; call this at startup to
+/// keep it out of the first injection's latency. Calling it again is cheap.
+/// Fails with `ErrorKind::RuntimeUnavailable` if the runtime cannot be set
+/// up (the next call tries again), or after `shutdown`.
+///
+/// # Examples
+/// ```no_run
+/// hook_inject::init()?;
+/// // ... inject as usual; at exit:
+/// hook_inject::shutdown()?;
+/// # Ok::<(), hook_inject::Error>(())
+/// ```
+pub fn init() -> Result<()> {
+    Injector::new().map(drop)
+}
+
+/// Load frida-core from the shared library at `path` (requires the
+/// `dynamic-frida` feature).
+///
+/// With `dynamic-frida`, frida-core isn't linked into the binary; it is
+/// loaded when the runtime is first set up, from `HOOK_INJECT_FRIDA_CORE`
+/// or else the system's library search path (`libfrida-core.so`,
+/// `libfrida-core.dylib` or `frida-core.dll`). Call this first to load it
+/// from somewhere else. Fails with `ErrorKind::RuntimeUnavailable` if the
+/// library cannot be loaded or lacks a function the crate needs, and with
+/// `ErrorKind::InvalidInput` if frida-core was already loaded from another
+/// path.
+///
+/// # Examples
+/// ```no_run
+/// hook_inject::load_frida_core("/opt/frida/17.7.3/libfrida-core.so")?;
+/// hook_inject::init()?;
+/// # Ok::<(), hook_inject::Error>(())
+/// ```
+#[cfg(feature = "dynamic-frida")]
+pub fn load_frida_core(path: impl AsRef<std::path::Path>) -> Result<()> {
+    backend::load_frida_core(path.as_ref())
+}
+
+/// Tear down the runtime, joining the threads it and this crate started.
+///
+/// Call it before exiting once every `Injector`, injection handle,
+/// `SpawnGate` and watcher has been dropped; while any is alive it fails
+/// with `ErrorKind::InvalidInput` and leaves the runtime running. Injected
+/// libraries stay loaded. Does nothing if the runtime was never set up.
+/// Frida cannot be set up twice in a process, so injecting afterwards
+/// fails with `ErrorKind::RuntimeUnavailable`.
+pub fn shutdown() -> Result<()> {
+    backend::shutdown()
+}
+
+/// Remove artifacts left in the staging directory by crashed injector runs.
+///
+/// Artifacts are matched by their `hook-inject.<injector pid>.<target pid>.*`
+/// name and removed once either process is gone. The default staging directory
+/// (`HOOK_INJECT_STAGING_DIR` or the system temp dir) is also swept
+/// automatically when the backend initializes. Returns the number of artifacts
+/// removed.
+///
+/// # Examples
+/// ```no_run
+/// let removed = hook_inject::clean_stale_artifacts()?;
+/// println!("removed {removed} stale artifacts");
+/// # Ok::<(), hook_inject::Error>(())
+/// ```
+pub fn clean_stale_artifacts() -> Result<usize> {
+    staging::sweep(&staging::staging_dir(&InjectOptions::default()))
+}
+
+/// Remove stale artifacts from a specific staging directory.
+///
+/// Use this for directories passed to `InjectOptions::staging_dir`.
+pub fn clean_stale_artifacts_in(dir: impl AsRef<std::path::Path>) -> Result<usize> {
+    staging::sweep(dir.as_ref())
+}
+
+/// What happens to a suspended program whose handle is dropped without being
+/// injected, resumed, or killed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// Kill the program so it never runs (the default).
+    #[default]
+    Kill,
+    /// Resume the program uninjected.
+    Resume,
+    /// Leave the program suspended; the caller takes responsibility for it.
+    Detach,
 }
 
 /// Handle to a suspended program spawned by the injector.
+///
+/// Dropping the handle applies its `DropPolicy`, so an early return or panic
+/// does not leave a frozen process behind.
 #[derive(Debug)]
 pub struct SuspendedProgram {
     backend: backend::BackendHandle,
     process: Process,
     stdio: Stdio,
+    // Present when the program was spawned natively and is held by SIGSTOP.
+    native: Option<std::process::Child>,
+    drop_policy: DropPolicy,
+    timeout: Option<Duration>,
 }
 
 impl SuspendedProgram {
@@ -123,50 +478,403 @@ impl SuspendedProgram {
             backend,
             process,
             stdio,
+            native: None,
+            drop_policy: DropPolicy::default(),
+            timeout: None,
         }
     }
 
+    pub(crate) fn with_native(mut self, native: std::process::Child) -> Self {
+        self.native = Some(native);
+        self
+    }
+
+    pub(crate) fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Choose what happens if this handle is dropped while still suspended.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{spawn, DropPolicy, Program};
+    ///
+    /// let suspended = spawn(Program::new("/usr/bin/true"))?.drop_policy(DropPolicy::Resume);
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn drop_policy(mut self, policy: DropPolicy) -> Self {
+        self.drop_policy = policy;
+        self
+    }
+
     /// Return the target process handle.
     pub fn process(&self) -> Process {
         self.process
     }
 
     /// Inject a library and resume the suspended program.
+    ///
+    /// If injection fails, the handle is dropped and its `DropPolicy` applies.
     pub fn inject(self, library: Library) -> Result<InjectedProgram> {
-        let injected = self.backend.inject_process(self.process, library)?;
-        if let Err(err) = self.backend.resume(self.process) {
+        self.try_inject(library).map_err(InjectFailure::into_error)
+    }
+
+    /// Inject a library and resume the suspended program, handing the
+    /// program back if that fails.
+    ///
+    /// On failure nothing is left injected and the program is still
+    /// suspended, so it can be retried, resumed or killed.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{spawn, Library, Program};
+    ///
+    /// let suspended = spawn(Program::new("/usr/bin/true"))?;
+    /// let library = Library::from_path("/path/to/libagent.so")?;
+    /// match suspended.try_inject(library) {
+    ///     Ok(injected) => println!("injected pid {}", injected.process().pid()),
+    ///     Err(failure) => {
+    ///         eprintln!("injection failed: {}", failure.error());
+    ///         failure.into_program().resume()?;
+    ///     }
+    /// }
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn try_inject(
+        self,
+        library: Library,
+    ) -> std::result::Result<InjectedProgram, InjectFailure> {
+        let options = InjectOptions::default();
+        let library = match library.with_options(&options) {
+            Ok(library) => library,
+            Err(error) => return Err(InjectFailure::new(error, self)),
+        };
+        self.try_inject_with(library, &options)
+    }
+
+    pub(crate) fn inject_with(
+        self,
+        library: Library,
+        options: &InjectOptions,
+    ) -> Result<InjectedProgram> {
+        self.try_inject_with(library, options)
+            .map_err(InjectFailure::into_error)
+    }
+
+    fn try_inject_with(
+        self,
+        library: Library,
+        options: &InjectOptions,
+    ) -> std::result::Result<InjectedProgram, InjectFailure> {
+        let injected = match self.backend.inject_process(self.process, library, options) {
+            Ok(injected) => injected,
+            Err(error) => return Err(InjectFailure::new(error, self)),
+        };
+        if let Err(error) = self.resume_process() {
+            // Roll back so the program is handed back as it was.
             let _ = injected.uninject();
-            return Err(err);
+            return Err(InjectFailure::new(error, self));
         }
 
-        let child = Child::new(self.process, self.stdio);
-        Ok(injected.into_program(child))
+        Ok(injected.into_program(self.into_child()))
     }
 
     /// Resume the suspended program without injection.
     ///
     /// Returns an opaque handle to the spawned program.
     pub fn resume(self) -> Result<Child> {
-        self.backend.resume(self.process)?;
-        Ok(Child::new(self.process, self.stdio))
+        self.resume_process()?;
+        Ok(self.into_child())
+    }
+
+    /// Kill the suspended program without ever letting it run.
+    ///
+    /// Use this to abandon a spawn, e.g. when validation fails before
+    /// injection.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{spawn, Program};
+    ///
+    /// let suspended = spawn(Program::new("/usr/bin/true"))?;
+    /// suspended.kill()?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn kill(mut self) -> Result<()> {
+        self.drop_policy = DropPolicy::Detach;
+        self.kill_process()
+    }
+
+    fn into_child(mut self) -> Child {
+        self.drop_policy = DropPolicy::Detach;
+        Child::new(self.backend.clone(), self.process, self.stdio).with_native(self.native.take())
+    }
+
+    fn resume_process(&self) -> Result<()> {
+        if self.native.is_some() {
+            spawn::release(self.process)
+        } else {
+            self.backend.resume(self.process, self.timeout)
+        }
+    }
+
+    fn kill_process(&mut self) -> Result<()> {
+        match self.native.as_mut() {
+            Some(native) => {
+                native.kill()?;
+                // Reap it so no zombie is left behind.
+                native.wait()?;
+                Ok(())
+            }
+            None => self.backend.kill(self.process),
+        }
+    }
+}
+
+/// A failed `SuspendedProgram::try_inject`, holding the program that is
+/// still suspended.
+///
+/// Dropping it applies the program's `DropPolicy`.
+#[derive(Debug)]
+pub struct InjectFailure {
+    error: Error,
+    program: SuspendedProgram,
+}
+
+impl InjectFailure {
+    fn new(error: Error, program: SuspendedProgram) -> Self {
+        Self { error, program }
+    }
+
+    /// Why injection failed.
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+
+    /// Take back the suspended program.
+    pub fn into_program(self) -> SuspendedProgram {
+        self.program
+    }
+
+    /// Discard the program, applying its `DropPolicy`, and return the error.
+    pub fn into_error(self) -> Error {
+        self.error
+    }
+}
+
+impl std::fmt::Display for InjectFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl std::error::Error for InjectFailure {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<InjectFailure> for Error {
+    fn from(failure: InjectFailure) -> Self {
+        failure.into_error()
+    }
+}
+
+impl Drop for SuspendedProgram {
+    fn drop(&mut self) {
+        let _ = match self.drop_policy {
+            DropPolicy::Kill => self.kill_process(),
+            DropPolicy::Resume => self.resume_process(),
+            DropPolicy::Detach => Ok(()),
+        };
     }
 }
 
 /// Handle to an injected library in a running process.
+///
+/// Dropping the handle leaves the library injected, unless
+/// `uninject_on_drop` is set; `detach` says so explicitly.
 #[derive(Debug)]
 pub struct InjectedProcess {
     backend: backend::BackendHandle,
-    id: u64,
+    id: InjectionId,
     process: Process,
+    library: Library,
+    notifier: Option<Arc<dyn Notifier>>,
+    label: Option<String>,
+    method: Option<InjectionMethod>,
+    staging_dir: Option<PathBuf>,
+    clock: Option<Arc<dyn Clock>>,
+    uninject_on_drop: bool,
+    // Tells the target apart from a later process reusing its pid.
+    start_time: Option<u64>,
 }
 
 impl InjectedProcess {
-    pub(crate) fn new(backend: backend::BackendHandle, id: u64, process: Process) -> Self {
+    pub(crate) fn new(
+        backend: backend::BackendHandle,
+        id: InjectionId,
+        process: Process,
+        library: Library,
+    ) -> Self {
         Self {
             backend,
             id,
             process,
+            library,
+            notifier: None,
+            label: None,
+            method: None,
+            staging_dir: None,
+            clock: None,
+            uninject_on_drop: false,
+            start_time: process::start_time(process.pid()),
+        }
+    }
+
+    pub(crate) fn with_notifier(mut self, notifier: Option<Arc<dyn Notifier>>) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
+    pub(crate) fn with_label(mut self, label: Option<String>) -> Self {
+        self.label = label;
+        self
+    }
+
+    pub(crate) fn with_method(mut self, method: Option<InjectionMethod>) -> Self {
+        self.method = method;
+        self
+    }
+
+    pub(crate) fn with_staging_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.staging_dir = dir;
+        self
+    }
+
+    pub(crate) fn with_clock(mut self, clock: Option<Arc<dyn Clock>>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The clock waits on the target go by, from `InjectOptions::clock`.
+    fn clock(&self) -> &dyn Clock {
+        self.clock.as_deref().unwrap_or(&SystemClock)
+    }
+
+    /// Options for what this injection loads or stages later: the method,
+    /// staging directory and clock it was injected with.
+    fn later_options(&self) -> InjectOptions {
+        let mut options = InjectOptions::default().with_clock(self.clock.clone());
+        if let Some(method) = self.method {
+            options = options.injection_method(method);
+        }
+        if let Some(dir) = &self.staging_dir {
+            options = options.staging_dir(dir);
         }
+        options
+    }
+
+    /// Another handle to the same injection, for background threads.
+    /// Dropping it leaves the library injected.
+    pub(crate) fn share(&self) -> Self {
+        Self {
+            backend: self.backend.clone(),
+            id: self.id,
+            process: self.process,
+            library: self.library.clone(),
+            notifier: self.notifier.clone(),
+            label: self.label.clone(),
+            method: self.method,
+            staging_dir: self.staging_dir.clone(),
+            clock: self.clock.clone(),
+            uninject_on_drop: false,
+            start_time: self.start_time,
+        }
+    }
+
+    /// Uninject the library when the handle is dropped (default false).
+    ///
+    /// This covers early returns and panics; errors from the implicit
+    /// uninject are ignored. Call `uninject` to observe them.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{inject_process, Library, Process};
+    ///
+    /// let process = Process::from_pid(1234)?;
+    /// let _injected = inject_process(process, Library::from_path("/path/to/libagent.so")?)?
+    ///     .uninject_on_drop(true);
+    /// // ... any `?` from here on still uninjects the agent.
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn uninject_on_drop(mut self, enabled: bool) -> Self {
+        self.uninject_on_drop = enabled;
+        self
+    }
+
+    /// Return the id of this injection, as listed by `injections`.
+    pub fn id(&self) -> InjectionId {
+        self.id
+    }
+
+    /// Return the label given with `InjectOptions::label`, if any.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Return the method the library was loaded with, on backends that offer
+    /// a choice (see `InjectOptions::injection_method`).
+    pub fn injection_method(&self) -> Option<InjectionMethod> {
+        self.method
+    }
+
+    /// Record this injection so another process can `reattach` to it.
+    ///
+    /// Fails with `ErrorKind::NotSupported` for libraries injected from
+    /// memory, which cannot be found again by path.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{inject_process, Library, Process};
+    ///
+    /// let process = Process::from_pid(1234)?;
+    /// let injected = inject_process(process, Library::from_path("/path/to/libagent.so")?)?;
+    /// let record = injected.record()?;
+    /// // Persist `record` (it implements `serde::Serialize` with the `serde`
+    /// // feature), exit, and reattach from a later invocation.
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn record(&self) -> Result<InjectionRecord> {
+        InjectionRecord::new(self.id, self.process, &self.library, self.label.as_deref())
+    }
+
+    /// Rebuild a handle from a record made by another process.
+    ///
+    /// Fails with `ErrorKind::ProcessNotFound` if the target exited, even if
+    /// its pid has been reused since (checked on Linux). The runtime that
+    /// monitored the injection went away with the process that made it, so
+    /// the reattached handle's `uninject` only unlists it and `on_unloaded`
+    /// is never called; `eject`, `configure`, `is_loaded` and `entry_status`
+    /// work as before.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # fn load() -> hook_inject::InjectionRecord { unimplemented!() }
+    /// use hook_inject::InjectedProcess;
+    ///
+    /// let record = load();
+    /// InjectedProcess::reattach(&record)?.eject()?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn reattach(record: &InjectionRecord) -> Result<InjectedProcess> {
+        let process = record.process()?;
+        let library = record.library()?;
+        let backend = backend::default_backend()?;
+        registry::insert_reattached(record.id(), process, &library, record.label())?;
+        Ok(InjectedProcess::new(backend, record.id(), process, library)
+            .with_label(record.label().map(str::to_string)))
     }
 
     /// Return the target process handle.
@@ -174,40 +882,595 @@ impl InjectedProcess {
         self.process
     }
 
+    /// Read the status reported by the agent's entrypoint.
+    ///
+    /// Agents opt in by exporting `ENTRY_STATUS_SYMBOL`; this tells an agent
+    /// that refused to initialize apart from one that is running. The
+    /// entrypoint may still be running when injection returns, so poll until
+    /// the status is no longer `EntryStatus::Pending`.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{inject_process, EntryStatus, Library, Process};
+    ///
+    /// let process = Process::from_pid(1234)?;
+    /// let injected = inject_process(process, Library::from_path("/path/to/libagent.so")?)?;
+    /// if let EntryStatus::Failed(code) = injected.entry_status()? {
+    ///     eprintln!("agent refused to initialize: {code}");
+    /// }
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn entry_status(&self) -> Result<EntryStatus> {
+        status::entry_status(self.process, &self.library)
+    }
+
+    /// Read the panic the agent recorded, if any.
+    ///
+    /// Agents opt in by exporting `PANIC_REPORT_SYMBOL`, which
+    /// `hook_inject_agent::install_panic_hook` fills in with the message,
+    /// location and backtrace of the first panic. `configure` fails with
+    /// `ErrorKind::AgentPanicked` instead of waiting for an entrypoint that
+    /// panicked.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{inject_process, Library, Process};
+    ///
+    /// let process = Process::from_pid(1234)?;
+    /// let injected = inject_process(process, Library::from_path("/path/to/libagent.so")?)?;
+    /// if let Some(report) = injected.panic_report()? {
+    ///     eprintln!("agent panicked: {report}");
+    /// }
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn panic_report(&self) -> Result<Option<String>> {
+        status::panic_report(self.process, &self.library)
+    }
+
+    /// Block until the agent reports it is ready, failing after `timeout`.
+    ///
+    /// Succeeds once the entrypoint stores `0` in `ENTRY_STATUS_SYMBOL`.
+    /// Fails with `ErrorKind::Runtime` if it stores a failure code,
+    /// `ErrorKind::AgentPanicked` if the agent panics first,
+    /// `ErrorKind::NotSupported` if the library does not export the symbol,
+    /// and `ErrorKind::TimedOut` otherwise. `InjectOptions::wait_ready` does
+    /// this as part of injection.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use hook_inject::{inject_process, Library, Process};
+    ///
+    /// let process = Process::from_pid(1234)?;
+    /// let injected = inject_process(process, Library::from_path("/path/to/libagent.so")?)?;
+    /// injected.wait_ready(Duration::from_secs(5))?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn wait_ready(&self, timeout: Duration) -> Result<()> {
+        status::wait_ready(self.process, &self.library, timeout, self.clock())
+    }
+
+    /// Report when the agent last sent a heartbeat.
+    ///
+    /// Agents opt in by exporting `HEARTBEAT_SYMBOL` and updating it as they
+    /// make progress (see `hook_inject_agent::Heartbeat`). An agent that
+    /// stops beating while still loaded has likely wedged.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use hook_inject::{inject_process, Library, Process};
+    ///
+    /// let process = Process::from_pid(1234)?;
+    /// let injected = inject_process(process, Library::from_path("/path/to/libagent.so")?)?;
+    /// if !injected.health()?.is_alive(Duration::from_secs(30)) {
+    ///     eprintln!("agent has not beaten for 30s");
+    /// }
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn health(&self) -> Result<Health> {
+        status::health(self.process, &self.library)
+    }
+
+    /// Check whether the library is mapped in the target.
+    ///
+    /// Injection succeeds once the entrypoint has been called, so an agent
+    /// whose constructor or entrypoint crashed and was unloaded still looks
+    /// injected; this looks the library up in the target's memory map (by
+    /// path, or by image header for libraries injected from memory). An
+    /// agent that does not stay resident (see `UnloadPolicy`) is unloaded
+    /// when its entrypoint returns, and then reports `false` too.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{inject_process, Library, Process};
+    ///
+    /// let process = Process::from_pid(1234)?;
+    /// let injected = inject_process(process, Library::from_path("/path/to/libagent.so")?)?;
+    /// if !injected.is_loaded()? {
+    ///     eprintln!("agent unloaded itself");
+    /// }
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn is_loaded(&self) -> Result<bool> {
+        status::is_loaded(self.process, &self.library)
+    }
+
+    /// Call `callback` once the target process exits.
+    ///
+    /// Unlike `on_unloaded`, which also covers an agent unloading while the
+    /// target keeps running, this only fires when the process itself is
+    /// gone. The callback runs on a background thread that checks on the
+    /// target periodically; a later process reusing its pid does not count
+    /// as the target (checked on Linux).
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{inject_process, Library, Process};
+    ///
+    /// let process = Process::from_pid(1234)?;
+    /// let injected = inject_process(process, Library::from_path("/path/to/libagent.so")?)?;
+    /// injected.on_process_exit(|| eprintln!("target exited"))?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn on_process_exit(&self, callback: impl FnOnce() + Send + 'static) -> Result<()> {
+        self.process.check_local()?;
+        process::on_exit(self.process.pid(), self.start_time, callback)
+    }
+
+    /// Block until the target process exits.
+    ///
+    /// Fails with `ErrorKind::TimedOut` if it is still running after
+    /// `timeout`. See `on_process_exit`.
+    pub fn wait_target_exit(&self, timeout: Duration) -> Result<()> {
+        self.process.check_local()?;
+        if process::wait_exit(
+            self.process.pid(),
+            self.start_time,
+            Some(timeout),
+            self.clock(),
+        ) {
+            return Ok(());
+        }
+        Err(Error::timed_out(format_args!(
+            "pid {} did not exit within {timeout:?}",
+            self.process.pid()
+        )))
+    }
+
+    /// Call `callback` once the library is unloaded from the target.
+    ///
+    /// The runtime reports an unload when the agent's entrypoint returns
+    /// without staying resident (see `UnloadPolicy`) and when the target
+    /// exits; `eject` reports one as well. The callback runs on a background
+    /// thread, or right away if the library is already known to be unloaded.
+    /// It is dropped without being called once the library is uninjected,
+    /// since the runtime stops monitoring it.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{inject_process, Library, Process};
+    ///
+    /// let process = Process::from_pid(1234)?;
+    /// let injected = inject_process(process, Library::from_path("/path/to/libagent.so")?)?;
+    /// let (tx, rx) = std::sync::mpsc::channel();
+    /// injected.on_unloaded(move || {
+    ///     let _ = tx.send(());
+    /// });
+    /// rx.recv().unwrap();
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn on_unloaded(&self, callback: impl FnOnce() + Send + 'static) {
+        self.backend.on_unloaded(self.id, callback);
+    }
+
+    /// Block until the library is unloaded from the target.
+    ///
+    /// Waits for the same report as `on_unloaded`, for an agent that unloads
+    /// itself once its work is done. Fails with `ErrorKind::TimedOut` if it
+    /// is still loaded after `timeout`.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use hook_inject::{inject_process, Library, Process};
+    ///
+    /// let process = Process::from_pid(1234)?;
+    /// let injected = inject_process(process, Library::from_path("/path/to/libagent.so")?)?;
+    /// injected.wait_until_unloaded(Duration::from_secs(5))?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn wait_until_unloaded(&self, timeout: Duration) -> Result<()> {
+        self.backend.wait_until_unloaded(self.id, timeout)
+    }
+
+    /// Call the library's configure entrypoint with new data.
+    ///
+    /// The entrypoint (`Library::with_configure_entrypoint`, by default
+    /// `hook_inject_configure`) runs on a new thread in the target, after the
+    /// main entrypoint has reported its status if the agent exports
+    /// `ENTRY_STATUS_SYMBOL`. It can be called any number of times to
+    /// reconfigure a live agent. Libraries injected from memory are not
+    /// supported.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{inject_process, Library, Process};
+    ///
+    /// let process = Process::from_pid(1234)?;
+    /// let injected = inject_process(process, Library::from_path("/path/to/libagent.so")?)?;
+    /// injected.configure(c"{\"level\":\"debug\"}")?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn configure(&self, data: impl AsRef<CStr>) -> Result<()> {
+        self.backend
+            .configure(self.process, &self.library, data.as_ref(), self.clock())
+    }
+
+    /// Push new binary data to the running agent.
+    ///
+    /// The bytes are staged like `Library::with_data_bytes`, and the
+    /// configure entrypoint is called with the staged file's path, so an
+    /// agent reads updates the same way it read its initial data. Use
+    /// `configure` to pass a string directly.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{inject_process, Library, Process};
+    ///
+    /// let process = Process::from_pid(1234)?;
+    /// let library = Library::from_path("/path/to/libagent.so")?.with_data_bytes(b"rate=100".to_vec());
+    /// let injected = inject_process(process, library)?;
+    /// injected.update_data(b"rate=10".to_vec())?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn update_data(&self, data: impl Into<Vec<u8>>) -> Result<()> {
+        self.backend.update_data(
+            self.process,
+            &self.library,
+            data.into(),
+            &self.later_options(),
+        )
+    }
+
+    /// Push new binary data to the running agent and wait until it
+    /// acknowledges it.
+    ///
+    /// The data is delivered like `update_data`; the agent then counts it as
+    /// applied or rejected in `CONFIG_ACK_SYMBOL`
+    /// (`hook_inject_agent::ConfigAck`). Fails with `ErrorKind::Runtime` if
+    /// the agent rejects it, `ErrorKind::NotSupported` if the library does
+    /// not export the symbol, and `ErrorKind::TimedOut` if neither happens
+    /// within `timeout`. Pushes to one agent must not overlap.
+    /// `ConfigWatcher` pushes a config file whenever it changes.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use hook_inject::{inject_process, Library, Process};
+    ///
+    /// let process = Process::from_pid(1234)?;
+    /// let injected = inject_process(process, Library::from_path("/path/to/libagent.so")?)?;
+    /// injected.push_config(b"verbosity=debug".to_vec(), Duration::from_secs(5))?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn push_config(&self, data: impl Into<Vec<u8>>, timeout: Duration) -> Result<()> {
+        status::push_config(self.process, &self.library, timeout, self.clock(), || {
+            self.update_data(data)
+        })
+    }
+
+    /// Stream the events the agent emits through `EVENTS_SYMBOL`
+    /// (`hook_inject_agent::EventStream`).
+    ///
+    /// Fails with `ErrorKind::NotSupported` if the library does not export
+    /// the symbol. See `AgentEvents`.
+    pub fn events(&self) -> Result<AgentEvents> {
+        AgentEvents::new(
+            self.id,
+            self.process,
+            self.library.clone(),
+            self.start_time,
+            EVENTS_SYMBOL,
+            "emit events",
+        )
+    }
+
+    /// Stream what the target writes to stdout and stderr, as forwarded by
+    /// the agent through `OUTPUT_SYMBOL` (`hook_inject_agent::capture_output`).
+    ///
+    /// Unlike `Stdio::Pipe`, this works for processes the injector did not
+    /// start. Fails with `ErrorKind::NotSupported` if the library does not
+    /// export the symbol. See `TargetOutput`.
+    pub fn output(&self) -> Result<TargetOutput> {
+        TargetOutput::new(self.id, self.process, self.library.clone(), self.start_time)
+    }
+
+    /// Call an exported function of the injected library with `data`.
+    ///
+    /// `symbol` must have the entrypoint signature
+    /// (`void (const char *data, int *stay_resident, void *state)`). It runs
+    /// on a new thread in the target, and `call` returns once it has been
+    /// started; this gives the agent a simple command channel. Fails with
+    /// `ErrorKind::InvalidInput` if the library does not export `symbol`.
+    /// Libraries injected from memory are not supported.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{inject_process, Library, Process};
+    ///
+    /// let process = Process::from_pid(1234)?;
+    /// let injected = inject_process(process, Library::from_path("/path/to/libagent.so")?)?;
+    /// injected.call("agent_dump_stats", "/tmp/stats.json")?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn call(&self, symbol: impl IntoCString, data: impl IntoCString) -> Result<()> {
+        let symbol = library::into_cstring(symbol, "entrypoint")?;
+        let data = library::into_cstring(data, "data")?;
+        self.backend
+            .call(self.process, &self.library, &symbol, &data)
+    }
+
     /// Stop monitoring the injected library (Frida: `demonitor`).
-    pub fn uninject(self) -> Result<()> {
-        self.backend.uninject(self.id)
+    ///
+    /// The library stays loaded in the target; use `eject` to unload it. If
+    /// the library has a shutdown entrypoint
+    /// (`Library::with_shutdown_entrypoint`), it is called first and given a
+    /// couple of seconds to return.
+    pub fn uninject(mut self) -> Result<()> {
+        self.uninject_in_place()
+    }
+
+    /// Release the handle, deliberately leaving the agent injected.
+    ///
+    /// Nothing is uninjected, even with `uninject_on_drop` set, and the
+    /// injection is dropped from `injections` and its `on_unloaded`
+    /// callbacks; the agent keeps running after this process exits. Call
+    /// `record` first to find it again later.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{inject_process, Library, Process};
+    ///
+    /// let process = Process::from_pid(1234)?;
+    /// inject_process(process, Library::from_path("/path/to/libagent.so")?)?.detach();
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn detach(mut self) {
+        self.uninject_on_drop = false;
+        self.backend.detach(self.id);
+    }
+
+    /// Unload the library from the target, then stop monitoring it.
+    ///
+    /// The library's unload entrypoint (`Library::with_unload_entrypoint`, by
+    /// default `hook_inject_unload`) is called with empty data and must
+    /// remove the agent's hooks and stop its threads. An agent that keeps
+    /// itself resident (e.g. by staying inside its main entrypoint or holding
+    /// a `dlopen` handle to itself) must also release that before returning.
+    /// `eject` then waits for the image to be unmapped and fails with
+    /// `ErrorKind::Runtime` if it is still loaded after a few seconds.
+    /// Libraries injected from memory are not supported.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{inject_process, Library, Process};
+    ///
+    /// let process = Process::from_pid(1234)?;
+    /// let injected = inject_process(process, Library::from_path("/path/to/libagent.so")?)?;
+    /// injected.eject()?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn eject(mut self) -> Result<()> {
+        self.backend
+            .eject(self.process, self.id, &self.library, self.clock())?;
+        self.uninject_on_drop = false;
+        notify::emit(
+            self.notifier.as_ref(),
+            InjectionEventKind::Ejected,
+            self.process,
+            self.label.as_deref(),
+        );
+        Ok(())
+    }
+
+    /// Replace the injected agent with `library`, keeping this handle.
+    ///
+    /// The current agent is ejected if it is still loaded (see `eject`),
+    /// or just uninjected if it already unloaded itself; `library` is then
+    /// injected with default options and reported to the notifier as
+    /// `InjectionEventKind::Reinjected`. On error the handle may be left
+    /// pointing at an agent that is no longer injected.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{inject_process, Library, Process};
+    ///
+    /// let process = Process::from_pid(1234)?;
+    /// let mut injected = inject_process(process, Library::from_crate("agent")?)?;
+    /// // ... edit the agent ...
+    /// injected.reinject(Library::from_crate("agent")?)?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn reinject(&mut self, library: impl Into<Library>) -> Result<()> {
+        let library = library.into().with_options(&InjectOptions::default())?;
+        self.id = self.backend.reinject(
+            self.process,
+            self.id,
+            &self.library,
+            &library,
+            self.label.as_deref(),
+            &self.later_options(),
+        )?;
+        self.library = library;
+        notify::emit(
+            self.notifier.as_ref(),
+            InjectionEventKind::Reinjected,
+            self.process,
+            self.label.as_deref(),
+        );
+        Ok(())
     }
 
-    pub(crate) fn into_program(self, child: Child) -> InjectedProgram {
-        InjectedProgram::new(self.backend, self.id, self.process, child)
+    pub(crate) fn uninject_in_place(&mut self) -> Result<()> {
+        self.backend.shutdown(self.process, &self.library)?;
+        self.backend.uninject(self.process, self.id)?;
+        self.uninject_on_drop = false;
+        notify::emit(
+            self.notifier.as_ref(),
+            InjectionEventKind::Ejected,
+            self.process,
+            self.label.as_deref(),
+        );
+        Ok(())
+    }
+
+    pub(crate) fn into_program(mut self, child: Child) -> InjectedProgram {
+        let enabled = std::mem::take(&mut self.uninject_on_drop);
+        InjectedProgram::new(
+            self.backend.clone(),
+            self.id,
+            self.process,
+            self.library.clone(),
+            child,
+        )
+        .with_notifier(self.notifier.take())
+        .with_label(self.label.take())
+        .with_method(self.method)
+        .with_staging_dir(self.staging_dir.take())
+        .with_clock(self.clock.take())
+        .uninject_on_drop(enabled)
+    }
+}
+
+impl Drop for InjectedProcess {
+    fn drop(&mut self) {
+        if self.uninject_on_drop {
+            let _ = self.uninject_in_place();
+        }
     }
 }
 
 /// Handle to an injected library in a launched process.
+///
+/// Dropping the handle leaves the library injected, unless
+/// `uninject_on_drop` is set; `detach` says so explicitly.
 #[derive(Debug)]
 pub struct InjectedProgram {
     backend: backend::BackendHandle,
-    id: u64,
+    id: InjectionId,
     process: Process,
+    library: Library,
     child: Child,
+    notifier: Option<Arc<dyn Notifier>>,
+    label: Option<String>,
+    method: Option<InjectionMethod>,
+    staging_dir: Option<PathBuf>,
+    clock: Option<Arc<dyn Clock>>,
+    uninject_on_drop: bool,
+    // Tells the target apart from a later process reusing its pid.
+    start_time: Option<u64>,
 }
 
 impl InjectedProgram {
     pub(crate) fn new(
         backend: backend::BackendHandle,
-        id: u64,
+        id: InjectionId,
         process: Process,
+        library: Library,
         child: Child,
     ) -> Self {
         Self {
             backend,
             id,
             process,
+            library,
             child,
+            notifier: None,
+            label: None,
+            method: None,
+            staging_dir: None,
+            clock: None,
+            uninject_on_drop: false,
+            start_time: process::start_time(process.pid()),
         }
     }
 
+    pub(crate) fn with_notifier(mut self, notifier: Option<Arc<dyn Notifier>>) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
+    pub(crate) fn with_label(mut self, label: Option<String>) -> Self {
+        self.label = label;
+        self
+    }
+
+    pub(crate) fn with_method(mut self, method: Option<InjectionMethod>) -> Self {
+        self.method = method;
+        self
+    }
+
+    pub(crate) fn with_staging_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.staging_dir = dir;
+        self
+    }
+
+    pub(crate) fn with_clock(mut self, clock: Option<Arc<dyn Clock>>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The clock waits on the target go by, from `InjectOptions::clock`.
+    fn clock(&self) -> &dyn Clock {
+        self.clock.as_deref().unwrap_or(&SystemClock)
+    }
+
+    /// Options for what this injection loads or stages later: the method,
+    /// staging directory and clock it was injected with.
+    fn later_options(&self) -> InjectOptions {
+        let mut options = InjectOptions::default().with_clock(self.clock.clone());
+        if let Some(method) = self.method {
+            options = options.injection_method(method);
+        }
+        if let Some(dir) = &self.staging_dir {
+            options = options.staging_dir(dir);
+        }
+        options
+    }
+
+    /// Uninject the library when the handle is dropped (default false).
+    ///
+    /// See `InjectedProcess::uninject_on_drop`.
+    pub fn uninject_on_drop(mut self, enabled: bool) -> Self {
+        self.uninject_on_drop = enabled;
+        self
+    }
+
+    /// Return the id of this injection, as listed by `injections`.
+    pub fn id(&self) -> InjectionId {
+        self.id
+    }
+
+    /// Return the label given with `InjectOptions::label`, if any.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Return the method the library was loaded with, on backends that offer
+    /// a choice (see `InjectOptions::injection_method`).
+    pub fn injection_method(&self) -> Option<InjectionMethod> {
+        self.method
+    }
+
+    /// Record this injection so another process can reattach to it.
+    ///
+    /// See `InjectedProcess::record`.
+    pub fn record(&self) -> Result<InjectionRecord> {
+        InjectionRecord::new(self.id, self.process, &self.library, self.label.as_deref())
+    }
+
     /// Return the target process handle.
     pub fn process(&self) -> Process {
         self.process
@@ -218,8 +1481,221 @@ impl InjectedProgram {
         &self.child
     }
 
+    /// Mutably access the spawned-process handle, e.g. to take its stdin.
+    pub fn child_mut(&mut self) -> &mut Child {
+        &mut self.child
+    }
+
+    /// Read the status reported by the agent's entrypoint.
+    ///
+    /// See `InjectedProcess::entry_status`.
+    pub fn entry_status(&self) -> Result<EntryStatus> {
+        status::entry_status(self.process, &self.library)
+    }
+
+    /// Read the panic the agent recorded, if any.
+    ///
+    /// See `InjectedProcess::panic_report`.
+    pub fn panic_report(&self) -> Result<Option<String>> {
+        status::panic_report(self.process, &self.library)
+    }
+
+    /// Block until the agent reports it is ready, failing after `timeout`.
+    ///
+    /// See `InjectedProcess::wait_ready`.
+    pub fn wait_ready(&self, timeout: Duration) -> Result<()> {
+        status::wait_ready(self.process, &self.library, timeout, self.clock())
+    }
+
+    /// Report when the agent last sent a heartbeat.
+    ///
+    /// See `InjectedProcess::health`.
+    pub fn health(&self) -> Result<Health> {
+        status::health(self.process, &self.library)
+    }
+
+    /// Check whether the library is mapped in the target.
+    ///
+    /// See `InjectedProcess::is_loaded`.
+    pub fn is_loaded(&self) -> Result<bool> {
+        status::is_loaded(self.process, &self.library)
+    }
+
+    /// Call `callback` once the target process exits.
+    ///
+    /// See `InjectedProcess::on_process_exit`.
+    pub fn on_process_exit(&self, callback: impl FnOnce() + Send + 'static) -> Result<()> {
+        self.process.check_local()?;
+        process::on_exit(self.process.pid(), self.start_time, callback)
+    }
+
+    /// Block until the target process exits.
+    ///
+    /// See `InjectedProcess::wait_target_exit`.
+    pub fn wait_target_exit(&self, timeout: Duration) -> Result<()> {
+        self.process.check_local()?;
+        if process::wait_exit(
+            self.process.pid(),
+            self.start_time,
+            Some(timeout),
+            self.clock(),
+        ) {
+            return Ok(());
+        }
+        Err(Error::timed_out(format_args!(
+            "pid {} did not exit within {timeout:?}",
+            self.process.pid()
+        )))
+    }
+
+    /// Call `callback` once the library is unloaded from the target.
+    ///
+    /// See `InjectedProcess::on_unloaded`.
+    pub fn on_unloaded(&self, callback: impl FnOnce() + Send + 'static) {
+        self.backend.on_unloaded(self.id, callback);
+    }
+
+    /// Block until the library is unloaded from the target.
+    ///
+    /// See `InjectedProcess::wait_until_unloaded`.
+    pub fn wait_until_unloaded(&self, timeout: Duration) -> Result<()> {
+        self.backend.wait_until_unloaded(self.id, timeout)
+    }
+
+    /// Call the library's configure entrypoint with new data.
+    ///
+    /// See `InjectedProcess::configure`.
+    pub fn configure(&self, data: impl AsRef<CStr>) -> Result<()> {
+        self.backend
+            .configure(self.process, &self.library, data.as_ref(), self.clock())
+    }
+
+    /// Push new binary data to the running agent.
+    ///
+    /// See `InjectedProcess::update_data`.
+    pub fn update_data(&self, data: impl Into<Vec<u8>>) -> Result<()> {
+        self.backend.update_data(
+            self.process,
+            &self.library,
+            data.into(),
+            &self.later_options(),
+        )
+    }
+
+    /// Push new binary data to the running agent and wait until it
+    /// acknowledges it.
+    ///
+    /// See `InjectedProcess::push_config`.
+    pub fn push_config(&self, data: impl Into<Vec<u8>>, timeout: Duration) -> Result<()> {
+        status::push_config(self.process, &self.library, timeout, self.clock(), || {
+            self.update_data(data)
+        })
+    }
+
+    /// Stream the events the agent emits.
+    ///
+    /// See `InjectedProcess::events`.
+    pub fn events(&self) -> Result<AgentEvents> {
+        AgentEvents::new(
+            self.id,
+            self.process,
+            self.library.clone(),
+            self.start_time,
+            EVENTS_SYMBOL,
+            "emit events",
+        )
+    }
+
+    /// Stream what the target writes to stdout and stderr.
+    ///
+    /// See `InjectedProcess::output`.
+    pub fn output(&self) -> Result<TargetOutput> {
+        TargetOutput::new(self.id, self.process, self.library.clone(), self.start_time)
+    }
+
+    /// Call an exported function of the injected library with `data`.
+    ///
+    /// See `InjectedProcess::call`.
+    pub fn call(&self, symbol: impl IntoCString, data: impl IntoCString) -> Result<()> {
+        let symbol = library::into_cstring(symbol, "entrypoint")?;
+        let data = library::into_cstring(data, "data")?;
+        self.backend
+            .call(self.process, &self.library, &symbol, &data)
+    }
+
     /// Stop monitoring the injected library (Frida: `demonitor`).
-    pub fn uninject(self) -> Result<()> {
-        self.backend.uninject(self.id)
+    ///
+    /// The library stays loaded in the target; use `eject` to unload it.
+    pub fn uninject(mut self) -> Result<()> {
+        self.uninject_in_place()
+    }
+
+    /// Release the handle, deliberately leaving the agent injected.
+    ///
+    /// See `InjectedProcess::detach`. The child process handle is dropped
+    /// too, leaving the program running.
+    pub fn detach(mut self) {
+        self.uninject_on_drop = false;
+        self.backend.detach(self.id);
+    }
+
+    /// Unload the library from the target, then stop monitoring it.
+    ///
+    /// See `InjectedProcess::eject`.
+    pub fn eject(mut self) -> Result<()> {
+        self.backend
+            .eject(self.process, self.id, &self.library, self.clock())?;
+        self.uninject_on_drop = false;
+        notify::emit(
+            self.notifier.as_ref(),
+            InjectionEventKind::Ejected,
+            self.process,
+            self.label.as_deref(),
+        );
+        Ok(())
+    }
+
+    /// Replace the injected agent with `library`, keeping this handle.
+    ///
+    /// See `InjectedProcess::reinject`.
+    pub fn reinject(&mut self, library: impl Into<Library>) -> Result<()> {
+        let library = library.into().with_options(&InjectOptions::default())?;
+        self.id = self.backend.reinject(
+            self.process,
+            self.id,
+            &self.library,
+            &library,
+            self.label.as_deref(),
+            &self.later_options(),
+        )?;
+        self.library = library;
+        notify::emit(
+            self.notifier.as_ref(),
+            InjectionEventKind::Reinjected,
+            self.process,
+            self.label.as_deref(),
+        );
+        Ok(())
+    }
+
+    fn uninject_in_place(&mut self) -> Result<()> {
+        self.backend.shutdown(self.process, &self.library)?;
+        self.backend.uninject(self.process, self.id)?;
+        self.uninject_on_drop = false;
+        notify::emit(
+            self.notifier.as_ref(),
+            InjectionEventKind::Ejected,
+            self.process,
+            self.label.as_deref(),
+        );
+        Ok(())
+    }
+}
+
+impl Drop for InjectedProgram {
+    fn drop(&mut self) {
+        if self.uninject_on_drop {
+            let _ = self.uninject_in_place();
+        }
     }
 }
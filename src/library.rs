@@ -1,17 +1,182 @@
+use std::borrow::Cow;
 use std::ffi::{CStr, CString};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+#[cfg(feature = "compression")]
+use std::sync::OnceLock;
 
+use hook_inject_build::BuildOptions;
+use sha2::{Digest, Sha256};
+
+use crate::arch::{self, ImageKind};
 use crate::{
-    Error, InjectedProcess, InjectedProgram, Process, Program, Result, inject_process,
-    inject_program,
+    Architecture, BinaryFormat, Error, InjectOptions, InjectedProcess, InjectedProgram, Process,
+    Program, Result, StockAgent, bundle, image, inject_process, inject_program,
 };
+#[cfg(feature = "compression")]
+use crate::{Compression, compress};
 
 const DEFAULT_ENTRYPOINT: &str = "frida_agent_main";
+const DEFAULT_CONFIGURE_ENTRYPOINT: &str = "hook_inject_configure";
+const DEFAULT_UNLOAD_ENTRYPOINT: &str = "hook_inject_unload";
+
+/// Whether an agent stays loaded after its main entrypoint returns.
+///
+/// The runtime unloads an agent once its entrypoint returns unless the agent
+/// sets the entrypoint's `stay_resident` flag. A policy other than `Agent`
+/// is passed to the agent as a header before its data string:
+/// `\x01resident\x01` or `\x01unload\x01`. `hook_inject_agent::entry_data`
+/// strips it and sets `stay_resident` to match; agents that do not use it
+/// see the header as part of their data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum UnloadPolicy {
+    /// Leave it to the agent; nothing is added to the data string.
+    #[default]
+    Agent,
+    /// Keep the agent loaded until it is ejected.
+    Resident,
+    /// Unload the agent as soon as its entrypoint returns.
+    Unload,
+}
+
+impl UnloadPolicy {
+    fn header(self) -> Option<&'static str> {
+        match self {
+            UnloadPolicy::Agent => None,
+            UnloadPolicy::Resident => Some("\x01resident\x01"),
+            UnloadPolicy::Unload => Some("\x01unload\x01"),
+        }
+    }
+}
+
+/// Whether a library is loaded into Java targets through the JVM's own
+/// attach mechanism instead of being injected natively
+/// (`Library::with_jvm_attach`).
+///
+/// With the attach API, the JVM loads the library itself and calls its
+/// JVMTI `Agent_OnAttach` with the library's data as the options string;
+/// the main entrypoint and the unload policy are not used, and the agent
+/// stays loaded until the JVM exits. HotSpot JVMs (OpenJDK and the builds
+/// based on it) on Linux and macOS only.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum JvmAttach {
+    /// Always inject natively.
+    #[default]
+    Never,
+    /// Attach to targets running a JVM, and inject natively into others.
+    Auto,
+    /// Attach, failing with `ErrorKind::NotSupported` if the target is not
+    /// running a JVM.
+    Always,
+}
+
+/// A managed assembly to run in a Windows .NET target once the library is
+/// injected (`Library::with_managed_assembly`).
+///
+/// A bootstrapper is injected after the library and hands the assembly to
+/// the runtime: the .NET (Core) runtime already in the target, through
+/// `hostfxr`, or else the .NET Framework CLR, the one loaded or v4 started
+/// for the purpose. The method is called once, on the bootstrapper's
+/// thread, and should start its own threads for long-running work:
+///
+/// - .NET: `public static int Method(IntPtr args, int sizeBytes)`, the
+///   argument being `sizeBytes` of UTF-16 at `args`. The type name is
+///   assembly-qualified (`Namespace.Type, Assembly`), and the assembly needs
+///   its `.runtimeconfig.json` beside it (`EnableDynamicLoading`).
+/// - .NET Framework: `public static int Method(string argument)`, with a
+///   plain type name.
+///
+/// Injection fails with `ErrorKind::Runtime` if the assembly cannot be run
+/// or the method returns anything but `0`.
+///
+/// # Examples
+/// ```no_run
+/// # use hook_inject::ManagedAssembly;
+/// let assembly = ManagedAssembly::new(r"C:\agents\Tracer.dll", "Tracer.Entry, Tracer", "Start")
+///     .with_argument("verbose");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ManagedAssembly {
+    path: PathBuf,
+    type_name: String,
+    method: String,
+    argument: String,
+}
+
+impl ManagedAssembly {
+    /// Run the static `method` of `type_name` in the assembly at `path`.
+    pub fn new(
+        path: impl Into<PathBuf>,
+        type_name: impl Into<String>,
+        method: impl Into<String>,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            type_name: type_name.into(),
+            method: method.into(),
+            argument: String::new(),
+        }
+    }
+
+    /// Set the string passed to the method (default empty).
+    pub fn with_argument(mut self, argument: impl Into<String>) -> Self {
+        self.argument = argument.into();
+        self
+    }
+
+    /// Return the assembly's path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Return the name of the type the method belongs to.
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    /// Return the name of the method called.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Return the string passed to the method.
+    pub fn argument(&self) -> &str {
+        &self.argument
+    }
+}
 
 #[derive(Clone, Debug)]
 pub(crate) enum LibrarySource {
     Path(PathBuf),
-    Blob(Vec<u8>),
+    // Shared so cached blobs can be cloned per injection without copying.
+    Blob(Arc<[u8]>),
+    #[cfg(feature = "compression")]
+    Compressed(Arc<CompressedBlob>),
+    // A path on the device a remote injector reaches, not on this machine.
+    Device(String),
+}
+
+impl LibrarySource {
+    /// Whether the image is in memory rather than in a file.
+    pub(crate) fn in_memory(&self) -> bool {
+        match self {
+            LibrarySource::Blob(_) => true,
+            #[cfg(feature = "compression")]
+            LibrarySource::Compressed(_) => true,
+            LibrarySource::Path(_) | LibrarySource::Device(_) => false,
+        }
+    }
+}
+
+/// A compressed blob, decoded on first use and shared by every clone.
+#[cfg(feature = "compression")]
+#[derive(Debug)]
+pub(crate) struct CompressedBlob {
+    compression: Compression,
+    bytes: Vec<u8>,
+    max_size: u64,
+    decoded: OnceLock<Arc<[u8]>>,
 }
 
 /// Reference to an injectable library or in-memory payload.
@@ -19,7 +184,24 @@ pub(crate) enum LibrarySource {
 pub struct Library {
     source: LibrarySource,
     entrypoint: CString,
+    // Called by `InjectedProcess::configure` once the agent is loaded.
+    configure_entrypoint: CString,
+    // Called by `InjectedProcess::eject` before the library is unloaded.
+    unload_entrypoint: CString,
+    // Called by `InjectedProcess::uninject` before demonitoring, if set.
+    shutdown_entrypoint: Option<CString>,
     data: CString,
+    // Binary data, staged to a file whose path replaces `data` on injection.
+    data_bytes: Option<Arc<[u8]>>,
+    unload_policy: UnloadPolicy,
+    jvm_attach: JvmAttach,
+    managed_assembly: Option<Arc<ManagedAssembly>>,
+    // Read from the image header; `None` if it was not recognized.
+    kind: Option<ImageKind>,
+    // Expected SHA-256 of the image, as hex.
+    sha256: Option<String>,
+    #[cfg(feature = "signatures")]
+    minisign: Option<Arc<crate::signature::Minisign>>,
 }
 
 impl Library {
@@ -41,6 +223,68 @@ impl Library {
         library_with_defaults(LibrarySource::Path(path))
     }
 
+    /// Find a library by name in a list of directories.
+    ///
+    /// `name` is turned into the platform filename the way a cdylib crate
+    /// name is (`my-agent` becomes `libmy_agent.so`, `libmy_agent.dylib` or
+    /// `my_agent.dll`). `search_paths` are tried in order, followed by the
+    /// directories listed in `HOOK_INJECT_LIBRARY_PATH`. If none contains
+    /// the file, the error (`ErrorKind::Io`) lists the directories tried.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use hook_inject::Library;
+    /// let lib = Library::from_name("agent", &["./target/release", "/opt/agents"])?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn from_name<P: AsRef<Path>>(name: &str, search_paths: &[P]) -> Result<Library> {
+        let filename = hook_inject_build::library_filename(name);
+        let mut dirs: Vec<PathBuf> = search_paths
+            .iter()
+            .map(|dir| dir.as_ref().to_path_buf())
+            .collect();
+        if let Some(paths) = std::env::var_os("HOOK_INJECT_LIBRARY_PATH") {
+            dirs.extend(std::env::split_paths(&paths).filter(|dir| !dir.as_os_str().is_empty()));
+        }
+
+        if let Some(path) = dirs
+            .iter()
+            .map(|dir| dir.join(&filename))
+            .find(|path| path.is_file())
+        {
+            return Self::from_path(path);
+        }
+        let tried: Vec<String> = dirs.iter().map(|dir| dir.display().to_string()).collect();
+        Err(Error::from_io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("{filename} not found in [{}]", tried.join(", ")),
+        )))
+    }
+
+    /// Refer to a library already on the device a remote or USB injector
+    /// reaches (see `Injector::device`), by its absolute path there.
+    ///
+    /// The library is loaded from that path instead of being sent over, so
+    /// it must be readable by the target; `AdbDevice::push_agent` puts one
+    /// in place on Android. Only remote injectors can inject it, and checks
+    /// that need the image (`with_sha256`, entrypoint exports) fail, since
+    /// it cannot be read from here.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use hook_inject::Library;
+    /// let lib = Library::from_device_path("/data/local/tmp/libagent.so")?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn from_device_path(path: impl Into<String>) -> Result<Library> {
+        let path = path.into();
+        if !path.starts_with('/') {
+            return Err(Error::invalid_input("device library path must be absolute"));
+        }
+        cstring_from_str(&path, "library path")?;
+        library_with_defaults(LibrarySource::Device(path))
+    }
+
     /// Create from raw in-memory bytes.
     ///
     /// # Examples
@@ -55,19 +299,96 @@ impl Library {
             return Err(Error::invalid_input("library blob is empty"));
         }
 
-        library_with_defaults(LibrarySource::Blob(bytes))
+        library_with_defaults(LibrarySource::Blob(bytes.into()))
+    }
+
+    /// Load a ready-made agent shipped with the crate.
+    ///
+    /// Fails with `ErrorKind::NotSupported` unless the agent's feature is
+    /// enabled (see `StockAgent`). Set the log path with `with_data`.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{inject_process, Library, Process, StockAgent};
+    ///
+    /// let library = Library::stock(StockAgent::FileTracer)?.with_data("/tmp/opens.log")?;
+    /// let injected = inject_process(Process::from_pid(1234)?, library)?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn stock(agent: StockAgent) -> Result<Library> {
+        agent.library()
+    }
+
+    // Target of `embed_agent!`; metadata left empty falls back to defaults.
+    #[doc(hidden)]
+    pub fn embedded(bytes: &'static [u8], entrypoint: &str, data: &str) -> Result<Library> {
+        let mut library = Self::from_bytes(bytes)?;
+        if !entrypoint.is_empty() {
+            library.entrypoint = cstring_from_str(entrypoint, "entrypoint")?;
+        }
+        library.data = cstring_from_str(data, "data")?;
+        Ok(library)
+    }
+
+    /// Create from compressed in-memory bytes (requires the `compression`
+    /// feature).
+    ///
+    /// The format (gzip or zstd) is detected from the data. Decompression is
+    /// deferred until the library is first injected, and happens once per
+    /// library and its clones. Until then `format` and `architecture`
+    /// return `None`. Blobs that decode to more than
+    /// `MAX_DECOMPRESSED_SIZE` bytes are rejected; see
+    /// `from_compressed_bytes_limited` for larger agents.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use hook_inject::Library;
+    /// let lib = Library::from_compressed_bytes(std::fs::read("libagent.so.zst")?)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "compression")]
+    pub fn from_compressed_bytes<B: Into<Vec<u8>>>(bytes: B) -> Result<Library> {
+        Library::from_compressed_bytes_limited(bytes, compress::MAX_DECOMPRESSED_SIZE)
+    }
+
+    /// `from_compressed_bytes`, failing with `ErrorKind::InvalidInput` when
+    /// the blob decodes to more than `max_size` bytes.
+    #[cfg(feature = "compression")]
+    pub fn from_compressed_bytes_limited<B: Into<Vec<u8>>>(
+        bytes: B,
+        max_size: u64,
+    ) -> Result<Library> {
+        let bytes = bytes.into();
+        let compression = Compression::detect(&bytes)
+            .ok_or_else(|| Error::invalid_input("library blob is not gzip or zstd compressed"))?;
+
+        library_with_defaults(LibrarySource::Compressed(Arc::new(CompressedBlob {
+            compression,
+            bytes,
+            max_size,
+            decoded: OnceLock::new(),
+        })))
     }
 
     /// Resolve a cdylib built from a Rust crate.
     ///
     /// The path may be a directory containing Cargo.toml or a direct path to Cargo.toml.
-    /// If the library is not found, this will run `cargo build` once and retry.
+    /// The crate is built with `cargo build` unless an artifact built from the
+    /// same sources and options exists (see
+    /// `hook_inject_build::build_cdylib_cached`), so repeated calls are cheap.
+    /// Shared libraries the cdylib links against must be findable by the
+    /// loader; ones produced by the build are copied next to it, and any
+    /// others missing fail with `ErrorKind::InvalidInput`.
     ///
     /// You can optionally specify metadata in `Cargo.toml`:
     /// ```text
     /// [package.metadata.hook-inject]
     /// entrypoint = "my_entry"
     /// data = "hello"
+    /// # Or read the data from a file relative to the crate:
+    /// # data-file = "agent-config.json"
+    /// # Optional: checked before each injection (see `with_sha256`).
+    /// sha256 = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
     /// ```
     ///
     /// # Examples
@@ -77,26 +398,70 @@ impl Library {
     /// # Ok::<(), hook_inject::Error>(())
     /// ```
     pub fn from_crate<P: AsRef<Path>>(path: P) -> Result<Library> {
-        let crate_path = path.as_ref();
-
-        let dylib = if let Some(result) = hook_inject_build::read_cdylib_file(crate_path) {
-            result.map_err(|err| {
-                Error::invalid_input(format_args!("Failed to read library: {}", err))
-            })?
-        } else {
-            hook_inject_build::build_cdylib(crate_path).map_err(|err| {
-                Error::invalid_input(format_args!("Failed to build library: {err}"))
-            })?
-        };
+        library_from_crate(path.as_ref(), &BuildOptions::default())
+    }
 
-        Ok(Library {
-            source: LibrarySource::Path(dylib.path),
-            entrypoint: cstring_from_str(
-                dylib.entrypoint.as_deref().unwrap_or(DEFAULT_ENTRYPOINT),
-                "entrypoint",
-            )?,
-            data: cstring_from_str(dylib.data.as_deref().unwrap_or_default(), "data")?,
-        })
+    /// Resolve a cdylib built from a Rust crate, controlling the implicit
+    /// `cargo build`.
+    ///
+    /// The options only apply when the library has to be built; an existing
+    /// artifact is used as is.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use hook_inject::{BuildOptions, Library};
+    /// // Fail instead of reaching the network, e.g. in sandboxed CI.
+    /// let options = BuildOptions::new().locked(true).offline(true);
+    /// let lib = Library::from_crate_with("./agent-crate", &options)?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn from_crate_with<P: AsRef<Path>>(path: P, options: &BuildOptions) -> Result<Library> {
+        library_from_crate(path.as_ref(), options)
+    }
+
+    /// Resolve a cdylib built from a Rust crate for another target triple.
+    ///
+    /// Like `from_crate`, but builds with `cargo build --target <triple>`
+    /// and looks for the library under `target/<triple>/`. The target must
+    /// be installed (`rustup target add <triple>`).
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use hook_inject::Library;
+    /// let lib = Library::from_crate_for_target("./agent-crate", "i686-unknown-linux-gnu")?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn from_crate_for_target<P: AsRef<Path>>(path: P, triple: &str) -> Result<Library> {
+        library_from_crate(path.as_ref(), &BuildOptions::new().target(triple))
+    }
+
+    /// Resolve a cdylib built from a Rust crate for the architecture of
+    /// `process`.
+    ///
+    /// Processes matching the injector's own architecture use `from_crate`;
+    /// others are cross-compiled to `Architecture::target_triple`, e.g. to
+    /// reach a 32-bit process from a 64-bit injector.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use hook_inject::{inject_process, Library, Process};
+    /// let process = Process::from_pid(1234)?;
+    /// let lib = Library::from_crate_for("./agent-crate", process)?;
+    /// let injected = inject_process(process, lib)?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn from_crate_for<P: AsRef<Path>>(path: P, process: Process) -> Result<Library> {
+        let architecture = process.architecture()?;
+        if Some(architecture) == Architecture::native() {
+            return Self::from_crate(path);
+        }
+
+        let triple = architecture.target_triple().ok_or_else(|| {
+            Error::not_supported(format_args!(
+                "no known target triple for {architecture} on this platform"
+            ))
+        })?;
+        Self::from_crate_for_target(path, triple)
     }
 
     /// Return the entrypoint symbol name.
@@ -104,38 +469,311 @@ impl Library {
         &self.entrypoint
     }
 
+    /// Return the symbol `InjectedProcess::configure` calls.
+    pub fn configure_entrypoint(&self) -> &CStr {
+        &self.configure_entrypoint
+    }
+
+    /// Return the symbol `InjectedProcess::eject` calls.
+    pub fn unload_entrypoint(&self) -> &CStr {
+        &self.unload_entrypoint
+    }
+
+    /// Return the symbol `InjectedProcess::uninject` calls first, if any.
+    pub fn shutdown_entrypoint(&self) -> Option<&CStr> {
+        self.shutdown_entrypoint.as_deref()
+    }
+
     /// Return the opaque data string passed to the entrypoint.
+    ///
+    /// Empty while binary data set with `with_data_bytes` is pending.
     pub fn data(&self) -> &CStr {
         &self.data
     }
 
+    /// Return the binary data set with `with_data_bytes`, if any.
+    pub fn data_bytes(&self) -> Option<&[u8]> {
+        self.data_bytes.as_deref()
+    }
+
+    /// Return whether the agent is asked to stay loaded.
+    pub fn unload_policy(&self) -> UnloadPolicy {
+        self.unload_policy
+    }
+
+    /// Return whether the library is loaded into JVMs with the attach API.
+    pub fn jvm_attach(&self) -> JvmAttach {
+        self.jvm_attach
+    }
+
+    /// Return the managed assembly run after injection, if any.
+    pub fn managed_assembly(&self) -> Option<&ManagedAssembly> {
+        self.managed_assembly.as_deref()
+    }
+
+    /// Image format read from the library header, if recognized.
+    pub fn format(&self) -> Option<BinaryFormat> {
+        self.kind.map(|kind| kind.format)
+    }
+
+    /// CPU architecture read from the library header, if recognized.
+    ///
+    /// Multi-architecture (fat) Mach-O libraries report `None`.
+    pub fn architecture(&self) -> Option<Architecture> {
+        self.kind.and_then(|kind| kind.architecture)
+    }
+
+    /// This library as an iOS device will load it: an arm64 Mach-O dylib
+    /// with a code signature, ad-hoc signed here (with `codesign` on macOS,
+    /// `ldid` elsewhere) if it has none.
+    ///
+    /// Injectors for iOS devices do this to every library they inject;
+    /// call it to find problems before reaching the device. Fails with
+    /// `ErrorKind::InvalidInput` for other images, or for unsigned ones
+    /// when no signing tool is installed. Libraries on the device are
+    /// returned as they are.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use hook_inject::Library;
+    /// let lib = Library::from_path("libagent.dylib")?.signed_for_ios()?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn signed_for_ios(&self) -> Result<Library> {
+        crate::ios::stage_dylib(self, &crate::InjectOptions::default())
+    }
+
     /// Override entrypoint function name.
     ///
+    /// Fails with `ErrorKind::InvalidInput` if the name contains a NUL byte.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use hook_inject::Library;
+    /// let lib = Library::from_path("/path/to/libagent.so")?.with_entrypoint("my_entry")?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn with_entrypoint(mut self, entrypoint: impl IntoCString) -> Result<Self> {
+        self.entrypoint = into_cstring(entrypoint, "entrypoint")?;
+        Ok(self)
+    }
+
+    /// Override the configure entrypoint (default `hook_inject_configure`).
+    ///
+    /// `InjectedProcess::configure` calls it with new data after injection.
+    /// It has the same signature as the main entrypoint, and is only
+    /// required to exist once `configure` is called.
+    ///
     /// # Examples
     /// ```no_run
     /// # use hook_inject::Library;
-    /// use std::ffi::CString;
-    /// let lib = Library::from_path("/path/to/libagent.so")?
-    ///     .with_entrypoint(CString::new("my_entry").unwrap());
+    /// let lib = Library::from_path("/path/to/libagent.so")?.with_configure_entrypoint("my_configure")?;
     /// # Ok::<(), hook_inject::Error>(())
     /// ```
-    pub fn with_entrypoint(mut self, entrypoint: impl Into<CString>) -> Self {
-        self.entrypoint = entrypoint.into();
+    pub fn with_configure_entrypoint(mut self, entrypoint: impl IntoCString) -> Result<Self> {
+        self.configure_entrypoint = into_cstring(entrypoint, "entrypoint")?;
+        Ok(self)
+    }
+
+    /// Override the unload entrypoint (default `hook_inject_unload`).
+    ///
+    /// `InjectedProcess::eject` calls it, with empty data, to have the agent
+    /// remove its hooks, stop its threads and release anything keeping it
+    /// loaded before the library is unmapped.
+    pub fn with_unload_entrypoint(mut self, entrypoint: impl IntoCString) -> Result<Self> {
+        self.unload_entrypoint = into_cstring(entrypoint, "entrypoint")?;
+        Ok(self)
+    }
+
+    /// Have `uninject` call `entrypoint` in the agent first (none by
+    /// default).
+    ///
+    /// The call gets empty data, and `uninject` waits a couple of seconds
+    /// for it to return so the agent can flush buffers and remove its hooks
+    /// before it is demonitored. It is skipped if the agent already unloaded.
+    /// Libraries injected from memory are not supported.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use hook_inject::Library;
+    /// let lib = Library::from_path("/path/to/libagent.so")?.with_shutdown_entrypoint("hook_inject_shutdown")?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn with_shutdown_entrypoint(mut self, entrypoint: impl IntoCString) -> Result<Self> {
+        self.shutdown_entrypoint = Some(into_cstring(entrypoint, "entrypoint")?);
+        Ok(self)
+    }
+
+    /// Ask the agent to stay loaded, or to be unloaded, once its main
+    /// entrypoint returns (default `UnloadPolicy::Agent`).
+    ///
+    /// The agent applies the policy with `hook_inject_agent::entry_data`;
+    /// see `UnloadPolicy` for how it is passed.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use hook_inject::{Library, UnloadPolicy};
+    /// let lib = Library::from_path("/path/to/libagent.so")?.with_unload_policy(UnloadPolicy::Resident);
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn with_unload_policy(mut self, policy: UnloadPolicy) -> Self {
+        self.unload_policy = policy;
+        self
+    }
+
+    /// Load the library into Java targets through the JVM attach API
+    /// (default `JvmAttach::Never`).
+    ///
+    /// The library must then export `Agent_OnAttach`, and is loaded from a
+    /// file. Injecting natively under a running JVM is fragile; attaching
+    /// lets the JVM load the agent at a safe point and hand it a `JavaVM`.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use hook_inject::{JvmAttach, Library};
+    /// let lib = Library::from_path("/path/to/libjvmagent.so")?
+    ///     .with_jvm_attach(JvmAttach::Auto)
+    ///     .with_data("verbose=true")?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn with_jvm_attach(mut self, mode: JvmAttach) -> Self {
+        self.jvm_attach = mode;
+        self
+    }
+
+    /// Run `assembly` in the target's .NET runtime once the library is
+    /// injected (Windows, `clr-bootstrap` feature).
+    ///
+    /// The library is injected natively as usual first, so it can be a
+    /// small native agent of its own or one that prepares the ground for the
+    /// managed code. Running processes only: a program being launched has
+    /// no runtime to hand the assembly to yet.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use hook_inject::{Library, ManagedAssembly};
+    /// let lib = Library::from_path(r"C:\agents\native_agent.dll")?.with_managed_assembly(
+    ///     ManagedAssembly::new(r"C:\agents\Tracer.dll", "Tracer.Entry, Tracer", "Start"),
+    /// );
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn with_managed_assembly(mut self, assembly: ManagedAssembly) -> Self {
+        self.managed_assembly = Some(Arc::new(assembly));
         self
     }
 
     /// Override data passed to the entrypoint.
     ///
+    /// Fails with `ErrorKind::InvalidInput` if the data contains a NUL byte;
+    /// see `with_data_bytes` for binary data.
+    ///
     /// # Examples
     /// ```no_run
     /// # use hook_inject::Library;
-    /// use std::ffi::CString;
-    /// let lib = Library::from_path("/path/to/libagent.so")?
-    ///     .with_data(CString::new("hello").unwrap());
+    /// let lib = Library::from_path("/path/to/libagent.so")?.with_data("hello")?;
     /// # Ok::<(), hook_inject::Error>(())
     /// ```
-    pub fn with_data(mut self, data: impl Into<CString>) -> Self {
-        self.data = data.into();
+    pub fn with_data(mut self, data: impl IntoCString) -> Result<Self> {
+        self.data = into_cstring(data, "data")?;
+        self.data_bytes = None;
+        Ok(self)
+    }
+
+    /// Pass a typed value to the entrypoint, serialized as JSON.
+    ///
+    /// The agent decodes it with `hook_inject_agent::payload_from_ptr` (or
+    /// `payload_from_cstr`). JSON escapes NUL, so any value fits the data
+    /// string. Replaces any data set before.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use hook_inject::Library;
+    /// #[derive(serde::Serialize)]
+    /// struct Config {
+    ///     port: u16,
+    /// }
+    ///
+    /// let lib = Library::from_path("/path/to/libagent.so")?.with_payload(&Config { port: 8080 })?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn with_payload<T: serde::Serialize + ?Sized>(self, value: &T) -> Result<Self> {
+        let json = serde_json::to_string(value).map_err(|err| {
+            Error::invalid_input(format_args!("failed to serialize payload: {err}"))
+                .with_source(err)
+        })?;
+        self.with_data(json)
+    }
+
+    /// Pass binary data, which may contain NUL bytes, to the entrypoint.
+    ///
+    /// The entrypoint's data argument is a C string, so the bytes are written
+    /// to an owner-only file in the staging directory at injection time and
+    /// the entrypoint receives that file's path instead. The agent reads the
+    /// file from its entrypoint; it is removed along with other artifacts
+    /// once the target exits (see `clean_stale_artifacts`). Replaces any data
+    /// set with `with_data`, and the other way round.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use hook_inject::Library;
+    /// let config: Vec<u8> = std::fs::read("agent-config.pb")?;
+    /// let lib = Library::from_path("/path/to/libagent.so")?.with_data_bytes(config);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn with_data_bytes(mut self, data: impl Into<Vec<u8>>) -> Self {
+        self.data = CString::default();
+        self.data_bytes = Some(data.into().into());
+        self
+    }
+
+    /// Require the image to have the given SHA-256 digest (hex).
+    ///
+    /// The hash is checked each time an injection starts, over the file for
+    /// path libraries and the decompressed image for compressed blobs; a
+    /// mismatch fails the injection with `ErrorKind::InvalidInput`. Crates
+    /// can pin it with a `sha256` key in `[package.metadata.hook-inject]`.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use hook_inject::Library;
+    /// let lib = Library::from_path("/opt/agents/libagent.so")?
+    ///     .with_sha256("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08");
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn with_sha256(mut self, expected: impl Into<String>) -> Self {
+        self.sha256 = Some(expected.into());
+        self
+    }
+
+    /// Require a valid minisign signature over the image (requires the
+    /// `signatures` feature).
+    ///
+    /// `public_key` is either the base64 key or the contents of a
+    /// `minisign.pub` file; `signature` is the contents of the detached
+    /// `.minisig` file. Like `with_sha256`, the check runs each time an
+    /// injection starts and a bad signature fails it with
+    /// `ErrorKind::InvalidInput`.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use hook_inject::Library;
+    /// let lib = Library::from_path("/opt/agents/libagent.so")?.with_minisign(
+    ///     "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3",
+    ///     std::fs::read_to_string("/opt/agents/libagent.so.minisig")?,
+    /// );
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "signatures")]
+    pub fn with_minisign(
+        mut self,
+        public_key: impl Into<String>,
+        signature: impl Into<String>,
+    ) -> Self {
+        self.minisign = Some(Arc::new(crate::signature::Minisign::new(
+            public_key.into(),
+            signature.into(),
+        )));
         self
     }
 
@@ -172,6 +810,288 @@ impl Library {
     pub(crate) fn source(&self) -> &LibrarySource {
         &self.source
     }
+
+    /// This library loaded from `path` instead of its own source.
+    pub(crate) fn staged_at(&self, path: PathBuf) -> Library {
+        Library {
+            source: LibrarySource::Path(path),
+            ..self.clone()
+        }
+    }
+
+    /// This library with `bytes` as its image.
+    pub(crate) fn with_image(&self, bytes: Arc<[u8]>) -> Library {
+        Library {
+            kind: arch::identify(&bytes),
+            source: LibrarySource::Blob(bytes),
+            ..self.clone()
+        }
+    }
+
+    /// This library loaded from `path` on a remote device.
+    pub(crate) fn staged_on_device(&self, path: String) -> Library {
+        Library {
+            source: LibrarySource::Device(path),
+            ..self.clone()
+        }
+    }
+
+    /// The data string passed to the main entrypoint, led by the unload
+    /// policy header if one is set.
+    pub(crate) fn entry_data(&self) -> Result<Cow<'_, CStr>> {
+        let Some(header) = self.unload_policy.header() else {
+            return Ok(Cow::Borrowed(&self.data));
+        };
+        let mut bytes = header.as_bytes().to_vec();
+        bytes.extend_from_slice(self.data.to_bytes());
+        CString::new(bytes)
+            .map(Cow::Owned)
+            .map_err(|err| Error::invalid_input("data contains NUL").with_source(err))
+    }
+
+    /// This library passing the path of staged binary data as its data
+    /// string.
+    pub(crate) fn data_staged_at(&self, path: &Path) -> Result<Library> {
+        Ok(Library {
+            data: cstring_from_str(&path.to_string_lossy(), "staging path")?,
+            data_bytes: None,
+            ..self.clone()
+        })
+    }
+
+    /// Apply per-call overrides, validating them against the library image.
+    pub(crate) fn with_options(self, options: &InjectOptions) -> Result<Library> {
+        let mut library = self.decompressed()?;
+        if let Some(entrypoint) = options.entrypoint_value() {
+            library.entrypoint = entrypoint.to_owned();
+        }
+
+        library.check_sha256()?;
+        #[cfg(feature = "signatures")]
+        if let Some(minisign) = &library.minisign {
+            minisign.verify(&library.image_bytes()?)?;
+        }
+
+        // A missing entrypoint makes the injection a silent no-op, so check
+        // the export table up front. The default entrypoint of an image we
+        // cannot identify is left to the runtime; an explicit one is not.
+        if library.kind.is_some() || options.entrypoint_value().is_some() {
+            library.check_entrypoint()?;
+        }
+        Ok(library)
+    }
+
+    /// This library with a compressed blob replaced by its decoded bytes.
+    pub(crate) fn decompressed(&self) -> Result<Library> {
+        #[cfg(feature = "compression")]
+        if let LibrarySource::Compressed(blob) = &self.source {
+            return self.decoded(blob);
+        }
+        Ok(self.clone())
+    }
+
+    #[cfg(feature = "compression")]
+    fn decoded(&self, blob: &CompressedBlob) -> Result<Library> {
+        let bytes = match blob.decoded.get() {
+            Some(bytes) => bytes.clone(),
+            None => {
+                let bytes = compress::decompress(blob.compression, &blob.bytes, blob.max_size)?;
+                if bytes.is_empty() {
+                    return Err(Error::invalid_input("library blob is empty"));
+                }
+                blob.decoded.get_or_init(|| bytes.into()).clone()
+            }
+        };
+
+        Ok(Library {
+            kind: arch::identify(&bytes),
+            source: LibrarySource::Blob(bytes),
+            ..self.clone()
+        })
+    }
+
+    /// The image bytes, read from disk for path libraries.
+    pub(crate) fn image_bytes(&self) -> Result<Arc<[u8]>> {
+        match &self.source {
+            LibrarySource::Path(path) => Ok(std::fs::read(path)?.into()),
+            LibrarySource::Blob(bytes) => Ok(bytes.clone()),
+            #[cfg(feature = "compression")]
+            LibrarySource::Compressed(_) => self.decompressed()?.image_bytes(),
+            LibrarySource::Device(path) => Err(Error::not_supported(format_args!(
+                "{path} is on a remote device and cannot be read here"
+            ))),
+        }
+    }
+
+    /// Whether `other` refers to the same image: the same file, or the same
+    /// bytes.
+    pub(crate) fn same_image(&self, other: &Library) -> bool {
+        if let (LibrarySource::Path(a), LibrarySource::Path(b)) = (&self.source, &other.source) {
+            let canonical = |path: &PathBuf| std::fs::canonicalize(path).unwrap_or(path.clone());
+            if canonical(a) == canonical(b) {
+                return true;
+            }
+        }
+        match (self.image_bytes(), other.image_bytes()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Fail with `invalid_input` unless the image has the expected digest.
+    fn check_sha256(&self) -> Result<()> {
+        let Some(expected) = &self.sha256 else {
+            return Ok(());
+        };
+        check_sha256_hex(expected)?;
+
+        let actual = sha256_hex(&self.image_bytes()?);
+        if actual.eq_ignore_ascii_case(expected) {
+            return Ok(());
+        }
+        let mismatch = format!("sha256 mismatch: expected {expected}, found {actual}");
+        Err(Error::invalid_input(match &self.source {
+            LibrarySource::Path(path) => format!("{}: {mismatch}", path.display()),
+            _ => mismatch,
+        }))
+    }
+
+    /// Fail with `invalid_input` unless the image exports the entrypoint.
+    fn check_entrypoint(&self) -> Result<()> {
+        self.check_export(&self.entrypoint)
+    }
+
+    /// Fail with `invalid_input` unless the image exports `symbol`.
+    pub(crate) fn check_export(&self, symbol: &CStr) -> Result<()> {
+        let symbol = symbol
+            .to_str()
+            .map_err(|_| Error::invalid_input("entrypoint is not valid UTF-8"))?;
+        let checked = image::require_export(&self.image_bytes()?, symbol);
+        match &self.source {
+            LibrarySource::Path(path) => checked
+                .map_err(|err| Error::invalid_input(format_args!("{}: {err}", path.display()))),
+            _ => checked,
+        }
+    }
+
+    /// Fail with `invalid_input` if this library cannot load into a target of
+    /// the given architecture on this platform.
+    ///
+    /// Unrecognized images and unknown targets pass; the runtime has the
+    /// final say for those.
+    pub(crate) fn check_target(&self, target: Option<Architecture>) -> Result<()> {
+        let Some(kind) = self.kind else {
+            return Ok(());
+        };
+        if let Some(native) = BinaryFormat::native()
+            && kind.format != native
+        {
+            return Err(Error::invalid_input(format_args!(
+                "library is a {} image, but this platform loads {native} images",
+                kind.format
+            )));
+        }
+        if let (Some(library), Some(target)) = (kind.architecture, target)
+            && library != target
+        {
+            return Err(Error::invalid_input(format_args!(
+                "library is built for {library}, but the target is {target}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn library_from_crate(crate_path: &Path, options: &BuildOptions) -> Result<Library> {
+    let dylib = hook_inject_build::build_cdylib_cached(crate_path, options).map_err(|err| {
+        Error::invalid_input(format_args!("Failed to build library: {err}")).with_source(err)
+    })?;
+
+    bundle::bundle_dependencies(&dylib.path)?;
+
+    let kind = arch::identify_file(&dylib.path).unwrap_or(None);
+    Ok(Library {
+        kind,
+        source: LibrarySource::Path(dylib.path),
+        entrypoint: cstring_from_str(
+            dylib.entrypoint.as_deref().unwrap_or(DEFAULT_ENTRYPOINT),
+            "entrypoint",
+        )?,
+        configure_entrypoint: cstring_from_str(DEFAULT_CONFIGURE_ENTRYPOINT, "entrypoint")?,
+        unload_entrypoint: cstring_from_str(DEFAULT_UNLOAD_ENTRYPOINT, "entrypoint")?,
+        shutdown_entrypoint: None,
+        unload_policy: UnloadPolicy::default(),
+        jvm_attach: JvmAttach::default(),
+        managed_assembly: None,
+        data: cstring_from_str(dylib.data.as_deref().unwrap_or_default(), "data")?,
+        data_bytes: None,
+        sha256: dylib.sha256,
+        #[cfg(feature = "signatures")]
+        minisign: None,
+    })
+}
+
+/// Fail with `invalid_input` unless `hex` looks like a SHA-256 digest.
+pub(crate) fn check_sha256_hex(hex: &str) -> Result<()> {
+    if hex.len() != 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(Error::invalid_input(
+            "expected sha256 must be 64 hex digits",
+        ));
+    }
+    Ok(())
+}
+
+/// Lowercase hex SHA-256 of `bytes`.
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// A string accepted as an entrypoint name or entrypoint data.
+///
+/// Implemented for Rust strings, which are checked for interior NUL bytes,
+/// and for C strings, which are taken as is.
+pub trait IntoCString {
+    /// Convert to a C string, failing on an interior NUL byte.
+    fn into_cstring(self) -> std::result::Result<CString, std::ffi::NulError>;
+}
+
+impl IntoCString for &str {
+    fn into_cstring(self) -> std::result::Result<CString, std::ffi::NulError> {
+        CString::new(self)
+    }
+}
+
+impl IntoCString for String {
+    fn into_cstring(self) -> std::result::Result<CString, std::ffi::NulError> {
+        CString::new(self)
+    }
+}
+
+impl IntoCString for &String {
+    fn into_cstring(self) -> std::result::Result<CString, std::ffi::NulError> {
+        CString::new(self.as_str())
+    }
+}
+
+impl IntoCString for CString {
+    fn into_cstring(self) -> std::result::Result<CString, std::ffi::NulError> {
+        Ok(self)
+    }
+}
+
+impl IntoCString for &CStr {
+    fn into_cstring(self) -> std::result::Result<CString, std::ffi::NulError> {
+        Ok(self.to_owned())
+    }
+}
+
+pub(crate) fn into_cstring(value: impl IntoCString, label: &'static str) -> Result<CString> {
+    value
+        .into_cstring()
+        .map_err(|err| Error::invalid_input(format_args!("{label} contains NUL")).with_source(err))
 }
 
 fn cstring_from_str(value: &str, label: &'static str) -> Result<CString> {
@@ -179,9 +1099,72 @@ fn cstring_from_str(value: &str, label: &'static str) -> Result<CString> {
 }
 
 fn library_with_defaults(source: LibrarySource) -> Result<Library> {
+    let kind = match &source {
+        LibrarySource::Path(path) => arch::identify_file(path).unwrap_or(None),
+        LibrarySource::Blob(bytes) => arch::identify(bytes),
+        #[cfg(feature = "compression")]
+        LibrarySource::Compressed(_) => None,
+        LibrarySource::Device(_) => None,
+    };
     Ok(Library {
+        kind,
         source,
         entrypoint: cstring_from_str(DEFAULT_ENTRYPOINT, "entrypoint")?,
+        configure_entrypoint: cstring_from_str(DEFAULT_CONFIGURE_ENTRYPOINT, "entrypoint")?,
+        unload_entrypoint: cstring_from_str(DEFAULT_UNLOAD_ENTRYPOINT, "entrypoint")?,
+        shutdown_entrypoint: None,
+        unload_policy: UnloadPolicy::default(),
+        jvm_attach: JvmAttach::default(),
+        managed_assembly: None,
         data: cstring_from_str("", "data")?,
+        data_bytes: None,
+        sha256: None,
+        #[cfg(feature = "signatures")]
+        minisign: None,
     })
 }
+
+/// Embed an agent built by `hook_inject_build::embed_agent` as a `Library`.
+///
+/// The agent crate is compiled by the injector's build script and its bytes
+/// are included in the injector binary, so a single executable carries its
+/// agent. Expands to a `Result<Library>` backed by an in-memory blob, with
+/// the entrypoint and data from the agent's `[package.metadata.hook-inject]`.
+///
+/// The argument is the agent's package name.
+///
+/// # Examples
+/// ```ignore
+/// // build.rs (with `hook-inject-build` as a build dependency)
+/// fn main() {
+///     hook_inject_build::embed_agent("../my-agent").unwrap();
+/// }
+///
+/// // main.rs
+/// let library = hook_inject::embed_agent!("my-agent")?;
+/// ```
+#[macro_export]
+macro_rules! embed_agent {
+    ($name:literal) => {
+        $crate::Library::embedded(
+            include_bytes!(concat!(
+                env!("OUT_DIR"),
+                "/hook-inject-agent-",
+                $name,
+                ".bin"
+            )),
+            include_str!(concat!(
+                env!("OUT_DIR"),
+                "/hook-inject-agent-",
+                $name,
+                ".entrypoint"
+            )),
+            include_str!(concat!(
+                env!("OUT_DIR"),
+                "/hook-inject-agent-",
+                $name,
+                ".data"
+            )),
+        )
+    };
+}
@@ -4,8 +4,10 @@ fn main() {
     let library =
         Library::from_crate("/Users/tundeoladipupo/RustProjects/hook-inject/fixtures/agent")
             .unwrap()
-            .with_entrypoint(c"hook_inject_entry")
-            .with_data(c"/tmp/new_file");
+            .with_entrypoint("hook_inject_entry")
+            .unwrap()
+            .with_data("/tmp/new_file")
+            .unwrap();
     // library
     //     .inject_program("/Users/tundeoladipupo/RustProjects/hook-inject/zz_lab/misc/mango")
     //     .unwrap();
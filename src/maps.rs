@@ -0,0 +1,499 @@
+use std::path::{Path, PathBuf};
+
+use crate::{Error, Result};
+
+/// Access permissions of a mapped region.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Protection {
+    read: bool,
+    write: bool,
+    execute: bool,
+}
+
+impl Protection {
+    /// Returns true if the region is readable.
+    pub fn read(&self) -> bool {
+        self.read
+    }
+
+    /// Returns true if the region is writable.
+    pub fn write(&self) -> bool {
+        self.write
+    }
+
+    /// Returns true if the region is executable.
+    pub fn execute(&self) -> bool {
+        self.execute
+    }
+}
+
+/// A contiguous mapped region in a target process.
+///
+/// Regions are returned in ascending address order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryRegion {
+    start: u64,
+    end: u64,
+    protection: Protection,
+    shared: bool,
+    offset: u64,
+    path: Option<PathBuf>,
+}
+
+impl MemoryRegion {
+    /// First address of the region.
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// One past the last address of the region.
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// Size of the region in bytes.
+    pub fn len(&self) -> u64 {
+        self.end - self.start
+    }
+
+    /// Returns true if the region has zero length.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Returns true if `address` falls inside the region.
+    pub fn contains(&self, address: u64) -> bool {
+        self.start <= address && address < self.end
+    }
+
+    /// Current access permissions.
+    pub fn protection(&self) -> Protection {
+        self.protection
+    }
+
+    /// Returns true if the mapping is shared rather than copy-on-write.
+    pub fn is_shared(&self) -> bool {
+        self.shared
+    }
+
+    /// Offset of the region within its backing file (0 for anonymous memory).
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Backing file, if the region maps one.
+    ///
+    /// Pseudo-paths such as `[heap]` or `[stack]` on Linux are reported as-is.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn memory_maps(pid: i32) -> Result<Vec<MemoryRegion>> {
+    let path = format!("/proc/{pid}/maps");
+    let contents = std::fs::read_to_string(&path).map_err(|err| match err.kind() {
+        std::io::ErrorKind::NotFound => Error::process_not_found(pid),
+        std::io::ErrorKind::PermissionDenied => {
+            Error::permission_denied(format!("permission denied reading {path}"))
+        }
+        _ => Error::from(err),
+    })?;
+
+    contents
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            parse_maps_line(line)
+                .ok_or_else(|| Error::runtime(format!("malformed line in {path}: {line}")))
+        })
+        .collect()
+}
+
+/// Read `buf.len()` bytes of the target's memory starting at `address`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn read_memory(pid: i32, address: u64, buf: &mut [u8]) -> Result<()> {
+    use std::os::unix::fs::FileExt;
+
+    let path = format!("/proc/{pid}/mem");
+    let file = std::fs::File::open(&path).map_err(|err| match err.kind() {
+        std::io::ErrorKind::NotFound => Error::process_not_found(pid),
+        std::io::ErrorKind::PermissionDenied => {
+            Error::permission_denied(format!("permission denied reading {path}"))
+        }
+        _ => Error::from(err),
+    })?;
+    file.read_exact_at(buf, address).map_err(|err| {
+        Error::runtime(format_args!(
+            "failed to read {} bytes at {address:#x} in pid {pid}: {err}",
+            buf.len()
+        ))
+    })
+}
+
+/// Parse one `/proc/<pid>/maps` line:
+/// `start-end perms offset dev inode [path]`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn parse_maps_line(line: &str) -> Option<MemoryRegion> {
+    let mut fields = line.splitn(6, ' ');
+    let range = fields.next()?;
+    let perms = fields.next()?.as_bytes();
+    let offset = fields.next()?;
+    let _dev = fields.next()?;
+    let _inode = fields.next()?;
+    let path = fields.next().map(str::trim_start).unwrap_or("");
+
+    let (start, end) = range.split_once('-')?;
+    if perms.len() < 4 {
+        return None;
+    }
+
+    Some(MemoryRegion {
+        start: u64::from_str_radix(start, 16).ok()?,
+        end: u64::from_str_radix(end, 16).ok()?,
+        protection: Protection {
+            read: perms[0] == b'r',
+            write: perms[1] == b'w',
+            execute: perms[2] == b'x',
+        },
+        shared: perms[3] == b's',
+        offset: u64::from_str_radix(offset, 16).ok()?,
+        path: (!path.is_empty()).then(|| PathBuf::from(path)),
+    })
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn memory_maps(pid: i32) -> Result<Vec<MemoryRegion>> {
+    use std::ffi::{CStr, c_char, c_int, c_void};
+    use std::os::unix::ffi::OsStrExt;
+
+    // <mach/vm_region.h>: vm_region_basic_info_data_64_t is declared under
+    // `#pragma pack(4)`.
+    #[repr(C, packed(4))]
+    #[derive(Default)]
+    #[allow(dead_code)]
+    struct VmRegionBasicInfo64 {
+        protection: c_int,
+        max_protection: c_int,
+        inheritance: u32,
+        shared: u32,
+        reserved: u32,
+        offset: u64,
+        behavior: c_int,
+        user_wired_count: u16,
+    }
+
+    const VM_REGION_BASIC_INFO_64: c_int = 9;
+    const VM_PROT_READ: c_int = 0x1;
+    const VM_PROT_WRITE: c_int = 0x2;
+    const VM_PROT_EXECUTE: c_int = 0x4;
+    const KERN_SUCCESS: c_int = 0;
+    const KERN_INVALID_ADDRESS: c_int = 1;
+
+    unsafe extern "C" {
+        fn mach_vm_region(
+            task: libc::mach_port_t,
+            address: *mut u64,
+            size: *mut u64,
+            flavor: c_int,
+            info: *mut c_int,
+            count: *mut u32,
+            object_name: *mut libc::mach_port_t,
+        ) -> c_int;
+    }
+
+    let task = task_port(pid)?;
+
+    let mut regions = Vec::new();
+    let mut address: u64 = 0;
+    let result = loop {
+        let mut size: u64 = 0;
+        let mut info = VmRegionBasicInfo64::default();
+        let mut count = (std::mem::size_of::<VmRegionBasicInfo64>() / 4) as u32;
+        let mut object_name: libc::mach_port_t = 0;
+        let kr = unsafe {
+            mach_vm_region(
+                task,
+                &mut address,
+                &mut size,
+                VM_REGION_BASIC_INFO_64,
+                &mut info as *mut _ as *mut c_int,
+                &mut count,
+                &mut object_name,
+            )
+        };
+        if kr == KERN_INVALID_ADDRESS {
+            break Ok(regions);
+        }
+        if kr != KERN_SUCCESS {
+            break Err(Error::runtime(format!(
+                "mach_vm_region failed (kern_return_t {kr})"
+            )));
+        }
+
+        let mut buf = [0 as c_char; libc::PATH_MAX as usize];
+        let len = unsafe {
+            libc::proc_regionfilename(
+                pid,
+                address,
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len() as u32,
+            )
+        };
+        let path = (len > 0).then(|| {
+            let name = unsafe { CStr::from_ptr(buf.as_ptr()) };
+            PathBuf::from(std::ffi::OsStr::from_bytes(name.to_bytes()))
+        });
+
+        let protection = info.protection;
+        regions.push(MemoryRegion {
+            start: address,
+            end: address + size,
+            protection: Protection {
+                read: protection & VM_PROT_READ != 0,
+                write: protection & VM_PROT_WRITE != 0,
+                execute: protection & VM_PROT_EXECUTE != 0,
+            },
+            shared: info.shared != 0,
+            offset: info.offset,
+            path,
+        });
+        address += size;
+    };
+
+    release_task_port(task);
+    result
+}
+
+/// Read `buf.len()` bytes of the target's memory starting at `address`.
+#[cfg(target_os = "macos")]
+pub(crate) fn read_memory(pid: i32, address: u64, buf: &mut [u8]) -> Result<()> {
+    use std::ffi::c_int;
+
+    unsafe extern "C" {
+        fn mach_vm_read_overwrite(
+            task: libc::mach_port_t,
+            address: u64,
+            size: u64,
+            data: u64,
+            out_size: *mut u64,
+        ) -> c_int;
+    }
+
+    let task = task_port(pid)?;
+    let mut read: u64 = 0;
+    let kr = unsafe {
+        mach_vm_read_overwrite(
+            task,
+            address,
+            buf.len() as u64,
+            buf.as_mut_ptr() as u64,
+            &mut read,
+        )
+    };
+    release_task_port(task);
+
+    if kr != 0 || read != buf.len() as u64 {
+        return Err(Error::runtime(format!(
+            "failed to read {} bytes at {address:#x} in pid {pid} (kern_return_t {kr})",
+            buf.len()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn task_port(pid: i32) -> Result<libc::mach_port_t> {
+    let mut task: libc::mach_port_t = 0;
+    let kr = unsafe { libc::task_for_pid(libc::mach_task_self(), pid, &mut task) };
+    if kr != 0 {
+        // task_for_pid does not distinguish a missing pid from a denied one;
+        // probe existence so callers get the precise error.
+        crate::Process::from_pid(pid)?;
+        return Err(Error::permission_denied(format!(
+            "task_for_pid failed for pid {pid} (kern_return_t {kr})"
+        )));
+    }
+    Ok(task)
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn release_task_port(task: libc::mach_port_t) {
+    unsafe extern "C" {
+        fn mach_port_deallocate(task: libc::mach_port_t, name: libc::mach_port_t) -> libc::c_int;
+    }
+
+    unsafe { mach_port_deallocate(libc::mach_task_self(), task) };
+}
+
+#[cfg(windows)]
+pub(crate) fn memory_maps(pid: i32) -> Result<Vec<MemoryRegion>> {
+    use std::os::windows::ffi::OsStringExt;
+    use windows_sys::Win32::Foundation::{CloseHandle, ERROR_ACCESS_DENIED, GetLastError, HANDLE};
+    use windows_sys::Win32::System::Memory::{
+        MEM_COMMIT, MEM_IMAGE, MEM_MAPPED, MEMORY_BASIC_INFORMATION, PAGE_EXECUTE,
+        PAGE_EXECUTE_READ, PAGE_EXECUTE_READWRITE, PAGE_EXECUTE_WRITECOPY, PAGE_READONLY,
+        PAGE_READWRITE, PAGE_WRITECOPY, VirtualQueryEx,
+    };
+    use windows_sys::Win32::System::ProcessStatus::GetMappedFileNameW;
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, PROCESS_QUERY_INFORMATION, PROCESS_VM_READ,
+    };
+
+    if pid <= 0 {
+        return Err(Error::invalid_input("pid must be > 0"));
+    }
+
+    let handle: HANDLE =
+        unsafe { OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, 0, pid as u32) };
+    if handle.is_null() {
+        let err = unsafe { GetLastError() };
+        if err == ERROR_ACCESS_DENIED {
+            return Err(Error::permission_denied(
+                "permission denied while opening process (OpenProcess)",
+            ));
+        }
+        return Err(Error::process_not_found(pid));
+    }
+
+    let mut regions = Vec::new();
+    let mut address: usize = 0;
+    loop {
+        let mut info = MEMORY_BASIC_INFORMATION::default();
+        let written = unsafe {
+            VirtualQueryEx(
+                handle,
+                address as *const _,
+                &mut info,
+                std::mem::size_of::<MEMORY_BASIC_INFORMATION>(),
+            )
+        };
+        if written == 0 {
+            break;
+        }
+
+        let start = info.BaseAddress as usize;
+        let end = start + info.RegionSize;
+        if info.State == MEM_COMMIT {
+            // Strip modifier bits (PAGE_GUARD, PAGE_NOCACHE, ...) before
+            // classifying the base protection.
+            let base = info.Protect & 0xff;
+            let execute = matches!(
+                base,
+                PAGE_EXECUTE | PAGE_EXECUTE_READ | PAGE_EXECUTE_READWRITE | PAGE_EXECUTE_WRITECOPY
+            );
+            let read = (execute && base != PAGE_EXECUTE)
+                || matches!(base, PAGE_READONLY | PAGE_READWRITE | PAGE_WRITECOPY);
+            let write = matches!(
+                base,
+                PAGE_READWRITE | PAGE_WRITECOPY | PAGE_EXECUTE_READWRITE | PAGE_EXECUTE_WRITECOPY
+            );
+
+            let path = if info.Type == MEM_IMAGE || info.Type == MEM_MAPPED {
+                let mut buf = [0u16; 1024];
+                let len = unsafe {
+                    GetMappedFileNameW(
+                        handle,
+                        start as *const _,
+                        buf.as_mut_ptr(),
+                        buf.len() as u32,
+                    )
+                };
+                (len > 0)
+                    .then(|| PathBuf::from(std::ffi::OsString::from_wide(&buf[..len as usize])))
+            } else {
+                None
+            };
+
+            regions.push(MemoryRegion {
+                start: start as u64,
+                end: end as u64,
+                protection: Protection {
+                    read,
+                    write,
+                    execute,
+                },
+                shared: info.Type == MEM_MAPPED
+                    && !matches!(base, PAGE_WRITECOPY | PAGE_EXECUTE_WRITECOPY),
+                offset: 0,
+                path,
+            });
+        }
+
+        if end <= address {
+            break;
+        }
+        address = end;
+    }
+
+    unsafe { CloseHandle(handle) };
+    Ok(regions)
+}
+
+/// Read `buf.len()` bytes of the target's memory starting at `address`.
+#[cfg(windows)]
+pub(crate) fn read_memory(pid: i32, address: u64, buf: &mut [u8]) -> Result<()> {
+    use windows_sys::Win32::Foundation::{CloseHandle, ERROR_ACCESS_DENIED, GetLastError, HANDLE};
+    use windows_sys::Win32::System::Diagnostics::Debug::ReadProcessMemory;
+    use windows_sys::Win32::System::Threading::{OpenProcess, PROCESS_VM_READ};
+
+    if pid <= 0 {
+        return Err(Error::invalid_input("pid must be > 0"));
+    }
+
+    let handle: HANDLE = unsafe { OpenProcess(PROCESS_VM_READ, 0, pid as u32) };
+    if handle.is_null() {
+        let err = unsafe { GetLastError() };
+        if err == ERROR_ACCESS_DENIED {
+            return Err(Error::permission_denied(
+                "permission denied while opening process (OpenProcess)",
+            ));
+        }
+        return Err(Error::process_not_found(pid));
+    }
+
+    let mut read: usize = 0;
+    let ok = unsafe {
+        ReadProcessMemory(
+            handle,
+            address as usize as *const _,
+            buf.as_mut_ptr() as *mut _,
+            buf.len(),
+            &mut read,
+        )
+    };
+    let err = unsafe { GetLastError() };
+    unsafe { CloseHandle(handle) };
+
+    if ok == 0 || read != buf.len() {
+        return Err(Error::runtime(format!(
+            "failed to read {} bytes at {address:#x} in pid {pid} (error {err})",
+            buf.len()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    windows
+)))]
+pub(crate) fn memory_maps(_pid: i32) -> Result<Vec<MemoryRegion>> {
+    Err(Error::not_supported(
+        "memory map inspection is not supported on this platform",
+    ))
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    windows
+)))]
+pub(crate) fn read_memory(_pid: i32, _address: u64, _buf: &mut [u8]) -> Result<()> {
+    Err(Error::not_supported(
+        "reading process memory is not supported on this platform",
+    ))
+}
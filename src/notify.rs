@@ -0,0 +1,172 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::Process;
+
+/// Receives injection lifecycle events.
+///
+/// Attach one with `InjectOptions::notifier`. `notify` is called on the
+/// thread performing the operation, so implementations should hand slow work
+/// (network I/O, retries) off to another thread.
+///
+/// # Examples
+/// ```no_run
+/// use hook_inject::{inject_process_with, InjectOptions, InjectionEvent, Library, Notifier, Process};
+///
+/// #[derive(Debug)]
+/// struct Log;
+///
+/// impl Notifier for Log {
+///     fn notify(&self, event: &InjectionEvent) {
+///         eprintln!("{}", event.to_json());
+///     }
+/// }
+///
+/// let options = InjectOptions::new().notifier(Log);
+/// let process = Process::from_pid(1234)?;
+/// let injected = inject_process_with(process, Library::from_path("/path/to/libagent.so")?, options)?;
+/// # Ok::<(), hook_inject::Error>(())
+/// ```
+pub trait Notifier: Send + Sync + fmt::Debug {
+    /// Handle a single event.
+    fn notify(&self, event: &InjectionEvent);
+}
+
+/// What happened to an injection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum InjectionEventKind {
+    /// The library was injected.
+    Injected,
+    /// The library was uninjected or ejected on request.
+    Ejected,
+    /// The library was injected again after the target replaced its image
+    /// with `exec` (see `InjectOptions::follow_exec`), or replaced on request
+    /// with `InjectedProcess::reinject`.
+    Reinjected,
+    /// The target process exited while the library was injected, as a
+    /// `Watchdog` finds.
+    TargetExited,
+    /// The agent stopped responding or unloaded without being asked to, as
+    /// a `Watchdog` finds, or could not be re-injected after the target's
+    /// `exec` (see `InjectOptions::follow_exec`).
+    AgentLost,
+}
+
+impl InjectionEventKind {
+    /// Stable lowercase name, as used in the JSON payload.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InjectionEventKind::Injected => "injected",
+            InjectionEventKind::Ejected => "ejected",
+            InjectionEventKind::Reinjected => "reinjected",
+            InjectionEventKind::TargetExited => "target_exited",
+            InjectionEventKind::AgentLost => "agent_lost",
+        }
+    }
+}
+
+impl fmt::Display for InjectionEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A single injection lifecycle event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InjectionEvent {
+    kind: InjectionEventKind,
+    process: Process,
+    label: Option<String>,
+    timestamp: SystemTime,
+}
+
+impl InjectionEvent {
+    /// Create an event stamped with the current time.
+    pub fn new(kind: InjectionEventKind, process: Process) -> Self {
+        Self {
+            kind,
+            process,
+            label: None,
+            timestamp: SystemTime::now(),
+        }
+    }
+
+    /// Attach the label of the injection (see `InjectOptions::label`).
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// What happened.
+    pub fn kind(&self) -> InjectionEventKind {
+        self.kind
+    }
+
+    /// The target process.
+    pub fn process(&self) -> Process {
+        self.process
+    }
+
+    /// The label of the injection, if it has one.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// When the event was recorded.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+
+    /// Serialize as a single-line JSON object:
+    /// `{"event":"injected","pid":1234,"timestamp_ms":1700000000000}`, with a
+    /// `"label"` field when the injection has one.
+    pub fn to_json(&self) -> String {
+        let millis = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis())
+            .unwrap_or(0);
+        let label = self
+            .label
+            .as_deref()
+            .map(|label| format!(r#","label":"{}""#, json_escape(label)))
+            .unwrap_or_default();
+        format!(
+            r#"{{"event":"{}","pid":{}{},"timestamp_ms":{}}}"#,
+            self.kind,
+            self.process.pid(),
+            label,
+            millis
+        )
+    }
+}
+
+pub(crate) fn emit(
+    notifier: Option<&Arc<dyn Notifier>>,
+    kind: InjectionEventKind,
+    process: Process,
+    label: Option<&str>,
+) {
+    if let Some(notifier) = notifier {
+        let event = InjectionEvent::new(kind, process);
+        notifier.notify(&match label {
+            Some(label) => event.with_label(label),
+            None => event,
+        });
+    }
+}
+
+fn json_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if u32::from(c) < 0x20 => escaped.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
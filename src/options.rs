@@ -0,0 +1,512 @@
+use std::ffi::{CStr, CString};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{Architecture, Clock, Descendant, Error, Notifier, Process, Result, SystemClock};
+
+/// Per-call options for `inject_process_with`, `inject_program_with` and
+/// `spawn_with`.
+///
+/// The defaults match `inject_process` / `inject_program`.
+///
+/// # Examples
+/// ```no_run
+/// use hook_inject::{inject_process_with, InjectOptions, Library, Process};
+///
+/// let process = Process::from_pid(1234)?;
+/// let library = Library::from_path("/path/to/libagent.so")?;
+/// let options = InjectOptions::new().staging_dir("/var/lib/injector/client-a");
+/// let injected = inject_process_with(process, library, options)?;
+/// # Ok::<(), hook_inject::Error>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct InjectOptions {
+    staging_dir: Option<PathBuf>,
+    entrypoint: Option<CString>,
+    realm: Realm,
+    notifier: Option<Arc<dyn Notifier>>,
+    timeout: Option<Duration>,
+    follow_exec: bool,
+    children: Option<ChildFilter>,
+    already_loaded: AlreadyLoaded,
+    label: Option<String>,
+    concurrency: Option<usize>,
+    ready_timeout: Option<Duration>,
+    method: Option<InjectionMethod>,
+    clock: Option<Arc<dyn Clock>>,
+}
+
+/// Decides which descendants of a followed target get the library.
+#[derive(Clone)]
+pub(crate) struct ChildFilter(Arc<dyn Fn(&Descendant) -> bool + Send + Sync>);
+
+impl ChildFilter {
+    pub(crate) fn accepts(&self, descendant: &Descendant) -> bool {
+        (self.0)(descendant)
+    }
+}
+
+impl std::fmt::Debug for ChildFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ChildFilter(..)")
+    }
+}
+
+/// What `inject_process_with` does when the library is already loaded in
+/// the target.
+///
+/// The library is looked up in the target's memory map like
+/// `InjectedProcess::is_loaded`: by path, or by image header for libraries
+/// injected from memory.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AlreadyLoaded {
+    /// Inject anyway, without checking.
+    #[default]
+    Inject,
+    /// Fail with `ErrorKind::AlreadyInjected`.
+    Fail,
+    /// Return a handle to the injection this process made earlier, failing
+    /// like `Fail` if something else loaded the library.
+    Reuse,
+}
+
+/// Execution realm to inject into (Frida: `FridaRealm`).
+///
+/// Android devices running apps under an emulated ABI (e.g. ARM code on an x86
+/// device via NativeBridge) have two realms in one process. Library injection
+/// through Frida only reaches the native realm, so requesting `Emulated`
+/// fails with `Error::not_supported` instead of silently loading the agent
+/// into the wrong one. So does `Native` for a local process whose code the
+/// machine, detected at run time, only emulates (e.g. x86_64 code under
+/// Rosetta or qemu on arm64), where the native realm runs none of the
+/// target's code, unless the injector runs that code too.
+///
+/// Only realm selection is offered: Frida's script runtime selection
+/// (QuickJS vs V8) only applies to JavaScript agents and has no equivalent
+/// for native library injection.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Realm {
+    /// The process's native ABI.
+    #[default]
+    Native,
+    /// The emulated ABI realm (Android NativeBridge).
+    Emulated,
+}
+
+/// How a native Windows backend gets the target to load the library (see
+/// `InjectOptions::injection_method`).
+///
+/// Targets differ in what they tolerate: some block or watch for threads
+/// created from outside, others have no thread that ever waits alertably.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum InjectionMethod {
+    /// Start a thread in the target with `CreateRemoteThread`. Load
+    /// failures are reported by the injection call.
+    #[default]
+    RemoteThread,
+    /// Queue an APC to the target's threads with `QueueUserAPC`; the first
+    /// to wait alertably starts the library's thread. This also reaches
+    /// programs held before their loader has run, which run it as they
+    /// start.
+    Apc,
+    /// Suspend one of the target's threads and redirect it with
+    /// `SetThreadContext` to start the library's thread, then carry on where
+    /// it was. A thread blocked in a wait does this once the wait ends.
+    /// x86_64 only, and not for programs held before their loader has run.
+    ThreadHijack,
+}
+
+impl std::fmt::Display for InjectionMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            InjectionMethod::RemoteThread => "remote thread",
+            InjectionMethod::Apc => "queued APC",
+            InjectionMethod::ThreadHijack => "thread hijack",
+        })
+    }
+}
+
+impl InjectOptions {
+    /// Create options with default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the directory used for files staged for this injection.
+    ///
+    /// This overrides `HOOK_INJECT_STAGING_DIR` for a single call, so that
+    /// artifacts belonging to different clients never share a directory.
+    /// The directory is created (owner-only on Unix) if it does not exist.
+    /// Blobs and binary data staged later for the same injection, by
+    /// `reinject`, `update_data` or `push_config`, go there too.
+    pub fn staging_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.staging_dir = Some(dir.into());
+        self
+    }
+
+    /// Override the library's entrypoint for this injection only.
+    ///
+    /// This lets one cached `Library` serve agents with several entry
+    /// functions. For blob libraries the symbol is checked against the blob's
+    /// export table before anything touches the target.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{inject_process_with, InjectOptions, Library, Process};
+    ///
+    /// let agent = Library::from_bytes(std::fs::read("/path/to/libagent.so")?)?;
+    /// let process = Process::from_pid(1234)?;
+    /// let options = InjectOptions::new().entrypoint(c"tracer_main");
+    /// let injected = inject_process_with(process, agent.clone(), options)?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn entrypoint(mut self, entrypoint: impl Into<CString>) -> Self {
+        self.entrypoint = Some(entrypoint.into());
+        self
+    }
+
+    /// Select the execution realm to inject into.
+    pub fn realm(mut self, realm: Realm) -> Self {
+        self.realm = realm;
+        self
+    }
+
+    /// Report lifecycle events for this injection to `notifier`.
+    ///
+    /// The notifier stays attached to the returned handle, so uninjecting it
+    /// later is reported too.
+    pub fn notifier(mut self, notifier: impl Notifier + 'static) -> Self {
+        self.notifier = Some(Arc::new(notifier));
+        self
+    }
+
+    /// Bound each blocking runtime operation (spawn, launch, inject, resume).
+    ///
+    /// A target that never reaches the point the runtime waits for (e.g. one
+    /// stuck in an anti-debug loop) makes the operation fail with
+    /// `Error::is_timed_out` instead of blocking the calling thread
+    /// indefinitely. A program spawned by a launch that times out is killed.
+    /// By default operations wait indefinitely.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use hook_inject::{inject_program_with, InjectOptions, Library, Program};
+    ///
+    /// let options = InjectOptions::new().timeout(Duration::from_secs(5));
+    /// let library = Library::from_path("/path/to/libagent.so")?;
+    /// if let Err(err) = inject_program_with(Program::new("/usr/bin/target"), library, options) {
+    ///     if err.is_timed_out() {
+    ///         eprintln!("target hung during launch");
+    ///     }
+    /// }
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Re-inject the library whenever the target calls `exec`.
+    ///
+    /// Launcher scripts that `exec` the real binary otherwise shed the agent
+    /// along with their old image. With this set the injector holds the
+    /// target at each `exec`, injects the library into the new image and
+    /// resumes it, reporting `InjectionEventKind::Reinjected` (or `AgentLost`
+    /// if re-injection fails) to the notifier. Uninjecting the handle stops
+    /// following.
+    ///
+    /// This uses Frida's child gating, which attaches Frida's own agent to
+    /// the target. Children forked or spawned by the target are resumed
+    /// untouched unless `follow_children` is also set.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{inject_program_with, InjectOptions, Library, Program};
+    ///
+    /// let options = InjectOptions::new().follow_exec(true);
+    /// let library = Library::from_path("/path/to/libagent.so")?;
+    /// let injected = inject_program_with(Program::new("/opt/app/launcher.sh"), library, options)?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn follow_exec(mut self, follow: bool) -> Self {
+        self.follow_exec = follow;
+        self
+    }
+
+    /// Inject the library into every forked or spawned descendant of the
+    /// target that `filter` approves.
+    ///
+    /// The injector holds each new child suspended, asks `filter`, injects the
+    /// approved ones (which are then followed in turn, so grandchildren are
+    /// covered too) and resumes the child either way. Each injected child is
+    /// reported to the notifier as `InjectionEventKind::Injected`.
+    /// Uninjecting the handle stops following and uninjects the descendants.
+    ///
+    /// The filter runs on a background thread while the child is suspended,
+    /// so it should return quickly. Like `follow_exec`, this uses Frida's
+    /// child gating.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{inject_process_with, InjectOptions, Library, Process};
+    ///
+    /// // Instrument renderer children but leave GPU helpers alone.
+    /// let options = InjectOptions::new().follow_children(|child| {
+    ///     child.path().is_none_or(|path| !path.ends_with("gpu-helper"))
+    /// });
+    /// let process = Process::from_pid(1234)?;
+    /// let injected = inject_process_with(process, Library::from_path("/path/to/libagent.so")?, options)?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn follow_children(
+        mut self,
+        filter: impl Fn(&Descendant) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.children = Some(ChildFilter(Arc::new(filter)));
+        self
+    }
+
+    /// Attach a free-form label to the injection, e.g. `"tracer-v2"`.
+    ///
+    /// The label identifies the injection in `injections`, in events sent to
+    /// the notifier and in records, which helps when a process hosts several
+    /// agents.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{inject_process_with, InjectOptions, Library, Process};
+    ///
+    /// let process = Process::from_pid(1234)?;
+    /// let options = InjectOptions::new().label("tracer-v2");
+    /// let injected = inject_process_with(process, Library::from_path("/path/to/libagent.so")?, options)?;
+    /// assert_eq!(injected.label(), Some("tracer-v2"));
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Return from injection only once the agent reports it is ready, failing
+    /// after `timeout`.
+    ///
+    /// Without this, a successful injection only means the entrypoint was
+    /// called. The agent reports readiness through `ENTRY_STATUS_SYMBOL`;
+    /// a failure code, a panic (see `PANIC_REPORT_SYMBOL`), an agent that
+    /// does not export the symbol, or the timeout fail the injection, which
+    /// is then undone. Launched programs are waited for after they resume,
+    /// and killed if their agent never gets ready.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use hook_inject::{inject_process_with, InjectOptions, Library, Process};
+    ///
+    /// let options = InjectOptions::new().wait_ready(Duration::from_secs(5));
+    /// let process = Process::from_pid(1234)?;
+    /// let injected = inject_process_with(process, Library::from_path("/path/to/libagent.so")?, options)?;
+    /// // The agent's hooks are installed here.
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn wait_ready(mut self, timeout: Duration) -> Self {
+        self.ready_timeout = Some(timeout);
+        self
+    }
+
+    /// Measure the waits on the target by `clock` (default the system
+    /// clock): for the agent to get ready or acknowledge a configuration,
+    /// for the loader or a bootstrapper to report back, and, through the
+    /// returned handle, for the target to exit.
+    ///
+    /// With a `ManualClock` the waits run their checks back to back, each
+    /// advancing the clock, so a target that never answers times out at
+    /// once.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use hook_inject::{inject_process_with, InjectOptions, Library, ManualClock, Process};
+    ///
+    /// let clock = ManualClock::new();
+    /// let options = InjectOptions::new()
+    ///     .clock(clock.clone())
+    ///     .wait_ready(Duration::from_secs(5));
+    /// let process = Process::from_pid(1234)?;
+    /// let injected = inject_process_with(process, Library::from_path("/path/to/libagent.so")?, options)?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Inject into up to `workers` targets at once in `inject_processes_with`
+    /// (default 1, one after another).
+    ///
+    /// Fails with `ErrorKind::InvalidInput` if `workers` is 0.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{inject_processes_with, InjectOptions, Library, Process};
+    ///
+    /// let workers: Vec<Process> = Vec::new();
+    /// let library = Library::from_path("/path/to/libagent.so")?;
+    /// let options = InjectOptions::new().concurrency(8);
+    /// for (process, result) in inject_processes_with(&workers, &library, options) {
+    ///     if let Err(err) = result {
+    ///         eprintln!("pid {}: {err}", process.pid());
+    ///     }
+    /// }
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn concurrency(mut self, workers: usize) -> Self {
+        self.concurrency = Some(workers);
+        self
+    }
+
+    /// Choose how the library is loaded, on backends that offer a choice
+    /// (`Backend::LoadLibrary`).
+    ///
+    /// With `Apc` and `ThreadHijack` the target loads the library on its own
+    /// schedule: the call returns once the target has been set up, and a
+    /// library that fails to load is reported as unloaded. Injected handles
+    /// report the method used with `injection_method`. Backends without a
+    /// choice fail with `ErrorKind::NotSupported`.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{Backend, InjectOptions, InjectionMethod, Injector, Library, Process};
+    ///
+    /// let injector = Injector::builder().backend(Backend::LoadLibrary).build()?;
+    /// let options = InjectOptions::new().injection_method(InjectionMethod::Apc);
+    /// let process = Process::from_pid(1234)?;
+    /// let injected = injector.inject_process_with(process, Library::from_path("agent.dll")?, options)?;
+    /// assert_eq!(injected.injection_method(), Some(InjectionMethod::Apc));
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn injection_method(mut self, method: InjectionMethod) -> Self {
+        self.method = Some(method);
+        self
+    }
+
+    /// Guard against injecting a library twice into the same process.
+    ///
+    /// A second copy of an agent installs its hooks twice. By default the
+    /// library is injected without checking; `AlreadyLoaded::Fail` and
+    /// `AlreadyLoaded::Reuse` check the target first. Launched programs
+    /// start without the library, so this only affects `inject_process_with`.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::{inject_process_with, AlreadyLoaded, InjectOptions, Library, Process};
+    ///
+    /// let options = InjectOptions::new().already_loaded(AlreadyLoaded::Reuse);
+    /// let process = Process::from_pid(1234)?;
+    /// let injected = inject_process_with(process, Library::from_path("/path/to/libagent.so")?, options)?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn already_loaded(mut self, policy: AlreadyLoaded) -> Self {
+        self.already_loaded = policy;
+        self
+    }
+
+    /// Reject option combinations the backend cannot honor.
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.realm == Realm::Emulated {
+            return Err(Error::not_supported(
+                "library injection into the emulated realm is not supported by the Frida injector",
+            ));
+        }
+        if self.concurrency == Some(0) {
+            return Err(Error::invalid_input("concurrency must be at least 1"));
+        }
+        Ok(())
+    }
+
+    /// Reject injecting `process`, which runs `target` code, into a realm
+    /// that runs none of it.
+    pub(crate) fn check_realm(&self, process: Process, target: Architecture) -> Result<()> {
+        let Some(host) = crate::arch::host_architecture() else {
+            return Ok(());
+        };
+        // 32-bit code runs natively on its 64-bit counterpart, and an
+        // injector that is emulated itself shares the target's realm.
+        let native = target == host
+            || matches!(
+                (host, target),
+                (Architecture::X86_64, Architecture::X86)
+                    | (Architecture::Aarch64, Architecture::Arm)
+            )
+            || Architecture::native() == Some(target);
+        if self.realm == Realm::Native && !native {
+            return Err(Error::not_supported(format_args!(
+                "pid {} runs {target} code, which this {host} machine only emulates; its \
+                 native realm runs none of that code, and injecting into the emulated realm \
+                 is not supported",
+                process.pid()
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn clock_value(&self) -> &dyn Clock {
+        self.clock.as_deref().unwrap_or(&SystemClock)
+    }
+
+    pub(crate) fn shared_clock(&self) -> Option<Arc<dyn Clock>> {
+        self.clock.clone()
+    }
+
+    pub(crate) fn with_clock(mut self, clock: Option<Arc<dyn Clock>>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub(crate) fn staging_dir_value(&self) -> Option<&Path> {
+        self.staging_dir.as_deref()
+    }
+
+    pub(crate) fn entrypoint_value(&self) -> Option<&CStr> {
+        self.entrypoint.as_deref()
+    }
+
+    pub(crate) fn notifier_value(&self) -> Option<&Arc<dyn Notifier>> {
+        self.notifier.as_ref()
+    }
+
+    pub(crate) fn timeout_value(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub(crate) fn follow_exec_value(&self) -> bool {
+        self.follow_exec
+    }
+
+    pub(crate) fn children_value(&self) -> Option<&ChildFilter> {
+        self.children.as_ref()
+    }
+
+    pub(crate) fn already_loaded_value(&self) -> AlreadyLoaded {
+        self.already_loaded
+    }
+
+    pub(crate) fn label_value(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub(crate) fn concurrency_value(&self) -> usize {
+        self.concurrency.unwrap_or(1)
+    }
+
+    pub(crate) fn ready_timeout_value(&self) -> Option<Duration> {
+        self.ready_timeout
+    }
+
+    pub(crate) fn injection_method_value(&self) -> Option<InjectionMethod> {
+        self.method
+    }
+}
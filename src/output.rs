@@ -0,0 +1,145 @@
+use std::time::{Duration, SystemTime};
+
+use crate::{AgentEvent, AgentEvents, InjectionId, Library, OUTPUT_SYMBOL, Process, Result};
+
+/// Which of the target's streams a chunk of output was written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// A chunk of output the target wrote, forwarded by its agent.
+///
+/// Chunks follow the target's writes loosely: one write may be split across
+/// chunks, and consecutive writes merged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputChunk {
+    stream: OutputStream,
+    timestamp: SystemTime,
+    data: Vec<u8>,
+}
+
+impl OutputChunk {
+    /// The stream the target wrote the chunk to.
+    pub fn stream(&self) -> OutputStream {
+        self.stream
+    }
+
+    /// When the agent forwarded the chunk, by the target's clock.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+
+    /// The bytes written.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Take the bytes written.
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    fn from_event(event: AgentEvent) -> Option<Self> {
+        let timestamp = event.timestamp();
+        let mut data = event.into_payload();
+        let stream = match data.first()? {
+            1 => OutputStream::Stdout,
+            2 => OutputStream::Stderr,
+            _ => return None,
+        };
+        data.remove(0);
+        Some(Self {
+            stream,
+            timestamp,
+            data,
+        })
+    }
+}
+
+/// The target's stdout and stderr, forwarded by its agent (see
+/// `OUTPUT_SYMBOL`).
+///
+/// Iterating blocks until the next chunk and ends once the agent is
+/// unloaded or the target exits; `try_read` returns what is available
+/// without waiting. Output the agent overwrote before it was read is
+/// counted in `lost_bytes`. Records that are not output are skipped.
+///
+/// # Examples
+/// ```no_run
+/// use std::io::Write;
+/// use hook_inject::{inject_process, Library, OutputStream, Process};
+///
+/// let process = Process::from_pid(1234)?;
+/// let injected = inject_process(process, Library::from_path("/path/to/libagent.so")?)?;
+/// for chunk in injected.output()? {
+///     let chunk = chunk?;
+///     match chunk.stream() {
+///         OutputStream::Stderr => std::io::stderr().write_all(chunk.data())?,
+///         _ => std::io::stdout().write_all(chunk.data())?,
+///     }
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct TargetOutput {
+    events: AgentEvents,
+}
+
+impl TargetOutput {
+    pub(crate) fn new(
+        injection: InjectionId,
+        process: Process,
+        library: Library,
+        start_time: Option<u64>,
+    ) -> Result<Self> {
+        let events = AgentEvents::new(
+            injection,
+            process,
+            library,
+            start_time,
+            OUTPUT_SYMBOL,
+            "capture output",
+        )?;
+        Ok(Self { events })
+    }
+
+    /// How long iterating waits between reads of the target (default 50ms).
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.events = self.events.poll_interval(interval);
+        self
+    }
+
+    /// Bytes of output the agent overwrote before they were read.
+    pub fn lost_bytes(&self) -> u64 {
+        self.events.lost_bytes()
+    }
+
+    /// Read the output forwarded since the last read, without waiting.
+    pub fn try_read(&mut self) -> Result<Vec<OutputChunk>> {
+        let events = self.events.try_read()?;
+        Ok(events
+            .into_iter()
+            .filter_map(OutputChunk::from_event)
+            .collect())
+    }
+}
+
+impl Iterator for TargetOutput {
+    type Item = Result<OutputChunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.events.next()? {
+                Ok(event) => {
+                    if let Some(chunk) = OutputChunk::from_event(event) {
+                        return Some(Ok(chunk));
+                    }
+                }
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
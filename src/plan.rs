@@ -0,0 +1,333 @@
+use std::fmt;
+
+use crate::{
+    Child, Error, ErrorKind, InjectOptions, InjectedSet, InjectionId, Library, Process,
+    ProcessMatcher, Program, Result, SuspendedProgram, backend, spawn_with,
+};
+
+/// A set of targets and the libraries to inject into each, applied all at
+/// once.
+///
+/// Targets are resolved first: pids are looked up, matchers must match at
+/// least one process, and programs are spawned suspended. Every library is
+/// then injected into every target, in the order both were added, and the
+/// spawned programs are resumed last; with `InjectOptions::wait_ready`,
+/// every agent must then report ready. If any step fails, everything done so
+/// far is undone: libraries are uninjected in reverse order and spawned
+/// programs are killed, so the plan applies fully or not at all.
+///
+/// Payloads travel with each library (`Library::with_data` and friends).
+///
+/// # Examples
+/// ```no_run
+/// use hook_inject::{InjectionPlan, Library, Program};
+///
+/// let report = InjectionPlan::new()
+///     .pid(1234)
+///     .matching("nginx")
+///     .launch(Program::new("/usr/bin/worker"))
+///     .library(Library::from_path("/path/to/libtracer.so")?.with_data("level=debug")?)
+///     .library(Library::from_path("/path/to/libprofiler.so")?)
+///     .execute()?;
+/// for target in report.targets() {
+///     println!("pid {}: {:?}", target.process().pid(), target.injections());
+/// }
+/// # Ok::<(), hook_inject::Error>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct InjectionPlan {
+    targets: Vec<Target>,
+    libraries: Vec<Library>,
+    options: InjectOptions,
+}
+
+#[derive(Debug)]
+enum Target {
+    Pid(i32),
+    Process(Process),
+    Matching(ProcessMatcher),
+    Launch(Program),
+}
+
+impl InjectionPlan {
+    /// Create an empty plan.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Target the process with `pid`, looked up when the plan is executed.
+    pub fn pid(mut self, pid: i32) -> Self {
+        self.targets.push(Target::Pid(pid));
+        self
+    }
+
+    /// Target `process`.
+    pub fn process(mut self, process: Process) -> Self {
+        self.targets.push(Target::Process(process));
+        self
+    }
+
+    /// Target every process matching `matcher` when the plan is executed.
+    ///
+    /// Strings match the executable name (see `ProcessMatcher`). Executing
+    /// fails if nothing matches.
+    pub fn matching(mut self, matcher: impl Into<ProcessMatcher>) -> Self {
+        self.targets.push(Target::Matching(matcher.into()));
+        self
+    }
+
+    /// Launch `program` suspended and target it; it is resumed once every
+    /// library is injected.
+    pub fn launch(mut self, program: impl Into<Program>) -> Self {
+        self.targets.push(Target::Launch(program.into()));
+        self
+    }
+
+    /// Inject `library` into every target, after the libraries added before.
+    pub fn library(mut self, library: impl Into<Library>) -> Self {
+        self.libraries.push(library.into());
+        self
+    }
+
+    /// Options used for every injection and launch.
+    pub fn options(mut self, options: InjectOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Apply the plan, or undo it and report why it failed.
+    ///
+    /// A process targeted more than once is injected once.
+    pub fn execute(self) -> std::result::Result<PlanReport, PlanError> {
+        let mut state = State::default();
+        match self.apply(&mut state) {
+            Ok(()) => Ok(state.into_report()),
+            Err((process, error)) => Err(PlanError {
+                process,
+                error,
+                rollback: state.roll_back(),
+            }),
+        }
+    }
+
+    fn apply(self, state: &mut State) -> std::result::Result<(), (Option<Process>, Error)> {
+        let fail = |error| (None, error);
+        if self.targets.is_empty() || self.libraries.is_empty() {
+            return Err(fail(Error::invalid_input(
+                "an injection plan needs at least one target and one library",
+            )));
+        }
+        self.options.validate().map_err(fail)?;
+        let backend = backend::default_backend().map_err(fail)?;
+        state.backend = Some(backend.clone());
+        let libraries = self
+            .libraries
+            .into_iter()
+            .map(|library| library.with_options(&self.options))
+            .collect::<Result<Vec<_>>>()
+            .map_err(fail)?;
+
+        for target in self.targets {
+            let processes = match target {
+                Target::Pid(pid) => vec![Process::from_pid(pid).map_err(fail)?],
+                Target::Process(process) => vec![process],
+                Target::Matching(matcher) => {
+                    let found = Process::find_all(matcher.clone()).map_err(fail)?;
+                    if found.is_empty() {
+                        return Err(fail(Error::new(
+                            ErrorKind::ProcessNotFound,
+                            format_args!("no process matching {matcher}"),
+                        )));
+                    }
+                    found
+                }
+                Target::Launch(program) => {
+                    let suspended = spawn_with(program, self.options.clone()).map_err(fail)?;
+                    let process = suspended.process();
+                    state.suspended.push(suspended);
+                    state.add_target(process, true);
+                    continue;
+                }
+            };
+            for process in processes {
+                state.add_target(process, false);
+            }
+        }
+
+        for index in 0..state.targets.len() {
+            let process = state.targets[index].process;
+            for library in &libraries {
+                let injected = backend
+                    .inject_process(process, library.clone(), &self.options)
+                    .map_err(|error| (Some(process), error))?;
+                state.targets[index].injections.push(injected.id());
+                state.injected.push(injected);
+            }
+        }
+
+        while !state.suspended.is_empty() {
+            let suspended = state.suspended.remove(0);
+            let process = suspended.process();
+            let child = suspended.resume().map_err(|error| (Some(process), error))?;
+            state.children.push((process, child));
+        }
+
+        if let Some(timeout) = self.options.ready_timeout_value() {
+            for injected in state.injected.entries() {
+                injected
+                    .wait_ready(timeout)
+                    .map_err(|error| (Some(injected.process()), error))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Progress of an executing plan, kept so it can be undone.
+#[derive(Default)]
+struct State {
+    backend: Option<backend::BackendHandle>,
+    targets: Vec<TargetReport>,
+    injected: InjectedSet,
+    suspended: Vec<SuspendedProgram>,
+    children: Vec<(Process, Child)>,
+}
+
+impl State {
+    fn add_target(&mut self, process: Process, launched: bool) {
+        if self.targets.iter().all(|target| target.process != process) {
+            self.targets.push(TargetReport {
+                process,
+                launched,
+                injections: Vec::new(),
+            });
+        }
+    }
+
+    fn into_report(self) -> PlanReport {
+        PlanReport {
+            targets: self.targets,
+            injected: self.injected,
+            children: self.children.into_iter().map(|(_, child)| child).collect(),
+        }
+    }
+
+    fn roll_back(self) -> Vec<(Process, Error)> {
+        let mut failures = match self.injected.force_uninject() {
+            Ok(()) => Vec::new(),
+            Err(err) => err.failures().to_vec(),
+        };
+        for suspended in self.suspended {
+            let process = suspended.process();
+            if let Err(err) = suspended.kill() {
+                failures.push((process, err));
+            }
+        }
+        if let Some(backend) = self.backend {
+            for (process, _) in self.children {
+                if let Err(err) = backend.kill(process) {
+                    failures.push((process, err));
+                }
+            }
+        }
+        failures
+    }
+}
+
+/// What an executed `InjectionPlan` did.
+#[derive(Debug)]
+pub struct PlanReport {
+    targets: Vec<TargetReport>,
+    injected: InjectedSet,
+    children: Vec<Child>,
+}
+
+impl PlanReport {
+    /// Each target, in the order it was added to the plan.
+    pub fn targets(&self) -> &[TargetReport] {
+        &self.targets
+    }
+
+    /// Take the injections, which uninject in reverse order (see
+    /// `InjectedSet`), and the launched programs.
+    pub fn into_parts(self) -> (InjectedSet, Vec<Child>) {
+        (self.injected, self.children)
+    }
+}
+
+/// One target of an executed `InjectionPlan`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetReport {
+    process: Process,
+    launched: bool,
+    injections: Vec<InjectionId>,
+}
+
+impl TargetReport {
+    /// The target process.
+    pub fn process(&self) -> Process {
+        self.process
+    }
+
+    /// Returns true if the plan launched the target.
+    pub fn launched(&self) -> bool {
+        self.launched
+    }
+
+    /// The injections made into the target, one per library, in plan order.
+    pub fn injections(&self) -> &[InjectionId] {
+        &self.injections
+    }
+}
+
+/// Why an `InjectionPlan` failed; everything it did was undone.
+#[derive(Debug)]
+pub struct PlanError {
+    process: Option<Process>,
+    error: Error,
+    rollback: Vec<(Process, Error)>,
+}
+
+impl PlanError {
+    /// The target being injected, resumed or waited for when the plan
+    /// failed, or `None` if it failed while resolving targets.
+    pub fn process(&self) -> Option<Process> {
+        self.process
+    }
+
+    /// The error that stopped the plan.
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+
+    /// Steps of the rollback that failed, leaving something behind.
+    pub fn rollback_failures(&self) -> &[(Process, Error)] {
+        &self.rollback
+    }
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("injection plan failed")?;
+        if let Some(process) = self.process {
+            write!(f, " at pid {}", process.pid())?;
+        }
+        write!(f, ": {}", self.error)?;
+        for (process, err) in &self.rollback {
+            write!(f, "; rollback failed for pid {}: {err}", process.pid())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for PlanError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<PlanError> for Error {
+    fn from(err: PlanError) -> Self {
+        Error::new(err.error.kind(), &err).with_source(err)
+    }
+}
@@ -1,9 +1,22 @@
-use crate::{Error, Result};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::clock::poll_until;
+use crate::maps::{self, MemoryRegion};
+use crate::{Architecture, Clock, Error, Result, SystemClock, arch};
+
+// How often `Process::wait_for` rescans the process list.
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+// How often `wait_exit` checks whether the process is still running.
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 /// Handle to a target process.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Process {
     pid: i32,
+    // The remote device the process runs on; 0 for this machine.
+    device: u32,
 }
 
 impl Process {
@@ -16,7 +29,7 @@ impl Process {
     /// let process = unsafe { Process::from_pid_unchecked(1234) };
     /// ```
     pub unsafe fn from_pid_unchecked(pid: i32) -> Process {
-        Process { pid }
+        Process { pid, device: 0 }
     }
 
     /// Create a process handle after verifying the PID exists.
@@ -37,7 +50,7 @@ impl Process {
         }
 
         if process_exists(pid)? {
-            Ok(Process { pid })
+            Ok(Process { pid, device: 0 })
         } else {
             Err(Error::process_not_found(pid))
         }
@@ -47,6 +60,295 @@ impl Process {
     pub fn pid(&self) -> i32 {
         self.pid
     }
+
+    /// Whether the process runs on a remote or USB device (see
+    /// `Injector::remote` and `Injector::usb`) rather than this machine.
+    ///
+    /// Inspecting a remote process (its memory maps, architecture, whether
+    /// an agent is loaded or has exited) fails with
+    /// `ErrorKind::NotSupported`.
+    pub fn is_remote(&self) -> bool {
+        self.device != 0
+    }
+
+    /// The same pid on remote device `device`.
+    #[cfg(feature = "frida")]
+    pub(crate) fn on_device(self, device: u32) -> Process {
+        Process { device, ..self }
+    }
+
+    /// Fail unless the process runs on this machine.
+    pub(crate) fn check_local(&self) -> Result<()> {
+        if self.is_remote() {
+            return Err(Error::not_supported(format_args!(
+                "pid {} runs on a remote device and cannot be inspected from here",
+                self.pid
+            )));
+        }
+        Ok(())
+    }
+
+    /// List the memory regions mapped in the process.
+    ///
+    /// Reads `/proc/<pid>/maps` on Linux, walks `mach_vm_region` on macOS
+    /// (requires `task_for_pid` rights), and `VirtualQueryEx` on Windows,
+    /// where only committed regions are reported.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use hook_inject::Process;
+    /// let process = Process::from_pid(1234)?;
+    /// for region in process.memory_maps()? {
+    ///     println!("{:#x}-{:#x} {:?}", region.start(), region.end(), region.path());
+    /// }
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn memory_maps(&self) -> Result<Vec<MemoryRegion>> {
+        self.check_local()?;
+        maps::memory_maps(self.pid)
+    }
+
+    /// CPU architecture the process is running as.
+    ///
+    /// This reflects emulation layers: a 32-bit process under WOW64 reports
+    /// `Architecture::X86`, and an x86_64 process under Rosetta 2 reports
+    /// `Architecture::X86_64`.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::Process;
+    ///
+    /// let process = Process::from_pid(1234)?;
+    /// println!("pid 1234 runs as {}", process.architecture()?);
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn architecture(&self) -> Result<Architecture> {
+        self.check_local()?;
+        arch::process_architecture(self.pid)
+    }
+
+    /// List the running processes matching `matcher`, ordered by PID.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::Process;
+    ///
+    /// for process in Process::find_all("nginx")? {
+    ///     println!("{}", process.pid());
+    /// }
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn find_all(matcher: impl Into<ProcessMatcher>) -> Result<Vec<Process>> {
+        let matcher = matcher.into();
+        let mut pids: Vec<i32> = list_processes()?
+            .into_iter()
+            .filter(|entry| matcher.matches(entry))
+            .map(|entry| entry.pid)
+            .collect();
+        pids.sort_unstable();
+        Ok(pids
+            .into_iter()
+            .map(|pid| Process { pid, device: 0 })
+            .collect())
+    }
+
+    /// Block until a process matching `matcher` appears, or `timeout` elapses.
+    ///
+    /// The process list is polled, so a target that starts and exits between
+    /// polls can be missed. If several processes match, the lowest PID wins.
+    /// Fails with an error for which `Error::is_timed_out` is true if nothing
+    /// matched in time.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use hook_inject::{inject_process, Library, Process};
+    ///
+    /// let process = Process::wait_for("target-app", Duration::from_secs(30))?;
+    /// let injected = inject_process(process, Library::from_path("/path/to/libagent.so")?)?;
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn wait_for(matcher: impl Into<ProcessMatcher>, timeout: Duration) -> Result<Process> {
+        Self::wait_for_with_clock(matcher, timeout, &SystemClock)
+    }
+
+    /// Like `wait_for`, measuring the timeout with the given clock.
+    pub fn wait_for_with_clock(
+        matcher: impl Into<ProcessMatcher>,
+        timeout: Duration,
+        clock: &dyn Clock,
+    ) -> Result<Process> {
+        let matcher = matcher.into();
+        let found = poll_until(clock, Some(timeout), WAIT_POLL_INTERVAL, || {
+            Ok(list_processes()?
+                .into_iter()
+                .filter(|entry| matcher.matches(entry))
+                .map(|entry| entry.pid)
+                .min())
+        })?;
+        match found {
+            Some(pid) => Ok(Process { pid, device: 0 }),
+            None => Err(Error::timed_out(format_args!(
+                "no process matching {matcher} appeared within {timeout:?}"
+            ))),
+        }
+    }
+}
+
+/// Selects processes for `Process::wait_for` and `SpawnGate`.
+///
+/// Strings convert to `ProcessMatcher::name`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessMatcher {
+    kind: MatcherKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MatcherKind {
+    Name(String),
+    Path(PathBuf),
+    Pattern(String),
+}
+
+impl ProcessMatcher {
+    /// Match by executable file name, e.g. `"nginx"` or `"notepad.exe"`.
+    ///
+    /// On Windows the comparison ignores ASCII case.
+    pub fn name(name: impl Into<String>) -> Self {
+        Self {
+            kind: MatcherKind::Name(name.into()),
+        }
+    }
+
+    /// Match by full executable path.
+    ///
+    /// Processes whose executable path cannot be read (e.g. owned by another
+    /// user) never match.
+    pub fn path(path: impl Into<PathBuf>) -> Self {
+        Self {
+            kind: MatcherKind::Path(path.into()),
+        }
+    }
+
+    /// Match the executable path or file name against a glob pattern.
+    ///
+    /// `*` matches any run of characters (including `/`) and `?` matches
+    /// exactly one, so `"*/helper-*"` selects every `helper-` binary and
+    /// `"crash?handler"` matches a bare name.
+    ///
+    /// # Examples
+    /// ```
+    /// use hook_inject::ProcessMatcher;
+    ///
+    /// let matcher = ProcessMatcher::pattern("*/bin/helper-*");
+    /// assert_eq!(matcher.to_string(), "pattern `*/bin/helper-*`");
+    /// ```
+    pub fn pattern(pattern: impl Into<String>) -> Self {
+        Self {
+            kind: MatcherKind::Pattern(pattern.into()),
+        }
+    }
+
+    /// Match a program announced by the backend, identified by path or name.
+    pub(crate) fn matches_program(&self, pid: i32, identifier: &str) -> bool {
+        let path = PathBuf::from(identifier);
+        self.matches(&ProcessEntry {
+            pid,
+            name: None,
+            path: Some(path),
+        })
+    }
+
+    fn matches(&self, entry: &ProcessEntry) -> bool {
+        match &self.kind {
+            MatcherKind::Name(name) => {
+                let exe_name = entry.path.as_deref().and_then(Path::file_name);
+                [exe_name, entry.name.as_deref().map(OsStr::new)]
+                    .into_iter()
+                    .flatten()
+                    .any(|candidate| name_eq(candidate, name))
+            }
+            MatcherKind::Path(path) => entry.path.as_deref() == Some(path.as_path()),
+            MatcherKind::Pattern(pattern) => {
+                let exe_name = entry.path.as_deref().and_then(Path::file_name);
+                [
+                    entry.path.as_deref().map(Path::as_os_str),
+                    exe_name,
+                    entry.name.as_deref().map(OsStr::new),
+                ]
+                .into_iter()
+                .flatten()
+                .any(|candidate| glob_match(pattern, &candidate.to_string_lossy()))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ProcessMatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            MatcherKind::Name(name) => write!(f, "name `{name}`"),
+            MatcherKind::Path(path) => write!(f, "path `{}`", path.display()),
+            MatcherKind::Pattern(pattern) => write!(f, "pattern `{pattern}`"),
+        }
+    }
+}
+
+impl From<&str> for ProcessMatcher {
+    fn from(name: &str) -> Self {
+        ProcessMatcher::name(name)
+    }
+}
+
+impl From<String> for ProcessMatcher {
+    fn from(name: String) -> Self {
+        ProcessMatcher::name(name)
+    }
+}
+
+// Iterative wildcard match with single-star backtracking.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn name_eq(candidate: &OsStr, name: &str) -> bool {
+    if cfg!(windows) {
+        candidate.eq_ignore_ascii_case(name)
+    } else {
+        candidate == name
+    }
+}
+
+/// A running process as seen by the platform's process listing.
+struct ProcessEntry {
+    pid: i32,
+    // Short name reported by the OS (e.g. `comm` on Linux), if any.
+    name: Option<String>,
+    path: Option<PathBuf>,
 }
 
 impl TryFrom<i32> for Process {
@@ -57,6 +359,95 @@ impl TryFrom<i32> for Process {
     }
 }
 
+/// When `pid` started, in clock ticks since boot, to tell it apart from a
+/// later process reusing the pid. `None` where it cannot be read.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn start_time(pid: i32) -> Option<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // The command name may contain spaces; fields resume after its `)`.
+    let fields = &stat[stat.rfind(')')? + 1..];
+    // `starttime` is field 22; the first field after the name is 3.
+    fields.split_whitespace().nth(19)?.parse().ok()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn start_time(_pid: i32) -> Option<u64> {
+    None
+}
+
+/// Block until `pid`, started at `start_time`, exits, or `timeout` passes.
+/// Returns whether it exited.
+///
+/// Polls, so it works for any process, not just children of this one.
+pub(crate) fn wait_exit(
+    pid: i32,
+    start_time: Option<u64>,
+    timeout: Option<Duration>,
+    clock: &dyn Clock,
+) -> bool {
+    poll_until(clock, timeout, EXIT_POLL_INTERVAL, || {
+        Ok(has_exited(pid, start_time).then_some(()))
+    })
+    .is_ok_and(|exited| exited.is_some())
+}
+
+/// Run `callback` on a new thread once `pid`, started at `start_time`, exits.
+pub(crate) fn on_exit(
+    pid: i32,
+    start_time: Option<u64>,
+    callback: impl FnOnce() + Send + 'static,
+) -> Result<()> {
+    std::thread::Builder::new()
+        .name("hook-inject-exit".into())
+        .spawn(move || {
+            wait_exit(pid, start_time, None, &SystemClock);
+            callback();
+        })?;
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn has_exited(pid: i32, start_time: Option<u64>) -> bool {
+    let Ok(stat) = std::fs::read_to_string(format!("/proc/{pid}/stat")) else {
+        return true;
+    };
+    let Some(fields) = stat.rfind(')').map(|end| &stat[end + 1..]) else {
+        return false;
+    };
+    let mut fields = fields.split_whitespace();
+    // A zombie has exited; it is only waiting for its parent to reap it.
+    if matches!(fields.next(), Some("Z" | "X")) {
+        return true;
+    }
+    start_time.is_some_and(|start| fields.nth(18).and_then(|f| f.parse().ok()) != Some(start))
+}
+
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+pub(crate) fn has_exited(pid: i32, _start_time: Option<u64>) -> bool {
+    matches!(process_exists(pid), Ok(false))
+}
+
+#[cfg(windows)]
+pub(crate) fn has_exited(pid: i32, _start_time: Option<u64>) -> bool {
+    use windows_sys::Win32::Foundation::{
+        CloseHandle, ERROR_ACCESS_DENIED, GetLastError, HANDLE, STILL_ACTIVE,
+    };
+    use windows_sys::Win32::System::Threading::{
+        GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    // An exited process stays openable while anyone holds a handle to it,
+    // so ask for its exit code instead.
+    let handle: HANDLE = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid as u32) };
+    if handle.is_null() {
+        return unsafe { GetLastError() } != ERROR_ACCESS_DENIED;
+    }
+    let mut code = 0u32;
+    let ok = unsafe { GetExitCodeProcess(handle, &mut code) };
+    unsafe { CloseHandle(handle) };
+    ok != 0 && code != STILL_ACTIVE as u32
+}
+
 #[cfg(unix)]
 fn process_exists(pid: i32) -> Result<bool> {
     use libc::kill;
@@ -103,3 +494,130 @@ fn process_exists(pid: i32) -> Result<bool> {
 
     Ok(false)
 }
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn list_processes() -> Result<Vec<ProcessEntry>> {
+    let mut entries = Vec::new();
+    for dirent in std::fs::read_dir("/proc")? {
+        let Ok(dirent) = dirent else { continue };
+        let Some(pid) = dirent
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<i32>().ok())
+        else {
+            continue;
+        };
+
+        // Processes can exit mid-scan and other users' `exe` links are
+        // unreadable; keep whatever is available.
+        let name = std::fs::read_to_string(dirent.path().join("comm"))
+            .ok()
+            .map(|comm| comm.trim_end_matches('\n').to_owned());
+        let path = std::fs::read_link(dirent.path().join("exe")).ok();
+        entries.push(ProcessEntry { pid, name, path });
+    }
+    Ok(entries)
+}
+
+#[cfg(target_os = "macos")]
+fn list_processes() -> Result<Vec<ProcessEntry>> {
+    use std::ffi::c_void;
+    use std::os::unix::ffi::OsStrExt;
+
+    let count = unsafe { libc::proc_listallpids(std::ptr::null_mut(), 0) };
+    if count < 0 {
+        return Err(Error::from(std::io::Error::last_os_error()));
+    }
+
+    // Leave headroom for processes started between the two calls.
+    let mut pids = vec![0 as libc::pid_t; count as usize + 64];
+    let size = (pids.len() * std::mem::size_of::<libc::pid_t>()) as libc::c_int;
+    let count = unsafe { libc::proc_listallpids(pids.as_mut_ptr() as *mut c_void, size) };
+    if count < 0 {
+        return Err(Error::from(std::io::Error::last_os_error()));
+    }
+    pids.truncate(count as usize);
+
+    let mut entries = Vec::with_capacity(pids.len());
+    for pid in pids {
+        let mut buf = vec![0u8; libc::PROC_PIDPATHINFO_MAXSIZE as usize];
+        let len =
+            unsafe { libc::proc_pidpath(pid, buf.as_mut_ptr() as *mut c_void, buf.len() as u32) };
+        let path = (len > 0).then(|| PathBuf::from(OsStr::from_bytes(&buf[..len as usize])));
+
+        let len =
+            unsafe { libc::proc_name(pid, buf.as_mut_ptr() as *mut c_void, buf.len() as u32) };
+        let name = (len > 0).then(|| String::from_utf8_lossy(&buf[..len as usize]).into_owned());
+
+        entries.push(ProcessEntry { pid, name, path });
+    }
+    Ok(entries)
+}
+
+#[cfg(windows)]
+fn list_processes() -> Result<Vec<ProcessEntry>> {
+    use std::os::windows::ffi::OsStringExt;
+    use windows_sys::Win32::Foundation::{CloseHandle, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, PROCESSENTRY32W, Process32FirstW, Process32NextW,
+        TH32CS_SNAPPROCESS,
+    };
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+        QueryFullProcessImageNameW,
+    };
+
+    let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) };
+    if snapshot == INVALID_HANDLE_VALUE {
+        return Err(Error::from(std::io::Error::last_os_error()));
+    }
+
+    let mut entries = Vec::new();
+    let mut entry = PROCESSENTRY32W {
+        dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
+        ..Default::default()
+    };
+    let mut more = unsafe { Process32FirstW(snapshot, &mut entry) } != 0;
+    while more {
+        let pid = entry.th32ProcessID;
+        let len = entry
+            .szExeFile
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(entry.szExeFile.len());
+        let name = String::from_utf16_lossy(&entry.szExeFile[..len]);
+
+        let handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+        let path = (!handle.is_null()).then(|| {
+            let mut buf = [0u16; 1024];
+            let mut size = buf.len() as u32;
+            let ok = unsafe {
+                QueryFullProcessImageNameW(handle, PROCESS_NAME_WIN32, buf.as_mut_ptr(), &mut size)
+            };
+            unsafe { CloseHandle(handle) };
+            (ok != 0).then(|| PathBuf::from(std::ffi::OsString::from_wide(&buf[..size as usize])))
+        });
+
+        entries.push(ProcessEntry {
+            pid: pid as i32,
+            name: Some(name),
+            path: path.flatten(),
+        });
+        more = unsafe { Process32NextW(snapshot, &mut entry) } != 0;
+    }
+
+    unsafe { CloseHandle(snapshot) };
+    Ok(entries)
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    windows
+)))]
+fn list_processes() -> Result<Vec<ProcessEntry>> {
+    Err(Error::not_supported(
+        "process enumeration is not supported on this platform",
+    ))
+}
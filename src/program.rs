@@ -1,8 +1,11 @@
 use std::ffi::OsStr;
+use std::io::{self, Write};
 use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-use crate::Process;
+use crate::backend::BackendHandle;
+use crate::{Error, Process, Result};
 
 // Note: not every `Command` setting is honored by Frida's spawn API. We capture
 // program, args, env, cwd, and stdio for injection purposes.
@@ -20,32 +23,95 @@ use crate::Process;
 /// program.arg("--version");
 /// ```
 ///
-/// Converting from `Command` captures program, args, env, and cwd; stdio defaults to `Inherit`
-/// for Frida launches, so call `.stdio()` if you need `Null` or `Pipe` there.
+/// A `Program` converted from a `Command` is launched by the `Command` itself, as with
+/// `inject_command`, so its stdio, per-stream or file-backed, is kept as set. Remote devices launch
+/// programs through frida-server and refuse converted `Command`s; build a `Program` instead.
+///
+/// Like `Command`, the launched program inherits the injector's environment by default, with
+/// variables set or removed via `env`/`env_remove` applied on top. A converted `Command` launches
+/// with its own environment, `Command::env_clear` included, though `inherits_env` cannot report
+/// that.
 #[derive(Debug)]
 pub struct Program {
     cmd: Command,
     stdio: Stdio,
+    env_inherit: bool,
+    resolve_path: bool,
+    pre_exec: bool,
+    suspend_at: Option<SuspendPoint>,
+    // Set for `Command`s, which are always launched by themselves.
+    force_native: bool,
+}
+
+/// Where a spawned program is held until it is injected or resumed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum SuspendPoint {
+    /// Right after `exec`, at the dynamic loader's entry, before any
+    /// dependency is loaded or any constructor runs.
+    ///
+    /// Supported on Linux and macOS; the program is launched natively and
+    /// held with `SIGSTOP`.
+    LoaderEntry,
+    /// At the executable's entrypoint, once the loader has mapped and
+    /// initialized its dependencies but before `main`.
+    ProgramEntry,
 }
 
 /// How to configure the child process stdio.
-#[derive(Clone, Debug, Copy)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum Stdio {
     /// Inherit parent stdio handles.
     Inherit,
     /// Redirect stdio to `/dev/null` (or equivalent).
     Null,
-    /// Create pipes for stdio (pipe handles are exposed by `Command::spawn`).
+    /// Create pipes for stdio.
+    ///
+    /// On the launch path only stdin is exposed (see `Child::take_stdin`);
+    /// spawn with `Command` if you need to read output.
     Pipe,
 }
 
 impl Program {
     /// Create a new launch specification.
     pub fn new<P: AsRef<OsStr>>(program: P) -> Self {
-        Self {
-            cmd: Command::new(program),
-            stdio: Stdio::Inherit,
-        }
+        Program::with_command(Command::new(program))
+    }
+
+    /// Parse a shell-style command string into a program and its arguments.
+    ///
+    /// Quoting and escaping follow POSIX shell word-splitting rules; no
+    /// expansion (variables, globs, redirections) is performed.
+    ///
+    /// # Examples
+    /// ```
+    /// use hook_inject::Program;
+    ///
+    /// let program = Program::from_shell("prog --flag 'quoted arg'")?;
+    /// assert_eq!(program.get_program(), "prog");
+    /// assert_eq!(program.get_args().collect::<Vec<_>>(), ["--flag", "quoted arg"]);
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn from_shell(command: &str) -> Result<Self> {
+        let words = shlex::split(command)
+            .ok_or_else(|| Error::invalid_input("command has unbalanced quotes or escapes"))?;
+        let mut words = words.into_iter();
+        let program = words
+            .next()
+            .ok_or_else(|| Error::invalid_input("command is empty"))?;
+
+        let mut spec = Program::new(program);
+        spec.args(words);
+        Ok(spec)
     }
 
     /// Set stdio mode for the launched process.
@@ -74,10 +140,198 @@ impl Program {
         self
     }
 
+    /// Return the stdio mode set with `Program::stdio`.
+    ///
+    /// A converted `Command`'s own stdio is kept but not reported here.
+    ///
+    /// # Examples
+    /// ```
+    /// use hook_inject::{Program, Stdio};
+    /// use std::process::{Command, Stdio as StdStdio};
+    ///
+    /// let mut cmd = Command::new("/usr/bin/true");
+    /// cmd.stdout(StdStdio::null());
+    /// assert_eq!(Program::from(cmd).stdio_mode(), Stdio::Inherit);
+    /// assert_eq!(Program::new("/usr/bin/true").stdio(Stdio::Null).stdio_mode(), Stdio::Null);
+    /// ```
+    pub fn stdio_mode(&self) -> Stdio {
+        self.stdio
+    }
+
+    /// Choose whether the launched program inherits the injector's environment.
+    ///
+    /// When disabled, only variables set explicitly via `env`/`envs` are passed.
+    ///
+    /// # Examples
+    /// ```
+    /// use hook_inject::Program;
+    ///
+    /// let mut program = Program::new("/usr/bin/env").env_inherit(false);
+    /// program.env("ONLY_THIS", "1");
+    /// assert!(!program.inherits_env());
+    /// ```
+    pub fn env_inherit(mut self, inherit: bool) -> Self {
+        self.env_inherit = inherit;
+        self
+    }
+
+    /// Clear the inherited environment, like `Command::env_clear`.
+    ///
+    /// Variables set after this call are still passed to the program.
+    pub fn env_clear(&mut self) -> &mut Self {
+        self.cmd.env_clear();
+        self.env_inherit = false;
+        self
+    }
+
+    /// Returns true if the launched program inherits the injector's environment.
+    ///
+    /// `Command::env_clear` on a converted `Command` is honored at launch,
+    /// but not reported here, since `Command` does not report it.
+    pub fn inherits_env(&self) -> bool {
+        self.env_inherit
+    }
+
+    /// Choose whether a bare program name is looked up in `PATH` before launch.
+    ///
+    /// Enabled by default to match `std::process::Command`. Frida's spawn API
+    /// needs a path to the executable, so `Program::new("python3")` is resolved
+    /// using the program's own `PATH` override if set, otherwise the
+    /// injector's. Names containing a path separator are used as-is.
+    pub fn resolve_path(mut self, resolve: bool) -> Self {
+        self.resolve_path = resolve;
+        self
+    }
+
+    /// Return the executable path that will be launched.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::Program;
+    ///
+    /// let path = Program::new("sh").resolved_program()?;
+    /// assert!(path.is_absolute());
+    /// # Ok::<(), hook_inject::Error>(())
+    /// ```
+    pub fn resolved_program(&self) -> Result<PathBuf> {
+        let program = self.cmd.get_program();
+        if !self.resolve_path || Path::new(program).components().count() != 1 {
+            return Ok(PathBuf::from(program));
+        }
+
+        let path_var = self
+            .cmd
+            .get_envs()
+            .find(|(key, _)| path_key(key))
+            .map(|(_, value)| value.map(OsStr::to_os_string))
+            .unwrap_or_else(|| std::env::var_os("PATH"));
+
+        path_var
+            .as_deref()
+            .and_then(|paths| find_in_path(program, paths))
+            .ok_or_else(|| {
+                Error::invalid_input(format_args!(
+                    "program not found in PATH: {}",
+                    program.to_string_lossy()
+                ))
+            })
+    }
+
+    /// Schedule a closure to run in the child after `fork` and before `exec`.
+    ///
+    /// Frida's spawn API cannot run caller code in the child, so programs with
+    /// `pre_exec` hooks are launched with `std::process::Command` instead and
+    /// held (stopped) right after `exec` until they are injected or resumed.
+    /// This is supported on Linux and macOS; other platforms return
+    /// `Error::not_supported` at launch.
+    ///
+    /// Hooks set on a `Command` before converting it into a `Program` cannot
+    /// be detected; set them through this method.
+    ///
+    /// # Safety
+    /// The same requirements as `std::os::unix::process::CommandExt::pre_exec`
+    /// apply: the closure runs in a forked child and must only perform
+    /// async-signal-safe operations.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use hook_inject::Program;
+    ///
+    /// let mut program = Program::new("/usr/bin/true");
+    /// unsafe {
+    ///     program.pre_exec(|| {
+    ///         // e.g. setrlimit / drop capabilities here.
+    ///         Ok(())
+    ///     });
+    /// }
+    /// ```
+    #[cfg(unix)]
+    pub unsafe fn pre_exec<F>(&mut self, f: F) -> &mut Self
+    where
+        F: FnMut() -> std::io::Result<()> + Send + Sync + 'static,
+    {
+        use std::os::unix::process::CommandExt;
+
+        unsafe { self.cmd.pre_exec(f) };
+        self.pre_exec = true;
+        self
+    }
+
+    /// Choose where `spawn`/`inject_program` hold the program before it runs.
+    ///
+    /// When unset, Frida's default spawn point is used, except that programs
+    /// with `pre_exec` hooks are held at `SuspendPoint::LoaderEntry`. A point
+    /// the launch path cannot honor fails with `Error::not_supported` at
+    /// launch instead of holding the program somewhere else.
+    ///
+    /// # Examples
+    /// ```
+    /// use hook_inject::{Program, SuspendPoint};
+    ///
+    /// let program = Program::new("/usr/bin/true").suspend_at(SuspendPoint::LoaderEntry);
+    /// assert_eq!(program.suspend_point(), Some(SuspendPoint::LoaderEntry));
+    /// ```
+    pub fn suspend_at(mut self, point: SuspendPoint) -> Self {
+        self.suspend_at = Some(point);
+        self
+    }
+
+    /// Return the requested suspension point, if any.
+    pub fn suspend_point(&self) -> Option<SuspendPoint> {
+        self.suspend_at
+    }
+
+    /// Returns true if launching requires the native (non-Frida) spawn path.
+    pub(crate) fn needs_native_spawn(&self) -> bool {
+        self.pre_exec || self.force_native || self.suspend_at == Some(SuspendPoint::LoaderEntry)
+    }
+
+    /// `cmd` as `inject_command` and `From<Command>` launch it: by the
+    /// `Command` itself, with every setting it has, stdio included.
+    pub(crate) fn native(cmd: Command) -> Self {
+        Program {
+            force_native: true,
+            ..Program::with_command(cmd)
+        }
+    }
+
+    fn with_command(cmd: Command) -> Self {
+        Program {
+            cmd,
+            stdio: Stdio::Inherit,
+            env_inherit: true,
+            resolve_path: true,
+            pre_exec: false,
+            suspend_at: None,
+            force_native: false,
+        }
+    }
+
     pub(crate) fn stdio_value(&self) -> Stdio {
         self.stdio
     }
 
+    #[cfg(feature = "frida")]
     pub(crate) fn command(&self) -> &Command {
         &self.cmd
     }
@@ -90,11 +344,148 @@ impl Program {
 
 impl From<Command> for Program {
     fn from(cmd: Command) -> Self {
-        Program {
-            cmd,
-            stdio: Stdio::Inherit,
+        // Frida's spawn API would drop the `Command`'s stdio, which cannot
+        // be read back from it.
+        Program::native(cmd)
+    }
+}
+
+fn path_key(key: &OsStr) -> bool {
+    if cfg!(windows) {
+        key.eq_ignore_ascii_case("PATH")
+    } else {
+        key == "PATH"
+    }
+}
+
+fn find_in_path(program: &OsStr, paths: &OsStr) -> Option<PathBuf> {
+    for dir in std::env::split_paths(paths) {
+        // Empty PATH entries mean the current directory.
+        let dir = if dir.as_os_str().is_empty() {
+            PathBuf::from(".")
+        } else {
+            dir
+        };
+
+        for candidate in executable_candidates(&dir.join(program)) {
+            if is_executable(&candidate) {
+                return Some(candidate);
+            }
         }
     }
+
+    None
+}
+
+#[cfg(windows)]
+fn executable_candidates(path: &Path) -> Vec<PathBuf> {
+    if path.extension().is_some() {
+        return vec![path.to_path_buf()];
+    }
+
+    let exts = std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".into());
+    exts.split(';')
+        .filter(|ext| !ext.is_empty())
+        .map(|ext| {
+            let mut name = path.as_os_str().to_os_string();
+            name.push(ext);
+            PathBuf::from(name)
+        })
+        .collect()
+}
+
+#[cfg(not(windows))]
+fn executable_candidates(path: &Path) -> Vec<PathBuf> {
+    vec![path.to_path_buf()]
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::metadata(path)
+        .map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+// === Config deserialization ===
+
+/// Launch spec as it appears in config files.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProgramSpec {
+    program: String,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    env: std::collections::BTreeMap<String, String>,
+    #[serde(default)]
+    env_remove: Vec<String>,
+    #[serde(default = "default_true")]
+    env_inherit: bool,
+    cwd: Option<PathBuf>,
+    #[serde(default = "default_stdio")]
+    stdio: Stdio,
+    #[serde(default = "default_true")]
+    resolve_path: bool,
+    suspend_at: Option<SuspendPoint>,
+}
+
+#[cfg(feature = "serde")]
+fn default_true() -> bool {
+    true
+}
+
+#[cfg(feature = "serde")]
+fn default_stdio() -> Stdio {
+    Stdio::Inherit
+}
+
+/// Deserialize a launch spec (requires the `serde` feature).
+///
+/// Recognized fields: `program` (required), `args`, `env`, `env_remove`,
+/// `env_inherit`, `cwd`, `stdio` (`"inherit"`, `"null"`, or `"pipe"`),
+/// `resolve_path`, and `suspend_at` (`"loader_entry"` or `"program_entry"`).
+///
+/// ```toml
+/// program = "python3"
+/// args = ["-m", "http.server"]
+/// cwd = "/srv/www"
+/// stdio = "null"
+///
+/// [env]
+/// PYTHONUNBUFFERED = "1"
+/// ```
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Program {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let spec = ProgramSpec::deserialize(deserializer)?;
+
+        let mut program = Program::new(spec.program)
+            .stdio(spec.stdio)
+            .resolve_path(spec.resolve_path);
+        if !spec.env_inherit {
+            program.env_clear();
+        }
+        program.args(spec.args).envs(spec.env);
+        for key in spec.env_remove {
+            program.env_remove(key);
+        }
+        if let Some(cwd) = spec.cwd {
+            program.current_dir(cwd);
+        }
+        program.suspend_at = spec.suspend_at;
+
+        Ok(program)
+    }
 }
 
 impl From<&OsStr> for Program {
@@ -131,22 +522,135 @@ impl DerefMut for Program {
 
 /// Opaque handle to a launched process spawned by the injector.
 ///
-/// This exists for API stability; it intentionally exposes no child-style
-/// methods until Frida exposes the necessary handles.
+/// This exists for API stability; beyond its stdio pipes it intentionally
+/// exposes no child-style methods until Frida exposes the necessary handles.
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct Child {
     pid: i32,
     stdio: Stdio,
+    stdin: Option<ChildStdin>,
+    // Set when the program was launched natively; kept so it can be reaped.
+    native: Option<std::process::Child>,
     _priv: (),
 }
 
 impl Child {
-    pub(crate) fn new(process: Process, stdio: Stdio) -> Self {
+    pub(crate) fn new(backend: BackendHandle, process: Process, stdio: Stdio) -> Self {
+        let stdin = match stdio {
+            Stdio::Pipe => Some(ChildStdin {
+                inner: StdinInner::Frida { backend, process },
+            }),
+            Stdio::Inherit | Stdio::Null => None,
+        };
+
         Self {
             pid: process.pid(),
             stdio,
+            stdin,
+            native: None,
             _priv: (),
         }
     }
+
+    pub(crate) fn with_native(mut self, native: Option<std::process::Child>) -> Self {
+        if let Some(mut native) = native {
+            // Natively spawned programs have real pipes; prefer them.
+            self.stdin = native.stdin.take().map(|stdin| ChildStdin {
+                inner: StdinInner::Native(stdin),
+            });
+            self.native = Some(native);
+        }
+        self
+    }
+
+    /// Take the writable stdin handle, if the program was launched with
+    /// `Stdio::Pipe`.
+    ///
+    /// Returns `None` for other stdio modes or if the handle was already taken.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::io::Write;
+    /// use hook_inject::{inject_program, Library, Program, Stdio};
+    ///
+    /// let program = Program::new("/usr/bin/cat").stdio(Stdio::Pipe);
+    /// let library = Library::from_path("/path/to/libagent.so")?;
+    /// let mut injected = inject_program(program, library)?;
+    /// if let Some(mut stdin) = injected.child_mut().take_stdin() {
+    ///     stdin.write_all(b"hello\n")?;
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn take_stdin(&mut self) -> Option<ChildStdin> {
+        self.stdin.take()
+    }
+
+    /// Take the stdout pipe of a program launched natively with piped stdout
+    /// (e.g. through `inject_command`).
+    ///
+    /// Frida launches do not expose output pipes, so this returns `None` for
+    /// them.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::io::Read;
+    /// use std::process::{Command, Stdio};
+    /// use hook_inject::{inject_command, Library};
+    ///
+    /// let mut cmd = Command::new("/usr/bin/env");
+    /// cmd.stdout(Stdio::piped());
+    /// let mut injected = inject_command(cmd, Library::from_path("/path/to/libagent.so")?)?;
+    /// let mut output = String::new();
+    /// injected.child_mut().take_stdout().unwrap().read_to_string(&mut output)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn take_stdout(&mut self) -> Option<std::process::ChildStdout> {
+        self.native.as_mut()?.stdout.take()
+    }
+
+    /// Take the stderr pipe of a program launched natively with piped stderr.
+    ///
+    /// Frida launches do not expose output pipes, so this returns `None` for
+    /// them.
+    pub fn take_stderr(&mut self) -> Option<std::process::ChildStderr> {
+        self.native.as_mut()?.stderr.take()
+    }
+}
+
+/// Writable handle to the stdin of a launched program.
+///
+/// For programs spawned through Frida, each write is forwarded with Frida's
+/// input API; dropping the handle does not close the target's stdin.
+#[derive(Debug)]
+pub struct ChildStdin {
+    inner: StdinInner,
+}
+
+#[derive(Debug)]
+enum StdinInner {
+    Frida {
+        backend: BackendHandle,
+        process: Process,
+    },
+    Native(std::process::ChildStdin),
+}
+
+impl Write for ChildStdin {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            StdinInner::Frida { backend, process } => {
+                backend.input(*process, buf).map_err(io::Error::other)?;
+                Ok(buf.len())
+            }
+            StdinInner::Native(stdin) => stdin.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.inner {
+            StdinInner::Frida { .. } => Ok(()),
+            StdinInner::Native(stdin) => stdin.flush(),
+        }
+    }
 }
@@ -0,0 +1,239 @@
+//! Injections made by this process that have not been uninjected.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+use crate::library::LibrarySource;
+use crate::{Error, Library, Process, Result, process};
+
+/// Identifies one injection made by this process.
+///
+/// Assigned by the injection runtime; `InjectedProcess::reinject` replaces
+/// it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InjectionId(u64);
+
+impl InjectionId {
+    pub(crate) fn new(id: u64) -> Self {
+        Self(id)
+    }
+
+    /// Return the runtime's numeric id.
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for InjectionId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.0, f)
+    }
+}
+
+/// A live injection, as listed by `injections`.
+#[derive(Debug, Clone)]
+pub struct Injection {
+    id: InjectionId,
+    process: Process,
+    library: Library,
+    label: Option<String>,
+    // False for injections reattached from a record: the runtime that made
+    // them, and monitors them, belongs to another process.
+    monitored: bool,
+}
+
+impl Injection {
+    /// Return the injection id.
+    pub fn id(&self) -> InjectionId {
+        self.id
+    }
+
+    /// Return the target process.
+    pub fn process(&self) -> Process {
+        self.process
+    }
+
+    /// Return the injected library.
+    pub fn library(&self) -> &Library {
+        &self.library
+    }
+
+    /// Return the label given with `InjectOptions::label`, if any.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+}
+
+// Every runtime, remote devices' included, hands out ids from a range of
+// its own, so the ids of all of them can key one map.
+static INJECTIONS: LazyLock<Mutex<BTreeMap<InjectionId, Injection>>> =
+    LazyLock::new(Default::default);
+
+/// List the injections this process made and has not uninjected or ejected,
+/// ordered by id.
+///
+/// Dropping a handle does not remove its injection, which stays live in the
+/// target. An agent that unloaded itself, or whose target exited, is still
+/// listed until its handle is uninjected; `InjectedProcess::is_loaded` tells
+/// them apart.
+///
+/// # Examples
+/// ```no_run
+/// for injection in hook_inject::injections() {
+///     println!("{} -> pid {}", injection.id(), injection.process().pid());
+/// }
+/// ```
+pub fn injections() -> Vec<Injection> {
+    lock().values().cloned().collect()
+}
+
+/// Find an injection this process made into `process` of the same image as
+/// `library`.
+pub(crate) fn find(process: Process, library: &Library) -> Option<Injection> {
+    lock()
+        .values()
+        .find(|injection| injection.process == process && injection.library.same_image(library))
+        .cloned()
+}
+
+pub(crate) fn insert(id: InjectionId, process: Process, library: &Library, label: Option<&str>) {
+    let injection = Injection {
+        id,
+        process,
+        library: library.clone(),
+        label: label.map(str::to_string),
+        monitored: true,
+    };
+    lock().insert(id, injection);
+}
+
+/// List an injection made by another process, failing if its id is taken.
+pub(crate) fn insert_reattached(
+    id: InjectionId,
+    process: Process,
+    library: &Library,
+    label: Option<&str>,
+) -> Result<()> {
+    let mut injections = lock();
+    if injections.contains_key(&id) {
+        return Err(Error::invalid_input(format_args!(
+            "injection {id} is already listed in this process"
+        )));
+    }
+    let injection = Injection {
+        id,
+        process,
+        library: library.clone(),
+        label: label.map(str::to_string),
+        monitored: false,
+    };
+    injections.insert(id, injection);
+    Ok(())
+}
+
+/// Unlist injection `id`, returning whether the runtime monitors it.
+pub(crate) fn remove(id: InjectionId) -> bool {
+    lock()
+        .remove(&id)
+        .is_none_or(|injection| injection.monitored)
+}
+
+/// What it takes to find an injection again from another process.
+///
+/// Made with `InjectedProcess::record` and turned back into a handle with
+/// `InjectedProcess::reattach`, e.g. by a CLI that injects in one invocation
+/// and ejects in a later one. With the `serde` feature it can be serialized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InjectionRecord {
+    id: u64,
+    pid: i32,
+    // Guards against the pid being reused by another process.
+    start_time: Option<u64>,
+    library: PathBuf,
+    configure_entrypoint: String,
+    unload_entrypoint: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    shutdown_entrypoint: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    label: Option<String>,
+}
+
+impl InjectionRecord {
+    pub(crate) fn new(
+        id: InjectionId,
+        process: Process,
+        library: &Library,
+        label: Option<&str>,
+    ) -> Result<Self> {
+        let LibrarySource::Path(path) = library.source() else {
+            return Err(Error::not_supported(
+                "only libraries injected from a file path can be recorded",
+            ));
+        };
+        let symbol = |symbol: &std::ffi::CStr| {
+            symbol
+                .to_str()
+                .map(str::to_string)
+                .map_err(|_| Error::not_supported("entrypoint names must be UTF-8 to be recorded"))
+        };
+        Ok(Self {
+            id: id.as_u64(),
+            pid: process.pid(),
+            start_time: process::start_time(process.pid()),
+            library: std::fs::canonicalize(path).unwrap_or_else(|_| path.clone()),
+            configure_entrypoint: symbol(library.configure_entrypoint())?,
+            unload_entrypoint: symbol(library.unload_entrypoint())?,
+            shutdown_entrypoint: library.shutdown_entrypoint().map(symbol).transpose()?,
+            label: label.map(str::to_string),
+        })
+    }
+
+    /// Return the recorded injection id.
+    pub fn id(&self) -> InjectionId {
+        InjectionId(self.id)
+    }
+
+    /// Return the recorded target pid.
+    pub fn pid(&self) -> i32 {
+        self.pid
+    }
+
+    /// Return the path of the injected library.
+    pub fn library_path(&self) -> &Path {
+        &self.library
+    }
+
+    /// Return the recorded label, if any.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// The target process, failing with `ErrorKind::ProcessNotFound` if it
+    /// exited (even if its pid was reused since).
+    pub(crate) fn process(&self) -> Result<Process> {
+        let process = Process::from_pid(self.pid)?;
+        if self.start_time.is_some() && process::start_time(self.pid) != self.start_time {
+            return Err(Error::process_not_found(self.pid));
+        }
+        Ok(process)
+    }
+
+    /// The injected library, with the entrypoints it was injected with.
+    pub(crate) fn library(&self) -> Result<Library> {
+        let library = Library::from_path(&self.library)?
+            .with_configure_entrypoint(self.configure_entrypoint.as_str())?
+            .with_unload_entrypoint(self.unload_entrypoint.as_str())?;
+        match &self.shutdown_entrypoint {
+            Some(symbol) => library.with_shutdown_entrypoint(symbol.as_str()),
+            None => Ok(library),
+        }
+    }
+}
+
+fn lock() -> std::sync::MutexGuard<'static, BTreeMap<InjectionId, Injection>> {
+    INJECTIONS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
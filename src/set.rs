@@ -0,0 +1,202 @@
+use std::fmt;
+
+use crate::{Error, InjectedProcess, Process};
+
+/// An ordered group of injected libraries that are ejected together.
+///
+/// Libraries are uninjected in reverse injection order, so an agent pushed
+/// after the loader it depends on is always unloaded first.
+///
+/// # Examples
+/// ```no_run
+/// use hook_inject::{inject_process, InjectedSet, Library, Process};
+///
+/// let process = Process::from_pid(1234)?;
+/// let mut set = InjectedSet::new();
+/// set.push(inject_process(process, Library::from_path("/path/to/libloader.so")?)?);
+/// set.push(inject_process(process, Library::from_path("/path/to/libplugin.so")?)?);
+///
+/// // Unloads libplugin, then libloader.
+/// set.uninject()?;
+/// # Ok::<(), hook_inject::Error>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct InjectedSet {
+    entries: Vec<InjectedProcess>,
+}
+
+impl InjectedSet {
+    /// Create an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an injected library; it will be ejected before everything already
+    /// in the set.
+    pub fn push(&mut self, injected: InjectedProcess) {
+        self.entries.push(injected);
+    }
+
+    /// Number of libraries in the set.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the set holds no libraries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub(crate) fn entries(&self) -> &[InjectedProcess] {
+        &self.entries
+    }
+
+    /// Target processes of the held libraries, in injection order.
+    pub fn processes(&self) -> impl Iterator<Item = Process> + '_ {
+        self.entries.iter().map(InjectedProcess::process)
+    }
+
+    /// Uninject every library in reverse injection order.
+    ///
+    /// Stops at the first failure: libraries injected before the failing one
+    /// may still be in use by it, so they are left loaded and returned, along
+    /// with the failing entry, in `UninjectError::into_remaining`.
+    pub fn uninject(self) -> Result<(), UninjectError> {
+        self.eject(false)
+    }
+
+    /// Uninject every library in reverse injection order, continuing past
+    /// failures.
+    ///
+    /// All failures are collected; only the entries that failed to uninject
+    /// are returned in `UninjectError::into_remaining`.
+    pub fn force_uninject(self) -> Result<(), UninjectError> {
+        self.eject(true)
+    }
+
+    fn eject(mut self, force: bool) -> Result<(), UninjectError> {
+        let mut failures = Vec::new();
+        let mut failed = Vec::new();
+
+        while let Some(mut entry) = self.entries.pop() {
+            if let Err(err) = entry.uninject_in_place() {
+                failures.push((entry.process(), err));
+                failed.push(entry);
+                if !force {
+                    break;
+                }
+            }
+        }
+
+        if failures.is_empty() {
+            return Ok(());
+        }
+
+        // Keep the remaining entries in injection order.
+        failed.reverse();
+        self.entries.extend(failed);
+        Err(UninjectError {
+            failures,
+            remaining: self,
+        })
+    }
+}
+
+impl Drop for InjectedSet {
+    fn drop(&mut self) {
+        // Entries set to `uninject_on_drop` go in reverse injection order too.
+        while let Some(entry) = self.entries.pop() {
+            drop(entry);
+        }
+    }
+}
+
+impl Extend<InjectedProcess> for InjectedSet {
+    fn extend<I: IntoIterator<Item = InjectedProcess>>(&mut self, iter: I) {
+        self.entries.extend(iter);
+    }
+}
+
+impl FromIterator<InjectedProcess> for InjectedSet {
+    fn from_iter<I: IntoIterator<Item = InjectedProcess>>(iter: I) -> Self {
+        Self {
+            entries: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// Outcome of injecting into several processes with `inject_by_name`.
+///
+/// Targets that were injected are held in an `InjectedSet`; the others are
+/// listed with their error.
+#[derive(Debug, Default)]
+pub struct InjectionBatch {
+    injected: InjectedSet,
+    failures: Vec<(Process, Error)>,
+}
+
+impl InjectionBatch {
+    pub(crate) fn collect(results: Vec<(Process, crate::Result<InjectedProcess>)>) -> Self {
+        let mut batch = Self::default();
+        for (process, result) in results {
+            match result {
+                Ok(injected) => batch.injected.push(injected),
+                Err(err) => batch.failures.push((process, err)),
+            }
+        }
+        batch
+    }
+
+    /// The targets that were injected.
+    pub fn injected(&self) -> &InjectedSet {
+        &self.injected
+    }
+
+    /// The targets that could not be injected, with why, in PID order.
+    pub fn failures(&self) -> &[(Process, Error)] {
+        &self.failures
+    }
+
+    /// Take the injected targets, e.g. to uninject them together later.
+    pub fn into_injected(self) -> InjectedSet {
+        self.injected
+    }
+}
+
+/// Failures from ejecting an `InjectedSet`.
+#[derive(Debug)]
+pub struct UninjectError {
+    failures: Vec<(Process, Error)>,
+    remaining: InjectedSet,
+}
+
+impl UninjectError {
+    /// Each failed step, in the order it was attempted.
+    pub fn failures(&self) -> &[(Process, Error)] {
+        &self.failures
+    }
+
+    /// Take back the libraries that are still injected so they can be retried.
+    pub fn into_remaining(self) -> InjectedSet {
+        self.remaining
+    }
+}
+
+impl fmt::Display for UninjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to uninject {} librar", self.failures.len())?;
+        f.write_str(if self.failures.len() == 1 { "y" } else { "ies" })?;
+        for (process, err) in &self.failures {
+            write!(f, "; pid {}: {err}", process.pid())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for UninjectError {}
+
+impl From<UninjectError> for Error {
+    fn from(err: UninjectError) -> Self {
+        Error::runtime(&err).with_source(err)
+    }
+}
@@ -0,0 +1,184 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::{Error, Result};
+
+/// A shared memory segment for bulk data between injector and agent.
+///
+/// The injector creates the segment and passes `descriptor()` to the agent
+/// in its data (e.g. as a field of a typed payload); the agent maps the same
+/// memory with `hook_inject_agent::SharedMemory::open`. Both sides see
+/// writes immediately, so high-rate data such as trace records can skip the
+/// entrypoint data and any pipe. The contents are not synchronized:
+/// coordinate through atomics placed in the segment itself.
+///
+/// The segment is named after this process and unlinked when dropped; an
+/// agent that already mapped it keeps its mapping. Supported on Unix.
+///
+/// # Examples
+/// ```no_run
+/// use hook_inject::{inject_process, Library, Process, SharedMemory};
+///
+/// let shm = SharedMemory::create(1 << 20)?;
+/// let library = Library::from_path("/path/to/libagent.so")?.with_data(shm.descriptor())?;
+/// let injected = inject_process(Process::from_pid(1234)?, library)?;
+/// // ... read what the agent writes through `shm.as_ptr()`.
+/// # Ok::<(), hook_inject::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct SharedMemory {
+    name: String,
+    ptr: *mut u8,
+    len: usize,
+}
+
+// The mapping is plain memory; callers synchronize access to its contents.
+unsafe impl Send for SharedMemory {}
+unsafe impl Sync for SharedMemory {}
+
+static NEXT: AtomicU32 = AtomicU32::new(0);
+
+impl SharedMemory {
+    /// Create a zeroed segment of `len` bytes and map it into this process.
+    pub fn create(len: usize) -> Result<Self> {
+        if len == 0 {
+            return Err(Error::invalid_input(
+                "shared memory must be at least one byte",
+            ));
+        }
+        // Short enough for macOS, which limits names to 31 bytes.
+        let name = format!(
+            "/hook-inject-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        );
+        let ptr = sys::create(&name, len)?;
+        Ok(Self { name, ptr, len })
+    }
+
+    /// The name the agent opens the segment by.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The size of the segment in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the segment is empty, which `create` never returns.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The string to hand to `hook_inject_agent::SharedMemory::open`:
+    /// `<name>:<len>`.
+    pub fn descriptor(&self) -> String {
+        format!("{}:{}", self.name, self.len)
+    }
+
+    /// The start of the mapping in this process.
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.ptr
+    }
+
+    /// View the segment as a byte slice.
+    ///
+    /// # Safety
+    /// No one, including the agent, may write to the segment while the
+    /// slice is alive.
+    pub unsafe fn as_slice(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    /// View the segment as a mutable byte slice.
+    ///
+    /// # Safety
+    /// No one, including the agent, may access the segment while the slice
+    /// is alive.
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn as_mut_slice(&self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl Drop for SharedMemory {
+    fn drop(&mut self) {
+        sys::destroy(&self.name, self.ptr, self.len);
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::ffi::CString;
+
+    use crate::{Error, Result};
+
+    pub(super) fn create(name: &str, len: usize) -> Result<*mut u8> {
+        let c_name = CString::new(name).expect("shared memory names have no NUL");
+        // SAFETY: `c_name` is a valid C string; the descriptor is closed below.
+        let fd = unsafe {
+            libc::shm_open(
+                c_name.as_ptr(),
+                libc::O_CREAT | libc::O_EXCL | libc::O_RDWR,
+                0o600,
+            )
+        };
+        if fd < 0 {
+            return Err(Error::from_io(std::io::Error::last_os_error()));
+        }
+        let mapped = map(fd, len);
+        // SAFETY: `fd` is open and owned here; the mapping outlives it.
+        unsafe { libc::close(fd) };
+        if mapped.is_err() {
+            // SAFETY: `c_name` is a valid C string.
+            unsafe { libc::shm_unlink(c_name.as_ptr()) };
+        }
+        mapped
+    }
+
+    fn map(fd: libc::c_int, len: usize) -> Result<*mut u8> {
+        let size = libc::off_t::try_from(len)
+            .map_err(|_| Error::invalid_input("shared memory size is too large"))?;
+        // SAFETY: `fd` is an open shared memory object.
+        if unsafe { libc::ftruncate(fd, size) } != 0 {
+            return Err(Error::from_io(std::io::Error::last_os_error()));
+        }
+        // SAFETY: a fresh shared mapping of `len` bytes of `fd`.
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(Error::from_io(std::io::Error::last_os_error()));
+        }
+        Ok(ptr.cast())
+    }
+
+    pub(super) fn destroy(name: &str, ptr: *mut u8, len: usize) {
+        // SAFETY: `ptr` and `len` describe a mapping made by `create`.
+        unsafe { libc::munmap(ptr.cast(), len) };
+        if let Ok(c_name) = CString::new(name) {
+            // SAFETY: `c_name` is a valid C string.
+            unsafe { libc::shm_unlink(c_name.as_ptr()) };
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod sys {
+    use crate::{Error, Result};
+
+    pub(super) fn create(_name: &str, _len: usize) -> Result<*mut u8> {
+        Err(Error::not_supported(
+            "shared memory is only supported on Unix",
+        ))
+    }
+
+    pub(super) fn destroy(_name: &str, _ptr: *mut u8, _len: usize) {}
+}
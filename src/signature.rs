@@ -0,0 +1,44 @@
+use minisign_verify::{PublicKey, Signature};
+
+use crate::{Error, Result};
+
+/// A minisign public key and detached signature a library must verify
+/// against.
+#[derive(Debug)]
+pub(crate) struct Minisign {
+    public_key: String,
+    signature: String,
+}
+
+impl Minisign {
+    pub(crate) fn new(public_key: String, signature: String) -> Self {
+        Self {
+            public_key,
+            signature,
+        }
+    }
+
+    /// Fail with `invalid_input` unless the signature covers `bytes`.
+    pub(crate) fn verify(&self, bytes: &[u8]) -> Result<()> {
+        // Either a bare base64 key or the contents of a `minisign.pub` file.
+        let public_key = self.public_key.trim();
+        let public_key = if public_key.contains('\n') {
+            PublicKey::decode(public_key)
+        } else {
+            PublicKey::from_base64(public_key)
+        }
+        .map_err(|err| {
+            Error::invalid_input(format_args!("invalid minisign public key: {err}"))
+                .with_source(err)
+        })?;
+        let signature = Signature::decode(self.signature.trim()).map_err(|err| {
+            Error::invalid_input(format_args!("invalid minisign signature: {err}")).with_source(err)
+        })?;
+
+        // Legacy (non-prehashed) signatures from `minisign -l` are accepted.
+        public_key.verify(bytes, &signature, true).map_err(|err| {
+            Error::invalid_input(format_args!("library signature check failed: {err}"))
+                .with_source(err)
+        })
+    }
+}
@@ -0,0 +1,415 @@
+// Native "spawn held" path used when a launch needs std `Command` features
+// that Frida's spawn API cannot provide (e.g. `pre_exec` hooks).
+//
+// The child asks to be traced right before `exec`, so the kernel stops it as
+// soon as the new image is loaded. We then detach while delivering `SIGSTOP`,
+// leaving an ordinary stopped process that can be injected and later resumed
+// with `SIGCONT`.
+//
+// That stop is at the loader entry. To hold at the program entry instead, we
+// plant a breakpoint at `AT_ENTRY` and let the loader run up to it first
+// (Linux x86_64/aarch64 only).
+//
+// On Windows the program is created with `CREATE_SUSPENDED`, which holds its
+// main thread before the loader runs, and released with `ResumeThread`.
+//
+// A timeout bounds each wait for the program to stop, so one that never gets
+// there (e.g. spinning in a static constructor) is killed instead of
+// blocking the caller.
+
+use std::process::Command;
+use std::time::Duration;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::time::Instant;
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use crate::clock::poll_until;
+use crate::{Clock, Error, Process, Result, SuspendPoint};
+
+// How often a wait with a deadline checks on the program.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub(crate) fn spawn_held(
+    mut cmd: Command,
+    point: SuspendPoint,
+    timeout: Option<Duration>,
+    clock: &dyn Clock,
+) -> Result<std::process::Child> {
+    use std::os::unix::process::CommandExt;
+
+    if point == SuspendPoint::ProgramEntry && !RUN_TO_ENTRY_SUPPORTED {
+        return Err(Error::not_supported(
+            "holding a natively spawned program at its entrypoint is not supported on this platform",
+        ));
+    }
+
+    // Registered last, so it runs after any caller-provided pre_exec hooks.
+    unsafe {
+        cmd.pre_exec(|| {
+            if trace_me() == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+
+    let mut child = cmd.spawn().map_err(Error::from)?;
+    let pid = child.id() as libc::pid_t;
+    let deadline = timeout.map(|timeout| clock.now() + timeout);
+    let held = wait_for_exec_stop(pid, deadline, clock)
+        .and_then(|()| match point {
+            SuspendPoint::LoaderEntry => Ok(()),
+            SuspendPoint::ProgramEntry => run_to_entry(pid, deadline, clock),
+        })
+        .and_then(|()| detach_stopped(pid, deadline, clock))
+        .map_err(|err| match timeout {
+            Some(timeout) if err.is_timed_out() => Error::timed_out(format_args!(
+                "program was not held at its {} within {timeout:?}",
+                match point {
+                    SuspendPoint::LoaderEntry => "loader entry",
+                    SuspendPoint::ProgramEntry => "entrypoint",
+                }
+            )),
+            _ => err,
+        });
+    if let Err(err) = held {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(err);
+    }
+
+    Ok(child)
+}
+
+// Creating the process suspended does not wait on it.
+#[cfg(windows)]
+pub(crate) fn spawn_held(
+    mut cmd: Command,
+    point: SuspendPoint,
+    _timeout: Option<Duration>,
+    _clock: &dyn Clock,
+) -> Result<std::process::Child> {
+    use std::os::windows::process::CommandExt;
+    use windows_sys::Win32::System::Threading::CREATE_SUSPENDED;
+
+    if point == SuspendPoint::ProgramEntry {
+        return Err(Error::not_supported(
+            "holding a natively spawned program at its entrypoint is not supported on this platform",
+        ));
+    }
+
+    cmd.creation_flags(CREATE_SUSPENDED);
+    cmd.spawn().map_err(Error::from)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub(crate) fn spawn_held(
+    _cmd: Command,
+    _point: SuspendPoint,
+    _timeout: Option<Duration>,
+    _clock: &dyn Clock,
+) -> Result<std::process::Child> {
+    Err(Error::not_supported(
+        "holding a natively spawned program is not supported on this platform",
+    ))
+}
+
+/// Where `inject_command` holds programs: pre-main where that is supported,
+/// otherwise as early as possible.
+pub(crate) fn preferred_point() -> SuspendPoint {
+    if RUN_TO_ENTRY_SUPPORTED {
+        SuspendPoint::ProgramEntry
+    } else {
+        SuspendPoint::LoaderEntry
+    }
+}
+
+/// Let a held program continue running.
+#[cfg(unix)]
+pub(crate) fn release(process: Process) -> Result<()> {
+    let res = unsafe { libc::kill(process.pid(), libc::SIGCONT) };
+    if res == -1 {
+        return Err(Error::from(std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+pub(crate) fn release(process: Process) -> Result<()> {
+    use windows_sys::Win32::Foundation::{CloseHandle, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, TH32CS_SNAPTHREAD, THREADENTRY32, Thread32First, Thread32Next,
+    };
+    use windows_sys::Win32::System::Threading::{OpenThread, ResumeThread, THREAD_SUSPEND_RESUME};
+
+    // A CREATE_SUSPENDED process has exactly one thread; resume every thread
+    // of the process in case the injector added its own.
+    let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0) };
+    if snapshot == INVALID_HANDLE_VALUE {
+        return Err(Error::from(std::io::Error::last_os_error()));
+    }
+
+    let mut resumed = 0;
+    let mut entry = THREADENTRY32 {
+        dwSize: std::mem::size_of::<THREADENTRY32>() as u32,
+        ..Default::default()
+    };
+    let mut more = unsafe { Thread32First(snapshot, &mut entry) } != 0;
+    while more {
+        if entry.th32OwnerProcessID == process.pid() as u32 {
+            let thread = unsafe { OpenThread(THREAD_SUSPEND_RESUME, 0, entry.th32ThreadID) };
+            if !thread.is_null() {
+                if unsafe { ResumeThread(thread) } != u32::MAX {
+                    resumed += 1;
+                }
+                unsafe { CloseHandle(thread) };
+            }
+        }
+        more = unsafe { Thread32Next(snapshot, &mut entry) } != 0;
+    }
+    unsafe { CloseHandle(snapshot) };
+
+    if resumed == 0 {
+        return Err(Error::runtime(format_args!(
+            "no thread of process {} could be resumed",
+            process.pid()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn release(_process: Process) -> Result<()> {
+    Err(Error::not_supported(
+        "holding a natively spawned program is not supported on this platform",
+    ))
+}
+
+#[cfg(target_os = "linux")]
+fn trace_me() -> libc::c_long {
+    unsafe {
+        libc::ptrace(
+            libc::PTRACE_TRACEME,
+            0,
+            std::ptr::null_mut::<libc::c_void>(),
+            std::ptr::null_mut::<libc::c_void>(),
+        )
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn trace_me() -> libc::c_int {
+    unsafe { libc::ptrace(libc::PT_TRACE_ME, 0, std::ptr::null_mut(), 0) }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn wait_for_exec_stop(
+    pid: libc::pid_t,
+    deadline: Option<Instant>,
+    clock: &dyn Clock,
+) -> Result<()> {
+    // A traced child reports SIGTRAP once exec has replaced its image.
+    let status = wait_status(pid, 0, deadline, clock)?;
+    if libc::WIFSTOPPED(status) && libc::WSTOPSIG(status) == libc::SIGTRAP {
+        return Ok(());
+    }
+
+    Err(Error::runtime(format_args!(
+        "program did not stop after exec (wait status {status:#x})"
+    )))
+}
+
+const RUN_TO_ENTRY_SUPPORTED: bool = cfg!(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+));
+
+/// Continue a traced program stopped at exec until it reaches `AT_ENTRY`.
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+fn run_to_entry(pid: libc::pid_t, deadline: Option<Instant>, clock: &dyn Clock) -> Result<()> {
+    use std::ptr::null_mut;
+
+    let entry = auxv_entry(pid)?;
+    let addr = entry as usize as *mut libc::c_void;
+
+    // PEEKTEXT returns the word itself, so -1 is only an error if errno says so.
+    unsafe { *libc::__errno_location() = 0 };
+    let original =
+        unsafe { libc::ptrace(libc::PTRACE_PEEKTEXT, pid, addr, null_mut::<libc::c_void>()) };
+    if original == -1 && std::io::Error::last_os_error().raw_os_error() != Some(0) {
+        return Err(Error::from(std::io::Error::last_os_error()));
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    let patched = (original & !0xff) | 0xcc; // int3
+    #[cfg(target_arch = "aarch64")]
+    let patched = (original & !0xffff_ffff) | 0xd420_0000; // brk #0
+
+    poke_text(pid, addr, patched)?;
+    let res = unsafe {
+        libc::ptrace(
+            libc::PTRACE_CONT,
+            pid,
+            null_mut::<libc::c_void>(),
+            null_mut::<libc::c_void>(),
+        )
+    };
+    if res == -1 {
+        return Err(Error::from(std::io::Error::last_os_error()));
+    }
+
+    let status = wait_status(pid, 0, deadline, clock)?;
+    if !(libc::WIFSTOPPED(status) && libc::WSTOPSIG(status) == libc::SIGTRAP) {
+        return Err(Error::runtime(format_args!(
+            "program did not reach its entrypoint (wait status {status:#x})"
+        )));
+    }
+    poke_text(pid, addr, original)?;
+
+    // int3 reports the address after the trap; rewind so the original
+    // instruction runs on resume. brk leaves the pc on the breakpoint.
+    #[cfg(target_arch = "x86_64")]
+    {
+        let mut regs: libc::user_regs_struct = unsafe { std::mem::zeroed() };
+        let res = unsafe {
+            libc::ptrace(
+                libc::PTRACE_GETREGS,
+                pid,
+                null_mut::<libc::c_void>(),
+                &mut regs as *mut libc::user_regs_struct,
+            )
+        };
+        if res == -1 {
+            return Err(Error::from(std::io::Error::last_os_error()));
+        }
+        regs.rip = entry;
+        let res = unsafe {
+            libc::ptrace(
+                libc::PTRACE_SETREGS,
+                pid,
+                null_mut::<libc::c_void>(),
+                &regs as *const libc::user_regs_struct,
+            )
+        };
+        if res == -1 {
+            return Err(Error::from(std::io::Error::last_os_error()));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+fn poke_text(pid: libc::pid_t, addr: *mut libc::c_void, word: libc::c_long) -> Result<()> {
+    let res = unsafe { libc::ptrace(libc::PTRACE_POKETEXT, pid, addr, word as *mut libc::c_void) };
+    if res == -1 {
+        return Err(Error::from(std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+fn auxv_entry(pid: libc::pid_t) -> Result<u64> {
+    let auxv = std::fs::read(format!("/proc/{pid}/auxv"))?;
+    auxv.chunks_exact(16)
+        .map(|pair| {
+            let key = u64::from_ne_bytes(pair[..8].try_into().unwrap());
+            let value = u64::from_ne_bytes(pair[8..].try_into().unwrap());
+            (key, value)
+        })
+        .find(|&(key, _)| key == libc::AT_ENTRY)
+        .map(|(_, value)| value)
+        .ok_or_else(|| Error::runtime("AT_ENTRY missing from auxiliary vector"))
+}
+
+#[cfg(any(
+    target_os = "macos",
+    all(
+        target_os = "linux",
+        not(any(target_arch = "x86_64", target_arch = "aarch64"))
+    )
+))]
+fn run_to_entry(_pid: libc::pid_t, _deadline: Option<Instant>, _clock: &dyn Clock) -> Result<()> {
+    unreachable!("guarded by RUN_TO_ENTRY_SUPPORTED")
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn detach_stopped(pid: libc::pid_t, deadline: Option<Instant>, clock: &dyn Clock) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    let res = unsafe {
+        libc::ptrace(
+            libc::PTRACE_DETACH,
+            pid,
+            std::ptr::null_mut::<libc::c_void>(),
+            libc::SIGSTOP as usize as *mut libc::c_void,
+        )
+    };
+    #[cfg(target_os = "macos")]
+    let res = unsafe { libc::ptrace(libc::PT_DETACH, pid, 1 as *mut libc::c_char, libc::SIGSTOP) };
+
+    if res == -1 {
+        return Err(Error::from(std::io::Error::last_os_error()));
+    }
+
+    // Consume the stop notification so the program is known to be held.
+    let status = wait_status(pid, libc::WUNTRACED, deadline, clock)?;
+    if !libc::WIFSTOPPED(status) {
+        return Err(Error::runtime(format_args!(
+            "program did not stay stopped after detach (wait status {status:#x})"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Wait for `pid` to change state, failing with `timed_out` once `deadline`
+/// passes on `clock`.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn wait_status(
+    pid: libc::pid_t,
+    flags: libc::c_int,
+    deadline: Option<Instant>,
+    clock: &dyn Clock,
+) -> Result<libc::c_int> {
+    let mut status: libc::c_int = 0;
+    let Some(deadline) = deadline else {
+        loop {
+            if unsafe { libc::waitpid(pid, &mut status, flags) } == pid {
+                return Ok(status);
+            }
+            let err = std::io::Error::last_os_error();
+            if err.kind() != std::io::ErrorKind::Interrupted {
+                return Err(Error::from(err));
+            }
+        }
+    };
+
+    let timeout = deadline.saturating_duration_since(clock.now());
+    poll_until(clock, Some(timeout), WAIT_POLL_INTERVAL, || {
+        let res = unsafe { libc::waitpid(pid, &mut status, flags | libc::WNOHANG) };
+        if res == pid {
+            return Ok(Some(status));
+        }
+        if res == 0 {
+            // The program has not changed state yet.
+            return Ok(None);
+        }
+        let err = std::io::Error::last_os_error();
+        if err.kind() != std::io::ErrorKind::Interrupted {
+            return Err(Error::from(err));
+        }
+        Ok(None)
+    })?
+    .ok_or_else(|| Error::timed_out("program did not stop in time"))
+}
@@ -0,0 +1,218 @@
+use std::path::{Path, PathBuf};
+
+use crate::library::LibrarySource;
+use crate::{BinaryFormat, Error, InjectOptions, Library, Process, Result};
+
+const STAGING_DIR_ENV: &str = "HOOK_INJECT_STAGING_DIR";
+
+/// Resolve the staging directory for an injection.
+///
+/// Precedence: per-call option, then `HOOK_INJECT_STAGING_DIR`, then a
+/// `hook-inject` directory under the system temp dir.
+pub(crate) fn staging_dir(options: &InjectOptions) -> PathBuf {
+    if let Some(dir) = options.staging_dir_value() {
+        return dir.to_path_buf();
+    }
+
+    if let Some(dir) = std::env::var_os(STAGING_DIR_ENV) {
+        return PathBuf::from(dir);
+    }
+
+    std::env::temp_dir().join("hook-inject")
+}
+
+/// Make sure an explicitly configured staging directory is usable.
+///
+/// Fail fast here instead of surfacing an I/O error halfway through an
+/// injection that has already touched the target.
+pub(crate) fn prepare(options: &InjectOptions) -> Result<()> {
+    if options.staging_dir_value().is_none() {
+        return Ok(());
+    }
+
+    let dir = staging_dir(options);
+    create_private_dir(&dir)?;
+    let meta = std::fs::metadata(&dir).map_err(Error::from)?;
+    if !meta.is_dir() {
+        return Err(Error::invalid_input(format_args!(
+            "staging dir is not a directory: {}",
+            dir.display()
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn create_private_dir(dir: &std::path::Path) -> Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    // Staged files may contain agent code; keep them owner-only.
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .map_err(Error::from)
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(dir: &std::path::Path) -> Result<()> {
+    std::fs::create_dir_all(dir).map_err(Error::from)
+}
+
+// === Artifact markers ===
+//
+// Every staged artifact is named `hook-inject.<injector pid>.<target pid>.<nonce>[.<suffix>]`
+// so a later run can tell which ones were orphaned by a crash. A target pid of
+// 0 means the artifact was staged before the target existed.
+const ARTIFACT_PREFIX: &str = "hook-inject.";
+
+/// Reserve a unique path for an artifact staged on behalf of `target_pid`.
+pub(crate) fn artifact_path(
+    options: &InjectOptions,
+    target_pid: i32,
+    suffix: &str,
+) -> Result<PathBuf> {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+
+    let dir = staging_dir(options);
+    create_private_dir(&dir)?;
+
+    let nonce = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default()
+        ^ NEXT.fetch_add(1, Ordering::Relaxed);
+    let mut name = format!(
+        "{ARTIFACT_PREFIX}{}.{target_pid}.{nonce:x}",
+        std::process::id()
+    );
+    if !suffix.is_empty() {
+        name.push('.');
+        name.push_str(suffix);
+    }
+
+    Ok(dir.join(name))
+}
+
+/// Write a blob library to a new owner-only file and return it as a path
+/// library with the same entrypoint and data.
+pub(crate) fn stage_library(
+    library: &Library,
+    target: Process,
+    options: &InjectOptions,
+) -> Result<Library> {
+    let library = library.decompressed()?;
+    let LibrarySource::Blob(bytes) = library.source() else {
+        return Ok(library);
+    };
+    let suffix = match BinaryFormat::native() {
+        Some(BinaryFormat::Pe) => "dll",
+        Some(BinaryFormat::MachO) => "dylib",
+        _ => "so",
+    };
+    let path = artifact_path(options, target.pid(), suffix)?;
+    write_private(&path, bytes)?;
+    Ok(library.staged_at(path))
+}
+
+/// Write `bytes` to a new owner-only file, staged before the target that
+/// uses it exists.
+pub(crate) fn stage_bytes(options: &InjectOptions, suffix: &str, bytes: &[u8]) -> Result<PathBuf> {
+    let path = artifact_path(options, 0, suffix)?;
+    write_private(&path, bytes)?;
+    Ok(path)
+}
+
+/// Write a library's binary entrypoint data to a new owner-only file and
+/// return the library with that file's path as its data string.
+pub(crate) fn stage_data(
+    library: &Library,
+    target: Process,
+    options: &InjectOptions,
+) -> Result<Library> {
+    let Some(bytes) = library.data_bytes() else {
+        return Ok(library.clone());
+    };
+    let path = artifact_path(options, target.pid(), "data")?;
+    write_private(&path, bytes)?;
+    library.data_staged_at(&path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&path);
+    })
+}
+
+fn write_private(path: &Path, bytes: &[u8]) -> Result<()> {
+    use std::io::Write;
+
+    let mut open = std::fs::OpenOptions::new();
+    open.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        open.mode(0o600);
+    }
+    let written = open
+        .open(path)
+        .and_then(|mut file| file.write_all(bytes).and_then(|()| file.sync_all()));
+    if let Err(err) = written {
+        let _ = std::fs::remove_file(path);
+        return Err(Error::from(err));
+    }
+    Ok(())
+}
+
+/// Parse `(injector pid, target pid)` from an artifact file name.
+fn parse_marker(name: &str) -> Option<(i32, i32)> {
+    let rest = name.strip_prefix(ARTIFACT_PREFIX)?;
+    let mut parts = rest.split('.');
+    let injector = parts.next()?.parse().ok()?;
+    let target = parts.next()?.parse().ok()?;
+    // Require the nonce so unrelated `hook-inject.*` files are left alone.
+    parts.next().filter(|nonce| !nonce.is_empty())?;
+    Some((injector, target))
+}
+
+fn process_gone(pid: i32) -> bool {
+    // Permission errors mean the process exists; only a definitive miss counts.
+    pid > 0
+        && Process::from_pid(pid)
+            .err()
+            .is_some_and(|err| err.is_process_not_found())
+}
+
+/// Remove artifacts in `dir` whose injector or target process is gone.
+pub(crate) fn sweep(dir: &Path) -> Result<usize> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(Error::from(err)),
+    };
+
+    let mut removed = 0;
+    for entry in entries {
+        let entry = entry.map_err(Error::from)?;
+        let name = entry.file_name();
+        let Some((injector, target)) = name.to_str().and_then(parse_marker) else {
+            continue;
+        };
+        if !process_gone(injector) && !process_gone(target) {
+            continue;
+        }
+
+        let path = entry.path();
+        let result = if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+            std::fs::remove_dir_all(&path)
+        } else {
+            std::fs::remove_file(&path)
+        };
+        // Files still mapped by a live process can't be removed on Windows;
+        // leave them for a later sweep.
+        if result.is_ok() {
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
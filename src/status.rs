@@ -0,0 +1,393 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::clock::poll_until;
+use crate::library::LibrarySource;
+use crate::{Clock, Error, ErrorKind, Library, Process, Result, SystemClock, image, maps};
+
+// How often the waits below read the agent's status back.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Name of the symbol an agent exports to report its entrypoint status.
+///
+/// The symbol is a 32-bit integer that starts out as `ENTRY_STATUS_PENDING`.
+/// The entrypoint stores `0` once it has initialized, or any other value to
+/// report why it refused to. The injector reads it back from the target's
+/// memory, so the convention works with every backend.
+///
+/// # Examples
+/// Agent side:
+/// ```
+/// use std::ffi::c_char;
+/// use std::sync::atomic::{AtomicI32, Ordering};
+///
+/// #[allow(non_upper_case_globals)]
+/// #[unsafe(no_mangle)]
+/// pub static hook_inject_entry_status: AtomicI32 = AtomicI32::new(hook_inject::ENTRY_STATUS_PENDING);
+///
+/// #[unsafe(no_mangle)]
+/// pub extern "C" fn frida_agent_main(_data: *const c_char, _stay_resident: *mut i32) {
+///     let status = if std::env::var_os("AGENT_DISABLED").is_some() { 1 } else { 0 };
+///     hook_inject_entry_status.store(status, Ordering::SeqCst);
+/// }
+/// ```
+pub const ENTRY_STATUS_SYMBOL: &str = "hook_inject_entry_status";
+
+/// Initial value of `ENTRY_STATUS_SYMBOL`, meaning "not reported yet".
+pub const ENTRY_STATUS_PENDING: i32 = i32::MIN;
+
+/// Name of the symbol an agent exports to report a panic.
+///
+/// The symbol is a 32-bit length, `0` until the agent panics, followed by
+/// up to `PANIC_REPORT_CAPACITY` bytes of UTF-8: the panic message, location
+/// and backtrace. Like `ENTRY_STATUS_SYMBOL` it is read back from the
+/// target's memory. `hook_inject_agent::PanicReport` has this layout, and
+/// `hook_inject_agent::install_panic_hook` fills it in.
+pub const PANIC_REPORT_SYMBOL: &str = "hook_inject_panic_report";
+
+/// Most bytes of text `PANIC_REPORT_SYMBOL` holds after its length.
+pub const PANIC_REPORT_CAPACITY: usize = 4092;
+
+/// Name of the symbol an agent exports to send heartbeats.
+///
+/// The symbol is a 64-bit integer holding the time of the agent's last
+/// heartbeat, in milliseconds since the Unix epoch, or `0` before the first
+/// one. The injector reads it back from the target's memory, which shares
+/// its wall clock. `hook_inject_agent::Heartbeat` has this layout.
+pub const HEARTBEAT_SYMBOL: &str = "hook_inject_heartbeat";
+
+/// Name of the symbol an agent exports to acknowledge configuration.
+///
+/// The symbol is two 64-bit counters: configurations the agent applied,
+/// then configurations it rejected. After pushing data,
+/// `InjectedProcess::push_config` waits for one of them to grow.
+/// `hook_inject_agent::ConfigAck` has this layout.
+pub const CONFIG_ACK_SYMBOL: &str = "hook_inject_config_ack";
+
+/// Name of the symbol an agent exports to emit events.
+///
+/// The symbol is a ring buffer: two 64-bit offsets, counting bytes ever
+/// written, of the oldest record the agent still holds and of the end of
+/// the newest, then `EVENT_BUFFER_CAPACITY` bytes of records. Each record is
+/// a 32-bit payload length, a 64-bit timestamp in milliseconds since the
+/// Unix epoch, and the payload. The agent moves the oldest offset past
+/// records before overwriting them. `InjectedProcess::events` reads it, and
+/// `hook_inject_agent::EventStream` has this layout.
+pub const EVENTS_SYMBOL: &str = "hook_inject_events";
+
+/// Bytes of records `EVENTS_SYMBOL` holds after its offsets.
+pub const EVENT_BUFFER_CAPACITY: usize = 65536;
+
+/// Name of the symbol an agent exports to forward the target's output.
+///
+/// It has the layout of `EVENTS_SYMBOL`; each record's payload is the
+/// descriptor written to (`1` for stdout, `2` for stderr) as one byte,
+/// followed by the bytes written. `InjectedProcess::output` reads it, and
+/// `hook_inject_agent::capture_output` fills it.
+pub const OUTPUT_SYMBOL: &str = "hook_inject_output";
+
+// Enough of the image header to tell loaded modules apart.
+const HEADER_PROBE_LEN: usize = 512;
+
+/// Status reported by an agent's entrypoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EntryStatus {
+    /// The library does not export `ENTRY_STATUS_SYMBOL`.
+    Unreported,
+    /// The entrypoint has not reported a status yet.
+    Pending,
+    /// The entrypoint reported success (`0`).
+    Ready,
+    /// The entrypoint reported a failure code.
+    Failed(i32),
+}
+
+impl EntryStatus {
+    fn from_code(code: i32) -> Self {
+        match code {
+            ENTRY_STATUS_PENDING => EntryStatus::Pending,
+            0 => EntryStatus::Ready,
+            code => EntryStatus::Failed(code),
+        }
+    }
+}
+
+/// Liveness of an agent, from its heartbeats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Health {
+    /// The library does not export `HEARTBEAT_SYMBOL`.
+    Unreported,
+    /// The agent has not sent a heartbeat yet.
+    Pending,
+    /// The agent last sent a heartbeat at this time.
+    LastSeen(SystemTime),
+}
+
+impl Health {
+    /// Returns true if the agent sent a heartbeat within `max_age`.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::{Duration, SystemTime};
+    /// use hook_inject::Health;
+    ///
+    /// let health = Health::LastSeen(SystemTime::now() - Duration::from_secs(30));
+    /// assert!(health.is_alive(Duration::from_secs(60)));
+    /// assert!(!health.is_alive(Duration::from_secs(10)));
+    /// assert!(!Health::Pending.is_alive(Duration::from_secs(60)));
+    /// ```
+    pub fn is_alive(&self, max_age: Duration) -> bool {
+        self.is_alive_with(max_age, &SystemClock)
+    }
+
+    /// `is_alive`, with the current time taken from `clock`.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use hook_inject::{Clock, Health, ManualClock};
+    ///
+    /// let clock = ManualClock::new();
+    /// let health = Health::LastSeen(clock.system_time());
+    /// clock.advance(Duration::from_secs(90));
+    /// assert!(!health.is_alive_with(Duration::from_secs(60), &clock));
+    /// ```
+    pub fn is_alive_with(&self, max_age: Duration, clock: &dyn Clock) -> bool {
+        match self {
+            // A heartbeat from the future (clock skew) counts as fresh.
+            Health::LastSeen(at) => clock
+                .system_time()
+                .duration_since(*at)
+                .map_or(true, |age| age <= max_age),
+            Health::Unreported | Health::Pending => false,
+        }
+    }
+}
+
+/// Read the entry status of `library` as loaded in `process`.
+///
+/// The module is located by comparing the start of each mapped region with
+/// the library's header, which works for both file and blob libraries.
+pub(crate) fn entry_status(process: Process, library: &Library) -> Result<EntryStatus> {
+    let [address] = addresses(process, library, [ENTRY_STATUS_SYMBOL])?;
+    match address {
+        Some(address) => read_entry_status(process, address),
+        None => Ok(EntryStatus::Unreported),
+    }
+}
+
+fn read_entry_status(process: Process, address: u64) -> Result<EntryStatus> {
+    let mut code = [0u8; 4];
+    maps::read_memory(process.pid(), address, &mut code)?;
+    Ok(EntryStatus::from_code(i32::from_ne_bytes(code)))
+}
+
+/// Read the last heartbeat of `library` as loaded in `process`.
+pub(crate) fn health(process: Process, library: &Library) -> Result<Health> {
+    let [Some(address)] = addresses(process, library, [HEARTBEAT_SYMBOL])? else {
+        return Ok(Health::Unreported);
+    };
+
+    let mut millis = [0u8; 8];
+    maps::read_memory(process.pid(), address, &mut millis)?;
+    Ok(match u64::from_ne_bytes(millis) {
+        0 => Health::Pending,
+        millis => Health::LastSeen(UNIX_EPOCH + Duration::from_millis(millis)),
+    })
+}
+
+/// Read the panic recorded by `library` as loaded in `process`, if any.
+pub(crate) fn panic_report(process: Process, library: &Library) -> Result<Option<String>> {
+    let [address] = addresses(process, library, [PANIC_REPORT_SYMBOL])?;
+    match address {
+        Some(address) => read_panic_report(process, address),
+        None => Ok(None),
+    }
+}
+
+fn read_panic_report(process: Process, address: u64) -> Result<Option<String>> {
+    let mut len = [0u8; 4];
+    maps::read_memory(process.pid(), address, &mut len)?;
+    let len = (u32::from_ne_bytes(len) as usize).min(PANIC_REPORT_CAPACITY);
+    if len == 0 {
+        return Ok(None);
+    }
+    let mut report = vec![0u8; len];
+    maps::read_memory(process.pid(), address.wrapping_add(4), &mut report)?;
+    Ok(Some(String::from_utf8_lossy(&report).into_owned()))
+}
+
+/// Whether `library` is mapped in `process`.
+///
+/// A library injected from a file is looked up by its path first; the
+/// header comparison covers blobs and files the loader mapped some other way.
+pub(crate) fn is_loaded(process: Process, library: &Library) -> Result<bool> {
+    if let LibrarySource::Path(path) = library.source() {
+        let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+        let regions = process.memory_maps()?;
+        if regions
+            .iter()
+            .any(|region| region.path() == Some(path.as_path()))
+        {
+            return Ok(true);
+        }
+    }
+
+    let bytes = library.image_bytes()?;
+    match module_base(process, &bytes) {
+        Ok(_) => Ok(true),
+        Err(err) if err.kind() == ErrorKind::Runtime => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Wait until the entrypoint of `library` in `process` is no longer
+/// `EntryStatus::Pending`, failing with `timed_out` after `timeout`, or
+/// with `agent_panicked` if the agent reports a panic first.
+pub(crate) fn wait_for_entry(
+    process: Process,
+    library: &Library,
+    timeout: Duration,
+    clock: &dyn Clock,
+) -> Result<EntryStatus> {
+    let [Some(entry), panic] =
+        addresses(process, library, [ENTRY_STATUS_SYMBOL, PANIC_REPORT_SYMBOL])?
+    else {
+        return Ok(EntryStatus::Unreported);
+    };
+
+    let status = poll_until(clock, Some(timeout), POLL_INTERVAL, || {
+        let status = read_entry_status(process, entry)?;
+        if status != EntryStatus::Pending {
+            return Ok(Some(status));
+        }
+        if let Some(panic) = panic
+            && let Some(report) = read_panic_report(process, panic)?
+        {
+            return Err(Error::agent_panicked(report));
+        }
+        Ok(None)
+    })?;
+    status.ok_or_else(|| {
+        Error::timed_out(format_args!(
+            "agent entrypoint did not report a status within {timeout:?}"
+        ))
+    })
+}
+
+/// Wait until `library` in `process` reports `EntryStatus::Ready`.
+pub(crate) fn wait_ready(
+    process: Process,
+    library: &Library,
+    timeout: Duration,
+    clock: &dyn Clock,
+) -> Result<()> {
+    match wait_for_entry(process, library, timeout, clock)? {
+        EntryStatus::Ready => Ok(()),
+        EntryStatus::Failed(code) => Err(Error::runtime(format_args!(
+            "agent entrypoint failed with code {code}"
+        ))),
+        EntryStatus::Unreported => Err(Error::not_supported(format_args!(
+            "agent does not export {ENTRY_STATUS_SYMBOL}, so it cannot report readiness"
+        ))),
+        EntryStatus::Pending => unreachable!("wait_for_entry returns once the status is reported"),
+    }
+}
+
+/// Read the `CONFIG_ACK_SYMBOL` counters at `address` in `process`.
+fn read_config_acks(process: Process, address: u64) -> Result<(u64, u64)> {
+    let mut counters = [0u8; 16];
+    maps::read_memory(process.pid(), address, &mut counters)?;
+    let (applied, rejected) = counters.split_at(8);
+    Ok((
+        u64::from_ne_bytes(applied.try_into().expect("8 bytes")),
+        u64::from_ne_bytes(rejected.try_into().expect("8 bytes")),
+    ))
+}
+
+/// Deliver a configuration with `push`, then wait for `library` in
+/// `process` to acknowledge it through `CONFIG_ACK_SYMBOL`.
+pub(crate) fn push_config(
+    process: Process,
+    library: &Library,
+    timeout: Duration,
+    clock: &dyn Clock,
+    push: impl FnOnce() -> Result<()>,
+) -> Result<()> {
+    let [Some(acks), panic] =
+        addresses(process, library, [CONFIG_ACK_SYMBOL, PANIC_REPORT_SYMBOL])?
+    else {
+        return Err(Error::not_supported(format_args!(
+            "agent does not export {CONFIG_ACK_SYMBOL}, so it cannot acknowledge configuration"
+        )));
+    };
+    let before = read_config_acks(process, acks)?;
+    push()?;
+
+    let acked = poll_until(clock, Some(timeout), POLL_INTERVAL, || {
+        let (applied, rejected) = read_config_acks(process, acks)?;
+        if rejected != before.1 {
+            return Err(Error::runtime("agent rejected the configuration"));
+        }
+        if applied != before.0 {
+            return Ok(Some(()));
+        }
+        if let Some(panic) = panic
+            && let Some(report) = read_panic_report(process, panic)?
+        {
+            return Err(Error::agent_panicked(report));
+        }
+        Ok(None)
+    })?;
+    acked.ok_or_else(|| {
+        Error::timed_out(format_args!(
+            "agent did not acknowledge the configuration within {timeout:?}"
+        ))
+    })
+}
+
+/// Where `library` holds each of `symbols` in `process`, `None` for those
+/// it does not export.
+///
+/// This reads the image and scans the memory maps once, so the polling
+/// loops above resolve their symbols up front and then read only them.
+fn addresses<const N: usize>(
+    process: Process,
+    library: &Library,
+    symbols: [&str; N],
+) -> Result<[Option<u64>; N]> {
+    let bytes = library.image_bytes()?;
+    let mut base = None;
+    let mut addresses = [None; N];
+    for (address, symbol) in addresses.iter_mut().zip(symbols) {
+        let Some(offset) = image::export_offset(&bytes, symbol)? else {
+            continue;
+        };
+        let base = match base {
+            Some(base) => base,
+            None => *base.insert(module_base(process, &bytes)?),
+        };
+        *address = Some(base.wrapping_add(offset));
+    }
+    Ok(addresses)
+}
+
+pub(crate) fn module_base(process: Process, image: &[u8]) -> Result<u64> {
+    let header = &image[..image.len().min(HEADER_PROBE_LEN)];
+    let mut probe = vec![0u8; header.len()];
+    for region in process.memory_maps()? {
+        if region.offset() != 0 || !region.protection().read() || region.len() < header.len() as u64
+        {
+            continue;
+        }
+        if maps::read_memory(process.pid(), region.start(), &mut probe).is_ok() && probe == header {
+            return Ok(region.start());
+        }
+    }
+
+    Err(Error::runtime(format_args!(
+        "library is not loaded in pid {}",
+        process.pid()
+    )))
+}
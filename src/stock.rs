@@ -0,0 +1,49 @@
+use crate::{Error, Library, Result};
+
+/// A ready-made agent shipped with the crate, for triage without writing
+/// and building one (`Library::stock`).
+///
+/// Each agent is embedded only when its Cargo feature is enabled
+/// (`stock-agents` enables all of them). The entrypoint data is the path of
+/// a log in the target's file system, appended to, or empty for the
+/// target's standard error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum StockAgent {
+    /// Writes the target's pid, executable, arguments, working directory
+    /// and environment, then unloads (`stock-env-dumper`).
+    EnvDumper,
+    /// Logs every file the target opens through `open` or `openat`, and the
+    /// result, until ejected. Linux only (`stock-file-tracer`).
+    FileTracer,
+    /// Logs every `connect` call with its peer address and result, until
+    /// ejected. Linux only (`stock-net-logger`).
+    NetLogger,
+}
+
+impl StockAgent {
+    /// The Cargo feature that embeds the agent.
+    pub fn feature(self) -> &'static str {
+        match self {
+            StockAgent::EnvDumper => "stock-env-dumper",
+            StockAgent::FileTracer => "stock-file-tracer",
+            StockAgent::NetLogger => "stock-net-logger",
+        }
+    }
+
+    pub(crate) fn library(self) -> Result<Library> {
+        match self {
+            #[cfg(feature = "stock-env-dumper")]
+            StockAgent::EnvDumper => crate::embed_agent!("hook-inject-env-dumper"),
+            #[cfg(feature = "stock-file-tracer")]
+            StockAgent::FileTracer => crate::embed_agent!("hook-inject-file-tracer"),
+            #[cfg(feature = "stock-net-logger")]
+            StockAgent::NetLogger => crate::embed_agent!("hook-inject-net-logger"),
+            #[allow(unreachable_patterns)]
+            _ => Err(Error::not_supported(format_args!(
+                "the {self:?} stock agent is not embedded; enable the `{}` feature",
+                self.feature()
+            ))),
+        }
+    }
+}
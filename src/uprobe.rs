@@ -0,0 +1,703 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::{Error, Process, Result, process};
+
+/// Identifies a probe placed by an `UprobeTracer`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ProbeId(u32);
+
+/// One hit of a probe: a thread of the target reached the probed function,
+/// or returned from it for a return probe.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProbeHit {
+    probe: ProbeId,
+    pid: i32,
+    tid: i32,
+    time: Duration,
+    address: u64,
+    args: [u64; 6],
+    return_value: Option<u64>,
+}
+
+impl ProbeHit {
+    /// The probe that was hit.
+    pub fn probe(&self) -> ProbeId {
+        self.probe
+    }
+
+    /// The process the hit was in.
+    pub fn pid(&self) -> i32 {
+        self.pid
+    }
+
+    /// The thread that hit the probe.
+    pub fn tid(&self) -> i32 {
+        self.tid
+    }
+
+    /// When the probe was hit, on the `CLOCK_MONOTONIC` clock.
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// The instruction address the probe fired at: the function's entry,
+    /// or the return address for a return probe.
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    /// The first six integer or pointer arguments, as passed in registers.
+    ///
+    /// Only meaningful for entry probes; by the time a function returns,
+    /// those registers hold something else.
+    pub fn args(&self) -> [u64; 6] {
+        self.args
+    }
+
+    /// The integer or pointer return value, for return probes.
+    pub fn return_value(&self) -> Option<u64> {
+        self.return_value
+    }
+}
+
+/// Observes a process through uprobes, without loading anything into it.
+///
+/// Where policy forbids injecting code (no `ptrace`, no writable code in
+/// the target), the kernel can still report calls: each probe is a uprobe
+/// opened with `perf_event_open`, firing when any thread of the target
+/// enters a function, or returns from it, with the argument registers or
+/// return value. Nothing runs in the target and nothing in it can be
+/// changed; this is the hook-only subset of what an injected agent can do.
+///
+/// Probes cover the target's threads when they are placed and the threads
+/// those start later, and are removed when the tracer is dropped. Linux on
+/// x86_64 and aarch64; placing probes needs `CAP_PERFMON` (or
+/// `CAP_SYS_ADMIN`), and fails with `ErrorKind::PermissionDenied` without
+/// it.
+///
+/// # Examples
+/// ```no_run
+/// use std::time::Duration;
+/// use hook_inject::{Process, UprobeTracer};
+///
+/// let mut tracer = UprobeTracer::new(Process::from_pid(1234)?)?;
+/// let open = tracer.probe("libc.so.6", "open")?;
+/// let opened = tracer.return_probe("libc.so.6", "open")?;
+/// while let Some(hit) = tracer.next_hit(Duration::from_secs(5))? {
+///     if hit.probe() == open {
+///         println!("thread {} opens {:#x}", hit.tid(), hit.args()[0]);
+///     } else if hit.probe() == opened {
+///         println!("thread {} got fd {:?}", hit.tid(), hit.return_value());
+///     }
+/// }
+/// # Ok::<(), hook_inject::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct UprobeTracer {
+    process: Process,
+    start_time: Option<u64>,
+    probes: Vec<(ProbeId, sys::Probe)>,
+    pending: VecDeque<ProbeHit>,
+    lost: u64,
+    next_id: u32,
+}
+
+impl UprobeTracer {
+    /// Prepare to trace `process`; no probe is placed yet.
+    ///
+    /// Fails with `ErrorKind::NotSupported` where uprobes are unavailable:
+    /// other platforms, and kernels built without `CONFIG_UPROBE_EVENTS`.
+    pub fn new(process: Process) -> Result<Self> {
+        sys::check()?;
+        Ok(Self {
+            process,
+            start_time: process::start_time(process.pid()),
+            probes: Vec::new(),
+            pending: VecDeque::new(),
+            lost: 0,
+            next_id: 0,
+        })
+    }
+
+    /// The traced process.
+    pub fn process(&self) -> Process {
+        self.process
+    }
+
+    /// Probe calls to `symbol` in `module`, a path or the file name of an
+    /// image mapped in the target (e.g. `libc.so.6`).
+    ///
+    /// The symbol is looked up in the image's dynamic and static symbol
+    /// tables; stripped images only have the former. Fails with
+    /// `ErrorKind::InvalidInput` if the module is not mapped or does not
+    /// define the symbol.
+    pub fn probe(&mut self, module: impl AsRef<Path>, symbol: &str) -> Result<ProbeId> {
+        self.place(module.as_ref(), symbol, false)
+    }
+
+    /// Probe returns from `symbol` in `module`, like `probe`.
+    pub fn return_probe(&mut self, module: impl AsRef<Path>, symbol: &str) -> Result<ProbeId> {
+        self.place(module.as_ref(), symbol, true)
+    }
+
+    /// Remove `probe`, dropping its unread hits. Returns false if it was
+    /// already removed.
+    pub fn remove(&mut self, probe: ProbeId) -> bool {
+        let before = self.probes.len();
+        self.probes.retain(|(id, _)| *id != probe);
+        self.pending.retain(|hit| hit.probe != probe);
+        self.probes.len() != before
+    }
+
+    /// Wait up to `timeout` for the next hit of any probe.
+    ///
+    /// Returns `None` on timeout, and once the target has exited and every
+    /// hit has been read.
+    pub fn next_hit(&mut self, timeout: Duration) -> Result<Option<ProbeHit>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            self.drain();
+            if let Some(hit) = self.pending.pop_front() {
+                return Ok(Some(hit));
+            }
+            let now = Instant::now();
+            if now >= deadline || process::has_exited(self.process.pid(), self.start_time) {
+                return Ok(None);
+            }
+            sys::wait(self.probes.iter().map(|(_, probe)| probe), deadline - now)?;
+        }
+    }
+
+    /// Hits the kernel dropped because they were not read fast enough.
+    pub fn lost(&self) -> u64 {
+        self.lost
+    }
+
+    fn place(&mut self, module: &Path, symbol: &str, retprobe: bool) -> Result<ProbeId> {
+        let path = self.mapped_path(module)?;
+        let bytes = std::fs::read(&path)?;
+        let offset = sys::symbol_offset(&bytes, symbol)?.ok_or_else(|| {
+            Error::invalid_input(format_args!(
+                "{symbol} is not defined in {}",
+                module.display()
+            ))
+        })?;
+        let id = ProbeId(self.next_id);
+        let probe = sys::Probe::open(self.process.pid(), &path, offset, retprobe)?;
+        self.next_id += 1;
+        self.probes.push((id, probe));
+        Ok(id)
+    }
+
+    /// The path of `module` as mapped in the target, reached through its
+    /// root so images in containers are found.
+    fn mapped_path(&self, module: &Path) -> Result<PathBuf> {
+        let pid = self.process.pid();
+        let regions = self.process.memory_maps()?;
+        let mapped = regions
+            .iter()
+            .filter_map(|region| region.path())
+            .find(|path| *path == module || path.file_name() == Some(module.as_os_str()))
+            .ok_or_else(|| {
+                Error::invalid_input(format_args!(
+                    "{} is not mapped in pid {pid}",
+                    module.display()
+                ))
+            })?;
+        let relative = mapped.strip_prefix("/").unwrap_or(mapped);
+        Ok(PathBuf::from(format!("/proc/{pid}/root")).join(relative))
+    }
+
+    fn drain(&mut self) {
+        for (id, probe) in &mut self.probes {
+            self.lost += probe.drain(*id, &mut self.pending);
+        }
+    }
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod sys {
+    use std::collections::VecDeque;
+    use std::ffi::CString;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Duration;
+
+    use super::{ProbeHit, ProbeId};
+    use crate::{Error, Result, image};
+
+    const PMU_DIR: &str = "/sys/bus/event_source/devices/uprobe";
+
+    // perf_event.h
+    const PERF_SAMPLE_IP: u64 = 1 << 0;
+    const PERF_SAMPLE_TID: u64 = 1 << 1;
+    const PERF_SAMPLE_TIME: u64 = 1 << 2;
+    const PERF_SAMPLE_REGS_USER: u64 = 1 << 12;
+    const ATTR_FLAG_INHERIT: u64 = 1 << 1;
+    const ATTR_FLAG_USE_CLOCKID: u64 = 1 << 25;
+    const PERF_FLAG_FD_CLOEXEC: libc::c_ulong = 1 << 3;
+    const PERF_EVENT_IOC_SET_OUTPUT: libc::c_ulong = 0x2405;
+    const PERF_RECORD_LOST: u32 = 2;
+    const PERF_RECORD_SAMPLE: u32 = 9;
+    const PERF_SAMPLE_REGS_ABI_NONE: u64 = 0;
+    // Offsets in `perf_event_mmap_page`.
+    const DATA_HEAD: usize = 1024;
+    const DATA_TAIL: usize = 1032;
+    const DATA_OFFSET: usize = 1040;
+    const DATA_SIZE: usize = 1048;
+
+    // Pages of samples per CPU and probe, a power of two.
+    const DATA_PAGES: usize = 8;
+    // How long to back off when only hung-up events are ready.
+    const HANGUP_BACKOFF: Duration = Duration::from_millis(20);
+
+    // The argument registers in perf's numbering, AX, CX, DX, SI, DI, R8 and
+    // R9, sampled in that order.
+    #[cfg(target_arch = "x86_64")]
+    const SAMPLE_REGS: u64 = 1 | 1 << 2 | 1 << 3 | 1 << 4 | 1 << 5 | 1 << 16 | 1 << 17;
+    // X0 to X5.
+    #[cfg(target_arch = "aarch64")]
+    const SAMPLE_REGS: u64 = 0x3f;
+
+    /// The arguments and return value in the sampled registers.
+    #[cfg(target_arch = "x86_64")]
+    fn split_regs(regs: &[u64]) -> ([u64; 6], u64) {
+        (
+            [regs[4], regs[3], regs[2], regs[1], regs[5], regs[6]],
+            regs[0],
+        )
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    fn split_regs(regs: &[u64]) -> ([u64; 6], u64) {
+        (
+            [regs[0], regs[1], regs[2], regs[3], regs[4], regs[5]],
+            regs[0],
+        )
+    }
+
+    /// `struct perf_event_attr`, as of `PERF_ATTR_SIZE_VER7`; older kernels
+    /// accept it as long as the fields they don't know are zero.
+    #[repr(C)]
+    #[derive(Default)]
+    struct PerfEventAttr {
+        kind: u32,
+        size: u32,
+        config: u64,
+        sample_period: u64,
+        sample_type: u64,
+        read_format: u64,
+        flags: u64,
+        wakeup_events: u32,
+        bp_type: u32,
+        // `uprobe_path` for uprobes.
+        config1: u64,
+        // `probe_offset` for uprobes.
+        config2: u64,
+        branch_sample_type: u64,
+        sample_regs_user: u64,
+        sample_stack_user: u32,
+        clockid: i32,
+        sample_regs_intr: u64,
+        aux_watermark: u32,
+        sample_max_stack: u16,
+        reserved_2: u16,
+        aux_sample_size: u32,
+        reserved_3: u32,
+        sig_data: u64,
+    }
+
+    pub(super) fn check() -> Result<()> {
+        pmu_type().map(|_| ())
+    }
+
+    pub(super) fn symbol_offset(bytes: &[u8], symbol: &str) -> Result<Option<u64>> {
+        image::symbol_file_offset(bytes, symbol)
+    }
+
+    /// Wait up to `timeout` for any of `probes` to have samples.
+    pub(super) fn wait<'a>(
+        probes: impl Iterator<Item = &'a Probe>,
+        timeout: Duration,
+    ) -> Result<()> {
+        let mut fds: Vec<_> = probes
+            .flat_map(|probe| &probe.rings)
+            .map(|ring| libc::pollfd {
+                fd: ring.event,
+                events: libc::POLLIN,
+                revents: 0,
+            })
+            .collect();
+        let millis = timeout.as_millis().min(i32::MAX as u128) as i32;
+        let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, millis) };
+        if ready < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == std::io::ErrorKind::Interrupted {
+                return Ok(());
+            }
+            return Err(Error::from(err));
+        }
+        // Events of exited threads stay readable; don't spin on them.
+        if ready > 0 && fds.iter().all(|fd| fd.revents & libc::POLLIN == 0) {
+            std::thread::sleep(HANGUP_BACKOFF.min(timeout));
+        }
+        Ok(())
+    }
+
+    /// A uprobe on every thread of a process.
+    ///
+    /// As with `perf record`, there is an event per thread and CPU, sampling
+    /// into a ring per CPU: inherited events, which cover the threads
+    /// started later, cannot be mapped per thread.
+    #[derive(Debug)]
+    pub(super) struct Probe {
+        rings: Vec<Ring>,
+        // Held open to keep the probe placed; dropped after the rings.
+        _events: Vec<OwnedFd>,
+        retprobe: bool,
+    }
+
+    impl Probe {
+        pub(super) fn open(pid: i32, path: &Path, offset: u64, retprobe: bool) -> Result<Self> {
+            let path_c = CString::new(path.as_os_str().as_bytes())
+                .map_err(|_| Error::invalid_input("module path contains a NUL byte"))?;
+            let mut attr = PerfEventAttr {
+                kind: pmu_type()?,
+                size: std::mem::size_of::<PerfEventAttr>() as u32,
+                config: if retprobe { 1 << retprobe_bit()? } else { 0 },
+                sample_period: 1,
+                sample_type: PERF_SAMPLE_IP
+                    | PERF_SAMPLE_TID
+                    | PERF_SAMPLE_TIME
+                    | PERF_SAMPLE_REGS_USER,
+                flags: ATTR_FLAG_INHERIT | ATTR_FLAG_USE_CLOCKID,
+                wakeup_events: 1,
+                config1: path_c.as_ptr() as u64,
+                config2: offset,
+                sample_regs_user: SAMPLE_REGS,
+                clockid: libc::CLOCK_MONOTONIC,
+                ..PerfEventAttr::default()
+            };
+
+            let threads = threads(pid)?;
+            let mut rings: Vec<Ring> = Vec::new();
+            let mut events = Vec::new();
+            for cpu in online_cpus()? {
+                let mut ring = None;
+                for &tid in &threads {
+                    let event = match open_event(&mut attr, tid, cpu) {
+                        Ok(event) => event,
+                        // The thread exited since it was listed.
+                        Err(err) if err.raw_os_error() == Some(libc::ESRCH) => continue,
+                        Err(err) => return Err(open_error(err, path)),
+                    };
+                    match ring {
+                        None => {
+                            rings.push(Ring::map(&event)?);
+                            ring = Some(event.as_raw_fd());
+                        }
+                        Some(ring) => {
+                            let redirected = unsafe {
+                                libc::ioctl(event.as_raw_fd(), PERF_EVENT_IOC_SET_OUTPUT as _, ring)
+                            };
+                            if redirected < 0 {
+                                return Err(Error::from(std::io::Error::last_os_error()));
+                            }
+                        }
+                    }
+                    events.push(event);
+                }
+            }
+            if events.is_empty() {
+                return Err(Error::process_not_found(pid));
+            }
+            Ok(Self {
+                rings,
+                _events: events,
+                retprobe,
+            })
+        }
+
+        /// Decode the samples in the ring into `hits`, returning how many
+        /// the kernel dropped.
+        pub(super) fn drain(&mut self, id: ProbeId, hits: &mut VecDeque<ProbeHit>) -> u64 {
+            let retprobe = self.retprobe;
+            let mut lost = 0;
+            for ring in &mut self.rings {
+                ring.drain(|kind, body| match kind {
+                    PERF_RECORD_SAMPLE => hits.extend(decode(id, retprobe, body)),
+                    PERF_RECORD_LOST => lost += read_u64(body, 8).unwrap_or(0),
+                    _ => {}
+                });
+            }
+            lost
+        }
+    }
+
+    /// A sample laid out as `sample_type` asks: ip, pid and tid, time, then
+    /// the register ABI and the registers.
+    fn decode(probe: ProbeId, retprobe: bool, body: &[u8]) -> Option<ProbeHit> {
+        let address = read_u64(body, 0)?;
+        let pid = read_u32(body, 8)? as i32;
+        let tid = read_u32(body, 12)? as i32;
+        let time = Duration::from_nanos(read_u64(body, 16)?);
+        let (args, return_value) = match read_u64(body, 24)? {
+            // A kernel thread, which has no user registers.
+            PERF_SAMPLE_REGS_ABI_NONE => ([0; 6], None),
+            _ => {
+                let regs = (0..SAMPLE_REGS.count_ones() as usize)
+                    .map(|index| read_u64(body, 32 + index * 8))
+                    .collect::<Option<Vec<_>>>()?;
+                let (args, returned) = split_regs(&regs);
+                (args, retprobe.then_some(returned))
+            }
+        };
+        Some(ProbeHit {
+            probe,
+            pid,
+            tid,
+            time,
+            address,
+            args: if retprobe { [0; 6] } else { args },
+            return_value,
+        })
+    }
+
+    fn read_u64(bytes: &[u8], at: usize) -> Option<u64> {
+        Some(u64::from_ne_bytes(bytes.get(at..at + 8)?.try_into().ok()?))
+    }
+
+    fn read_u32(bytes: &[u8], at: usize) -> Option<u32> {
+        Some(u32::from_ne_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+    }
+
+    /// The perf ring buffer of an event: a control page, then the data.
+    #[derive(Debug)]
+    struct Ring {
+        // The event the ring belongs to, to poll.
+        event: libc::c_int,
+        base: *mut u8,
+        len: usize,
+        data_offset: usize,
+        data_size: usize,
+    }
+
+    // The mapping is only touched through `&mut self`.
+    unsafe impl Send for Ring {}
+    unsafe impl Sync for Ring {}
+
+    impl Ring {
+        fn map(event: &OwnedFd) -> Result<Self> {
+            let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+            let len = page * (1 + DATA_PAGES);
+            let base = unsafe {
+                libc::mmap(
+                    std::ptr::null_mut(),
+                    len,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED,
+                    event.as_raw_fd(),
+                    0,
+                )
+            };
+            if base == libc::MAP_FAILED {
+                return Err(Error::from(std::io::Error::last_os_error()));
+            }
+            let base = base.cast::<u8>();
+            // Kernels before 4.1 leave these zero; the data then follows the
+            // control page.
+            let (data_offset, data_size) = unsafe {
+                (
+                    base.add(DATA_OFFSET).cast::<u64>().read_volatile() as usize,
+                    base.add(DATA_SIZE).cast::<u64>().read_volatile() as usize,
+                )
+            };
+            let (data_offset, data_size) = match data_size {
+                0 => (page, page * DATA_PAGES),
+                _ => (data_offset, data_size),
+            };
+            Ok(Self {
+                event: event.as_raw_fd(),
+                base,
+                len,
+                data_offset,
+                data_size,
+            })
+        }
+
+        /// Pass each record written since the last call to `record`, as its
+        /// type and body, and hand the space back to the kernel.
+        fn drain(&mut self, mut record: impl FnMut(u32, &[u8])) {
+            let (head, tail) = unsafe {
+                (
+                    &*self.base.add(DATA_HEAD).cast::<AtomicU64>(),
+                    &*self.base.add(DATA_TAIL).cast::<AtomicU64>(),
+                )
+            };
+            let end = head.load(Ordering::Acquire);
+            let mut at = tail.load(Ordering::Relaxed);
+            let mut bytes = Vec::new();
+            while at < end {
+                let mut header = [0u8; 8];
+                self.copy(at, &mut header);
+                let kind = u32::from_ne_bytes([header[0], header[1], header[2], header[3]]);
+                let size = u16::from_ne_bytes([header[6], header[7]]) as usize;
+                if size < header.len() {
+                    break;
+                }
+                bytes.resize(size - header.len(), 0);
+                self.copy(at + header.len() as u64, &mut bytes);
+                record(kind, &bytes);
+                at += size as u64;
+            }
+            tail.store(at, Ordering::Release);
+        }
+
+        /// Copy out the bytes at ring position `at`, which may wrap.
+        fn copy(&self, at: u64, out: &mut [u8]) {
+            let start = (at % self.data_size as u64) as usize;
+            let first = out.len().min(self.data_size - start);
+            unsafe {
+                let data = self.base.add(self.data_offset);
+                std::ptr::copy_nonoverlapping(data.add(start), out.as_mut_ptr(), first);
+                std::ptr::copy_nonoverlapping(data, out.as_mut_ptr().add(first), out.len() - first);
+            }
+        }
+    }
+
+    impl Drop for Ring {
+        fn drop(&mut self) {
+            unsafe { libc::munmap(self.base.cast(), self.len) };
+        }
+    }
+
+    fn open_event(attr: &mut PerfEventAttr, tid: i32, cpu: i32) -> std::io::Result<OwnedFd> {
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_perf_event_open,
+                attr as *mut PerfEventAttr,
+                tid,
+                cpu,
+                -1,
+                PERF_FLAG_FD_CLOEXEC,
+            )
+        };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(unsafe { OwnedFd::from_raw_fd(fd as libc::c_int) })
+    }
+
+    fn open_error(err: std::io::Error, path: &Path) -> Error {
+        match err.raw_os_error() {
+            Some(libc::EACCES | libc::EPERM) => Error::permission_denied(format_args!(
+                "placing uprobes needs CAP_PERFMON or CAP_SYS_ADMIN: {err}"
+            )),
+            Some(libc::EINVAL) => Error::invalid_input(format_args!(
+                "the kernel refused a uprobe in {}: {err}",
+                path.display()
+            )),
+            _ => Error::from(err),
+        }
+    }
+
+    fn threads(pid: i32) -> Result<Vec<i32>> {
+        let entries =
+            std::fs::read_dir(format!("/proc/{pid}/task")).map_err(|err| match err.kind() {
+                std::io::ErrorKind::NotFound => Error::process_not_found(pid),
+                _ => Error::from(err),
+            })?;
+        Ok(entries
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+            .collect())
+    }
+
+    /// The online CPUs, from a list like `0-3,6`.
+    fn online_cpus() -> Result<Vec<i32>> {
+        let list = std::fs::read_to_string("/sys/devices/system/cpu/online")?;
+        let mut cpus = Vec::new();
+        for range in list.trim().split(',').filter(|range| !range.is_empty()) {
+            let (first, last) = range.split_once('-').unwrap_or((range, range));
+            match (first.parse::<i32>(), last.parse::<i32>()) {
+                (Ok(first), Ok(last)) => cpus.extend(first..=last),
+                _ => return Err(Error::runtime(format_args!("unreadable CPU list: {list}"))),
+            }
+        }
+        Ok(cpus)
+    }
+
+    fn pmu_type() -> Result<u32> {
+        read_pmu_file("type")?
+            .trim()
+            .parse()
+            .map_err(|_| Error::runtime("unreadable uprobe PMU type"))
+    }
+
+    /// The `config` bit that makes a uprobe a return probe.
+    fn retprobe_bit() -> Result<u32> {
+        read_pmu_file("format/retprobe")?
+            .trim()
+            .strip_prefix("config:")
+            .and_then(|bit| bit.parse().ok())
+            .ok_or_else(|| Error::runtime("unreadable uprobe PMU retprobe format"))
+    }
+
+    fn read_pmu_file(name: &str) -> Result<String> {
+        std::fs::read_to_string(format!("{PMU_DIR}/{name}")).map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => {
+                Error::not_supported("this kernel has no uprobe PMU (CONFIG_UPROBE_EVENTS)")
+            }
+            _ => Error::from(err),
+        })
+    }
+}
+
+#[cfg(not(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+)))]
+mod sys {
+    use std::collections::VecDeque;
+    use std::path::Path;
+    use std::time::Duration;
+
+    use super::{ProbeHit, ProbeId};
+    use crate::{Error, Result};
+
+    #[derive(Debug)]
+    pub(super) struct Probe;
+
+    impl Probe {
+        pub(super) fn open(_pid: i32, _path: &Path, _offset: u64, _retprobe: bool) -> Result<Self> {
+            check().map(|()| Probe)
+        }
+
+        pub(super) fn drain(&mut self, _id: ProbeId, _hits: &mut VecDeque<ProbeHit>) -> u64 {
+            0
+        }
+    }
+
+    pub(super) fn check() -> Result<()> {
+        Err(Error::not_supported(
+            "uprobes are only supported on Linux (x86_64 and aarch64)",
+        ))
+    }
+
+    pub(super) fn symbol_offset(_bytes: &[u8], _symbol: &str) -> Result<Option<u64>> {
+        check().map(|()| None)
+    }
+
+    pub(super) fn wait<'a>(
+        _probes: impl Iterator<Item = &'a Probe>,
+        _timeout: Duration,
+    ) -> Result<()> {
+        check()
+    }
+}
@@ -0,0 +1,55 @@
+use std::fmt;
+
+use crate::backend;
+
+/// The injection runtime compiled into this build (see `runtime_version`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeVersion {
+    version: Option<String>,
+    platform: Option<&'static str>,
+}
+
+impl RuntimeVersion {
+    /// The frida-core version, as the linked library reports it. Unknown in
+    /// builds without the `frida` feature.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// The devkit platform the crate was built against, such as
+    /// `linux-x86_64`.
+    ///
+    /// Unknown when the devkit came from `FRIDA_CORE_DEVKIT_DIR` and the
+    /// platform could not be detected.
+    pub fn platform(&self) -> Option<&str> {
+        self.platform
+    }
+}
+
+impl fmt::Display for RuntimeVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "frida-core {} ({})",
+            self.version().unwrap_or("unknown"),
+            self.platform().unwrap_or("unknown platform")
+        )
+    }
+}
+
+/// Report the frida-core version this build links and the devkit platform
+/// it was built for.
+///
+/// Doesn't set the runtime up, so it is cheap to include in logs and bug
+/// reports.
+///
+/// # Examples
+/// ```
+/// println!("{}", hook_inject::runtime_version()); // frida-core 17.7.3 (linux-x86_64)
+/// ```
+pub fn runtime_version() -> RuntimeVersion {
+    RuntimeVersion {
+        version: backend::frida_version(),
+        platform: option_env!("HOOK_INJECT_BUILT_DEVKIT_PLATFORM"),
+    }
+}
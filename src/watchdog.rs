@@ -0,0 +1,297 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Sender, TryRecvError};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::{Clock, Health, InjectedProcess, InjectionEventKind, InjectionId, SystemClock, notify};
+
+// How often a waiting watchdog looks for a stop or unload signal.
+const SIGNAL_POLL: Duration = Duration::from_millis(50);
+
+/// Keeps an agent injected, re-injecting it when it goes away.
+///
+/// A watched agent is considered lost when the runtime reports it unloaded
+/// (see `InjectedProcess::on_unloaded`), when it is no longer mapped in the
+/// target, or, with `heartbeat_timeout`, when its heartbeats stop (see
+/// `InjectedProcess::health`). A lost agent is re-injected with
+/// `InjectedProcess::reinject`; failed attempts are retried with
+/// exponential backoff, up to `max_retries` in a row, after which the
+/// watchdog reports `InjectionEventKind::AgentLost` and stops. Watching
+/// also stops when the target exits.
+///
+/// # Examples
+/// ```no_run
+/// use std::time::Duration;
+/// use hook_inject::{inject_process, Library, Process, Watchdog};
+///
+/// let process = Process::from_pid(1234)?;
+/// let injected = inject_process(process, Library::from_path("/path/to/libagent.so")?)?;
+/// let watched = Watchdog::new()
+///     .heartbeat_timeout(Duration::from_secs(30))
+///     .max_retries(3)
+///     .watch(injected);
+/// // ... later:
+/// let injected = watched.stop();
+/// injected.uninject()?;
+/// # Ok::<(), hook_inject::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Watchdog {
+    check_interval: Duration,
+    backoff: Duration,
+    max_backoff: Duration,
+    max_retries: u32,
+    heartbeat_timeout: Option<Duration>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self {
+            check_interval: Duration::from_secs(1),
+            backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            max_retries: 5,
+            heartbeat_timeout: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
+
+impl Watchdog {
+    /// Create a watchdog with the default policy: checks every second,
+    /// backoff from 500ms up to 30s, and 5 retries.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How often to check the agent between unload reports (default 1s).
+    pub fn check_interval(mut self, interval: Duration) -> Self {
+        self.check_interval = interval;
+        self
+    }
+
+    /// Wait before retrying a failed re-injection, doubled after each
+    /// further failure up to `max_backoff` (default 500ms).
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Longest wait between retries (default 30s).
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Give up after this many failed re-injections in a row (default 5).
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    /// Also treat the agent as lost when its last heartbeat is older than
+    /// `timeout` (off by default).
+    ///
+    /// Agents that do not send heartbeats, or have not sent one yet, are
+    /// never lost this way. A wedged agent is ejected before it is
+    /// re-injected.
+    pub fn heartbeat_timeout(mut self, timeout: Duration) -> Self {
+        self.heartbeat_timeout = Some(timeout);
+        self
+    }
+
+    /// Time check intervals, backoff and heartbeats with the given clock.
+    ///
+    /// With a `ManualClock` the checks run back to back, each advancing the
+    /// clock by the interval or backoff it would have waited.
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Start watching `injected` from a background thread.
+    pub fn watch(self, injected: InjectedProcess) -> WatchedInjection {
+        let (sender, receiver) = mpsc::channel();
+        let restarts = Arc::new(AtomicU32::new(0));
+        let supervisor = Supervisor {
+            policy: self,
+            sender: sender.clone(),
+            restarts: restarts.clone(),
+        };
+        let thread = std::thread::Builder::new()
+            .name("hook-inject-watchdog".into())
+            .spawn(move || supervisor.run(injected, receiver))
+            .expect("failed to spawn watchdog thread");
+        WatchedInjection {
+            sender,
+            thread: Some(thread),
+            restarts,
+        }
+    }
+}
+
+/// An injection kept alive by a `Watchdog`.
+///
+/// Dropping it stops the watchdog and drops the injection handle, which
+/// leaves the agent injected unless the handle was set to
+/// `uninject_on_drop`.
+#[derive(Debug)]
+pub struct WatchedInjection {
+    sender: Sender<Signal>,
+    thread: Option<JoinHandle<InjectedProcess>>,
+    restarts: Arc<AtomicU32>,
+}
+
+impl WatchedInjection {
+    /// How many times the agent was re-injected.
+    pub fn restarts(&self) -> u32 {
+        self.restarts.load(Ordering::SeqCst)
+    }
+
+    /// Returns true until the watchdog gives up or the target exits.
+    pub fn is_watching(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_some_and(|thread| !thread.is_finished())
+    }
+
+    /// Stop watching and take back the injection handle, which points at the
+    /// latest injection.
+    pub fn stop(mut self) -> InjectedProcess {
+        self.join()
+    }
+
+    fn join(&mut self) -> InjectedProcess {
+        let _ = self.sender.send(Signal::Stop);
+        let thread = self.thread.take().expect("watchdog joined twice");
+        thread
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    }
+}
+
+impl Drop for WatchedInjection {
+    fn drop(&mut self) {
+        if self.thread.is_some() {
+            drop(self.join());
+        }
+    }
+}
+
+enum Signal {
+    Stop,
+    Unloaded(InjectionId),
+}
+
+struct Supervisor {
+    policy: Watchdog,
+    sender: Sender<Signal>,
+    restarts: Arc<AtomicU32>,
+}
+
+impl Supervisor {
+    fn run(
+        self,
+        mut injected: InjectedProcess,
+        signals: mpsc::Receiver<Signal>,
+    ) -> InjectedProcess {
+        self.listen(&injected);
+        let mut failures = 0;
+        let mut wait = self.policy.check_interval;
+        loop {
+            match self.wait(&signals, wait) {
+                Some(Signal::Stop) => return injected,
+                // Unloads of injections the watchdog already replaced.
+                Some(Signal::Unloaded(id)) if id != injected.id() => continue,
+                Some(Signal::Unloaded(_)) | None => {}
+            }
+            wait = self.policy.check_interval;
+
+            if injected.wait_target_exit(Duration::ZERO).is_ok() {
+                self.emit(&injected, InjectionEventKind::TargetExited);
+                return injected;
+            }
+            if !self.is_lost(&injected) {
+                failures = 0;
+                continue;
+            }
+            if failures >= self.policy.max_retries {
+                self.emit(&injected, InjectionEventKind::AgentLost);
+                return injected;
+            }
+
+            let library = injected.library.clone();
+            match injected.reinject(library) {
+                Ok(()) => {
+                    failures = 0;
+                    self.restarts.fetch_add(1, Ordering::SeqCst);
+                    self.listen(&injected);
+                }
+                Err(_) => {
+                    wait = self.backoff(failures);
+                    failures += 1;
+                }
+            }
+        }
+    }
+
+    fn is_lost(&self, injected: &InjectedProcess) -> bool {
+        match injected.is_loaded() {
+            Ok(true) => self.policy.heartbeat_timeout.is_some_and(|timeout| {
+                matches!(
+                    injected.health(),
+                    Ok(health @ Health::LastSeen(_))
+                        if !health.is_alive_with(timeout, &*self.policy.clock)
+                )
+            }),
+            Ok(false) => true,
+            // Transient, e.g. the memory map could not be read; check again.
+            Err(_) => false,
+        }
+    }
+
+    /// Wait up to `duration` on the policy's clock for a signal; a dropped
+    /// sender reads as `Stop`.
+    fn wait(&self, signals: &mpsc::Receiver<Signal>, duration: Duration) -> Option<Signal> {
+        let clock = &self.policy.clock;
+        let deadline = clock.now() + duration;
+        loop {
+            match signals.try_recv() {
+                Ok(signal) => return Some(signal),
+                Err(TryRecvError::Disconnected) => return Some(Signal::Stop),
+                Err(TryRecvError::Empty) => {}
+            }
+            let now = clock.now();
+            if now >= deadline {
+                return None;
+            }
+            clock.sleep(SIGNAL_POLL.min(deadline - now));
+        }
+    }
+
+    fn backoff(&self, failures: u32) -> Duration {
+        self.policy
+            .backoff
+            .saturating_mul(2u32.saturating_pow(failures))
+            .min(self.policy.max_backoff)
+    }
+
+    fn listen(&self, injected: &InjectedProcess) {
+        let sender = self.sender.clone();
+        let id = injected.id();
+        injected.on_unloaded(move || {
+            let _ = sender.send(Signal::Unloaded(id));
+        });
+    }
+
+    fn emit(&self, injected: &InjectedProcess, kind: InjectionEventKind) {
+        notify::emit(
+            injected.notifier.as_ref(),
+            kind,
+            injected.process(),
+            injected.label(),
+        );
+    }
+}
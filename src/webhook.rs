@@ -0,0 +1,289 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{Clock, Error, InjectionEvent, Notifier, Result, SystemClock};
+
+const SIGNATURE_HEADER: &str = "X-Hook-Inject-Signature";
+const EVENT_HEADER: &str = "X-Hook-Inject-Event";
+
+/// Notifier that POSTs each event as JSON to an HTTP endpoint (requires the
+/// `webhook` feature).
+///
+/// Events are queued and delivered in order by one background thread per
+/// notifier, each retried with exponential backoff on connection errors and
+/// non-2xx responses. Dropping the notifier waits for the queue to drain, up
+/// to `drain_timeout`, then leaves the rest to the thread. Clones start out
+/// with a queue of their own. With a secret set,
+/// every request carries an `X-Hook-Inject-Signature: sha256=<hex>` header
+/// holding the HMAC-SHA256 of the body; receivers can check it with
+/// `WebhookNotifier::verify`. Requests are made with the `curl` executable.
+///
+/// # Examples
+/// ```no_run
+/// use hook_inject::{InjectOptions, WebhookNotifier};
+///
+/// let webhook = WebhookNotifier::new("https://fleet.example.com/hooks/inject")
+///     .secret("shared-secret")
+///     .max_attempts(5);
+/// let options = InjectOptions::new().notifier(webhook);
+/// ```
+pub struct WebhookNotifier {
+    url: String,
+    secret: Option<Vec<u8>>,
+    max_attempts: u32,
+    backoff: Duration,
+    timeout: Duration,
+    drain_timeout: Duration,
+    clock: Arc<dyn Clock>,
+    worker: Mutex<Worker>,
+}
+
+// The delivery thread, started by the first event. `exited` disconnects
+// once the thread is done.
+#[derive(Default)]
+struct Worker {
+    queue: Option<mpsc::Sender<InjectionEvent>>,
+    exited: Option<mpsc::Receiver<()>>,
+}
+
+impl WebhookNotifier {
+    /// Post events to `url`.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            secret: None,
+            max_attempts: 3,
+            backoff: Duration::from_millis(500),
+            timeout: Duration::from_secs(10),
+            drain_timeout: Duration::from_secs(10),
+            clock: Arc::new(SystemClock),
+            worker: Mutex::default(),
+        }
+    }
+
+    /// Sign request bodies with this shared secret.
+    pub fn secret(mut self, secret: impl Into<Vec<u8>>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    /// Total number of delivery attempts per event (default 3, minimum 1).
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Delay before the first retry; doubled after each failure (default 500ms).
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Per-request timeout (default 10s).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// How long dropping the notifier waits for queued events to be
+    /// delivered (default 10s). Events still queued after that are
+    /// delivered by the background thread if the process lives on.
+    pub fn drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// Measure retry delays with the given clock.
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Deliver one event synchronously, retrying as configured.
+    ///
+    /// Returns the last error if every attempt fails.
+    pub fn deliver(&self, event: &InjectionEvent) -> Result<()> {
+        let body = event.to_json();
+        let mut delay = self.backoff;
+        let mut attempt = 1;
+        loop {
+            match self.post(event, &body) {
+                Ok(()) => return Ok(()),
+                Err(err) if attempt >= self.max_attempts => return Err(err),
+                Err(_) => {
+                    self.clock.sleep(delay);
+                    delay = delay.saturating_mul(2);
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Check a signature header value against a request body.
+    ///
+    /// # Examples
+    /// ```
+    /// use hook_inject::WebhookNotifier;
+    ///
+    /// let header = "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843";
+    /// assert!(WebhookNotifier::verify(b"Jefe", b"what do ya want for nothing?", header));
+    /// ```
+    pub fn verify(secret: &[u8], body: &[u8], header: &str) -> bool {
+        let Some(hex) = header.strip_prefix("sha256=") else {
+            return false;
+        };
+        let Some(expected) = decode_hex(hex) else {
+            return false;
+        };
+        let mut mac = new_mac(secret);
+        mac.update(body);
+        mac.verify_slice(&expected).is_ok()
+    }
+
+    fn post(&self, event: &InjectionEvent, body: &str) -> Result<()> {
+        let mut cmd = Command::new("curl");
+        cmd.arg("--silent")
+            .arg("--show-error")
+            .arg("--fail")
+            .arg("--max-time")
+            .arg(self.timeout.as_secs_f64().to_string())
+            .arg("--request")
+            .arg("POST")
+            .arg("--header")
+            .arg("Content-Type: application/json")
+            .arg("--header")
+            .arg(format!("{EVENT_HEADER}: {}", event.kind()))
+            .arg("--data-binary")
+            .arg("@-")
+            .arg("--output")
+            .arg(if cfg!(windows) { "NUL" } else { "/dev/null" });
+        if let Some(secret) = &self.secret {
+            cmd.arg("--header").arg(format!(
+                "{SIGNATURE_HEADER}: {}",
+                sign(secret, body.as_bytes())
+            ));
+        }
+        // `--url` keeps a URL starting with `-` from being read as an option.
+        cmd.arg("--url")
+            .arg(&self.url)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped());
+
+        let mut child = cmd
+            .spawn()
+            .map_err(|err| Error::runtime(format_args!("failed to run curl: {err}")))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(body.as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(Error::runtime(format_args!(
+                "webhook delivery to {} failed: {}",
+                self.url,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Clone for WebhookNotifier {
+    fn clone(&self) -> Self {
+        Self {
+            url: self.url.clone(),
+            secret: self.secret.clone(),
+            max_attempts: self.max_attempts,
+            backoff: self.backoff,
+            timeout: self.timeout,
+            drain_timeout: self.drain_timeout,
+            clock: Arc::clone(&self.clock),
+            worker: Mutex::default(),
+        }
+    }
+}
+
+impl std::fmt::Debug for WebhookNotifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never print the secret.
+        f.debug_struct("WebhookNotifier")
+            .field("url", &self.url)
+            .field("signed", &self.secret.is_some())
+            .field("max_attempts", &self.max_attempts)
+            .field("backoff", &self.backoff)
+            .field("timeout", &self.timeout)
+            .field("drain_timeout", &self.drain_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn notify(&self, event: &InjectionEvent) {
+        let mut worker = self
+            .worker
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let Worker { queue, exited } = &mut *worker;
+        let queue = queue.get_or_insert_with(|| {
+            let (sender, receiver) = mpsc::channel::<InjectionEvent>();
+            let (done, done_receiver) = mpsc::channel::<()>();
+            let notifier = self.clone();
+            let spawned = std::thread::Builder::new()
+                .name("hook-inject-webhook".into())
+                .spawn(move || {
+                    let _done = done;
+                    for event in receiver {
+                        let _ = notifier.deliver(&event);
+                    }
+                });
+            *exited = spawned.ok().map(|_| done_receiver);
+            sender
+        });
+        let _ = queue.send(event.clone());
+    }
+}
+
+impl Drop for WebhookNotifier {
+    fn drop(&mut self) {
+        let worker = self
+            .worker
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        // Closing the queue lets the thread deliver what is left, then exit;
+        // past the drain timeout it is left to finish on its own.
+        worker.queue = None;
+        if let Some(exited) = worker.exited.take() {
+            let _ = exited.recv_timeout(self.drain_timeout);
+        }
+    }
+}
+
+fn new_mac(secret: &[u8]) -> Hmac<Sha256> {
+    Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length")
+}
+
+fn sign(secret: &[u8], body: &[u8]) -> String {
+    let mut mac = new_mac(secret);
+    mac.update(body);
+    let digest = mac.finalize().into_bytes();
+    let mut out = String::from("sha256=");
+    for byte in digest {
+        out.push_str(&format!("{byte:02x}"));
+    }
+    out
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -0,0 +1,83 @@
+use hook_inject::{Architecture, ErrorKind, InjectOptions, Library, Process};
+
+// Both cases change HOOK_INJECT_ADB, so they share one test.
+#[cfg(unix)]
+#[test]
+fn adb_devices_come_from_adb() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir().join(format!("hook-inject-adb-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let adb = dir.join("adb");
+    std::fs::write(
+        &adb,
+        "#!/bin/sh\n\
+         case \"$*\" in\n\
+         'devices -l') printf '%s\\n' '* daemon started successfully' 'List of devices attached' \
+         'emulator-5554          device product:sdk_gphone64_x86_64 model:sdk_gphone64_x86_64 device:emu64xa transport_id:1' \
+         '2A221FDH2000EX         device usb:1-1 product:panther model:Pixel_7 device:panther transport_id:2' \
+         'ZY22         unauthorized usb:1-2 transport_id:3' '' ;;\n\
+         *getprop*) echo arm64-v8a ;;\n\
+         *' shell '*) ;;\n\
+         *' push '*) echo \"$4\" > \"$(dirname \"$0\")/pushed\" ;;\n\
+         *) echo \"error: $*\" >&2; exit 1 ;;\n\
+         esac\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&adb, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    unsafe { std::env::set_var("HOOK_INJECT_ADB", &adb) };
+    let devices = hook_inject::adb_devices().unwrap();
+    assert_eq!(devices.len(), 3);
+    assert_eq!(devices[0].serial(), "emulator-5554");
+    assert_eq!(devices[1].model(), Some("Pixel 7"));
+    assert!(devices[1].is_online());
+    assert_eq!(devices[1].architecture().unwrap(), Architecture::Aarch64);
+    assert!(!devices[2].is_online());
+    assert_eq!(devices[2].model(), None);
+    let err = devices[2].injector().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::RuntimeUnavailable);
+
+    let library = Library::from_device_path("/data/local/tmp/libagent.so").unwrap();
+    let err = devices[1]
+        .push_agent_as(&library, "com.example; rm -rf /")
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    // In-memory agents are staged where the options say before the push.
+    let staging = dir.join("staging");
+    let options = InjectOptions::new().staging_dir(&staging);
+    let blob = Library::from_bytes(b"\x7fELF agent".to_vec()).unwrap();
+    devices[1].push_agent_with(&blob, &options).unwrap();
+    let local = std::fs::read_to_string(dir.join("pushed")).unwrap();
+    assert!(
+        std::path::Path::new(local.trim()).starts_with(&staging),
+        "{local}"
+    );
+
+    unsafe { std::env::set_var("HOOK_INJECT_ADB", dir.join("missing")) };
+    let err = hook_inject::adb_devices().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::RuntimeUnavailable);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn device_paths_must_be_absolute() {
+    let err = Library::from_device_path("libagent.so").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn device_libraries_need_a_remote_injector() {
+    let library = Library::from_device_path("/data/local/tmp/libagent.so").unwrap();
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+    let err = hook_inject::inject_process(process, library).unwrap_err();
+    assert!(
+        matches!(
+            err.kind(),
+            ErrorKind::InvalidInput | ErrorKind::RuntimeUnavailable
+        ),
+        "{err}"
+    );
+}
@@ -0,0 +1,145 @@
+// Only where a native backend is built.
+#![cfg(any(
+    all(
+        any(target_os = "linux", target_os = "macos", windows),
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ),
+    all(
+        any(target_os = "linux", target_os = "macos"),
+        feature = "preload-backend"
+    )
+))]
+
+mod common;
+
+use std::process::{Command, Stdio};
+
+use common::{fixture_agent, stamp_path};
+use hook_inject::{Backend, ErrorKind, Injector, Library, Process};
+
+// A program that outlives each attempt, as a command line.
+#[cfg(unix)]
+const IDLE: &str = "sleep 30";
+#[cfg(windows)]
+const IDLE: &str = "ping -n 30 127.0.0.1";
+
+/// Something a backend is asked to do, given a running idle process.
+type Attempt = fn(&Injector, Process, Library) -> hook_inject::Result<()>;
+
+#[test]
+fn backends_reject_what_they_cannot_do() {
+    let mut cases: Vec<(Backend, &str, Attempt)> = Vec::new();
+    #[cfg(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    cases.extend([
+        (
+            Backend::Ptrace,
+            "following exec",
+            (|injector, _, library| {
+                let options = hook_inject::InjectOptions::new().follow_exec(true);
+                let program = hook_inject::Program::from_shell(IDLE).unwrap();
+                injector
+                    .inject_program_with(program, library, options)
+                    .map(drop)
+            }) as Attempt,
+        ),
+        (
+            Backend::Ptrace,
+            "choosing the injection method",
+            |injector, process, library| {
+                let options = hook_inject::InjectOptions::new()
+                    .injection_method(hook_inject::InjectionMethod::Apc);
+                injector
+                    .inject_process_with(process, library, options)
+                    .map(drop)
+            },
+        ),
+    ]);
+    #[cfg(all(
+        any(target_os = "linux", target_os = "macos"),
+        feature = "preload-backend"
+    ))]
+    cases.extend([
+        (
+            Backend::Preload,
+            "running processes",
+            (|injector, process, library| injector.inject_process(process, library).map(drop))
+                as Attempt,
+        ),
+        (
+            Backend::Preload,
+            "following exec",
+            |injector, _, library| {
+                let options = hook_inject::InjectOptions::new().follow_exec(true);
+                let program = hook_inject::Program::from_shell(IDLE).unwrap();
+                injector
+                    .inject_program_with(program, library, options)
+                    .map(drop)
+            },
+        ),
+    ]);
+    // dyld strips DYLD_INSERT_LIBRARIES for platform binaries while SIP is
+    // enabled.
+    #[cfg(all(target_os = "macos", feature = "preload-backend"))]
+    cases.push((
+        Backend::Preload,
+        "platform binaries",
+        |injector, _, library| {
+            let program = hook_inject::Program::new("/bin/sleep");
+            injector.inject_program(program, library).map(drop)
+        },
+    ));
+    // Programs held before dyld has run have no task port to inject through.
+    #[cfg(all(
+        target_os = "macos",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    cases.push((
+        Backend::TaskPort,
+        "held programs",
+        |injector, _, library| {
+            let program = hook_inject::Program::from_shell(IDLE).unwrap();
+            injector.inject_program(program, library).map(drop)
+        },
+    ));
+    #[cfg(all(windows, any(target_arch = "x86_64", target_arch = "aarch64")))]
+    cases.push((
+        Backend::LoadLibrary,
+        "following exec",
+        |injector, _, library| {
+            let options = hook_inject::InjectOptions::new().follow_exec(true);
+            let program = hook_inject::Program::from_shell(IDLE).unwrap();
+            injector
+                .inject_program_with(program, library, options)
+                .map(drop)
+        },
+    ));
+
+    let mut words = IDLE.split(' ');
+    let mut child = Command::new(words.next().unwrap())
+        .args(words)
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    let process = Process::from_pid(child.id() as i32).unwrap();
+    let stamp = stamp_path("capabilities");
+    for (backend, what, attempt) in cases {
+        let injector = Injector::builder().backend(backend).build().unwrap();
+        match attempt(&injector, process, fixture_agent(&stamp)) {
+            Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+                eprintln!("skipping {backend} {what} ({err})");
+            }
+            Err(err) => assert_eq!(
+                err.kind(),
+                ErrorKind::NotSupported,
+                "{backend} {what}: {err}"
+            ),
+            Ok(()) => panic!("{backend} should not support {what}"),
+        }
+    }
+    assert!(!stamp.exists(), "no attempt should have run the agent");
+    let _ = child.kill();
+    let _ = child.wait();
+}
@@ -0,0 +1,30 @@
+use hook_inject::{Backend, ErrorKind, Injector};
+
+// Its own test binary: the environment is shared by every test in one.
+#[test]
+fn backend_env_var_selects_or_rejects() {
+    unsafe { std::env::remove_var("HOOK_INJECT_BACKEND") };
+    assert_eq!(Backend::from_env().unwrap(), Backend::default());
+
+    if cfg!(feature = "frida") {
+        unsafe { std::env::set_var("HOOK_INJECT_BACKEND", "FRIDA") };
+        assert_eq!(Backend::from_env().unwrap(), Backend::Frida);
+    }
+
+    unsafe { std::env::set_var("HOOK_INJECT_BACKEND", "no-such-engine") };
+    let err = Injector::new().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotSupported);
+    assert!(err.to_string().contains("no-such-engine"), "{err}");
+    assert_eq!(
+        hook_inject::init().unwrap_err().kind(),
+        ErrorKind::NotSupported
+    );
+
+    // An explicit choice wins over the environment.
+    let backend = Backend::default();
+    match Injector::builder().backend(backend).build() {
+        Ok(injector) => assert_eq!(injector.backend(), backend),
+        Err(err) => assert_eq!(err.kind(), ErrorKind::RuntimeUnavailable),
+    }
+    unsafe { std::env::remove_var("HOOK_INJECT_BACKEND") };
+}
@@ -12,3 +12,107 @@ fn blob_accepts_bytes() {
     assert_eq!(lib.entrypoint().to_str().unwrap(), "frida_agent_main");
     assert_eq!(lib.data().to_str().unwrap(), "");
 }
+
+#[test]
+fn entrypoint_override_requires_export() {
+    use hook_inject::{InjectOptions, Process, inject_process_with};
+
+    let exe = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+    let lib = Library::from_bytes(exe).unwrap();
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+    let options = InjectOptions::new().entrypoint(c"hook_inject_no_such_entry");
+
+    let err = inject_process_with(process, lib, options).unwrap_err();
+    assert!(err.to_string().contains("is not exported"));
+}
+
+#[test]
+fn entrypoint_override_rejects_unparseable_blob() {
+    use hook_inject::{InjectOptions, Process, inject_process_with};
+
+    let lib = Library::from_bytes(vec![1, 2, 3]).unwrap();
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+    let options = InjectOptions::new().entrypoint(c"entry");
+
+    let err = inject_process_with(process, lib, options).unwrap_err();
+    assert!(err.to_string().contains("failed to parse library"));
+}
+
+#[test]
+fn blob_records_format_and_architecture() {
+    use hook_inject::{BinaryFormat, Process};
+
+    let exe = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+    let lib = Library::from_bytes(exe).unwrap();
+    assert_eq!(lib.format(), BinaryFormat::native());
+
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+    if let Ok(arch) = process.architecture() {
+        assert_eq!(lib.architecture(), Some(arch));
+    }
+
+    let unknown = Library::from_bytes(vec![1, 2, 3]).unwrap();
+    assert_eq!(unknown.format(), None);
+    assert_eq!(unknown.architecture(), None);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn blob_reads_elf_machine() {
+    use hook_inject::{Architecture, BinaryFormat};
+
+    let mut header = vec![0u8; 64];
+    header[..4].copy_from_slice(b"\x7fELF");
+    header[4] = 2; // ELFCLASS64
+    header[18..20].copy_from_slice(&183u16.to_le_bytes()); // EM_AARCH64
+
+    let lib = Library::from_bytes(header).unwrap();
+    assert_eq!(lib.format(), Some(BinaryFormat::Elf));
+    assert_eq!(lib.architecture(), Some(Architecture::Aarch64));
+}
+
+#[test]
+fn path_library_requires_default_entrypoint() {
+    use hook_inject::{Process, inject_process};
+
+    // The test binary is a recognized image that exports no agent entrypoint.
+    let lib = Library::from_path(std::env::current_exe().unwrap()).unwrap();
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+
+    let err = inject_process(process, lib).unwrap_err();
+    assert_eq!(err.kind(), hook_inject::ErrorKind::InvalidInput);
+    assert!(
+        err.to_string()
+            .contains("entrypoint `frida_agent_main` is not exported")
+    );
+}
+
+#[test]
+fn sha256_is_checked_before_injection() {
+    use hook_inject::{ErrorKind, InjectOptions, Process, inject_process_with};
+
+    // SHA-256 of "test".
+    let digest = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+    let options = InjectOptions::new().entrypoint(c"entry");
+
+    // A matching digest moves on to parsing the (non-image) blob.
+    let lib = Library::from_bytes(b"test".to_vec())
+        .unwrap()
+        .with_sha256(digest.to_uppercase());
+    let err = inject_process_with(process, lib, options.clone()).unwrap_err();
+    assert!(err.to_string().contains("failed to parse library"), "{err}");
+
+    let lib = Library::from_bytes(b"tesT".to_vec())
+        .unwrap()
+        .with_sha256(digest);
+    let err = inject_process_with(process, lib, options.clone()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert!(err.to_string().contains("sha256 mismatch"), "{err}");
+
+    let lib = Library::from_bytes(b"test".to_vec())
+        .unwrap()
+        .with_sha256("abc");
+    let err = inject_process_with(process, lib, options).unwrap_err();
+    assert!(err.to_string().contains("64 hex digits"), "{err}");
+}
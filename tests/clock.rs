@@ -0,0 +1,30 @@
+use std::time::Duration;
+
+use hook_inject::{Clock, ManualClock};
+
+#[test]
+fn manual_clock_sleep_advances_without_blocking() {
+    let clock = ManualClock::new();
+    let start = clock.now();
+    clock.sleep(Duration::from_secs(3600));
+    assert_eq!(clock.now() - start, Duration::from_secs(3600));
+}
+
+#[test]
+fn manual_clock_clones_share_time() {
+    let clock = ManualClock::new();
+    let other = clock.clone();
+    clock.advance(Duration::from_millis(250));
+    assert_eq!(other.elapsed(), Duration::from_millis(250));
+}
+
+#[test]
+fn manual_clock_wall_time_moves_with_it() {
+    let clock = ManualClock::new();
+    let start = clock.system_time();
+    clock.sleep(Duration::from_secs(90));
+    assert_eq!(
+        clock.system_time().duration_since(start).unwrap(),
+        Duration::from_secs(90)
+    );
+}
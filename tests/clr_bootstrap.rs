@@ -0,0 +1,46 @@
+#![cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+
+mod common;
+
+use std::process::Command;
+
+use hook_inject::{Backend, ErrorKind, Injector, Library, ManagedAssembly, Process, Program};
+
+fn fixture_agent() -> Library {
+    common::fixture_library().with_managed_assembly(ManagedAssembly::new(
+        "Tracer.dll",
+        "Tracer.Entry, Tracer",
+        "Start",
+    ))
+}
+
+fn injector() -> Injector {
+    Injector::builder()
+        .backend(Backend::Ptrace)
+        .build()
+        .expect("the ptrace backend has no runtime to set up")
+}
+
+#[test]
+fn managed_assemblies_are_rejected_off_windows() {
+    let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+    let process = Process::from_pid(child.id() as i32).unwrap();
+    match injector().inject_process(process, fixture_agent()) {
+        Err(err) => assert_eq!(err.kind(), ErrorKind::NotSupported, "{err}"),
+        Ok(_) => panic!("managed assemblies are bootstrapped on Windows only"),
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[test]
+fn managed_assemblies_are_rejected_for_launched_programs() {
+    let program = Program::from_shell("sleep 5").unwrap();
+    match injector().inject_program(program, fixture_agent()) {
+        Err(err) => assert_eq!(err.kind(), ErrorKind::NotSupported, "{err}"),
+        Ok(_) => panic!("a program being launched has no runtime yet"),
+    }
+}
@@ -0,0 +1,66 @@
+//! Helpers shared by the backend tests; each test crate uses some of them.
+#![allow(dead_code)]
+
+use std::error::Error as _;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use hook_inject::Library;
+
+/// The fixture agent, which writes `ok` to `stamp` when it runs.
+pub fn fixture_agent(stamp: &Path) -> Library {
+    fixture_library()
+        .with_data(stamp.to_string_lossy().as_ref())
+        .unwrap()
+}
+
+/// The fixture agent as built, without data.
+///
+/// Built once per test binary, under a file lock so test binaries running
+/// at once do not build it over each other; a failed build fails every
+/// test that needs it with cargo's error.
+pub fn fixture_library() -> Library {
+    static FIXTURE: OnceLock<Result<Library, String>> = OnceLock::new();
+    match FIXTURE.get_or_init(build_fixture) {
+        Ok(library) => library.clone(),
+        Err(err) => panic!("building the fixture agent failed: {err}"),
+    }
+}
+
+fn build_fixture() -> Result<Library, String> {
+    let lock_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("fixture-agent.lock");
+    let lock = File::create(&lock_path)
+        .and_then(|file| file.lock().map(|()| file))
+        .map_err(|err| format!("locking {}: {err}", lock_path.display()))?;
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let built = Library::from_crate(root.join("fixtures/agent")).map_err(|err| {
+        let mut message = err.to_string();
+        let mut source = err.source();
+        while let Some(err) = source {
+            message.push_str(&format!("\n  caused by: {err}"));
+            source = err.source();
+        }
+        message
+    });
+    drop(lock);
+    built
+}
+
+/// A stamp file for the fixture agent, unique to `name` and this run.
+pub fn stamp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("hook-inject-{name}-{}.stamp", std::process::id()))
+}
+
+/// Wait up to five seconds for the file at `path` to appear.
+pub fn wait_for(path: &Path) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if path.is_file() {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    false
+}
@@ -0,0 +1,187 @@
+#![cfg(feature = "compression")]
+
+use hook_inject::{
+    Compression, ErrorKind, InjectOptions, Library, Process, inject_process, inject_process_with,
+};
+
+// "not a library, not a library, not a library\n"
+const GZIP_TEXT: &[u8] = &[
+    31, 139, 8, 0, 0, 0, 0, 0, 2, 3, 203, 203, 47, 81, 72, 84, 200, 201, 76, 42, 74, 44, 170, 212,
+    81, 200, 195, 195, 229, 2, 0, 111, 60, 23, 249, 44, 0, 0, 0,
+];
+const ZSTD_TEXT: &[u8] = &[
+    40, 181, 47, 253, 36, 44, 181, 0, 0, 128, 110, 111, 116, 32, 97, 32, 108, 105, 98, 114, 97,
+    114, 121, 44, 32, 10, 1, 0, 194, 204, 58, 249, 222, 78, 21,
+];
+
+#[test]
+fn compressed_blob_detects_format() {
+    assert_eq!(Compression::detect(GZIP_TEXT), Some(Compression::Gzip));
+    assert_eq!(Compression::detect(ZSTD_TEXT), Some(Compression::Zstd));
+    assert_eq!(Compression::detect(b"\x7fELF"), None);
+
+    let err = Library::from_compressed_bytes(vec![1, 2, 3]).unwrap_err();
+    assert!(err.to_string().contains("not gzip or zstd compressed"));
+}
+
+#[test]
+fn compressed_blob_is_decoded_before_injection() {
+    for bytes in [GZIP_TEXT, ZSTD_TEXT] {
+        let lib = Library::from_compressed_bytes(bytes).unwrap();
+        assert_eq!(lib.format(), None);
+        let process = Process::from_pid(std::process::id() as i32).unwrap();
+        let options = InjectOptions::new().entrypoint(c"entry");
+
+        // The decoded text is not an image, so parsing is what fails.
+        let err = inject_process_with(process, lib, options).unwrap_err();
+        assert!(err.to_string().contains("failed to parse library"), "{err}");
+    }
+}
+
+#[test]
+fn compressed_blob_rejects_corrupt_stream() {
+    let mut bytes = ZSTD_TEXT.to_vec();
+    bytes.truncate(bytes.len() - 6);
+    let lib = Library::from_compressed_bytes(bytes).unwrap();
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+
+    let err = inject_process(process, lib).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert!(
+        err.to_string()
+            .contains("failed to decompress zstd library")
+    );
+}
+
+#[test]
+fn compressed_blob_round_trips_an_image() {
+    // Exercise the decoders on a real image when the CLIs are around.
+    let exe = std::env::current_exe().unwrap();
+    for (tool, args) in [("gzip", &["-c", "-6"]), ("zstd", &["-c", "-19"])] {
+        let Ok(output) = std::process::Command::new(tool)
+            .args(args)
+            .arg(&exe)
+            .output()
+        else {
+            continue;
+        };
+        if !output.status.success() {
+            continue;
+        }
+
+        let lib = Library::from_compressed_bytes(output.stdout).unwrap();
+        let process = Process::from_pid(std::process::id() as i32).unwrap();
+        let options = InjectOptions::new().entrypoint(c"hook_inject_no_such_entry");
+
+        let err = inject_process_with(process, lib, options).unwrap_err();
+        assert!(err.to_string().contains("is not exported"), "{tool}: {err}");
+    }
+}
+
+/// Inject `bytes` as a compressed agent into this process, for the error.
+fn inject_compressed(bytes: Vec<u8>) -> hook_inject::Error {
+    let lib = Library::from_compressed_bytes(bytes).unwrap();
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+    let options = InjectOptions::new().entrypoint(c"entry");
+    inject_process_with(process, lib, options).unwrap_err()
+}
+
+#[test]
+fn compressed_blob_rejects_damaged_input() {
+    let flip = |bytes: &[u8], at: usize| {
+        let mut bytes = bytes.to_vec();
+        bytes[at] ^= 0x01;
+        bytes
+    };
+    let cases = [
+        (
+            "gzip",
+            GZIP_TEXT[..GZIP_TEXT.len() - 4].to_vec(),
+            "truncated",
+        ),
+        ("gzip", flip(GZIP_TEXT, GZIP_TEXT.len() - 8), "crc flipped"),
+        (
+            "gzip",
+            [&GZIP_TEXT[..2], &[0xff; 16]].concat(),
+            "garbage header",
+        ),
+        ("zstd", ZSTD_TEXT[..8].to_vec(), "truncated header"),
+        (
+            "zstd",
+            flip(ZSTD_TEXT, ZSTD_TEXT.len() - 1),
+            "checksum flipped",
+        ),
+        (
+            "zstd",
+            [&ZSTD_TEXT[..4], &[0xff; 16]].concat(),
+            "garbage header",
+        ),
+    ];
+    for (format, bytes, case) in cases {
+        let err = inject_compressed(bytes);
+        assert_eq!(
+            err.kind(),
+            ErrorKind::InvalidInput,
+            "{format} {case}: {err}"
+        );
+        assert!(
+            err.to_string()
+                .contains(&format!("failed to decompress {format} library")),
+            "{format} {case}: {err}"
+        );
+    }
+}
+
+#[test]
+fn compressed_blob_survives_mutated_input() {
+    // xorshift, seeded so a failure reproduces.
+    let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    for original in [GZIP_TEXT, ZSTD_TEXT] {
+        for _ in 0..64 {
+            let mut bytes = original.to_vec();
+            for _ in 0..1 + next() % 4 {
+                let at = 4 + (next() as usize) % (bytes.len() - 4);
+                bytes[at] = next() as u8;
+            }
+            if next() % 4 == 0 {
+                bytes.truncate(4 + (next() as usize) % (bytes.len() - 4));
+            }
+            // Whatever the decoder makes of it, the text is no image.
+            let err = inject_compressed(bytes);
+            assert_eq!(err.kind(), ErrorKind::InvalidInput, "{err}");
+        }
+    }
+}
+
+#[test]
+fn compressed_blob_stops_at_the_size_limit() {
+    // Both texts decode to 44 bytes.
+    for (format, bytes) in [("gzip", GZIP_TEXT), ("zstd", ZSTD_TEXT)] {
+        let lib = Library::from_compressed_bytes_limited(bytes, 16).unwrap();
+        let process = Process::from_pid(std::process::id() as i32).unwrap();
+        let options = InjectOptions::new().entrypoint(c"entry");
+        let err = inject_process_with(process, lib, options).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput, "{format}: {err}");
+        assert!(
+            err.to_string()
+                .contains("decompressed size exceeds 16 bytes"),
+            "{format}: {err}"
+        );
+
+        // Exactly at the limit is fine; parsing is what fails then.
+        let lib = Library::from_compressed_bytes_limited(bytes, 44).unwrap();
+        let process = Process::from_pid(std::process::id() as i32).unwrap();
+        let options = InjectOptions::new().entrypoint(c"entry");
+        let err = inject_process_with(process, lib, options).unwrap_err();
+        assert!(
+            err.to_string().contains("failed to parse library"),
+            "{format}: {err}"
+        );
+    }
+}
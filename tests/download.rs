@@ -0,0 +1,50 @@
+#![cfg(feature = "download")]
+
+use hook_inject::{ErrorKind, Library};
+
+// SHA-256 of "test".
+const DIGEST: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+#[test]
+fn from_url_caches_by_digest() {
+    let root = std::env::temp_dir().join(format!("hook-inject-download-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    let cache = root.join("cache");
+    // SAFETY: this is the only test in this binary, so nothing reads the
+    // environment concurrently.
+    unsafe { std::env::set_var("HOOK_INJECT_CACHE_DIR", &cache) };
+
+    let source = root.join("libagent.so");
+    std::fs::write(&source, b"test").unwrap();
+    let url = format!("file://{}", source.display());
+    if std::process::Command::new("curl")
+        .arg("--version")
+        .output()
+        .is_err()
+    {
+        return;
+    }
+
+    Library::from_url(&url, DIGEST).expect("download");
+    let cached = cache.join(DIGEST).join("libagent.so");
+    assert_eq!(std::fs::read(&cached).unwrap(), b"test");
+
+    // A cache hit does not go back to the source.
+    std::fs::remove_file(&source).unwrap();
+    Library::from_url(&url, &DIGEST.to_uppercase()).expect("cached");
+
+    std::fs::write(&source, b"tesT").unwrap();
+    std::fs::remove_file(&cached).unwrap();
+    let err = Library::from_url(&url, DIGEST).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert!(err.to_string().contains("sha256 mismatch"), "{err}");
+    assert!(!cached.exists());
+
+    let err = Library::from_url("http://example.com/libagent.so", DIGEST).unwrap_err();
+    assert!(err.to_string().contains("must use https"), "{err}");
+    let err = Library::from_url(&url, "../../etc").unwrap_err();
+    assert!(err.to_string().contains("64 hex digits"), "{err}");
+
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,10 @@
+#![cfg(feature = "dynamic-frida")]
+
+use hook_inject::{ErrorKind, feature_report, load_frida_core};
+
+#[test]
+fn missing_library_is_unavailable() {
+    let err = load_frida_core("/nonexistent/libfrida-core.so").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::RuntimeUnavailable);
+    assert!(feature_report().is_available("dynamic-frida"));
+}
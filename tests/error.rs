@@ -0,0 +1,20 @@
+use std::error::Error as _;
+
+use hook_inject::{Error, ErrorKind};
+
+#[test]
+fn public_constructor_classifies_like_builtin_errors() {
+    let err = Error::new(ErrorKind::NotSupported, "no such backend feature");
+    assert_eq!(err.kind(), ErrorKind::NotSupported);
+    assert!(err.is_not_supported());
+    assert_eq!(err.to_string(), "no such backend feature");
+    assert!(err.source().is_none());
+}
+
+#[test]
+fn with_source_is_exposed() {
+    let io = std::io::Error::other("pipe closed");
+    let err = Error::new(ErrorKind::Runtime, "agent channel failed").with_source(io);
+    assert!(err.is_runtime_error());
+    assert_eq!(err.source().unwrap().to_string(), "pipe closed");
+}
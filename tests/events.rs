@@ -0,0 +1,59 @@
+use hook_inject_agent::EventStream;
+
+const HEADER: usize = 16;
+
+fn raw(stream: &EventStream) -> &[u8] {
+    let len = HEADER + hook_inject::EVENT_BUFFER_CAPACITY;
+    unsafe { std::slice::from_raw_parts(stream as *const _ as *const u8, len) }
+}
+
+fn offsets(stream: &EventStream) -> (u64, u64) {
+    let raw = raw(stream);
+    (
+        u64::from_ne_bytes(raw[..8].try_into().unwrap()),
+        u64::from_ne_bytes(raw[8..16].try_into().unwrap()),
+    )
+}
+
+fn payload_at(stream: &EventStream, offset: u64) -> Vec<u8> {
+    let records = &raw(stream)[HEADER..];
+    let at = offset as usize % records.len();
+    let len = u32::from_ne_bytes(records[at..at + 4].try_into().unwrap()) as usize;
+    records[at + 12..at + 12 + len].to_vec()
+}
+
+#[test]
+fn capacities_match() {
+    assert_eq!(
+        hook_inject::EVENT_BUFFER_CAPACITY,
+        hook_inject_agent::EVENT_BUFFER_CAPACITY
+    );
+}
+
+#[test]
+fn events_are_framed_in_order() {
+    let stream = Box::new(EventStream::new());
+    assert_eq!(offsets(&stream), (0, 0));
+
+    assert!(stream.emit(b"first"));
+    assert!(stream.emit_json(&[1, 2]));
+    assert_eq!(offsets(&stream), (0, 12 + 5 + 12 + 5));
+    assert_eq!(payload_at(&stream, 0), b"first");
+    assert_eq!(payload_at(&stream, 17), b"[1,2]");
+}
+
+#[test]
+fn overflow_moves_the_tail_to_a_record_boundary() {
+    let stream = Box::new(EventStream::new());
+    let payload = vec![7u8; 1000];
+    for _ in 0..100 {
+        assert!(stream.emit(&payload));
+    }
+    let (tail, head) = offsets(&stream);
+    assert_eq!(head, 100 * 1012);
+    assert_eq!(tail % 1012, 0);
+    assert!(head - tail <= hook_inject::EVENT_BUFFER_CAPACITY as u64);
+    assert_eq!(payload_at(&stream, tail), payload);
+
+    assert!(!stream.emit(&vec![0u8; hook_inject::EVENT_BUFFER_CAPACITY]));
+}
@@ -0,0 +1,61 @@
+use std::collections::HashSet;
+
+use hook_inject::{CapabilityStatus, feature_report};
+
+#[test]
+fn capability_names_are_unique() {
+    let report = feature_report();
+    let mut seen = HashSet::new();
+    for capability in report.capabilities() {
+        assert!(seen.insert(capability.name()), "{}", capability.name());
+    }
+}
+
+#[test]
+fn core_injection_is_available() {
+    let report = feature_report();
+    assert!(report.is_available("inject-process"));
+    assert!(!report.is_available("no-such-capability"));
+}
+
+#[test]
+fn display_lists_reasons() {
+    let status = CapabilityStatus::MissingFeature("serde");
+    assert_eq!(status.to_string(), "requires the `serde` feature");
+    assert!(feature_report().to_string().contains("inject-process"));
+}
+
+#[test]
+fn runtime_version_names_the_engine() {
+    let version = hook_inject::runtime_version();
+    let shown = version.to_string();
+    assert!(shown.starts_with("frida-core "), "{shown}");
+    if let Some(number) = version.version() {
+        assert!(shown.contains(number), "{shown}");
+        assert!(
+            number.chars().next().is_some_and(|c| c.is_ascii_digit()),
+            "{number}"
+        );
+    }
+}
+
+#[cfg(not(feature = "frida"))]
+#[test]
+fn without_frida_only_native_engines_are_reported() {
+    let report = feature_report();
+    let frida = CapabilityStatus::MissingFeature("frida");
+    for name in ["frida-backend", "follow-exec", "remote-device", "ios"] {
+        assert_eq!(report.get(name).unwrap().status(), frida, "{name}");
+    }
+    assert_eq!(
+        report.is_available("inject-process"),
+        report.is_available("ptrace-backend")
+            || report.is_available("loadlibrary-backend")
+            || report.is_available("taskport-backend")
+    );
+    // Native launches keep their output pipes.
+    assert_eq!(
+        report.is_available("launch-stdio-pipes"),
+        report.is_available("inject-program")
+    );
+}
@@ -0,0 +1,40 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hook_inject::{Clock, Health, ManualClock};
+use hook_inject_agent::Heartbeat;
+
+fn millis(heartbeat: &Heartbeat) -> u64 {
+    assert_eq!(std::mem::size_of::<Heartbeat>(), 8);
+    let bytes = unsafe { std::slice::from_raw_parts(heartbeat as *const _ as *const u8, 8) };
+    u64::from_ne_bytes(bytes.try_into().unwrap())
+}
+
+#[test]
+fn heartbeat_stores_unix_millis() {
+    let heartbeat = Heartbeat::new();
+    assert_eq!(millis(&heartbeat), 0);
+
+    heartbeat.beat();
+    let last_seen = UNIX_EPOCH + Duration::from_millis(millis(&heartbeat));
+    let age = SystemTime::now().duration_since(last_seen).unwrap();
+    assert!(age < Duration::from_secs(5));
+    assert!(Health::LastSeen(last_seen).is_alive(Duration::from_secs(5)));
+}
+
+#[test]
+fn unreported_health_is_not_alive() {
+    assert!(!Health::Unreported.is_alive(Duration::MAX));
+}
+
+#[test]
+fn health_ages_with_the_clock() {
+    let clock = ManualClock::new();
+    let health = Health::LastSeen(clock.system_time());
+    assert!(health.is_alive_with(Duration::from_secs(30), &clock));
+
+    clock.advance(Duration::from_secs(30));
+    assert!(health.is_alive_with(Duration::from_secs(30), &clock));
+    clock.advance(Duration::from_millis(1));
+    assert!(!health.is_alive_with(Duration::from_secs(30), &clock));
+    assert!(!Health::Pending.is_alive_with(Duration::MAX, &clock));
+}
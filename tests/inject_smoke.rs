@@ -1,10 +1,29 @@
+// These drive the default backend, Frida, end to end.
+#![cfg(feature = "frida")]
+
+mod common;
+
 #[test]
 fn inject_fixture_into_target() {
     use std::path::PathBuf;
     use std::process::Command;
     use std::time::{Duration, Instant};
 
-    use hook_inject::{Library, Process, inject_process};
+    use std::sync::{Arc, Mutex};
+
+    use hook_inject::{
+        AlreadyLoaded, EntryStatus, InjectOptions, InjectionEvent, InjectionEventKind, Notifier,
+        Process, inject_process_with,
+    };
+
+    #[derive(Debug, Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<InjectionEventKind>>>);
+
+    impl Notifier for Recorder {
+        fn notify(&self, event: &InjectionEvent) {
+            self.0.lock().unwrap().push(event.kind());
+        }
+    }
 
     if !unix_socket_available() {
         eprintln!("skipping inject smoke test (unix socket bind denied)");
@@ -49,11 +68,13 @@ fn inject_fixture_into_target() {
         .expect("failed to spawn fixture target");
 
     let process = Process::from_pid(child.id() as i32).expect("target pid should exist");
-    let library = Library::from_crate(root.join("fixtures/agent"))
-        .expect("fixture lib")
-        .with_data(std::ffi::CString::new(stamp.to_string_lossy().as_ref()).unwrap());
+    let library = common::fixture_agent(&stamp);
 
-    let _inject = inject_process(process, library).expect("injection should succeed");
+    let recorder = Recorder::default();
+    let options = InjectOptions::new().notifier(recorder.clone());
+    let inject = inject_process_with(process, library.clone(), options)
+        .expect("injection should succeed")
+        .uninject_on_drop(true);
 
     let deadline = Instant::now() + Duration::from_secs(5);
     while Instant::now() < deadline {
@@ -67,10 +88,206 @@ fn inject_fixture_into_target() {
     let contents = std::fs::read(&stamp).expect("read stamp");
     assert_eq!(contents, b"ok");
 
+    let mut status = inject.entry_status().expect("read entry status");
+    while status == EntryStatus::Pending && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(50));
+        status = inject.entry_status().expect("read entry status");
+    }
+    assert_eq!(status, EntryStatus::Ready);
+
+    let configured = stamp.with_extension("configured");
+    inject
+        .configure(std::ffi::CString::new(configured.to_string_lossy().as_ref()).unwrap())
+        .expect("configure should succeed");
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline && !configured.is_file() {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    assert_eq!(
+        std::fs::read(&configured).expect("read configure stamp"),
+        b"configured"
+    );
+
+    let id = inject.id();
+    assert!(
+        hook_inject::injections()
+            .iter()
+            .any(|injection| injection.id() == id && injection.process() == process)
+    );
+
+    let options = InjectOptions::new().already_loaded(AlreadyLoaded::Fail);
+    let err = inject_process_with(process, library.clone(), options).unwrap_err();
+    assert!(err.is_already_injected(), "{err}");
+    let options = InjectOptions::new().already_loaded(AlreadyLoaded::Reuse);
+    let reused = inject_process_with(process, library, options).expect("reuse injection");
+    assert_eq!(reused.id(), id);
+    drop(reused);
+
+    drop(inject);
+    assert!(
+        !hook_inject::injections()
+            .iter()
+            .any(|injection| injection.id() == id)
+    );
+    assert_eq!(
+        *recorder.0.lock().unwrap(),
+        [InjectionEventKind::Injected, InjectionEventKind::Ejected]
+    );
+
     let _ = child.kill();
     let _ = child.wait();
 }
 
+#[cfg(target_os = "linux")]
+#[test]
+fn inject_fixture_via_command_with_piped_stdout() {
+    use std::io::Read;
+    use std::path::PathBuf;
+    use std::process::{Command, Stdio};
+    use std::time::Duration;
+
+    use hook_inject::inject_command;
+
+    if !unix_socket_available() {
+        eprintln!("skipping inject_command smoke test (unix socket bind denied)");
+        return;
+    }
+
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let stamp = std::env::temp_dir().join(format!(
+        "hook-inject-command-{}-{}.stamp",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis()
+    ));
+
+    let status = Command::new("cargo")
+        .arg("build")
+        .arg("-p")
+        .arg("hook-inject-fixture-agent")
+        .current_dir(&root)
+        .status()
+        .expect("failed to build fixture agent");
+    assert!(status.success());
+
+    let library = common::fixture_agent(&stamp);
+
+    let mut cmd = Command::new("/bin/sh");
+    cmd.arg("-c")
+        .arg("sleep 1; echo hello")
+        .stdout(Stdio::piped());
+    let mut injected = inject_command(cmd, library).expect("injection should succeed");
+    // The fixture agent does not stay resident once it has written the stamp.
+    injected
+        .wait_until_unloaded(Duration::from_secs(5))
+        .expect("agent should unload");
+    assert!(stamp.is_file(), "expected injection to write stamp file");
+
+    let mut output = String::new();
+    injected
+        .child_mut()
+        .take_stdout()
+        .expect("stdout should be piped")
+        .read_to_string(&mut output)
+        .expect("read stdout");
+    assert_eq!(output, "hello\n");
+    injected
+        .wait_target_exit(Duration::from_secs(5))
+        .expect("program should exit after printing");
+}
+
+#[cfg(unix)]
+#[test]
+fn inject_fixture_follows_exec() {
+    use std::path::PathBuf;
+    use std::process::Command;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    use hook_inject::{
+        InjectOptions, InjectionEvent, InjectionEventKind, Injector, Notifier, Program,
+    };
+
+    #[derive(Debug, Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<InjectionEventKind>>>);
+
+    impl Notifier for Recorder {
+        fn notify(&self, event: &InjectionEvent) {
+            self.0.lock().unwrap().push(event.kind());
+        }
+    }
+
+    if !unix_socket_available() {
+        eprintln!("skipping follow_exec smoke test (unix socket bind denied)");
+        return;
+    }
+
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let stamp = std::env::temp_dir().join(format!(
+        "hook-inject-follow-{}-{}.stamp",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis()
+    ));
+    let wait_for_stamp = || {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline && !stamp.is_file() {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        stamp.is_file()
+    };
+
+    let status = Command::new("cargo")
+        .arg("build")
+        .arg("-p")
+        .arg("hook-inject-fixture-agent")
+        .current_dir(&root)
+        .status()
+        .expect("failed to build fixture agent");
+    assert!(status.success());
+
+    let library = common::fixture_agent(&stamp);
+    let recorder = Recorder::default();
+    let options = InjectOptions::new()
+        .follow_exec(true)
+        .notifier(recorder.clone());
+    // The shell waits for the stamp to be taken away before it execs, so
+    // the stamp seen afterwards is the re-injected agent's.
+    let script = format!(
+        "while [ -e '{0}' ] || [ ! -e '{0}.taken' ]; do sleep 0.05; done; exec sleep 1",
+        stamp.display()
+    );
+    let mut program = Program::new("/bin/sh");
+    program.args(["-c", script.as_str()]);
+    let injected = Injector::new()
+        .expect("frida runtime")
+        .inject_program_with(program, library, options)
+        .expect("injection should succeed");
+
+    assert!(wait_for_stamp(), "expected injection to write stamp file");
+    std::fs::rename(&stamp, stamp.with_extension("stamp.taken")).unwrap();
+    assert!(
+        wait_for_stamp(),
+        "expected the agent to be re-injected after exec"
+    );
+    assert_eq!(std::fs::read(&stamp).unwrap(), b"ok");
+
+    injected
+        .wait_target_exit(Duration::from_secs(5))
+        .expect("program should exit after exec");
+    assert_eq!(
+        *recorder.0.lock().unwrap(),
+        [InjectionEventKind::Injected, InjectionEventKind::Reinjected]
+    );
+    injected.uninject().unwrap();
+    let _ = std::fs::remove_file(&stamp);
+    let _ = std::fs::remove_file(stamp.with_extension("stamp.taken"));
+}
+
 #[cfg(unix)]
 fn unix_socket_available() -> bool {
     use std::os::unix::net::UnixListener;
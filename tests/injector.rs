@@ -0,0 +1,75 @@
+use hook_inject::{Backend, ErrorKind, InjectOptions, Injector, Realm};
+
+#[test]
+fn invalid_default_options_fail_the_build() {
+    let err = Injector::builder()
+        .options(InjectOptions::new().concurrency(0))
+        .build()
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    let err = Injector::builder()
+        .options(InjectOptions::new().realm(Realm::Emulated))
+        .build()
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotSupported);
+}
+
+#[cfg(feature = "frida")]
+#[test]
+fn every_build_reports_the_runtime_state() {
+    let first = Injector::builder().backend(Backend::Frida).build();
+    let second = Injector::new();
+    match (first, second) {
+        (Ok(first), Ok(second)) => {
+            assert_eq!(first.backend(), Backend::Frida);
+            assert_eq!(second.backend(), Backend::Frida);
+        }
+        // Without a runtime, each build reports it rather than a cached
+        // failure from an earlier call.
+        (Err(first), Err(second)) => {
+            assert_eq!(first.kind(), ErrorKind::RuntimeUnavailable);
+            assert_eq!(second.kind(), ErrorKind::RuntimeUnavailable);
+        }
+        (first, second) => panic!("runtime setup changed between builds: {first:?}, {second:?}"),
+    }
+}
+
+#[cfg(feature = "frida")]
+#[test]
+fn backends_parse_by_name() {
+    assert!(Backend::compiled().contains(&Backend::Frida));
+    for &backend in Backend::compiled() {
+        assert_eq!(backend.to_string().parse::<Backend>().unwrap(), backend);
+    }
+    assert_eq!(" Frida ".parse::<Backend>().unwrap(), Backend::Frida);
+
+    let err = "ptrace-but-not-compiled".parse::<Backend>().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotSupported);
+    assert!(err.to_string().contains("available: frida"), "{err}");
+}
+
+#[cfg(not(feature = "frida"))]
+#[test]
+fn native_only_builds_leave_frida_out() {
+    assert!(!Backend::compiled().contains(&Backend::Frida));
+    if let Some(&first) = Backend::compiled().first() {
+        assert_eq!(Backend::default(), first);
+    }
+    let err = "frida".parse::<Backend>().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotSupported);
+    let err = Injector::builder()
+        .backend(Backend::Frida)
+        .build()
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotSupported);
+    let err = Injector::remote("127.0.0.1:27042").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotSupported);
+    assert_eq!(
+        hook_inject::feature_report()
+            .get("frida-backend")
+            .unwrap()
+            .status(),
+        hook_inject::CapabilityStatus::MissingFeature("frida")
+    );
+}
@@ -0,0 +1,50 @@
+use hook_inject::{ErrorKind, Library};
+
+// A Mach-O dylib header for `cputype`, with no load commands (and so no
+// code signature).
+fn macho_dylib(cputype: u32) -> Vec<u8> {
+    let mut header = Vec::new();
+    for word in [0xfeed_facf_u32, cputype, 0, 6, 0, 0, 0, 0] {
+        header.extend_from_slice(&word.to_le_bytes());
+    }
+    header
+}
+
+#[test]
+fn ios_needs_arm64_macho() {
+    let elf = Library::from_bytes(
+        [
+            b"\x7fELF\x02\x01\x01".as_slice(),
+            &[0; 11],
+            &183u16.to_le_bytes(),
+            &[0; 44],
+        ]
+        .concat(),
+    )
+    .unwrap();
+    let err = elf.signed_for_ios().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert!(err.to_string().contains("Mach-O"), "{err}");
+
+    let x86_64 = Library::from_bytes(macho_dylib(0x0100_0007)).unwrap();
+    let err = x86_64.signed_for_ios().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert!(err.to_string().contains("arm64"), "{err}");
+}
+
+#[test]
+fn unsigned_dylibs_are_signed_or_rejected() {
+    // The header alone is not signable, so this fails unless a signing tool
+    // accepts it; either way the unsigned image is never passed on.
+    let library = Library::from_bytes(macho_dylib(0x0100_000c)).unwrap();
+    match library.signed_for_ios() {
+        Ok(signed) => assert_eq!(signed.architecture(), library.architecture()),
+        Err(err) => assert_eq!(err.kind(), ErrorKind::InvalidInput, "{err}"),
+    }
+}
+
+#[test]
+fn device_libraries_are_left_alone() {
+    let library = Library::from_device_path("/var/jb/usr/lib/agent.dylib").unwrap();
+    assert!(library.signed_for_ios().is_ok());
+}
@@ -0,0 +1,44 @@
+#![cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+
+mod common;
+
+use std::process::Command;
+
+use hook_inject::{Backend, ErrorKind, Injector, JvmAttach, Library, Process, Program};
+
+fn fixture_agent() -> Library {
+    common::fixture_library().with_jvm_attach(JvmAttach::Always)
+}
+
+// JVMs are attached to the same way whichever backend is chosen; ptrace
+// has no runtime to set up.
+fn injector() -> Injector {
+    Injector::builder()
+        .backend(Backend::Ptrace)
+        .build()
+        .expect("the ptrace backend has no runtime to set up")
+}
+
+#[test]
+fn always_rejects_processes_without_a_jvm() {
+    let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+    let process = Process::from_pid(child.id() as i32).unwrap();
+    match injector().inject_process(process, fixture_agent()) {
+        Err(err) => assert_eq!(err.kind(), ErrorKind::NotSupported, "{err}"),
+        Ok(_) => panic!("sleep is not a JVM"),
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[test]
+fn always_rejects_launched_programs() {
+    let program = Program::from_shell("sleep 5").unwrap();
+    match injector().inject_program(program, fixture_agent()) {
+        Err(err) => assert_eq!(err.kind(), ErrorKind::NotSupported, "{err}"),
+        Ok(_) => panic!("a program being launched is not a JVM yet"),
+    }
+}
@@ -7,7 +7,8 @@ fn entrypoint_sets_value() {
     let entry = CString::new("entry_fn").expect("cstring");
     let lib = Library::from_bytes(vec![1])
         .expect("library")
-        .with_entrypoint(entry.clone());
+        .with_entrypoint(entry.clone())
+        .expect("entrypoint");
     assert_eq!(lib.entrypoint().to_bytes(), entry.as_bytes());
 }
 
@@ -16,6 +17,114 @@ fn data_sets_value() {
     let data = CString::new("payload").expect("cstring");
     let lib = Library::from_bytes(vec![1])
         .expect("library")
-        .with_data(data.clone());
+        .with_data(data.clone())
+        .expect("data");
     assert_eq!(lib.data().to_bytes(), data.as_bytes());
 }
+
+#[test]
+fn data_bytes_replace_data_string() {
+    let payload = b"\x08\x96\x01\x00\x12\x00".to_vec();
+    let lib = Library::from_bytes(vec![1])
+        .expect("library")
+        .with_data(CString::new("payload").expect("cstring"))
+        .expect("data")
+        .with_data_bytes(payload.clone());
+    assert_eq!(lib.data_bytes(), Some(&payload[..]));
+    assert!(lib.data().to_bytes().is_empty());
+
+    let lib = lib
+        .with_data(CString::new("payload").expect("cstring"))
+        .expect("data");
+    assert_eq!(lib.data_bytes(), None);
+}
+
+#[test]
+fn configure_entrypoint_defaults_and_overrides() {
+    let lib = Library::from_bytes(vec![1]).expect("library");
+    assert_eq!(lib.configure_entrypoint(), c"hook_inject_configure");
+    let lib = lib
+        .with_configure_entrypoint(c"reconfigure")
+        .expect("entrypoint");
+    assert_eq!(lib.configure_entrypoint(), c"reconfigure");
+}
+
+#[test]
+fn unload_entrypoint_defaults_and_overrides() {
+    let lib = Library::from_bytes(vec![1]).expect("library");
+    assert_eq!(lib.unload_entrypoint(), c"hook_inject_unload");
+    let lib = lib.with_unload_entrypoint("teardown").expect("entrypoint");
+    assert_eq!(lib.unload_entrypoint(), c"teardown");
+    assert!(lib.with_unload_entrypoint("tear\0down").is_err());
+}
+
+#[test]
+fn shutdown_entrypoint_is_opt_in() {
+    let lib = Library::from_bytes(vec![1]).expect("library");
+    assert_eq!(lib.shutdown_entrypoint(), None);
+    let lib = lib
+        .with_shutdown_entrypoint("hook_inject_shutdown")
+        .expect("entrypoint");
+    assert_eq!(lib.shutdown_entrypoint(), Some(c"hook_inject_shutdown"));
+}
+
+#[test]
+fn unload_policy_defaults_to_agent() {
+    use hook_inject::UnloadPolicy;
+
+    let lib = Library::from_bytes(vec![1]).expect("library");
+    assert_eq!(lib.unload_policy(), UnloadPolicy::Agent);
+    let lib = lib
+        .with_data("payload")
+        .expect("data")
+        .with_unload_policy(UnloadPolicy::Resident);
+    assert_eq!(lib.unload_policy(), UnloadPolicy::Resident);
+    // The header is added on injection, not to the stored data.
+    assert_eq!(lib.data(), c"payload");
+}
+
+#[test]
+fn jvm_attach_defaults_to_never() {
+    use hook_inject::JvmAttach;
+
+    let lib = Library::from_bytes(vec![1]).expect("library");
+    assert_eq!(lib.jvm_attach(), JvmAttach::Never);
+    let lib = lib.with_jvm_attach(JvmAttach::Auto);
+    assert_eq!(lib.jvm_attach(), JvmAttach::Auto);
+}
+
+#[test]
+fn managed_assembly_is_opt_in() {
+    use hook_inject::ManagedAssembly;
+
+    let lib = Library::from_bytes(vec![1]).expect("library");
+    assert!(lib.managed_assembly().is_none());
+    let lib = lib.with_managed_assembly(
+        ManagedAssembly::new("Tracer.dll", "Tracer.Entry, Tracer", "Start").with_argument("-v"),
+    );
+    let assembly = lib.managed_assembly().expect("assembly");
+    assert_eq!(assembly.path(), std::path::Path::new("Tracer.dll"));
+    assert_eq!(assembly.type_name(), "Tracer.Entry, Tracer");
+    assert_eq!(assembly.method(), "Start");
+    assert_eq!(assembly.argument(), "-v");
+}
+
+#[test]
+fn setters_accept_rust_strings_and_reject_nul() {
+    use hook_inject::ErrorKind;
+
+    let lib = Library::from_bytes(vec![1])
+        .expect("library")
+        .with_entrypoint("entry_fn")
+        .expect("entrypoint")
+        .with_data(String::from("payload"))
+        .expect("data");
+    assert_eq!(lib.entrypoint(), c"entry_fn");
+    assert_eq!(lib.data(), c"payload");
+
+    let err = lib.clone().with_entrypoint("entry\0fn").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert!(err.to_string().contains("entrypoint contains NUL"));
+    let err = lib.with_data("pay\0load").unwrap_err();
+    assert!(err.to_string().contains("data contains NUL"));
+}
@@ -10,6 +10,32 @@ fn from_path_rejects_dir() {
     assert!(msg.contains("library path must be a file"));
 }
 
+#[test]
+fn from_name_searches_directories_in_order() {
+    use hook_inject::ErrorKind;
+
+    let root = std::env::temp_dir().join(format!("hook-inject-from-name-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let (first, second) = (root.join("first"), root.join("second"));
+    std::fs::create_dir_all(&first).unwrap();
+    std::fs::create_dir_all(&second).unwrap();
+    let filename = hook_inject_build::library_filename("my-agent");
+    std::fs::write(second.join(&filename), b"agent").unwrap();
+
+    Library::from_name("my-agent", &[&first, &second]).expect("found in second directory");
+
+    let err = Library::from_name("other-agent", &[&first, &second]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Io);
+    let msg = err.to_string();
+    assert!(
+        msg.contains(&hook_inject_build::library_filename("other-agent")),
+        "{msg}"
+    );
+    assert!(msg.contains(&first.display().to_string()), "{msg}");
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
 #[test]
 fn from_crate_finds_fixture_cdylib() {
     let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -39,3 +65,179 @@ fn from_crate_rejects_missing_manifest() {
     let err = Library::from_crate(&tmp).unwrap_err();
     assert!(err.to_string().contains("missing Cargo.toml"));
 }
+
+#[test]
+fn from_crate_for_native_process_uses_host_build() {
+    use hook_inject::Process;
+
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let fixture = root.join("fixtures/agent");
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+    if process.architecture().is_err() {
+        return;
+    }
+
+    let lib = Library::from_crate_for(&fixture, process).expect("fixture cdylib");
+    let host = Library::from_crate(&fixture).expect("fixture cdylib");
+    assert_eq!(lib.architecture(), host.architecture());
+}
+
+#[test]
+fn native_architecture_has_target_triple() {
+    use hook_inject::Architecture;
+
+    let Some(native) = Architecture::native() else {
+        return;
+    };
+    if let Some(triple) = native.target_triple() {
+        assert!(triple.starts_with(native.as_str()) || triple.starts_with("i686"));
+    }
+}
+
+#[test]
+fn from_crate_with_offline_finds_fixture_cdylib() {
+    use hook_inject::BuildOptions;
+
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let fixture = root.join("fixtures/agent");
+    let options = BuildOptions::new().locked(true).offline(true);
+
+    let lib = Library::from_crate_with(fixture, &options).expect("fixture cdylib");
+    assert_eq!(lib.entrypoint().to_str().unwrap(), "hook_inject_entry");
+}
+
+/// Write an agent crate whose build script compiles and links `libhelper.so`.
+#[cfg(target_os = "linux")]
+fn helper_linked_agent(name: &str, origin_rpath: bool) -> Option<PathBuf> {
+    let has_cc = std::process::Command::new("cc")
+        .arg("--version")
+        .output()
+        .is_ok_and(|out| out.status.success());
+    if !has_cc {
+        return None;
+    }
+
+    let dir = std::env::temp_dir().join(format!("hook-inject-{name}"));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"{name}\"\nversion = \"0.0.0\"\nedition = \"2021\"\n\n\
+             [lib]\ncrate-type = [\"cdylib\"]\n\n[workspace]\n"
+        ),
+    )
+    .unwrap();
+    std::fs::write(dir.join("helper.c"), "int helper(void) { return 7; }\n").unwrap();
+    let rpath = if origin_rpath {
+        r#"println!("cargo:rustc-cdylib-link-arg=-Wl,-rpath,$ORIGIN");"#
+    } else {
+        ""
+    };
+    std::fs::write(
+        dir.join("build.rs"),
+        format!(
+            r#"fn main() {{
+    let out = std::env::var("OUT_DIR").unwrap();
+    let status = std::process::Command::new("cc")
+        .args(["-shared", "-fPIC", "-Wl,-soname,libhelper.so", "-o"])
+        .arg(format!("{{out}}/libhelper.so"))
+        .arg("helper.c")
+        .status()
+        .unwrap();
+    assert!(status.success());
+    println!("cargo:rustc-link-search=native={{out}}");
+    println!("cargo:rustc-link-lib=dylib=helper");
+    {rpath}
+}}
+"#
+        ),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("src/lib.rs"),
+        "extern \"C\" { fn helper() -> i32; }\n\n\
+         #[no_mangle]\npub extern \"C\" fn frida_agent_main() { unsafe { helper(); } }\n",
+    )
+    .unwrap();
+    Some(dir)
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn from_crate_stages_built_dependencies() {
+    let Some(dir) = helper_linked_agent("bundled-agent", true) else {
+        return;
+    };
+
+    let options = hook_inject::BuildOptions::new().target_dir(dir.join("target"));
+    Library::from_crate_with(&dir, &options).expect("dependency staged next to the agent");
+    assert!(dir.join("target/debug/libhelper.so").is_file());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn from_crate_reports_unreachable_dependencies() {
+    let Some(dir) = helper_linked_agent("unbundled-agent", false) else {
+        return;
+    };
+
+    // Relocating would add `$ORIGIN` where `patchelf` is installed.
+    let options = hook_inject::BuildOptions::new()
+        .target_dir(dir.join("target"))
+        .relocate(false);
+    let err = Library::from_crate_with(&dir, &options).unwrap_err();
+    let msg = err.to_string();
+    assert!(
+        msg.contains("shared library dependencies not found"),
+        "{msg}"
+    );
+    assert!(msg.contains("libhelper.so"), "{msg}");
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+#[test]
+fn inject_checks_agent_libc_against_target() {
+    use hook_inject::{ErrorKind, Process, inject_process};
+
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let fixture = root.join("fixtures/agent");
+    Library::from_crate(&fixture).expect("fixture cdylib");
+    let built = hook_inject_build::read_cdylib_file(&fixture)
+        .unwrap()
+        .unwrap();
+    let bytes = std::fs::read(built.path).unwrap();
+    let patched = |from: &[u8], to: &[u8]| {
+        let mut bytes = bytes.clone();
+        for i in 0..bytes.len() - from.len() {
+            if &bytes[i..i + from.len()] == from {
+                bytes[i..i + to.len()].copy_from_slice(to);
+            }
+        }
+        Library::from_bytes(bytes)
+            .unwrap()
+            .with_entrypoint(c"hook_inject_entry")
+            .unwrap()
+    };
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+
+    let newer = patched(b"GLIBC_2.", b"GLIBC_9.");
+    let err = inject_process(process, newer).unwrap_err();
+    if err.kind() == ErrorKind::RuntimeUnavailable {
+        // Target checks run once the runtime is up.
+        return;
+    }
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert!(
+        err.to_string().contains("library requires GLIBC_9."),
+        "{err}"
+    );
+
+    let musl = patched(b"libc.so.6\0", b"libc.so\0\0\0");
+    let err = inject_process(process, musl).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("library links against musl, but the target uses glibc"),
+        "{err}"
+    );
+}
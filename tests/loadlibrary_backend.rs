@@ -0,0 +1,96 @@
+#![cfg(all(windows, any(target_arch = "x86_64", target_arch = "aarch64")))]
+
+mod common;
+
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use common::{fixture_agent, stamp_path, wait_for};
+use hook_inject::{Backend, ErrorKind, InjectOptions, InjectionMethod, Injector, Process};
+
+#[test]
+fn loadlibrary_injects_into_running_process() {
+    let injector = Injector::builder()
+        .backend(Backend::LoadLibrary)
+        .build()
+        .expect("the loadlibrary backend has no runtime to set up");
+    assert_eq!(injector.backend(), Backend::LoadLibrary);
+
+    let stamp = stamp_path("loadlibrary-running");
+    let _ = std::fs::remove_file(&stamp);
+    let library = fixture_agent(&stamp);
+    let mut child = Command::new("ping")
+        .args(["-n", "30", "127.0.0.1"])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    let process = Process::from_pid(child.id() as i32).unwrap();
+
+    let injected = match injector.inject_process(process, library) {
+        Ok(injected) => injected,
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            eprintln!("skipping loadlibrary injection test ({err})");
+            let _ = child.kill();
+            let _ = child.wait();
+            return;
+        }
+        Err(err) => panic!("injection should succeed: {err}"),
+    };
+    assert_eq!(
+        injected.injection_method(),
+        Some(InjectionMethod::RemoteThread)
+    );
+    assert!(wait_for(&stamp), "expected injection to write stamp file");
+    // The fixture agent does not stay resident.
+    injected
+        .wait_until_unloaded(Duration::from_secs(5))
+        .expect("agent should unload");
+
+    // The target carries on as before.
+    assert!(child.try_wait().unwrap().is_none());
+    let _ = child.kill();
+    let _ = child.wait();
+    let _ = std::fs::remove_file(&stamp);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn loadlibrary_injects_by_hijacking_a_thread() {
+    let injector = Injector::builder()
+        .backend(Backend::LoadLibrary)
+        .build()
+        .unwrap();
+    let stamp = stamp_path("loadlibrary-hijack");
+    let _ = std::fs::remove_file(&stamp);
+    let mut child = Command::new("ping")
+        .args(["-n", "30", "127.0.0.1"])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    let process = Process::from_pid(child.id() as i32).unwrap();
+    let options = InjectOptions::new().injection_method(InjectionMethod::ThreadHijack);
+
+    let injected = match injector.inject_process_with(process, fixture_agent(&stamp), options) {
+        Ok(injected) => injected,
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            eprintln!("skipping loadlibrary hijack test ({err})");
+            let _ = child.kill();
+            let _ = child.wait();
+            return;
+        }
+        Err(err) => panic!("injection should succeed: {err}"),
+    };
+    assert_eq!(
+        injected.injection_method(),
+        Some(InjectionMethod::ThreadHijack)
+    );
+    // The hijacked thread runs the loader once it next gets scheduled.
+    assert!(wait_for(&stamp), "expected injection to write stamp file");
+    injected
+        .wait_until_unloaded(Duration::from_secs(5))
+        .expect("agent should unload");
+    assert!(child.try_wait().unwrap().is_none());
+    let _ = child.kill();
+    let _ = child.wait();
+    let _ = std::fs::remove_file(&stamp);
+}
@@ -0,0 +1,16 @@
+use hook_inject::{InjectionEvent, InjectionEventKind, Process};
+
+#[test]
+fn event_json_carries_the_label() {
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+    let event = InjectionEvent::new(InjectionEventKind::Injected, process);
+    assert_eq!(event.label(), None);
+    assert!(!event.to_json().contains("label"));
+
+    let event = event.with_label("tracer \"v2\"");
+    assert_eq!(event.label(), Some("tracer \"v2\""));
+    assert!(event.to_json().starts_with(&format!(
+        r#"{{"event":"injected","pid":{},"label":"tracer \"v2\"","timestamp_ms":"#,
+        std::process::id()
+    )));
+}
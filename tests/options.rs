@@ -0,0 +1,28 @@
+use hook_inject::{
+    ErrorKind, InjectOptions, Library, Process, Realm, inject_process_with, inject_processes_with,
+};
+
+#[test]
+fn emulated_realm_is_rejected() {
+    let lib = Library::from_bytes(vec![1, 2, 3]).unwrap();
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+    let options = InjectOptions::new().realm(Realm::Emulated);
+
+    let err = inject_process_with(process, lib, options).unwrap_err();
+    assert!(err.is_not_supported());
+    assert!(err.to_string().contains("emulated realm"));
+}
+
+#[test]
+fn zero_concurrency_fails_every_target() {
+    let lib = Library::from_bytes(vec![1, 2, 3]).unwrap();
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+    let options = InjectOptions::new().concurrency(0);
+
+    let results = inject_processes_with(&[process, process], &lib, options);
+    assert_eq!(results.len(), 2);
+    for (target, result) in results {
+        assert_eq!(target, process);
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+}
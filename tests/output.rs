@@ -0,0 +1,48 @@
+use std::io::Write;
+
+use hook_inject_agent::EventStream;
+
+// Records start after the two offsets; each has a length and a timestamp.
+const HEADER: usize = 16;
+const RECORD_HEADER: usize = 12;
+
+fn payloads(stream: &EventStream) -> Vec<Vec<u8>> {
+    let len = HEADER + hook_inject::EVENT_BUFFER_CAPACITY;
+    let raw = unsafe { std::slice::from_raw_parts(stream as *const _ as *const u8, len) };
+    let head = u64::from_ne_bytes(raw[8..16].try_into().unwrap()) as usize;
+    let records = &raw[HEADER..HEADER + head];
+    let mut payloads = Vec::new();
+    let mut at = 0;
+    while at < records.len() {
+        let len = u32::from_ne_bytes(records[at..at + 4].try_into().unwrap()) as usize;
+        payloads.push(records[at + RECORD_HEADER..at + RECORD_HEADER + len].to_vec());
+        at += RECORD_HEADER + len;
+    }
+    payloads
+}
+
+#[cfg(unix)]
+#[test]
+fn captured_output_is_tagged_with_its_descriptor() {
+    let stream: &'static EventStream = Box::leak(Box::new(EventStream::new()));
+    let capture = hook_inject_agent::capture_output(stream).unwrap();
+    // Written to the descriptors directly; the test harness only captures
+    // the print macros.
+    std::io::stdout().write_all(b"to stdout\n").unwrap();
+    std::io::stdout().flush().unwrap();
+    std::io::stderr().write_all(b"to stderr\n").unwrap();
+    capture.stop().unwrap();
+
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    for payload in payloads(stream) {
+        match payload[0] {
+            1 => stdout.extend_from_slice(&payload[1..]),
+            2 => stderr.extend_from_slice(&payload[1..]),
+            fd => panic!("unexpected descriptor {fd}"),
+        }
+    }
+    assert_eq!(stdout, b"to stdout\n");
+    assert_eq!(stderr, b"to stderr\n");
+    assert_eq!(hook_inject::OUTPUT_SYMBOL, "hook_inject_output");
+}
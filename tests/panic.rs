@@ -0,0 +1,33 @@
+use hook_inject_agent::PanicReport;
+
+#[test]
+fn panic_report_layout_matches_the_injector() {
+    assert_eq!(
+        hook_inject::PANIC_REPORT_CAPACITY,
+        hook_inject_agent::PANIC_REPORT_CAPACITY
+    );
+    assert!(std::mem::size_of::<PanicReport>() >= 4 + hook_inject::PANIC_REPORT_CAPACITY);
+
+    let report = PanicReport::new();
+    assert_eq!(report.get(), None);
+    // The length comes first, in native byte order.
+    let len = |report: &PanicReport| {
+        let bytes = unsafe { std::slice::from_raw_parts(report as *const _ as *const u8, 4) };
+        u32::from_ne_bytes(bytes.try_into().unwrap())
+    };
+    assert_eq!(len(&report), 0);
+    report.record("boom");
+    assert_eq!(len(&report), 4);
+}
+
+#[test]
+fn panic_report_keeps_the_first_panic_truncated_on_a_char_boundary() {
+    let report = PanicReport::new();
+    let text = "é".repeat(hook_inject::PANIC_REPORT_CAPACITY);
+    report.record(&text);
+    report.record("later");
+
+    let recorded = report.get().unwrap();
+    assert!(text.starts_with(recorded));
+    assert!(recorded.len() > hook_inject::PANIC_REPORT_CAPACITY - 2);
+}
@@ -0,0 +1,34 @@
+#![cfg(feature = "serde")]
+
+use std::collections::BTreeMap;
+
+use hook_inject::Library;
+
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Config {
+    name: String,
+    ports: Vec<u16>,
+    headers: BTreeMap<String, String>,
+}
+
+#[test]
+fn payload_round_trips_to_agent() {
+    let config = Config {
+        name: "quote \" backslash \\ nul \0 newline \n".to_string(),
+        ports: vec![80, 443],
+        headers: BTreeMap::from([("x-agent".to_string(), "ü".to_string())]),
+    };
+    let lib = Library::from_bytes(vec![1])
+        .unwrap()
+        .with_data_bytes(vec![0, 1])
+        .with_payload(&config)
+        .unwrap();
+    assert_eq!(lib.data_bytes(), None);
+
+    let decoded: Config = hook_inject_agent::payload_from_cstr(lib.data()).unwrap();
+    assert_eq!(decoded, config);
+    let decoded: Config =
+        unsafe { hook_inject_agent::payload_from_ptr(lib.data().as_ptr()) }.unwrap();
+    assert_eq!(decoded, config);
+    assert!(unsafe { hook_inject_agent::payload_from_ptr::<Config>(std::ptr::null()) }.is_err());
+}
@@ -0,0 +1,20 @@
+use hook_inject::{Error, ErrorKind, InjectionPlan, Library};
+
+#[test]
+fn plan_without_targets_is_rejected() {
+    let library = Library::from_bytes(vec![1, 2, 3]).unwrap();
+    let err = InjectionPlan::new().library(library).execute().unwrap_err();
+    assert_eq!(err.error().kind(), ErrorKind::InvalidInput);
+    assert_eq!(err.process(), None);
+    assert!(err.rollback_failures().is_empty());
+
+    let err = Error::from(err);
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert!(err.to_string().starts_with("injection plan failed: "));
+}
+
+#[test]
+fn plan_without_libraries_is_rejected() {
+    let err = InjectionPlan::new().pid(1).execute().unwrap_err();
+    assert_eq!(err.error().kind(), ErrorKind::InvalidInput);
+}
@@ -0,0 +1,77 @@
+#![cfg(all(
+    any(target_os = "linux", target_os = "macos"),
+    feature = "preload-backend"
+))]
+
+mod common;
+
+use std::time::Duration;
+
+use common::{fixture_agent, stamp_path, wait_for};
+use hook_inject::{Backend, Injector, Program};
+
+#[test]
+fn preload_injects_into_launched_program() {
+    let injector = Injector::builder()
+        .backend(Backend::Preload)
+        .build()
+        .expect("the preload backend has no runtime to set up");
+    assert_eq!(injector.backend(), Backend::Preload);
+
+    let stamp = stamp_path("preload-launched");
+    let _ = std::fs::remove_file(&stamp);
+    // System binaries are out of reach on macOS, so the target is this
+    // test binary, idling briefly.
+    let mut program = Program::new(std::env::current_exe().unwrap());
+    program.args(["--ignored", "--exact", "brief_target"]);
+    let injected = injector
+        .inject_program(program, fixture_agent(&stamp))
+        .expect("injection should succeed");
+    // The entrypoint runs before `main`, so it is done by now.
+    assert!(stamp.is_file(), "expected injection to write stamp file");
+    // The fixture agent does not stay resident.
+    injected
+        .wait_until_unloaded(Duration::from_secs(5))
+        .expect("agent should unload");
+    injected
+        .wait_target_exit(Duration::from_secs(5))
+        .expect("program should run to completion");
+    let _ = std::fs::remove_file(&stamp);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn preload_leaves_the_programs_children_alone() {
+    let injector = Injector::builder()
+        .backend(Backend::Preload)
+        .build()
+        .unwrap();
+    let stamp = stamp_path("preload-children");
+    let _ = std::fs::remove_file(&stamp);
+    let log = stamp_path("preload-children-env");
+    // The shell forks for the pipeline, and its children see the
+    // environment it was left with.
+    let mut program = Program::new("sh");
+    program.args([
+        "-c".to_string(),
+        format!("env | grep -c HOOK_INJECT_PRELOAD > {}", log.display()),
+    ]);
+    let injected = injector
+        .inject_program(program, fixture_agent(&stamp))
+        .expect("injection should succeed");
+    injected
+        .wait_target_exit(Duration::from_secs(5))
+        .expect("program should run to completion");
+    assert!(wait_for(&stamp), "expected injection to write stamp file");
+    let count = std::fs::read_to_string(&log).unwrap_or_default();
+    assert_eq!(count.trim(), "0");
+    let _ = std::fs::remove_file(&stamp);
+    let _ = std::fs::remove_file(&log);
+}
+
+// The target for `preload_injects_into_launched_program`, run as a child.
+#[test]
+#[ignore]
+fn brief_target() {
+    std::thread::sleep(Duration::from_millis(300));
+}
@@ -5,3 +5,88 @@ fn from_pid_rejects_nonpositive() {
     let err = Process::from_pid(0).unwrap_err();
     assert!(err.to_string().contains("pid must be > 0"));
 }
+
+#[cfg(target_os = "linux")]
+#[test]
+fn memory_maps_include_own_executable() {
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+    let regions = process.memory_maps().unwrap();
+    assert!(!regions.is_empty());
+    assert!(regions.windows(2).all(|w| w[0].start() <= w[1].start()));
+
+    let exe = std::env::current_exe().unwrap();
+    let code = regions
+        .iter()
+        .find(|r| r.path() == Some(exe.as_path()) && r.protection().execute())
+        .expect("executable text mapping");
+    assert!(code.protection().read());
+    assert!(!code.is_empty());
+
+    let here = memory_maps_include_own_executable as *const () as usize as u64;
+    assert!(regions.iter().any(|r| r.contains(here)));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn wait_for_finds_running_process_by_path() {
+    use hook_inject::ProcessMatcher;
+    use std::time::Duration;
+
+    let exe = std::env::current_exe().unwrap();
+    let found = Process::wait_for(ProcessMatcher::path(&exe), Duration::from_secs(5)).unwrap();
+    // The lowest matching PID wins, which is at most our own.
+    assert!(found.pid() <= std::process::id() as i32);
+}
+
+#[test]
+fn wait_for_times_out_on_manual_clock() {
+    use hook_inject::ManualClock;
+    use std::time::Duration;
+
+    let clock = ManualClock::new();
+    let err = Process::wait_for_with_clock(
+        "hook-inject-no-such-process",
+        Duration::from_secs(30),
+        &clock,
+    )
+    .unwrap_err();
+    assert!(err.is_timed_out());
+    assert_eq!(clock.elapsed(), Duration::from_secs(30));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn wait_for_matches_glob_pattern() {
+    use hook_inject::ProcessMatcher;
+    use std::time::Duration;
+
+    let exe = std::env::current_exe().unwrap();
+    let name = exe.file_name().unwrap().to_string_lossy().into_owned();
+    let prefix = &name[..name.len() / 2];
+
+    let by_path = ProcessMatcher::pattern(format!("*/{prefix}*"));
+    assert!(Process::wait_for(by_path, Duration::from_secs(5)).is_ok());
+
+    let by_name = ProcessMatcher::pattern(format!("{prefix}?*"));
+    assert!(Process::wait_for(by_name, Duration::from_secs(5)).is_ok());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn find_all_lists_own_process() {
+    use hook_inject::ProcessMatcher;
+
+    let exe = std::env::current_exe().unwrap();
+    let found = Process::find_all(ProcessMatcher::path(&exe)).unwrap();
+    assert!(found.iter().any(|p| p.pid() == std::process::id() as i32));
+    assert!(found.windows(2).all(|pair| pair[0].pid() < pair[1].pid()));
+}
+
+#[test]
+fn inject_by_name_without_matches_is_not_found() {
+    use hook_inject::{ErrorKind, Library, inject_by_name};
+
+    let library = Library::from_bytes(vec![1, 2, 3]).unwrap();
+    let err = inject_by_name("hook-inject-no-such-process", library).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ProcessNotFound);
+}
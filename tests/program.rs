@@ -0,0 +1,140 @@
+use hook_inject::{Program, Stdio};
+
+#[test]
+fn from_shell_splits_quoted_args() {
+    let program = Program::from_shell(r#"prog --flag 'quoted arg' "double \"q\"""#).unwrap();
+    assert_eq!(program.get_program(), "prog");
+    let args: Vec<_> = program.get_args().collect();
+    assert_eq!(args, ["--flag", "quoted arg", "double \"q\""]);
+}
+
+#[test]
+fn from_shell_rejects_empty() {
+    let err = Program::from_shell("   ").unwrap_err();
+    assert!(err.to_string().contains("command is empty"));
+}
+
+#[test]
+fn from_shell_rejects_unbalanced_quotes() {
+    let err = Program::from_shell("prog 'unterminated").unwrap_err();
+    assert!(err.to_string().contains("unbalanced quotes"));
+}
+
+#[test]
+fn env_is_inherited_by_default() {
+    assert!(Program::new("prog").inherits_env());
+    assert!(!Program::new("prog").env_inherit(false).inherits_env());
+}
+
+#[test]
+fn env_clear_disables_inheritance() {
+    let mut program = Program::new("prog");
+    program.env_clear().env("KEEP", "1");
+    assert!(!program.inherits_env());
+
+    // A `Command`'s own `env_clear` cannot be read back, only honored at
+    // launch.
+    let mut program = Program::from(std::process::Command::new("prog"));
+    assert!(program.inherits_env());
+    program.env_clear();
+    assert!(!program.inherits_env());
+}
+
+#[cfg(unix)]
+#[test]
+fn resolves_bare_names_via_path() {
+    let path = Program::new("sh").resolved_program().unwrap();
+    assert!(path.is_absolute());
+    assert!(path.ends_with("sh"));
+
+    let mut program = Program::new("sh");
+    program.env("PATH", "/nonexistent-hook-inject-dir");
+    let err = program.resolved_program().unwrap_err();
+    assert!(err.to_string().contains("program not found in PATH"));
+}
+
+#[test]
+fn path_resolution_can_be_disabled() {
+    let program = Program::new("definitely-not-a-real-program").resolve_path(false);
+    assert_eq!(
+        program.resolved_program().unwrap(),
+        std::path::PathBuf::from("definitely-not-a-real-program")
+    );
+
+    let explicit = Program::new("./relative/prog");
+    assert_eq!(
+        explicit.resolved_program().unwrap(),
+        std::path::PathBuf::from("./relative/prog")
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn converted_commands_keep_their_stdio() {
+    use std::process::{Command, Stdio as StdStdio};
+
+    let mut cmd = Command::new("true");
+    cmd.stdin(StdStdio::piped())
+        .stdout(StdStdio::piped())
+        .stderr(StdStdio::null());
+    let program = Program::from(cmd);
+    assert_eq!(program.stdio_mode(), Stdio::Inherit);
+    let mut child = program.into_command().spawn().unwrap();
+    assert!(child.stdin.is_some() && child.stdout.is_some() && child.stderr.is_none());
+    child.wait().unwrap();
+
+    let program = Program::from(Command::new("true")).stdio(Stdio::Pipe);
+    assert_eq!(program.stdio_mode(), Stdio::Pipe);
+    let mut child = program.into_command().spawn().unwrap();
+    assert!(child.stdin.is_some() && child.stdout.is_some() && child.stderr.is_some());
+    child.wait().unwrap();
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+#[test]
+fn launched_commands_keep_their_stdio() {
+    use hook_inject::{Backend, Injector};
+    use std::process::{Command, Stdio as StdStdio};
+
+    let injector = Injector::builder()
+        .backend(Backend::Ptrace)
+        .build()
+        .unwrap();
+    let mut cmd = Command::new("sleep");
+    cmd.arg("30")
+        .stdin(StdStdio::null())
+        .stdout(StdStdio::null())
+        .stderr(StdStdio::null());
+    let suspended = injector.spawn(cmd).unwrap();
+    let pid = suspended.process().pid();
+    for fd in 0..3 {
+        let target = std::fs::read_link(format!("/proc/{pid}/fd/{fd}")).unwrap();
+        assert_eq!(target, std::path::Path::new("/dev/null"), "fd {fd}");
+    }
+    suspended.kill().unwrap();
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+#[test]
+fn launched_commands_keep_env_clear() {
+    use hook_inject::{Backend, Injector};
+    use std::process::Command;
+
+    let injector = Injector::builder()
+        .backend(Backend::Ptrace)
+        .build()
+        .unwrap();
+    let mut cmd = Command::new("/bin/sleep");
+    cmd.arg("30").env_clear().env("ONLY_THIS", "1");
+    let suspended = injector.spawn(cmd).unwrap();
+    let pid = suspended.process().pid();
+    let environ = std::fs::read(format!("/proc/{pid}/environ")).unwrap();
+    assert_eq!(environ, b"ONLY_THIS=1\0");
+    suspended.kill().unwrap();
+}
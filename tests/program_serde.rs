@@ -0,0 +1,47 @@
+#![cfg(feature = "serde")]
+
+use hook_inject::{Program, SuspendPoint};
+
+#[test]
+fn deserializes_toml_launch_spec() {
+    let program: Program = toml::from_str(
+        r#"
+        program = "/usr/bin/env"
+        args = ["-u", "HOME"]
+        cwd = "/tmp"
+        stdio = "null"
+        suspend_at = "loader_entry"
+        env_remove = ["LANG"]
+
+        [env]
+        HOOK_INJECT_TEST = "1"
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(program.get_program(), "/usr/bin/env");
+    assert_eq!(program.get_args().collect::<Vec<_>>(), ["-u", "HOME"]);
+    assert_eq!(
+        program.get_current_dir(),
+        Some(std::path::Path::new("/tmp"))
+    );
+    let envs: Vec<_> = program.get_envs().collect();
+    assert!(envs.contains(&("HOOK_INJECT_TEST".as_ref(), Some("1".as_ref()))));
+    assert!(envs.contains(&("LANG".as_ref(), None)));
+    assert!(program.inherits_env());
+    assert_eq!(program.suspend_point(), Some(SuspendPoint::LoaderEntry));
+}
+
+#[test]
+fn deserializes_json_without_inheritance() {
+    let program: Program =
+        serde_json::from_str(r#"{"program": "prog", "env_inherit": false}"#).unwrap();
+    assert_eq!(program.get_program(), "prog");
+    assert!(!program.inherits_env());
+}
+
+#[test]
+fn rejects_unknown_fields() {
+    let err = serde_json::from_str::<Program>(r#"{"program": "prog", "argz": []}"#).unwrap_err();
+    assert!(err.to_string().contains("unknown field"));
+}
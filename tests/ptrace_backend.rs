@@ -0,0 +1,279 @@
+#![cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+
+mod common;
+
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use common::{fixture_agent, stamp_path, wait_for};
+use hook_inject::{Backend, ErrorKind, InjectOptions, Injector, Process, Program, Stdio};
+
+#[test]
+fn ptrace_injects_into_running_process() {
+    let injector = Injector::builder()
+        .backend(Backend::Ptrace)
+        .build()
+        .expect("the ptrace backend has no runtime to set up");
+    assert_eq!(injector.backend(), Backend::Ptrace);
+
+    let stamp = stamp_path("ptrace-running");
+    let _ = std::fs::remove_file(&stamp);
+    let library = fixture_agent(&stamp);
+    let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+    let process = Process::from_pid(child.id() as i32).unwrap();
+
+    let injected = match injector.inject_process(process, library) {
+        Ok(injected) => injected,
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            eprintln!("skipping ptrace injection test ({err})");
+            let _ = child.kill();
+            let _ = child.wait();
+            return;
+        }
+        Err(err) => panic!("injection should succeed: {err}"),
+    };
+    assert!(wait_for(&stamp), "expected injection to write stamp file");
+    // The fixture agent does not stay resident.
+    injected
+        .wait_until_unloaded(Duration::from_secs(5))
+        .expect("agent should unload");
+
+    // The target carries on as before.
+    assert!(child.try_wait().unwrap().is_none());
+    let _ = child.kill();
+    let _ = child.wait();
+    let _ = std::fs::remove_file(&stamp);
+}
+
+#[test]
+fn ptrace_injects_into_launched_program() {
+    let injector = Injector::builder()
+        .backend(Backend::Ptrace)
+        .build()
+        .unwrap();
+    let stamp = stamp_path("ptrace-launched");
+    let _ = std::fs::remove_file(&stamp);
+
+    let program = Program::from_shell("sleep 0.3").unwrap();
+    let injected = match injector.inject_program(program, fixture_agent(&stamp)) {
+        Ok(injected) => injected,
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            eprintln!("skipping ptrace launch test ({err})");
+            return;
+        }
+        Err(err) => panic!("injection should succeed: {err}"),
+    };
+    assert_eq!(injected.injection_method(), None);
+    assert!(wait_for(&stamp), "expected injection to write stamp file");
+    injected
+        .wait_target_exit(Duration::from_secs(5))
+        .expect("program should run to completion");
+    let _ = std::fs::remove_file(&stamp);
+}
+
+#[test]
+fn ptrace_launch_keeps_the_callers_options() {
+    let injector = Injector::builder()
+        .backend(Backend::Ptrace)
+        .build()
+        .unwrap();
+    let stamp = stamp_path("ptrace-options");
+    let _ = std::fs::remove_file(&stamp);
+
+    // A pre_exec hook takes the native spawn path.
+    let mut program = Program::from_shell("sleep 0.3").unwrap();
+    unsafe {
+        program.pre_exec(|| Ok(()));
+    }
+    let options = InjectOptions::new().label("native-launch");
+    let injected = match injector.inject_program_with(program, fixture_agent(&stamp), options) {
+        Ok(injected) => injected,
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            eprintln!("skipping ptrace options test ({err})");
+            return;
+        }
+        Err(err) => panic!("injection should succeed: {err}"),
+    };
+    assert_eq!(injected.label(), Some("native-launch"));
+    assert!(
+        hook_inject::injections()
+            .iter()
+            .any(|injection| injection.id() == injected.id()
+                && injection.label() == Some("native-launch"))
+    );
+    assert!(wait_for(&stamp), "expected injection to write stamp file");
+    injected
+        .wait_target_exit(Duration::from_secs(5))
+        .expect("program should run to completion");
+    let _ = std::fs::remove_file(&stamp);
+}
+
+#[test]
+fn ptrace_launch_feeds_piped_stdin() {
+    use std::io::{Read, Write};
+
+    let injector = Injector::builder()
+        .backend(Backend::Ptrace)
+        .build()
+        .unwrap();
+    let stamp = stamp_path("ptrace-stdin");
+    let _ = std::fs::remove_file(&stamp);
+
+    let mut program = Program::new("/bin/sh");
+    program.args(["-c", "read line; echo \"got $line\""]);
+    let program = program.stdio(Stdio::Pipe);
+    let mut injected = match injector.inject_program(program, fixture_agent(&stamp)) {
+        Ok(injected) => injected,
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            eprintln!("skipping ptrace stdin test ({err})");
+            return;
+        }
+        Err(err) => panic!("injection should succeed: {err}"),
+    };
+    let child = injected.child_mut();
+    let mut stdin = child.take_stdin().expect("stdin should be piped");
+    stdin.write_all(b"hello\n").unwrap();
+    drop(stdin);
+    let mut output = String::new();
+    child
+        .take_stdout()
+        .expect("stdout should be piped")
+        .read_to_string(&mut output)
+        .unwrap();
+    assert_eq!(output, "got hello\n");
+    injected
+        .wait_target_exit(Duration::from_secs(5))
+        .expect("program should exit after reading");
+    let _ = std::fs::remove_file(&stamp);
+}
+
+#[cfg(feature = "preload-backend")]
+#[test]
+fn injections_of_two_runtimes_are_kept_apart() {
+    let ptrace = Injector::builder()
+        .backend(Backend::Ptrace)
+        .build()
+        .unwrap();
+    let preload = Injector::builder()
+        .backend(Backend::Preload)
+        .build()
+        .unwrap();
+    let traced_stamp = stamp_path("ptrace-registry-traced");
+    let preloaded_stamp = stamp_path("ptrace-registry-preloaded");
+    let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+    let process = Process::from_pid(child.id() as i32).unwrap();
+
+    let traced = match ptrace.inject_process(process, fixture_agent(&traced_stamp)) {
+        Ok(injected) => injected,
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            eprintln!("skipping two-runtime registry test ({err})");
+            let _ = child.kill();
+            let _ = child.wait();
+            return;
+        }
+        Err(err) => panic!("injection should succeed: {err}"),
+    };
+    let preloaded = preload
+        .inject_program(
+            Program::from_shell("sleep 0.3").unwrap(),
+            fixture_agent(&preloaded_stamp),
+        )
+        .expect("injection should succeed");
+    assert_ne!(traced.id(), preloaded.id());
+
+    let listed = |id| {
+        hook_inject::injections()
+            .iter()
+            .any(|injection| injection.id() == id)
+    };
+    assert!(listed(traced.id()));
+    assert!(listed(preloaded.id()));
+    // Uninjecting one runtime's injection leaves the other's listed.
+    let traced_id = traced.id();
+    traced.uninject().unwrap();
+    assert!(!listed(traced_id));
+    assert!(listed(preloaded.id()));
+
+    let _ = preloaded.wait_target_exit(Duration::from_secs(5));
+    preloaded.uninject().unwrap();
+    let _ = child.kill();
+    let _ = child.wait();
+    let _ = std::fs::remove_file(&traced_stamp);
+    let _ = std::fs::remove_file(&preloaded_stamp);
+}
+
+#[test]
+fn ptrace_launch_times_out_when_program_never_stops() {
+    let injector = Injector::builder()
+        .backend(Backend::Ptrace)
+        .build()
+        .unwrap();
+    let stamp = stamp_path("ptrace-timeout");
+    let fifo = stamp.with_extension("fifo");
+    let _ = std::fs::remove_file(&fifo);
+    assert!(
+        Command::new("mkfifo")
+            .arg(&fifo)
+            .status()
+            .unwrap()
+            .success()
+    );
+    // The loader blocks opening the FIFO as a preload, so the program never
+    // reaches its entrypoint, where the launch holds it.
+    let mut program = Program::new("true");
+    program.env("LD_PRELOAD", &fifo);
+    let options = InjectOptions::new().timeout(Duration::from_millis(200));
+    let started = Instant::now();
+    match injector.inject_program_with(program, fixture_agent(&stamp), options) {
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            eprintln!("skipping ptrace timeout test ({err})");
+        }
+        Err(err) => assert!(err.is_timed_out(), "{err}"),
+        Ok(_) => panic!("the launch should time out"),
+    }
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(!stamp.exists());
+    let _ = std::fs::remove_file(&fifo);
+}
+
+#[test]
+fn waits_on_the_target_go_by_the_options_clock() {
+    use hook_inject::ManualClock;
+
+    let injector = Injector::builder()
+        .backend(Backend::Ptrace)
+        .build()
+        .unwrap();
+    let stamp = stamp_path("ptrace-clock");
+    let _ = std::fs::remove_file(&stamp);
+    let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+    let process = Process::from_pid(child.id() as i32).unwrap();
+    let clock = ManualClock::new();
+    let options = InjectOptions::new().clock(clock.clone());
+
+    let injected = match injector.inject_process_with(process, fixture_agent(&stamp), options) {
+        Ok(injected) => injected,
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            eprintln!("skipping ptrace clock test ({err})");
+            let _ = child.kill();
+            let _ = child.wait();
+            return;
+        }
+        Err(err) => panic!("injection should succeed: {err}"),
+    };
+    // An hour on the manual clock passes without blocking the test.
+    let started = Instant::now();
+    let err = injected
+        .wait_target_exit(Duration::from_secs(3600))
+        .unwrap_err();
+    assert!(err.is_timed_out(), "{err}");
+    assert!(clock.elapsed() >= Duration::from_secs(3600));
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    let _ = child.kill();
+    let _ = child.wait();
+    let _ = std::fs::remove_file(&stamp);
+}
@@ -0,0 +1,43 @@
+#![cfg(feature = "serde")]
+
+use hook_inject::InjectionRecord;
+
+fn record(pid: u32, start_time: u64) -> InjectionRecord {
+    serde_json::from_value(serde_json::json!({
+        "id": 7,
+        "pid": pid,
+        "start_time": start_time,
+        "library": "/path/to/libagent.so",
+        "configure_entrypoint": "hook_inject_configure",
+        "unload_entrypoint": "hook_inject_unload",
+    }))
+    .unwrap()
+}
+
+#[test]
+fn record_round_trips_through_json() {
+    let record = record(std::process::id(), 1);
+    assert_eq!(record.id().as_u64(), 7);
+    assert_eq!(record.pid(), std::process::id() as i32);
+    assert_eq!(
+        record.library_path(),
+        std::path::Path::new("/path/to/libagent.so")
+    );
+    assert_eq!(record.label(), None);
+
+    let json = serde_json::to_string(&record).unwrap();
+    assert_eq!(
+        serde_json::from_str::<InjectionRecord>(&json).unwrap(),
+        record
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn reattach_rejects_a_reused_pid() {
+    use hook_inject::{ErrorKind, InjectedProcess};
+
+    // This process did not start at tick 1, so the pid "was reused".
+    let err = InjectedProcess::reattach(&record(std::process::id(), 1)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ProcessNotFound);
+}
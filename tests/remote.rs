@@ -0,0 +1,109 @@
+// Remote devices are reached through Frida.
+#![cfg(feature = "frida")]
+
+use std::time::Duration;
+
+use hook_inject::{Backend, DeviceKind, ErrorKind, InjectOptions, Injector, Process};
+
+#[test]
+fn only_frida_reaches_remote_devices() {
+    let Some(&backend) = Backend::compiled()
+        .iter()
+        .find(|backend| **backend != Backend::Frida)
+    else {
+        eprintln!("skipping: no backend other than frida in this build");
+        return;
+    };
+    let err = Injector::builder()
+        .backend(backend)
+        .remote("127.0.0.1:27042")
+        .build()
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotSupported);
+}
+
+#[test]
+fn empty_address_is_rejected() {
+    let err = Injector::remote("").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn unreachable_server_fails_to_connect() {
+    // Nothing listens on port 1, so this fails however the runtime is built.
+    let err = Injector::builder()
+        .remote("127.0.0.1:1")
+        .options(InjectOptions::new().timeout(Duration::from_secs(5)))
+        .build()
+        .unwrap_err();
+    assert!(
+        matches!(
+            err.kind(),
+            ErrorKind::RuntimeUnavailable | ErrorKind::TimedOut
+        ),
+        "{err}"
+    );
+}
+
+#[test]
+fn local_processes_are_not_remote() {
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+    assert!(!process.is_remote());
+    assert!(process.memory_maps().is_ok());
+
+    let injector = Injector::builder().backend(Backend::Frida).build();
+    if let Ok(injector) = injector {
+        assert!(!injector.is_remote());
+        let found = injector.process(process.pid()).unwrap();
+        assert_eq!(found, process);
+    }
+}
+
+#[test]
+fn devices_include_this_machine() {
+    match hook_inject::devices() {
+        Ok(devices) => {
+            let local = devices
+                .iter()
+                .find(|device| device.kind() == DeviceKind::Local)
+                .expect("the local device is always listed");
+            assert_eq!(local.id(), "local");
+        }
+        Err(err) => assert_eq!(err.kind(), ErrorKind::RuntimeUnavailable, "{err}"),
+    }
+}
+
+#[test]
+fn local_device_is_this_machine() {
+    if let Ok(injector) = Injector::device("local") {
+        assert!(!injector.is_remote());
+        assert_eq!(injector.backend(), Backend::Frida);
+    }
+}
+
+#[test]
+fn missing_usb_device_fails_to_connect() {
+    let usb = hook_inject::devices()
+        .map(|devices| {
+            devices
+                .iter()
+                .any(|device| device.kind() == DeviceKind::Usb)
+        })
+        .unwrap_or(false);
+    if usb {
+        eprintln!("skipping: a USB device is attached");
+        return;
+    }
+    let err = Injector::builder()
+        .usb()
+        .options(InjectOptions::new().timeout(Duration::from_millis(200)))
+        .build()
+        .unwrap_err();
+    assert!(
+        matches!(
+            err.kind(),
+            ErrorKind::RuntimeUnavailable | ErrorKind::TimedOut
+        ),
+        "{err}"
+    );
+}
@@ -0,0 +1,31 @@
+// Only Frida cannot be set up again.
+#![cfg(feature = "frida")]
+
+use hook_inject::{ErrorKind, Injector};
+
+// One test: shutting down is final for the whole process.
+#[test]
+fn shutdown_refuses_while_in_use_and_is_final() {
+    match hook_inject::init() {
+        Ok(()) => {
+            let injector = Injector::new().unwrap();
+            let err = hook_inject::shutdown().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+            assert!(err.to_string().contains("still used by 1"), "{err}");
+            // Refusing leaves the runtime usable.
+            hook_inject::init().unwrap();
+
+            drop(injector);
+            hook_inject::shutdown().unwrap();
+            let err = hook_inject::init().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::RuntimeUnavailable);
+            assert!(Injector::new().is_err());
+        }
+        Err(err) => {
+            assert_eq!(err.kind(), ErrorKind::RuntimeUnavailable);
+            // Nothing was set up, so there is nothing to tear down.
+            hook_inject::shutdown().unwrap();
+        }
+    }
+    hook_inject::shutdown().unwrap();
+}
@@ -0,0 +1,20 @@
+use hook_inject::{InjectedSet, Process, inject_process_all};
+
+#[test]
+fn empty_set_uninjects_cleanly() {
+    let set = InjectedSet::new();
+    assert!(set.is_empty());
+    assert_eq!(set.processes().count(), 0);
+    set.uninject().unwrap();
+
+    let set: InjectedSet = std::iter::empty().collect();
+    assert_eq!(set.len(), 0);
+    set.force_uninject().unwrap();
+}
+
+#[test]
+fn injecting_no_libraries_yields_an_empty_set() {
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+    let set = inject_process_all(process, &[]).unwrap();
+    assert!(set.is_empty());
+}
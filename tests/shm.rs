@@ -0,0 +1,38 @@
+#![cfg(unix)]
+
+use hook_inject::SharedMemory;
+
+#[test]
+fn agent_sees_injector_writes() {
+    let shm = SharedMemory::create(4096).unwrap();
+    assert_eq!(shm.len(), 4096);
+    assert_eq!(shm.descriptor(), format!("{}:4096", shm.name()));
+
+    unsafe { shm.as_mut_slice()[..5].copy_from_slice(b"hello") };
+    let agent = hook_inject_agent::SharedMemory::open(&shm.descriptor()).unwrap();
+    assert_eq!(agent.len(), 4096);
+    assert_eq!(unsafe { &agent.as_slice()[..5] }, b"hello");
+
+    unsafe { agent.as_mut_slice()[5] = b'!' };
+    assert_eq!(unsafe { &shm.as_slice()[..6] }, b"hello!");
+}
+
+#[test]
+fn segments_are_removed_on_drop() {
+    let shm = SharedMemory::create(16).unwrap();
+    let descriptor = shm.descriptor();
+    drop(shm);
+    assert!(hook_inject_agent::SharedMemory::open(&descriptor).is_err());
+}
+
+#[test]
+fn invalid_sizes_and_descriptors_are_rejected() {
+    assert_eq!(
+        SharedMemory::create(0).unwrap_err().kind(),
+        hook_inject::ErrorKind::InvalidInput
+    );
+    for descriptor in ["", "name", "name:", ":16", "name:0", "name:x"] {
+        let err = hook_inject_agent::SharedMemory::open(descriptor).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput, "{descriptor}");
+    }
+}
@@ -0,0 +1,52 @@
+#![cfg(feature = "signatures")]
+
+use hook_inject::{ErrorKind, InjectOptions, Library, Process, inject_process_with};
+
+const PUBLIC_KEY: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+// `minisign -S` over the bytes "test".
+const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1556193335\tfile:test
+y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==
+";
+
+fn inject(lib: Library) -> hook_inject::Error {
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+    let options = InjectOptions::new().entrypoint(c"entry");
+    inject_process_with(process, lib, options).unwrap_err()
+}
+
+#[test]
+fn signed_library_passes_verification() {
+    let lib = Library::from_bytes(b"test".to_vec())
+        .unwrap()
+        .with_minisign(PUBLIC_KEY, SIGNATURE);
+
+    // Past the signature check, the (non-image) blob fails to parse.
+    let err = inject(lib);
+    assert!(err.to_string().contains("failed to parse library"), "{err}");
+}
+
+#[test]
+fn tampered_library_is_rejected() {
+    let lib = Library::from_bytes(b"Test".to_vec())
+        .unwrap()
+        .with_minisign(PUBLIC_KEY, SIGNATURE);
+
+    let err = inject(lib);
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert!(err.to_string().contains("signature check failed"), "{err}");
+}
+
+#[test]
+fn malformed_key_is_rejected() {
+    let lib = Library::from_bytes(b"test".to_vec())
+        .unwrap()
+        .with_minisign("not a key", SIGNATURE);
+
+    let err = inject(lib);
+    assert!(
+        err.to_string().contains("invalid minisign public key"),
+        "{err}"
+    );
+}
@@ -11,3 +11,115 @@ fn spawn_resume_smoke() {
     let suspended = spawn(program).expect("spawn suspended");
     let _child = suspended.resume().expect("resume");
 }
+
+#[cfg(unix)]
+#[test]
+fn spawn_with_pre_exec_holds_after_exec() {
+    use hook_inject::{Program, spawn};
+
+    if !cfg!(target_os = "linux") {
+        eprintln!("skipping pre_exec spawn smoke test (non-linux)");
+        return;
+    }
+
+    let mut program = Program::new("/usr/bin/true");
+    unsafe {
+        program.pre_exec(|| Ok(()));
+    }
+
+    let suspended = spawn(program).expect("spawn held");
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", suspended.process().pid()))
+        .expect("read stat");
+    assert_eq!(stat.split_whitespace().nth(2), Some("T"));
+    let _child = suspended.resume().expect("resume");
+}
+
+#[test]
+fn spawn_kill_smoke() {
+    use hook_inject::{Process, Program, spawn};
+
+    if !cfg!(target_os = "linux") {
+        eprintln!("skipping spawn kill smoke test (non-linux)");
+        return;
+    }
+
+    let mut program = Program::new("/usr/bin/sleep");
+    program.arg("60");
+    let suspended = spawn(program).expect("spawn suspended");
+    let pid = suspended.process().pid();
+    suspended.kill().expect("kill");
+
+    for _ in 0..50 {
+        if Process::from_pid(pid).is_err() {
+            return;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    panic!("killed process {pid} is still alive");
+}
+
+#[test]
+fn spawn_drop_kills_by_default() {
+    use hook_inject::{Process, Program, spawn};
+
+    if !cfg!(target_os = "linux") {
+        eprintln!("skipping spawn drop smoke test (non-linux)");
+        return;
+    }
+
+    let mut program = Program::new("/usr/bin/sleep");
+    program.arg("60");
+    let suspended = spawn(program).expect("spawn suspended");
+    let pid = suspended.process().pid();
+    drop(suspended);
+
+    for _ in 0..50 {
+        if Process::from_pid(pid).is_err() {
+            return;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    panic!("dropped suspended process {pid} is still alive");
+}
+
+#[test]
+fn spawn_at_each_suspend_point() {
+    use hook_inject::{Program, SuspendPoint, spawn};
+
+    if !cfg!(target_os = "linux") {
+        eprintln!("skipping suspend point smoke test (non-linux)");
+        return;
+    }
+
+    for point in [SuspendPoint::LoaderEntry, SuspendPoint::ProgramEntry] {
+        let mut program = Program::new("/bin/sh");
+        program.arg("-c").arg("exit 0");
+        let suspended = spawn(program.suspend_at(point)).expect("spawn suspended");
+        let _child = suspended.resume().expect("resume");
+    }
+}
+
+#[test]
+fn spawn_try_inject_hands_back_program() {
+    use hook_inject::{Library, Process, Program, spawn};
+
+    if !cfg!(target_os = "linux") {
+        eprintln!("skipping spawn try_inject smoke test (non-linux)");
+        return;
+    }
+
+    let mut program = Program::new("/usr/bin/sleep");
+    program.arg("60");
+    let suspended = spawn(program).expect("spawn suspended");
+    let pid = suspended.process().pid();
+
+    // Exports no agent entrypoint, so injection fails.
+    let library = Library::from_path(std::env::current_exe().unwrap()).unwrap();
+    let failure = suspended.try_inject(library).unwrap_err();
+    assert!(failure.error().to_string().contains("is not exported"));
+
+    let suspended = failure.into_program();
+    assert_eq!(suspended.process().pid(), pid);
+    assert!(Process::from_pid(pid).is_ok());
+    suspended.kill().expect("kill");
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use hook_inject::clean_stale_artifacts_in;
+
+#[test]
+fn sweep_removes_only_orphaned_artifacts() {
+    let dir = std::env::temp_dir().join(format!("hook-inject-sweep-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let me = std::process::id();
+    let dead = i32::MAX;
+    let orphaned = dir.join(format!("hook-inject.{dead}.{me}.1.so"));
+    let orphaned_target = dir.join(format!("hook-inject.{me}.{dead}.2.sock"));
+    let live = dir.join(format!("hook-inject.{me}.{me}.3.so"));
+    let unrelated = dir.join("hook-inject.notes.txt");
+    for path in [&orphaned, &orphaned_target, &live, &unrelated] {
+        std::fs::write(path, b"x").unwrap();
+    }
+
+    let removed = clean_stale_artifacts_in(&dir).unwrap();
+    assert_eq!(removed, 2);
+    assert!(!orphaned.exists());
+    assert!(!orphaned_target.exists());
+    assert!(live.exists());
+    assert!(unrelated.exists());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn sweep_ignores_missing_dir() {
+    let dir = std::env::temp_dir().join("hook-inject-sweep-does-not-exist");
+    assert_eq!(clean_stale_artifacts_in(dir).unwrap(), 0);
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+#[test]
+fn per_call_staging_dir_holds_staged_data_through_reinjection() {
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use std::time::Duration;
+
+    use hook_inject::{Backend, ErrorKind, InjectOptions, Injector, Process};
+
+    let staged = |dir: &Path| -> Vec<PathBuf> {
+        let mut paths: Vec<_> = std::fs::read_dir(dir)
+            .map(|entries| entries.map(|entry| entry.unwrap().path()).collect())
+            .unwrap_or_default();
+        paths.retain(|path| path.extension().is_some_and(|ext| ext == "data"));
+        paths
+    };
+    let dir = std::env::temp_dir().join(format!("hook-inject-per-call-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    // The fixture agent writes `ok` to the file its data names.
+    let library = common::fixture_library().with_data_bytes(b"per-call".to_vec());
+    let injector = Injector::builder()
+        .backend(Backend::Ptrace)
+        .build()
+        .unwrap();
+    let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+    let process = Process::from_pid(child.id() as i32).unwrap();
+    let options = InjectOptions::new().staging_dir(&dir);
+
+    let mut injected = match injector.inject_process_with(process, library.clone(), options) {
+        Ok(injected) => injected,
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            eprintln!("skipping per-call staging test ({err})");
+            let _ = child.kill();
+            let _ = child.wait();
+            return;
+        }
+        Err(err) => panic!("injection should succeed: {err}"),
+    };
+    injected
+        .wait_until_unloaded(Duration::from_secs(5))
+        .expect("agent should unload");
+    assert_eq!(staged(&dir).len(), 1);
+
+    // Reinjecting stages the new data next to the first.
+    injected.reinject(library).unwrap();
+    injected
+        .wait_until_unloaded(Duration::from_secs(5))
+        .expect("agent should unload");
+    let paths = staged(&dir);
+    assert_eq!(paths.len(), 2);
+    for path in paths {
+        assert_eq!(std::fs::read(&path).unwrap(), b"ok", "{}", path.display());
+    }
+
+    let _ = child.kill();
+    let _ = child.wait();
+    let _ = std::fs::remove_dir_all(&dir);
+}
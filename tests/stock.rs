@@ -0,0 +1,23 @@
+use hook_inject::{ErrorKind, Library, StockAgent};
+
+#[test]
+fn stock_agents_follow_their_features() {
+    let agents = [
+        (StockAgent::EnvDumper, cfg!(feature = "stock-env-dumper")),
+        (StockAgent::FileTracer, cfg!(feature = "stock-file-tracer")),
+        (StockAgent::NetLogger, cfg!(feature = "stock-net-logger")),
+    ];
+    for (agent, embedded) in agents {
+        match Library::stock(agent) {
+            Ok(library) => {
+                assert!(embedded, "{agent:?} loaded without {}", agent.feature());
+                assert_eq!(library.entrypoint().to_str(), Ok("hook_inject_entry"));
+            }
+            Err(err) => {
+                assert!(!embedded, "{agent:?} failed: {err}");
+                assert_eq!(err.kind(), ErrorKind::NotSupported);
+                assert!(err.to_string().contains(agent.feature()));
+            }
+        }
+    }
+}
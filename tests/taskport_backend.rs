@@ -0,0 +1,62 @@
+#![cfg(all(
+    target_os = "macos",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+
+mod common;
+
+use std::process::Command;
+use std::time::Duration;
+
+use common::{fixture_agent, stamp_path, wait_for};
+use hook_inject::{Backend, ErrorKind, Injector, Process};
+
+#[test]
+fn taskport_injects_into_running_process() {
+    let injector = Injector::builder()
+        .backend(Backend::TaskPort)
+        .build()
+        .expect("the taskport backend has no runtime to set up");
+    assert_eq!(injector.backend(), Backend::TaskPort);
+
+    let stamp = stamp_path("taskport-running");
+    let _ = std::fs::remove_file(&stamp);
+    let library = fixture_agent(&stamp);
+    // `/bin/sleep` is an arm64e platform binary on Apple silicon, so the
+    // target is this test binary, idling.
+    let mut child = Command::new(std::env::current_exe().unwrap())
+        .args(["--ignored", "--exact", "idle_target", "--nocapture"])
+        .spawn()
+        .unwrap();
+    let process = Process::from_pid(child.id() as i32).unwrap();
+    std::thread::sleep(Duration::from_millis(200));
+
+    let injected = match injector.inject_process(process, library) {
+        Ok(injected) => injected,
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            eprintln!("skipping taskport injection test ({err})");
+            let _ = child.kill();
+            let _ = child.wait();
+            return;
+        }
+        Err(err) => panic!("injection should succeed: {err}"),
+    };
+    assert!(wait_for(&stamp), "expected injection to write stamp file");
+    // The fixture agent does not stay resident.
+    injected
+        .wait_until_unloaded(Duration::from_secs(5))
+        .expect("agent should unload");
+
+    // The target carries on as before.
+    assert!(child.try_wait().unwrap().is_none());
+    let _ = child.kill();
+    let _ = child.wait();
+    let _ = std::fs::remove_file(&stamp);
+}
+
+// The target for `taskport_injects_into_running_process`, run as a child.
+#[test]
+#[ignore]
+fn idle_target() {
+    std::thread::sleep(Duration::from_secs(30));
+}
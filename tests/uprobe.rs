@@ -0,0 +1,77 @@
+#![cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+
+use std::time::Duration;
+
+use hook_inject::{ErrorKind, Process, UprobeTracer};
+
+#[unsafe(no_mangle)]
+#[inline(never)]
+pub extern "C" fn hook_inject_uprobe_target(value: u64) -> u64 {
+    std::hint::black_box(value) * 2
+}
+
+fn own_tracer() -> UprobeTracer {
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+    UprobeTracer::new(process).expect("uprobes should be supported on this kernel")
+}
+
+#[test]
+fn uprobe_rejects_unknown_symbols() {
+    let mut tracer = own_tracer();
+    let exe = std::env::current_exe().unwrap();
+    match tracer.probe(&exe, "hook_inject_no_such_symbol") {
+        Err(err) => assert_eq!(err.kind(), ErrorKind::InvalidInput, "{err}"),
+        Ok(_) => panic!("unknown symbols cannot be probed"),
+    }
+}
+
+#[test]
+fn uprobe_rejects_unmapped_modules() {
+    let mut tracer = own_tracer();
+    match tracer.probe("libhook-inject-not-mapped.so", "open") {
+        Err(err) => assert_eq!(err.kind(), ErrorKind::InvalidInput, "{err}"),
+        Ok(_) => panic!("unmapped modules cannot be probed"),
+    }
+}
+
+#[test]
+fn uprobe_reports_arguments_and_return_values() {
+    let mut tracer = own_tracer();
+    let exe = std::env::current_exe().unwrap();
+    let entry = match tracer.probe(&exe, "hook_inject_uprobe_target") {
+        Ok(entry) => entry,
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            eprintln!("skipping uprobe test: {err}");
+            return;
+        }
+        Err(err) => panic!("probe failed: {err}"),
+    };
+    let exit = tracer
+        .return_probe(&exe, "hook_inject_uprobe_target")
+        .unwrap();
+
+    assert_eq!(hook_inject_uprobe_target(21), 42);
+
+    let mut hits = Vec::new();
+    while let Some(hit) = tracer.next_hit(Duration::from_secs(2)).unwrap() {
+        hits.push(hit);
+        if hits.len() == 2 {
+            break;
+        }
+    }
+    let entered = hits.iter().find(|hit| hit.probe() == entry);
+    let returned = hits.iter().find(|hit| hit.probe() == exit);
+    let entered = entered.expect("expected an entry hit");
+    assert_eq!(entered.args()[0], 21);
+    assert_eq!(entered.pid(), std::process::id() as i32);
+    assert_eq!(entered.return_value(), None);
+    let returned = returned.expect("expected a return hit");
+    assert_eq!(returned.return_value(), Some(42));
+
+    assert!(tracer.remove(entry));
+    assert!(!tracer.remove(entry));
+    assert_eq!(tracer.lost(), 0);
+}
@@ -0,0 +1,103 @@
+#![cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+
+mod common;
+
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use hook_inject::{
+    Backend, ErrorKind, InjectOptions, InjectionEvent, InjectionEventKind, Injector, Library,
+    ManualClock, Notifier, Process, Watchdog,
+};
+
+#[derive(Debug, Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<InjectionEventKind>>>);
+
+impl Notifier for Recorder {
+    fn notify(&self, event: &InjectionEvent) {
+        self.0.lock().unwrap().push(event.kind());
+    }
+}
+
+#[test]
+fn watchdog_backs_off_on_the_clock_and_gives_up() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let fixture = root.join("fixtures/agent");
+    common::fixture_library();
+    let built = hook_inject_build::read_cdylib_file(&fixture)
+        .expect("fixture metadata")
+        .expect("fixture artifact");
+    // A copy the test can break, so re-injecting it fails.
+    let agent = std::env::temp_dir().join(format!(
+        "hook-inject-watchdog-{}-{}",
+        std::process::id(),
+        built.path.file_name().unwrap().to_string_lossy()
+    ));
+    std::fs::copy(&built.path, &agent).unwrap();
+    let stamp = agent.with_extension("stamp");
+    let library = Library::from_path(&agent)
+        .unwrap()
+        .with_entrypoint(built.entrypoint.as_deref().unwrap_or("hook_inject_entry"))
+        .unwrap()
+        .with_data(stamp.to_string_lossy().as_ref())
+        .unwrap();
+
+    let injector = Injector::builder()
+        .backend(Backend::Ptrace)
+        .build()
+        .unwrap();
+    let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+    let process = Process::from_pid(child.id() as i32).unwrap();
+    let recorder = Recorder::default();
+    let options = InjectOptions::new().notifier(recorder.clone());
+    let injected = match injector.inject_process_with(process, library, options) {
+        Ok(injected) => injected,
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            eprintln!("skipping watchdog test ({err})");
+            let _ = child.kill();
+            let _ = child.wait();
+            let _ = std::fs::remove_file(&agent);
+            return;
+        }
+        Err(err) => panic!("injection should succeed: {err}"),
+    };
+    // The fixture agent does not stay resident, so the watchdog finds it
+    // gone at its first check.
+    injected
+        .wait_until_unloaded(Duration::from_secs(5))
+        .expect("agent should unload");
+    std::fs::write(&agent, b"not a library").unwrap();
+
+    let clock = ManualClock::new();
+    let watched = Watchdog::new()
+        .check_interval(Duration::from_secs(1))
+        .backoff(Duration::from_secs(60))
+        .max_backoff(Duration::from_secs(180))
+        .max_retries(3)
+        .clock(clock.clone())
+        .watch(injected);
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while watched.is_watching() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    assert!(!watched.is_watching(), "the watchdog should give up");
+    assert_eq!(watched.restarts(), 0);
+    // The unload already reported wakes the first check at once; then come
+    // backoffs of 1, 2 and (capped) 3 minutes after each failed re-injection.
+    assert_eq!(clock.elapsed(), Duration::from_secs(60 + 120 + 180));
+    assert_eq!(
+        *recorder.0.lock().unwrap(),
+        [InjectionEventKind::Injected, InjectionEventKind::AgentLost]
+    );
+
+    drop(watched);
+    let _ = child.kill();
+    let _ = child.wait();
+    let _ = std::fs::remove_file(&agent);
+    let _ = std::fs::remove_file(&stamp);
+}
@@ -0,0 +1,168 @@
+#![cfg(feature = "webhook")]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::time::Duration;
+
+use hook_inject::{
+    InjectionEvent, InjectionEventKind, ManualClock, Notifier, Process, WebhookNotifier,
+};
+
+struct Request {
+    headers: Vec<String>,
+    body: String,
+}
+
+// Accept one request and answer it with `status`.
+fn serve_one(listener: &TcpListener, status: &str) -> Request {
+    let (stream, _) = listener.accept().unwrap();
+    let mut reader = BufReader::new(stream);
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let line = line.trim_end().to_owned();
+        if line.is_empty() {
+            break;
+        }
+        headers.push(line);
+    }
+
+    let len = headers
+        .iter()
+        .find_map(|h| {
+            h.to_ascii_lowercase()
+                .strip_prefix("content-length:")
+                .map(|v| v.trim().parse::<usize>().unwrap())
+        })
+        .unwrap_or(0);
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body).unwrap();
+
+    let mut stream = reader.into_inner();
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+    )
+    .unwrap();
+    Request {
+        headers,
+        body: String::from_utf8(body).unwrap(),
+    }
+}
+
+fn header<'a>(request: &'a Request, name: &str) -> Option<&'a str> {
+    request.headers.iter().find_map(|h| {
+        let (key, value) = h.split_once(':')?;
+        key.eq_ignore_ascii_case(name).then(|| value.trim())
+    })
+}
+
+#[test]
+fn delivers_signed_event_after_retry() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let clock = ManualClock::new();
+    let webhook = WebhookNotifier::new(url)
+        .secret("s3cret")
+        .backoff(Duration::from_secs(2))
+        .clock(clock.clone());
+
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+    let event = InjectionEvent::new(InjectionEventKind::Injected, process);
+    let delivery = std::thread::spawn(move || webhook.deliver(&event));
+
+    let _failed = serve_one(&listener, "503 Service Unavailable");
+    let request = serve_one(&listener, "204 No Content");
+    delivery.join().unwrap().unwrap();
+
+    assert_eq!(clock.elapsed(), Duration::from_secs(2));
+    assert!(request.headers[0].starts_with("POST /hook "));
+    assert_eq!(header(&request, "X-Hook-Inject-Event"), Some("injected"));
+    assert!(request.body.starts_with(&format!(
+        r#"{{"event":"injected","pid":{},"#,
+        std::process::id()
+    )));
+    let signature = header(&request, "X-Hook-Inject-Signature").unwrap();
+    assert!(WebhookNotifier::verify(
+        b"s3cret",
+        request.body.as_bytes(),
+        signature
+    ));
+    assert!(!WebhookNotifier::verify(
+        b"other",
+        request.body.as_bytes(),
+        signature
+    ));
+}
+
+#[test]
+fn gives_up_after_max_attempts() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let webhook = WebhookNotifier::new(url)
+        .max_attempts(2)
+        .clock(ManualClock::new());
+
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+    let event = InjectionEvent::new(InjectionEventKind::Ejected, process);
+    let delivery = std::thread::spawn(move || webhook.deliver(&event));
+
+    serve_one(&listener, "500 Internal Server Error");
+    serve_one(&listener, "500 Internal Server Error");
+    assert!(delivery.join().unwrap().is_err());
+}
+
+#[test]
+fn notify_delivers_in_order_and_drop_flushes() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let webhook = WebhookNotifier::new(url);
+
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+    let kinds = [
+        InjectionEventKind::Injected,
+        InjectionEventKind::Reinjected,
+        InjectionEventKind::Ejected,
+    ];
+    for kind in kinds {
+        webhook.notify(&InjectionEvent::new(kind, process));
+    }
+    // Dropping waits for the queued events, so it has to happen while they
+    // are being served.
+    let dropped = std::thread::spawn(move || drop(webhook));
+
+    let delivered: Vec<String> = kinds
+        .iter()
+        .map(|_| {
+            let request = serve_one(&listener, "204 No Content");
+            header(&request, "X-Hook-Inject-Event").unwrap().to_owned()
+        })
+        .collect();
+    dropped.join().unwrap();
+    assert_eq!(delivered, ["injected", "reinjected", "ejected"]);
+}
+
+#[test]
+fn url_is_never_read_as_a_curl_option() {
+    let webhook = WebhookNotifier::new("--version").max_attempts(1);
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+    let event = InjectionEvent::new(InjectionEventKind::Injected, process);
+    assert!(webhook.deliver(&event).is_err());
+}
+
+#[test]
+fn drop_gives_up_draining_after_the_timeout() {
+    // Accepts connections but never answers them.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let webhook = WebhookNotifier::new(url)
+        .timeout(Duration::from_secs(30))
+        .drain_timeout(Duration::from_millis(200));
+
+    let process = Process::from_pid(std::process::id() as i32).unwrap();
+    webhook.notify(&InjectionEvent::new(InjectionEventKind::Injected, process));
+    let started = std::time::Instant::now();
+    drop(webhook);
+    assert!(started.elapsed() < Duration::from_secs(10));
+}